@@ -0,0 +1,7 @@
+// Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! The shared library `libsuse_kabi_tools.so` with the C bindings of the suse-kabi-tools library,
+//! as provided by its `ffi` module.
+
+pub use kabi::ffi::*;
@@ -0,0 +1,62 @@
+/* Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com> */
+/* SPDX-License-Identifier: GPL-2.0-or-later */
+
+/*
+ * C bindings of suse-kabi-tools, available when the library is built with the `ffi` feature.
+ *
+ * Functions which can fail return a negative value and, if the err parameter is not NULL, store
+ * a description of the error in a newly allocated string. All strings returned by the library must
+ * be released by ksym_string_free().
+ *
+ * An internal failure (a Rust panic) never unwinds into the caller. A function which can fail
+ * reports it as an error with the description "Internal error: ...". ksym_corpus_new() and
+ * ksym_corpus_pretty_type() then return NULL, and ksym_corpus_has_export() returns 0. A corpus
+ * involved in such a failure may hold partially loaded data and should be released.
+ */
+
+#ifndef SUSE_KABI_TOOLS_H
+#define SUSE_KABI_TOOLS_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An opaque handle of a symtypes corpus. */
+typedef struct ksym_corpus ksym_corpus;
+
+/* Creates a new empty corpus. The handle must be released by ksym_corpus_free(). */
+ksym_corpus *ksym_corpus_new(void);
+
+/* Releases a corpus. */
+void ksym_corpus_free(ksym_corpus *corpus);
+
+/* Releases a string returned by the library. */
+void ksym_string_free(char *string);
+
+/* Loads symtypes data from a file or directory. Returns 0 on success and -1 on error. */
+int ksym_corpus_load(ksym_corpus *corpus, const char *path, int num_workers, char **err);
+
+/* Writes the corpus in the consolidated form. Returns 0 on success and -1 on error. */
+int ksym_corpus_write_consolidated(const ksym_corpus *corpus, const char *path, char **err);
+
+/*
+ * Compares two corpuses. Returns 0 if no differences were found, 1 if differences were found and
+ * -1 on error. If report is not NULL, a human-readable report of the differences is stored in it.
+ */
+int ksym_corpus_compare(const ksym_corpus *corpus, const ksym_corpus *other, int num_workers,
+			char **report, char **err);
+
+/* Returns 1 if the corpus contains the specified export, and 0 otherwise. */
+int ksym_corpus_has_export(const ksym_corpus *corpus, const char *name);
+
+/*
+ * Returns the pretty-formatted definition of a type or export in the specified file, or NULL if
+ * the file does not contain such a record.
+ */
+char *ksym_corpus_pretty_type(const ksym_corpus *corpus, const char *file, const char *name);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SUSE_KABI_TOOLS_H */
@@ -42,6 +42,7 @@ Linux kernel.
 %{cargo_install}
 install -D -m 0644 %{_builddir}/%{name}-%{version}/doc/ksymtypes.1 %{buildroot}%{_mandir}/man1/ksymtypes.1
 install -D -m 0644 %{_builddir}/%{name}-%{version}/doc/ksymtypes.5 %{buildroot}%{_mandir}/man5/ksymtypes.5
+install -D -m 0644 %{_builddir}/%{name}-%{version}/doc/ksymvers.1 %{buildroot}%{_mandir}/man1/ksymvers.1
 install -D -m 0755 kabi.pl %{buildroot}/%{_bindir}/suse-kabi
 
 %check
@@ -50,9 +51,11 @@ install -D -m 0755 kabi.pl %{buildroot}/%{_bindir}/suse-kabi
 %files
 %license COPYING
 %{_bindir}/ksymtypes
+%{_bindir}/ksymvers
 %{_bindir}/suse-kabi
 %{_mandir}/man1/ksymtypes.1%{?ext_man}
 %{_mandir}/man5/ksymtypes.5%{?ext_man}
+%{_mandir}/man1/ksymvers.1%{?ext_man}
 
 %changelog
 
@@ -0,0 +1,549 @@
+// Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+#[cfg(test)]
+mod tests;
+
+// The model is built from the flat token stream of a symtypes record, without a full C grammar.
+// Declarations are split on separators at the top nesting level and the declared name is
+// recognized as the first identifier which is not a keyword and is followed by a token that can
+// end a declarator. This covers the output of genksyms well, but it is not a complete C parser.
+
+/// A declaration of a possibly unnamed entity, such as a structure member, a function parameter,
+/// a typedef or an exported variable.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Declaration {
+    name: Option<String>,
+    type_words: Vec<String>,
+}
+
+impl Declaration {
+    /// Parses a declaration from its words.
+    fn parse(words: &[&str]) -> Self {
+        let name_idx = find_declarator_name(words);
+        Self {
+            name: name_idx.map(|idx| words[idx].to_string()),
+            type_words: words
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| Some(i) != name_idx)
+                .map(|(_, word)| word.to_string())
+                .collect(),
+        }
+    }
+
+    /// Returns the declared name, or [`None`] if the declaration is unnamed.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the words of the declared type, with the name left out. For instance,
+    /// `int ( * fn ) ( long )` has the type words `int ( * ) ( long )`.
+    pub fn type_words(&self) -> &[String] {
+        &self.type_words
+    }
+
+    /// Returns the declared type as a single string, with words separated by a space.
+    pub fn type_string(&self) -> String {
+        self.type_words.join(" ")
+    }
+}
+
+/// A definition of a structure or a union.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Aggregate {
+    name: Option<String>,
+    members: Option<Vec<Declaration>>,
+}
+
+impl Aggregate {
+    /// Returns the tag name, or [`None`] if the aggregate is anonymous.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the members, or [`None`] if the definition is incomplete (`UNKNOWN`).
+    pub fn members(&self) -> Option<&[Declaration]> {
+        self.members.as_deref()
+    }
+}
+
+/// A constant of an enumeration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Enumerator {
+    name: String,
+    value: Option<String>,
+}
+
+impl Enumerator {
+    /// Returns the name of the constant.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the explicitly specified value, or [`None`] if the value is implicit.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+}
+
+/// A definition of an enumeration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Enum {
+    name: Option<String>,
+    enumerators: Option<Vec<Enumerator>>,
+}
+
+impl Enum {
+    /// Returns the tag name, or [`None`] if the enumeration is anonymous.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the constants, or [`None`] if the definition is incomplete (`UNKNOWN`).
+    pub fn enumerators(&self) -> Option<&[Enumerator]> {
+        self.enumerators.as_deref()
+    }
+}
+
+/// A declaration of a function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Function {
+    name: String,
+    return_words: Vec<String>,
+    params: Vec<Declaration>,
+}
+
+impl Function {
+    /// Returns the name of the function.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the words of the return type.
+    pub fn return_words(&self) -> &[String] {
+        &self.return_words
+    }
+
+    /// Returns the parameters. A function declared with `( void )` has no parameters, a variadic
+    /// function has a last parameter with the type `...`.
+    pub fn params(&self) -> &[Declaration] {
+        &self.params
+    }
+}
+
+/// A structured model of a symtypes record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Decl {
+    /// A structure definition, from an `s#` record.
+    Struct(Aggregate),
+    /// A union definition, from a `u#` record.
+    Union(Aggregate),
+    /// An enumeration definition, from an `e#` record.
+    Enum(Enum),
+    /// A typedef, from a `t#` record.
+    Typedef(Declaration),
+    /// An exported function.
+    Function(Function),
+    /// An exported variable.
+    Variable(Declaration),
+    /// A value of an enumerator constant, from an `E#` record.
+    Constant(String),
+    /// A record which could not be recognized, with its words.
+    Other(Vec<String>),
+}
+
+impl Decl {
+    /// Parses the words of the record `name` into a structured model.
+    pub fn parse<S: AsRef<str>>(name: &str, words: &[S]) -> Self {
+        let words: Vec<_> = words.iter().map(|word| word.as_ref()).collect();
+        let other = || Self::Other(words.iter().map(|word| word.to_string()).collect());
+
+        if name.starts_with("E#") {
+            return Self::Constant(words.join(" "));
+        }
+        match words.first().copied() {
+            Some("struct") => parse_aggregate(&words).map_or_else(other, Self::Struct),
+            Some("union") => parse_aggregate(&words).map_or_else(other, Self::Union),
+            Some("enum") => parse_enum(&words).map_or_else(other, Self::Enum),
+            Some("typedef") => Self::Typedef(Declaration::parse(&words[1..])),
+            _ if name.chars().nth(1) != Some('#') => parse_export(name, &words),
+            _ => other(),
+        }
+    }
+}
+
+/// Returns whether the specified word opens a nested block.
+fn is_open(word: &str) -> bool {
+    matches!(word, "(" | "[" | "{")
+}
+
+/// Returns whether the specified word closes a nested block.
+fn is_close(word: &str) -> bool {
+    matches!(word, ")" | "]" | "}")
+}
+
+/// Returns the index of the word which closes the block opened at `open_idx`.
+fn find_closing(words: &[&str], open_idx: usize) -> Option<usize> {
+    let mut depth: usize = 0;
+    for (i, &word) in words.iter().enumerate().skip(open_idx) {
+        if is_open(word) {
+            depth += 1;
+        } else if is_close(word) {
+            depth = depth.saturating_sub(1);
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+/// Splits the words on the separator `sep` at the top nesting level. Empty parts are dropped.
+fn split_top_level<'a, 'b>(words: &'b [&'a str], sep: &str) -> Vec<&'b [&'a str]> {
+    let mut parts = Vec::new();
+    let mut depth: usize = 0;
+    let mut start = 0;
+    for (i, &word) in words.iter().enumerate() {
+        if is_open(word) {
+            depth += 1;
+        } else if is_close(word) {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && word == sep {
+            parts.push(&words[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&words[start..]);
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Returns the words of the body of a `struct`, `union` or `enum` definition, together with the
+/// tag name, or [`None`] if the words do not form such a definition.
+fn split_body<'a, 'b>(words: &'b [&'a str]) -> Option<(Option<String>, &'b [&'a str])> {
+    let (name, open_idx) = match words.get(1).copied() {
+        Some("{") => (None, 1),
+        Some(name) if words.get(2) == Some(&"{") => (Some(name.to_string()), 2),
+        _ => return None,
+    };
+    let close_idx = find_closing(words, open_idx)?;
+    Some((name, &words[open_idx + 1..close_idx]))
+}
+
+/// Parses a `struct` or `union` definition.
+fn parse_aggregate(words: &[&str]) -> Option<Aggregate> {
+    let (name, body) = split_body(words)?;
+    let members = if body == ["UNKNOWN"] {
+        None
+    } else {
+        Some(
+            split_top_level(body, ";")
+                .into_iter()
+                .map(Declaration::parse)
+                .collect(),
+        )
+    };
+    Some(Aggregate { name, members })
+}
+
+/// Parses an `enum` definition.
+fn parse_enum(words: &[&str]) -> Option<Enum> {
+    let (name, body) = split_body(words)?;
+    let enumerators = if body == ["UNKNOWN"] {
+        None
+    } else {
+        Some(
+            split_top_level(body, ",")
+                .into_iter()
+                .map(|part| Enumerator {
+                    name: part[0].to_string(),
+                    value: match part.get(1) {
+                        Some(&"=") => Some(part[2..].join(" ")),
+                        _ => None,
+                    },
+                })
+                .collect(),
+        )
+    };
+    Some(Enum { name, enumerators })
+}
+
+/// Parses a declaration of the export `name`, which is either a function or a variable.
+fn parse_export(name: &str, words: &[&str]) -> Decl {
+    // The export is a function if its name is directly followed by a parameter list. The name is
+    // usually at the top level, but it is nested in parentheses if the function returns a pointer
+    // to a function, as in `void ( * foo ( int ) ) ( long )`. The return type is then formed by the
+    // words around the name and its parameter list.
+    if let Some(i) = words.iter().position(|&word| word == name) {
+        if words.get(i + 1) == Some(&"(") {
+            if let Some(close_idx) = find_closing(words, i + 1) {
+                let params_words = &words[i + 2..close_idx];
+                let params = if params_words == ["void"] {
+                    Vec::new()
+                } else {
+                    split_top_level(params_words, ",")
+                        .into_iter()
+                        .map(Declaration::parse)
+                        .collect()
+                };
+                return Decl::Function(Function {
+                    name: name.to_string(),
+                    return_words: words[..i]
+                        .iter()
+                        .chain(&words[close_idx + 1..])
+                        .map(|word| word.to_string())
+                        .collect(),
+                    params,
+                });
+            }
+        }
+    }
+    Decl::Variable(Declaration::parse(words))
+}
+
+/// C keywords and built-in type words which cannot be a declared name.
+const KEYWORDS: &[&str] = &[
+    "UNKNOWN",
+    "_Bool",
+    "_Complex",
+    "__attribute__",
+    "__const__",
+    "__extension__",
+    "__inline__",
+    "__restrict",
+    "__restrict__",
+    "__signed__",
+    "__volatile__",
+    "bool",
+    "char",
+    "const",
+    "double",
+    "enum",
+    "extern",
+    "float",
+    "inline",
+    "int",
+    "long",
+    "restrict",
+    "short",
+    "signed",
+    "static",
+    "struct",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+];
+
+/// Returns whether the specified word can be a declared name.
+fn is_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        && !KEYWORDS.contains(&word)
+}
+
+/// Returns the index of the name in the words of a declaration, or [`None`] if the declaration is
+/// unnamed. Words inside nested `{ }` blocks are not considered.
+fn find_declarator_name(words: &[&str]) -> Option<usize> {
+    let mut brace_depth: usize = 0;
+    for (i, &word) in words.iter().enumerate() {
+        match word {
+            "{" => brace_depth += 1,
+            "}" => brace_depth = brace_depth.saturating_sub(1),
+            _ if brace_depth == 0 && is_identifier(word) => {
+                let ends_declarator = match words.get(i + 1) {
+                    None => true,
+                    Some(&next) => matches!(next, ")" | "[" | ":" | "=" | "(" | "," | ";"),
+                };
+                if ends_declarator {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A change of a member between two definitions of a structure or a union.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum MemberChange {
+    /// A member was added at the given 1-based position.
+    Added {
+        name: String,
+        position: usize,
+        type_string: String,
+    },
+    /// A member was removed from the given 1-based position.
+    Removed { name: String, position: usize },
+    /// The type of a member changed.
+    TypeChanged {
+        name: String,
+        old_type: String,
+        new_type: String,
+    },
+    /// A member moved relative to the other members present in both definitions.
+    Moved {
+        name: String,
+        old_position: usize,
+        new_position: usize,
+    },
+}
+
+impl std::fmt::Display for MemberChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Added {
+                name,
+                position,
+                type_string,
+            } => write!(
+                f,
+                "member '{}' added at position {} with type {}",
+                name, position, type_string
+            ),
+            Self::Removed { name, position } => {
+                write!(f, "member '{}' removed from position {}", name, position)
+            }
+            Self::TypeChanged {
+                name,
+                old_type,
+                new_type,
+            } => write!(
+                f,
+                "member '{}' changed type from {} to {}",
+                name, old_type, new_type
+            ),
+            Self::Moved {
+                name,
+                old_position,
+                new_position,
+            } => write!(
+                f,
+                "member '{}' moved from position {} to {}",
+                name, old_position, new_position
+            ),
+        }
+    }
+}
+
+impl Aggregate {
+    /// Returns the changes of named members between `self` and the `other` definition, or
+    /// [`None`] if either definition is incomplete. Unnamed members are not reported.
+    pub fn member_changes(&self, other: &Aggregate) -> Option<Vec<MemberChange>> {
+        let (members, other_members) = (self.members()?, other.members()?);
+        let find = |members: &[Declaration], name: &str| {
+            members
+                .iter()
+                .position(|member| member.name() == Some(name))
+        };
+
+        let mut changes = Vec::new();
+        for (i, member) in members.iter().enumerate() {
+            let Some(name) = member.name() else {
+                continue;
+            };
+            match find(other_members, name) {
+                Some(other_idx) => {
+                    let other_member = &other_members[other_idx];
+                    if member.type_words() != other_member.type_words() {
+                        changes.push(MemberChange::TypeChanged {
+                            name: name.to_string(),
+                            old_type: member.type_string(),
+                            new_type: other_member.type_string(),
+                        });
+                    }
+                }
+                None => changes.push(MemberChange::Removed {
+                    name: name.to_string(),
+                    position: i + 1,
+                }),
+            }
+        }
+        for (i, other_member) in other_members.iter().enumerate() {
+            let Some(name) = other_member.name() else {
+                continue;
+            };
+            if find(members, name).is_none() {
+                changes.push(MemberChange::Added {
+                    name: name.to_string(),
+                    position: i + 1,
+                    type_string: other_member.type_string(),
+                });
+            }
+        }
+
+        // Report members which changed their order relative to the other common members and also
+        // their absolute position.
+        let common = |members: &[Declaration], other_members: &[Declaration]| -> Vec<String> {
+            members
+                .iter()
+                .filter_map(|member| member.name())
+                .filter(|name| find(other_members, name).is_some())
+                .map(|name| name.to_string())
+                .collect()
+        };
+        let (order, other_order) = (
+            common(members, other_members),
+            common(other_members, members),
+        );
+        for (name, other_name) in order.iter().zip(other_order.iter()) {
+            let old_position = find(members, name).unwrap() + 1;
+            let new_position = find(other_members, name).unwrap() + 1;
+            if name != other_name && old_position != new_position {
+                changes.push(MemberChange::Moved {
+                    name: name.clone(),
+                    old_position,
+                    new_position,
+                });
+            }
+        }
+
+        Some(changes)
+    }
+}
+
+impl Decl {
+    /// Returns a description of the declaration for use in messages, such as `struct foo`, or
+    /// [`None`] if the declaration is not a structure or a union.
+    fn aggregate_title(&self) -> Option<String> {
+        let (keyword, aggregate) = match self {
+            Self::Struct(aggregate) => ("struct", aggregate),
+            Self::Union(aggregate) => ("union", aggregate),
+            _ => return None,
+        };
+        Some(format!(
+            "{} {}",
+            keyword,
+            aggregate.name().unwrap_or("<anonymous>")
+        ))
+    }
+
+    /// Returns human-readable descriptions of member changes between `self` and the `other`
+    /// declaration, for instance, `struct foo: member 'a' changed type from int to long`. An empty
+    /// list is returned if the declarations are not comparable structures or unions.
+    pub fn describe_member_changes(&self, other: &Decl) -> Vec<String> {
+        let changes = match (self, other) {
+            (Self::Struct(aggregate), Self::Struct(other_aggregate))
+            | (Self::Union(aggregate), Self::Union(other_aggregate)) => {
+                aggregate.member_changes(other_aggregate)
+            }
+            _ => None,
+        };
+        let title = self.aggregate_title();
+        match (changes, title) {
+            (Some(changes), Some(title)) => changes
+                .iter()
+                .map(|change| format!("{}: {}", title, change))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
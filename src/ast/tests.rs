@@ -0,0 +1,213 @@
+// Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use super::*;
+
+/// Returns the names and type strings of the specified declarations.
+fn decls(decls: &[Declaration]) -> Vec<(Option<&str>, String)> {
+    decls
+        .iter()
+        .map(|decl| (decl.name(), decl.type_string()))
+        .collect()
+}
+
+#[test]
+fn parse_struct() {
+    // Check that the members of a structure are parsed, including bit fields, arrays, function
+    // pointers and nested anonymous types.
+    let decl = Decl::parse(
+        "s#foo",
+        &[
+            "struct", "foo", "{", "int", "a", ";", "s#bar", "*", "b", ";", "unsigned", "int", "c",
+            ":", "3", ";", "char", "d", "[", "16", "]", ";", "int", "(", "*", "e", ")", "(",
+            "long", ")", ";", "union", "{", "int", "x", ";", "long", "y", ";", "}", "f", ";", "}",
+        ],
+    );
+    let Decl::Struct(aggregate) = decl else {
+        panic!("assertion failed: {:?} is not a struct", decl);
+    };
+    assert_eq!(aggregate.name(), Some("foo"));
+    assert_eq!(
+        decls(aggregate.members().unwrap()),
+        [
+            (Some("a"), "int".to_string()),
+            (Some("b"), "s#bar *".to_string()),
+            (Some("c"), "unsigned int : 3".to_string()),
+            (Some("d"), "char [ 16 ]".to_string()),
+            (Some("e"), "int ( * ) ( long )".to_string()),
+            (Some("f"), "union { int x ; long y ; }".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn parse_incomplete_union() {
+    // Check that an incomplete definition has no members.
+    let decl = Decl::parse("u#foo", &["union", "foo", "{", "UNKNOWN", "}"]);
+    let Decl::Union(aggregate) = decl else {
+        panic!("assertion failed: {:?} is not a union", decl);
+    };
+    assert_eq!(aggregate.name(), Some("foo"));
+    assert_eq!(aggregate.members(), None);
+}
+
+#[test]
+fn parse_enum() {
+    // Check that the constants of an enumeration are parsed with their values.
+    let decl = Decl::parse(
+        "e#foo",
+        &[
+            "enum", "foo", "{", "FOO_A", ",", "FOO_B", "=", "E#FOO_B", ",", "FOO_C", "=", "(", "1",
+            "<<", "3", ")", "}",
+        ],
+    );
+    let Decl::Enum(enumeration) = decl else {
+        panic!("assertion failed: {:?} is not an enum", decl);
+    };
+    assert_eq!(enumeration.name(), Some("foo"));
+    let enumerators: Vec<_> = enumeration
+        .enumerators()
+        .unwrap()
+        .iter()
+        .map(|enumerator| (enumerator.name(), enumerator.value()))
+        .collect();
+    assert_eq!(
+        enumerators,
+        [
+            ("FOO_A", None),
+            ("FOO_B", Some("E#FOO_B")),
+            ("FOO_C", Some("( 1 << 3 )")),
+        ]
+    );
+}
+
+#[test]
+fn parse_typedef() {
+    // Check that a typedef provides its name and aliased type.
+    let decl = Decl::parse("t#u64", &["typedef", "unsigned", "long", "long", "u64"]);
+    let Decl::Typedef(typedef) = decl else {
+        panic!("assertion failed: {:?} is not a typedef", decl);
+    };
+    assert_eq!(typedef.name(), Some("u64"));
+    assert_eq!(typedef.type_string(), "unsigned long long");
+}
+
+#[test]
+fn parse_function() {
+    // Check that an exported function provides its return type and parameters.
+    let decl = Decl::parse(
+        "bar",
+        &[
+            "s#foo", "*", "bar", "(", "const", "char", "*", "name", ",", "int", "(", "*", ")", "(",
+            "void", ")", ",", "...", ")",
+        ],
+    );
+    let Decl::Function(function) = decl else {
+        panic!("assertion failed: {:?} is not a function", decl);
+    };
+    assert_eq!(function.name(), "bar");
+    assert_eq!(function.return_words(), ["s#foo", "*"]);
+    assert_eq!(
+        decls(function.params()),
+        [
+            (Some("name"), "const char *".to_string()),
+            (None, "int ( * ) ( void )".to_string()),
+            (None, "...".to_string()),
+        ]
+    );
+
+    let decl = Decl::parse("baz", &["int", "baz", "(", "void", ")"]);
+    let Decl::Function(function) = decl else {
+        panic!("assertion failed: {:?} is not a function", decl);
+    };
+    assert_eq!(function.params(), []);
+
+    // Check that a function returning a function pointer has its name nested in parentheses.
+    let decl = Decl::parse(
+        "qux",
+        &[
+            "void", "(", "*", "qux", "(", "int", ")", ")", "(", "long", ")",
+        ],
+    );
+    let Decl::Function(function) = decl else {
+        panic!("assertion failed: {:?} is not a function", decl);
+    };
+    assert_eq!(
+        function.return_words(),
+        ["void", "(", "*", ")", "(", "long", ")"]
+    );
+    assert_eq!(decls(function.params()), [(None, "int".to_string())]);
+}
+
+#[test]
+fn parse_variable() {
+    // Check that an exported variable, including a function pointer, is parsed as a declaration.
+    let decl = Decl::parse("jiffies", &["volatile", "unsigned", "long", "jiffies"]);
+    let Decl::Variable(variable) = decl else {
+        panic!("assertion failed: {:?} is not a variable", decl);
+    };
+    assert_eq!(variable.name(), Some("jiffies"));
+    assert_eq!(variable.type_string(), "volatile unsigned long");
+
+    let decl = Decl::parse(
+        "handler",
+        &["void", "(", "*", "handler", ")", "(", "int", ")"],
+    );
+    let Decl::Variable(variable) = decl else {
+        panic!("assertion failed: {:?} is not a variable", decl);
+    };
+    assert_eq!(variable.name(), Some("handler"));
+    assert_eq!(variable.type_string(), "void ( * ) ( int )");
+}
+
+#[test]
+fn parse_constant_and_other() {
+    // Check that enumerator constants and unrecognized records are preserved as words.
+    assert_eq!(
+        Decl::parse("E#FOO_MAX", &["(", "7", "+", "1", ")"]),
+        Decl::Constant("( 7 + 1 )".to_string())
+    );
+    assert_eq!(
+        Decl::parse("s#foo", &["struct", "foo"]),
+        Decl::Other(vec!["struct".to_string(), "foo".to_string()])
+    );
+}
+
+#[test]
+fn member_changes() {
+    // Check that added, removed, changed and moved members are described.
+    let old_decl = Decl::parse(
+        "s#foo",
+        &[
+            "struct", "foo", "{", "int", "a", ";", "int", "b", ";", "int", "c", ";", "int", "d",
+            ";", "}",
+        ],
+    );
+    let new_decl = Decl::parse(
+        "s#foo",
+        &[
+            "struct", "foo", "{", "long", "a", ";", "int", "d", ";", "int", "c", ";", "s#bar", "*",
+            "e", ";", "}",
+        ],
+    );
+    assert_eq!(
+        old_decl.describe_member_changes(&new_decl),
+        [
+            "struct foo: member 'a' changed type from int to long",
+            "struct foo: member 'b' removed from position 2",
+            "struct foo: member 'e' added at position 4 with type s#bar *",
+            "struct foo: member 'd' moved from position 4 to 2",
+        ]
+    );
+}
+
+#[test]
+fn member_changes_incomplete() {
+    // Check that no member changes are described when a definition is incomplete or the
+    // declarations are not comparable.
+    let old_decl = Decl::parse("s#foo", &["struct", "foo", "{", "UNKNOWN", "}"]);
+    let new_decl = Decl::parse("s#foo", &["struct", "foo", "{", "int", "a", ";", "}"]);
+    assert!(old_decl.describe_member_changes(&new_decl).is_empty());
+    let new_decl = Decl::parse("s#foo", &["union", "foo", "{", "int", "a", ";", "}"]);
+    assert!(old_decl.describe_member_changes(&new_decl).is_empty());
+}
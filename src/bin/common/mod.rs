@@ -0,0 +1,481 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! Command-line handling shared by the programs of the package.
+
+use suse_kabi_tools::rules::{AnnotationStyle, Rules};
+use suse_kabi_tools::symvers::read_symbol_list;
+use suse_kabi_tools::{display_path, LogLevel, PathStyle};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+/// An exit status of a program, as documented in the EXIT STATUS section of its manual page.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExitStatus {
+    /// The command completed and found no differences or problems.
+    Success = 0,
+    /// The command completed and found differences or problems.
+    Differences = 1,
+    /// The command line or configuration is invalid.
+    Usage = 2,
+    /// The command failed, for instance, because an input could not be read or parsed.
+    Error = 3,
+}
+
+impl ExitStatus {
+    /// Terminates the program with the exit status.
+    pub fn exit(self) -> ! {
+        process::exit(self as i32)
+    }
+}
+
+/// An iterator over command-line arguments which allows to split bundled short options into
+/// separate arguments.
+///
+/// A bundle such as `-dd` is split into `-d` and `-d`. A short option which takes a value ends the
+/// bundle and the rest of the argument becomes its value, for instance, `-ho out` is split into
+/// `-h` and `-o`, followed by `out`.
+pub struct ShortOptions<I: Iterator<Item = String>> {
+    args: I,
+    flags: &'static str,
+    value_options: &'static str,
+    pending: VecDeque<String>,
+}
+
+impl<I: Iterator<Item = String>> ShortOptions<I> {
+    /// Creates a new iterator over `args`, recognizing short options without a value in `flags`
+    /// and short options with a value in `value_options`.
+    pub fn new(args: I, flags: &'static str, value_options: &'static str) -> Self {
+        Self {
+            args,
+            flags,
+            value_options,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Returns the underlying iterator, positioned after the last returned argument.
+    pub fn into_inner(self) -> I {
+        assert!(self.pending.is_empty());
+        self.args
+    }
+
+    /// Splits the `arg` if it is a bundle of known short options and queues the separate options
+    /// to be returned next. Returns `true` if the `arg` was split, or `false` if it should be
+    /// handled as is.
+    pub fn unbundle(&mut self, arg: &str) -> bool {
+        let Some(bundle) = arg.strip_prefix('-') else {
+            return false;
+        };
+        if bundle.starts_with('-') || bundle.chars().count() < 2 {
+            return false;
+        }
+
+        let mut split = Vec::new();
+        for (idx, c) in bundle.char_indices() {
+            if self.flags.contains(c) {
+                split.push(format!("-{}", c));
+            } else if self.value_options.contains(c) {
+                split.push(format!("-{}", &bundle[idx..]));
+                break;
+            } else {
+                return false;
+            }
+        }
+        for option in split.into_iter().rev() {
+            self.pending.push_front(option);
+        }
+        true
+    }
+}
+
+impl<I: Iterator<Item = String>> Iterator for ShortOptions<I> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.pending.pop_front().or_else(|| self.args.next())
+    }
+}
+
+/// A checker of the arguments of a command, which collects all problems found in the arguments
+/// and reports them at once, followed by a hint how to display the usage.
+pub struct ArgChecker {
+    command: Option<&'static str>,
+    num_errors: usize,
+    operands: Vec<String>,
+}
+
+impl ArgChecker {
+    /// Creates a new checker for the arguments of the specified `command`, or for the global
+    /// arguments if `command` is [`None`].
+    pub fn new(command: Option<&'static str>) -> Self {
+        Self {
+            command,
+            num_errors: 0,
+            operands: Vec::new(),
+        }
+    }
+
+    /// Records a problem which was already reported.
+    pub fn failed(&mut self) {
+        self.num_errors += 1;
+    }
+
+    /// Reports a problem described by `message`.
+    pub fn error(&mut self, message: &str) {
+        eprintln!("{}", message);
+        self.failed();
+    }
+
+    /// Adds an operand of the command.
+    pub fn operand(&mut self, arg: String) {
+        self.operands.push(arg);
+    }
+
+    /// Checks that the command was given exactly the operands described by `names` and returns
+    /// them. Fails with a usage hint if any problem was found in the arguments.
+    pub fn finish<const N: usize>(mut self, names: [&str; N]) -> Result<[String; N], ExitStatus> {
+        for name in names.iter().skip(self.operands.len()) {
+            self.error(&format!("The {} is missing", name));
+        }
+        for arg in self.operands.iter().skip(N) {
+            eprintln!(
+                "Excess {} argument '{}' specified",
+                self.command.unwrap_or("global"),
+                arg
+            );
+            self.num_errors += 1;
+        }
+        self.check()?;
+        Ok(self.operands.try_into().unwrap())
+    }
+
+    /// Fails with a usage hint and [`ExitStatus::Usage`] if any problem was found in the
+    /// arguments.
+    pub fn check(&self) -> Result<(), ExitStatus> {
+        if self.num_errors == 0 {
+            return Ok(());
+        }
+        let program = env!("CARGO_BIN_NAME");
+        match self.command {
+            Some(command) => {
+                eprintln!("Try '{} {} --help' for more information.", program, command)
+            }
+            None => eprintln!("Try '{} --help' for more information.", program),
+        }
+        Err(ExitStatus::Usage)
+    }
+}
+
+/// Resolves the `name` of a command given on the command line to one of the `commands`. The name
+/// can be a full command name, one of the `aliases` or an unambiguous prefix of a command name.
+///
+/// An unknown or ambiguous name is reported in the `checker`, with a suggestion of the closest
+/// command if the name looks like a typo.
+pub fn resolve_command(
+    name: &str,
+    commands: &[&'static str],
+    aliases: &[(&str, &'static str)],
+    checker: &mut ArgChecker,
+) -> Option<&'static str> {
+    if let Some(command) = commands.iter().find(|command| **command == name) {
+        return Some(command);
+    }
+    if let Some((_, command)) = aliases.iter().find(|(alias, _)| *alias == name) {
+        return Some(command);
+    }
+
+    let matches: Vec<_> = commands
+        .iter()
+        .filter(|command| command.starts_with(name))
+        .collect();
+    match matches.as_slice() {
+        [command] => return Some(command),
+        [] => {}
+        _ => {
+            let candidates: Vec<_> = matches
+                .iter()
+                .map(|command| format!("'{}'", command))
+                .collect();
+            checker.error(&format!(
+                "Ambiguous command '{}', possible commands: {}",
+                name,
+                candidates.join(", ")
+            ));
+            return None;
+        }
+    }
+
+    // Suggest the closest command, considering also the aliases.
+    let max_distance = usize::max(2, name.chars().count() / 3);
+    let suggestion = commands
+        .iter()
+        .map(|command| (*command, *command))
+        .chain(aliases.iter().copied())
+        .map(|(word, command)| (edit_distance(name, word), command))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance);
+    match suggestion {
+        Some((_, command)) => checker.error(&format!(
+            "Unrecognized command '{}', did you mean '{}'?",
+            name, command
+        )),
+        None => checker.error(&format!("Unrecognized command '{}'", name)),
+    }
+    None
+}
+
+/// Returns the Levenshtein distance between two strings, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Evaluates the result of handling an argument. If handling of the argument failed, the problem
+/// is recorded in the [`ArgChecker`] and the parsing continues with the next argument.
+macro_rules! check_arg {
+    ($checker:expr, $result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(()) => {
+                $checker.failed();
+                continue;
+            }
+        }
+    };
+}
+
+/// Handles an option with a mandatory value.
+///
+/// When the `arg` matches the `short` or `long` variant, the function returns [`Ok(Some(String))`]
+/// with the option value. Otherwise, [`Ok(None)`] is returned when the `arg` doesn't match, or
+/// [`Err`] in case of an error. An option without a short variant is specified by `short` set to
+/// [`None`].
+pub fn handle_value_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+    short: Option<&str>,
+    long: &str,
+) -> Result<Option<String>, ()> {
+    // Handle '-<short> <value>' and '--<long> <value>'.
+    if Some(arg) == short || arg == long {
+        match args.next() {
+            Some(value) => return Ok(Some(value.to_string())),
+            None => {
+                eprintln!("Missing argument for '{}'", long);
+                return Err(());
+            }
+        };
+    }
+
+    // Handle '-<short><value>'.
+    if let Some(value) = short.and_then(|short| arg.strip_prefix(short)) {
+        return Ok(Some(value.to_string()));
+    }
+
+    // Handle '--<long>=<value>'.
+    if let Some(rem) = arg.strip_prefix(long) {
+        if let Some(value) = rem.strip_prefix('=') {
+            return Ok(Some(value.to_string()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--rules` option which specifies a severities file, and returns the rules read
+/// from the file.
+pub fn handle_rules_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<Rules>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--rules")? {
+        return load_rules(&value).map(Some);
+    }
+
+    Ok(None)
+}
+
+/// Reads the rules from the severities file `path`, reporting any error on the standard error.
+pub fn load_rules<P: AsRef<Path>>(path: P) -> Result<Rules, ()> {
+    let path = path.as_ref();
+    let mut rules = Rules::new();
+    if let Err(err) = rules.load(path) {
+        eprintln!(
+            "Failed to read severities from '{}': {}",
+            display_path(path),
+            err
+        );
+        return Err(());
+    }
+    Ok(rules)
+}
+
+/// Reads the names of the exports listed in the whitelist file `path`, reporting any error on the
+/// standard error.
+pub fn load_whitelist<P: AsRef<Path>>(path: P) -> Result<Vec<String>, ()> {
+    let path = path.as_ref();
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Failed to open whitelist '{}': {}", display_path(path), err);
+            return Err(());
+        }
+    };
+    read_symbol_list(file).map_err(|err| {
+        eprintln!("Failed to read whitelist '{}': {}", display_path(path), err);
+    })
+}
+
+/// A format of the output of a command.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// A human-readable text.
+    #[default]
+    Text,
+    /// A JSON document, suitable for processing by other tools.
+    Json,
+    /// JSON objects, one per change on separate lines, suitable for streaming processing.
+    Jsonl,
+    /// One-line findings in the style of rpmlint, suitable for OBS build results.
+    Obs,
+    /// Annotations for a CI system, GitHub Actions or GitLab CI.
+    Annotations,
+    /// Gauges in the Prometheus text format, suitable for monitoring systems.
+    Metrics,
+    /// A badness report in the format of the SUSE kABI result aggregation scripts.
+    Badness,
+    /// A wrapped plain-ASCII report, suitable for sending by mail.
+    Mail,
+    /// A Markdown document, suitable for posting to a code review or a wiki.
+    Markdown,
+    /// A standalone HTML document, suitable for viewing in a web browser.
+    Html,
+}
+
+impl OutputFormat {
+    /// Returns the format with the specified name, or [`None`] if the name is not recognized.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "jsonl" => Some(Self::Jsonl),
+            "obs" => Some(Self::Obs),
+            "annotations" => Some(Self::Annotations),
+            "metrics" => Some(Self::Metrics),
+            "badness" => Some(Self::Badness),
+            "mail" => Some(Self::Mail),
+            "markdown" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the format.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+            Self::Obs => "obs",
+            Self::Annotations => "annotations",
+            Self::Metrics => "metrics",
+            Self::Badness => "badness",
+            Self::Mail => "mail",
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+        }
+    }
+
+    /// Returns whether the format reports changes of exports rated by severities.
+    pub fn is_rated(self) -> bool {
+        matches!(self, Self::Obs | Self::Annotations | Self::Badness)
+    }
+
+    /// Returns whether the format is supported only by the compare commands.
+    pub fn is_compare_only(self) -> bool {
+        self.is_rated()
+            || matches!(
+                self,
+                Self::Jsonl | Self::Metrics | Self::Mail | Self::Markdown | Self::Html
+            )
+    }
+}
+
+/// Handles the `--format` option which specifies the format of the output of a command.
+pub fn handle_format_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<OutputFormat>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--format")? {
+        match OutputFormat::from_name(&value) {
+            Some(format) => return Ok(Some(format)),
+            None => {
+                eprintln!("Invalid value for '{}': unknown format '{}'", arg, value);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Returns the style of annotations for the CI system in which the program runs, GitLab CI if the
+/// `GITLAB_CI` variable is set, and GitHub Actions otherwise.
+pub fn annotation_style() -> AnnotationStyle {
+    if env::var_os("GITLAB_CI").is_some() {
+        AnnotationStyle::GitLab
+    } else {
+        AnnotationStyle::GitHub
+    }
+}
+
+/// Handles the `--log-level` option which specifies the maximum level of logged messages.
+pub fn handle_log_level_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<LogLevel>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--log-level")? {
+        match LogLevel::from_name(&value) {
+            Some(level) => return Ok(Some(level)),
+            None => {
+                eprintln!("Invalid value for '{}': unknown level '{}'", arg, value);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--absolute-paths` and `--relative-to` options which select how paths of files are
+/// shown.
+pub fn handle_path_style_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<PathStyle>, ()> {
+    if arg == "--absolute-paths" {
+        return Ok(Some(PathStyle::Absolute));
+    }
+
+    if let Some(value) = handle_value_option(arg, args, None, "--relative-to")? {
+        if value.is_empty() {
+            eprintln!("Invalid value for '{}': empty directory", arg);
+            return Err(());
+        }
+        return Ok(Some(PathStyle::RelativeTo(PathBuf::from(value))));
+    }
+
+    Ok(None)
+}
@@ -1,314 +1,2989 @@
 // Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
 // SPDX-License-Identifier: GPL-2.0-or-later
 
-use suse_kabi_tools::sym::SymCorpus;
-use suse_kabi_tools::{debug, init_debug_level};
-use std::path::Path;
-use std::time::Instant;
-use std::{env, io, process};
+use regex::Regex;
+use suse_kabi_tools::btf::Btf;
+use suse_kabi_tools::diff::{Algorithm, DiffOptions, Format};
+use suse_kabi_tools::glob;
+use suse_kabi_tools::rules::{Rules, Severity};
+use suse_kabi_tools::sym::{
+    ChangeKind, CompareOptions, CompareResult, ConsolidateOptions, DuplicateExports, InputDialect,
+    LoadOptions, LoadTimings, Metrics, ScoreOptions, SymCorpus, POINTER_SIZE_TYPES,
+};
+use suse_kabi_tools::symvers::SymversCorpus;
+use suse_kabi_tools::{
+    debug, display_path, init_logging, json_string, set_path_style, Compression, Diagnostics,
+    LogLevel, StderrLogSink, Warning,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{env, fs, io, mem, thread};
 
-/// An elapsed timer to measure time of some operation.
+#[macro_use]
+mod common;
+
+use common::{
+    annotation_style, handle_format_option, handle_log_level_option, handle_path_style_option,
+    handle_rules_option, handle_value_option, load_rules, load_whitelist, resolve_command,
+    ArgChecker, ExitStatus, OutputFormat, ShortOptions,
+};
+
+/// The number of the slowest input files listed in the JSON timing output.
+const NUM_SLOWEST_FILES: usize = 10;
+
+/// A collector of durations of the operations performed by a command.
 ///
-/// The time is measured between when the object is instantiated and when it is dropped. A message
-/// with the elapsed time is output when the object is dropped.
-enum Timing {
-    Active { desc: String, start: Instant },
-    Inactive,
+/// In the text format, the duration of each operation is output as soon as the operation
+/// completes. In the JSON format, the durations are collected, together with the detailed timings
+/// of loading the inputs, and output as a single object when the report is dropped.
+struct TimingReport {
+    format: Option<OutputFormat>,
+    phases: RefCell<Vec<(&'static str, String, Duration)>>,
+    loads: RefCell<Vec<(String, Arc<LoadTimings>)>>,
 }
 
-impl Timing {
-    fn new(do_timing: bool, desc: &str) -> Self {
-        if do_timing {
+impl TimingReport {
+    /// Creates a new report, which is inactive if `format` is [`None`].
+    fn new(format: Option<OutputFormat>) -> Self {
+        Self {
+            format,
+            phases: RefCell::new(Vec::new()),
+            loads: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Starts measuring an operation of the given `phase`, described by `desc`.
+    fn start(&self, phase: &'static str, desc: &str) -> Timing<'_> {
+        if self.format.is_some() {
             Timing::Active {
+                report: self,
+                phase,
                 desc: desc.to_string(),
                 start: Instant::now(),
             }
-        } else {
-            Timing::Inactive
+        } else {
+            Timing::Inactive
+        }
+    }
+
+    /// Returns the `options` for loading the `path`, extended to collect the detailed timings of
+    /// the loading if the report is in the JSON format.
+    fn load_options(&self, path: &str, options: &LoadOptions) -> LoadOptions {
+        if self.format != Some(OutputFormat::Json) {
+            return options.clone();
+        }
+        let timings = Arc::new(LoadTimings::new());
+        self.loads
+            .borrow_mut()
+            .push((path.to_string(), timings.clone()));
+        options.clone().timings(timings)
+    }
+
+    /// Records the duration of a completed operation.
+    fn record(&self, phase: &'static str, desc: String, time: Duration) {
+        match self.format {
+            Some(OutputFormat::Text) => eprintln!("{}: {:.3?}", desc, time),
+            Some(OutputFormat::Json) => self.phases.borrow_mut().push((phase, desc, time)),
+            _ => {}
+        }
+    }
+
+    /// Formats the collected durations as a JSON object.
+    fn to_json(&self) -> String {
+        let phases = self
+            .phases
+            .borrow()
+            .iter()
+            .map(|(phase, desc, time)| {
+                format!(
+                    "{{\"phase\":{},\"description\":{},\"seconds\":{:.6}}}",
+                    json_string(phase),
+                    json_string(desc),
+                    time.as_secs_f64()
+                )
+            })
+            .collect::<Vec<_>>();
+        let loads = self
+            .loads
+            .borrow()
+            .iter()
+            .map(|(path, timings)| {
+                let parse = timings.parse().as_secs_f64();
+                let workers = timings
+                    .workers()
+                    .iter()
+                    .map(|worker| {
+                        let busy = worker.busy().as_secs_f64();
+                        let utilization = if parse > 0.0 { busy / parse } else { 0.0 };
+                        format!(
+                            "{{\"num_files\":{},\"busy_seconds\":{:.6},\"utilization\":{:.3}}}",
+                            worker.num_files(),
+                            busy,
+                            utilization
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let files = timings
+                    .slowest_files(NUM_SLOWEST_FILES)
+                    .iter()
+                    .map(|(file, time)| {
+                        format!(
+                            "{{\"path\":{},\"seconds\":{:.6}}}",
+                            json_string(&file.to_string_lossy()),
+                            time.as_secs_f64()
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    concat!(
+                        "{{\"path\":{},\"scan_seconds\":{:.6},\"parse_seconds\":{:.6},",
+                        "\"merge_seconds\":{:.6},\"workers\":[{}],\"slowest_files\":[{}]}}"
+                    ),
+                    json_string(path),
+                    timings.scan().as_secs_f64(),
+                    parse,
+                    timings.merge().as_secs_f64(),
+                    workers.join(","),
+                    files.join(",")
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"phases\":[{}],\"loads\":[{}]}}",
+            phases.join(","),
+            loads.join(",")
+        )
+    }
+}
+
+impl Drop for TimingReport {
+    fn drop(&mut self) {
+        if self.format == Some(OutputFormat::Json) {
+            eprintln!("{}", self.to_json());
+        }
+    }
+}
+
+/// An elapsed timer to measure time of some operation.
+///
+/// The time is measured between when the object is instantiated and when it is dropped. The
+/// elapsed time is recorded in the associated [`TimingReport`] when the object is dropped.
+enum Timing<'a> {
+    Active {
+        report: &'a TimingReport,
+        phase: &'static str,
+        desc: String,
+        start: Instant,
+    },
+    Inactive,
+}
+
+impl Drop for Timing<'_> {
+    fn drop(&mut self) {
+        match self {
+            Timing::Active {
+                report,
+                phase,
+                desc,
+                start,
+            } => {
+                report.record(phase, mem::take(desc), start.elapsed());
+            }
+            Timing::Inactive => {}
+        }
+    }
+}
+
+/// A receiver of warnings which outputs them on the standard error and counts them.
+struct StderrDiagnostics {
+    num_warnings: AtomicUsize,
+    quiet: bool,
+}
+
+impl StderrDiagnostics {
+    /// Creates a new receiver. Warnings are only counted and not output if `quiet` is set.
+    fn new(quiet: bool) -> Self {
+        Self {
+            num_warnings: AtomicUsize::new(0),
+            quiet,
+        }
+    }
+
+    /// Checks whether the operation should fail because some warnings were emitted and they are
+    /// treated as errors.
+    fn check(&self, warnings_as_errors: bool) -> Result<(), ExitStatus> {
+        let num_warnings = self.num_warnings.load(Ordering::Relaxed);
+        if warnings_as_errors && num_warnings > 0 {
+            eprintln!("Found {} warning(s) treated as errors", num_warnings);
+            return Err(ExitStatus::Error);
+        }
+        Ok(())
+    }
+}
+
+impl Diagnostics for StderrDiagnostics {
+    fn warning(&self, warning: Warning) {
+        if !self.quiet {
+            eprintln!("Warning: {}", warning);
+        }
+        self.num_warnings.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A setting of when type diffs in text reports are colored.
+#[derive(Clone, Copy, Default, Eq, PartialEq)]
+enum ColorMode {
+    /// Color the diffs if the standard output is a terminal.
+    #[default]
+    Auto,
+    /// Always color the diffs.
+    Always,
+    /// Never color the diffs.
+    Never,
+}
+
+impl ColorMode {
+    /// Returns the mode with the specified name, `auto`, `always` or `never`, or [`None`] if the
+    /// name is not recognized.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a report written to the standard output is colored in this mode.
+    fn is_enabled(self) -> bool {
+        match self {
+            Self::Auto => io::stdout().is_terminal(),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Default settings read from configuration files and environment variables, which are overridden
+/// by command-line options.
+#[derive(Clone, Default)]
+struct Config {
+    jobs: Option<i32>,
+    format: Option<OutputFormat>,
+    color: ColorMode,
+    rules: Option<PathBuf>,
+    whitelist: Option<PathBuf>,
+    max_badness: Option<u64>,
+    suppressions: Vec<Suppression>,
+}
+
+/// Environment variables which specify settings, and the corresponding configuration keys.
+const CONFIG_ENV_VARS: [(&str, &str); 6] = [
+    ("KSYMTYPES_JOBS", "jobs"),
+    ("KSYMTYPES_FORMAT", "format"),
+    ("KSYMTYPES_COLOR", "color"),
+    ("KSYMTYPES_RULES", "rules"),
+    ("KSYMTYPES_WHITELIST", "whitelist"),
+    ("KSYMTYPES_MAX_BADNESS", "max-badness"),
+];
+
+impl Config {
+    /// Reads the configuration from the user configuration file and then from the local
+    /// configuration file, which takes precedence. Missing files are skipped.
+    fn load_files(&mut self) -> Result<(), ()> {
+        let mut paths = Vec::new();
+        if let Some(config_dir) = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        {
+            paths.push(config_dir.join("ksymtypes").join("config.toml"));
+        }
+        if let Ok(current_dir) = env::current_dir() {
+            if let Some(path) = current_dir
+                .ancestors()
+                .map(|dir| dir.join(".ksymtypes.toml"))
+                .find(|path| path.is_file())
+            {
+                paths.push(path);
+            }
+        }
+
+        for path in paths {
+            if path.exists() {
+                self.load_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the configuration from the file `path`, which takes precedence over the settings
+    /// read so far.
+    fn load_file(&mut self, path: &Path) -> Result<(), ()> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!(
+                    "Failed to read configuration from '{}': {}",
+                    path.display(),
+                    err
+                );
+                return Err(());
+            }
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+        if let Err(err) = self.parse(&data, dir) {
+            eprintln!("Invalid configuration in '{}': {}", path.display(), err);
+            return Err(());
+        }
+        Ok(())
+    }
+
+    /// Reads the configuration from the environment variables listed in [`CONFIG_ENV_VARS`],
+    /// which take precedence over the configuration files.
+    fn load_env(&mut self) -> Result<(), ()> {
+        for (name, key) in CONFIG_ENV_VARS {
+            let Some(value) = env::var_os(name) else {
+                continue;
+            };
+            let value = value.to_string_lossy();
+            let value = match value.parse() {
+                Ok(value) => ConfigValue::Integer(value),
+                Err(_) => ConfigValue::String(value.into_owned()),
+            };
+            match self.set(key, value, Path::new("")) {
+                Ok(()) => {}
+                Err(ConfigError::MissingFile(path)) => {
+                    eprintln!("File '{}' for '{}' does not exist", path.display(), name);
+                    return Err(());
+                }
+                Err(_) => {
+                    eprintln!("Invalid value for '{}'", name);
+                    return Err(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses configuration data and updates the settings specified in it. Suppressions are added
+    /// to the ones read so far.
+    ///
+    /// The data is a subset of TOML with one `key = value` setting per line, where the value is
+    /// a basic string or a decimal integer, optionally followed by a comment. The settings are
+    /// followed by `[[suppress]]` tables, each describing one suppression. Relative paths in the
+    /// settings are relative to the directory `dir` of the configuration file.
+    fn parse(&mut self, data: &str, dir: &Path) -> Result<(), String> {
+        let num_suppressions = self.suppressions.len();
+        // The suppression being read, with the index of the line of its header.
+        let mut suppression: Option<(usize, Suppression)> = None;
+        let mut keys = HashSet::new();
+        for (line_idx, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                if let Some((header_idx, suppression)) = suppression.take() {
+                    self.add_suppression(suppression, header_idx, num_suppressions)?;
+                }
+                let header = match line.split_once('#') {
+                    Some((header, _)) => header.trim_end(),
+                    None => line,
+                };
+                if header != "[[suppress]]" {
+                    return Err(format!("line {}: unknown table '{}'", line_idx + 1, header));
+                }
+                suppression = Some((line_idx, Suppression::default()));
+                keys.clear();
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected 'key = value'", line_idx + 1))?;
+            let key = key.trim();
+            if !keys.insert(key.to_string()) {
+                return Err(format!("line {}: duplicate key '{}'", line_idx + 1, key));
+            }
+            match (parse_config_value(value.trim()), &mut suppression) {
+                (Some(value), Some((_, suppression))) => suppression.set(key, value),
+                (Some(value), None) => self.set(key, value, dir),
+                (None, _) => Err(ConfigError::InvalidValue),
+            }
+            .map_err(|err| match err {
+                ConfigError::UnknownKey => format!("line {}: unknown key '{}'", line_idx + 1, key),
+                ConfigError::InvalidValue => {
+                    format!("line {}: invalid value for '{}'", line_idx + 1, key)
+                }
+                ConfigError::MissingFile(path) => format!(
+                    "line {}: file '{}' for '{}' does not exist",
+                    line_idx + 1,
+                    path.display(),
+                    key
+                ),
+            })?;
+        }
+        if let Some((header_idx, suppression)) = suppression {
+            self.add_suppression(suppression, header_idx, num_suppressions)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a suppression read from the table with the header on the line with the index
+    /// `header_idx`. The suppression must specify its name and justification and must not overlap
+    /// with the suppressions read from the same file, which start at the index `first`.
+    fn add_suppression(
+        &mut self,
+        suppression: Suppression,
+        header_idx: usize,
+        first: usize,
+    ) -> Result<(), String> {
+        for (key, value) in [
+            ("name", &suppression.name),
+            ("justification", &suppression.justification),
+        ] {
+            if value.is_empty() {
+                return Err(format!(
+                    "line {}: suppression without '{}'",
+                    header_idx + 1,
+                    key
+                ));
+            }
+        }
+        if self.suppressions[first..].iter().any(|other| {
+            other.name == suppression.name
+                && (other.kind.is_none()
+                    || suppression.kind.is_none()
+                    || other.kind == suppression.kind)
+        }) {
+            return Err(format!(
+                "line {}: duplicate suppression of '{}'",
+                header_idx + 1,
+                suppression.name
+            ));
+        }
+        self.suppressions.push(suppression);
+        Ok(())
+    }
+
+    /// Removes the changes acknowledged by the suppressions from a comparison result.
+    fn suppress_changes(&self, result: &mut CompareResult) {
+        if self.suppressions.is_empty() {
+            return;
+        }
+        for suppression in &self.suppressions {
+            debug!(
+                "Suppress {} changes of '{}': {}",
+                suppression
+                    .kind
+                    .map_or("all".to_string(), |kind| kind.to_string()),
+                suppression.name,
+                suppression.justification
+            );
+        }
+        result.suppress(|name, kind| {
+            self.suppressions
+                .iter()
+                .any(|suppression| suppression.matches(name, kind))
+        });
+    }
+
+    /// Sets the value of a setting identified by its configuration key. A relative path is taken
+    /// relative to the directory `dir`.
+    fn set(&mut self, key: &str, value: ConfigValue, dir: &Path) -> Result<(), ConfigError> {
+        match (key, value) {
+            ("jobs", ConfigValue::String(value)) if value == "auto" => {
+                self.jobs = Some(auto_jobs());
+            }
+            ("jobs", ConfigValue::Integer(jobs)) if jobs >= 1 => {
+                self.jobs = Some(jobs);
+            }
+            ("format", ConfigValue::String(value)) if OutputFormat::from_name(&value).is_some() => {
+                self.format = OutputFormat::from_name(&value);
+            }
+            ("color", ConfigValue::String(value)) if ColorMode::from_name(&value).is_some() => {
+                self.color = ColorMode::from_name(&value).unwrap();
+            }
+            ("rules" | "whitelist", ConfigValue::String(value)) if !value.is_empty() => {
+                let path = dir.join(value);
+                if !path.is_file() {
+                    return Err(ConfigError::MissingFile(path));
+                }
+                if key == "rules" {
+                    self.rules = Some(path);
+                } else {
+                    self.whitelist = Some(path);
+                }
+            }
+            ("max-badness", ConfigValue::Integer(value)) if value >= 0 => {
+                self.max_badness = Some(value as u64);
+            }
+            ("jobs" | "format" | "color" | "rules" | "whitelist" | "max-badness", _) => {
+                return Err(ConfigError::InvalidValue)
+            }
+            _ => return Err(ConfigError::UnknownKey),
+        }
+        Ok(())
+    }
+}
+
+/// An acknowledged change of an export or a type, read from a `[[suppress]]` table of
+/// a configuration file. Changes of all kinds are suppressed if `kind` is [`None`].
+#[derive(Clone, Default)]
+struct Suppression {
+    name: String,
+    kind: Option<ChangeKind>,
+    justification: String,
+}
+
+impl Suppression {
+    /// Sets the value of a key of the suppression table.
+    fn set(&mut self, key: &str, value: ConfigValue) -> Result<(), ConfigError> {
+        match (key, value) {
+            ("name", ConfigValue::String(value)) if !value.is_empty() => {
+                self.name = value;
+            }
+            ("kind", ConfigValue::String(value)) if ChangeKind::from_name(&value).is_some() => {
+                self.kind = ChangeKind::from_name(&value);
+            }
+            ("justification", ConfigValue::String(value)) if !value.trim().is_empty() => {
+                self.justification = value;
+            }
+            ("name" | "kind" | "justification", _) => return Err(ConfigError::InvalidValue),
+            _ => return Err(ConfigError::UnknownKey),
+        }
+        Ok(())
+    }
+
+    /// Returns whether the suppression applies to a change of the export or type `name` of the
+    /// given kind.
+    fn matches(&self, name: &str, kind: ChangeKind) -> bool {
+        self.name == name && self.kind.is_none_or(|suppressed| suppressed == kind)
+    }
+}
+
+/// An error found when setting a configuration value.
+enum ConfigError {
+    UnknownKey,
+    InvalidValue,
+    MissingFile(PathBuf),
+}
+
+/// A value of a setting in a configuration file.
+enum ConfigValue {
+    String(String),
+    Integer(i32),
+}
+
+/// Parses a configuration value, optionally followed by a comment. Returns [`None`] if the value
+/// is not a basic string in double quotes or a decimal integer.
+fn parse_config_value(value: &str) -> Option<ConfigValue> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut res = String::new();
+        let mut chars = rest.chars();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => match chars.next()? {
+                    '"' => res.push('"'),
+                    '\\' => res.push('\\'),
+                    'n' => res.push('\n'),
+                    't' => res.push('\t'),
+                    _ => return None,
+                },
+                ch => res.push(ch),
+            }
+        }
+        let rest = chars.as_str().trim_start();
+        return (rest.is_empty() || rest.starts_with('#')).then_some(ConfigValue::String(res));
+    }
+
+    let value = match value.split_once('#') {
+        Some((value, _)) => value.trim_end(),
+        None => value,
+    };
+    value.parse().ok().map(ConfigValue::Integer)
+}
+
+/// Returns the configuration of a command, with the settings from the configuration file
+/// `config_path` given by its `--config` option, if any, taking precedence over `config`.
+fn load_command_config(config: &Config, config_path: Option<&str>) -> Result<Config, ExitStatus> {
+    let mut config = config.clone();
+    if let Some(config_path) = config_path {
+        config
+            .load_file(Path::new(config_path))
+            .map_err(|()| ExitStatus::Usage)?;
+    }
+    Ok(config)
+}
+
+/// Prints the global usage message on the standard output.
+fn print_usage() {
+    print!("{}", USAGE);
+}
+
+/// The global usage message.
+const USAGE: &str = concat!(
+    "Usage: ksymtypes [OPTION...] COMMAND\n",
+    "\n",
+    "Options:\n",
+    "  --absolute-paths              show absolute paths of files\n",
+    "  --color=WHEN                  color type diffs in text reports WHEN, 'auto'\n",
+    "                                (default), 'always' or 'never'\n",
+    "  -d, --debug                   enable debug output, twice for trace output\n",
+    "  -h, --help                    display this help and exit\n",
+    "  --log-level=LEVEL             log messages up to LEVEL, 'error', 'warn'\n",
+    "                                (default), 'info', 'debug' or 'trace'\n",
+    "  --log-timestamps              prefix log messages with elapsed time\n",
+    "  --no-config                   do not read configuration files\n",
+    "  -q, --quiet                   do not output timings and warnings, only errors\n",
+    "  --relative-to=DIR             show paths of files relative to DIR\n",
+    "  --timing[=FORMAT]             output durations of operations in FORMAT, 'text'\n",
+    "                                (default) or 'json'\n",
+    "  --verbose                     output also build details with --version\n",
+    "  --version                     output version information and exit\n",
+    "\n",
+    "Commands:\n",
+    "  consolidate                   consolidate symtypes into a single file\n",
+    "  compare                       show differences between two symtypes corpuses\n",
+    "  report                        render a saved JSON comparison result\n",
+    "  symvers                       generate Module.symvers from symtypes\n",
+    "  symref                        generate genksyms reference files from symtypes\n",
+    "  explain                       explain changed CRCs by differences in symtypes\n",
+    "  verify-btf                    check symtypes against BTF of the kernel\n",
+    "  scan-build                    check a kernel build against a reference\n",
+    "  completions                   print a shell completion script\n",
+);
+
+/// Cargo features which the program can be built with, paired with whether each is enabled.
+const FEATURES: [(&str, bool); 8] = [
+    ("ffi", cfg!(feature = "ffi")),
+    ("gzip", cfg!(feature = "gzip")),
+    ("mmap", cfg!(feature = "mmap")),
+    ("rpm", cfg!(feature = "rpm")),
+    ("serde", cfg!(feature = "serde")),
+    ("tar", cfg!(feature = "tar")),
+    ("xz", cfg!(feature = "xz")),
+    ("zstd", cfg!(feature = "zstd")),
+];
+
+/// Returns the input formats supported by the program.
+fn input_formats() -> Vec<&'static str> {
+    let mut formats = vec!["symtypes", "consolidated symtypes", "directory", "stdin"];
+    if cfg!(feature = "tar") {
+        formats.push("tar");
+    }
+    if cfg!(feature = "rpm") {
+        formats.push("rpm");
+    }
+    if cfg!(feature = "gzip") {
+        formats.push("gzip");
+    }
+    if cfg!(feature = "xz") {
+        formats.push("xz");
+    }
+    if cfg!(feature = "zstd") {
+        formats.push("zstd");
+    }
+    formats
+}
+
+/// Prints the version information on the standard output. If `verbose` is set, also prints
+/// details about the build.
+fn print_version(verbose: bool) {
+    println!("ksymtypes {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return;
+    }
+
+    let features = FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    println!("commit: {}", env!("KSYMTYPES_GIT_COMMIT"));
+    println!("build date: {}", env!("KSYMTYPES_BUILD_DATE"));
+    println!(
+        "features: {}",
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        }
+    );
+    println!("input formats: {}", input_formats().join(", "));
+}
+
+/// Prints the usage message for the `consolidate` command on the standard output.
+fn print_consolidate_usage() {
+    print!("{}", CONSOLIDATE_USAGE);
+}
+
+/// The usage message for the `consolidate` command.
+const CONSOLIDATE_USAGE: &str = concat!(
+    "Usage: ksymtypes consolidate [OPTION...] PATH\n",
+    "Consolidate symtypes into a single file.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  -j NUM, --jobs=NUM            use NUM workers to perform the operation, or one\n",
+    "                                per available CPU if NUM is 'auto' (default)\n",
+    "  --crcs                        record the CRC of each export in the output\n",
+    "  --input-dialect=DIALECT       read symtypes in DIALECT, 'auto' (default),\n",
+    "                                'current' or 'legacy'\n",
+    "  --max-errors=NUM              report up to NUM errors found in the input,\n",
+    "                                or all errors if NUM is 0\n",
+    "  --no-validate                 do not check that all type references resolve\n",
+    "  -o FILE, --output=FILE        write the result in FILE, instead of stdout\n",
+    "  --warnings-as-errors          fail if any warning is reported\n",
+);
+
+/// Prints the usage message for the `compare` command on the standard output.
+fn print_compare_usage() {
+    print!("{}", COMPARE_USAGE);
+}
+
+/// The usage message for the `compare` command.
+const COMPARE_USAGE: &str = concat!(
+    "Usage: ksymtypes compare [OPTION...] PATH PATH2\n",
+    "Show differences between two symtypes corpuses.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  -j NUM, --jobs=NUM            use NUM workers to perform the operation, or one\n",
+    "                                per available CPU if NUM is 'auto' (default)\n",
+    "  --arch-types=FILE             tolerate changes of the architecture-specific\n",
+    "                                types listed in FILE with --cross-arch\n",
+    "  --changed-sources=FILE        compare only symtypes of the sources listed in\n",
+    "                                FILE, one per line\n",
+    "  --config=FILE                 read the rules, whitelist, threshold and\n",
+    "                                suppressions from the configuration file FILE\n",
+    "  --cross-arch                  tolerate differences between architectures\n",
+    "  --diff-algorithm=ALGO         compute type diffs using ALGO, 'myers' (default)\n",
+    "                                or 'patience'\n",
+    "  --diff-headers                precede type diffs with ---/+++ header lines\n",
+    "  --diff-ignore=REGEX           leave out lines matching REGEX from type diffs\n",
+    "  --diff-max-cost=NUM           show a type diff with more than NUM changed\n",
+    "                                lines as a whole removal and addition\n",
+    "  --diff-max-lines=NUM          show at most NUM lines of each type diff\n",
+    "  --diff-merge-gap=NUM          show type changes separated by at most NUM\n",
+    "                                unchanged lines in one hunk\n",
+    "  --diff-moves                  annotate blocks of lines moved in type diffs\n",
+    "  --diff-view=VIEW              show type diffs in VIEW, 'unified' (default),\n",
+    "                                'normal', 'ed' or 'side-by-side'\n",
+    "  --diff-width=NUM              use NUM columns for the side-by-side view\n",
+    "  --format=FORMAT               write the report in FORMAT, 'text' (default),\n",
+    "                                'json', 'jsonl', 'obs', 'annotations',\n",
+    "                                'metrics', 'badness', 'mail', 'markdown' or\n",
+    "                                'html'\n",
+    "  --input-dialect=DIALECT       read symtypes in DIALECT, 'auto' (default),\n",
+    "                                'current' or 'legacy'\n",
+    "  --max-errors=NUM              report up to NUM errors found in the input,\n",
+    "                                or all errors if NUM is 0\n",
+    "  --no-validate                 do not check that all type references resolve\n",
+    "  --output-dir=DIR              write the diffs of each changed export to\n",
+    "                                a separate file in DIR, instead of stdout\n",
+    "  --rules=FILE                  rate the changes by the severities in FILE\n",
+    "  --score                       append the kABI badness score of the changes\n",
+    "  --source-map=FILE             map the changed sources to objects by the make\n",
+    "                                rules in FILE\n",
+    "  --symvers=FILE                annotate the exports with their modules and\n",
+    "                                export types from the Module.symvers file FILE\n",
+    "  --warnings-as-errors          fail if any warning is reported\n",
+);
+
+/// Prints the usage message for the `report` command on the standard output.
+fn print_report_usage() {
+    print!("{}", REPORT_USAGE);
+}
+
+/// The usage message for the `report` command.
+const REPORT_USAGE: &str = concat!(
+    "Usage: ksymtypes report [OPTION...] PATH\n",
+    "Render a comparison result saved by 'compare --format=json' in another format.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  --format=FORMAT               write the report in FORMAT, 'text' (default),\n",
+    "                                'json', 'jsonl', 'obs', 'annotations',\n",
+    "                                'badness', 'mail', 'markdown' or 'html'\n",
+);
+
+/// Prints the usage message for the `symvers` command on the standard output.
+fn print_symvers_usage() {
+    print!("{}", SYMVERS_USAGE);
+}
+
+/// The usage message for the `symvers` command.
+const SYMVERS_USAGE: &str = concat!(
+    "Usage: ksymtypes symvers [OPTION...] PATH\n",
+    "Generate Module.symvers data from symtypes.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  -j NUM, --jobs=NUM            use NUM workers to perform the operation, or one\n",
+    "                                per available CPU if NUM is 'auto' (default)\n",
+    "  -o FILE, --output=FILE        write the result in FILE, instead of stdout\n",
+    "  --symvers=FILE                take export types and namespaces from FILE\n",
+);
+
+/// Prints the usage message for the `symref` command on the standard output.
+fn print_symref_usage() {
+    print!("{}", SYMREF_USAGE);
+}
+
+/// The usage message for the `symref` command.
+const SYMREF_USAGE: &str = concat!(
+    "Usage: ksymtypes symref [OPTION...] PATH\n",
+    "Generate genksyms reference files from symtypes.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  -j NUM, --jobs=NUM            use NUM workers to perform the operation, or one\n",
+    "                                per available CPU if NUM is 'auto' (default)\n",
+    "  -o DIR, --output=DIR          write the files in DIR, instead of the current\n",
+    "                                directory\n",
+    "  --overrides=FILE              mark the symbols listed in FILE as overrides\n",
+);
+
+/// Prints the usage message for the `explain` command on the standard output.
+fn print_explain_usage() {
+    print!("{}", EXPLAIN_USAGE);
+}
+
+/// The usage message for the `explain` command.
+const EXPLAIN_USAGE: &str = concat!(
+    "Usage: ksymtypes explain [OPTION...] SYMVERS SYMVERS2 PATH PATH2\n",
+    "Explain changed CRCs between two symvers files by differences in symtypes.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  -j NUM, --jobs=NUM            use NUM workers to perform the operation, or one\n",
+    "                                per available CPU if NUM is 'auto' (default)\n",
+    "  --diff-headers                precede type diffs with ---/+++ header lines\n",
+    "  --format=FORMAT               write the report in FORMAT, 'text' (default)\n",
+    "                                or 'json'\n",
+);
+
+/// Prints the usage message for the `verify-btf` command on the standard output.
+fn print_verify_btf_usage() {
+    print!("{}", VERIFY_BTF_USAGE);
+}
+
+/// The usage message for the `verify-btf` command.
+const VERIFY_BTF_USAGE: &str = concat!(
+    "Usage: ksymtypes verify-btf [OPTION...] PATH BTF\n",
+    "Check that types of exports in symtypes are consistent with BTF of the kernel.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  -j NUM, --jobs=NUM            use NUM workers to perform the operation, or one\n",
+    "                                per available CPU if NUM is 'auto' (default)\n",
+    "  --format=FORMAT               write the report in FORMAT, 'text' (default)\n",
+    "                                or 'json'\n",
+);
+
+/// Prints the usage message for the `scan-build` command on the standard output.
+fn print_scan_build_usage() {
+    print!("{}", SCAN_BUILD_USAGE);
+}
+
+/// The usage message for the `scan-build` command.
+const SCAN_BUILD_USAGE: &str = concat!(
+    "Usage: ksymtypes scan-build [OPTION...] --reference=PATH\n",
+    "Check symtypes of a kernel build against a reference corpus.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  -j NUM, --jobs=NUM            use NUM workers to perform the operation, or one\n",
+    "                                per available CPU if NUM is 'auto' (default)\n",
+    "  --config=FILE                 read the rules, whitelist, threshold and\n",
+    "                                suppressions from the configuration file FILE\n",
+    "  --input-dialect=DIALECT       read symtypes in DIALECT, 'auto' (default),\n",
+    "                                'current' or 'legacy'\n",
+    "  --max-errors=NUM              report up to NUM errors found in the input,\n",
+    "                                or all errors if NUM is 0\n",
+    "  --obj=DIR                     scan the build output in DIR, instead of\n",
+    "                                $KBUILD_OUTPUT or the current directory\n",
+    "  --reference=PATH              compare the build with the corpus in PATH\n",
+    "  --rules=FILE                  rate the changes by the severities in FILE\n",
+    "  --warnings-as-errors          fail if any warning is reported\n",
+);
+
+/// Prints the usage message for the `completions` command on the standard output.
+fn print_completions_usage() {
+    print!("{}", COMPLETIONS_USAGE);
+}
+
+/// The usage message for the `completions` command.
+const COMPLETIONS_USAGE: &str = concat!(
+    "Usage: ksymtypes completions SHELL\n",
+    "Print a completion script for SHELL, 'bash', 'fish' or 'zsh'.\n",
+    "\n",
+    "Options:\n",
+    "  -h, --help                    display this help and exit\n",
+);
+
+/// Shells for which the `completions` command can print a completion script.
+const SHELLS: [&str; 3] = ["bash", "fish", "zsh"];
+
+/// The commands with their usage messages and the words completed as their operands, or an empty
+/// list if the operands are paths.
+const COMMANDS: [(&str, &str, &[&str]); 9] = [
+    ("consolidate", CONSOLIDATE_USAGE, &[]),
+    ("compare", COMPARE_USAGE, &[]),
+    ("report", REPORT_USAGE, &[]),
+    ("symvers", SYMVERS_USAGE, &[]),
+    ("symref", SYMREF_USAGE, &[]),
+    ("explain", EXPLAIN_USAGE, &[]),
+    ("verify-btf", VERIFY_BTF_USAGE, &[]),
+    ("scan-build", SCAN_BUILD_USAGE, &[]),
+    ("completions", COMPLETIONS_USAGE, &SHELLS),
+];
+
+/// Alternative names of the commands.
+const COMMAND_ALIASES: [(&str, &str); 2] = [("cmp", "compare"), ("cons", "consolidate")];
+
+/// An option or a command listed in a usage message.
+struct UsageEntry {
+    short: Option<char>,
+    long: Option<String>,
+    takes_value: bool,
+    desc: String,
+}
+
+impl UsageEntry {
+    /// Returns the words which are completed for the entry. The long variant of an option which
+    /// takes a value is completed with the trailing `=`.
+    fn words(&self) -> Vec<String> {
+        let mut words = Vec::new();
+        if let Some(short) = self.short {
+            words.push(format!("-{}", short));
+        }
+        if let Some(long) = &self.long {
+            words.push(if self.takes_value {
+                format!("{}=", long)
+            } else {
+                long.clone()
+            });
+        }
+        words
+    }
+}
+
+/// Parses a usage message and returns the entries of its `Options:` and `Commands:` sections.
+///
+/// Each entry consists of a specification, such as `-j NUM, --jobs=NUM`, and a description, which
+/// can continue on the following indented lines. The generated shell completions are based on the
+/// usage messages, which keeps them in sync with the options documented by `--help`.
+fn parse_usage(usage: &str) -> (Vec<UsageEntry>, Vec<UsageEntry>) {
+    let mut options: Vec<UsageEntry> = Vec::new();
+    let mut commands: Vec<UsageEntry> = Vec::new();
+    let mut section = None;
+
+    for line in usage.lines() {
+        match line {
+            "Options:" | "Commands:" => {
+                section = Some(line);
+                continue;
+            }
+            "" => {
+                section = None;
+                continue;
+            }
+            _ => {}
+        }
+        let entries = match section {
+            Some("Options:") => &mut options,
+            Some("Commands:") => &mut commands,
+            _ => continue,
+        };
+
+        let Some(line) = line.strip_prefix("  ") else {
+            continue;
+        };
+        if line.starts_with(' ') {
+            // A continuation of the previous description.
+            if let Some(entry) = entries.last_mut() {
+                entry.desc.push(' ');
+                entry.desc.push_str(line.trim());
+            }
+            continue;
+        }
+
+        let (spec, desc) = line.split_once("  ").unwrap_or((line, ""));
+        let mut entry = UsageEntry {
+            short: None,
+            long: None,
+            takes_value: false,
+            desc: desc.trim().to_string(),
+        };
+        for variant in spec.split(", ") {
+            let name_len = variant.find([' ', '=', '[']).unwrap_or(variant.len());
+            let (name, value) = variant.split_at(name_len);
+            if !value.is_empty() && !value.starts_with('[') {
+                entry.takes_value = true;
+            }
+            match name.strip_prefix('-') {
+                Some(short) if !short.starts_with('-') => entry.short = short.chars().next(),
+                _ => entry.long = Some(name.to_string()),
+            }
+        }
+        entries.push(entry);
+    }
+
+    (options, commands)
+}
+
+/// Quotes `string` as a single-quoted shell word.
+fn shell_quote(string: &str) -> String {
+    format!("'{}'", string.replace('\'', "'\\''"))
+}
+
+/// Generates a bash completion script.
+fn bash_completions() -> String {
+    let (global_options, commands) = parse_usage(USAGE);
+    let words = |entries: &[UsageEntry]| {
+        entries
+            .iter()
+            .flat_map(UsageEntry::words)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut cases = format!(
+        "        '')\n            opts={}\n            words={}\n            ;;\n",
+        shell_quote(&words(&global_options)),
+        shell_quote(&words(&commands))
+    );
+    for (command, usage, operands) in COMMANDS {
+        let (options, _) = parse_usage(usage);
+        cases.push_str(&format!(
+            "        {})\n            opts={}\n            words={}\n            ;;\n",
+            command,
+            shell_quote(&words(&options)),
+            shell_quote(&operands.join(" "))
+        ));
+    }
+
+    format!(
+        concat!(
+            "# bash completion for ksymtypes\n",
+            "_ksymtypes() {{\n",
+            "    local cur=${{COMP_WORDS[COMP_CWORD]}} cmd= opts= words= i\n",
+            "    for ((i = 1; i < COMP_CWORD; i++)); do\n",
+            "        if [[ ${{COMP_WORDS[i]}} != -* ]]; then\n",
+            "            cmd=${{COMP_WORDS[i]}}\n",
+            "            break\n",
+            "        fi\n",
+            "    done\n",
+            "    case $cmd in\n",
+            "{}",
+            "    esac\n",
+            "    if [[ $cur == -* ]]; then\n",
+            "        COMPREPLY=($(compgen -W \"$opts\" -- \"$cur\"))\n",
+            "        [[ ${{#COMPREPLY[@]}} -eq 1 && ${{COMPREPLY[0]}} == *= ]] && compopt -o nospace\n",
+            "    elif [[ -n $words ]]; then\n",
+            "        COMPREPLY=($(compgen -W \"$words\" -- \"$cur\"))\n",
+            "    else\n",
+            "        COMPREPLY=($(compgen -f -- \"$cur\"))\n",
+            "    fi\n",
+            "}}\n",
+            "complete -F _ksymtypes ksymtypes\n",
+        ),
+        cases
+    )
+}
+
+/// Generates a zsh completion script.
+fn zsh_completions() -> String {
+    let (global_options, commands) = parse_usage(USAGE);
+    let described = |entries: &[UsageEntry]| {
+        entries
+            .iter()
+            .flat_map(|entry| {
+                entry
+                    .words()
+                    .into_iter()
+                    .map(|word| shell_quote(&format!("{}:{}", word, entry.desc)))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut cases = format!(
+        "        '')\n            opts=({})\n            args=({})\n            ;;\n",
+        described(&global_options),
+        described(&commands)
+    );
+    for (command, usage, operands) in COMMANDS {
+        let (options, _) = parse_usage(usage);
+        cases.push_str(&format!(
+            "        {})\n            opts=({})\n            args=({})\n            ;;\n",
+            command,
+            described(&options),
+            operands.join(" ")
+        ));
+    }
+
+    format!(
+        concat!(
+            "#compdef ksymtypes\n",
+            "\n",
+            "_ksymtypes() {{\n",
+            "    local cmd i\n",
+            "    local -a opts args\n",
+            "    for ((i = 2; i < CURRENT; i++)); do\n",
+            "        if [[ $words[i] != -* ]]; then\n",
+            "            cmd=$words[i]\n",
+            "            break\n",
+            "        fi\n",
+            "    done\n",
+            "    case $cmd in\n",
+            "{}",
+            "    esac\n",
+            "    if [[ $PREFIX == -* ]]; then\n",
+            "        _describe -t options option opts\n",
+            "    elif (( $#args )); then\n",
+            "        _describe -t arguments argument args\n",
+            "    else\n",
+            "        _files\n",
+            "    fi\n",
+            "}}\n",
+            "\n",
+            "_ksymtypes \"$@\"\n",
+        ),
+        cases
+    )
+}
+
+/// Generates a fish completion script.
+fn fish_completions() -> String {
+    let complete = |condition: &str, entry: &UsageEntry| {
+        let mut line = format!("complete -c ksymtypes -n {}", shell_quote(condition));
+        if let Some(short) = entry.short {
+            line.push_str(&format!(" -s {}", short));
+        }
+        if let Some(long) = &entry.long {
+            line.push_str(&format!(" -l {}", &long[2..]));
+        }
+        if entry.takes_value {
+            line.push_str(" -r");
+        }
+        line.push_str(&format!(" -d {}\n", shell_quote(&entry.desc)));
+        line
+    };
+
+    let mut script = "# fish completion for ksymtypes\ncomplete -c ksymtypes -f\n".to_string();
+    let (global_options, commands) = parse_usage(USAGE);
+    for option in &global_options {
+        script.push_str(&complete("__fish_use_subcommand", option));
+    }
+    for command in &commands {
+        script.push_str(&format!(
+            "complete -c ksymtypes -n {} -a {} -d {}\n",
+            shell_quote("__fish_use_subcommand"),
+            command.long.as_deref().unwrap_or_default(),
+            shell_quote(&command.desc)
+        ));
+    }
+    for (command, usage, operands) in COMMANDS {
+        let condition = format!("__fish_seen_subcommand_from {}", command);
+        let (options, _) = parse_usage(usage);
+        for option in &options {
+            script.push_str(&complete(&condition, option));
+        }
+        if operands.is_empty() {
+            script.push_str(&format!(
+                "complete -c ksymtypes -n {} -F\n",
+                shell_quote(&condition)
+            ));
+        } else {
+            script.push_str(&format!(
+                "complete -c ksymtypes -n {} -a {}\n",
+                shell_quote(&condition),
+                shell_quote(&operands.join(" "))
+            ));
+        }
+    }
+    script
+}
+
+/// Returns the number of workers used when the number is specified as `auto`, which is the number
+/// of available CPUs.
+fn auto_jobs() -> i32 {
+    thread::available_parallelism().map_or(1, |jobs| i32::try_from(jobs.get()).unwrap_or(i32::MAX))
+}
+
+/// Handles the `-j`/`--jobs` option which specifies the number of workers to perform a given
+/// operation simultaneously.
+fn handle_jobs_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<i32>, ()> {
+    if let Some(value) = handle_value_option(arg, args, Some("-j"), "--jobs")? {
+        if value == "auto" {
+            return Ok(Some(auto_jobs()));
+        }
+        match value.parse::<i32>() {
+            Ok(jobs) => {
+                if jobs < 1 {
+                    eprintln!("Invalid value for '{}': must be positive", arg);
+                    return Err(());
+                }
+                return Ok(Some(jobs));
+            }
+            Err(err) => {
+                eprintln!("Invalid value for '{}': {}", arg, err);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--input-dialect` option which specifies the dialect of the symtypes data.
+fn handle_input_dialect_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<InputDialect>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--input-dialect")? {
+        match InputDialect::from_name(&value) {
+            Some(input_dialect) => return Ok(Some(input_dialect)),
+            None => {
+                eprintln!("Invalid value for '{}': unknown dialect '{}'", arg, value);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--max-errors` option which specifies how many errors found in the input are
+/// collected before the operation is stopped.
+fn handle_max_errors_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<usize>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--max-errors")? {
+        match value.parse::<usize>() {
+            Ok(max_errors) => return Ok(Some(max_errors)),
+            Err(err) => {
+                eprintln!("Invalid value for '{}': {}", arg, err);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--diff-algorithm` option which specifies the algorithm used to compute diffs.
+fn handle_diff_algorithm_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<Algorithm>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--diff-algorithm")? {
+        match Algorithm::from_name(&value) {
+            Some(algorithm) => return Ok(Some(algorithm)),
+            None => {
+                eprintln!("Invalid value for '{}': unknown algorithm '{}'", arg, value);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--diff-max-cost` option which specifies the maximum cost of a diff that is computed
+/// exactly.
+fn handle_diff_max_cost_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<usize>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--diff-max-cost")? {
+        match value.parse::<usize>() {
+            Ok(max_cost) => return Ok(Some(max_cost)),
+            Err(err) => {
+                eprintln!("Invalid value for '{}': {}", arg, err);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--diff-ignore` option which specifies a pattern of lines to ignore in diffs.
+fn handle_diff_ignore_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<Regex>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--diff-ignore")? {
+        match Regex::new(&value) {
+            Ok(pattern) => return Ok(Some(pattern)),
+            Err(err) => {
+                eprintln!("Invalid value for '{}': {}", arg, err);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--diff-max-lines` option which specifies the maximum number of lines shown for
+/// a diff.
+fn handle_diff_max_lines_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<usize>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--diff-max-lines")? {
+        match value.parse::<usize>() {
+            Ok(max_lines) => return Ok(Some(max_lines)),
+            Err(err) => {
+                eprintln!("Invalid value for '{}': {}", arg, err);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--diff-merge-gap` option which specifies the maximum number of unchanged lines
+/// between changes in one hunk.
+fn handle_diff_merge_gap_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<usize>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--diff-merge-gap")? {
+        match value.parse::<usize>() {
+            Ok(merge_gap) => return Ok(Some(merge_gap)),
+            Err(err) => {
+                eprintln!("Invalid value for '{}': {}", arg, err);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--diff-view` option which specifies the format of diffs.
+fn handle_diff_view_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<Format>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--diff-view")? {
+        match Format::from_name(&value) {
+            Some(format) => return Ok(Some(format)),
+            None => {
+                eprintln!("Invalid value for '{}': unknown view '{}'", arg, value);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Handles the `--diff-width` option which specifies the width of the side-by-side diff view.
+fn handle_diff_width_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<usize>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--diff-width")? {
+        match value.parse::<usize>() {
+            Ok(width) => return Ok(Some(width)),
+            Err(err) => {
+                eprintln!("Invalid value for '{}': {}", arg, err);
+                return Err(());
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Expands a path operand which contains wildcards into the matching paths. An operand without
+/// wildcards or which names an existing path is taken literally.
+fn expand_operand(path: &str) -> Result<Vec<PathBuf>, ExitStatus> {
+    if !glob::is_pattern(path) || Path::new(path).exists() {
+        return Ok(vec![PathBuf::from(path)]);
+    }
+    match glob::expand(path) {
+        Ok(paths) if paths.is_empty() => {
+            eprintln!("No files match the pattern '{}'", path);
+            Err(ExitStatus::Error)
+        }
+        Ok(paths) => Ok(paths),
+        Err(err) => {
+            eprintln!("Failed to expand the pattern '{}': {}", path, err);
+            Err(ExitStatus::Error)
+        }
+    }
+}
+
+/// Returns whether the compare source `path` is a Module.symvers file, which is loaded as a corpus
+/// describing only the CRCs of the exports.
+fn is_symvers_operand(path: &str) -> bool {
+    Compression::from_path(path)
+        .strip_extension(path)
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .is_some_and(|file_name| file_name.ends_with(".symvers"))
+}
+
+/// Reads the changed source files listed in `path`, one per line, and returns the symtypes files
+/// affected by the changes.
+///
+/// The changed sources are mapped to objects by the make rules in the file `source_map`, in the
+/// form `OBJECT...: SOURCE...`, as written by `gcc -MD`. A source which is not listed by any rule
+/// is mapped to the object of the same name, for instance, `drivers/foo.c` to `drivers/foo.o`. The
+/// symtypes file of an object has the same name with the extension `.symtypes`.
+fn read_changed_symfiles(path: &str, source_map: Option<&str>) -> Result<Vec<PathBuf>, ExitStatus> {
+    let read = |path: &str, desc: &str| {
+        fs::read_to_string(path).map_err(|err| {
+            eprintln!(
+                "Failed to read {} from '{}': {}",
+                desc,
+                display_path(Path::new(path)),
+                err
+            );
+            ExitStatus::Error
+        })
+    };
+
+    // Collect the objects built from each source.
+    let mut objects: HashMap<String, Vec<String>> = HashMap::new();
+    if let Some(source_map) = source_map {
+        let data = read(source_map, "the source map")?.replace("\\\n", " ");
+        for line in data.lines() {
+            let Some((targets, prereqs)) = line.split_once(':') else {
+                continue;
+            };
+            // Skip comments and variable assignments.
+            if targets.trim_start().starts_with('#') || prereqs.starts_with('=') {
+                continue;
+            }
+            for source in prereqs.split_ascii_whitespace() {
+                objects
+                    .entry(source.to_string())
+                    .or_default()
+                    .extend(targets.split_ascii_whitespace().map(str::to_string));
+            }
+        }
+    }
+
+    let mut symfiles = BTreeSet::new();
+    for source in read(path, "changed sources")?.lines() {
+        let source = source.trim();
+        if source.is_empty() {
+            continue;
+        }
+        match objects.get(source) {
+            Some(objects) => symfiles.extend(
+                objects
+                    .iter()
+                    .map(|object| Path::new(object).with_extension("symtypes")),
+            ),
+            None => {
+                symfiles.insert(Path::new(source).with_extension("symtypes"));
+            }
+        }
+    }
+    debug!(
+        "Restricting the comparison to {} symtypes files",
+        symfiles.len()
+    );
+    Ok(symfiles.into_iter().collect())
+}
+
+/// Handles the `consolidate` command which consolidates symtypes into a single file.
+fn do_consolidate<I: IntoIterator<Item = String>>(
+    timing: Option<OutputFormat>,
+    quiet: bool,
+    config: &Config,
+    args: I,
+) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "jo");
+    let mut output = "-".to_string();
+    let mut num_workers = config.jobs.unwrap_or_else(auto_jobs);
+    let mut validate_refs = true;
+    let mut max_errors = 1;
+    let mut input_dialect = InputDialect::default();
+    let mut warnings_as_errors = false;
+    let mut crcs = false;
+    let mut checker = ArgChecker::new(Some("consolidate"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, Some("-o"), "--output")
+            ) {
+                output = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_jobs_option(&arg, &mut args)) {
+                num_workers = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_max_errors_option(&arg, &mut args)) {
+                max_errors = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_input_dialect_option(&arg, &mut args)) {
+                input_dialect = value;
+                continue;
+            }
+            if arg == "--no-validate" {
+                validate_refs = false;
+                continue;
+            }
+            if arg == "--warnings-as-errors" {
+                warnings_as_errors = true;
+                continue;
+            }
+            if arg == "--crcs" {
+                crcs = true;
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                print_consolidate_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized consolidate option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    let [path] = checker.finish(["consolidate source"])?;
+    let shown_path = display_path(Path::new(&path));
+    let shown_output = display_path(Path::new(&output));
+
+    // Do the consolidation.
+    let timing_report = TimingReport::new(timing);
+    let diagnostics = Arc::new(StderrDiagnostics::new(quiet));
+    let load_options = LoadOptions::new()
+        .num_workers(num_workers)
+        .validate_refs(validate_refs)
+        .max_errors(max_errors)
+        .input_dialect(input_dialect)
+        .diagnostics(diagnostics.clone());
+    let mut syms = SymCorpus::new();
+
+    {
+        let _timing =
+            timing_report.start("load", &format!("Reading symtypes from '{}'", shown_path));
+
+        let paths = expand_operand(&path)?;
+        let load_options = timing_report.load_options(&shown_path, &load_options);
+        if let Err(err) = syms.load_multiple_with(&paths, &load_options) {
+            eprintln!("Failed to read symtypes from '{}': {}", shown_path, err);
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    diagnostics.check(warnings_as_errors)?;
+
+    {
+        let _timing = timing_report.start(
+            "write",
+            &format!("Writing consolidated symtypes to '{}'", shown_output),
+        );
+
+        let consolidate_options = ConsolidateOptions::new()
+            .crcs(crcs)
+            .diagnostics(diagnostics.clone());
+        if let Err(err) = syms.write_consolidated_file_with(&output, &consolidate_options) {
+            eprintln!(
+                "Failed to write consolidated symtypes to '{}': {}",
+                shown_output, err
+            );
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    diagnostics.check(warnings_as_errors)?;
+    Ok(ExitStatus::Success)
+}
+
+/// Handles the `compare` command which shows differences between two symtypes corpuses.
+fn do_compare<I: IntoIterator<Item = String>>(
+    timing: Option<OutputFormat>,
+    quiet: bool,
+    config: &Config,
+    args: I,
+) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "j");
+    let mut num_workers = None;
+    let mut validate_refs = true;
+    let mut max_errors = 1;
+    let mut input_dialect = InputDialect::default();
+    let mut warnings_as_errors = false;
+    let mut do_score = false;
+    let mut rules = None;
+    let mut diff_algorithm = Algorithm::default();
+    let mut diff_max_cost = None;
+    let mut diff_max_lines = None;
+    let mut diff_ignore_patterns = Vec::new();
+    let mut diff_merge_gap = None;
+    let mut diff_format = Format::default();
+    let mut diff_width = None;
+    let mut format = None;
+    let mut diff_headers = false;
+    let mut diff_moves = false;
+    let mut output_dir = None;
+    let mut changed_sources = None;
+    let mut source_map = None;
+    let mut symvers_path = None;
+    let mut cross_arch = false;
+    let mut arch_types_path = None;
+    let mut config_path = None;
+    let mut checker = ArgChecker::new(Some("compare"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(checker, handle_jobs_option(&arg, &mut args)) {
+                num_workers = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_max_errors_option(&arg, &mut args)) {
+                max_errors = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_input_dialect_option(&arg, &mut args)) {
+                input_dialect = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_diff_algorithm_option(&arg, &mut args))
+            {
+                diff_algorithm = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_diff_max_cost_option(&arg, &mut args)) {
+                diff_max_cost = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_diff_ignore_option(&arg, &mut args)) {
+                diff_ignore_patterns.push(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_diff_max_lines_option(&arg, &mut args))
+            {
+                diff_max_lines = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_diff_merge_gap_option(&arg, &mut args))
+            {
+                diff_merge_gap = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_diff_view_option(&arg, &mut args)) {
+                diff_format = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_diff_width_option(&arg, &mut args)) {
+                diff_width = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_format_option(&arg, &mut args)) {
+                format = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_rules_option(&arg, &mut args)) {
+                rules = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--output-dir")
+            ) {
+                output_dir = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--changed-sources")
+            ) {
+                changed_sources = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--config")
+            ) {
+                config_path = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--source-map")
+            ) {
+                source_map = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--symvers")
+            ) {
+                symvers_path = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--arch-types")
+            ) {
+                arch_types_path = Some(value);
+                continue;
+            }
+            if arg == "--cross-arch" {
+                cross_arch = true;
+                continue;
+            }
+            if arg == "--no-validate" {
+                validate_refs = false;
+                continue;
+            }
+            if arg == "--warnings-as-errors" {
+                warnings_as_errors = true;
+                continue;
+            }
+            if arg == "--diff-headers" {
+                diff_headers = true;
+                continue;
+            }
+            if arg == "--diff-moves" {
+                diff_moves = true;
+                continue;
+            }
+            if arg == "--score" {
+                do_score = true;
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                print_compare_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized compare option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    // Settings in the configuration file given by --config take precedence over the defaults.
+    let config = load_command_config(config, config_path.as_deref())?;
+    let num_workers = num_workers.or(config.jobs).unwrap_or_else(auto_jobs);
+    let format = format.or(config.format).unwrap_or_default();
+
+    if format == OutputFormat::Json && do_score {
+        checker.error("The '--score' option cannot be used with '--format=json'");
+    }
+    let rules_supported = !matches!(
+        format,
+        OutputFormat::Json | OutputFormat::Jsonl | OutputFormat::Metrics
+    );
+    if !rules_supported && rules.is_some() {
+        checker.error(&format!(
+            "The '--rules' option cannot be used with '--format={}'",
+            format.name()
+        ));
+    }
+    if output_dir.is_some() && format != OutputFormat::Text {
+        checker.error(&format!(
+            "The '--output-dir' option cannot be used with '--format={}'",
+            format.name()
+        ));
+    }
+    if format.is_compare_only() && do_score {
+        checker.error(&format!(
+            "The '--score' option cannot be used with '--format={}'",
+            format.name()
+        ));
+    }
+    if source_map.is_some() && changed_sources.is_none() {
+        checker.error("The '--source-map' option can be used only with '--changed-sources'");
+    }
+    if arch_types_path.is_some() && !cross_arch {
+        checker.error("The '--arch-types' option can be used only with '--cross-arch'");
+    }
+
+    let [path, path2] = checker.finish(["first compare source", "second compare source"])?;
+    if path == "-" && path2 == "-" {
+        eprintln!("The standard input can be used only for one compare source");
+        return Err(ExitStatus::Usage);
+    }
+
+    // Use the configured rules, unless the format cannot show the severities.
+    if let Some(rules_path) = config.rules.as_ref().filter(|_| rules_supported) {
+        if rules.is_none() {
+            rules = Some(load_rules(rules_path).map_err(|()| ExitStatus::Error)?);
+        }
+    }
+
+    // Do the comparison.
+    debug!("Compare '{}' and '{}'", path, path2);
+    let shown_path = display_path(Path::new(&path));
+    let shown_path2 = display_path(Path::new(&path2));
+
+    let timing_report = TimingReport::new(timing);
+    let diagnostics = Arc::new(StderrDiagnostics::new(quiet));
+    let mut load_options = LoadOptions::new()
+        .num_workers(num_workers)
+        .validate_refs(validate_refs)
+        .max_errors(max_errors)
+        .input_dialect(input_dialect)
+        .diagnostics(diagnostics.clone());
+    if let Some(changed_sources) = &changed_sources {
+        let symfiles = read_changed_symfiles(changed_sources, source_map.as_deref())?;
+        load_options = load_options.only_files(symfiles);
+    }
+    let whitelist = match &config.whitelist {
+        Some(whitelist_path) => {
+            let whitelist = load_whitelist(whitelist_path).map_err(|()| ExitStatus::Error)?;
+            load_options = load_options.only_exports(whitelist.iter().cloned());
+            Some(whitelist)
+        }
+        None => None,
+    };
+
+    // Read the names of the architecture-specific types, one per line, skipping comments.
+    let mut tolerated_types = Vec::new();
+    if cross_arch {
+        tolerated_types.extend(POINTER_SIZE_TYPES.iter().map(|name| name.to_string()));
+    }
+    if let Some(arch_types_path) = &arch_types_path {
+        match fs::read_to_string(arch_types_path) {
+            Ok(data) => tolerated_types.extend(
+                data.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            ),
+            Err(err) => {
+                eprintln!(
+                    "Failed to read architecture-specific types from '{}': {}",
+                    display_path(Path::new(arch_types_path)),
+                    err
+                );
+                return Err(ExitStatus::Error);
+            }
+        }
+    }
+
+    let mut syms = {
+        let _timing =
+            timing_report.start("load", &format!("Reading symtypes from '{}'", shown_path));
+
+        if is_symvers_operand(&path) {
+            let mut symvers = SymversCorpus::new();
+            if let Err(err) = symvers.load(&path) {
+                eprintln!("Failed to read symvers from '{}': {}", shown_path, err);
+                return Err(ExitStatus::Error);
+            }
+            if let Some(whitelist) = &whitelist {
+                symvers.retain_whitelist(whitelist);
+            }
+            SymCorpus::from_symvers(&symvers)
+        } else {
+            let mut syms = SymCorpus::new();
+            let paths = expand_operand(&path)?;
+            let load_options = timing_report.load_options(&shown_path, &load_options);
+            if let Err(err) = syms.load_multiple_with(&paths, &load_options) {
+                eprintln!("Failed to read symtypes from '{}': {}", shown_path, err);
+                return Err(ExitStatus::Error);
+            }
+            syms
+        }
+    };
+
+    let mut syms2 = {
+        let _timing =
+            timing_report.start("load", &format!("Reading symtypes from '{}'", shown_path2));
+
+        if is_symvers_operand(&path2) {
+            let mut symvers = SymversCorpus::new();
+            if let Err(err) = symvers.load(&path2) {
+                eprintln!("Failed to read symvers from '{}': {}", shown_path2, err);
+                return Err(ExitStatus::Error);
+            }
+            if let Some(whitelist) = &whitelist {
+                symvers.retain_whitelist(whitelist);
+            }
+            SymCorpus::from_symvers(&symvers)
+        } else {
+            let mut syms2 = SymCorpus::new();
+            let paths = expand_operand(&path2)?;
+            let load_options = timing_report.load_options(&shown_path2, &load_options);
+            if let Err(err) = syms2.load_multiple_with(&paths, &load_options) {
+                eprintln!("Failed to read symtypes from '{}': {}", shown_path2, err);
+                return Err(ExitStatus::Error);
+            }
+            syms2
+        }
+    };
+
+    if let Some(symvers_path) = &symvers_path {
+        let mut symvers = SymversCorpus::new();
+        if let Err(err) = symvers.load(symvers_path) {
+            eprintln!(
+                "Failed to read symvers from '{}': {}",
+                display_path(Path::new(symvers_path)),
+                err
+            );
+            return Err(ExitStatus::Error);
+        }
+        // A corpus created from symvers data already knows its modules and export types.
+        for syms in [&mut syms, &mut syms2] {
+            if !syms.is_crc_only() {
+                syms.add_symvers(&symvers);
+            }
+        }
+    }
+
+    diagnostics.check(warnings_as_errors)?;
+
+    let mut result = {
+        let _timing = timing_report.start("compare", "Comparison");
+
+        let compare_options = CompareOptions::new()
+            .num_workers(num_workers)
+            .tolerate_types(tolerated_types)
+            .diagnostics(diagnostics.clone());
+        syms.compare_with_options(&syms2, &compare_options)
+    };
+    config.suppress_changes(&mut result);
+
+    diagnostics.check(warnings_as_errors)?;
+
+    let mut diff_options = DiffOptions::new()
+        .algorithm(diff_algorithm)
+        .format(diff_format)
+        .detect_moves(diff_moves);
+    if let Some(max_cost) = diff_max_cost {
+        diff_options = diff_options.max_cost(max_cost);
+    }
+    if let Some(max_lines) = diff_max_lines {
+        diff_options = diff_options.max_lines(max_lines);
+    }
+    if let Some(merge_gap) = diff_merge_gap {
+        diff_options = diff_options.merge_gap(merge_gap);
+    }
+    if let Some(width) = diff_width {
+        diff_options = diff_options.width(width);
+    }
+    for pattern in diff_ignore_patterns {
+        diff_options = diff_options.ignore_pattern(pattern);
+    }
+    if diff_headers {
+        diff_options = diff_options.labels(shown_path.as_str(), shown_path2.as_str());
+    }
+    if format == OutputFormat::Text && output_dir.is_none() {
+        diff_options = diff_options.color(config.color.is_enabled());
+    }
+    let verdicts = rules.map(|rules| rules.check_changes(result.export_changes()));
+    // Without rules, all changes are rated as breaking the kABI.
+    let rated_changes = || {
+        verdicts
+            .clone()
+            .unwrap_or_else(|| Rules::new().check_changes(result.export_changes()))
+    };
+    let _timing = timing_report.start("write", "Writing the report");
+    let write_result = match format {
+        OutputFormat::Text => match &output_dir {
+            Some(output_dir) => result.write_split_report_with(output_dir, &diff_options),
+            None => result.write_report_with(&diff_options, io::stdout()),
+        },
+        OutputFormat::Json => result.write_json_report_with(&diff_options, io::stdout()),
+        OutputFormat::Jsonl => result.write_jsonl_report_with(&diff_options, io::stdout()),
+        OutputFormat::Mail => result.write_mail_report_with(&diff_options, io::stdout()),
+        OutputFormat::Markdown => result.write_markdown_report_with(&diff_options, io::stdout()),
+        OutputFormat::Html => result.write_html_report_with(&diff_options, io::stdout()),
+        OutputFormat::Obs => rated_changes().write_obs_report(io::stdout()),
+        OutputFormat::Annotations => {
+            rated_changes().write_annotations(annotation_style(), io::stdout())
+        }
+        OutputFormat::Badness => {
+            rated_changes().write_badness_report(&ScoreOptions::new(), io::stdout())
+        }
+        OutputFormat::Metrics => Metrics::new(
+            syms2.num_exports(),
+            result.export_changes(),
+            &ScoreOptions::new(),
+        )
+        .write_report(io::stdout()),
+    };
+    if let Err(err) = write_result {
+        eprintln!(
+            "Failed to compare symtypes from '{}' and '{}': {}",
+            shown_path, shown_path2, err
+        );
+        return Err(ExitStatus::Error);
+    }
+
+    // Separate the severities and the score from the report of the changes, unless the report is
+    // written to the output directory.
+    let mut separate = output_dir.is_none() && !result.is_empty();
+    if let Some(verdicts) = verdicts.as_ref().filter(|_| !format.is_rated()) {
+        if separate && !verdicts.verdicts().is_empty() {
+            println!();
+        }
+        separate |= !verdicts.verdicts().is_empty();
+        if let Err(err) = verdicts.write_report(io::stdout()) {
+            eprintln!(
+                "Failed to rate changes between '{}' and '{}': {}",
+                shown_path, shown_path2, err
+            );
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    if do_score {
+        if separate {
+            println!();
+        }
+        let score = result.score(&ScoreOptions::new());
+        if let Err(err) = score.write_report(io::stdout()) {
+            eprintln!(
+                "Failed to score changes between '{}' and '{}': {}",
+                shown_path, shown_path2, err
+            );
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    // With a threshold, the changes are considered differences if their badness exceeds it. With
+    // rules, only changes rated FAIL are considered differences. The OBS and annotations outputs
+    // consider only changes which break the kABI.
+    let has_differences = if let Some(max_badness) = config.max_badness {
+        rated_changes().badness(&ScoreOptions::new()) > max_badness
+    } else {
+        match &verdicts {
+            Some(verdicts) => verdicts.worst() == Some(Severity::Fail),
+            None if format.is_rated() => result
+                .export_changes()
+                .iter()
+                .any(|(_, _, kind)| *kind != ChangeKind::Added),
+            None => !result.is_empty(),
+        }
+    };
+    if has_differences {
+        Ok(ExitStatus::Differences)
+    } else {
+        Ok(ExitStatus::Success)
+    }
+}
+
+/// Handles the `report` command which renders a comparison result saved in the JSON format.
+fn do_report<I: IntoIterator<Item = String>>(
+    config: &Config,
+    args: I,
+) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "");
+    let mut format = config.format.unwrap_or_default();
+    let mut checker = ArgChecker::new(Some("report"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(checker, handle_format_option(&arg, &mut args)) {
+                format = value;
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                print_report_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized report option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    // The metrics need the number of all exports, which is not saved in the result.
+    if format == OutputFormat::Metrics {
+        checker.error("The 'metrics' format cannot be used with the report command");
+    }
+
+    let [path] = checker.finish(["report source"])?;
+
+    // Read the saved result.
+    debug!("Render the comparison result from '{}'", path);
+    let shown_path = display_path(Path::new(&path));
+
+    let result = load_report(&path, &shown_path)?;
+
+    let diff_options =
+        DiffOptions::new().color(format == OutputFormat::Text && config.color.is_enabled());
+    // All changes are rated as breaking the kABI, as without rules in the compare command.
+    let rated_changes = || Rules::new().check_changes(result.export_changes());
+    let write_result = match format {
+        OutputFormat::Text => result.write_report_with(&diff_options, io::stdout()),
+        OutputFormat::Json => result.write_json_report_with(&diff_options, io::stdout()),
+        OutputFormat::Jsonl => result.write_jsonl_report_with(&diff_options, io::stdout()),
+        OutputFormat::Mail => result.write_mail_report_with(&diff_options, io::stdout()),
+        OutputFormat::Markdown => result.write_markdown_report_with(&diff_options, io::stdout()),
+        OutputFormat::Html => result.write_html_report_with(&diff_options, io::stdout()),
+        OutputFormat::Obs => rated_changes().write_obs_report(io::stdout()),
+        OutputFormat::Annotations => {
+            rated_changes().write_annotations(annotation_style(), io::stdout())
+        }
+        OutputFormat::Badness => {
+            rated_changes().write_badness_report(&ScoreOptions::new(), io::stdout())
+        }
+        // The metrics format is rejected above.
+        OutputFormat::Metrics => unreachable!(),
+    };
+    if let Err(err) = write_result {
+        eprintln!(
+            "Failed to render the comparison result from '{}': {}",
+            shown_path, err
+        );
+        return Err(ExitStatus::Error);
+    }
+
+    // The OBS and annotations outputs consider only changes which break the kABI.
+    let has_differences = if format.is_rated() {
+        result
+            .export_changes()
+            .iter()
+            .any(|(_, _, kind)| *kind != ChangeKind::Added)
+    } else {
+        !result.is_empty()
+    };
+    if has_differences {
+        Ok(ExitStatus::Differences)
+    } else {
+        Ok(ExitStatus::Success)
+    }
+}
+
+/// Loads a comparison result saved in the JSON format from the file `path`.
+#[cfg(feature = "serde")]
+fn load_report(path: &str, shown_path: &str) -> Result<CompareResult, ExitStatus> {
+    CompareResult::load_json_report(path).map_err(|err| {
+        eprintln!(
+            "Failed to read the comparison result from '{}': {}",
+            shown_path, err
+        );
+        ExitStatus::Error
+    })
+}
+
+/// Reports that a comparison result cannot be loaded, because reading JSON requires the `serde`
+/// feature.
+#[cfg(not(feature = "serde"))]
+fn load_report(_path: &str, shown_path: &str) -> Result<CompareResult, ExitStatus> {
+    eprintln!(
+        "Failed to read the comparison result from '{}': reading JSON requires the 'serde' feature",
+        shown_path
+    );
+    Err(ExitStatus::Error)
+}
+
+/// Handles the `symvers` command which generates Module.symvers data from symtypes.
+fn do_symvers<I: IntoIterator<Item = String>>(
+    timing: Option<OutputFormat>,
+    quiet: bool,
+    config: &Config,
+    args: I,
+) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "jo");
+    let mut output = "-".to_string();
+    let mut num_workers = config.jobs.unwrap_or_else(auto_jobs);
+    let mut symvers_path = None;
+    let mut checker = ArgChecker::new(Some("symvers"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, Some("-o"), "--output")
+            ) {
+                output = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_jobs_option(&arg, &mut args)) {
+                num_workers = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--symvers")
+            ) {
+                symvers_path = Some(value);
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                print_symvers_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized symvers option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    let [path] = checker.finish(["symvers source"])?;
+    let shown_path = display_path(Path::new(&path));
+    let shown_output = display_path(Path::new(&output));
+
+    // Load the corpus and generate the symvers data.
+    let timing_report = TimingReport::new(timing);
+    let diagnostics = Arc::new(StderrDiagnostics::new(quiet));
+    let load_options = LoadOptions::new()
+        .num_workers(num_workers)
+        .diagnostics(diagnostics.clone());
+    let mut syms = SymCorpus::new();
+
+    {
+        let _timing =
+            timing_report.start("load", &format!("Reading symtypes from '{}'", shown_path));
+
+        let paths = expand_operand(&path)?;
+        let load_options = timing_report.load_options(&shown_path, &load_options);
+        if let Err(err) = syms.load_multiple_with(&paths, &load_options) {
+            eprintln!("Failed to read symtypes from '{}': {}", shown_path, err);
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    if let Some(symvers_path) = symvers_path {
+        if let Err(err) = syms.load_symvers(&symvers_path) {
+            eprintln!(
+                "Failed to read symvers from '{}': {}",
+                display_path(Path::new(&symvers_path)),
+                err
+            );
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    {
+        let _timing =
+            timing_report.start("write", &format!("Writing symvers to '{}'", shown_output));
+
+        let symvers = SymversCorpus::from_symtypes(&syms);
+        if let Err(err) = symvers.write_file(&output) {
+            eprintln!("Failed to write symvers to '{}': {}", shown_output, err);
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/// Handles the `symref` command which generates genksyms reference files from symtypes.
+fn do_symref<I: IntoIterator<Item = String>>(
+    timing: Option<OutputFormat>,
+    quiet: bool,
+    config: &Config,
+    args: I,
+) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "jo");
+    let mut output = ".".to_string();
+    let mut num_workers = config.jobs.unwrap_or_else(auto_jobs);
+    let mut overrides_path = None;
+    let mut checker = ArgChecker::new(Some("symref"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, Some("-o"), "--output")
+            ) {
+                output = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_jobs_option(&arg, &mut args)) {
+                num_workers = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--overrides")
+            ) {
+                overrides_path = Some(value);
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                print_symref_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized symref option '{}'", arg));
+                continue;
+            }
         }
+
+        checker.operand(arg);
     }
-}
 
-impl Drop for Timing {
-    fn drop(&mut self) {
-        match self {
-            Timing::Active { desc, start } => {
-                eprintln!("{}: {:.3?}", desc, start.elapsed());
+    let [path] = checker.finish(["symref source"])?;
+    let shown_path = display_path(Path::new(&path));
+    let shown_output = display_path(Path::new(&output));
+
+    // Read the names of the overridden symbols, one per line, skipping comments.
+    let mut overrides = Vec::new();
+    if let Some(overrides_path) = overrides_path {
+        match fs::read_to_string(&overrides_path) {
+            Ok(data) => overrides.extend(
+                data.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            ),
+            Err(err) => {
+                eprintln!(
+                    "Failed to read overrides from '{}': {}",
+                    display_path(Path::new(&overrides_path)),
+                    err
+                );
+                return Err(ExitStatus::Error);
             }
-            Timing::Inactive => {}
         }
     }
-}
 
-/// Prints the global usage message on the standard output.
-fn print_usage() {
-    print!(concat!(
-        "Usage: ksymtypes [OPTION...] COMMAND\n",
-        "\n",
-        "Options:\n",
-        "  -d, --debug                   enable debug output\n",
-        "  -h, --help                    display this help and exit\n",
-        "  --version                     output version information and exit\n",
-        "\n",
-        "Commands:\n",
-        "  consolidate                   consolidate symtypes into a single file\n",
-        "  compare                       show differences between two symtypes corpuses\n",
-    ));
-}
-
-/// Prints the version information on the standard output.
-fn print_version() {
-    println!("ksymtypes {}", env!("CARGO_PKG_VERSION"));
-}
+    // Load the corpus and write the reference files.
+    let timing_report = TimingReport::new(timing);
+    let diagnostics = Arc::new(StderrDiagnostics::new(quiet));
+    let load_options = LoadOptions::new()
+        .num_workers(num_workers)
+        .diagnostics(diagnostics.clone());
+    let mut syms = SymCorpus::new();
 
-/// Prints the usage message for the `consolidate` command on the standard output.
-fn print_consolidate_usage() {
-    print!(concat!(
-        "Usage: ksymtypes consolidate [OPTION...] PATH\n",
-        "Consolidate symtypes into a single file.\n",
-        "\n",
-        "Options:\n",
-        "  -h, --help                    display this help and exit\n",
-        "  -j NUM, --jobs=NUM            use NUM workers to perform the operation\n",
-        "  -o FILE, --output=FILE        write the result in FILE, instead of stdout\n",
-    ));
-}
+    {
+        let _timing =
+            timing_report.start("load", &format!("Reading symtypes from '{}'", shown_path));
 
-/// Prints the usage message for the `compare` command on the standard output.
-fn print_compare_usage() {
-    print!(concat!(
-        "Usage: ksymtypes compare [OPTION...] PATH PATH2\n",
-        "Show differences between two symtypes corpuses.\n",
-        "\n",
-        "Options:\n",
-        "  -h, --help                    display this help and exit\n",
-        "  -j NUM, --jobs=NUM            use NUM workers to perform the operation\n",
-    ));
+        let paths = expand_operand(&path)?;
+        let load_options = timing_report.load_options(&shown_path, &load_options);
+        if let Err(err) = syms.load_multiple_with(&paths, &load_options) {
+            eprintln!("Failed to read symtypes from '{}': {}", shown_path, err);
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    {
+        let _timing = timing_report.start(
+            "write",
+            &format!("Writing reference files to '{}'", shown_output),
+        );
+
+        if let Err(err) = syms.write_symref_files(&output, &overrides) {
+            eprintln!(
+                "Failed to write reference files to '{}': {}",
+                shown_output, err
+            );
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    Ok(ExitStatus::Success)
 }
 
-/// Handles an option with a mandatory value.
-///
-/// When the `arg` matches the `short` or `long` variant, the function returns [`Ok(Some(String))`]
-/// with the option value. Otherwise, [`Ok(None)`] is returned when the `arg` doesn't match, or
-/// [`Err`] in case of an error.
-fn handle_value_option<I: Iterator<Item = String>>(
-    arg: &str,
-    args: &mut I,
-    short: &str,
-    long: &str,
-) -> Result<Option<String>, ()> {
-    // Handle '-<short> <value>' and '--<long> <value>'.
-    if arg == short || arg == long {
-        match args.next() {
-            Some(value) => return Ok(Some(value.to_string())),
-            None => {
-                eprintln!("Missing argument for '{}'", long);
-                return Err(());
+/// Handles the `explain` command which links changed CRCs in two symvers files to the responsible
+/// differences between two symtypes corpuses.
+fn do_explain<I: IntoIterator<Item = String>>(
+    timing: Option<OutputFormat>,
+    quiet: bool,
+    config: &Config,
+    args: I,
+) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "j");
+    let mut num_workers = config.jobs.unwrap_or_else(auto_jobs);
+    let mut format = config.format.unwrap_or_default();
+    let mut diff_headers = false;
+    let mut checker = ArgChecker::new(Some("explain"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(checker, handle_jobs_option(&arg, &mut args)) {
+                num_workers = value;
+                continue;
             }
-        };
+            if let Some(value) = check_arg!(checker, handle_format_option(&arg, &mut args)) {
+                format = value;
+                continue;
+            }
+            if arg == "--diff-headers" {
+                diff_headers = true;
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                print_explain_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized explain option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    if format.is_compare_only() {
+        checker.error(&format!(
+            "The '{}' format can be used only with the compare command",
+            format.name()
+        ));
     }
 
-    // Handle '-<short><value>'.
-    if let Some(value) = arg.strip_prefix(short) {
-        return Ok(Some(value.to_string()));
+    let operands = checker.finish([
+        "first symvers source",
+        "second symvers source",
+        "first symtypes source",
+        "second symtypes source",
+    ])?;
+    if operands.iter().filter(|operand| *operand == "-").count() > 1 {
+        eprintln!("The standard input can be used only for one explain source");
+        return Err(ExitStatus::Usage);
     }
+    let [symvers_path, symvers_path2, path, path2] = operands;
 
-    // Handle '--<long>=<value>'.
-    if let Some(rem) = arg.strip_prefix(long) {
-        if let Some(value) = rem.strip_prefix('=') {
-            return Ok(Some(value.to_string()));
+    // Compare the symvers files.
+    debug!(
+        "Explain CRC changes between '{}' and '{}'",
+        symvers_path, symvers_path2
+    );
+
+    let mut symvers_results = Vec::new();
+    for symvers_path in [&symvers_path, &symvers_path2] {
+        let mut symvers = SymversCorpus::new();
+        if let Err(err) = symvers.load(symvers_path) {
+            eprintln!(
+                "Failed to read symvers from '{}': {}",
+                display_path(Path::new(symvers_path)),
+                err
+            );
+            return Err(ExitStatus::Error);
         }
+        symvers_results.push(symvers);
     }
+    let symvers_result = symvers_results[0].compare(&symvers_results[1]);
 
-    Ok(None)
-}
+    // Compare the symtypes corpuses.
+    let shown_path = display_path(Path::new(&path));
+    let shown_path2 = display_path(Path::new(&path2));
 
-/// Handles the `-j`/`--jobs` option which specifies the number of workers to perform a given
-/// operation simultaneously.
-fn handle_jobs_option<I: Iterator<Item = String>>(
-    arg: &str,
-    args: &mut I,
-) -> Result<Option<i32>, ()> {
-    if let Some(value) = handle_value_option(arg, args, "-j", "--jobs")? {
-        match value.parse::<i32>() {
-            Ok(jobs) => {
-                if jobs < 1 {
-                    eprintln!("Invalid value for '{}': must be positive", arg);
-                    return Err(());
-                }
-                return Ok(Some(jobs));
-            }
-            Err(err) => {
-                eprintln!("Invalid value for '{}': {}", arg, err);
-                return Err(());
-            }
-        };
+    let timing_report = TimingReport::new(timing);
+    let diagnostics = Arc::new(StderrDiagnostics::new(quiet));
+    let load_options = LoadOptions::new()
+        .num_workers(num_workers)
+        .diagnostics(diagnostics.clone());
+
+    let mut corpuses = Vec::new();
+    for (path, shown_path) in [(&path, &shown_path), (&path2, &shown_path2)] {
+        let _timing =
+            timing_report.start("load", &format!("Reading symtypes from '{}'", shown_path));
+
+        let mut syms = SymCorpus::new();
+        let paths = expand_operand(path)?;
+        let load_options = timing_report.load_options(shown_path, &load_options);
+        if let Err(err) = syms.load_multiple_with(&paths, &load_options) {
+            eprintln!("Failed to read symtypes from '{}': {}", shown_path, err);
+            return Err(ExitStatus::Error);
+        }
+        corpuses.push(syms);
     }
 
-    Ok(None)
+    diagnostics.check(false)?;
+
+    let explanations = {
+        let _timing = timing_report.start("compare", "Comparison");
+
+        let compare_options = CompareOptions::new()
+            .num_workers(num_workers)
+            .diagnostics(diagnostics.clone());
+        let result = corpuses[0].compare_with_options(&corpuses[1], &compare_options);
+        symvers_result.explain_crc_changes(&result)
+    };
+
+    let mut diff_options =
+        DiffOptions::new().color(format == OutputFormat::Text && config.color.is_enabled());
+    if diff_headers {
+        diff_options = diff_options.labels(shown_path.as_str(), shown_path2.as_str());
+    }
+    let _timing = timing_report.start("write", "Writing the report");
+    let write_result = match format {
+        OutputFormat::Text => explanations.write_report_with(&diff_options, io::stdout()),
+        OutputFormat::Json => explanations.write_json_report_with(&diff_options, io::stdout()),
+        // Formats supported only by the compare command are rejected above.
+        _ => unreachable!(),
+    };
+    if let Err(err) = write_result {
+        eprintln!(
+            "Failed to explain CRC changes by symtypes from '{}' and '{}': {}",
+            shown_path, shown_path2, err
+        );
+        return Err(ExitStatus::Error);
+    }
+
+    if explanations.is_empty() {
+        Ok(ExitStatus::Success)
+    } else {
+        Ok(ExitStatus::Differences)
+    }
 }
 
-/// Handles the `consolidate` command which consolidates symtypes into a single file.
-fn do_consolidate<I: IntoIterator<Item = String>>(do_timing: bool, args: I) -> Result<(), ()> {
+/// Handles the `verify-btf` command which checks a symtypes corpus against BTF of the kernel.
+fn do_verify_btf<I: IntoIterator<Item = String>>(
+    timing: Option<OutputFormat>,
+    quiet: bool,
+    config: &Config,
+    args: I,
+) -> Result<ExitStatus, ExitStatus> {
     // Parse specific command options.
-    let mut args = args.into_iter();
-    let mut output = "-".to_string();
-    let mut num_workers = 1;
+    let mut args = ShortOptions::new(args.into_iter(), "h", "j");
+    let mut num_workers = config.jobs.unwrap_or_else(auto_jobs);
+    let mut format = config.format.unwrap_or_default();
+    let mut checker = ArgChecker::new(Some("verify-btf"));
     let mut past_dash_dash = false;
-    let mut maybe_path = None;
 
     while let Some(arg) = args.next() {
         if !past_dash_dash {
-            if let Some(value) = handle_value_option(&arg, &mut args, "-o", "--output")? {
-                output = value;
+            if let Some(value) = check_arg!(checker, handle_jobs_option(&arg, &mut args)) {
+                num_workers = value;
                 continue;
             }
-            if let Some(value) = handle_jobs_option(&arg, &mut args)? {
-                num_workers = value;
+            if let Some(value) = check_arg!(checker, handle_format_option(&arg, &mut args)) {
+                format = value;
                 continue;
             }
             if arg == "-h" || arg == "--help" {
-                print_consolidate_usage();
-                return Ok(());
+                print_verify_btf_usage();
+                return Ok(ExitStatus::Success);
             }
             if arg == "--" {
                 past_dash_dash = true;
                 continue;
             }
-            if arg.starts_with('-') || arg.starts_with("--") {
-                eprintln!("Unrecognized consolidate option '{}'", arg);
-                return Err(());
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized verify-btf option '{}'", arg));
+                continue;
             }
         }
 
-        if maybe_path.is_none() {
-            maybe_path = Some(arg);
-            continue;
-        }
-        eprintln!("Excess consolidate argument '{}' specified", arg);
-        return Err(());
+        checker.operand(arg);
+    }
+
+    if format.is_compare_only() {
+        checker.error(&format!(
+            "The '{}' format can be used only with the compare command",
+            format.name()
+        ));
     }
 
-    let path = maybe_path.ok_or_else(|| {
-        eprintln!("The consolidate source is missing");
-    })?;
+    let [path, btf_path] = checker.finish(["symtypes source", "BTF source"])?;
+    if path == "-" && btf_path == "-" {
+        eprintln!("The standard input can be used only for one verify-btf source");
+        return Err(ExitStatus::Usage);
+    }
+    let shown_path = display_path(Path::new(&path));
+    let shown_btf_path = display_path(Path::new(&btf_path));
 
-    // Do the consolidation.
+    // Load the corpus and the BTF data.
+    let timing_report = TimingReport::new(timing);
+    let diagnostics = Arc::new(StderrDiagnostics::new(quiet));
+    let load_options = LoadOptions::new()
+        .num_workers(num_workers)
+        .diagnostics(diagnostics.clone());
     let mut syms = SymCorpus::new();
 
     {
-        let _timing = Timing::new(do_timing, &format!("Reading symtypes from '{}'", path));
+        let _timing =
+            timing_report.start("load", &format!("Reading symtypes from '{}'", shown_path));
 
-        if let Err(err) = syms.load(&path, num_workers) {
-            eprintln!("Failed to read symtypes from '{}': {}", path, err);
-            return Err(());
+        let paths = expand_operand(&path)?;
+        let load_options = timing_report.load_options(&shown_path, &load_options);
+        if let Err(err) = syms.load_multiple_with(&paths, &load_options) {
+            eprintln!("Failed to read symtypes from '{}': {}", shown_path, err);
+            return Err(ExitStatus::Error);
         }
     }
 
+    diagnostics.check(false)?;
+
+    let mut btf = Btf::new();
     {
-        let _timing = Timing::new(
-            do_timing,
-            &format!("Writing consolidated symtypes to '{}'", output),
-        );
+        let _timing =
+            timing_report.start("load", &format!("Reading BTF from '{}'", shown_btf_path));
 
-        if let Err(err) = syms.write_consolidated(&output) {
-            eprintln!(
-                "Failed to write consolidated symtypes to '{}': {}",
-                output, err
-            );
-            return Err(());
+        if let Err(err) = btf.load(&btf_path) {
+            eprintln!("Failed to read BTF from '{}': {}", shown_btf_path, err);
+            return Err(ExitStatus::Error);
         }
     }
 
-    Ok(())
+    // Check the corpus and write the report.
+    let verification = {
+        let _timing = timing_report.start("verify", "Verification");
+        btf.verify(&syms)
+    };
+
+    let _timing = timing_report.start("write", "Writing the report");
+    let write_result = match format {
+        OutputFormat::Text => verification.write_report(io::stdout()),
+        OutputFormat::Json => verification.write_json_report(io::stdout()),
+        // Formats supported only by the compare command are rejected above.
+        _ => unreachable!(),
+    };
+    if let Err(err) = write_result {
+        eprintln!(
+            "Failed to verify symtypes from '{}' against BTF from '{}': {}",
+            shown_path, shown_btf_path, err
+        );
+        return Err(ExitStatus::Error);
+    }
+
+    if verification.is_empty() {
+        Ok(ExitStatus::Success)
+    } else {
+        Ok(ExitStatus::Differences)
+    }
 }
 
-/// Handles the `compare` command which shows differences between two symtypes corpuses.
-fn do_compare<I: IntoIterator<Item = String>>(do_timing: bool, args: I) -> Result<(), ()> {
+/// Handles the `scan-build` command which checks symtypes of a kernel build against a reference
+/// corpus.
+fn do_scan_build<I: IntoIterator<Item = String>>(
+    timing: Option<OutputFormat>,
+    quiet: bool,
+    config: &Config,
+    args: I,
+) -> Result<ExitStatus, ExitStatus> {
     // Parse specific command options.
-    let mut args = args.into_iter();
-    let mut num_workers = 1;
+    let mut args = ShortOptions::new(args.into_iter(), "h", "j");
+    let mut num_workers = None;
+    let mut max_errors = 1;
+    let mut input_dialect = InputDialect::default();
+    let mut warnings_as_errors = false;
+    let mut obj_dir = None;
+    let mut reference = None;
+    let mut rules = None;
+    let mut config_path = None;
+    let mut checker = ArgChecker::new(Some("scan-build"));
     let mut past_dash_dash = false;
-    let mut maybe_path = None;
-    let mut maybe_path2 = None;
 
     while let Some(arg) = args.next() {
         if !past_dash_dash {
-            if let Some(value) = handle_jobs_option(&arg, &mut args)? {
-                num_workers = value;
+            if let Some(value) = check_arg!(checker, handle_jobs_option(&arg, &mut args)) {
+                num_workers = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_max_errors_option(&arg, &mut args)) {
+                max_errors = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_input_dialect_option(&arg, &mut args)) {
+                input_dialect = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--config")
+            ) {
+                config_path = Some(value);
+                continue;
+            }
+            if let Some(value) =
+                check_arg!(checker, handle_value_option(&arg, &mut args, None, "--obj"))
+            {
+                obj_dir = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--reference")
+            ) {
+                reference = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_rules_option(&arg, &mut args)) {
+                rules = Some(value);
+                continue;
+            }
+            if arg == "--warnings-as-errors" {
+                warnings_as_errors = true;
                 continue;
             }
             if arg == "-h" || arg == "--help" {
-                print_compare_usage();
-                return Ok(());
+                print_scan_build_usage();
+                return Ok(ExitStatus::Success);
             }
             if arg == "--" {
                 past_dash_dash = true;
                 continue;
             }
-            if arg.starts_with('-') || arg.starts_with("--") {
-                eprintln!("Unrecognized compare option '{}'", arg);
-                return Err(());
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized scan-build option '{}'", arg));
+                continue;
             }
         }
 
-        if maybe_path.is_none() {
-            maybe_path = Some(arg);
-            continue;
-        }
-        if maybe_path2.is_none() {
-            maybe_path2 = Some(arg);
-            continue;
-        }
-        eprintln!("Excess compare argument '{}' specified", arg);
-        return Err(());
+        checker.operand(arg);
     }
 
-    let path = maybe_path.ok_or_else(|| {
-        eprintln!("The first compare source is missing");
-    })?;
-    let path2 = maybe_path2.ok_or_else(|| {
-        eprintln!("The second compare source is missing");
-    })?;
+    if reference.is_none() {
+        checker.error("The '--reference' option is missing");
+    }
 
-    // Do the comparison.
-    debug!("Compare '{}' and '{}'", path, path2);
+    checker.finish([])?;
+    let reference = reference.unwrap();
 
-    let syms = {
-        let _timing = Timing::new(do_timing, &format!("Reading symtypes from '{}'", path));
+    // Settings in the configuration file given by --config take precedence over the defaults.
+    let config = load_command_config(config, config_path.as_deref())?;
+    let num_workers = num_workers.or(config.jobs).unwrap_or_else(auto_jobs);
+    if let Some(rules_path) = &config.rules {
+        if rules.is_none() {
+            rules = Some(load_rules(rules_path).map_err(|()| ExitStatus::Error)?);
+        }
+    }
+    // Builds with O= place their output in the directory given by KBUILD_OUTPUT, if set.
+    let obj_dir = obj_dir
+        .or_else(|| env::var("KBUILD_OUTPUT").ok())
+        .unwrap_or_else(|| ".".to_string());
+    let shown_reference = display_path(Path::new(&reference));
+    let shown_obj_dir = display_path(Path::new(&obj_dir));
 
-        let mut syms = SymCorpus::new();
-        if let Err(err) = syms.load(&path, num_workers) {
-            eprintln!("Failed to read symtypes from '{}': {}", path, err);
-            return Err(());
+    // Load the reference corpus and the symtypes files of the build.
+    let timing_report = TimingReport::new(timing);
+    let diagnostics = Arc::new(StderrDiagnostics::new(quiet));
+    let mut load_options = LoadOptions::new()
+        .num_workers(num_workers)
+        .max_errors(max_errors)
+        .input_dialect(input_dialect)
+        .diagnostics(diagnostics.clone());
+    if let Some(whitelist_path) = &config.whitelist {
+        let whitelist = load_whitelist(whitelist_path).map_err(|()| ExitStatus::Error)?;
+        load_options = load_options.only_exports(whitelist);
+    }
+
+    let ref_syms = {
+        let _timing = timing_report.start(
+            "load",
+            &format!("Reading symtypes from '{}'", shown_reference),
+        );
+
+        let mut ref_syms = SymCorpus::new();
+        let paths = expand_operand(&reference)?;
+        let load_options = timing_report.load_options(&shown_reference, &load_options);
+        if let Err(err) = ref_syms.load_multiple_with(&paths, &load_options) {
+            eprintln!(
+                "Failed to read symtypes from '{}': {}",
+                shown_reference, err
+            );
+            return Err(ExitStatus::Error);
         }
-        syms
+        ref_syms
     };
 
-    let syms2 = {
-        let _timing = Timing::new(do_timing, &format!("Reading symtypes from '{}'", path2));
+    let build_syms = {
+        let _timing = timing_report.start(
+            "load",
+            &format!("Reading symtypes from '{}'", shown_obj_dir),
+        );
 
-        let mut syms2 = SymCorpus::new();
-        if let Err(err) = syms2.load(&path2, num_workers) {
-            eprintln!("Failed to read symtypes from '{}': {}", path2, err);
-            return Err(());
+        // The symbolic link to the source tree in an O= output directory is not followed, and an
+        // export found in several files, as produced by LTO builds, is taken from the first one.
+        let mut build_syms = SymCorpus::new();
+        let load_options = timing_report
+            .load_options(&shown_obj_dir, &load_options)
+            .duplicate_exports(DuplicateExports::KeepFirst);
+        if let Err(err) = build_syms.load_with(&obj_dir, &load_options) {
+            eprintln!("Failed to read symtypes from '{}': {}", shown_obj_dir, err);
+            return Err(ExitStatus::Error);
         }
-        syms2
+        build_syms
     };
 
-    {
-        let _timing = Timing::new(do_timing, "Comparison");
+    diagnostics.check(warnings_as_errors)?;
+
+    // Compare the build with the reference and write the report.
+    let mut result = {
+        let _timing = timing_report.start("compare", "Comparison");
+
+        let compare_options = CompareOptions::new()
+            .num_workers(num_workers)
+            .diagnostics(diagnostics.clone());
+        ref_syms.compare_with_options(&build_syms, &compare_options)
+    };
+    config.suppress_changes(&mut result);
+
+    diagnostics.check(warnings_as_errors)?;
+
+    let _timing = timing_report.start("write", "Writing the report");
+    let diff_options = DiffOptions::new().color(config.color.is_enabled());
+    if let Err(err) = result.write_report_with(&diff_options, io::stdout()) {
+        eprintln!(
+            "Failed to compare symtypes from '{}' and '{}': {}",
+            shown_reference, shown_obj_dir, err
+        );
+        return Err(ExitStatus::Error);
+    }
 
-        if let Err(err) = syms.compare_with(&syms2, io::stdout(), num_workers) {
+    let verdicts = rules.map(|rules| rules.check_changes(result.export_changes()));
+    if let Some(verdicts) = &verdicts {
+        if !result.is_empty() && !verdicts.verdicts().is_empty() {
+            println!();
+        }
+        if let Err(err) = verdicts.write_report(io::stdout()) {
             eprintln!(
-                "Failed to compare symtypes from '{}' and '{}': {}",
-                path, path2, err
+                "Failed to rate changes between '{}' and '{}': {}",
+                shown_reference, shown_obj_dir, err
             );
-            return Err(());
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    // With a threshold, the changes are considered differences if their badness exceeds it. With
+    // rules, only changes rated FAIL are considered differences.
+    // Without rules, all changes are rated as breaking the kABI.
+    let has_differences = if let Some(max_badness) = config.max_badness {
+        let badness = match &verdicts {
+            Some(verdicts) => verdicts.badness(&ScoreOptions::new()),
+            None => Rules::new()
+                .check_changes(result.export_changes())
+                .badness(&ScoreOptions::new()),
+        };
+        badness > max_badness
+    } else {
+        match &verdicts {
+            Some(verdicts) => verdicts.worst() == Some(Severity::Fail),
+            None => !result.is_empty(),
+        }
+    };
+    if has_differences {
+        Ok(ExitStatus::Differences)
+    } else {
+        Ok(ExitStatus::Success)
+    }
+}
+
+/// Handles the `completions` command which prints a shell completion script.
+fn do_completions<I: IntoIterator<Item = String>>(args: I) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "");
+    let mut checker = ArgChecker::new(Some("completions"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if arg == "-h" || arg == "--help" {
+                print_completions_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized completions option '{}'", arg));
+                continue;
+            }
         }
+
+        checker.operand(arg);
     }
 
-    Ok(())
+    let [shell] = checker.finish(["completions shell"])?;
+
+    // Print the completion script.
+    let script = match shell.as_str() {
+        "bash" => bash_completions(),
+        "fish" => fish_completions(),
+        "zsh" => zsh_completions(),
+        _ => {
+            eprintln!("Unrecognized completions shell '{}'", shell);
+            return Err(ExitStatus::Usage);
+        }
+    };
+    print!("{}", script);
+    Ok(ExitStatus::Success)
 }
 
 fn main() {
@@ -319,59 +2994,168 @@ fn main() {
         Some(_) => {}
         None => {
             eprintln!("Unknown program name");
-            process::exit(1);
+            ExitStatus::Error.exit();
         }
     };
+    let mut args = ShortOptions::new(args, "dhq", "");
 
     // Handle global options and stop at the command.
     let mut maybe_command = None;
-    let mut do_timing = false;
+    let mut timing = None;
+    let mut quiet = false;
     let mut debug_level = 0;
-    for arg in args.by_ref() {
+    let mut log_level = None;
+    let mut log_timestamps = false;
+    let mut read_config = true;
+    let mut version = false;
+    let mut verbose = false;
+    let mut path_style = None;
+    let mut color = None;
+    let mut checker = ArgChecker::new(None);
+    while let Some(arg) = args.next() {
         if arg == "-d" || arg == "--debug" {
             debug_level += 1;
             continue;
         }
+        if let Some(value) = check_arg!(
+            checker,
+            handle_value_option(&arg, &mut args, None, "--color")
+        ) {
+            match ColorMode::from_name(&value) {
+                Some(mode) => color = Some(mode),
+                None => checker.error(&format!(
+                    "Invalid value for '{}': unknown mode '{}'",
+                    arg, value
+                )),
+            };
+            continue;
+        }
         if arg == "--timing" {
-            do_timing = true;
+            timing = Some(OutputFormat::Text);
+            continue;
+        }
+        if let Some(value) = arg.strip_prefix("--timing=") {
+            match OutputFormat::from_name(value) {
+                Some(format) if !format.is_compare_only() => timing = Some(format),
+                _ => checker.error(&format!(
+                    "Invalid value for '{}': unknown format '{}'",
+                    arg, value
+                )),
+            };
+            continue;
+        }
+        if let Some(value) = check_arg!(checker, handle_log_level_option(&arg, &mut args)) {
+            log_level = Some(value);
+            continue;
+        }
+        if arg == "--log-timestamps" {
+            log_timestamps = true;
+            continue;
+        }
+        if arg == "--no-config" {
+            read_config = false;
+            continue;
+        }
+        if let Some(value) = check_arg!(checker, handle_path_style_option(&arg, &mut args)) {
+            path_style = Some(value);
+            continue;
+        }
+        if arg == "-q" || arg == "--quiet" {
+            quiet = true;
             continue;
         }
 
         if arg == "-h" || arg == "--help" {
             print_usage();
-            process::exit(0);
+            ExitStatus::Success.exit();
         }
         if arg == "--version" {
-            print_version();
-            process::exit(0);
+            version = true;
+            continue;
+        }
+        if arg == "--verbose" {
+            verbose = true;
+            continue;
+        }
+        if args.unbundle(&arg) {
+            continue;
         }
         if arg.starts_with('-') || arg.starts_with("--") {
-            eprintln!("Unrecognized global option '{}'", arg);
-            process::exit(1);
+            checker.error(&format!("Unrecognized global option '{}'", arg));
+            continue;
         }
         maybe_command = Some(arg);
         break;
     }
+    let args = args.into_inner();
 
-    init_debug_level(debug_level);
-
-    let command = match maybe_command {
-        Some(command) => command,
+    if version {
+        if let Err(status) = checker.check() {
+            status.exit();
+        }
+        print_version(verbose);
+        ExitStatus::Success.exit();
+    }
+    let command = match &maybe_command {
+        Some(name) => resolve_command(
+            name,
+            &COMMANDS.map(|(name, _, _)| name),
+            &COMMAND_ALIASES,
+            &mut checker,
+        ),
         None => {
-            eprintln!("No command specified");
-            process::exit(1);
+            checker.error("No command specified");
+            None
         }
     };
+    if let Err(status) = checker.check() {
+        status.exit();
+    }
+    let command = command.unwrap();
+
+    // An explicit --log-level takes precedence over -d and --quiet.
+    let log_level = log_level.unwrap_or(match (debug_level, quiet) {
+        (0, true) => LogLevel::Error,
+        (0, false) => LogLevel::Warn,
+        (1, _) => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    });
+    init_logging(log_level, Box::new(StderrLogSink::new(log_timestamps)));
+    if let Some(path_style) = path_style {
+        set_path_style(path_style);
+    }
+
+    // Informational output is suppressed in the quiet mode.
+    if quiet {
+        timing = None;
+    }
+
+    let mut config = Config::default();
+    if read_config && config.load_files().is_err() {
+        ExitStatus::Usage.exit();
+    }
+    if config.load_env().is_err() {
+        ExitStatus::Usage.exit();
+    }
+    if let Some(color) = color {
+        config.color = color;
+    }
 
     // Process the specified command.
-    let result = match command.as_str() {
-        "consolidate" => do_consolidate(do_timing, args),
-        "compare" => do_compare(do_timing, args),
-        _ => {
-            eprintln!("Unrecognized command '{}'", command);
-            Err(())
-        }
+    let result = match command {
+        "consolidate" => do_consolidate(timing, quiet, &config, args),
+        "compare" => do_compare(timing, quiet, &config, args),
+        "report" => do_report(&config, args),
+        "symvers" => do_symvers(timing, quiet, &config, args),
+        "symref" => do_symref(timing, quiet, &config, args),
+        "explain" => do_explain(timing, quiet, &config, args),
+        "verify-btf" => do_verify_btf(timing, quiet, &config, args),
+        "scan-build" => do_scan_build(timing, quiet, &config, args),
+        "completions" => do_completions(args),
+        _ => unreachable!(),
     };
 
-    process::exit(if result.is_ok() { 0 } else { 1 });
+    match result {
+        Ok(status) | Err(status) => status.exit(),
+    }
 }
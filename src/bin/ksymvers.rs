@@ -0,0 +1,700 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use suse_kabi_tools::rules::{Rules, Severity};
+use suse_kabi_tools::sym::{ChangeKind, Metrics, ScoreOptions};
+use suse_kabi_tools::symvers::SymversCorpus;
+use suse_kabi_tools::{
+    debug, display_path, init_logging, json_string, set_path_style, LogLevel, StderrLogSink,
+};
+use std::path::Path;
+use std::{env, io};
+
+#[macro_use]
+mod common;
+
+use common::{
+    annotation_style, handle_format_option, handle_log_level_option, handle_path_style_option,
+    handle_rules_option, handle_value_option, load_whitelist, resolve_command, ArgChecker,
+    ExitStatus, OutputFormat, ShortOptions,
+};
+
+/// Prints the global usage message on the standard output.
+fn print_usage() {
+    print!("{}", USAGE);
+}
+
+/// The global usage message.
+const USAGE: &str = concat!(
+    "Usage: ksymvers [OPTION...] COMMAND\n",
+    "\n",
+    "Options:\n",
+    "  --absolute-paths              show absolute paths of files\n",
+    "  -d, --debug                   enable debug output, twice for trace output\n",
+    "  -h, --help                    display this help and exit\n",
+    "  --log-level=LEVEL             log messages up to LEVEL, 'error', 'warn'\n",
+    "                                (default), 'info', 'debug' or 'trace'\n",
+    "  -q, --quiet                   do not output warnings, only errors\n",
+    "  --relative-to=DIR             show paths of files relative to DIR\n",
+    "  --version                     output version information and exit\n",
+    "\n",
+    "Commands:\n",
+    "  compare                       show differences between two symvers files\n",
+    "  validate                      check a symvers file for problems\n",
+    "  filter                        select exports from a symvers file\n",
+    "  namespaces                    list exports by their namespaces\n",
+);
+
+/// The names of the commands.
+const COMMANDS: [&str; 4] = ["compare", "validate", "filter", "namespaces"];
+
+/// Alternative names of the commands.
+const COMMAND_ALIASES: [(&str, &str); 1] = [("cmp", "compare")];
+
+/// Prints the version information on the standard output.
+fn print_version() {
+    println!("ksymvers {}", env!("CARGO_PKG_VERSION"));
+}
+
+/// Prints the usage message for the `compare` command on the standard output.
+fn print_compare_usage() {
+    print!("{}", COMPARE_USAGE);
+}
+
+/// The usage message for the `compare` command.
+const COMPARE_USAGE: &str = concat!(
+    "Usage: ksymvers compare [COMPARE-OPTION...] PATH PATH2\n",
+    "Show differences between two symvers files.\n",
+    "\n",
+    "Compare options:\n",
+    "  --format=FORMAT               write the report in FORMAT, 'text' (default),\n",
+    "                                'json', 'jsonl', 'obs', 'annotations',\n",
+    "                                'metrics', 'badness' or 'mail'\n",
+    "  -h, --help                    display this help and exit\n",
+    "  --legacy-output               write the report in the format of the\n",
+    "                                kernel-source kABI check script\n",
+    "  --rules=FILE                  rate the changes by the severities in FILE\n",
+    "  --score                       append the kABI badness score of the changes\n",
+    "  --whitelist=FILE              consider only exports listed in FILE\n",
+);
+
+/// Prints the usage message for the `validate` command on the standard output.
+fn print_validate_usage() {
+    print!("{}", VALIDATE_USAGE);
+}
+
+/// The usage message for the `validate` command.
+const VALIDATE_USAGE: &str = concat!(
+    "Usage: ksymvers validate [VALIDATE-OPTION...] PATH\n",
+    "Check a symvers file for problems.\n",
+    "\n",
+    "Validate options:\n",
+    "  --format=FORMAT               write the report in FORMAT, 'text' (default)\n",
+    "                                or 'json'\n",
+    "  -h, --help                    display this help and exit\n",
+);
+
+/// Prints the usage message for the `filter` command on the standard output.
+fn print_filter_usage() {
+    print!("{}", FILTER_USAGE);
+}
+
+/// The usage message for the `filter` command.
+const FILTER_USAGE: &str = concat!(
+    "Usage: ksymvers filter [FILTER-OPTION...] PATH\n",
+    "Select exports from a symvers file.\n",
+    "\n",
+    "Filter options:\n",
+    "  -h, --help                    display this help and exit\n",
+    "  --module=MODULE               keep exports provided by MODULE\n",
+    "  --namespace=NAMESPACE         keep exports in NAMESPACE\n",
+    "  -o FILE, --output=FILE        write the result to FILE, instead of stdout\n",
+    "  --whitelist=FILE              keep exports listed in FILE\n",
+);
+
+/// Prints the usage message for the `namespaces` command on the standard output.
+fn print_namespaces_usage() {
+    print!("{}", NAMESPACES_USAGE);
+}
+
+/// The usage message for the `namespaces` command.
+const NAMESPACES_USAGE: &str = concat!(
+    "Usage: ksymvers namespaces [NAMESPACES-OPTION...] PATH\n",
+    "List exports by their namespaces.\n",
+    "\n",
+    "Namespaces options:\n",
+    "  --format=FORMAT               write the report in FORMAT, 'text' (default)\n",
+    "                                or 'json'\n",
+    "  -h, --help                    display this help and exit\n",
+);
+
+/// Handles the `--whitelist` option which specifies a file with a list of exports, and returns
+/// the names read from the file.
+fn handle_whitelist_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<Vec<String>>, ()> {
+    if let Some(value) = handle_value_option(arg, args, None, "--whitelist")? {
+        return load_whitelist(value).map(Some);
+    }
+
+    Ok(None)
+}
+
+/// Loads symvers data from the specified path.
+fn load_symvers(path: &str) -> Result<SymversCorpus, ExitStatus> {
+    let mut symvers = SymversCorpus::new();
+    if let Err(err) = symvers.load(path) {
+        eprintln!(
+            "Failed to read symvers from '{}': {}",
+            display_path(Path::new(path)),
+            err
+        );
+        return Err(ExitStatus::Error);
+    }
+    Ok(symvers)
+}
+
+/// Handles the `compare` command which shows differences between two symvers files.
+fn do_compare<I: IntoIterator<Item = String>>(args: I) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "");
+    let mut do_score = false;
+    let mut whitelist = None;
+    let mut rules = None;
+    let mut legacy_output = false;
+    let mut format = OutputFormat::default();
+    let mut checker = ArgChecker::new(Some("compare"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(checker, handle_format_option(&arg, &mut args)) {
+                format = value;
+                continue;
+            }
+            if arg == "--score" {
+                do_score = true;
+                continue;
+            }
+            if arg == "--legacy-output" {
+                legacy_output = true;
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_whitelist_option(&arg, &mut args)) {
+                whitelist = Some(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_rules_option(&arg, &mut args)) {
+                rules = Some(value);
+                continue;
+            }
+
+            if arg == "-h" || arg == "--help" {
+                print_compare_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized compare option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    if format == OutputFormat::Json && do_score {
+        checker.error("The '--score' option cannot be used with '--format=json'");
+    }
+    if matches!(
+        format,
+        OutputFormat::Json | OutputFormat::Jsonl | OutputFormat::Metrics
+    ) && rules.is_some()
+    {
+        checker.error(&format!(
+            "The '--rules' option cannot be used with '--format={}'",
+            format.name()
+        ));
+    }
+    if legacy_output && format == OutputFormat::Json {
+        checker.error("The '--legacy-output' option cannot be used with '--format=json'");
+    }
+    if legacy_output && do_score {
+        checker.error("The '--legacy-output' option cannot be used with '--score'");
+    }
+    if format.is_compare_only() && do_score {
+        checker.error(&format!(
+            "The '--score' option cannot be used with '--format={}'",
+            format.name()
+        ));
+    }
+    if legacy_output && format.is_compare_only() {
+        checker.error(&format!(
+            "The '--legacy-output' option cannot be used with '--format={}'",
+            format.name()
+        ));
+    }
+
+    if matches!(format, OutputFormat::Markdown | OutputFormat::Html) {
+        checker.error(&format!(
+            "The '{}' format can be used only with ksymtypes",
+            format.name()
+        ));
+    }
+
+    let [path, path2] = checker.finish(["first compare source", "second compare source"])?;
+    if path == "-" && path2 == "-" {
+        eprintln!("The standard input can be used only for one compare source");
+        return Err(ExitStatus::Usage);
+    }
+
+    // Do the comparison.
+    debug!("Compare '{}' and '{}'", path, path2);
+
+    let mut symvers = load_symvers(&path)?;
+    let mut symvers2 = load_symvers(&path2)?;
+    if let Some(whitelist) = &whitelist {
+        symvers.retain_whitelist(whitelist);
+        symvers2.retain_whitelist(whitelist);
+    }
+
+    let result = symvers.compare(&symvers2);
+    let verdicts = rules
+        .as_ref()
+        .map(|rules| rules.check_changes(result.export_changes()));
+    // Without rules, all changes are rated as breaking the kABI.
+    let rated_changes = || {
+        verdicts
+            .clone()
+            .unwrap_or_else(|| Rules::new().check_changes(result.export_changes()))
+    };
+    let write_result = match format {
+        _ if legacy_output => result.write_legacy_report(rules.as_ref(), io::stdout()),
+        OutputFormat::Text => result.write_report(io::stdout()),
+        OutputFormat::Json => result.write_json_report(io::stdout()),
+        OutputFormat::Jsonl => result.write_jsonl_report(io::stdout()),
+        OutputFormat::Mail => result.write_mail_report(io::stdout()),
+        OutputFormat::Obs => rated_changes().write_obs_report(io::stdout()),
+        OutputFormat::Annotations => {
+            rated_changes().write_annotations(annotation_style(), io::stdout())
+        }
+        OutputFormat::Badness => {
+            rated_changes().write_badness_report(&ScoreOptions::new(), io::stdout())
+        }
+        OutputFormat::Metrics => Metrics::new(
+            symvers2.exports().len(),
+            result.export_changes(),
+            &ScoreOptions::new(),
+        )
+        .write_report(io::stdout()),
+        // Formats supported only by ksymtypes are rejected above.
+        OutputFormat::Markdown | OutputFormat::Html => unreachable!(),
+    };
+    if let Err(err) = write_result {
+        eprintln!(
+            "Failed to compare symvers from '{}' and '{}': {}",
+            display_path(Path::new(&path)),
+            display_path(Path::new(&path2)),
+            err
+        );
+        return Err(ExitStatus::Error);
+    }
+
+    if let Some(verdicts) = verdicts
+        .as_ref()
+        .filter(|_| !legacy_output && !format.is_rated())
+    {
+        // Separate the severities from the report of the changes.
+        if !verdicts.verdicts().is_empty() {
+            println!();
+        }
+        if let Err(err) = verdicts.write_report(io::stdout()) {
+            eprintln!(
+                "Failed to rate changes between '{}' and '{}': {}",
+                display_path(Path::new(&path)),
+                display_path(Path::new(&path2)),
+                err
+            );
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    if do_score {
+        // Separate the score from the report of the changes.
+        if !result.is_empty() {
+            println!();
+        }
+        let score = result.score(&ScoreOptions::new());
+        if let Err(err) = score.write_report(io::stdout()) {
+            eprintln!(
+                "Failed to score changes between '{}' and '{}': {}",
+                display_path(Path::new(&path)),
+                display_path(Path::new(&path2)),
+                err
+            );
+            return Err(ExitStatus::Error);
+        }
+    }
+
+    // With rules, only changes rated FAIL are considered differences. The legacy, OBS and
+    // annotations outputs consider only changes which break the kABI, the same as the kernel-source
+    // check script.
+    let has_differences = match &verdicts {
+        Some(verdicts) => verdicts.worst() == Some(Severity::Fail),
+        None if legacy_output || format.is_rated() => result
+            .export_changes()
+            .iter()
+            .any(|(_, _, kind)| *kind != ChangeKind::Added),
+        None => !result.is_empty(),
+    };
+    if has_differences {
+        Ok(ExitStatus::Differences)
+    } else {
+        Ok(ExitStatus::Success)
+    }
+}
+
+/// Handles the `validate` command which checks a symvers file for problems.
+fn do_validate<I: IntoIterator<Item = String>>(args: I) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "");
+    let mut format = OutputFormat::default();
+    let mut checker = ArgChecker::new(Some("validate"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(checker, handle_format_option(&arg, &mut args)) {
+                format = value;
+                continue;
+            }
+
+            if arg == "-h" || arg == "--help" {
+                print_validate_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized validate option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    if format.is_compare_only() {
+        checker.error(&format!(
+            "The '{}' format can be used only with the compare command",
+            format.name()
+        ));
+    }
+
+    let [path] = checker.finish(["validate source"])?;
+
+    // Check the data.
+    debug!("Validate '{}'", path);
+
+    let symvers = load_symvers(&path)?;
+    let warnings = symvers.validate();
+    match format {
+        OutputFormat::Text => {
+            for warning in &warnings {
+                println!("{}", warning);
+            }
+        }
+        OutputFormat::Json => {
+            let problems: Vec<_> = warnings
+                .iter()
+                .map(|warning| json_string(warning.message()))
+                .collect();
+            println!("{{\"problems\":[{}]}}", problems.join(","));
+        }
+        // Formats supported only by the compare command are rejected above.
+        _ => unreachable!(),
+    }
+    if warnings.is_empty() {
+        Ok(ExitStatus::Success)
+    } else {
+        Ok(ExitStatus::Differences)
+    }
+}
+
+/// Handles the `filter` command which selects exports from a symvers file.
+fn do_filter<I: IntoIterator<Item = String>>(args: I) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "o");
+    let mut output = "-".to_string();
+    let mut modules = Vec::new();
+    let mut namespaces = Vec::new();
+    let mut whitelist = None;
+    let mut checker = ArgChecker::new(Some("filter"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, Some("-o"), "--output")
+            ) {
+                output = value;
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--module")
+            ) {
+                modules.push(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(
+                checker,
+                handle_value_option(&arg, &mut args, None, "--namespace")
+            ) {
+                namespaces.push(value);
+                continue;
+            }
+            if let Some(value) = check_arg!(checker, handle_whitelist_option(&arg, &mut args)) {
+                whitelist = Some(value);
+                continue;
+            }
+
+            if arg == "-h" || arg == "--help" {
+                print_filter_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized filter option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    let [path] = checker.finish(["filter source"])?;
+
+    // Select the exports. Each kind of the filters must be satisfied if it is specified.
+    debug!("Filter '{}'", path);
+
+    let mut symvers = load_symvers(&path)?;
+    if let Some(whitelist) = &whitelist {
+        symvers.retain_whitelist(whitelist);
+    }
+    if !modules.is_empty() {
+        symvers.retain(|export| modules.iter().any(|module| module == export.module()));
+    }
+    if !namespaces.is_empty() {
+        symvers.retain(|export| {
+            namespaces
+                .iter()
+                .any(|namespace| Some(namespace.as_str()) == export.namespace())
+        });
+    }
+
+    if let Err(err) = symvers.write_file(&output) {
+        eprintln!(
+            "Failed to write symvers to '{}': {}",
+            display_path(Path::new(&output)),
+            err
+        );
+        return Err(ExitStatus::Error);
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/// Handles the `namespaces` command which lists exports by their namespaces.
+fn do_namespaces<I: IntoIterator<Item = String>>(args: I) -> Result<ExitStatus, ExitStatus> {
+    // Parse specific command options.
+    let mut args = ShortOptions::new(args.into_iter(), "h", "");
+    let mut format = OutputFormat::default();
+    let mut checker = ArgChecker::new(Some("namespaces"));
+    let mut past_dash_dash = false;
+
+    while let Some(arg) = args.next() {
+        if !past_dash_dash {
+            if let Some(value) = check_arg!(checker, handle_format_option(&arg, &mut args)) {
+                format = value;
+                continue;
+            }
+
+            if arg == "-h" || arg == "--help" {
+                print_namespaces_usage();
+                return Ok(ExitStatus::Success);
+            }
+            if arg == "--" {
+                past_dash_dash = true;
+                continue;
+            }
+            if args.unbundle(&arg) {
+                continue;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                checker.error(&format!("Unrecognized namespaces option '{}'", arg));
+                continue;
+            }
+        }
+
+        checker.operand(arg);
+    }
+
+    if format.is_compare_only() {
+        checker.error(&format!(
+            "The '{}' format can be used only with the compare command",
+            format.name()
+        ));
+    }
+
+    let [path] = checker.finish(["namespaces source"])?;
+
+    // Write the report, with the exports of each namespace sorted by their name.
+    let symvers = load_symvers(&path)?;
+    let mut json_namespaces = Vec::new();
+    for (i, namespace) in symvers.namespaces().into_iter().enumerate() {
+        let mut exports: Vec<_> = symvers.namespace_exports(namespace).collect();
+        exports.sort_by(|a, b| a.name().cmp(b.name()));
+        match format {
+            OutputFormat::Text => {
+                if i > 0 {
+                    println!();
+                }
+                println!("Namespace '{}' ({} exports):", namespace, exports.len());
+                for export in exports {
+                    println!(" {} ({})", export.name(), export.module());
+                }
+            }
+            OutputFormat::Json => {
+                let exports: Vec<_> = exports.iter().map(|export| export.to_json()).collect();
+                json_namespaces.push(format!(
+                    "{{\"name\":{},\"exports\":[{}]}}",
+                    json_string(namespace),
+                    exports.join(",")
+                ));
+            }
+            // Formats supported only by the compare command are rejected above.
+            _ => unreachable!(),
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{{\"namespaces\":[{}]}}", json_namespaces.join(","));
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+fn main() {
+    let mut args = env::args();
+
+    // Skip over the program name.
+    match args.next() {
+        Some(_) => {}
+        None => {
+            eprintln!("Unknown program name");
+            ExitStatus::Error.exit();
+        }
+    };
+    let mut args = ShortOptions::new(args, "dhq", "");
+
+    // Handle global options and stop at the command.
+    let mut maybe_command = None;
+    let mut quiet = false;
+    let mut debug_level = 0;
+    let mut log_level = None;
+    let mut path_style = None;
+    let mut checker = ArgChecker::new(None);
+    while let Some(arg) = args.next() {
+        if arg == "-d" || arg == "--debug" {
+            debug_level += 1;
+            continue;
+        }
+        if let Some(value) = check_arg!(checker, handle_log_level_option(&arg, &mut args)) {
+            log_level = Some(value);
+            continue;
+        }
+        if arg == "-q" || arg == "--quiet" {
+            quiet = true;
+            continue;
+        }
+        if let Some(value) = check_arg!(checker, handle_path_style_option(&arg, &mut args)) {
+            path_style = Some(value);
+            continue;
+        }
+
+        if arg == "-h" || arg == "--help" {
+            print_usage();
+            ExitStatus::Success.exit();
+        }
+        if arg == "--version" {
+            print_version();
+            ExitStatus::Success.exit();
+        }
+        if args.unbundle(&arg) {
+            continue;
+        }
+        if arg.starts_with('-') || arg.starts_with("--") {
+            checker.error(&format!("Unrecognized global option '{}'", arg));
+            continue;
+        }
+        maybe_command = Some(arg);
+        break;
+    }
+    let args = args.into_inner();
+
+    let command = match &maybe_command {
+        Some(name) => resolve_command(name, &COMMANDS, &COMMAND_ALIASES, &mut checker),
+        None => {
+            checker.error("No command specified");
+            None
+        }
+    };
+    if let Err(status) = checker.check() {
+        status.exit();
+    }
+    let command = command.unwrap();
+
+    // An explicit --log-level takes precedence over -d and --quiet.
+    let log_level = log_level.unwrap_or(match (debug_level, quiet) {
+        (0, true) => LogLevel::Error,
+        (0, false) => LogLevel::Warn,
+        (1, _) => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    });
+    init_logging(log_level, Box::new(StderrLogSink::new(false)));
+    if let Some(path_style) = path_style {
+        set_path_style(path_style);
+    }
+
+    // Process the specified command.
+    let result = match command {
+        "compare" => do_compare(args),
+        "validate" => do_validate(args),
+        "filter" => do_filter(args),
+        "namespaces" => do_namespaces(args),
+        _ => unreachable!(),
+    };
+
+    match result {
+        Ok(status) | Err(status) => status.exit(),
+    }
+}
@@ -0,0 +1,580 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! Reading of the BPF Type Format (BTF) data of the kernel.
+//!
+//! The data can be provided as raw BTF, such as `/sys/kernel/btf/vmlinux`, or as an ELF file with
+//! a `.BTF` section, such as `vmlinux`. Only the information needed to cross-check symtypes data is
+//! kept: the names of functions and variables, and the member names of structures and unions.
+//! Split BTF of modules, which refers to the base BTF of vmlinux, is not supported.
+
+use crate::ast::Decl;
+use crate::sym::{RecordKind, SymCorpus, TypeView, TypeVisitor};
+use crate::{debug, json_string, MapIOErr, PathFile};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{prelude::*, BufWriter};
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// The magic number at the start of BTF data.
+const BTF_MAGIC: u16 = 0xeb9f;
+
+/// The size of a `struct btf_type` record.
+const TYPE_SIZE: usize = 12;
+
+// Kinds of BTF types, as defined by `BTF_KIND_*` in the kernel.
+const KIND_INT: u32 = 1;
+const KIND_ARRAY: u32 = 3;
+const KIND_STRUCT: u32 = 4;
+const KIND_UNION: u32 = 5;
+const KIND_ENUM: u32 = 6;
+const KIND_FUNC: u32 = 12;
+const KIND_FUNC_PROTO: u32 = 13;
+const KIND_VAR: u32 = 14;
+const KIND_DATASEC: u32 = 15;
+const KIND_DECL_TAG: u32 = 17;
+const KIND_ENUM64: u32 = 19;
+const KIND_MAX: u32 = 19;
+
+/// A kind of an aggregate type recorded in BTF.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AggregateKind {
+    /// A structure.
+    Struct,
+    /// A union.
+    Union,
+}
+
+/// Type information from BTF data.
+#[derive(Debug, Default)]
+pub struct Btf {
+    /// Names of all functions and variables.
+    symbols: FxHashSet<String>,
+    /// Member names of all named structures and unions. A name can have multiple definitions.
+    aggregates: FxHashMap<(AggregateKind, String), Vec<Vec<String>>>,
+}
+
+impl Btf {
+    /// Creates a new empty BTF.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads BTF data from a file, which can be compressed in one of the supported formats. The
+    /// path `-` denotes the standard input.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+        let err_desc = "Failed to read BTF data";
+
+        let mut data = Vec::new();
+        if path == Path::new("-") {
+            io::stdin()
+                .lock()
+                .read_to_end(&mut data)
+                .map_err(|err| crate::Error::new_io(err_desc, err))?;
+        } else {
+            let file = PathFile::open(path).map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to open file '{}'", crate::display_path(path)),
+                    err,
+                )
+            })?;
+            crate::Compression::from_path(path)
+                .reader(file)
+                .and_then(|mut reader| reader.read_to_end(&mut data))
+                .map_err(|err| crate::Error::new_io(err_desc, err))?;
+        }
+        self.load_bytes(path, &data)
+            .map_err(|err| crate::Error::new_io(err_desc, err))
+    }
+
+    /// Loads BTF data from a buffer with raw BTF or an ELF file. The `path` identifies the origin
+    /// of the data.
+    pub fn load_bytes<P: AsRef<Path>>(&mut self, path: P, data: &[u8]) -> io::Result<()> {
+        debug!("Loading BTF '{}'", path.as_ref().display());
+
+        if data.starts_with(b"\x7fELF") {
+            let section = find_elf_section(data, ".BTF")?
+                .ok_or_else(|| invalid_data("No .BTF section found in the ELF file"))?;
+            return self.load_raw(section);
+        }
+        self.load_raw(data)
+    }
+
+    /// Returns whether a function or variable `name` is described by the BTF.
+    pub fn has_symbol(&self, name: &str) -> bool {
+        self.symbols.contains(name)
+    }
+
+    /// Returns the member names of all definitions of the structure or union `name`. An anonymous
+    /// member has an empty name.
+    pub fn aggregate_members(&self, kind: AggregateKind, name: &str) -> &[Vec<String>] {
+        self.aggregates
+            .get(&(kind, name.to_string()))
+            .map_or(&[], |defs| defs.as_slice())
+    }
+
+    /// Checks the structures and unions used by the exports of `corpus` against the BTF. Only
+    /// exports which are also described by the BTF are checked.
+    ///
+    /// A type is consistent if the names of its members, in their order, match a definition of the
+    /// same structure or union in the BTF. A type which is incomplete in the symtypes or not
+    /// defined by the BTF is skipped.
+    pub fn verify(&self, corpus: &SymCorpus) -> BtfVerification {
+        let mut exports: Vec<_> = corpus
+            .export_views()
+            .filter(|export| self.has_symbol(export.name()))
+            .collect();
+        exports.sort_by_key(|export| export.name());
+
+        let mut visitor = VerifyVisitor {
+            btf: self,
+            export: "",
+            checked: FxHashMap::default(),
+            divergences: BTreeMap::new(),
+        };
+        for export in &exports {
+            visitor.export = export.name();
+            export.visit(&mut visitor);
+        }
+
+        BtfVerification {
+            num_checked_exports: exports.len(),
+            divergences: visitor.divergences.into_values().collect(),
+        }
+    }
+
+    /// Loads raw BTF data.
+    fn load_raw(&mut self, data: &[u8]) -> io::Result<()> {
+        let reader = match data.get(0..2) {
+            Some(magic) if u16::from_le_bytes([magic[0], magic[1]]) == BTF_MAGIC => ByteReader {
+                data,
+                big_endian: false,
+            },
+            Some(magic) if u16::from_be_bytes([magic[0], magic[1]]) == BTF_MAGIC => ByteReader {
+                data,
+                big_endian: true,
+            },
+            _ => return Err(invalid_data("Not a BTF or ELF file")),
+        };
+
+        let hdr_len = reader.u32(4)? as usize;
+        let type_off = reader.u32(8)? as usize;
+        let type_len = reader.u32(12)? as usize;
+        let str_off = reader.u32(16)? as usize;
+        let str_len = reader.u32(20)? as usize;
+        let types = reader.slice(hdr_len + type_off, type_len)?;
+        let strings = reader.slice(hdr_len + str_off, str_len)?;
+        let types = ByteReader {
+            data: types,
+            ..reader
+        };
+        let name_at = |offset: u32| -> io::Result<String> {
+            let tail = strings
+                .get(offset as usize..)
+                .ok_or_else(|| invalid_data("Name offset out of the string section"))?;
+            let end = tail
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(tail.len());
+            Ok(String::from_utf8_lossy(&tail[..end]).into_owned())
+        };
+
+        let mut pos = 0;
+        while pos < types.data.len() {
+            let name_off = types.u32(pos)?;
+            let info = types.u32(pos + 4)?;
+            let vlen = (info & 0xffff) as usize;
+            let kind = (info >> 24) & 0x1f;
+            pos += TYPE_SIZE;
+
+            if kind == 0 || kind > KIND_MAX {
+                return Err(invalid_data(&format!("Unknown BTF type kind {}", kind)));
+            }
+            let extra = match kind {
+                KIND_INT | KIND_VAR | KIND_DECL_TAG => 4,
+                KIND_ARRAY => 12,
+                KIND_STRUCT | KIND_UNION | KIND_DATASEC | KIND_ENUM64 => vlen * 12,
+                KIND_ENUM | KIND_FUNC_PROTO => vlen * 8,
+                _ => 0,
+            };
+
+            match kind {
+                KIND_FUNC | KIND_VAR => {
+                    self.symbols.insert(name_at(name_off)?);
+                }
+                KIND_STRUCT | KIND_UNION if name_off != 0 => {
+                    let mut members = Vec::with_capacity(vlen);
+                    for i in 0..vlen {
+                        members.push(name_at(types.u32(pos + i * 12)?)?);
+                    }
+                    let kind = if kind == KIND_STRUCT {
+                        AggregateKind::Struct
+                    } else {
+                        AggregateKind::Union
+                    };
+                    let defs = self
+                        .aggregates
+                        .entry((kind, name_at(name_off)?))
+                        .or_default();
+                    if !defs.contains(&members) {
+                        defs.push(members);
+                    }
+                }
+                _ => {}
+            }
+            pos += extra;
+        }
+        Ok(())
+    }
+}
+
+/// A type graph walker used by [`Btf::verify()`].
+struct VerifyVisitor<'a> {
+    btf: &'a Btf,
+    /// The export being walked.
+    export: &'a str,
+    /// Whether each visited type variant is consistent with the BTF.
+    checked: FxHashMap<(&'a str, usize), bool>,
+    /// Found divergences, keyed by the type name and variant index.
+    divergences: BTreeMap<(&'a str, usize), BtfDivergence>,
+}
+
+impl<'a> TypeVisitor<'a> for VerifyVisitor<'a> {
+    fn enter_type(&mut self, view: &TypeView<'a>, _depth: usize) -> bool {
+        let key = (view.name(), view.variant_index());
+        if let Some(&consistent) = self.checked.get(&key) {
+            if !consistent {
+                let divergence = self.divergences.get_mut(&key).unwrap();
+                if !divergence.exports.iter().any(|name| name == self.export) {
+                    divergence.exports.push(self.export.to_string());
+                }
+            }
+            // The types referenced by this one were already walked.
+            return false;
+        }
+
+        let kind = match view.kind() {
+            RecordKind::Struct => AggregateKind::Struct,
+            RecordKind::Union => AggregateKind::Union,
+            _ => {
+                self.checked.insert(key, true);
+                return true;
+            }
+        };
+        let members = match view.parse() {
+            Decl::Struct(aggregate) | Decl::Union(aggregate) => {
+                aggregate.members().map(|members| {
+                    members
+                        .iter()
+                        .map(|member| member.name().unwrap_or_default().to_string())
+                        .collect::<Vec<_>>()
+                })
+            }
+            _ => None,
+        };
+        let btf_defs = self.btf.aggregate_members(kind, &view.name()[2..]);
+        let consistent = match &members {
+            Some(members) => btf_defs.is_empty() || btf_defs.contains(members),
+            None => true,
+        };
+        self.checked.insert(key, consistent);
+        if !consistent {
+            self.divergences.insert(
+                key,
+                BtfDivergence {
+                    type_name: view.name().to_string(),
+                    file: view.file().to_path_buf(),
+                    exports: vec![self.export.to_string()],
+                    members: members.unwrap_or_default(),
+                    btf_members: btf_defs[0].clone(),
+                },
+            );
+        }
+        true
+    }
+}
+
+/// A structure or union whose symtypes definition is inconsistent with the BTF.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BtfDivergence {
+    type_name: String,
+    file: PathBuf,
+    exports: Vec<String>,
+    members: Vec<String>,
+    btf_members: Vec<String>,
+}
+
+impl BtfDivergence {
+    /// Returns the name of the type, such as `s#foo`.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// Returns the path of the first `.symtypes` file in which the inconsistent definition was
+    /// found.
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Returns the names of the checked exports which use the type.
+    pub fn exports(&self) -> &[String] {
+        &self.exports
+    }
+
+    /// Returns the member names in the symtypes definition. An anonymous member has an empty
+    /// name.
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+
+    /// Returns the member names of the first definition of the type in the BTF.
+    pub fn btf_members(&self) -> &[String] {
+        &self.btf_members
+    }
+}
+
+/// A result of checking a symtypes corpus against BTF, computed by [`Btf::verify()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BtfVerification {
+    num_checked_exports: usize,
+    divergences: Vec<BtfDivergence>,
+}
+
+impl BtfVerification {
+    /// Returns the number of exports which were checked.
+    pub fn num_checked_exports(&self) -> usize {
+        self.num_checked_exports
+    }
+
+    /// Returns the found divergences, sorted by the type name.
+    pub fn divergences(&self) -> &[BtfDivergence] {
+        &self.divergences
+    }
+
+    /// Returns whether no divergence was found.
+    pub fn is_empty(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    /// Writes a human-readable report of the divergences to the provided output stream.
+    pub fn write_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a BTF verification report";
+
+        for divergence in &self.divergences {
+            let exports: Vec<_> = divergence
+                .exports
+                .iter()
+                .map(|name| format!("'{}'", name))
+                .collect();
+            writeln!(
+                writer,
+                "Type '{}' in '{}' is inconsistent with BTF, used by {}",
+                divergence.type_name,
+                crate::display_path(&divergence.file),
+                exports.join(", ")
+            )
+            .map_io_err(err_desc)?;
+            writeln!(
+                writer,
+                "  symtypes members: {}",
+                format_members(&divergence.members)
+            )
+            .map_io_err(err_desc)?;
+            writeln!(
+                writer,
+                "  BTF members: {}",
+                format_members(&divergence.btf_members)
+            )
+            .map_io_err(err_desc)?;
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes the result as a JSON object to the provided output stream.
+    ///
+    /// The object contains the number of `checked_exports` and an array `divergences`. Each
+    /// divergence is described by the `type` name, the `file` with its definition, the affected
+    /// `exports`, and the member names in `symtypes_members` and `btf_members`.
+    pub fn write_json_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a BTF verification report";
+        let json_array = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| json_string(name))
+                .collect::<Vec<_>>()
+        };
+
+        write!(
+            writer,
+            "{{\"checked_exports\":{},\"divergences\":[",
+            self.num_checked_exports
+        )
+        .map_io_err(err_desc)?;
+        for (i, divergence) in self.divergences.iter().enumerate() {
+            write!(
+                writer,
+                concat!(
+                    "{}{{\"type\":{},\"file\":{},\"exports\":[{}],\"symtypes_members\":[{}],",
+                    "\"btf_members\":[{}]}}"
+                ),
+                if i > 0 { "," } else { "" },
+                json_string(&divergence.type_name),
+                json_string(&crate::display_path(&divergence.file).to_string()),
+                json_array(&divergence.exports).join(","),
+                json_array(&divergence.members).join(","),
+                json_array(&divergence.btf_members).join(",")
+            )
+            .map_io_err(err_desc)?;
+        }
+        writeln!(writer, "]}}").map_io_err(err_desc)?;
+
+        writer.flush().map_io_err(err_desc)
+    }
+}
+
+/// Formats member names for a report, showing an anonymous member as `(anonymous)`.
+fn format_members(members: &[String]) -> String {
+    members
+        .iter()
+        .map(|name| if name.is_empty() { "(anonymous)" } else { name })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A reader of integers in BTF or ELF data with a given byte order.
+#[derive(Clone, Copy)]
+struct ByteReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> ByteReader<'a> {
+    /// Returns `len` bytes at `offset`.
+    fn slice(&self, offset: usize, len: usize) -> io::Result<&'a [u8]> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| invalid_data("Unexpected end of data"))
+    }
+
+    /// Reads an integer of `N` bytes at `offset`.
+    fn bytes<const N: usize>(&self, offset: usize) -> io::Result<[u8; N]> {
+        Ok(self.slice(offset, N)?.try_into().unwrap())
+    }
+
+    /// Reads a 16-bit integer at `offset`.
+    fn u16(&self, offset: usize) -> io::Result<u16> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    /// Reads a 32-bit integer at `offset`.
+    fn u32(&self, offset: usize) -> io::Result<u32> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    /// Reads a 64-bit integer at `offset`.
+    fn u64(&self, offset: usize) -> io::Result<u64> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        })
+    }
+}
+
+/// Returns the contents of the section `name` in the ELF file `data`, or [`None`] if the file has
+/// no such section.
+fn find_elf_section<'a>(data: &'a [u8], name: &str) -> io::Result<Option<&'a [u8]>> {
+    let is_64bit = match data.get(4) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return Err(invalid_data("Unknown ELF class")),
+    };
+    let reader = match data.get(5) {
+        Some(1) => ByteReader {
+            data,
+            big_endian: false,
+        },
+        Some(2) => ByteReader {
+            data,
+            big_endian: true,
+        },
+        _ => return Err(invalid_data("Unknown ELF data encoding")),
+    };
+
+    // Read the location of the section headers from the ELF header.
+    let (shoff, shentsize, shnum, shstrndx) = if is_64bit {
+        (
+            reader.u64(0x28)?,
+            reader.u16(0x3a)?,
+            reader.u16(0x3c)?,
+            reader.u16(0x3e)?,
+        )
+    } else {
+        (
+            u64::from(reader.u32(0x20)?),
+            reader.u16(0x2e)?,
+            reader.u16(0x30)?,
+            reader.u16(0x32)?,
+        )
+    };
+    let shoff = usize::try_from(shoff).map_err(|_| invalid_data("Invalid ELF header"))?;
+
+    // Returns the name offset and the contents of the section at `idx`.
+    let section = |idx: u16| -> io::Result<(usize, &'a [u8])> {
+        let header = shoff + usize::from(idx) * usize::from(shentsize);
+        let (name, offset, size) = if is_64bit {
+            (
+                reader.u32(header)?,
+                reader.u64(header + 0x18)?,
+                reader.u64(header + 0x20)?,
+            )
+        } else {
+            (
+                reader.u32(header)?,
+                u64::from(reader.u32(header + 0x10)?),
+                u64::from(reader.u32(header + 0x14)?),
+            )
+        };
+        let offset = usize::try_from(offset).map_err(|_| invalid_data("Invalid ELF section"))?;
+        let size = usize::try_from(size).map_err(|_| invalid_data("Invalid ELF section"))?;
+        Ok((name as usize, reader.slice(offset, size)?))
+    };
+
+    let (_, names) = section(shstrndx)?;
+    for idx in 0..shnum {
+        let (name_off, contents) = section(idx)?;
+        let section_name = names
+            .get(name_off..)
+            .and_then(|tail| tail.split(|&byte| byte == 0).next())
+            .unwrap_or_default();
+        if section_name == name.as_bytes() {
+            return Ok(Some(contents));
+        }
+    }
+    Ok(None)
+}
+
+/// Creates an [`io::Error`] for malformed data.
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,150 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use super::*;
+use crate::assert_ok;
+
+/// The string section of the test BTF data, with the names at offsets 1 (`foo`), 5 (`a`), 7 (`b`)
+/// and 9 (`bar`).
+const STRINGS: &[u8] = b"\0foo\0a\0b\0bar\0";
+
+/// The records of the type section of the test BTF data, which describe
+/// `struct foo { int a; int b; }` and a function `bar`.
+const TYPES: &[&[u32]] = &[
+    &[0, 1 << 24, 4, 0x20],                  // [1] int
+    &[1, 4 << 24 | 2, 8, 5, 1, 0, 7, 1, 32], // [2] struct foo
+    &[0, 13 << 24 | 1, 0, 0, 1],             // [3] void (int)
+    &[9, 12 << 24, 3],                       // [4] bar
+];
+
+/// Builds raw little-endian BTF data from the words of the records of its type section and its
+/// string section.
+fn make_btf(types: &[&[u32]], strings: &[u8]) -> Vec<u8> {
+    let types = types.concat();
+    let type_len = (types.len() * 4) as u32;
+    let mut data = Vec::new();
+    data.extend_from_slice(&BTF_MAGIC.to_le_bytes());
+    data.extend_from_slice(&[1, 0]);
+    for word in [24, 0, type_len, type_len, strings.len() as u32] {
+        data.extend_from_slice(&u32::to_le_bytes(word));
+    }
+    for word in types {
+        data.extend_from_slice(&word.to_le_bytes());
+    }
+    data.extend_from_slice(strings);
+    data
+}
+
+/// Builds a little-endian 64-bit ELF file with a `.BTF` section containing `btf`.
+fn make_elf(btf: &[u8]) -> Vec<u8> {
+    let names = b"\0.shstrtab\0.BTF\0";
+    let shoff = 64 + names.len() + btf.len();
+    let mut data = vec![0; 64];
+    data[..6].copy_from_slice(b"\x7fELF\x02\x01");
+    data[0x28..0x30].copy_from_slice(&(shoff as u64).to_le_bytes());
+    data[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+    data[0x3c..0x3e].copy_from_slice(&3u16.to_le_bytes());
+    data[0x3e..0x40].copy_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(names);
+    data.extend_from_slice(btf);
+    for (name, offset, size) in [
+        (0, 0, 0),
+        (1, 64, names.len()),
+        (11, 64 + names.len(), btf.len()),
+    ] {
+        let mut header = vec![0; 64];
+        header[..4].copy_from_slice(&(name as u32).to_le_bytes());
+        header[0x18..0x20].copy_from_slice(&(offset as u64).to_le_bytes());
+        header[0x20..0x28].copy_from_slice(&(size as u64).to_le_bytes());
+        data.extend_from_slice(&header);
+    }
+    data
+}
+
+#[test]
+fn read_raw() {
+    // Check that functions and structure members are read from raw BTF data.
+    let mut btf = Btf::new();
+    let result = btf.load_bytes("vmlinux.btf", &make_btf(TYPES, STRINGS));
+    assert_ok!(result);
+    assert!(btf.has_symbol("bar"));
+    assert!(!btf.has_symbol("foo"));
+    assert_eq!(
+        btf.aggregate_members(AggregateKind::Struct, "foo"),
+        [vec!["a".to_string(), "b".to_string()]]
+    );
+    assert!(btf
+        .aggregate_members(AggregateKind::Union, "foo")
+        .is_empty());
+}
+
+#[test]
+fn read_elf() {
+    // Check that BTF data is read from the .BTF section of an ELF file.
+    let mut btf = Btf::new();
+    let result = btf.load_bytes("vmlinux", &make_elf(&make_btf(TYPES, STRINGS)));
+    assert_ok!(result);
+    assert!(btf.has_symbol("bar"));
+}
+
+#[test]
+fn read_invalid() {
+    // Check that data which is neither BTF nor ELF is rejected, as is an unknown type kind.
+    let mut btf = Btf::new();
+    let result = btf.load_bytes("vmlinux.btf", b"garbage");
+    assert_eq!(result.unwrap_err().to_string(), "Not a BTF or ELF file");
+
+    let result = btf.load_bytes("vmlinux.btf", &make_btf(&[&[0, 31 << 24, 0]], STRINGS));
+    assert_eq!(result.unwrap_err().to_string(), "Unknown BTF type kind 31");
+}
+
+#[test]
+fn verify_corpus() {
+    // Check that structures used by exports described by the BTF are checked for the names and
+    // order of their members.
+    let mut btf = Btf::new();
+    assert_ok!(btf.load_bytes("vmlinux.btf", &make_btf(TYPES, STRINGS)));
+
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int b ; int a ; }\n",
+            "bar void bar ( s#foo * )\n",
+            "baz void baz ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let verification = btf.verify(&syms);
+    assert_eq!(verification.num_checked_exports(), 1);
+    assert_eq!(verification.divergences().len(), 1);
+    let divergence = &verification.divergences()[0];
+    assert_eq!(divergence.type_name(), "s#foo");
+    assert_eq!(divergence.exports(), ["bar"]);
+    assert_eq!(divergence.members(), ["b", "a"]);
+    assert_eq!(divergence.btf_members(), ["a", "b"]);
+
+    let mut out = Vec::new();
+    assert_ok!(verification.write_report(&mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Type 's#foo' in 'test.symtypes' is inconsistent with BTF, used by 'bar'\n",
+            "  symtypes members: b, a\n",
+            "  BTF members: a, b\n", //
+        )
+    );
+
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; int b ; }\n",
+            "bar void bar ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert!(btf.verify(&syms).is_empty());
+}
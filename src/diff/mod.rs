@@ -1,10 +1,17 @@
 // Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
 // SPDX-License-Identifier: GPL-2.0-or-later
 
-use crate::MapIOErr;
+use crate::{json_string, run_workers, MapIOErr};
+use regex::Regex;
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
 use std::fmt::Display;
+use std::hash::Hash;
 use std::io::{prelude::*, BufWriter};
-use std::ops::{Index, IndexMut};
+use std::iter::zip;
+use std::mem;
+use std::ops::{Index, IndexMut, Range};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[cfg(test)]
 mod tests;
@@ -12,6 +19,257 @@ mod tests;
 // Implementation of the Myers diff algorithm:
 // Myers, E.W. An O(ND) difference algorithm and its variations. Algorithmica 1, 251--266 (1986).
 // https://doi.org/10.1007/BF01840446
+//
+// Inputs larger than `LINEAR_SPACE_THRESHOLD` are compared using the linear space refinement
+// described in the same paper, which recursively splits the inputs at the middle snake of an
+// optimal path, instead of recording all explored edit steps.
+//
+// When the number of explored edit steps exceeds the maximum cost specified in `DiffOptions`, the
+// algorithms give up and the differing part of the inputs is reported as removed and added as
+// a whole.
+//
+// The patience diff algorithm, as described by Bram Cohen, anchors the diff on lines which are
+// unique in both inputs and uses the Myers algorithm for the remaining parts.
+//
+// Inputs larger than `CHUNKED_THRESHOLD` can be split at lines unique in both inputs into chunks
+// of at least `CHUNK_SIZE` lines, which are then compared independently by multiple workers.
+//
+// When multiple minimal edit scripts exist, the algorithms may pick one which interleaves removed
+// and inserted lines or splits a block of lines at an awkward place. The resulting edit script is
+// therefore post-processed, similarly to the heuristics of Git, to slide groups of changes to
+// boundaries suggested by the indentation of the lines and to put removed lines before inserted
+// ones.
+
+/// An algorithm used to compute a diff.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Algorithm {
+    /// The Myers algorithm, which finds a minimal diff.
+    #[default]
+    Myers,
+    /// The patience algorithm, which aligns the inputs on lines that are unique in both of them.
+    /// It produces more readable diffs for inputs with many repeated lines, such as structure
+    /// bodies, at the cost of not always being minimal.
+    Patience,
+}
+
+impl Algorithm {
+    /// Returns the algorithm with the specified name, `myers` or `patience`, or [`None`] if the
+    /// name is not recognized.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "myers" => Some(Self::Myers),
+            "patience" => Some(Self::Patience),
+            _ => None,
+        }
+    }
+}
+
+/// An output format of a diff written by [`write_with()`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+    /// The unified format with 3 lines of context, as produced by `diff -u`.
+    #[default]
+    Unified,
+    /// The normal format without context, as produced by `diff`.
+    Normal,
+    /// The ed script format, as produced by `diff -e`.
+    Ed,
+    /// The complete inputs in two columns, with markers of changed lines, similar to the output
+    /// produced by `diff -y`.
+    SideBySide,
+    /// A JSON array of hunks with 3 lines of context. Each hunk is an object with the positions
+    /// `pos_a`, `len_a`, `pos_b` and `len_b`, and an array of `lines`, each described by its
+    /// `type`, which is `context`, `removed` or `added`, and its `text`. A hunk containing
+    /// detected moves also has an array of `moves`, each with the positions `pos_a` and `pos_b`
+    /// and the `num_lines` of the moved block.
+    Json,
+}
+
+impl Format {
+    /// Returns the format with the specified name, `unified`, `normal`, `ed`, `side-by-side` or
+    /// `json`, or [`None`] if the name is not recognized.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unified" => Some(Self::Unified),
+            "normal" => Some(Self::Normal),
+            "ed" => Some(Self::Ed),
+            "side-by-side" => Some(Self::SideBySide),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Options to control computing of a diff by [`hunks_with()`], [`unified_with()`] and
+/// [`write_with()`].
+///
+/// The options are created with their default values by [`DiffOptions::new()`] and can be
+/// adjusted using the builder methods:
+///
+/// ```
+/// use suse_kabi_tools::diff::{Algorithm, DiffOptions};
+///
+/// let options = DiffOptions::new().algorithm(Algorithm::Patience).max_cost(1000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DiffOptions {
+    algorithm: Algorithm,
+    max_cost: Option<usize>,
+    format: Format,
+    width: usize,
+    labels: Option<(String, String)>,
+    max_lines: Option<usize>,
+    ignore_patterns: Vec<Regex>,
+    detect_moves: bool,
+    merge_gap: usize,
+    num_workers: Option<i32>,
+    color: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::default(),
+            max_cost: None,
+            format: Format::default(),
+            width: 130,
+            labels: None,
+            max_lines: None,
+            ignore_patterns: Vec::new(),
+            detect_moves: false,
+            merge_gap: 2 * CONTEXT_SIZE,
+            num_workers: None,
+            color: false,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Creates new options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the algorithm used to compute the diff.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the maximum cost, the number of removed and added lines, which the Myers algorithm
+    /// explores before it gives up. The differing part of the inputs is then reported as
+    /// completely removed and added, instead of spending an unbounded time on degenerate inputs.
+    /// By default, the cost is unlimited.
+    pub fn max_cost(mut self, max_cost: usize) -> Self {
+        self.max_cost = Some(max_cost);
+        self
+    }
+
+    /// Sets the output format used by [`write_with()`].
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the total width of a line in the side-by-side format. The default is 130 columns.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets labels of the old and new input. When set, a diff in the unified format is preceded
+    /// by the `--- <old>` and `+++ <new>` header lines, which makes it a valid patch.
+    pub fn labels<S: Into<String>>(mut self, old: S, new: S) -> Self {
+        self.labels = Some((old.into(), new.into()));
+        self
+    }
+
+    /// Sets the maximum number of lines written for a diff in a text format. The remaining lines
+    /// are omitted and replaced by a `... diff truncated (N more lines)` line. The limit does not
+    /// apply to the JSON format. By default, the output is not limited.
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines);
+        self
+    }
+
+    /// Adds a pattern of lines to ignore. Lines matching any of the patterns are dropped from both
+    /// inputs before they are compared, and so they are never reported as changed and do not
+    /// appear in the output. Line numbers in the output then refer to the remaining lines.
+    pub fn ignore_pattern(mut self, pattern: Regex) -> Self {
+        self.ignore_patterns.push(pattern);
+        self
+    }
+
+    /// Sets whether to detect blocks of removed lines which reappear verbatim elsewhere in the new
+    /// input. Such moves are recorded in the hunks and annotated in the unified and JSON formats,
+    /// instead of being shown only as an unrelated removal and addition. The default is `false`.
+    pub fn detect_moves(mut self, detect_moves: bool) -> Self {
+        self.detect_moves = detect_moves;
+        self
+    }
+
+    /// Sets the maximum number of unchanged lines between two changes which are still shown in one
+    /// hunk, in the unified and JSON formats. Changes separated by more lines are shown in separate
+    /// hunks. The default is 6, the context needed both after a change and before the next one.
+    /// A gap smaller than 3 lines behaves as 3, since hunks cannot share context lines, and
+    /// [`usize::MAX`] produces a single hunk.
+    pub fn merge_gap(mut self, merge_gap: usize) -> Self {
+        self.merge_gap = merge_gap;
+        self
+    }
+
+    /// Sets the number of workers to compare large inputs simultaneously. When set, inputs with
+    /// more than 100000 lines combined are split at lines unique in both inputs into chunks, which
+    /// are compared independently. The chunks do not depend on the number of workers, and so
+    /// neither does the diff, but it is not necessarily minimal. By default, inputs are not split.
+    pub fn num_workers(mut self, num_workers: i32) -> Self {
+        self.num_workers = Some(num_workers);
+        self
+    }
+
+    /// Sets whether removed and added lines in the unified format are colored in red and green by
+    /// ANSI escape sequences, for showing the diff on a terminal. The default is `false`.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Returns the label of the old input, if set.
+    pub fn old_label(&self) -> Option<&str> {
+        self.labels.as_ref().map(|(old, _)| old.as_str())
+    }
+
+    /// Returns the label of the new input, if set.
+    pub fn new_label(&self) -> Option<&str> {
+        self.labels.as_ref().map(|(_, new)| new.as_str())
+    }
+
+    /// Returns the maximum number of unchanged lines merged into one hunk, adjusted so that hunks
+    /// do not share context lines.
+    fn effective_merge_gap(&self) -> usize {
+        self.merge_gap.max(CONTEXT_SIZE)
+    }
+}
+
+/// The combined length of the inputs above which the Myers algorithm switches to its linear space
+/// variant.
+const LINEAR_SPACE_THRESHOLD: usize = 10000;
+
+/// The combined length of the inputs above which they are split into chunks, if enabled by
+/// [`DiffOptions::num_workers()`].
+const CHUNKED_THRESHOLD: usize = 100000;
+
+/// The minimum combined length of chunks of split inputs.
+const CHUNK_SIZE: usize = 10000;
+
+/// The number of context lines surrounding changes in a hunk.
+const CONTEXT_SIZE: usize = 3;
+
+/// ANSI escape sequences which color removed and added lines and reset the color, enabled by
+/// [`DiffOptions::color()`].
+const COLOR_REMOVED: &str = "\x1b[31m";
+const COLOR_ADDED: &str = "\x1b[32m";
+const COLOR_RESET: &str = "\x1b[0m";
 
 /// A step in the edit script.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -43,12 +301,21 @@ impl<T> IndexMut<isize> for IVec<T> {
     }
 }
 
+/// A step recorded in an [`EditChain`]. A run of kept lines is recorded as a single step, which
+/// keeps the chain small when the inputs are mostly identical.
+#[derive(Clone, Copy)]
+enum ChainStep {
+    KeepA { start: usize, len: usize },
+    RemoveA(usize),
+    InsertB(usize),
+}
+
 /// An edit step + an identifier of the previous steps leading to the current point during the edit
 /// graph traversal.
 #[derive(Clone, Copy)]
 struct EditChain {
     prev: usize,
-    step: Edit,
+    step: ChainStep,
 }
 
 /// A state of a diagonal during the edit graph traversal.
@@ -60,7 +327,47 @@ struct DiagonalState {
 
 /// Compares `a` with `b` and returns an edit script describing how to transform the former to the
 /// latter.
-fn myers<T: AsRef<str> + PartialEq>(a: &[T], b: &[T]) -> EditScript {
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> EditScript {
+    myers_limited(a, b, usize::MAX)
+}
+
+/// Compares `a` with `b` and returns an edit script describing how to transform the former to the
+/// latter. If the cost of the edit script exceeds `max_cost`, a coarse edit script is returned.
+fn myers_limited<T: PartialEq>(a: &[T], b: &[T], max_cost: usize) -> EditScript {
+    // Trim the common prefix and suffix first, changed types typically differ only in a few lines.
+    let (prefix, suffix) = common_affixes(a, b);
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mid_script = if mid_a.len() + mid_b.len() > LINEAR_SPACE_THRESHOLD {
+        myers_linear(mid_a, mid_b, max_cost)
+    } else {
+        myers_greedy(mid_a, mid_b, max_cost)
+    };
+
+    let mut edit_script = EditScript::with_capacity(prefix + mid_script.len() + suffix);
+    edit_script.extend((0..prefix).map(Edit::KeepA));
+    extend_with_offset(&mut edit_script, mid_script, (prefix, prefix));
+    edit_script.extend((a.len() - suffix..a.len()).map(Edit::KeepA));
+    edit_script
+}
+
+/// Appends steps of `sub_script`, which was computed for sub-slices of the inputs at the offsets
+/// `(off_a, off_b)`, to `edit_script`.
+fn extend_with_offset(
+    edit_script: &mut EditScript,
+    sub_script: EditScript,
+    (off_a, off_b): (usize, usize),
+) {
+    edit_script.extend(sub_script.into_iter().map(|edit| match edit {
+        Edit::KeepA(index_a) => Edit::KeepA(off_a + index_a),
+        Edit::RemoveA(index_a) => Edit::RemoveA(off_a + index_a),
+        Edit::InsertB(index_b) => Edit::InsertB(off_b + index_b),
+    }));
+}
+
+/// Compares `a` with `b` using the basic greedy Myers algorithm, which records all explored edit
+/// steps, and returns an edit script describing how to transform the former to the latter.
+fn myers_greedy<T: PartialEq>(a: &[T], b: &[T], max_cost: usize) -> EditScript {
     let max = a.len() + b.len();
     let mut v = IVec(vec![
         DiagonalState {
@@ -74,6 +381,11 @@ fn myers<T: AsRef<str> + PartialEq>(a: &[T], b: &[T]) -> EditScript {
     let mut edit_chains = Vec::new();
 
     for d in 0..(max as isize + 1) {
+        if d as usize > max_cost {
+            let mut edit_script = EditScript::new();
+            coarse_range(a, b, (0, 0), &mut edit_script);
+            return edit_script;
+        }
         for k in (-d..d + 1).step_by(2) {
             // Determine where to progress, insert from `b` or remove from `a`.
             let insert_b = k == -d || (k != d && v[k - 1].x < v[k + 1].x);
@@ -90,20 +402,26 @@ fn myers<T: AsRef<str> + PartialEq>(a: &[T], b: &[T]) -> EditScript {
                 edit_chains.push(EditChain {
                     prev: edit_index,
                     step: if insert_b {
-                        Edit::InsertB(y - 1)
+                        ChainStep::InsertB(y - 1)
                     } else {
-                        Edit::RemoveA(x - 1)
+                        ChainStep::RemoveA(x - 1)
                     },
                 });
                 edit_index = edit_chains.len() - 1;
             }
 
-            // Look for a snake.
+            // Look for a snake and record it as a single run.
+            let snake_start = x;
             while x < a.len() && y < b.len() && a[x] == b[y] {
                 (x, y) = (x + 1, y + 1);
+            }
+            if x > snake_start {
                 edit_chains.push(EditChain {
                     prev: edit_index,
-                    step: Edit::KeepA(x - 1),
+                    step: ChainStep::KeepA {
+                        start: snake_start,
+                        len: x - snake_start,
+                    },
                 });
                 edit_index = edit_chains.len() - 1;
             }
@@ -114,7 +432,13 @@ fn myers<T: AsRef<str> + PartialEq>(a: &[T], b: &[T]) -> EditScript {
                 let mut edit_script = EditScript::new();
                 while edit_index != usize::MAX {
                     let edit_chain = edit_chains[edit_index];
-                    edit_script.push(edit_chain.step);
+                    match edit_chain.step {
+                        ChainStep::KeepA { start, len } => {
+                            edit_script.extend((start..start + len).rev().map(Edit::KeepA))
+                        }
+                        ChainStep::RemoveA(index_a) => edit_script.push(Edit::RemoveA(index_a)),
+                        ChainStep::InsertB(index_b) => edit_script.push(Edit::InsertB(index_b)),
+                    }
                     edit_index = edit_chain.prev;
                 }
                 edit_script.reverse();
@@ -126,48 +450,1414 @@ fn myers<T: AsRef<str> + PartialEq>(a: &[T], b: &[T]) -> EditScript {
     unreachable!();
 }
 
-/// Writes a single diff hunk to the provided output stream.
-fn write_hunk<W: Write>(
-    hunk_pos_a: usize,
-    hunk_len_a: usize,
-    hunk_pos_b: usize,
-    hunk_len_b: usize,
-    hunk_data: &[String],
+/// A line of a diff hunk.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(tag = "type", content = "text", rename_all = "lowercase")
+)]
+pub enum HunkLine<'a, T> {
+    /// A line present in both inputs.
+    Context(&'a T),
+    /// A line present only in the first input.
+    Removed(&'a T),
+    /// A line present only in the second input.
+    Added(&'a T),
+}
+
+/// A block of lines removed from one place of the first input and added verbatim at another place
+/// of the second input.
+///
+/// The positions are 1-based line numbers, as in the unified format.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Move {
+    pos_a: usize,
+    pos_b: usize,
+    num_lines: usize,
+}
+
+impl Move {
+    /// Returns the position of the block in the first input.
+    pub fn pos_a(&self) -> usize {
+        self.pos_a
+    }
+
+    /// Returns the position of the block in the second input.
+    pub fn pos_b(&self) -> usize {
+        self.pos_b
+    }
+
+    /// Returns the number of lines of the block.
+    pub fn num_lines(&self) -> usize {
+        self.num_lines
+    }
+}
+
+/// A group of changes between two inputs, along with the surrounding context lines.
+///
+/// The positions are 1-based line numbers, as in the unified format.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Hunk<'a, T> {
+    pos_a: usize,
+    len_a: usize,
+    pos_b: usize,
+    len_b: usize,
+    lines: Vec<HunkLine<'a, T>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    moves: Vec<Move>,
+}
+
+impl<'a, T> Hunk<'a, T> {
+    /// Returns the position of the hunk in the first input.
+    pub fn pos_a(&self) -> usize {
+        self.pos_a
+    }
+
+    /// Returns the number of lines of the first input covered by the hunk.
+    pub fn len_a(&self) -> usize {
+        self.len_a
+    }
+
+    /// Returns the position of the hunk in the second input.
+    pub fn pos_b(&self) -> usize {
+        self.pos_b
+    }
+
+    /// Returns the number of lines of the second input covered by the hunk.
+    pub fn len_b(&self) -> usize {
+        self.len_b
+    }
+
+    /// Returns the lines of the hunk.
+    pub fn lines(&self) -> &[HunkLine<'a, T>] {
+        &self.lines
+    }
+
+    /// Returns the moves whose removed or added block lies in the hunk. Moves are detected only
+    /// if enabled by [`DiffOptions::detect_moves()`].
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+}
+
+/// Compares `a` with `b` using the linear space variant of the Myers algorithm and returns an edit
+/// script describing how to transform the former to the latter.
+fn myers_linear<T: PartialEq>(a: &[T], b: &[T], max_cost: usize) -> EditScript {
+    let mut edit_script = EditScript::new();
+    myers_linear_range(a, b, (0, 0), max_cost, &mut edit_script);
+    edit_script
+}
+
+/// Compares `a` with `b`, which are sub-slices of the inputs at the offsets `(off_a, off_b)`, using
+/// the linear space variant of the Myers algorithm and appends the steps to `edit_script`.
+fn myers_linear_range<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    (off_a, off_b): (usize, usize),
+    max_cost: usize,
+    edit_script: &mut EditScript,
+) {
+    let (prefix, suffix) = common_affixes(a, b);
+    edit_script.extend((off_a..off_a + prefix).map(Edit::KeepA));
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (mid_off_a, mid_off_b) = (off_a + prefix, off_b + prefix);
+
+    if mid_a.is_empty() {
+        edit_script.extend((mid_off_b..mid_off_b + mid_b.len()).map(Edit::InsertB));
+    } else if mid_b.is_empty() {
+        edit_script.extend((mid_off_a..mid_off_a + mid_a.len()).map(Edit::RemoveA));
+    } else {
+        // Split the inputs at a point on an optimal path and diff both parts separately.
+        match middle_snake(mid_a, mid_b, max_cost) {
+            Some((x, y)) => {
+                myers_linear_range(
+                    &mid_a[..x],
+                    &mid_b[..y],
+                    (mid_off_a, mid_off_b),
+                    max_cost,
+                    edit_script,
+                );
+                myers_linear_range(
+                    &mid_a[x..],
+                    &mid_b[y..],
+                    (mid_off_a + x, mid_off_b + y),
+                    max_cost,
+                    edit_script,
+                );
+            }
+            None => coarse_range(mid_a, mid_b, (mid_off_a, mid_off_b), edit_script),
+        }
+    }
+
+    edit_script.extend((off_a + a.len() - suffix..off_a + a.len()).map(Edit::KeepA));
+}
+
+/// Finds the middle snake of an optimal path from `(0,0)` to `(a.len(),b.len())` by searching from
+/// both ends simultaneously, and returns the point where the snake starts, or [`None`] if the cost
+/// of the path exceeds `max_cost`.
+///
+/// The inputs must be non-empty and must differ in their first and last items, which guarantees
+/// that the returned point is neither the start nor the end of the path.
+fn middle_snake<T: PartialEq>(a: &[T], b: &[T], max_cost: usize) -> Option<(usize, usize)> {
+    let (n, m) = (a.len(), b.len());
+    let delta = n as isize - m as isize;
+    let odd = delta % 2 != 0;
+    let max_d = (n + m).div_ceil(2) as isize;
+
+    // The forward search records the furthest reaching `x` on each diagonal `k = x - y`. The
+    // backward search operates on the reversed inputs, where the diagonal `k` corresponds to the
+    // forward diagonal `delta - k`.
+    let mut vf = IVec(vec![0usize; 2 * max_d as usize + 3]);
+    let mut vb = IVec(vec![0usize; 2 * max_d as usize + 3]);
+
+    for d in 0..(max_d + 1) {
+        // A path found by the forward search in this round has the cost `2 * d - 1`.
+        if d > 0 && (2 * d - 1) as usize > max_cost {
+            return None;
+        }
+
+        for k in (-d..d + 1).step_by(2) {
+            let mut x = if k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
+                vf[k + 1]
+            } else {
+                vf[k - 1] + 1
+            };
+            let mut y = x.wrapping_add_signed(-k);
+            let (start_x, start_y) = (x, y);
+            while x < n && y < m && a[x] == b[y] {
+                (x, y) = (x + 1, y + 1);
+            }
+            vf[k] = x;
+
+            // Check for an overlap with a path of the previous backward search.
+            let rk = delta - k;
+            if odd && (-(d - 1)..d).contains(&rk) && vf[k] + vb[rk] >= n {
+                return Some((start_x, start_y));
+            }
+        }
+
+        // A path found by the backward search in this round has the cost `2 * d`.
+        if (2 * d) as usize > max_cost {
+            return None;
+        }
+
+        for k in (-d..d + 1).step_by(2) {
+            let mut x = if k == -d || (k != d && vb[k - 1] < vb[k + 1]) {
+                vb[k + 1]
+            } else {
+                vb[k - 1] + 1
+            };
+            let mut y = x.wrapping_add_signed(-k);
+            let (start_x, start_y) = (x, y);
+            while x < n && y < m && a[n - 1 - x] == b[m - 1 - y] {
+                (x, y) = (x + 1, y + 1);
+            }
+            vb[k] = x;
+
+            // Check for an overlap with a path of the current forward search.
+            let fk = delta - k;
+            if !odd && (-d..d + 1).contains(&fk) && vb[k] + vf[fk] >= n {
+                return Some((n - start_x, m - start_y));
+            }
+        }
+    }
+    unreachable!();
+}
+
+/// Appends steps to `edit_script` which keep the common prefix and suffix of `a` and `b`, which are
+/// sub-slices of the inputs at the offsets `(off_a, off_b)`, and replace the rest of `a` with the
+/// rest of `b` as a whole.
+fn coarse_range<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    (off_a, off_b): (usize, usize),
+    edit_script: &mut EditScript,
+) {
+    let (prefix, suffix) = common_affixes(a, b);
+    edit_script.extend((off_a..off_a + prefix).map(Edit::KeepA));
+    edit_script.extend((off_a + prefix..off_a + a.len() - suffix).map(Edit::RemoveA));
+    edit_script.extend((off_b + prefix..off_b + b.len() - suffix).map(Edit::InsertB));
+    edit_script.extend((off_a + a.len() - suffix..off_a + a.len()).map(Edit::KeepA));
+}
+
+/// Returns the lengths of the common prefix and suffix of `a` and `b`. The suffix is determined
+/// only in the part which is not covered by the prefix.
+fn common_affixes<T: PartialEq>(a: &[T], b: &[T]) -> (usize, usize) {
+    let prefix = zip(a, b).take_while(|(x, y)| x == y).count();
+    let suffix = zip(a[prefix..].iter().rev(), b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    (prefix, suffix)
+}
+
+/// Compares `a` with `b` using the patience algorithm and returns an edit script describing how to
+/// transform the former to the latter.
+fn patience<T: Eq + Hash>(a: &[T], b: &[T], max_cost: usize) -> EditScript {
+    let mut edit_script = EditScript::new();
+    patience_range(a, b, (0, 0), max_cost, &mut edit_script);
+    edit_script
+}
+
+/// Compares `a` with `b`, which are sub-slices of the inputs at the offsets `(off_a, off_b)`, using
+/// the patience algorithm and appends the steps to `edit_script`.
+fn patience_range<T: Eq + Hash>(
+    a: &[T],
+    b: &[T],
+    (off_a, off_b): (usize, usize),
+    max_cost: usize,
+    edit_script: &mut EditScript,
+) {
+    // Keep the common prefix and suffix.
+    let (prefix, suffix) = common_affixes(a, b);
+    edit_script.extend((off_a..off_a + prefix).map(Edit::KeepA));
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (mid_off_a, mid_off_b) = (off_a + prefix, off_b + prefix);
+
+    // Find lines which are unique in both inputs and select the longest sequence of them which
+    // appears in the same order in both inputs as anchors.
+    let anchors = unique_anchors_by(mid_a, mid_b, Some);
+    if anchors.is_empty() {
+        // Fall back to the Myers algorithm.
+        let fallback_script = myers_limited(mid_a, mid_b, max_cost);
+        extend_with_offset(edit_script, fallback_script, (mid_off_a, mid_off_b));
+    } else {
+        // Diff the parts between the anchors.
+        let (mut pos_a, mut pos_b) = (0, 0);
+        for (anchor_a, anchor_b) in anchors {
+            patience_range(
+                &mid_a[pos_a..anchor_a],
+                &mid_b[pos_b..anchor_b],
+                (mid_off_a + pos_a, mid_off_b + pos_b),
+                max_cost,
+                edit_script,
+            );
+            edit_script.push(Edit::KeepA(mid_off_a + anchor_a));
+            (pos_a, pos_b) = (anchor_a + 1, anchor_b + 1);
+        }
+        patience_range(
+            &mid_a[pos_a..],
+            &mid_b[pos_b..],
+            (mid_off_a + pos_a, mid_off_b + pos_b),
+            max_cost,
+            edit_script,
+        );
+    }
+
+    edit_script.extend((off_a + a.len() - suffix..off_a + a.len()).map(Edit::KeepA));
+}
+
+/// Returns the longest sequence of index pairs `(index_a, index_b)` of lines whose keys are unique
+/// in both `a` and `b` and which appear in the same order in both inputs. Lines for which the `key`
+/// function returns [`None`] are never selected.
+fn unique_anchors_by<'a, T, K: Eq + Hash, F: Fn(&'a T) -> Option<K>>(
+    a: &'a [T],
+    b: &'a [T],
+    key: F,
+) -> Vec<(usize, usize)> {
+    // Count the occurrences of each key and remember its position in both inputs.
+    let mut occurrences: FxHashMap<K, (usize, usize, usize, usize)> = FxHashMap::default();
+    for (index_a, line) in a.iter().enumerate() {
+        if let Some(line_key) = key(line) {
+            let entry = occurrences.entry(line_key).or_insert((0, 0, 0, 0));
+            entry.0 += 1;
+            entry.1 = index_a;
+        }
+    }
+    for (index_b, line) in b.iter().enumerate() {
+        if let Some(entry) = key(line).and_then(|line_key| occurrences.get_mut(&line_key)) {
+            entry.2 += 1;
+            entry.3 = index_b;
+        }
+    }
+    let mut pairs: Vec<_> = occurrences
+        .into_values()
+        .filter(|&(count_a, _, count_b, _)| count_a == 1 && count_b == 1)
+        .map(|(_, index_a, _, index_b)| (index_a, index_b))
+        .collect();
+    pairs.sort();
+
+    // Find the longest increasing subsequence of the positions in `b` by patience sorting. Each
+    // pile records the index of its top pair, each pair records its predecessor.
+    let mut piles: Vec<usize> = Vec::new();
+    let mut predecessors = vec![usize::MAX; pairs.len()];
+    for (i, &(_, index_b)) in pairs.iter().enumerate() {
+        let pile = piles.partition_point(|&top| pairs[top].1 < index_b);
+        if pile > 0 {
+            predecessors[i] = piles[pile - 1];
+        }
+        if pile == piles.len() {
+            piles.push(i);
+        } else {
+            piles[pile] = i;
+        }
+    }
+
+    let mut anchors = Vec::new();
+    let mut i = piles.last().copied().unwrap_or(usize::MAX);
+    while i != usize::MAX {
+        anchors.push(pairs[i]);
+        i = predecessors[i];
+    }
+    anchors.reverse();
+    anchors
+}
+
+/// Post-processes an edit script describing how to transform `a` to `b` to make the resulting diff
+/// more readable.
+///
+/// Each group of only removed or only inserted lines is slid over equal surrounding lines to the
+/// position where it forms the most self-contained block, as measured by the `indentation` of its
+/// lines, and as far down as possible among equally good positions. In each group of both removed
+/// and inserted lines, the removed lines are put before the inserted ones.
+fn tidy_edit_script<T: PartialEq, F: Fn(&T) -> usize>(
+    a: &[T],
+    b: &[T],
+    mut edit_script: EditScript,
+    indentation: F,
+) -> EditScript {
+    let is_keep = |edit: &Edit| matches!(edit, Edit::KeepA(_));
+
+    // Slide the groups of only removed or only inserted lines.
+    let mut group_begin = 0;
+    while group_begin < edit_script.len() {
+        if is_keep(&edit_script[group_begin]) {
+            group_begin += 1;
+            continue;
+        }
+        let mut group_end = group_begin;
+        while group_end < edit_script.len() && !is_keep(&edit_script[group_end]) {
+            group_end += 1;
+        }
+        let removal = matches!(edit_script[group_begin], Edit::RemoveA(_));
+        group_begin = if edit_script[group_begin..group_end]
+            .iter()
+            .all(|edit| matches!(edit, Edit::RemoveA(_)) == removal)
+        {
+            slide_group(a, b, &mut edit_script, group_begin, group_end, &indentation)
+        } else {
+            group_end
+        };
+    }
+
+    // Put removed lines before inserted ones. The sort is stable and so it keeps the order of
+    // the lines otherwise.
+    for group in edit_script.chunk_by_mut(|x, y| is_keep(x) == is_keep(y)) {
+        group.sort_by_key(|edit| matches!(edit, Edit::InsertB(_)));
+    }
+
+    edit_script
+}
+
+/// Slides a group of only removed or only inserted lines, found at `group_begin..group_end` in an
+/// edit script, to its most readable position, as described by [`tidy_edit_script()`]. Returns the
+/// new end of the group in the edit script.
+fn slide_group<T: PartialEq, F: Fn(&T) -> usize>(
+    a: &[T],
+    b: &[T],
+    edit_script: &mut EditScript,
+    group_begin: usize,
+    group_end: usize,
+    indentation: &F,
+) -> usize {
+    let is_keep = |edit: &Edit| matches!(edit, Edit::KeepA(_));
+    let (lines, start) = match edit_script[group_begin] {
+        Edit::RemoveA(index_a) => (a, index_a),
+        Edit::InsertB(index_b) => (b, index_b),
+        Edit::KeepA(_) => unreachable!(),
+    };
+    let len = group_end - group_begin;
+
+    // Determine how far the group can slide up and down, which is possible as long as the kept
+    // line next to it is equal to the line at the opposite end of the group.
+    let mut up = 0;
+    while up < group_begin
+        && is_keep(&edit_script[group_begin - up - 1])
+        && lines[start - up - 1] == lines[start + len - up - 1]
+    {
+        up += 1;
+    }
+    let mut down = 0;
+    while group_end + down < edit_script.len()
+        && is_keep(&edit_script[group_end + down])
+        && lines[start + down] == lines[start + len + down]
+    {
+        down += 1;
+    }
+    if up == 0 && down == 0 {
+        return group_end;
+    }
+
+    // Pick the position with the smallest difference between the indentation of the first and
+    // last line of the group and the minimum indentation in the group, which is zero when the
+    // group forms a block, such as a complete nested structure. The minimum is tracked in
+    // a sliding window of increasing indentations.
+    let first = start - up;
+    let indents: Vec<_> = lines[first..start + len + down]
+        .iter()
+        .map(indentation)
+        .collect();
+    let mut window = VecDeque::new();
+    let (mut best_offset, mut best_penalty) = (0, usize::MAX);
+    for (i, &indent) in indents.iter().enumerate() {
+        while window.back().is_some_and(|&j| indents[j] >= indent) {
+            window.pop_back();
+        }
+        window.push_back(i);
+        if i + 1 < len {
+            continue;
+        }
+        let offset = i + 1 - len;
+        if window[0] < offset {
+            window.pop_front();
+        }
+        let min = indents[window[0]];
+        let penalty = indents[offset] - min + indents[i] - min;
+        if penalty <= best_penalty {
+            (best_offset, best_penalty) = (offset, penalty);
+        }
+    }
+
+    // Rewrite the part of the edit script covering the group and the lines it can slide over.
+    let region = group_begin - up..group_end + down;
+    let new_group_begin = region.start + best_offset;
+    if matches!(edit_script[group_begin], Edit::RemoveA(_)) {
+        for (i, edit) in edit_script[region].iter_mut().enumerate() {
+            *edit = if (best_offset..best_offset + len).contains(&i) {
+                Edit::RemoveA(first + i)
+            } else {
+                Edit::KeepA(first + i)
+            };
+        }
+    } else {
+        let first_keep = match edit_script[region.start] {
+            Edit::KeepA(index_a) => index_a,
+            _ => match edit_script[group_end] {
+                Edit::KeepA(index_a) => index_a,
+                _ => unreachable!(),
+            },
+        };
+        for (i, edit) in edit_script[region].iter_mut().enumerate() {
+            *edit = if i < best_offset {
+                Edit::KeepA(first_keep + i)
+            } else if i < best_offset + len {
+                Edit::InsertB(first + i)
+            } else {
+                Edit::KeepA(first_keep + i - len)
+            };
+        }
+    }
+    new_group_begin + len
+}
+
+/// Returns the indentation of a line, counting a tab as 8 columns.
+fn indentation<T: Display>(line: &T) -> usize {
+    line.to_string()
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 8 } else { 1 })
+        .sum()
+}
+
+/// Compares `a` with `b` using the Myers algorithm and returns a post-processed edit script
+/// describing how to transform the former to the latter.
+fn edit_script<T: PartialEq>(a: &[T], b: &[T]) -> EditScript {
+    tidy_edit_script(a, b, myers(a, b), |_| 0)
+}
+
+/// Writes a single diff hunk to the provided output stream. Removed and added lines are colored if
+/// `color` is set.
+fn write_hunk<T: Display, W: Write>(
+    hunk: &Hunk<T>,
+    color: bool,
     writer: &mut BufWriter<W>,
 ) -> Result<(), crate::Error> {
     let err_desc = "Failed to write a diff hunk";
+    let (removed, added, reset) = if color {
+        (COLOR_REMOVED, COLOR_ADDED, COLOR_RESET)
+    } else {
+        ("", "", "")
+    };
 
-    writeln!(
+    write!(
         writer,
         "@@ -{},{} +{},{} @@",
-        hunk_pos_a, hunk_len_a, hunk_pos_b, hunk_len_b
+        hunk.pos_a, hunk.len_a, hunk.pos_b, hunk.len_b
     )
     .map_io_err(err_desc)?;
-    for hunk_str in hunk_data {
-        writeln!(writer, "{}", hunk_str).map_io_err(err_desc)?;
+    for (i, m) in hunk.moves.iter().enumerate() {
+        write!(
+            writer,
+            "{} moved -{},{} +{},{}",
+            if i > 0 { ";" } else { "" },
+            m.pos_a,
+            m.num_lines,
+            m.pos_b,
+            m.num_lines
+        )
+        .map_io_err(err_desc)?;
+    }
+    writeln!(writer).map_io_err(err_desc)?;
+    for line in &hunk.lines {
+        match line {
+            HunkLine::Context(line) => writeln!(writer, " {}", line),
+            HunkLine::Removed(line) => writeln!(writer, "{}-{}{}", removed, line, reset),
+            HunkLine::Added(line) => writeln!(writer, "{}+{}{}", added, line, reset),
+        }
+        .map_io_err(err_desc)?;
     }
     Ok(())
 }
 
 /// Compares `a` with `b` and writes their unified diff to the provided output stream.
-pub fn unified<T: AsRef<str> + PartialEq + Display, W: Write>(
+///
+/// The items are compared by [`PartialEq`] and each item is written as a line using its
+/// [`Display`] implementation.
+pub fn unified<T: PartialEq + Display, W: Write>(
     a: &[T],
     b: &[T],
     writer: W,
 ) -> Result<(), crate::Error> {
     let mut writer = BufWriter::new(writer);
+    for hunk in hunks(a, b) {
+        write_hunk(&hunk, false, &mut writer)?;
+    }
+    Ok(())
+}
+
+/// Compares `a` with `b`, using the specified options, and writes their unified diff to the
+/// provided output stream.
+pub fn unified_with<T: Eq + Hash + Display + Sync, W: Write>(
+    a: &[T],
+    b: &[T],
+    options: &DiffOptions,
+    writer: W,
+) -> Result<(), crate::Error> {
+    let mut writer = TruncatingWriter::new(writer, options.max_lines);
+    write_unified(&hunks_with(a, b, options), options, &mut writer)?;
+    writer.finish()
+}
+
+/// A writer which passes through only the specified number of lines and counts the remaining ones,
+/// to report them by [`TruncatingWriter::finish()`].
+struct TruncatingWriter<W: Write> {
+    inner: W,
+    remaining: usize,
+    skipped: usize,
+}
+
+impl<W: Write> TruncatingWriter<W> {
+    /// Creates a new writer which passes up to `max_lines` lines to `inner`, or all lines if
+    /// `max_lines` is [`None`].
+    fn new(inner: W, max_lines: Option<usize>) -> Self {
+        Self {
+            inner,
+            remaining: max_lines.unwrap_or(usize::MAX),
+            skipped: 0,
+        }
+    }
+
+    /// Writes a trailer about the omitted lines, if any.
+    fn finish(mut self) -> Result<(), crate::Error> {
+        let err_desc = "Failed to write a diff";
+        if self.skipped > 0 {
+            writeln!(
+                self.inner,
+                "... diff truncated ({} more lines)",
+                self.skipped
+            )
+            .map_io_err(err_desc)?;
+        }
+        self.inner.flush().map_io_err(err_desc)
+    }
+}
+
+impl<W: Write> Write for TruncatingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // Pass through data up to the end of the last allowed line.
+        let mut end = buf.len();
+        if self.remaining == 0 {
+            end = 0;
+        } else {
+            for (i, _) in buf.iter().enumerate().filter(|&(_, &byte)| byte == b'\n') {
+                self.remaining -= 1;
+                if self.remaining == 0 {
+                    end = i + 1;
+                    break;
+                }
+            }
+        }
+        self.inner.write_all(&buf[..end])?;
+        self.skipped += buf[end..].iter().filter(|&&byte| byte == b'\n').count();
+        Ok(buf.len())
+    }
 
-    // Diff the two inputs and calculate the edit script.
-    let edit_script = myers(a, b);
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes hunks in the unified format, preceded by the header lines if labels are set in the
+/// options and there is at least one hunk, to the provided output stream.
+fn write_unified<T: Display, W: Write>(
+    hunks: &[Hunk<T>],
+    options: &DiffOptions,
+    writer: W,
+) -> Result<(), crate::Error> {
+    let mut writer = BufWriter::new(writer);
+    if let (Some((old, new)), false) = (&options.labels, hunks.is_empty()) {
+        writeln!(writer, "--- {}\n+++ {}", old, new).map_io_err("Failed to write a diff header")?;
+    }
+    for hunk in hunks {
+        write_hunk(hunk, options.color, &mut writer)?;
+    }
+    writer.flush().map_io_err("Failed to write a diff")
+}
 
+/// Compares `a` with `b`, using the specified options, and writes their diff in the format selected
+/// by [`DiffOptions::format()`] to the provided output stream.
+pub fn write_with<T: Eq + Hash + Display + Sync, W: Write>(
+    a: &[T],
+    b: &[T],
+    options: &DiffOptions,
+    writer: W,
+) -> Result<(), crate::Error> {
+    let (a, b) = (filter_ignored(a, options), filter_ignored(b, options));
+    write_edit_script(&a, &b, &edit_script_with(&a, &b, options), options, writer)
+}
+
+/// Returns references to the lines of `input` which do not match any ignore pattern specified in
+/// the options.
+fn filter_ignored<'a, T: Display>(input: &'a [T], options: &DiffOptions) -> Vec<&'a T> {
+    if options.ignore_patterns.is_empty() {
+        return input.iter().collect();
+    }
+    input
+        .iter()
+        .filter(|line| {
+            let line = line.to_string();
+            !options
+                .ignore_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(&line))
+        })
+        .collect()
+}
+
+/// Compares `a` with `b`, using the specified options, and writes their diff to the provided output
+/// stream, in the same way as [`write_with()`]. Additionally, lines with a key, as returned by the
+/// `key` function, that is unique in both inputs are aligned with each other, even if their content
+/// differs. This allows, for instance, to show a changed declaration of a structure member as one
+/// removed and one added line, instead of shifting the rest of the structure.
+pub fn write_anchored_with<'a, T, K, F, W>(
+    a: &'a [T],
+    b: &'a [T],
+    key: F,
+    options: &DiffOptions,
+    writer: W,
+) -> Result<(), crate::Error>
+where
+    T: Eq + Hash + Display + Sync,
+    K: Eq + Hash,
+    F: Fn(&'a T) -> Option<K>,
+    W: Write,
+{
+    let (a, b) = (filter_ignored(a, options), filter_ignored(b, options));
+
+    // Align the lines with matching keys and diff the parts between them.
+    let mut edit_script = EditScript::new();
+    let (mut pos_a, mut pos_b) = (0, 0);
+    let anchors = unique_anchors_by(&a, &b, |line: &&'a T| key(*line));
+    for (anchor_a, anchor_b) in anchors.into_iter().chain([(a.len(), b.len())]) {
+        let gap_script = edit_script_with(&a[pos_a..anchor_a], &b[pos_b..anchor_b], options);
+        extend_with_offset(&mut edit_script, gap_script, (pos_a, pos_b));
+        if anchor_a < a.len() {
+            if a[anchor_a] == b[anchor_b] {
+                edit_script.push(Edit::KeepA(anchor_a));
+            } else {
+                edit_script.push(Edit::RemoveA(anchor_a));
+                edit_script.push(Edit::InsertB(anchor_b));
+            }
+        }
+        (pos_a, pos_b) = (anchor_a + 1, anchor_b + 1);
+    }
+
+    write_edit_script(&a, &b, &edit_script, options, writer)
+}
+
+/// Writes the changes described by an edit script in the format selected by the options to the
+/// provided output stream.
+fn write_edit_script<T: PartialEq + Display, W: Write>(
+    a: &[T],
+    b: &[T],
+    edit_script: &EditScript,
+    options: &DiffOptions,
+    writer: W,
+) -> Result<(), crate::Error> {
+    if options.format == Format::Json {
+        return write_json_hunks(&annotated_hunks(a, b, edit_script, options), writer);
+    }
+
+    let mut writer = TruncatingWriter::new(writer, options.max_lines);
+    match options.format {
+        Format::Unified => write_unified(
+            &annotated_hunks(a, b, edit_script, options),
+            options,
+            &mut writer,
+        )?,
+        Format::Normal => write_normal(a, b, edit_script, &mut writer)?,
+        Format::Ed => write_ed(b, edit_script, &mut writer)?,
+        Format::SideBySide => write_side_by_side(a, b, edit_script, options.width, &mut writer)?,
+        Format::Json => unreachable!(),
+    }
+    writer.finish()
+}
+
+/// Writes hunks as a JSON array, in the form described by [`Format::Json`], to the provided output
+/// stream. No newline is written after the array, which allows embedding it in other JSON data.
+pub fn write_json_hunks<T: Display, W: Write>(
+    hunks: &[Hunk<T>],
+    writer: W,
+) -> Result<(), crate::Error> {
+    let mut writer = BufWriter::new(writer);
+    let err_desc = "Failed to write a diff";
+
+    write!(writer, "[").map_io_err(err_desc)?;
+    for (i, hunk) in hunks.iter().enumerate() {
+        write!(
+            writer,
+            "{}{{\"pos_a\":{},\"len_a\":{},\"pos_b\":{},\"len_b\":{},\"lines\":[",
+            if i > 0 { "," } else { "" },
+            hunk.pos_a,
+            hunk.len_a,
+            hunk.pos_b,
+            hunk.len_b
+        )
+        .map_io_err(err_desc)?;
+        for (j, line) in hunk.lines.iter().enumerate() {
+            let (kind, text) = match line {
+                HunkLine::Context(text) => ("context", text),
+                HunkLine::Removed(text) => ("removed", text),
+                HunkLine::Added(text) => ("added", text),
+            };
+            write!(
+                writer,
+                "{}{{\"type\":\"{}\",\"text\":{}}}",
+                if j > 0 { "," } else { "" },
+                kind,
+                json_string(&text.to_string())
+            )
+            .map_io_err(err_desc)?;
+        }
+        write!(writer, "]").map_io_err(err_desc)?;
+        if !hunk.moves.is_empty() {
+            write!(writer, ",\"moves\":[").map_io_err(err_desc)?;
+            for (j, m) in hunk.moves.iter().enumerate() {
+                write!(
+                    writer,
+                    "{}{{\"pos_a\":{},\"pos_b\":{},\"num_lines\":{}}}",
+                    if j > 0 { "," } else { "" },
+                    m.pos_a,
+                    m.pos_b,
+                    m.num_lines
+                )
+                .map_io_err(err_desc)?;
+            }
+            write!(writer, "]").map_io_err(err_desc)?;
+        }
+        write!(writer, "}}").map_io_err(err_desc)?;
+    }
+    write!(writer, "]").map_io_err(err_desc)?;
+
+    writer.flush().map_io_err(err_desc)
+}
+
+/// A group of adjacent changes, described by 0-based positions and lengths of the affected parts
+/// of both inputs.
+struct ChangeGroup {
+    start_a: usize,
+    len_a: usize,
+    start_b: usize,
+    len_b: usize,
+}
+
+/// Collects groups of adjacent removed and inserted lines from an edit script.
+fn change_groups(edit_script: &EditScript) -> Vec<ChangeGroup> {
+    let (mut pos_a, mut pos_b) = (0, 0);
+    let mut groups: Vec<ChangeGroup> = Vec::new();
+    let mut in_group = false;
+
+    for edit in edit_script {
+        if !matches!(edit, Edit::KeepA(_)) && !in_group {
+            groups.push(ChangeGroup {
+                start_a: pos_a,
+                len_a: 0,
+                start_b: pos_b,
+                len_b: 0,
+            });
+        }
+        in_group = !matches!(edit, Edit::KeepA(_));
+
+        match edit {
+            Edit::KeepA(_) => {
+                pos_a += 1;
+                pos_b += 1;
+            }
+            Edit::RemoveA(_) => {
+                groups.last_mut().unwrap().len_a += 1;
+                pos_a += 1;
+            }
+            Edit::InsertB(_) => {
+                groups.last_mut().unwrap().len_b += 1;
+                pos_b += 1;
+            }
+        }
+    }
+    groups
+}
+
+/// Formats a range of lines at the 0-based position `start` as a 1-based line number, or as
+/// a comma-separated pair of the first and last line number if the range has more than one line.
+fn format_range(start: usize, len: usize) -> String {
+    if len == 1 {
+        format!("{}", start + 1)
+    } else {
+        format!("{},{}", start + 1, start + len)
+    }
+}
+
+/// Writes the changes described by an edit script in the normal diff format to the provided output
+/// stream.
+fn write_normal<T: Display, W: Write>(
+    a: &[T],
+    b: &[T],
+    edit_script: &EditScript,
+    writer: W,
+) -> Result<(), crate::Error> {
+    let mut writer = BufWriter::new(writer);
+    let err_desc = "Failed to write a diff";
+
+    for group in change_groups(edit_script) {
+        let range_a = format_range(group.start_a, group.len_a);
+        let range_b = format_range(group.start_b, group.len_b);
+        match (group.len_a, group.len_b) {
+            (_, 0) => writeln!(writer, "{}d{}", range_a, group.start_b),
+            (0, _) => writeln!(writer, "{}a{}", group.start_a, range_b),
+            _ => writeln!(writer, "{}c{}", range_a, range_b),
+        }
+        .map_io_err(err_desc)?;
+
+        for line in &a[group.start_a..group.start_a + group.len_a] {
+            writeln!(writer, "< {}", line).map_io_err(err_desc)?;
+        }
+        if group.len_a > 0 && group.len_b > 0 {
+            writeln!(writer, "---").map_io_err(err_desc)?;
+        }
+        for line in &b[group.start_b..group.start_b + group.len_b] {
+            writeln!(writer, "> {}", line).map_io_err(err_desc)?;
+        }
+    }
+
+    writer.flush().map_io_err(err_desc)
+}
+
+/// Writes the changes described by an edit script as an ed script to the provided output stream.
+/// The commands are written from the end of the input, so that applying them does not shift the
+/// line numbers of the following commands.
+fn write_ed<T: Display, W: Write>(
+    b: &[T],
+    edit_script: &EditScript,
+    writer: W,
+) -> Result<(), crate::Error> {
+    let mut writer = BufWriter::new(writer);
+    let err_desc = "Failed to write a diff";
+
+    for group in change_groups(edit_script).iter().rev() {
+        let range_a = format_range(group.start_a, group.len_a);
+        match (group.len_a, group.len_b) {
+            (_, 0) => writeln!(writer, "{}d", range_a),
+            (0, _) => writeln!(writer, "{}a", group.start_a),
+            _ => writeln!(writer, "{}c", range_a),
+        }
+        .map_io_err(err_desc)?;
+
+        if group.len_b > 0 {
+            for line in &b[group.start_b..group.start_b + group.len_b] {
+                writeln!(writer, "{}", line).map_io_err(err_desc)?;
+            }
+            writeln!(writer, ".").map_io_err(err_desc)?;
+        }
+    }
+
+    writer.flush().map_io_err(err_desc)
+}
+
+/// Writes the inputs side by side in two columns to the provided output stream, according to an
+/// edit script describing their differences. A pair of a removed and an added line is marked by
+/// `|`, a removed line by `<` and an added line by `>`.
+fn write_side_by_side<T: Display, W: Write>(
+    a: &[T],
+    b: &[T],
+    edit_script: &EditScript,
+    width: usize,
+    writer: W,
+) -> Result<(), crate::Error> {
+    let mut writer = BufWriter::new(writer);
+    let err_desc = "Failed to write a diff";
+    let column = width.saturating_sub(3) / 2;
+
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let mut pos_b = 0;
+    for edit in edit_script {
+        match *edit {
+            Edit::KeepA(index_a) => {
+                write_side_by_side_changes(a, b, &removed, &added, column, &mut writer)?;
+                removed.clear();
+                added.clear();
+                let (left, right) = (
+                    fit_column(&a[index_a], column),
+                    fit_column(&b[pos_b], column),
+                );
+                write_side_by_side_row(&left, ' ', &right, column, &mut writer)?;
+                pos_b += 1;
+            }
+            Edit::RemoveA(index_a) => removed.push(index_a),
+            Edit::InsertB(index_b) => {
+                added.push(index_b);
+                pos_b += 1;
+            }
+        }
+    }
+    write_side_by_side_changes(a, b, &removed, &added, column, &mut writer)?;
+
+    writer.flush().map_io_err(err_desc)
+}
+
+/// Writes a group of removed and added lines, specified by their indices in `a` and `b`, in the
+/// side-by-side format. The removed and added lines are paired in their order.
+fn write_side_by_side_changes<T: Display, W: Write>(
+    a: &[T],
+    b: &[T],
+    removed: &[usize],
+    added: &[usize],
+    column: usize,
+    writer: &mut BufWriter<W>,
+) -> Result<(), crate::Error> {
+    for i in 0..std::cmp::max(removed.len(), added.len()) {
+        let (marker, left, right) = match (removed.get(i), added.get(i)) {
+            (Some(&index_a), Some(&index_b)) => ('|', Some(&a[index_a]), Some(&b[index_b])),
+            (Some(&index_a), None) => ('<', Some(&a[index_a]), None),
+            (None, index_b) => ('>', None, index_b.map(|&index_b| &b[index_b])),
+        };
+        let left = left
+            .map(|line| fit_column(line, column))
+            .unwrap_or_default();
+        let right = right
+            .map(|line| fit_column(line, column))
+            .unwrap_or_default();
+        write_side_by_side_row(&left, marker, &right, column, writer)?;
+    }
+    Ok(())
+}
+
+/// Writes a single row of the side-by-side format, omitting any trailing whitespace.
+fn write_side_by_side_row<W: Write>(
+    left: &str,
+    marker: char,
+    right: &str,
+    column: usize,
+    writer: &mut BufWriter<W>,
+) -> Result<(), crate::Error> {
+    let row = format!("{:<column$} {} {}", left, marker, right);
+    writeln!(writer, "{}", row.trim_end()).map_io_err("Failed to write a diff")
+}
+
+/// Formats a line for a column of the side-by-side format, expanding tabs and truncating the
+/// result to `column` characters.
+fn fit_column<T: Display>(line: &T, column: usize) -> String {
+    let mut res = String::new();
+    let mut len = 0;
+    for ch in line.to_string().chars() {
+        if ch == '\t' {
+            let tab_len = 8 - len % 8;
+            res.extend(std::iter::repeat_n(' ', tab_len));
+            len += tab_len;
+        } else {
+            res.push(ch);
+            len += 1;
+        }
+    }
+    res.chars().take(column).collect()
+}
+
+/// Compares `a` with `b` and returns their differences as hunks with 3 lines of context, in the
+/// same form as they are written by [`unified()`].
+///
+/// The items can be of any type comparable by [`PartialEq`], for instance, lines, tokens or
+/// symbol names. Each item is treated as one line of the hunks.
+pub fn hunks<'a, T: PartialEq>(a: &'a [T], b: &'a [T]) -> Vec<Hunk<'a, T>> {
+    edit_script_hunks(a, b, &edit_script(a, b), 2 * CONTEXT_SIZE)
+}
+
+/// Compares `a` with `b`, using the specified options, and returns their differences as hunks
+/// with 3 lines of context.
+///
+/// Unlike [`hunks()`], the items need to be hashable, which allows the algorithms to look up
+/// unique lines.
+pub fn hunks_with<'a, T: Eq + Hash + Display + Sync>(
+    a: &'a [T],
+    b: &'a [T],
+    options: &DiffOptions,
+) -> Vec<Hunk<'a, T>> {
+    let (a, b) = (filter_ignored(a, options), filter_ignored(b, options));
+    let hunks = annotated_hunks(&a, &b, &edit_script_with(&a, &b, options), options);
+
+    // Turn the hunks of references to the filtered lines into hunks of the lines.
+    hunks
+        .into_iter()
+        .map(|hunk| Hunk {
+            pos_a: hunk.pos_a,
+            len_a: hunk.len_a,
+            pos_b: hunk.pos_b,
+            len_b: hunk.len_b,
+            lines: hunk
+                .lines
+                .into_iter()
+                .map(|line| match line {
+                    HunkLine::Context(line) => HunkLine::Context(*line),
+                    HunkLine::Removed(line) => HunkLine::Removed(*line),
+                    HunkLine::Added(line) => HunkLine::Added(*line),
+                })
+                .collect(),
+            moves: hunk.moves,
+        })
+        .collect()
+}
+
+/// An error returned by [`apply()`] and [`apply_with_fuzz()`] when a hunk does not match the input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ApplyError {
+    hunk_index: usize,
+}
+
+impl ApplyError {
+    /// Returns the 0-based index of the hunk which failed to apply.
+    pub fn hunk_index(&self) -> usize {
+        self.hunk_index
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+impl std::fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Hunk #{} failed to apply", self.hunk_index + 1)
+    }
+}
+
+/// Applies hunks, as returned by [`hunks()`] or [`hunks_with()`], to `a` and returns the resulting
+/// lines.
+///
+/// The context and removed lines of each hunk must match `a` exactly, but a hunk can be found at
+/// an offset from its position, for instance, when `a` is not the input from which the hunks were
+/// computed but a version of it with extra lines.
+///
+/// # Examples
+///
+/// ```
+/// use suse_kabi_tools::diff::{apply, hunks};
+///
+/// let a = ["struct foo {", "int a;", "};"];
+/// let b = ["struct foo {", "long a;", "};"];
+/// assert_eq!(apply(&a, &hunks(&a, &b)), Ok(b.to_vec()));
+/// ```
+pub fn apply<T: PartialEq + Clone>(a: &[T], hunks: &[Hunk<T>]) -> Result<Vec<T>, ApplyError> {
+    apply_with_fuzz(a, hunks, 0)
+}
+
+/// Applies hunks to `a`, similarly to [`apply()`], but allows up to `fuzz` leading and trailing
+/// context lines of each hunk to not match, as `patch --fuzz` does.
+pub fn apply_with_fuzz<T: PartialEq + Clone>(
+    a: &[T],
+    hunks: &[Hunk<T>],
+    fuzz: usize,
+) -> Result<Vec<T>, ApplyError> {
+    let mut res = Vec::with_capacity(a.len());
+    let mut pos = 0;
+    let mut offset = 0isize;
+
+    for (hunk_index, hunk) in hunks.iter().enumerate() {
+        let old: Vec<_> = hunk
+            .lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(line) | HunkLine::Removed(line) => Some(*line),
+                HunkLine::Added(_) => None,
+            })
+            .collect();
+        let new: Vec<_> = hunk
+            .lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(line) | HunkLine::Added(line) => Some(*line),
+                HunkLine::Removed(_) => None,
+            })
+            .collect();
+        let is_context = |line: &&HunkLine<T>| matches!(line, HunkLine::Context(_));
+        let leading = hunk.lines.iter().take_while(is_context).count();
+        let trailing = if leading == hunk.lines.len() {
+            0
+        } else {
+            hunk.lines.iter().rev().take_while(is_context).count()
+        };
+
+        let start = hunk.pos_a.saturating_sub(1);
+
+        // Look for the old lines, ignoring more context lines at each fuzz level.
+        let found = (0..=fuzz).find_map(|fuzz| {
+            let (skip_leading, skip_trailing) = (fuzz.min(leading), fuzz.min(trailing));
+            let old = &old[skip_leading..old.len() - skip_trailing];
+            let expected = (start + skip_leading).saturating_add_signed(offset);
+            find_lines(a, old, pos, expected).map(|found| (found, skip_leading, skip_trailing))
+        });
+        let Some((found, skip_leading, skip_trailing)) = found else {
+            return Err(ApplyError { hunk_index });
+        };
+
+        // Copy the lines before the hunk and replace the old lines with the new ones.
+        res.extend_from_slice(&a[pos..found]);
+        res.extend(
+            new[skip_leading..new.len() - skip_trailing]
+                .iter()
+                .map(|&line| line.clone()),
+        );
+        pos = found + old.len() - skip_leading - skip_trailing;
+        offset = found as isize - (start + skip_leading) as isize;
+    }
+
+    res.extend_from_slice(&a[pos..]);
+    Ok(res)
+}
+
+/// Looks up `lines` in `a` at or after the index `min_pos`, preferring the positions nearest to
+/// `expected`, and returns the index of the first found line.
+fn find_lines<T: PartialEq>(
+    a: &[T],
+    lines: &[&T],
+    min_pos: usize,
+    expected: usize,
+) -> Option<usize> {
+    if a.len() < lines.len() || min_pos > a.len() - lines.len() {
+        return None;
+    }
+    let max_pos = a.len() - lines.len();
+    let expected = expected.clamp(min_pos, max_pos);
+    let matches = |pos: usize| zip(&a[pos..], lines).all(|(x, &y)| x == y);
+
+    for distance in 0..=(max_pos - min_pos) {
+        if expected + distance <= max_pos && matches(expected + distance) {
+            return Some(expected + distance);
+        }
+        if distance > 0 && expected >= min_pos + distance && matches(expected - distance) {
+            return Some(expected - distance);
+        }
+    }
+    None
+}
+
+/// Statistics of differences between two inputs, as returned by [`diff_stats()`] and
+/// [`diff_stats_with()`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiffStats {
+    inserted: usize,
+    removed: usize,
+    kept: usize,
+    hunks: usize,
+}
+
+impl DiffStats {
+    /// Returns the number of lines present only in the second input.
+    pub fn inserted(&self) -> usize {
+        self.inserted
+    }
+
+    /// Returns the number of lines present only in the first input.
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// Returns the number of lines present in both inputs.
+    pub fn kept(&self) -> usize {
+        self.kept
+    }
+
+    /// Returns the number of hunks with 3 lines of context, as they would be written in the
+    /// unified format.
+    pub fn hunks(&self) -> usize {
+        self.hunks
+    }
+
+    /// Returns the total number of inserted and removed lines.
+    pub fn changed(&self) -> usize {
+        self.inserted + self.removed
+    }
+}
+
+/// Compares `a` with `b` and returns statistics of their differences, without formatting any
+/// output.
+pub fn diff_stats<T: PartialEq>(a: &[T], b: &[T]) -> DiffStats {
+    edit_script_stats(&edit_script(a, b), 2 * CONTEXT_SIZE)
+}
+
+/// Compares `a` with `b`, using the specified options, and returns statistics of their
+/// differences, without formatting any output.
+pub fn diff_stats_with<T: Eq + Hash + Display + Sync>(
+    a: &[T],
+    b: &[T],
+    options: &DiffOptions,
+) -> DiffStats {
+    let (a, b) = (filter_ignored(a, options), filter_ignored(b, options));
+    edit_script_stats(
+        &edit_script_with(&a, &b, options),
+        options.effective_merge_gap(),
+    )
+}
+
+/// Counts the kept, removed and inserted lines of an edit script, and the resulting hunks when
+/// changes separated by at most `merge_gap` unchanged lines are merged.
+fn edit_script_stats(edit_script: &EditScript, merge_gap: usize) -> DiffStats {
+    let mut stats = DiffStats::default();
+    let mut in_hunk = false;
+    let mut context_len = 0;
+    for &edit in edit_script {
+        match edit {
+            Edit::KeepA(_) => {
+                stats.kept += 1;
+
+                // A hunk is closed off when the context after its last change exceeds the gap.
+                context_len += 1;
+                if in_hunk && context_len > merge_gap {
+                    in_hunk = false;
+                }
+            }
+            Edit::RemoveA(_) | Edit::InsertB(_) => {
+                if let Edit::RemoveA(_) = edit {
+                    stats.removed += 1;
+                } else {
+                    stats.inserted += 1;
+                }
+                if !in_hunk {
+                    stats.hunks += 1;
+                    in_hunk = true;
+                }
+                context_len = 0;
+            }
+        }
+    }
+    stats
+}
+
+/// Compares `a` with `b` using the algorithm selected by the options and returns a post-processed
+/// edit script describing how to transform the former to the latter.
+fn edit_script_with<T: Eq + Hash + Display + Sync>(
+    a: &[T],
+    b: &[T],
+    options: &DiffOptions,
+) -> EditScript {
+    let edit_script = match options.num_workers {
+        Some(num_workers) if a.len() + b.len() > CHUNKED_THRESHOLD => {
+            chunked(a, b, options, num_workers)
+        }
+        _ => algorithm_edit_script(a, b, options),
+    };
+    tidy_edit_script(a, b, edit_script, indentation)
+}
+
+/// Compares `a` with `b` using the algorithm selected by the options and returns an edit script
+/// describing how to transform the former to the latter.
+fn algorithm_edit_script<T: Eq + Hash>(a: &[T], b: &[T], options: &DiffOptions) -> EditScript {
+    let max_cost = options.max_cost.unwrap_or(usize::MAX);
+    match options.algorithm {
+        Algorithm::Myers => myers_limited(a, b, max_cost),
+        Algorithm::Patience => patience(a, b, max_cost),
+    }
+}
+
+/// Compares `a` with `b` by splitting them into chunks at lines unique in both inputs, comparing
+/// the chunks by `num_workers` workers and merging the results. Returns an edit script describing
+/// how to transform the former to the latter.
+fn chunked<T: Eq + Hash + Sync>(
+    a: &[T],
+    b: &[T],
+    options: &DiffOptions,
+    num_workers: i32,
+) -> EditScript {
+    // Split the inputs at anchors so that each chunk has at least `CHUNK_SIZE` lines. Each chunk
+    // other than the first one starts with its anchor.
+    let mut chunks: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    let (mut pos_a, mut pos_b) = (0, 0);
+    for (anchor_a, anchor_b) in unique_anchors_by(a, b, Some) {
+        if anchor_a - pos_a + anchor_b - pos_b >= CHUNK_SIZE {
+            chunks.push((pos_a..anchor_a, pos_b..anchor_b));
+            (pos_a, pos_b) = (anchor_a, anchor_b);
+        }
+    }
+    chunks.push((pos_a..a.len(), pos_b..b.len()));
+
+    // Compare the chunks.
+    let next_chunk_idx = AtomicUsize::new(0);
+    let mut chunk_scripts: Vec<_> = run_workers(num_workers.max(1), || {
+        let mut chunk_scripts = Vec::new();
+        loop {
+            let chunk_idx = next_chunk_idx.fetch_add(1, Ordering::Relaxed);
+            if chunk_idx >= chunks.len() {
+                break;
+            }
+            let (range_a, range_b) = &chunks[chunk_idx];
+            let edit_script =
+                algorithm_edit_script(&a[range_a.clone()], &b[range_b.clone()], options);
+            chunk_scripts.push((chunk_idx, edit_script));
+        }
+        chunk_scripts
+    })
+    .into_iter()
+    .flatten()
+    .collect();
+
+    // Merge the edit scripts of the chunks.
+    chunk_scripts.sort_by_key(|(chunk_idx, _)| *chunk_idx);
+    let mut edit_script = EditScript::new();
+    for (chunk_idx, chunk_script) in chunk_scripts {
+        let (range_a, range_b) = &chunks[chunk_idx];
+        extend_with_offset(
+            &mut edit_script,
+            chunk_script,
+            (range_a.start, range_b.start),
+        );
+    }
+    edit_script
+}
+
+/// Turns an edit script describing how to transform `a` to `b` into hunks with 3 lines of context,
+/// merging changes separated by at most `merge_gap` unchanged lines, which must be at least the
+/// number of context lines, into one hunk.
+fn edit_script_hunks<'a, T>(
+    a: &'a [T],
+    b: &'a [T],
+    edit_script: &EditScript,
+    merge_gap: usize,
+) -> Vec<Hunk<'a, T>> {
     // Turn the edit script into hunks in the unified format.
-    const CONTEXT_SIZE: usize = 3;
     let (mut context_begin, mut context_end) = (0, 0);
     let (mut pos_a, mut pos_b) = (1, 1);
     let (mut hunk_pos_a, mut hunk_len_a, mut hunk_pos_b, mut hunk_len_b) = (0, 0, 0, 0);
     let mut hunk_data = Vec::new();
+    let mut hunks = Vec::new();
 
-    for edit in edit_script {
+    for &edit in edit_script {
         match edit {
             Edit::KeepA(index_a) => {
                 // Start recording a new context, or extend the current one.
@@ -183,22 +1873,21 @@ pub fn unified<T: AsRef<str> + PartialEq + Display, W: Write>(
                 pos_b += 1;
 
                 // If handling a hunk, check if it should be closed off.
-                if !hunk_data.is_empty() && context_end - context_begin > 2 * CONTEXT_SIZE {
+                if !hunk_data.is_empty() && context_end - context_begin > merge_gap {
                     for line in a.iter().skip(context_begin).take(CONTEXT_SIZE) {
-                        hunk_data.push(format!(" {}", line));
+                        hunk_data.push(HunkLine::Context(line));
                     }
                     hunk_len_a += CONTEXT_SIZE;
                     hunk_len_b += CONTEXT_SIZE;
                     context_begin += CONTEXT_SIZE;
-                    write_hunk(
-                        hunk_pos_a,
-                        hunk_len_a,
-                        hunk_pos_b,
-                        hunk_len_b,
-                        &hunk_data,
-                        &mut writer,
-                    )?;
-                    hunk_data.clear();
+                    hunks.push(Hunk {
+                        pos_a: hunk_pos_a,
+                        len_a: hunk_len_a,
+                        pos_b: hunk_pos_b,
+                        len_b: hunk_len_b,
+                        lines: mem::take(&mut hunk_data),
+                        moves: Vec::new(),
+                    });
                 }
             }
 
@@ -223,7 +1912,7 @@ pub fn unified<T: AsRef<str> + PartialEq + Display, W: Write>(
 
                 // Add any accumulated context.
                 for line in a.iter().take(context_end).skip(context_begin) {
-                    hunk_data.push(format!(" {}", line));
+                    hunk_data.push(HunkLine::Context(line));
                 }
                 hunk_len_a += context_end - context_begin;
                 hunk_len_b += context_end - context_begin;
@@ -231,10 +1920,10 @@ pub fn unified<T: AsRef<str> + PartialEq + Display, W: Write>(
 
                 // Record the removed/added string.
                 if let Edit::RemoveA(index_a) = edit {
-                    hunk_data.push(format!("-{}", a[index_a]));
+                    hunk_data.push(HunkLine::Removed(&a[index_a]));
                     hunk_len_a += 1;
                 } else if let Edit::InsertB(index_b) = edit {
-                    hunk_data.push(format!("+{}", b[index_b]));
+                    hunk_data.push(HunkLine::Added(&b[index_b]));
                     hunk_len_b += 1;
                 }
             }
@@ -247,19 +1936,120 @@ pub fn unified<T: AsRef<str> + PartialEq + Display, W: Write>(
             context_end = context_begin + CONTEXT_SIZE;
         }
         for line in a.iter().take(context_end).skip(context_begin) {
-            hunk_data.push(format!(" {}", line));
+            hunk_data.push(HunkLine::Context(line));
         }
         hunk_len_a += context_end - context_begin;
         hunk_len_b += context_end - context_begin;
-        write_hunk(
-            hunk_pos_a,
-            hunk_len_a,
-            hunk_pos_b,
-            hunk_len_b,
-            &hunk_data,
-            &mut writer,
-        )?;
+        hunks.push(Hunk {
+            pos_a: hunk_pos_a,
+            len_a: hunk_len_a,
+            pos_b: hunk_pos_b,
+            len_b: hunk_len_b,
+            lines: hunk_data,
+            moves: Vec::new(),
+        });
     }
 
-    Ok(())
+    hunks
+}
+
+/// Turns an edit script describing how to transform `a` to `b` into hunks with 3 lines of context
+/// and, if enabled by the options, records detected moves in them.
+fn annotated_hunks<'a, T: PartialEq>(
+    a: &'a [T],
+    b: &'a [T],
+    edit_script: &EditScript,
+    options: &DiffOptions,
+) -> Vec<Hunk<'a, T>> {
+    let mut hunks = edit_script_hunks(a, b, edit_script, options.effective_merge_gap());
+    if options.detect_moves {
+        for m in find_moves(a, b, edit_script) {
+            for hunk in &mut hunks {
+                if (hunk.pos_a..hunk.pos_a + hunk.len_a).contains(&m.pos_a)
+                    || (hunk.pos_b..hunk.pos_b + hunk.len_b).contains(&m.pos_b)
+                {
+                    hunk.moves.push(m);
+                }
+            }
+        }
+    }
+    hunks
+}
+
+/// Finds blocks of lines which an edit script removes from `a` and inserts verbatim elsewhere in
+/// `b`, and returns them ordered by their position in `a`.
+fn find_moves<T: PartialEq>(a: &[T], b: &[T], edit_script: &EditScript) -> Vec<Move> {
+    // Collect runs of consecutive removed and inserted lines, as (start, length) pairs.
+    let mut removed: Vec<(usize, usize)> = Vec::new();
+    let mut inserted: Vec<(usize, usize)> = Vec::new();
+    for &edit in edit_script {
+        let (runs, index) = match edit {
+            Edit::KeepA(_) => continue,
+            Edit::RemoveA(index_a) => (&mut removed, index_a),
+            Edit::InsertB(index_b) => (&mut inserted, index_b),
+        };
+        match runs.last_mut() {
+            Some((start, len)) if *start + *len == index => *len += 1,
+            _ => runs.push((index, 1)),
+        }
+    }
+
+    // Look up each removed run in the inserted lines, and then each remaining inserted run in the
+    // remaining removed lines.
+    let mut moves = Vec::new();
+    let mut remaining = Vec::new();
+    for (start, len) in removed {
+        match take_block(&a[start..start + len], b, &mut inserted) {
+            Some(pos_b) => moves.push(Move {
+                pos_a: start + 1,
+                pos_b: pos_b + 1,
+                num_lines: len,
+            }),
+            None => remaining.push((start, len)),
+        }
+    }
+    for (start, len) in inserted {
+        if let Some(pos_a) = take_block(&b[start..start + len], a, &mut remaining) {
+            moves.push(Move {
+                pos_a: pos_a + 1,
+                pos_b: start + 1,
+                num_lines: len,
+            });
+        }
+    }
+
+    moves.sort_by_key(|m| m.pos_a);
+    moves
+}
+
+/// Looks up `block` in the runs of `lines`. If found, removes the matching part from the runs and
+/// returns its start.
+fn take_block<T: PartialEq>(
+    block: &[T],
+    lines: &[T],
+    runs: &mut Vec<(usize, usize)>,
+) -> Option<usize> {
+    for (i, &(start, len)) in runs.iter().enumerate() {
+        if len < block.len() {
+            continue;
+        }
+        let Some(offset) = lines[start..start + len]
+            .windows(block.len())
+            .position(|window| window == block)
+        else {
+            continue;
+        };
+
+        // Split the run into the parts before and after the block.
+        let end = offset + block.len();
+        runs.remove(i);
+        if end < len {
+            runs.insert(i, (start + end, len - end));
+        }
+        if offset > 0 {
+            runs.insert(i, (start, offset));
+        }
+        return Some(start + offset);
+    }
+    None
 }
@@ -160,3 +160,871 @@ fn diff_keep_subsequent() {
         ]
     );
 }
+
+#[test]
+fn diff_long_keep_runs() {
+    // Check that long runs of kept lines around a change are expanded into individual steps.
+    let a: Vec<_> = (0..10000).map(|i| i.to_string()).collect();
+    let mut b = a.clone();
+    b[5000] = "X".to_string();
+    let edit_script = myers(&a, &b);
+    assert_eq!(edit_script.len(), 10001);
+    assert!(edit_script[..5000]
+        .iter()
+        .enumerate()
+        .all(|(i, &edit)| edit == Edit::KeepA(i)));
+    assert_eq!(
+        edit_script[5000..5003],
+        [Edit::RemoveA(5000), Edit::InsertB(5000), Edit::KeepA(5001)]
+    );
+    assert_eq!(edit_script[10000], Edit::KeepA(9999));
+}
+
+#[test]
+fn hunks_context() {
+    // Check that changes separated by more than twice the context size are split into separate
+    // hunks, each with up to 3 lines of context.
+    let a = ["A", "B", "C", "D", "E", "F", "G", "H", "I", "J"];
+    let b = ["X", "B", "C", "D", "E", "F", "G", "H", "I"];
+    let hunks = hunks(&a, &b);
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(
+        (
+            hunks[0].pos_a(),
+            hunks[0].len_a(),
+            hunks[0].pos_b(),
+            hunks[0].len_b()
+        ),
+        (1, 4, 1, 4)
+    );
+    assert_eq!(
+        hunks[0].lines(),
+        [
+            HunkLine::Removed(&"A"),
+            HunkLine::Added(&"X"),
+            HunkLine::Context(&"B"),
+            HunkLine::Context(&"C"),
+            HunkLine::Context(&"D"),
+        ]
+    );
+    assert_eq!(
+        (
+            hunks[1].pos_a(),
+            hunks[1].len_a(),
+            hunks[1].pos_b(),
+            hunks[1].len_b()
+        ),
+        (7, 4, 7, 3)
+    );
+    assert_eq!(
+        hunks[1].lines(),
+        [
+            HunkLine::Context(&"G"),
+            HunkLine::Context(&"H"),
+            HunkLine::Context(&"I"),
+            HunkLine::Removed(&"J"),
+        ]
+    );
+
+    let mut out = Vec::new();
+    let result = unified(&a, &b, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,4 +1,4 @@\n",
+            "-A\n",
+            "+X\n",
+            " B\n",
+            " C\n",
+            " D\n",
+            "@@ -7,4 +7,3 @@\n",
+            " G\n",
+            " H\n",
+            " I\n",
+            "-J\n", //
+        )
+    );
+}
+
+#[test]
+fn hunks_arbitrary_items() {
+    // Check that items of a type which is not a string can be compared.
+    #[derive(Debug, PartialEq)]
+    struct Item(u32);
+
+    let a = [Item(1), Item(2), Item(3)];
+    let b = [Item(1), Item(4), Item(3)];
+    let hunks = hunks(&a, &b);
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(
+        hunks[0].lines(),
+        [
+            HunkLine::Context(&Item(1)),
+            HunkLine::Removed(&Item(2)),
+            HunkLine::Added(&Item(4)),
+            HunkLine::Context(&Item(3)),
+        ]
+    );
+
+    let mut out = Vec::new();
+    let result = unified(&[1, 2, 3], &[1, 3], &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,3 +1,2 @@\n",
+            " 1\n",
+            "-2\n",
+            " 3\n", //
+        )
+    );
+}
+
+#[test]
+fn diff_patience() {
+    // Check that the patience algorithm anchors the diff on lines that are unique in both inputs,
+    // whereas the Myers algorithm aligns the inputs on repeated lines.
+    let a = ["struct s {", "long a;", "int x;", "int x;", "};"];
+    let b = ["struct s {", "long b;", "int x;", "long a;", "};"];
+    let mut out = Vec::new();
+    let result = unified_with(&a, &b, &DiffOptions::new(), &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,5 +1,5 @@\n",
+            " struct s {\n",
+            "-long a;\n",
+            "+long b;\n",
+            " int x;\n",
+            "-int x;\n",
+            "+long a;\n",
+            " };\n", //
+        )
+    );
+
+    let mut out = Vec::new();
+    let options = DiffOptions::new().algorithm(Algorithm::Patience);
+    let result = unified_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,5 +1,5 @@\n",
+            " struct s {\n",
+            "+long b;\n",
+            "+int x;\n",
+            " long a;\n",
+            "-int x;\n",
+            "-int x;\n",
+            " };\n", //
+        )
+    );
+}
+
+#[test]
+fn diff_patience_fallback() {
+    // Check that the patience algorithm falls back to the Myers algorithm when the inputs have no
+    // unique lines in common.
+    let a = ["x", "x", "y", "x"];
+    let b = ["y", "x", "x", "y"];
+    let options = DiffOptions::new().algorithm(Algorithm::Patience);
+    assert_eq!(hunks_with(&a, &b, &options), hunks(&a, &b));
+}
+
+#[test]
+fn diff_common_affixes() {
+    // Check that the common prefix and suffix are kept and the edits in the middle part are
+    // reported at their original positions.
+    let a = ["p", "p", "x", "z", "s"];
+    let b = ["p", "p", "y", "s"];
+    let edit_script = myers(&a, &b);
+    assert_eq!(
+        edit_script,
+        [
+            Edit::KeepA(0),
+            Edit::KeepA(1),
+            Edit::RemoveA(2),
+            Edit::RemoveA(3),
+            Edit::InsertB(2),
+            Edit::KeepA(4),
+        ]
+    );
+
+    // Check that the common suffix is kept even if the same item could be aligned earlier.
+    let a = ["y", "x"];
+    let b = ["x", "x"];
+    let edit_script = myers(&a, &b);
+    assert_eq!(
+        edit_script,
+        [Edit::RemoveA(0), Edit::InsertB(0), Edit::KeepA(1)]
+    );
+}
+
+#[test]
+fn diff_linear_space() {
+    // Check that the linear space variant of the Myers algorithm finds an edit script of the same
+    // length as the greedy variant.
+    let a = ["a", "b", "c", "a", "b", "b", "a"];
+    let b = ["c", "b", "a", "b", "a", "c"];
+    let greedy = myers_greedy(&a, &b, usize::MAX);
+    let linear = myers_linear(&a, &b, usize::MAX);
+    let count_edits = |edit_script: &EditScript| {
+        edit_script
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::KeepA(_)))
+            .count()
+    };
+    assert_eq!(count_edits(&greedy), 5);
+    assert_eq!(count_edits(&linear), 5);
+}
+
+#[test]
+fn diff_max_cost() {
+    // Check that the differing part of the inputs is replaced as a whole when the cost of the diff
+    // exceeds the specified maximum.
+    let a = ["a", "b", "c", "x", "d", "e", "x", "f"];
+    let b = ["a", "c", "x", "y", "e", "x", "g", "f"];
+    let expected = [
+        Edit::KeepA(0),
+        Edit::RemoveA(1),
+        Edit::RemoveA(2),
+        Edit::RemoveA(3),
+        Edit::RemoveA(4),
+        Edit::RemoveA(5),
+        Edit::RemoveA(6),
+        Edit::InsertB(1),
+        Edit::InsertB(2),
+        Edit::InsertB(3),
+        Edit::InsertB(4),
+        Edit::InsertB(5),
+        Edit::InsertB(6),
+        Edit::KeepA(7),
+    ];
+    assert_eq!(myers_greedy(&a, &b, 3), expected);
+    assert_eq!(myers_linear(&a, &b, 3), expected);
+
+    // Check that a diff within the limit is computed exactly.
+    assert_eq!(myers_greedy(&a, &b, 4), myers(&a, &b));
+    assert_eq!(myers_linear(&a, &b, 4).len(), myers(&a, &b).len());
+
+    // Check that the limit applies to the Myers fallback of the patience algorithm.
+    let a = ["x", "x", "y", "x"];
+    let b = ["y", "x", "x", "y"];
+    assert_eq!(
+        patience(&a, &b, 1),
+        [
+            Edit::RemoveA(0),
+            Edit::RemoveA(1),
+            Edit::RemoveA(2),
+            Edit::RemoveA(3),
+            Edit::InsertB(0),
+            Edit::InsertB(1),
+            Edit::InsertB(2),
+            Edit::InsertB(3),
+        ]
+    );
+}
+
+#[test]
+fn diff_large_inputs() {
+    // Check that inputs above the linear space threshold are diffed correctly.
+    let a: Vec<_> = (0..LINEAR_SPACE_THRESHOLD).map(|i| i.to_string()).collect();
+    let mut b = a.clone();
+    b[100] = "x".to_string();
+    b.insert(9000, "y".to_string());
+    let mut out = Vec::new();
+    let result = unified(&a, &b, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -98,7 +98,7 @@\n",
+            " 97\n",
+            " 98\n",
+            " 99\n",
+            "-100\n",
+            "+x\n",
+            " 101\n",
+            " 102\n",
+            " 103\n",
+            "@@ -8998,6 +8998,7 @@\n",
+            " 8997\n",
+            " 8998\n",
+            " 8999\n",
+            "+y\n",
+            " 9000\n",
+            " 9001\n",
+            " 9002\n", //
+        )
+    );
+}
+
+#[test]
+fn write_normal_format() {
+    // Check that changes are written in the normal format.
+    let a = ["a", "b", "c", "d", "e", "f"];
+    let b = ["x", "a", "c", "y", "z", "e"];
+    let mut out = Vec::new();
+    let options = DiffOptions::new().format(Format::Normal);
+    let result = write_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "0a1\n", "> x\n", "2d2\n", "< b\n", "4c4,5\n", "< d\n", "---\n", "> y\n", "> z\n",
+            "6d6\n", "< f\n", //
+        )
+    );
+}
+
+#[test]
+fn write_ed_format() {
+    // Check that changes are written as an ed script, starting from the end of the input.
+    let a = ["a", "b", "c", "d", "e", "f"];
+    let b = ["x", "a", "c", "y", "z", "e"];
+    let mut out = Vec::new();
+    let options = DiffOptions::new().format(Format::Ed);
+    let result = write_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "6d\n",
+            "4c\n",
+            "y\n",
+            "z\n",
+            ".\n",
+            "2d\n",
+            "0a\n",
+            "x\n",
+            ".\n", //
+        )
+    );
+}
+
+#[test]
+fn write_side_by_side_format() {
+    // Check that the inputs are written side by side with markers of changed lines, long lines are
+    // truncated and tabs are expanded.
+    let a = ["struct s {", "\tint a;", "\tint b;", "\tint c;", "}"];
+    let b = [
+        "struct s {",
+        "\tlong a;",
+        "\tint c;",
+        "\tint a_long_name;",
+        "}",
+    ];
+    let mut out = Vec::new();
+    let options = DiffOptions::new().format(Format::SideBySide).width(37);
+    let result = write_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "struct s {          struct s {\n",
+            "        int a;    |         long a;\n",
+            "        int b;    <\n",
+            "        int c;              int c;\n",
+            "                  >         int a_lon\n",
+            "}                   }\n", //
+        )
+    );
+}
+
+#[test]
+fn write_anchored() {
+    // Check that lines with the same key are aligned with each other, even if their content
+    // differs, and the parts between them are diffed as usual.
+    let a = ["{", "a=1", "b=2", "x", "c=3", "}"];
+    let b = ["{", "a=4", "y", "b=2", "c=5", "}"];
+    let mut out = Vec::new();
+    let key = |line: &&str| line.split_once('=').map(|(name, _)| name.to_string());
+    let result = write_anchored_with(&a, &b, key, &DiffOptions::new(), &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,6 +1,6 @@\n",
+            " {\n",
+            "-a=1\n",
+            "+a=4\n",
+            "+y\n",
+            " b=2\n",
+            "-x\n",
+            "-c=3\n",
+            "+c=5\n",
+            " }\n", //
+        )
+    );
+}
+
+#[test]
+fn write_json_format() {
+    // Check that hunks are written as JSON and special characters in lines are escaped.
+    let a = ["a", "b \"quoted\"", "c"];
+    let b = ["a", "b\tc", "c"];
+    let mut out = Vec::new();
+    let options = DiffOptions::new().format(Format::Json);
+    let result = write_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "[{\"pos_a\":1,\"len_a\":3,\"pos_b\":1,\"len_b\":3,\"lines\":[",
+            "{\"type\":\"context\",\"text\":\"a\"},",
+            "{\"type\":\"removed\",\"text\":\"b \\\"quoted\\\"\"},",
+            "{\"type\":\"added\",\"text\":\"b\\tc\"},",
+            "{\"type\":\"context\",\"text\":\"c\"}]}]", //
+        )
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_hunks() {
+    // Check that the serialized hunks match the JSON format.
+    let a = ["a", "b", "c", "d", "e", "f", "g", "h", "i"];
+    let b = ["a", "x", "c", "d", "e", "f", "g", "h"];
+    let hunks = hunks(&a, &b);
+    let mut out = Vec::new();
+    let result = write_json_hunks(&hunks, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        serde_json::to_string(&hunks).unwrap(),
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_moves() {
+    // Check that the serialized hunks with moves match the JSON format.
+    let a = ["a", "b", "c", "d"];
+    let b = ["b", "c", "a", "d"];
+    let hunks = hunks_with(&a, &b, &DiffOptions::new().detect_moves(true));
+    let mut out = Vec::new();
+    let result = write_json_hunks(&hunks, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        serde_json::to_string(&hunks).unwrap(),
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[test]
+fn unified_labels() {
+    // Check that a unified diff is preceded by the header lines when labels are set, and that
+    // nothing is written if the inputs are identical.
+    let a = ["a", "b"];
+    let b = ["a", "c"];
+    let options = DiffOptions::new().labels("old/file", "new/file");
+    let mut out = Vec::new();
+    let result = unified_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "--- old/file\n",
+            "+++ new/file\n",
+            "@@ -1,2 +1,2 @@\n",
+            " a\n",
+            "-b\n",
+            "+c\n", //
+        )
+    );
+
+    let mut out = Vec::new();
+    let result = unified_with(&a, &a, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(String::from_utf8(out).unwrap(), "");
+}
+
+#[test]
+fn unified_color() {
+    // Check that removed and added lines are colored when requested, but context lines and hunk
+    // headers are not.
+    let a = ["a", "b"];
+    let b = ["a", "c"];
+    let options = DiffOptions::new().color(true);
+    let mut out = Vec::new();
+    let result = unified_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,2 +1,2 @@\n",
+            " a\n",
+            "\x1b[31m-b\x1b[0m\n",
+            "\x1b[32m+c\x1b[0m\n", //
+        )
+    );
+}
+
+#[test]
+fn write_max_lines() {
+    // Check that a diff is truncated to the specified number of lines and a trailer with the number
+    // of omitted lines is written.
+    let a = ["a", "b", "c", "d"];
+    let b = ["w", "x", "y", "z"];
+    let mut out = Vec::new();
+    let options = DiffOptions::new().max_lines(4);
+    let result = write_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,4 +1,4 @@\n",
+            "-a\n",
+            "-b\n",
+            "-c\n",
+            "... diff truncated (5 more lines)\n", //
+        )
+    );
+
+    // Check that the trailer is not written if the diff fits.
+    let mut out = Vec::new();
+    let options = DiffOptions::new().format(Format::Normal).max_lines(10);
+    let result = write_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "1,4c1,4\n",
+            "< a\n",
+            "< b\n",
+            "< c\n",
+            "< d\n",
+            "---\n",
+            "> w\n",
+            "> x\n",
+            "> y\n",
+            "> z\n", //
+        )
+    );
+}
+
+#[test]
+fn ignore_patterns() {
+    // Check that lines matching an ignore pattern are dropped from both inputs before they are
+    // compared.
+    let a = [
+        "struct s {",
+        "int a;",
+        "int b __attribute__((packed));",
+        "};",
+    ];
+    let b = [
+        "struct s {",
+        "long a;",
+        "int b;",
+        "int c __attribute__((aligned(8)));",
+        "};",
+    ];
+    let options = DiffOptions::new().ignore_pattern(Regex::new("__attribute__").unwrap());
+    let mut out = Vec::new();
+    let result = unified_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,3 +1,4 @@\n",
+            " struct s {\n",
+            "-int a;\n",
+            "+long a;\n",
+            "+int b;\n",
+            " };\n", //
+        )
+    );
+
+    let mut out = Vec::new();
+    let result = write_with(&a, &b, &options.format(Format::Normal), &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "2c2,3\n",
+            "< int a;\n",
+            "---\n",
+            "> long a;\n",
+            "> int b;\n", //
+        )
+    );
+}
+
+#[test]
+fn detect_moves() {
+    // Check that a block of removed lines which is added verbatim elsewhere is recorded as a move
+    // in all hunks which contain either of its ends.
+    let a = ["m1", "m2", "a", "b", "c", "d", "e", "f", "g", "h", "i"];
+    let b = ["a", "b", "c", "d", "e", "f", "g", "h", "m1", "m2", "x", "i"];
+    let options = DiffOptions::new().detect_moves(true);
+    let mut out = Vec::new();
+    let result = unified_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,5 +1,3 @@ moved -1,2 +9,2\n",
+            "-m1\n",
+            "-m2\n",
+            " a\n",
+            " b\n",
+            " c\n",
+            "@@ -8,4 +6,7 @@ moved -1,2 +9,2\n",
+            " f\n",
+            " g\n",
+            " h\n",
+            "+m1\n",
+            "+m2\n",
+            "+x\n",
+            " i\n", //
+        )
+    );
+
+    // Check that an added block found within a larger removed block is recorded as a move.
+    let a = ["x", "y", "k1", "k2"];
+    let b = ["k1", "k2", "y"];
+    let hunks = hunks_with(&a, &b, &options);
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(
+        hunks[0].moves(),
+        [Move {
+            pos_a: 2,
+            pos_b: 3,
+            num_lines: 1
+        }]
+    );
+
+    // Check that no moves are recorded by default.
+    let hunks = hunks_with(&a, &b, &DiffOptions::new());
+    assert_eq!(hunks[0].moves(), []);
+}
+
+#[test]
+fn stats() {
+    // Check that the statistics count the inserted, removed and kept lines, and the hunks as
+    // produced by the unified format.
+    let a = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k"];
+    let b = ["x", "a", "b", "c", "d", "e", "f", "g", "h", "j", "y", "k"];
+    let stats = diff_stats(&a, &b);
+    assert_eq!(stats.inserted(), 2);
+    assert_eq!(stats.removed(), 1);
+    assert_eq!(stats.kept(), 10);
+    assert_eq!(stats.changed(), 3);
+    assert_eq!(stats.hunks(), hunks(&a, &b).len());
+    assert_eq!(stats.hunks(), 2);
+
+    // Check that the changes are merged into one hunk if they are separated by at most 6 lines.
+    let b = ["x", "a", "b", "c", "d", "e", "f", "y", "h", "i", "j", "k"];
+    let stats = diff_stats(&a, &b);
+    assert_eq!(stats.hunks(), hunks(&a, &b).len());
+    assert_eq!(stats.hunks(), 1);
+
+    // Check that the statistics of identical inputs are empty, other than the kept lines.
+    let options = DiffOptions::new().algorithm(Algorithm::Patience);
+    let stats = diff_stats_with(&a, &a, &options);
+    assert_eq!(
+        stats,
+        DiffStats {
+            inserted: 0,
+            removed: 0,
+            kept: 11,
+            hunks: 0
+        }
+    );
+}
+
+#[test]
+fn merge_gap() {
+    // Check that the changes separated by more unchanged lines than the merge gap are shown in
+    // separate hunks, and that the statistics count the same hunks.
+    let a = ["x", "a", "b", "c", "d", "e", "y", "f"];
+    let b = ["a", "b", "c", "d", "e", "f"];
+    let options = DiffOptions::new().merge_gap(4);
+    let mut out = Vec::new();
+    let result = unified_with(&a, &b, &options, &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,4 +1,3 @@\n",
+            "-x\n",
+            " a\n",
+            " b\n",
+            " c\n",
+            "@@ -5,4 +4,3 @@\n",
+            " d\n",
+            " e\n",
+            "-y\n",
+            " f\n", //
+        )
+    );
+    assert_eq!(diff_stats_with(&a, &b, &options).hunks(), 2);
+
+    // Check that a gap smaller than the context behaves as the context size.
+    let options = DiffOptions::new().merge_gap(0);
+    assert_eq!(hunks_with(&a, &b, &options).len(), 2);
+    assert_eq!(diff_stats_with(&a, &b, &options).hunks(), 2);
+
+    // Check that the default gap merges the changes into one hunk.
+    assert_eq!(hunks_with(&a, &b, &DiffOptions::new()).len(), 1);
+
+    // Check that the maximum gap produces a single hunk.
+    let a = ["x", "a", "b", "c", "d", "e", "f", "g", "h", "i", "y"];
+    let b = ["a", "b", "c", "d", "e", "f", "g", "h", "i"];
+    let options = DiffOptions::new().merge_gap(usize::MAX);
+    assert_eq!(hunks_with(&a, &b, &options).len(), 1);
+    assert_eq!(diff_stats_with(&a, &b, &options).hunks(), 1);
+    assert_eq!(hunks(&a, &b).len(), 2);
+}
+
+#[test]
+fn tidy_slide_block() {
+    // Check that an inserted block is slid to the position where it forms a complete nested
+    // structure.
+    let a = ["struct s {", "\tstruct {", "\t\tint y;", "\t} c;", "};"];
+    let b = [
+        "struct s {",
+        "\tstruct {",
+        "\t\tint y;",
+        "\t} b;",
+        "\tstruct {",
+        "\t\tint y;",
+        "\t} c;",
+        "};",
+    ];
+    let mut out = Vec::new();
+    let result = write_with(&a, &b, &DiffOptions::new(), &mut out);
+    assert!(result.is_ok());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,4 +1,7 @@\n",
+            " struct s {\n",
+            "+\tstruct {\n",
+            "+\t\tint y;\n",
+            "+\t} b;\n",
+            " \tstruct {\n",
+            " \t\tint y;\n",
+            " \t} c;\n", //
+        )
+    );
+}
+
+#[test]
+fn tidy_group_changes() {
+    // Check that removed lines are put before inserted lines in a group of changes.
+    let a = ["a", "c"];
+    let b = ["c", "d", "c"];
+    let edit_script = myers_linear(&a, &b, usize::MAX);
+    assert_eq!(
+        edit_script,
+        [
+            Edit::InsertB(0),
+            Edit::InsertB(1),
+            Edit::RemoveA(0),
+            Edit::KeepA(1)
+        ]
+    );
+    let edit_script = tidy_edit_script(&a, &b, edit_script, |_| 0);
+    assert_eq!(
+        edit_script,
+        [
+            Edit::RemoveA(0),
+            Edit::InsertB(0),
+            Edit::InsertB(1),
+            Edit::KeepA(1)
+        ]
+    );
+
+    // Check that a removed line is slid as far down as possible when the indentation does not
+    // suggest a better position.
+    let a = ["c", "a", "c", "c"];
+    let b = ["a", "c"];
+    let edit_script = myers(&a, &b);
+    assert_eq!(
+        edit_script,
+        [
+            Edit::RemoveA(0),
+            Edit::KeepA(1),
+            Edit::RemoveA(2),
+            Edit::KeepA(3)
+        ]
+    );
+    let edit_script = tidy_edit_script(&a, &b, edit_script, |_| 0);
+    assert_eq!(
+        edit_script,
+        [
+            Edit::RemoveA(0),
+            Edit::KeepA(1),
+            Edit::KeepA(2),
+            Edit::RemoveA(3)
+        ]
+    );
+}
+
+#[test]
+fn apply_hunks() {
+    // Check that applying the hunks between two inputs to the first one produces the second one.
+    let a = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k"];
+    let b = ["x", "a", "b", "c", "d", "e", "f", "g", "h", "j", "y", "k"];
+    let hunks = hunks(&a, &b);
+    assert_eq!(apply(&a, &hunks), Ok(b.to_vec()));
+    assert_eq!(apply(&[], &super::hunks(&[], &b)), Ok(b.to_vec()));
+    assert_eq!(apply(&a, &super::hunks(&a, &[])), Ok(vec![]));
+
+    // Check that the hunks are found at an offset.
+    let c = [
+        "p", "q", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "r",
+    ];
+    assert_eq!(
+        apply(&c, &hunks),
+        Ok(vec![
+            "p", "q", "x", "a", "b", "c", "d", "e", "f", "g", "h", "j", "y", "k", "r"
+        ])
+    );
+
+    // Check that a hunk with a mismatched context line is applied only with fuzz.
+    let c = ["a", "b", "c", "d", "e", "z", "g", "h", "i", "j", "k"];
+    assert_eq!(apply(&c, &hunks), Err(ApplyError { hunk_index: 1 }));
+    assert_eq!(
+        apply(&c, &hunks).unwrap_err().to_string(),
+        "Hunk #2 failed to apply"
+    );
+    assert_eq!(
+        apply_with_fuzz(&c, &hunks, 1),
+        Ok(vec![
+            "x", "a", "b", "c", "d", "e", "z", "g", "h", "j", "y", "k"
+        ])
+    );
+
+    // Check that a hunk with a mismatched removed line is not applied.
+    let c = ["a", "b", "c", "d", "e", "f", "g", "h", "z", "j", "k"];
+    assert_eq!(
+        apply_with_fuzz(&c, &hunks, 3),
+        Err(ApplyError { hunk_index: 1 })
+    );
+}
+
+#[test]
+fn chunked_inputs() {
+    // Check that inputs above the chunked threshold are split into chunks, which are compared
+    // independently, and that the result does not depend on the number of workers.
+    let a: Vec<_> = (0..CHUNKED_THRESHOLD / 2 + 1)
+        .map(|i| i.to_string())
+        .collect();
+    let mut b = a.clone();
+    for i in (0..b.len()).step_by(997) {
+        b[i] = "x".to_string();
+    }
+    b.insert(30000, "y".to_string());
+    let options = DiffOptions::new().num_workers(4);
+    let edit_script = chunked(&a, &b, &options, 4);
+    assert_eq!(edit_script, chunked(&a, &b, &options, 1));
+
+    let hunks = hunks_with(&a, &b, &options);
+    assert_eq!(hunks, hunks_with(&a, &b, &DiffOptions::new()));
+    assert_eq!(apply(&a, &hunks), Ok(b.clone()));
+}
@@ -0,0 +1,253 @@
+// Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! C bindings of the core APIs.
+//!
+//! A corpus is represented by an opaque `ksym_corpus` handle. Functions which can fail return
+//! a negative value and, if the `err` parameter is not NULL, store a description of the error in
+//! a newly allocated string. All strings returned by the library must be released by
+//! [`ksym_string_free()`]. The declarations are provided in `include/suse_kabi_tools.h`.
+//!
+//! A panic inside the library is caught at the boundary of each function and does not unwind into
+//! the caller. A function which can fail then reports an internal error, other functions return
+//! their value for a missing result.
+//!
+//! The shared library is built from the `suse-kabi-tools-ffi` package in the `ffi` directory, by
+//! `cargo build --release -p suse-kabi-tools-ffi`.
+
+use crate::sym::SymCorpus;
+use std::any::Any;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+#[cfg(test)]
+mod tests;
+
+/// Converts the specified string into a newly allocated C string. Any interior NUL byte is
+/// replaced by a question mark.
+fn into_c_string(string: String) -> *mut c_char {
+    let string = CString::new(string.replace('\0', "?")).unwrap();
+    string.into_raw()
+}
+
+/// Stores the description of an error in `err`, if it is not NULL, and returns -1.
+///
+/// # Safety
+///
+/// The `err` pointer must be NULL or valid for writes.
+unsafe fn set_error(err: *mut *mut c_char, desc: String) -> c_int {
+    if !err.is_null() {
+        *err = into_c_string(desc);
+    }
+    -1
+}
+
+/// Returns the message of a panic with the specified payload.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Runs `body` and returns its result, or `on_panic` if it panics, so that the panic does not
+/// unwind into the C caller.
+fn guard<T, F: FnOnce() -> T>(on_panic: T, body: F) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+/// Runs `body` and returns its result. If it panics, stores the description of the panic in `err`
+/// and returns -1, so that the panic does not unwind into the C caller.
+///
+/// # Safety
+///
+/// The `err` pointer must be NULL or valid for writes.
+unsafe fn guard_err<F: FnOnce() -> c_int>(err: *mut *mut c_char, body: F) -> c_int {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(res) => res,
+        Err(payload) => set_error(
+            err,
+            format!("Internal error: {}", panic_message(payload.as_ref())),
+        ),
+    }
+}
+
+/// Converts the specified C string to a [`str`], or returns an error description if it is NULL or
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// The `string` pointer must be NULL or point to a NUL-terminated string.
+unsafe fn to_str<'a>(string: *const c_char, what: &str) -> Result<&'a str, String> {
+    if string.is_null() {
+        return Err(format!("The {} is NULL", what));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| format!("The {} is not valid UTF-8", what))
+}
+
+/// Creates a new empty corpus. The returned handle must be released by [`ksym_corpus_free()`].
+#[no_mangle]
+pub extern "C" fn ksym_corpus_new() -> *mut SymCorpus {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(SymCorpus::new()))
+    })
+}
+
+/// Releases a corpus created by [`ksym_corpus_new()`].
+///
+/// # Safety
+///
+/// The `corpus` must be NULL or a handle returned by [`ksym_corpus_new()`] which was not released
+/// yet.
+#[no_mangle]
+pub unsafe extern "C" fn ksym_corpus_free(corpus: *mut SymCorpus) {
+    guard((), || {
+        if !corpus.is_null() {
+            drop(Box::from_raw(corpus));
+        }
+    })
+}
+
+/// Releases a string returned by the library.
+///
+/// # Safety
+///
+/// The `string` must be NULL or a string returned by the library which was not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn ksym_string_free(string: *mut c_char) {
+    guard((), || {
+        if !string.is_null() {
+            drop(CString::from_raw(string));
+        }
+    })
+}
+
+/// Loads symtypes data from the file or directory `path` into the corpus, using `num_workers`
+/// workers. Returns 0 on success and -1 on error.
+///
+/// # Safety
+///
+/// The `corpus` must be a valid handle, `path` must point to a NUL-terminated string and `err` must
+/// be NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ksym_corpus_load(
+    corpus: *mut SymCorpus,
+    path: *const c_char,
+    num_workers: c_int,
+    err: *mut *mut c_char,
+) -> c_int {
+    guard_err(err, || {
+        let path = match to_str(path, "path") {
+            Ok(path) => path,
+            Err(desc) => return set_error(err, desc),
+        };
+        match (*corpus).load(path, num_workers) {
+            Ok(()) => 0,
+            Err(load_err) => set_error(err, load_err.to_string()),
+        }
+    })
+}
+
+/// Writes the corpus in the consolidated form into the file `path`. Returns 0 on success and -1 on
+/// error.
+///
+/// # Safety
+///
+/// The `corpus` must be a valid handle, `path` must point to a NUL-terminated string and `err` must
+/// be NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ksym_corpus_write_consolidated(
+    corpus: *const SymCorpus,
+    path: *const c_char,
+    err: *mut *mut c_char,
+) -> c_int {
+    guard_err(err, || {
+        let path = match to_str(path, "path") {
+            Ok(path) => path,
+            Err(desc) => return set_error(err, desc),
+        };
+        match (*corpus).write_consolidated(path) {
+            Ok(()) => 0,
+            Err(write_err) => set_error(err, write_err.to_string()),
+        }
+    })
+}
+
+/// Compares the corpus with `other`, using `num_workers` workers. Returns 0 if no differences were
+/// found, 1 if differences were found and -1 on error. If `report` is not NULL, a human-readable
+/// report of the differences is stored in it.
+///
+/// # Safety
+///
+/// The `corpus` and `other` must be valid handles, and `report` and `err` must be NULL or valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn ksym_corpus_compare(
+    corpus: *const SymCorpus,
+    other: *const SymCorpus,
+    num_workers: c_int,
+    report: *mut *mut c_char,
+    err: *mut *mut c_char,
+) -> c_int {
+    guard_err(err, || {
+        let result = (*corpus).compare(&*other, num_workers);
+        if !report.is_null() {
+            let mut out = Vec::new();
+            if let Err(write_err) = result.write_report(&mut out) {
+                return set_error(err, write_err.to_string());
+            }
+            *report = into_c_string(String::from_utf8_lossy(&out).into_owned());
+        }
+        if result.is_empty() {
+            0
+        } else {
+            1
+        }
+    })
+}
+
+/// Returns 1 if the corpus contains the export `name`, and 0 otherwise.
+///
+/// # Safety
+///
+/// The `corpus` must be a valid handle and `name` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ksym_corpus_has_export(
+    corpus: *const SymCorpus,
+    name: *const c_char,
+) -> c_int {
+    guard(0, || match to_str(name, "name") {
+        Ok(name) => (*corpus).get_export(name).is_some().into(),
+        Err(_) => 0,
+    })
+}
+
+/// Returns the pretty-formatted definition of the type or export `name` in the file `file` as
+/// a newly allocated string, or NULL if the file does not contain such a record.
+///
+/// # Safety
+///
+/// The `corpus` must be a valid handle, and `file` and `name` must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn ksym_corpus_pretty_type(
+    corpus: *const SymCorpus,
+    file: *const c_char,
+    name: *const c_char,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let (Ok(file), Ok(name)) = (to_str(file, "file"), to_str(name, "name")) else {
+            return ptr::null_mut();
+        };
+        match (*corpus).pretty_type(Path::new(file), name) {
+            Some(lines) => into_c_string(lines.join("\n")),
+            None => ptr::null_mut(),
+        }
+    })
+}
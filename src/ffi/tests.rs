@@ -0,0 +1,79 @@
+// Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use super::*;
+
+/// Converts a string returned by the library to a [`String`] and releases it.
+unsafe fn take_string(string: *mut c_char) -> String {
+    assert!(!string.is_null());
+    let res = CStr::from_ptr(string).to_str().unwrap().to_string();
+    ksym_string_free(string);
+    res
+}
+
+#[test]
+fn ffi_load_compare() {
+    // Check that corpuses can be loaded, compared and queried through the C interface.
+    unsafe {
+        let corpus = ksym_corpus_new();
+        let other = ksym_corpus_new();
+        let mut err = ptr::null_mut();
+        let path = CString::new("tests/compare_cmd/a.symtypes").unwrap();
+        assert_eq!(ksym_corpus_load(corpus, path.as_ptr(), 1, &mut err), 0);
+        let path = CString::new("tests/compare_cmd/b.symtypes").unwrap();
+        assert_eq!(ksym_corpus_load(other, path.as_ptr(), 1, &mut err), 0);
+        assert!(err.is_null());
+
+        let name = CString::new("foo").unwrap();
+        assert_eq!(ksym_corpus_has_export(corpus, name.as_ptr()), 1);
+        let file = CString::new("tests/compare_cmd/a.symtypes").unwrap();
+        let pretty = ksym_corpus_pretty_type(corpus, file.as_ptr(), name.as_ptr());
+        assert_eq!(take_string(pretty), "void foo (\n\tint a\n)");
+
+        let mut report = ptr::null_mut();
+        assert_eq!(
+            ksym_corpus_compare(corpus, corpus, 1, &mut report, &mut err),
+            0
+        );
+        assert_eq!(take_string(report), "");
+        assert_eq!(
+            ksym_corpus_compare(corpus, other, 1, &mut report, &mut err),
+            1
+        );
+        assert!(take_string(report).contains("because of a changed 'foo':"));
+
+        ksym_corpus_free(corpus);
+        ksym_corpus_free(other);
+    }
+}
+
+#[test]
+fn ffi_load_error() {
+    // Check that a failure to load a corpus is reported through the error string.
+    unsafe {
+        let corpus = ksym_corpus_new();
+        let mut err = ptr::null_mut();
+        let path = CString::new("tests/missing.symtypes").unwrap();
+        assert_eq!(ksym_corpus_load(corpus, path.as_ptr(), 1, &mut err), -1);
+        assert!(take_string(err).starts_with("Failed to query path 'tests/missing.symtypes'"));
+        assert_eq!(ksym_corpus_load(corpus, ptr::null(), 1, &mut err), -1);
+        assert_eq!(take_string(err), "The path is NULL");
+        ksym_corpus_free(corpus);
+    }
+}
+
+#[test]
+fn ffi_panic_guard() {
+    // Check that a panic is reported as an error instead of unwinding into the C caller.
+    unsafe {
+        let mut err = ptr::null_mut();
+        assert_eq!(guard_err(&mut err, || panic!("Broken invariant")), -1);
+        assert_eq!(take_string(err), "Internal error: Broken invariant");
+        assert_eq!(
+            guard_err(ptr::null_mut(), || panic!("Broken invariant")),
+            -1
+        );
+        assert_eq!(guard_err(&mut err, || 1), 1);
+    }
+    assert_eq!(guard(0, || panic!("Broken invariant {}", 1)), 0);
+}
@@ -0,0 +1,171 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! Expansion of glob patterns into paths.
+//!
+//! The patterns are matched against the filesystem independently of the shell and the platform,
+//! which allows to specify a large number of files without exceeding the limit on the length of
+//! command-line arguments. A pattern component can contain `*` matching any sequence of
+//! characters, `?` matching a single character and `[...]` matching one character from a set. The
+//! set can include ranges such as `a-z` and is negated by a leading `!` or `^`. A component `**`
+//! matches zero or more directories. Wildcards don't match a leading `.` of a file name and `**`
+//! doesn't descend into hidden directories or follow symbolic links.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// Returns whether the `pattern` contains any wildcard characters.
+pub fn is_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Expands the `pattern` into a sorted list of existing paths which match it.
+///
+/// An empty list is returned if no path matches the pattern.
+pub fn expand(pattern: &str) -> Result<Vec<PathBuf>, crate::Error> {
+    let components: Vec<_> = Path::new(pattern).components().collect();
+    let mut paths = Vec::new();
+    expand_components(PathBuf::new(), &components, &mut paths)?;
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Expands the remaining pattern `components` relative to the already matched `base` path and
+/// adds all found paths to `paths`.
+fn expand_components(
+    base: PathBuf,
+    components: &[Component],
+    paths: &mut Vec<PathBuf>,
+) -> Result<(), crate::Error> {
+    let Some((component, rest)) = components.split_first() else {
+        if !base.as_os_str().is_empty() && fs::symlink_metadata(&base).is_ok() {
+            paths.push(base);
+        }
+        return Ok(());
+    };
+
+    let name = match component {
+        Component::Normal(name) => name.to_str(),
+        _ => None,
+    };
+    match name {
+        Some("**") => {
+            // Match zero directories, and then one or more directories by keeping the `**`
+            // component for each subdirectory.
+            expand_components(base.clone(), rest, paths)?;
+            for (entry_name, entry_path) in read_dir(&base)? {
+                let is_dir = fs::symlink_metadata(&entry_path).is_ok_and(|md| md.is_dir());
+                if is_dir && !entry_name.starts_with('.') {
+                    expand_components(entry_path, components, paths)?;
+                }
+            }
+        }
+        Some(name) if is_pattern(name) => {
+            for (entry_name, entry_path) in read_dir(&base)? {
+                if matches_name(name, &entry_name) {
+                    expand_components(entry_path, rest, paths)?;
+                }
+            }
+        }
+        _ => expand_components(base.join(component), rest, paths)?,
+    }
+    Ok(())
+}
+
+/// Reads the names and paths of all entries in the directory `base`, or the current directory if
+/// `base` is empty. Nothing is returned if `base` is not a directory. Entries with names which are
+/// not valid UTF-8 are skipped because they cannot be matched by a pattern.
+fn read_dir(base: &Path) -> Result<Vec<(String, PathBuf)>, crate::Error> {
+    let dir = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base
+    };
+    if !fs::metadata(dir).is_ok_and(|md| md.is_dir()) {
+        return Ok(Vec::new());
+    }
+
+    let err_desc = || format!("Failed to read directory '{}'", crate::display_path(dir));
+    let mut entries = Vec::new();
+    for maybe_entry in fs::read_dir(dir).map_err(|err| crate::Error::new_io(&err_desc(), err))? {
+        let entry = maybe_entry.map_err(|err| crate::Error::new_io(&err_desc(), err))?;
+        if let Ok(name) = entry.file_name().into_string() {
+            let path = base.join(&name);
+            entries.push((name, path));
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns whether the file `name` matches the `pattern` of a single path component.
+pub fn matches_name(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<_> = pattern.chars().collect();
+    let name: Vec<_> = name.chars().collect();
+    matches_chars(&pattern, &name)
+}
+
+/// Returns whether the whole `string` matches the `pattern`. Unlike in [`matches_name()`], `*`
+/// and `?` match also `/` and a leading `.`, which allows to match arbitrary strings, such as
+/// paths of modules or names of symbols.
+pub fn matches(pattern: &str, string: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let string: Vec<_> = string.chars().collect();
+    matches_chars(&pattern, &string)
+}
+
+/// Returns whether the characters of `name` match the characters of `pattern`.
+fn matches_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|idx| matches_chars(rest, &name[idx..])),
+        Some(('?', rest)) => !name.is_empty() && matches_chars(rest, &name[1..]),
+        Some(('[', rest)) => {
+            let Some(&ch) = name.first() else {
+                return false;
+            };
+            match matches_class(rest, ch) {
+                Some((true, rest)) => matches_chars(rest, &name[1..]),
+                Some((false, _)) => false,
+                // An unterminated set is matched literally.
+                None => ch == '[' && matches_chars(rest, &name[1..]),
+            }
+        }
+        Some((&pattern_ch, rest)) => {
+            name.first() == Some(&pattern_ch) && matches_chars(rest, &name[1..])
+        }
+    }
+}
+
+/// Matches the character `ch` against a set which follows `[` in the `pattern`, and returns
+/// whether it matches together with the rest of the pattern after the closing `]`, or [`None`] if
+/// the set is not terminated. A `]` right at the start of the set is taken literally.
+fn matches_class(pattern: &[char], ch: char) -> Option<(bool, &[char])> {
+    let (negated, mut pattern) = match pattern.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match pattern {
+            [] => return None,
+            [']', rest @ ..] if !first => return Some((matched != negated, rest)),
+            [low, '-', high, rest @ ..] if *high != ']' => {
+                matched |= (*low..=*high).contains(&ch);
+                pattern = rest;
+            }
+            [set_ch, rest @ ..] => {
+                matched |= *set_ch == ch;
+                pattern = rest;
+            }
+        }
+        first = false;
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use super::*;
+
+#[test]
+fn match_names() {
+    // Check that file names are matched against wildcards and character sets.
+    assert!(matches_name("*.symtypes", "a.symtypes"));
+    assert!(matches_name("*", "a.symtypes"));
+    assert!(!matches_name("*.symtypes", "a.symvers"));
+    assert!(matches_name("?.symtypes", "a.symtypes"));
+    assert!(!matches_name("?.symtypes", "ab.symtypes"));
+    assert!(matches_name("[ab].symtypes", "b.symtypes"));
+    assert!(!matches_name("[ab].symtypes", "c.symtypes"));
+    assert!(matches_name("[a-c].symtypes", "c.symtypes"));
+    assert!(matches_name("[!a-c].symtypes", "d.symtypes"));
+    assert!(!matches_name("[^a-c].symtypes", "b.symtypes"));
+    assert!(matches_name("[]a].symtypes", "].symtypes"));
+    assert!(matches_name("[a.symtypes", "[a.symtypes"));
+    assert!(matches_name("*a*b*", "xaybz"));
+    assert!(!matches_name("*a*b*", "xbyaz"));
+}
+
+#[test]
+fn match_hidden_names() {
+    // Check that wildcards don't match a leading dot of a file name.
+    assert!(!matches_name("*", ".ksymtypes.toml"));
+    assert!(!matches_name("?ksymtypes.toml", ".ksymtypes.toml"));
+    assert!(matches_name(".*", ".ksymtypes.toml"));
+}
+
+#[test]
+fn match_strings() {
+    // Check that wildcards in string patterns match also slashes and leading dots.
+    assert!(matches("drivers/*", "drivers/scsi/sd_mod"));
+    assert!(matches("*/sd_mod", "drivers/scsi/sd_mod"));
+    assert!(!matches("drivers/*", "fs/ext4"));
+    assert!(matches("*", ".hidden"));
+    assert!(matches("?_foo", "__foo"));
+    assert!(matches("vmlinux", "vmlinux"));
+    assert!(!matches("vmlinux", "vmlinux2"));
+}
+
+#[test]
+fn expand_patterns() {
+    // Check that patterns are expanded into sorted lists of existing paths.
+    let paths = expand("tests/compare_cmd/*.symtypes").unwrap();
+    assert_eq!(
+        paths,
+        [
+            PathBuf::from("tests/compare_cmd/a.symtypes"),
+            PathBuf::from("tests/compare_cmd/b.symtypes"),
+        ]
+    );
+
+    let paths = expand("tests/consolidate_cmd*/a.symtypes").unwrap();
+    assert_eq!(
+        paths,
+        [
+            PathBuf::from("tests/consolidate_cmd/a.symtypes"),
+            PathBuf::from("tests/consolidate_cmd_errors/a.symtypes"),
+            PathBuf::from("tests/consolidate_cmd_no_validate/a.symtypes"),
+        ]
+    );
+
+    let paths = expand("tests/**/b.symtypes").unwrap();
+    assert!(paths.contains(&PathBuf::from("tests/compare_cmd/b.symtypes")));
+    assert!(paths.contains(&PathBuf::from("tests/consolidate_cmd/b.symtypes")));
+    assert!(paths.iter().all(|path| path.ends_with("b.symtypes")));
+
+    let paths = expand("tests/**/.ksymtypes.toml").unwrap();
+    assert_eq!(
+        paths,
+        [
+            PathBuf::from("tests/config_cmd/.ksymtypes.toml"),
+            PathBuf::from("tests/config_cmd_invalid/.ksymtypes.toml"),
+            PathBuf::from("tests/config_cmd_rules/.ksymtypes.toml"),
+        ]
+    );
+
+    let paths = expand("tests/compare_cmd/*.symvers").unwrap();
+    assert!(paths.is_empty());
+}
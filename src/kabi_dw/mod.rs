@@ -0,0 +1,236 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! Import of type dumps produced by kabi-dw.
+//!
+//! The kabi-dw tool extracts the types of exports from the DWARF data of the kernel and writes each
+//! symbol to a separate file named `<kind>--<name>.txt`, where the kind is `func`, `var`, `struct`,
+//! `union`, `enum` or `typedef`. A file starts with header lines in the form `<key>: <value>`,
+//! terminated by a `Symbol:` line, which is followed by the definition of the symbol. The
+//! definition refers to other symbols by their file names, as in `@"struct--foo.txt"`.
+//!
+//! The dumps are converted to symtypes data. Each function and variable becomes an export, each
+//! other symbol a type record with the respective prefix, for instance `s#foo`, and each reference
+//! a reference to such a record. Members of structures and unions, which are written on separate
+//! lines, are terminated by `;` as in symtypes data. The exports are grouped to symtypes files by the source file
+//! recorded in their `File:` header, with the extension replaced by `.symtypes`, and each such file
+//! receives all the types reachable from its exports.
+
+use crate::{debug, ParseError, ParseErrorKind, PathFile};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// The name of the symtypes file which receives exports with no recorded source file.
+const UNKNOWN_FILE: &str = "unknown.symtypes";
+
+/// A symbol parsed from a kabi-dw dump.
+#[derive(Debug)]
+struct Dump {
+    /// The name of the symtypes record, for instance `s#foo`.
+    name: String,
+    /// Whether the symbol is a function or variable.
+    is_export: bool,
+    /// The source file recorded in the `File:` header.
+    file: Option<String>,
+    /// The words of the definition, with references converted to symtypes record names.
+    words: Vec<String>,
+}
+
+/// Returns the symtypes record name for a kabi-dw file name, or [`None`] if the file name does not
+/// have the form of a kabi-dw dump.
+fn record_name(file_name: &str) -> Option<(String, bool)> {
+    let stem = file_name.strip_suffix(".txt")?;
+    let (kind, name) = stem.split_once("--")?;
+    if name.is_empty() {
+        return None;
+    }
+    let prefix = match kind {
+        "func" | "var" => return Some((name.to_string(), true)),
+        "struct" => "s#",
+        "union" => "u#",
+        "enum" => "e#",
+        "typedef" => "t#",
+        _ => return None,
+    };
+    Some((format!("{}{}", prefix, name), false))
+}
+
+/// Returns whether the specified directory contains kabi-dw dumps, which is determined by the
+/// presence of a function or variable dump directly in the directory.
+pub fn is_dump_dir<P: AsRef<Path>>(path: P) -> bool {
+    let Ok(entries) = fs::read_dir(path) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        entry.file_name().to_str().is_some_and(|file_name| {
+            (file_name.starts_with("func--") || file_name.starts_with("var--"))
+                && record_name(file_name).is_some()
+        })
+    })
+}
+
+/// Reads all kabi-dw dumps in the directory `path` and its subdirectories and converts them to
+/// symtypes data. Returns the paths and contents of the resulting symtypes files, sorted by the
+/// path.
+///
+/// A dump which has no `Symbol:` line is rejected with [`ParseErrorKind::MalformedDump`]. Files
+/// which are not named as kabi-dw dumps are skipped.
+pub fn read_dir<P: AsRef<Path>>(path: P) -> Result<Vec<(PathBuf, String)>, crate::Error> {
+    let path = path.as_ref();
+    debug!("Loading kabi-dw dumps from '{}'", path.display());
+
+    let mut dumps = BTreeMap::new();
+    read_dir_inner(path, path, &mut dumps)?;
+    Ok(convert(&dumps))
+}
+
+/// Reads all kabi-dw dumps in the directory `dir_path`, which is a subdirectory of `root`, into
+/// `dumps`.
+fn read_dir_inner(
+    root: &Path,
+    dir_path: &Path,
+    dumps: &mut BTreeMap<String, Dump>,
+) -> Result<(), crate::Error> {
+    let err_desc = || {
+        format!(
+            "Failed to read directory '{}'",
+            crate::display_path(dir_path)
+        )
+    };
+    let mut entries = fs::read_dir(dir_path)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| crate::Error::new_io(&err_desc(), err))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let entry_path = entry.path();
+        let file_type = entry
+            .file_type()
+            .map_err(|err| crate::Error::new_io(&err_desc(), err))?;
+        if file_type.is_dir() {
+            read_dir_inner(root, &entry_path, dumps)?;
+            continue;
+        }
+
+        let Some((name, is_export)) = entry.file_name().to_str().and_then(record_name) else {
+            continue;
+        };
+        let mut data = String::new();
+        PathFile::open(&entry_path)
+            .and_then(|mut file| file.read_to_string(&mut data))
+            .map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to read file '{}'", crate::display_path(&entry_path)),
+                    err,
+                )
+            })?;
+        let rel_path = entry_path.strip_prefix(root).unwrap_or(&entry_path);
+        let dump = parse_dump(rel_path, name, is_export, &data)?;
+        // Keep the first dump of a symbol which is present in multiple subdirectories.
+        dumps.entry(dump.name.clone()).or_insert(dump);
+    }
+    Ok(())
+}
+
+/// Parses the contents of a kabi-dw dump of the symbol `name`, read from `path`.
+fn parse_dump(path: &Path, name: String, is_export: bool, data: &str) -> Result<Dump, ParseError> {
+    let mut file = None;
+    let mut lines = data.lines().enumerate();
+    loop {
+        let Some((_, line)) = lines.next() else {
+            return Err(ParseError::new(
+                ParseErrorKind::MalformedDump,
+                path,
+                data.lines().count(),
+                "Expected a 'Symbol:' line".to_string(),
+            ));
+        };
+        if line.trim_end() == "Symbol:" {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("File:") {
+            file = Some(value.trim().to_string());
+        }
+    }
+
+    // Each member of a structure or union is written on a separate line, terminate the members by
+    // a semicolon to match the symtypes form.
+    let is_aggregate = name.starts_with("s#") || name.starts_with("u#");
+    let mut words = Vec::new();
+    let mut depth: usize = 0;
+    for (_, line) in lines {
+        for word in line.split_ascii_whitespace() {
+            match word {
+                "{" => depth += 1,
+                "}" => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            words.push(
+                word.strip_prefix("@\"")
+                    .and_then(|target| target.strip_suffix('"'))
+                    .and_then(record_name)
+                    .map_or_else(|| word.to_string(), |(name, _)| name),
+            );
+        }
+        if is_aggregate && depth > 0 && words.last().is_some_and(|word| word != "{") {
+            words.push(";".to_string());
+        }
+    }
+
+    Ok(Dump {
+        name,
+        is_export,
+        file,
+        words,
+    })
+}
+
+/// Converts the dumps to symtypes files, grouping the exports by their source files.
+fn convert(dumps: &BTreeMap<String, Dump>) -> Vec<(PathBuf, String)> {
+    let mut files: BTreeMap<PathBuf, Vec<&Dump>> = BTreeMap::new();
+    for dump in dumps.values().filter(|dump| dump.is_export) {
+        let file = match &dump.file {
+            Some(file) => Path::new(file).with_extension("symtypes"),
+            None => PathBuf::from(UNKNOWN_FILE),
+        };
+        files.entry(file).or_default().push(dump);
+    }
+
+    files
+        .into_iter()
+        .map(|(path, exports)| {
+            // Collect the types reachable from the exports.
+            let mut types = BTreeSet::new();
+            let mut pending: Vec<_> = exports.iter().map(|dump| &dump.words).collect();
+            while let Some(words) = pending.pop() {
+                for word in words {
+                    if let Some(dump) = dumps.get(word.as_str()) {
+                        if !dump.is_export && types.insert(word.as_str()) {
+                            pending.push(&dump.words);
+                        }
+                    }
+                }
+            }
+
+            let mut data = String::new();
+            let records = types
+                .iter()
+                .map(|name| &dumps[*name])
+                .chain(exports.iter().copied());
+            for dump in records {
+                data.push_str(&dump.name);
+                for word in &dump.words {
+                    data.push(' ');
+                    data.push_str(word);
+                }
+                data.push('\n');
+            }
+            (path, data)
+        })
+        .collect()
+}
@@ -0,0 +1,81 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use super::*;
+
+#[test]
+fn record_names() {
+    // Check that the names of dump files are mapped to symtypes record names.
+    assert_eq!(
+        record_name("func--foo.txt"),
+        Some(("foo".to_string(), true))
+    );
+    assert_eq!(record_name("var--foo.txt"), Some(("foo".to_string(), true)));
+    assert_eq!(
+        record_name("struct--foo.txt"),
+        Some(("s#foo".to_string(), false))
+    );
+    assert_eq!(
+        record_name("union--foo.txt"),
+        Some(("u#foo".to_string(), false))
+    );
+    assert_eq!(
+        record_name("enum--foo.txt"),
+        Some(("e#foo".to_string(), false))
+    );
+    assert_eq!(
+        record_name("typedef--foo.txt"),
+        Some(("t#foo".to_string(), false))
+    );
+    assert_eq!(record_name("weak--foo.txt"), None);
+    assert_eq!(record_name("func--.txt"), None);
+    assert_eq!(record_name("func--foo"), None);
+}
+
+#[test]
+fn read_dumps() {
+    // Check that dumps are converted to symtypes files grouped by the source files of the exports,
+    // each with the types reachable from its exports.
+    assert!(is_dump_dir("tests/kabi_dw/a"));
+    assert!(!is_dump_dir("tests/kabi_dw"));
+
+    let files = read_dir("tests/kabi_dw/a").unwrap();
+    assert_eq!(
+        files,
+        [
+            (
+                PathBuf::from("kernel/baz.symtypes"),
+                concat!(
+                    "baz var baz int\n", //
+                )
+                .to_string()
+            ),
+            (
+                PathBuf::from("kernel/foo.symtypes"),
+                concat!(
+                    "s#bar struct bar { 0x0 a int ; 0x4 b t#u32 ; }\n",
+                    "t#u32 typedef u32 unsigned int\n",
+                    "foo func foo ( bar * s#bar ) int\n", //
+                )
+                .to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn read_malformed_dump() {
+    // Check that a dump without the symbol definition is rejected.
+    let result = parse_dump(
+        Path::new("func--foo.txt"),
+        "foo".to_string(),
+        true,
+        "Version: 1.0\nFile: kernel/foo.c\n",
+    );
+    let err = result.unwrap_err();
+    assert_eq!(err.kind(), ParseErrorKind::MalformedDump);
+    assert_eq!(
+        err.to_string(),
+        "func--foo.txt:3: Expected a 'Symbol:' line"
+    );
+}
@@ -1,13 +1,30 @@
 // Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
 // SPDX-License-Identifier: GPL-2.0-or-later
 
+// Tests use concat!() also for single-line inputs to keep the format of all test data consistent.
+#![cfg_attr(test, allow(clippy::useless_concat))]
+
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Instant;
 
+pub mod ast;
+pub mod btf;
 pub mod diff;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod glob;
+pub mod kabi_dw;
+#[cfg(feature = "rpm")]
+pub mod rpm;
+pub mod rules;
 pub mod sym;
+pub mod symvers;
 
 /// An error type for the crate, annotating standard errors with contextual information and
 /// providing custom errors.
@@ -17,7 +34,8 @@ pub enum Error {
         desc: String,
         io_err: std::io::Error,
     },
-    Parse(String),
+    Parse(ParseError),
+    Multiple(Vec<Error>),
 }
 
 impl Error {
@@ -29,9 +47,32 @@ impl Error {
         }
     }
 
-    /// Creates a new `Error::Parse`.
-    fn new_parse(desc: &str) -> Self {
-        Error::Parse(desc.to_string())
+    /// Creates an error from a non-empty list of errors, which is `Error::Multiple` only if the
+    /// list has more than one item.
+    fn from_multiple(mut errors: Vec<Error>) -> Self {
+        assert!(!errors.is_empty());
+        if errors.len() == 1 {
+            errors.pop().unwrap()
+        } else {
+            Error::Multiple(errors)
+        }
+    }
+
+    /// Records the directory to which the paths of files in parse errors are relative.
+    fn with_root(self, root: &Path) -> Self {
+        match self {
+            Self::Parse(err) => Self::Parse(err.with_root(root)),
+            Self::Multiple(errors) => {
+                Self::Multiple(errors.into_iter().map(|err| err.with_root(root)).collect())
+            }
+            err => err,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
     }
 }
 
@@ -44,7 +85,295 @@ impl std::fmt::Display for Error {
                 write!(f, "{}: ", desc)?;
                 io_err.fmt(f)
             }
-            Self::Parse(desc) => write!(f, "{}", desc),
+            Self::Parse(err) => err.fmt(f),
+            Self::Multiple(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    err.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A kind of a parse error, allowing to handle specific errors programmatically.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// A record has no name.
+    MissingName,
+    /// A record with the same name is present multiple times in a file.
+    DuplicateRecord,
+    /// A record specifies a variant but the file is not consolidated.
+    UnexpectedVariant,
+    /// A referenced type is not defined.
+    UndefinedType,
+    /// An implicitly referenced type has multiple variants in a consolidated file.
+    AmbiguousType,
+    /// An export is defined in multiple files.
+    DuplicateExport,
+    /// A line of a symvers file does not have the expected format.
+    MalformedSymvers,
+    /// A line of a severities file does not have the expected format.
+    MalformedRule,
+    /// A kabi-dw dump does not have the expected format.
+    MalformedDump,
+    /// A JSON document is not valid or does not have the expected structure.
+    MalformedJson,
+    /// A consolidated file declares a format version which is not supported.
+    UnsupportedVersion,
+    /// A CRC recorded in a consolidated file is not a valid hexadecimal number.
+    InvalidCrc,
+}
+
+/// An error found when parsing input data, with the location of the problem.
+#[derive(Debug)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    path: PathBuf,
+    root: Option<Box<Path>>,
+    line: usize,
+    column: Option<usize>,
+    message: String,
+    source_line: Option<String>,
+}
+
+impl ParseError {
+    /// Maximum number of characters of the offending line shown on each side of the column.
+    const EXCERPT_CONTEXT: usize = 40;
+
+    /// Creates a new error found on the line with the 0-based index `line_idx` of a given file.
+    fn new(kind: ParseErrorKind, path: &Path, line_idx: usize, message: String) -> Self {
+        Self {
+            kind,
+            path: path.to_path_buf(),
+            root: None,
+            line: line_idx + 1,
+            column: None,
+            message,
+            source_line: None,
+        }
+    }
+
+    /// Attaches the content of the offending line and the 0-based byte index of the problem on it.
+    fn with_source(mut self, source_line: &str, column_idx: usize) -> Self {
+        self.column = Some(column_idx + 1);
+        self.source_line = Some(source_line.to_string());
+        self
+    }
+
+    /// Records the directory to which the path of the file is relative, so the path can be shown
+    /// in the global [`PathStyle`]. Without a root, the path is always shown as stored.
+    fn with_root(mut self, root: &Path) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// Returns the path of the file in which the error was found.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the line number of the error, starting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the column number of the error, starting from 1, if the error relates to a specific
+    /// word on the line.
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    /// Returns the description of the error, without its location.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the content of the offending line, if available.
+    ///
+    /// For problems detected only after a whole file is read, the line is reconstructed from the
+    /// parsed record and its words are always separated by a single space.
+    pub fn source_line(&self) -> Option<&str> {
+        self.source_line.as_deref()
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:",
+            match &self.root {
+                Some(root) => display_stored_path(root, &self.path),
+                None => self.path.display().to_string(),
+            },
+            self.line
+        )?;
+        if let Some(column) = self.column {
+            write!(f, "{}:", column)?;
+        }
+        write!(f, " {}", self.message)?;
+
+        // Show the offending line with a marker under the problematic word. Long lines are
+        // shortened to a window around the column.
+        if let (Some(source_line), Some(column)) = (&self.source_line, self.column) {
+            let column_idx = column - 1;
+            let mut start = column_idx.saturating_sub(Self::EXCERPT_CONTEXT);
+            while !source_line.is_char_boundary(start) {
+                start -= 1;
+            }
+            let mut end = usize::min(column_idx + Self::EXCERPT_CONTEXT, source_line.len());
+            while !source_line.is_char_boundary(end) {
+                end += 1;
+            }
+            let prefix = if start > 0 { "..." } else { "" };
+            let suffix = if end < source_line.len() { "..." } else { "" };
+            let word_len = source_line[column_idx..]
+                .split(char::is_whitespace)
+                .next()
+                .unwrap()
+                .chars()
+                .count();
+            write!(
+                f,
+                "\n  {}{}{}\n  {}^{}",
+                prefix,
+                &source_line[start..end],
+                suffix,
+                " ".repeat(prefix.len() + source_line[start..column_idx].chars().count()),
+                "~".repeat(word_len.saturating_sub(1)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A kind of a warning, allowing to handle specific warnings programmatically.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum WarningKind {
+    /// A file with the same path is loaded multiple times.
+    DuplicateFile,
+    /// A malformed record is skipped in the lenient mode.
+    SkippedRecord,
+    /// A duplicate export is ignored.
+    DuplicateExport,
+    /// A referenced type is not defined and is skipped.
+    UndefinedType,
+    /// A type has an incomplete definition, which can cause or hide changes.
+    IncompleteType,
+    /// A symvers export has a zero CRC.
+    MissingCrc,
+    /// A symvers export has a CRC which is not a hexadecimal 32-bit number.
+    MalformedCrc,
+    /// A symvers export has an unknown export type.
+    UnknownExportType,
+}
+
+/// A warning about a problem which doesn't prevent an operation from completing.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Warning {
+    kind: WarningKind,
+    message: String,
+}
+
+impl Warning {
+    /// Returns the kind of the warning.
+    pub fn kind(&self) -> WarningKind {
+        self.kind
+    }
+
+    /// Returns the description of the warning.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// A receiver of warnings emitted when loading, consolidating and comparing symtypes data.
+///
+/// The warnings can be emitted by multiple workers simultaneously.
+pub trait Diagnostics: Send + Sync {
+    /// Handles a single warning.
+    fn warning(&self, warning: Warning);
+}
+
+/// An optional [`Diagnostics`] receiver stored in options of an operation. Warnings are output as
+/// debug messages if no receiver is set.
+#[derive(Clone, Default)]
+struct DiagnosticsSink(Option<Arc<dyn Diagnostics>>);
+
+impl DiagnosticsSink {
+    /// Emits a warning of the given kind.
+    fn warning(&self, kind: WarningKind, message: String) {
+        match &self.0 {
+            Some(diagnostics) => diagnostics.warning(Warning { kind, message }),
+            None => crate::debug!("{}", message),
+        }
+    }
+}
+
+impl std::fmt::Debug for DiagnosticsSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.0 {
+            Some(_) => write!(f, "Some(..)"),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+/// A phase of an operation reported to a progress observer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ProgressPhase {
+    /// Loading of symtypes data, counted in loaded files.
+    Load,
+    /// Consolidation of a corpus, counted in processed files.
+    Consolidate,
+    /// Comparison of two corpuses, counted in compared exports.
+    Compare,
+}
+
+/// A progress observer, called with the current phase, the number of items completed so far and
+/// the total number of items, if it is known in advance.
+///
+/// The observer can be called by multiple workers simultaneously.
+pub type ProgressFn = dyn Fn(ProgressPhase, usize, Option<usize>) + Send + Sync;
+
+/// An optional progress observer stored in options of an operation.
+#[derive(Clone, Default)]
+struct ProgressSink(Option<Arc<ProgressFn>>);
+
+impl ProgressSink {
+    /// Reports the progress of the given phase.
+    fn report(&self, phase: ProgressPhase, current: usize, total: Option<usize>) {
+        if let Some(progress) = &self.0 {
+            progress(phase, current, total);
+        }
+    }
+}
+
+impl std::fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.0 {
+            Some(_) => write!(f, "Some(..)"),
+            None => write!(f, "None"),
         }
     }
 }
@@ -61,6 +390,130 @@ impl MapIOErr for Result<(), std::io::Error> {
     }
 }
 
+/// Whether the target supports spawning threads. WebAssembly without the atomics feature, such as
+/// `wasm32-unknown-unknown`, does not, and all work is then done in the current thread.
+const HAS_THREADS: bool = !cfg!(all(target_family = "wasm", not(target_feature = "atomics")));
+
+/// Runs `work` by `num_workers` workers and returns their results.
+///
+/// Targets without thread support run the work by a single worker in the current thread.
+fn run_workers<T, F>(num_workers: i32, work: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn() -> T + Sync,
+{
+    if !HAS_THREADS {
+        return (0..num_workers.min(1)).map(|_| work()).collect();
+    }
+
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..num_workers).map(|_| s.spawn(&work)).collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+/// Formats `string` as a JSON string literal, including the surrounding quotes.
+pub fn json_string(string: &str) -> String {
+    let mut res = String::with_capacity(string.len() + 2);
+    res.push('"');
+    for ch in string.chars() {
+        match ch {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => res.push(ch),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// The maximum width of prose lines in reports in the mail format.
+const MAIL_WIDTH: usize = 72;
+
+/// Replaces non-ASCII characters in `line` by `?`, so that it can be included in a report in the
+/// mail format.
+fn mail_ascii(line: &str) -> String {
+    line.chars()
+        .map(|ch| if ch.is_ascii() { ch } else { '?' })
+        .collect()
+}
+
+/// Wraps `text` to lines of at most [`MAIL_WIDTH`] columns, each prefixed by `indent`, for
+/// a report in the mail format. The text is broken at whitespace, and words longer than the width
+/// are kept whole.
+fn mail_wrap(text: &str, indent: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in mail_ascii(text).split_ascii_whitespace() {
+        if !line.is_empty() && indent.len() + line.len() + 1 + word.len() > MAIL_WIDTH {
+            lines.push(format!("{}{}", indent, line));
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(format!("{}{}", indent, line));
+    }
+    lines
+}
+
+/// Returns the lines of a section heading in a report in the mail format, with the title
+/// underlined by `underline` characters.
+fn mail_heading(title: &str, underline: char) -> [String; 2] {
+    [title.to_string(), underline.to_string().repeat(title.len())]
+}
+
+/// Escapes characters of `text` which have a special meaning in Markdown, so that it is shown
+/// literally in a report in the Markdown format.
+fn markdown_escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(
+            ch,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            res.push('\\');
+        }
+        res.push(ch);
+    }
+    res
+}
+
+/// Escapes characters of `text` which have a special meaning in HTML, so that it can be included
+/// in a report in the HTML format.
+fn html_escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            ch => res.push(ch),
+        }
+    }
+    res
+}
+
+/// Parses a CRC in the `0x<hex>` format.
+fn parse_crc(crc: &str) -> Option<u32> {
+    let hex = crc.strip_prefix("0x")?;
+    if hex.is_empty() || hex.len() > 8 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
 /// A [`std::fs::File`] wrapper that tracks the file path to provide better error context.
 struct PathFile {
     path: PathBuf,
@@ -87,7 +540,10 @@ impl Read for PathFile {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.file.read(buf).map_err(|err| {
             io::Error::other(Error::new_io(
-                &format!("Failed to read data from file '{}'", self.path.display()),
+                &format!(
+                    "Failed to read data from file '{}'",
+                    display_path(&self.path)
+                ),
                 err,
             ))
         })
@@ -98,7 +554,10 @@ impl Write for PathFile {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.file.write(buf).map_err(|err| {
             io::Error::other(Error::new_io(
-                &format!("Failed to write data to file '{}'", self.path.display()),
+                &format!(
+                    "Failed to write data to file '{}'",
+                    display_path(&self.path)
+                ),
                 err,
             ))
         })
@@ -107,29 +566,423 @@ impl Write for PathFile {
     fn flush(&mut self) -> io::Result<()> {
         self.file.flush().map_err(|err| {
             io::Error::other(Error::new_io(
-                &format!("Failed to flush data to file '{}'", self.path.display()),
+                &format!(
+                    "Failed to flush data to file '{}'",
+                    display_path(&self.path)
+                ),
                 err,
             ))
         })
     }
 }
 
-/// Global debugging level.
-pub static DEBUG_LEVEL: std::sync::OnceLock<usize> = std::sync::OnceLock::new();
+/// A compression format of data.
+///
+/// The variants other than `None` are available only if the crate is built with the corresponding
+/// feature.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "xz")]
+    Xz,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Determines the compression format from the extension of the specified path. Returns
+    /// [`Compression::None`] if the extension doesn't indicate any supported format.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "gzip")]
+            Some("gz") => Self::Gzip,
+            #[cfg(feature = "xz")]
+            Some("xz") => Self::Xz,
+            #[cfg(feature = "zstd")]
+            Some("zst") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+
+    /// Returns the specified path without the extension of the given compression format.
+    pub fn strip_extension<P: AsRef<Path>>(self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        if self == Self::None {
+            path.to_path_buf()
+        } else {
+            path.with_extension("")
+        }
+    }
+
+    /// Wraps the provided input stream in a decoder of the given compression format.
+    pub fn reader<'a, R: Read + Send + 'a>(
+        self,
+        reader: R,
+    ) -> io::Result<Box<dyn Read + Send + 'a>> {
+        match self {
+            Self::None => Ok(Box::new(reader)),
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+            #[cfg(feature = "xz")]
+            Self::Xz => Ok(Box::new(xz2::read::XzDecoder::new_multi_decoder(reader))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+        }
+    }
+
+    /// Writes data produced by `write_fn` to the provided output stream, compressing it in the
+    /// given format.
+    fn write_with<W: Write, F: FnOnce(&mut dyn Write) -> Result<(), Error>>(
+        self,
+        writer: W,
+        write_fn: F,
+    ) -> Result<(), Error> {
+        match self {
+            Self::None => {
+                let mut writer = writer;
+                write_fn(&mut writer)
+            }
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                write_fn(&mut encoder)?;
+                encoder
+                    .finish()
+                    .map(|_| ())
+                    .map_io_err("Failed to finish compressed data")
+            }
+            #[cfg(feature = "xz")]
+            Self::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(writer, 6);
+                write_fn(&mut encoder)?;
+                encoder
+                    .finish()
+                    .map(|_| ())
+                    .map_io_err("Failed to finish compressed data")
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(writer, 0)
+                    .map_err(|err| Error::new_io("Failed to initialize zstd compression", err))?;
+                write_fn(&mut encoder)?;
+                encoder
+                    .finish()
+                    .map(|_| ())
+                    .map_io_err("Failed to finish compressed data")
+            }
+        }
+    }
+}
+
+/// A severity level of a log message, ordered from the most severe to the most verbose one.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum LogLevel {
+    /// An error which prevents an operation from completing.
+    Error,
+    /// A problem which doesn't prevent an operation from completing.
+    Warn,
+    /// An informational message about the progress of an operation.
+    Info,
+    /// A message useful for debugging.
+    Debug,
+    /// A detailed message useful for debugging.
+    Trace,
+}
+
+impl LogLevel {
+    /// Returns the level with the specified name, `error`, `warn`, `info`, `debug` or `trace`, or
+    /// [`None`] if the name is not recognized.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "error" => Some(Self::Error),
+            "warn" => Some(Self::Warn),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            "trace" => Some(Self::Trace),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the level.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+}
+
+/// A receiver of log messages.
+///
+/// The messages can be emitted by multiple workers simultaneously.
+pub trait LogSink: Send + Sync {
+    /// Handles a single message of the given level.
+    fn log(&self, level: LogLevel, message: &std::fmt::Arguments);
+}
+
+/// A [`LogSink`] which writes messages on the standard error, prefixed with their level and
+/// optionally with the time elapsed since the sink was created.
+pub struct StderrLogSink {
+    start: Option<Instant>,
+}
+
+impl StderrLogSink {
+    /// Creates a new sink, which prefixes the messages with timestamps if `timestamps` is set.
+    pub fn new(timestamps: bool) -> Self {
+        Self {
+            start: timestamps.then(Instant::now),
+        }
+    }
+}
+
+impl LogSink for StderrLogSink {
+    fn log(&self, level: LogLevel, message: &std::fmt::Arguments) {
+        match self.start {
+            Some(start) => eprintln!(
+                "[{:12.6}] {}: {}",
+                start.elapsed().as_secs_f64(),
+                level.name(),
+                message
+            ),
+            None => eprintln!("{}: {}", level.name(), message),
+        }
+    }
+}
+
+/// The maximum level of messages which are logged, stored as the discriminant of [`LogLevel`].
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(DEFAULT_LOG_LEVEL as u8);
+
+/// The global sink of log messages, or [`None`] to use [`StderrLogSink`].
+static LOG_SINK: RwLock<Option<Box<dyn LogSink>>> = RwLock::new(None);
+
+/// The maximum level of messages which are logged when the logging is not configured.
+const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Warn;
+
+/// Configures the global logging. Messages up to the `max_level` are passed to the `sink`.
+///
+/// The function can be called multiple times, for instance, by a long-running process which
+/// embeds the library, and each call replaces the previous configuration. When the logging is not
+/// configured, errors and warnings are written on the standard error by [`StderrLogSink`].
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use suse_kabi_tools::{debug, init_logging, LogLevel, LogSink, StderrLogSink};
+///
+/// struct Collector(Arc<Mutex<Vec<String>>>);
+///
+/// impl LogSink for Collector {
+///     fn log(&self, level: LogLevel, message: &std::fmt::Arguments) {
+///         self.0.lock().unwrap().push(format!("{}: {}", level.name(), message));
+///     }
+/// }
+///
+/// let messages = Arc::new(Mutex::new(Vec::new()));
+/// init_logging(LogLevel::Debug, Box::new(Collector(messages.clone())));
+/// debug!("first");
+/// init_logging(LogLevel::Info, Box::new(Collector(messages.clone())));
+/// debug!("second");
+/// init_logging(LogLevel::Warn, Box::new(StderrLogSink::new(false)));
+///
+/// assert_eq!(*messages.lock().unwrap(), ["debug: first"]);
+/// ```
+pub fn init_logging(max_level: LogLevel, sink: Box<dyn LogSink>) {
+    *LOG_SINK.write().unwrap_or_else(|err| err.into_inner()) = Some(sink);
+    set_log_level(max_level);
+}
+
+/// Sets the maximum level of messages which are logged, keeping the current sink.
+pub fn set_log_level(max_level: LogLevel) {
+    LOG_LEVEL.store(max_level as u8, Ordering::Relaxed);
+}
 
-/// Initializes the global debugging level, can be called only once.
-pub fn init_debug_level(level: usize) {
-    assert!(DEBUG_LEVEL.get().is_none());
-    DEBUG_LEVEL.get_or_init(|| level);
+/// Returns the maximum level of messages which are logged.
+pub fn log_level() -> LogLevel {
+    match LOG_LEVEL.load(Ordering::Relaxed) {
+        level if level == LogLevel::Error as u8 => LogLevel::Error,
+        level if level == LogLevel::Warn as u8 => LogLevel::Warn,
+        level if level == LogLevel::Info as u8 => LogLevel::Info,
+        level if level == LogLevel::Debug as u8 => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    }
 }
 
-/// Prints a formatted message to the standard error if debugging is enabled.
+/// Returns whether messages of the given level are logged.
+pub fn log_enabled(level: LogLevel) -> bool {
+    level <= log_level()
+}
+
+/// Passes a message of the given level to the global sink if the level is enabled. This is the
+/// function behind the [`log!`] macro and its variants.
+pub fn log(level: LogLevel, message: std::fmt::Arguments) {
+    if !log_enabled(level) {
+        return;
+    }
+    match &*LOG_SINK.read().unwrap_or_else(|err| err.into_inner()) {
+        Some(sink) => sink.log(level, &message),
+        None => StderrLogSink::new(false).log(level, &message),
+    }
+}
+
+/// A style in which paths of files are shown in messages and reports.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum PathStyle {
+    /// Show paths as they were specified or as they are stored in a corpus.
+    #[default]
+    AsStored,
+    /// Show absolute paths.
+    Absolute,
+    /// Show paths relative to the given base directory.
+    RelativeTo(PathBuf),
+}
+
+/// The global style in which paths are shown.
+static PATH_STYLE: RwLock<PathStyle> = RwLock::new(PathStyle::AsStored);
+
+/// Sets the global style in which paths are shown by [`display_path()`] and in error messages.
+pub fn set_path_style(style: PathStyle) {
+    *PATH_STYLE.write().unwrap_or_else(|err| err.into_inner()) = style;
+}
+
+/// Returns the global style in which paths are shown.
+pub fn path_style() -> PathStyle {
+    PATH_STYLE
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+}
+
+/// Formats the specified path in the global [`PathStyle`]. The path `-`, which denotes the
+/// standard input or output, is always shown as is.
+///
+/// Absolute and relative forms are computed lexically, without accessing the filesystem, so
+/// symbolic links are not resolved.
+///
+/// ```
+/// use std::path::{Path, PathBuf};
+/// use suse_kabi_tools::{display_path, set_path_style, PathStyle};
+///
+/// set_path_style(PathStyle::RelativeTo(PathBuf::from("/build/kernel")));
+/// assert_eq!(display_path(Path::new("/build/kernel/fs/ext4.symtypes")), "fs/ext4.symtypes");
+/// assert_eq!(display_path(Path::new("/build/other/../abi")), "../abi");
+/// set_path_style(PathStyle::Absolute);
+/// assert_eq!(display_path(Path::new("/build/./kernel//a.symtypes")), "/build/kernel/a.symtypes");
+/// assert_eq!(display_path(Path::new("-")), "-");
+/// ```
+pub fn display_path(path: &Path) -> String {
+    if path == Path::new("-") {
+        return path.display().to_string();
+    }
+    match &*PATH_STYLE.read().unwrap_or_else(|err| err.into_inner()) {
+        PathStyle::AsStored => path.display().to_string(),
+        PathStyle::Absolute => absolute_path(path).display().to_string(),
+        PathStyle::RelativeTo(base) => relative_path(&absolute_path(path), &absolute_path(base))
+            .display()
+            .to_string(),
+    }
+}
+
+/// Formats a path which is stored relative to the `root` directory in the global [`PathStyle`].
+/// The path is shown unchanged in the [`PathStyle::AsStored`] style.
+fn display_stored_path(root: &Path, path: &Path) -> String {
+    if path_style() == PathStyle::AsStored {
+        path.display().to_string()
+    } else {
+        display_path(&root.join(path))
+    }
+}
+
+/// Returns the absolute form of the specified path, with `.` and `..` components resolved
+/// lexically.
+fn absolute_path(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            _ => result.push(component),
+        }
+    }
+    result
+}
+
+/// Returns the absolute `path` expressed relative to the absolute `base` directory.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_components = path.components().collect::<Vec<_>>();
+    let base_components = base.components().collect::<Vec<_>>();
+    let common = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut result = PathBuf::new();
+    for _ in common..base_components.len() {
+        result.push(Component::ParentDir);
+    }
+    for component in &path_components[common..] {
+        result.push(component);
+    }
+    if result.as_os_str().is_empty() {
+        result.push(Component::CurDir);
+    }
+    result
+}
+
+/// Logs a formatted message of the given [`LogLevel`].
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::log($level, format_args!($($arg)*))
+    }
+}
+
+/// Logs a formatted message of the [`LogLevel::Error`] level.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log!($crate::LogLevel::Error, $($arg)*)
+    }
+}
+
+/// Logs a formatted message of the [`LogLevel::Warn`] level.
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::log!($crate::LogLevel::Warn, $($arg)*)
+    }
+}
+
+/// Logs a formatted message of the [`LogLevel::Info`] level.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log!($crate::LogLevel::Info, $($arg)*)
+    }
+}
+
+/// Logs a formatted message of the [`LogLevel::Debug`] level.
 #[macro_export]
 macro_rules! debug {
     ($($arg:tt)*) => {
-        if *$crate::DEBUG_LEVEL.get().unwrap_or(&0) > 0 {
-            eprintln!($($arg)*);
-        }
+        $crate::log!($crate::LogLevel::Debug, $($arg)*)
+    }
+}
+
+/// Logs a formatted message of the [`LogLevel::Trace`] level.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::log!($crate::LogLevel::Trace, $($arg)*)
     }
 }
 
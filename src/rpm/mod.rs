@@ -0,0 +1,207 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! Reading of files from RPM packages.
+//!
+//! An RPM package starts with a lead and two headers, a signature header and a main header, which
+//! are followed by a payload. The payload is a cpio archive in the `newc` format, compressed by
+//! the program recorded in the main header. This allows to read symtypes and symvers files
+//! directly from packages such as `kernel-syms` or `kernel-default-devel`, without unpacking them
+//! first. The payload can be decompressed if the respective compression format is enabled.
+
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+pub mod tests;
+
+/// The size of the lead of a package.
+const LEAD_SIZE: usize = 96;
+/// The magic number at the start of the lead.
+const LEAD_MAGIC: [u8; 4] = [0xed, 0xab, 0xee, 0xdb];
+/// The magic number at the start of a header.
+const HEADER_MAGIC: [u8; 3] = [0x8e, 0xad, 0xe8];
+/// The maximum number of entries in the index of a header, as accepted by rpm.
+const MAX_HEADER_ENTRIES: usize = 0x10000;
+/// The maximum size of the data store of a header, as accepted by rpm.
+const MAX_HEADER_DATA: usize = 256 * 1024 * 1024;
+/// The tag of the payload compressor in the main header.
+const TAG_PAYLOAD_COMPRESSOR: u32 = 1125;
+/// The type of a header entry with a NUL-terminated string.
+const TYPE_STRING: u32 = 6;
+/// The size of the fixed part of a cpio header.
+const CPIO_HEADER_SIZE: usize = 110;
+/// The name of the last member of a cpio archive.
+const CPIO_TRAILER: &str = "TRAILER!!!";
+
+/// Returns whether the `path` names an RPM package, as indicated by its extension.
+pub fn is_package(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "rpm")
+}
+
+/// Returns an error about malformed data in a package.
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads the content of regular files in the payload of an RPM package from the provided input
+/// stream.
+///
+/// The path of each file, relative to the root of the filesystem, is passed to `filter`. The
+/// content of each accepted file is read into memory and passed together with its path to
+/// `deliver`. Reading stops when `deliver` returns `false`.
+pub fn read_files<R, F, D>(reader: R, mut filter: F, mut deliver: D) -> io::Result<()>
+where
+    R: Read + Send,
+    F: FnMut(&Path) -> bool,
+    D: FnMut(PathBuf, Vec<u8>) -> bool,
+{
+    let mut reader = reader;
+
+    // Check the lead.
+    let mut lead = [0; LEAD_SIZE];
+    reader.read_exact(&mut lead)?;
+    if lead[..LEAD_MAGIC.len()] != LEAD_MAGIC {
+        return Err(invalid_data("Not an RPM package".to_string()));
+    }
+
+    // Skip the signature header, which is padded to a multiple of 8 bytes.
+    let (_, size) = read_header(&mut reader, "signature")?;
+    skip(&mut reader, size.next_multiple_of(8) - size)?;
+
+    // Read the main header and decompress the payload.
+    let (header, _) = read_header(&mut reader, "main")?;
+    let compressor = header
+        .string(TAG_PAYLOAD_COMPRESSOR)
+        .unwrap_or("gzip")
+        .to_string();
+    let mut payload = payload_compression(&compressor)?.reader(reader)?;
+
+    // Read the cpio members.
+    loop {
+        let mut cpio_header = [0; CPIO_HEADER_SIZE];
+        payload.read_exact(&mut cpio_header)?;
+        if &cpio_header[..6] != b"070701" && &cpio_header[..6] != b"070702" {
+            return Err(invalid_data(
+                "Unsupported format of the payload, expected a cpio archive in the newc format"
+                    .to_string(),
+            ));
+        }
+        let field = |idx: usize| {
+            let hex = std::str::from_utf8(&cpio_header[6 + idx * 8..14 + idx * 8]).ok();
+            hex.and_then(|hex| usize::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| invalid_data("Malformed cpio header in the payload".to_string()))
+        };
+        let mode = field(1)?;
+        let file_size = field(6)?;
+        let name_size = field(11)?;
+
+        let mut name = vec![0; name_size];
+        payload.read_exact(&mut name)?;
+        skip(
+            &mut payload,
+            (CPIO_HEADER_SIZE + name_size).next_multiple_of(4) - (CPIO_HEADER_SIZE + name_size),
+        )?;
+        let name = String::from_utf8_lossy(name.strip_suffix(&[0]).unwrap_or(&name)).into_owned();
+        if name == CPIO_TRAILER {
+            return Ok(());
+        }
+
+        // Members are stored with the prefix './'.
+        let path = PathBuf::from(name.trim_start_matches("./").trim_start_matches('/'));
+        let padding = file_size.next_multiple_of(4) - file_size;
+        if mode & 0o170000 == 0o100000 && filter(&path) {
+            let mut data = vec![0; file_size];
+            payload.read_exact(&mut data)?;
+            skip(&mut payload, padding)?;
+            if !deliver(path, data) {
+                return Ok(());
+            }
+        } else {
+            skip(&mut payload, file_size + padding)?;
+        }
+    }
+}
+
+/// A header of an RPM package, with its index of entries and their data.
+struct Header {
+    entries: Vec<[u32; 4]>,
+    data: Vec<u8>,
+}
+
+impl Header {
+    /// Returns the value of the string entry with the specified `tag`, or [`None`] if the header
+    /// has no such entry.
+    fn string(&self, tag: u32) -> Option<&str> {
+        let &[_, _, offset, _] = self
+            .entries
+            .iter()
+            .find(|entry| entry[0] == tag && entry[1] == TYPE_STRING)?;
+        let value = self.data.get(offset as usize..)?;
+        let end = value.iter().position(|&byte| byte == 0)?;
+        std::str::from_utf8(&value[..end]).ok()
+    }
+}
+
+/// Reads a header of an RPM package, described by `kind` in error messages, and returns it
+/// together with its size in bytes.
+fn read_header<R: Read>(reader: &mut R, kind: &str) -> io::Result<(Header, usize)> {
+    let mut intro = [0; 16];
+    reader.read_exact(&mut intro)?;
+    if intro[..HEADER_MAGIC.len()] != HEADER_MAGIC {
+        return Err(invalid_data(format!(
+            "Missing the magic number of the {} header",
+            kind
+        )));
+    }
+    let num_entries = u32::from_be_bytes(intro[8..12].try_into().unwrap()) as usize;
+    let data_size = u32::from_be_bytes(intro[12..16].try_into().unwrap()) as usize;
+    if num_entries > MAX_HEADER_ENTRIES || data_size > MAX_HEADER_DATA {
+        return Err(invalid_data(format!("The {} header is too large", kind)));
+    }
+
+    let mut index = vec![0; num_entries * 16];
+    reader.read_exact(&mut index)?;
+    let entries = index
+        .chunks_exact(16)
+        .map(|entry| {
+            let mut fields = [0; 4];
+            for (i, field) in fields.iter_mut().enumerate() {
+                *field = u32::from_be_bytes(entry[i * 4..i * 4 + 4].try_into().unwrap());
+            }
+            fields
+        })
+        .collect();
+    let mut data = vec![0; data_size];
+    reader.read_exact(&mut data)?;
+
+    let size = intro.len() + index.len() + data.len();
+    Ok((Header { entries, data }, size))
+}
+
+/// Returns the compression format of the payload for the name of its `compressor`, as recorded
+/// in the main header.
+fn payload_compression(compressor: &str) -> io::Result<crate::Compression> {
+    match compressor {
+        #[cfg(feature = "gzip")]
+        "gzip" => Ok(crate::Compression::Gzip),
+        #[cfg(feature = "xz")]
+        "xz" => Ok(crate::Compression::Xz),
+        #[cfg(feature = "zstd")]
+        "zstd" => Ok(crate::Compression::Zstd),
+        _ => Err(invalid_data(format!(
+            "Unsupported compression '{}' of the payload",
+            compressor
+        ))),
+    }
+}
+
+/// Skips `size` bytes of the provided input stream.
+fn skip<R: Read>(reader: &mut R, size: usize) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(size as u64), &mut io::sink())?;
+    if skipped != size as u64 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
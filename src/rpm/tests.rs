@@ -0,0 +1,171 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use super::*;
+
+/// Creates a header with the specified string entries.
+fn make_header(strings: &[(u32, &str)]) -> Vec<u8> {
+    let mut index = Vec::new();
+    let mut data = Vec::new();
+    for &(tag, value) in strings {
+        for field in [tag, TYPE_STRING, data.len() as u32, 1] {
+            index.extend(field.to_be_bytes());
+        }
+        data.extend(value.as_bytes());
+        data.push(0);
+    }
+    let mut header = vec![0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0];
+    header.extend((strings.len() as u32).to_be_bytes());
+    header.extend((data.len() as u32).to_be_bytes());
+    header.extend(index);
+    header.extend(data);
+    header
+}
+
+/// Creates a cpio archive in the newc format with the specified members.
+fn make_cpio(members: &[(&str, u32, &[u8])]) -> Vec<u8> {
+    let mut cpio = Vec::new();
+    let trailer = (CPIO_TRAILER, 0, &[][..]);
+    for &(name, mode, data) in members.iter().chain([&trailer]) {
+        let mode = mode as usize;
+        cpio.extend(b"070701");
+        let fields = [
+            0,
+            mode,
+            0,
+            0,
+            1,
+            0,
+            data.len(),
+            0,
+            0,
+            0,
+            0,
+            name.len() + 1,
+            0,
+        ];
+        for field in fields {
+            cpio.extend(format!("{:08x}", field).as_bytes());
+        }
+        cpio.extend(name.as_bytes());
+        cpio.push(0);
+        cpio.resize(cpio.len().next_multiple_of(4), 0);
+        cpio.extend(data);
+        cpio.resize(cpio.len().next_multiple_of(4), 0);
+    }
+    cpio
+}
+
+/// Creates a package with the specified payload compressor and cpio members.
+pub fn make_package(compressor: &str, members: &[(&str, u32, &[u8])]) -> Vec<u8> {
+    let mut package = LEAD_MAGIC.to_vec();
+    package.resize(LEAD_SIZE, 0);
+    package.extend(make_header(&[(1000, "sig")]));
+    package.resize(package.len().next_multiple_of(8), 0);
+    package.extend(make_header(&[(TAG_PAYLOAD_COMPRESSOR, compressor)]));
+
+    let cpio = make_cpio(members);
+    let mut payload = Vec::new();
+    let result = crate::Compression::from_path(format!("payload.{}", compressor_ext(compressor)))
+        .write_with(&mut payload, |writer| {
+            writer
+                .write_all(&cpio)
+                .map_err(|err| crate::Error::new_io("Failed to write payload", err))
+        });
+    assert!(result.is_ok());
+    package.extend(payload);
+    package
+}
+
+/// Returns the file extension of the format produced by `compressor`.
+fn compressor_ext(compressor: &str) -> &str {
+    match compressor {
+        "gzip" => "gz",
+        "zstd" => "zst",
+        _ => compressor,
+    }
+}
+
+#[test]
+fn recognize_packages() {
+    // Check that packages are recognized by their extension.
+    assert!(is_package(Path::new("kernel-syms-6.4.0-1.1.x86_64.rpm")));
+    assert!(!is_package(Path::new("symtypes.tar.gz")));
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn read_package_files() {
+    // Check that regular files accepted by the filter are read from a package, with the './'
+    // prefix removed from their paths, and that directories and symbolic links are skipped.
+    let package = make_package(
+        "gzip",
+        &[
+            ("./usr/src/linux-obj", 0o040755, b""),
+            ("./usr/src/linux-obj/Module.symvers", 0o100644, b"symvers\n"),
+            ("./usr/src/linux-obj/fs/a.symtypes", 0o100644, b"a\n"),
+            ("./usr/src/linux-obj/fs/b.symtypes", 0o120777, b"a.symtypes"),
+            ("./usr/src/linux-obj/README", 0o100644, b"readme\n"),
+        ],
+    );
+    let mut files = Vec::new();
+    let result = read_files(
+        package.as_slice(),
+        |path| path.extension().is_some(),
+        |path, data| {
+            files.push((path, String::from_utf8(data).unwrap()));
+            true
+        },
+    );
+    assert!(result.is_ok());
+    assert_eq!(
+        files,
+        [
+            (
+                PathBuf::from("usr/src/linux-obj/Module.symvers"),
+                "symvers\n".to_string()
+            ),
+            (
+                PathBuf::from("usr/src/linux-obj/fs/a.symtypes"),
+                "a\n".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+#[cfg(all(feature = "xz", feature = "zstd"))]
+fn read_compressed_payloads() {
+    // Check that payloads compressed by xz and zstd are read.
+    for compressor in ["xz", "zstd"] {
+        let package = make_package(compressor, &[("./a.symtypes", 0o100644, b"a\n")]);
+        let mut paths = Vec::new();
+        let result = read_files(
+            package.as_slice(),
+            |_| true,
+            |path, _| {
+                paths.push(path);
+                true
+            },
+        );
+        assert!(result.is_ok());
+        assert_eq!(paths, [PathBuf::from("a.symtypes")]);
+    }
+}
+
+#[test]
+fn read_malformed() {
+    // Check that data which is not a package or has an unknown payload compression is rejected.
+    let result = read_files(&[0; LEAD_SIZE][..], |_| true, |_, _| true);
+    assert_eq!(result.unwrap_err().to_string(), "Not an RPM package");
+
+    let mut package = LEAD_MAGIC.to_vec();
+    package.resize(LEAD_SIZE, 0);
+    package.extend(make_header(&[]));
+    package.extend(make_header(&[(TAG_PAYLOAD_COMPRESSOR, "bzip2")]));
+    let result = read_files(package.as_slice(), |_| true, |_, _| true);
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Unsupported compression 'bzip2' of the payload"
+    );
+}
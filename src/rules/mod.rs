@@ -0,0 +1,510 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! Severity rules in the format of kABI severities files.
+//!
+//! A severities file, as used by the SUSE kernel packaging, assigns a severity to changes of
+//! exports. Each non-empty line has the form `<pattern> <severity>`, where the fields are separated
+//! by whitespace and the severity is one of `PASS`, `WARN` or `FAIL`. A `#` starts a comment which
+//! extends to the end of the line. A pattern which contains a `/` or is `vmlinux` applies to the
+//! modules which provide the exports, other patterns apply to the names of the exports. Patterns
+//! can contain the wildcards described by [`crate::glob::matches()`].
+//!
+//! A change of an export gets the severity of the first symbol rule which matches its name. If no
+//! symbol rule matches, the first matching module rule applies. Changes not matched by any rule
+//! are considered `FAIL`.
+
+use crate::sym::{ChangeKind, ScoreOptions};
+use crate::{debug, json_string, MapIOErr, ParseError, ParseErrorKind, PathFile};
+use rustc_hash::FxHasher;
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+use std::io;
+use std::io::{prelude::*, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// A severity of a change, ordered from the least to the most severe one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    /// The change is allowed.
+    Pass,
+    /// The change is allowed but should be reviewed.
+    Warn,
+    /// The change breaks the kABI.
+    Fail,
+}
+
+impl Severity {
+    /// Returns the severity with the specified name, as used in severities files, or [`None`] if
+    /// the name is not recognized.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PASS" => Some(Self::Pass),
+            "WARN" => Some(Self::Warn),
+            "FAIL" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Pass => write!(f, "PASS"),
+            Self::Warn => write!(f, "WARN"),
+            Self::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// A style of annotations written by [`Verdicts::write_annotations()`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AnnotationStyle {
+    /// Workflow commands of GitHub Actions.
+    #[default]
+    GitHub,
+    /// A code quality report of GitLab CI.
+    GitLab,
+}
+
+/// A single rule of a severities file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Rule {
+    pattern: String,
+    severity: Severity,
+}
+
+/// Rules which assign severities to changes of exports.
+#[derive(Clone, Debug, Default)]
+pub struct Rules {
+    symbol_rules: Vec<Rule>,
+    module_rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Creates new empty rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads rules from a severities file, which can be compressed in one of the supported
+    /// formats. The path `-` denotes the standard input.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            return self.load_buffer(path, io::stdin().lock());
+        }
+
+        let file = PathFile::open(path).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to open file '{}'", crate::display_path(path)),
+                err,
+            )
+        })?;
+        let reader = crate::Compression::from_path(path)
+            .reader(file)
+            .map_err(|err| {
+                crate::Error::new_io(
+                    &format!(
+                        "Failed to read severities file '{}'",
+                        crate::display_path(path)
+                    ),
+                    err,
+                )
+            })?;
+        self.load_buffer(path, reader)
+            .map_err(|err| err.with_root(Path::new("")))
+    }
+
+    /// Loads rules from a specified reader with severities data. The rules are appended after the
+    /// already loaded ones.
+    ///
+    /// The `path` identifies the origin of the data and is used in error messages. A line which
+    /// doesn't consist of a pattern and a known severity is rejected with
+    /// [`ParseErrorKind::MalformedRule`].
+    pub fn load_buffer<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        reader: R,
+    ) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+        debug!("Loading severities '{}'", path.display());
+
+        let mut symbol_rules = Vec::new();
+        let mut module_rules = Vec::new();
+        let reader = BufReader::new(reader);
+        for (line_idx, line) in reader.lines().enumerate() {
+            let line =
+                line.map_err(|err| crate::Error::new_io("Failed to read severities data", err))?;
+            let rule = line.split('#').next().unwrap_or_default();
+            let fields: Vec<_> = rule.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+
+            if fields.len() != 2 {
+                return Err(ParseError::new(
+                    ParseErrorKind::MalformedRule,
+                    path,
+                    line_idx,
+                    format!(
+                        "Expected a pattern and a severity, found {} fields",
+                        fields.len()
+                    ),
+                )
+                .with_source(&line, 0)
+                .into());
+            }
+            let Some(severity) = Severity::from_name(fields[1]) else {
+                let column_idx = rule.rfind(fields[1]).unwrap_or_default();
+                return Err(ParseError::new(
+                    ParseErrorKind::MalformedRule,
+                    path,
+                    line_idx,
+                    format!(
+                        "Unrecognized severity '{}', expected 'PASS', 'WARN' or 'FAIL'",
+                        fields[1]
+                    ),
+                )
+                .with_source(&line, column_idx)
+                .into());
+            };
+
+            let pattern = fields[0];
+            let rule = Rule {
+                pattern: pattern.to_string(),
+                severity,
+            };
+            if pattern.contains('/') || pattern == "vmlinux" {
+                module_rules.push(rule);
+            } else {
+                symbol_rules.push(rule);
+            }
+        }
+
+        self.symbol_rules.append(&mut symbol_rules);
+        self.module_rules.append(&mut module_rules);
+        Ok(())
+    }
+
+    /// Returns whether no rules are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.symbol_rules.is_empty() && self.module_rules.is_empty()
+    }
+
+    /// Returns the severity of a change of the export `name`, provided by `module`, if known.
+    ///
+    /// The module is matched with its extension removed, so that the path of a `.symtypes` file,
+    /// for instance, `drivers/scsi/sd_mod.symtypes`, matches the same rules as the module name in
+    /// `Module.symvers` data, `drivers/scsi/sd_mod`.
+    pub fn severity(&self, name: &str, module: Option<&Path>) -> Severity {
+        if let Some(rule) = self
+            .symbol_rules
+            .iter()
+            .find(|rule| crate::glob::matches(&rule.pattern, name))
+        {
+            return rule.severity;
+        }
+
+        let module = module.and_then(|module| module.with_extension("").to_str().map(String::from));
+        if let Some(module) = module {
+            if let Some(rule) = self
+                .module_rules
+                .iter()
+                .find(|rule| crate::glob::matches(&rule.pattern, &module))
+            {
+                return rule.severity;
+            }
+        }
+
+        Severity::Fail
+    }
+
+    /// Assigns severities to the specified changes, as provided by
+    /// [`crate::sym::CompareResult::export_changes()`] or
+    /// [`crate::symvers::SymversCompareResult::export_changes()`]. Each change is described by the
+    /// name of the export, the path which defines it, if known, and the kind of the change. Added
+    /// exports cannot break existing users and are skipped.
+    pub fn check_changes<'a, I: IntoIterator<Item = (&'a str, Option<&'a Path>, ChangeKind)>>(
+        &self,
+        changes: I,
+    ) -> Verdicts {
+        let mut verdicts: Vec<_> = changes
+            .into_iter()
+            .filter(|(_, _, kind)| *kind != ChangeKind::Added)
+            .map(|(name, module, kind)| Verdict {
+                name: name.to_string(),
+                path: module.map(Path::to_path_buf),
+                kind,
+                severity: self.severity(name, module),
+            })
+            .collect();
+        verdicts.sort_by(|verdict, other| verdict.name.cmp(&other.name));
+        Verdicts { verdicts }
+    }
+}
+
+/// A severity assigned to a change of an export.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Verdict {
+    name: String,
+    path: Option<PathBuf>,
+    kind: ChangeKind,
+    severity: Severity,
+}
+
+impl Verdict {
+    /// Returns the name of the export.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the path which defines the export, if known. This is the `.symtypes` file when
+    /// comparing symtypes data, or the module when comparing symvers data.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns a human-readable description of the change.
+    fn describe(&self) -> String {
+        format!("Export '{}' has been {}", self.name, self.kind)
+    }
+
+    /// Returns the kind of the change.
+    pub fn kind(&self) -> ChangeKind {
+        self.kind
+    }
+
+    /// Returns the severity of the change.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+/// Severities assigned to changes of exports, computed by [`Rules::check_changes()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Verdicts {
+    verdicts: Vec<Verdict>,
+}
+
+impl Verdicts {
+    /// Returns the verdicts, sorted by the export name.
+    pub fn verdicts(&self) -> &[Verdict] {
+        &self.verdicts
+    }
+
+    /// Returns the highest severity of all changes, or [`None`] if there are no changes.
+    pub fn worst(&self) -> Option<Severity> {
+        self.verdicts.iter().map(|verdict| verdict.severity).max()
+    }
+
+    /// Returns the total badness of the changes, computed by [`ScoreOptions::score_changes()`]
+    /// using `options`. Changes rated [`Severity::Pass`] are tolerated and not included, as in the
+    /// total of [`Verdicts::write_badness_report()`].
+    pub fn badness(&self, options: &ScoreOptions) -> u64 {
+        options
+            .score_changes(
+                self.verdicts
+                    .iter()
+                    .filter(|verdict| verdict.severity != Severity::Pass)
+                    .map(|verdict| (verdict.name.as_str(), verdict.path(), verdict.kind)),
+            )
+            .total()
+    }
+
+    /// Writes a human-readable report of the severities to the provided output stream.
+    pub fn write_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write severities";
+
+        for verdict in &self.verdicts {
+            writeln!(
+                writer,
+                "{}: {} export '{}'",
+                verdict.severity, verdict.kind, verdict.name
+            )
+            .map_io_err(err_desc)?;
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes the severities to the provided output stream in the form of rpmlint findings, so
+    /// that they can be shown directly in the results of an OBS build.
+    ///
+    /// Each change is written on a line in the form `kabi: <S>: export-<kind> <name> <module>`,
+    /// where `<S>` is `I`, `W` or `E` for a change rated [`Severity::Pass`], [`Severity::Warn`] or
+    /// [`Severity::Fail`], respectively, and `<module>` is `-` if the module is not known.
+    pub fn write_obs_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write severities";
+
+        for verdict in &self.verdicts {
+            let severity = match verdict.severity {
+                Severity::Pass => 'I',
+                Severity::Warn => 'W',
+                Severity::Fail => 'E',
+            };
+            let module = verdict
+                .path
+                .as_ref()
+                .map(|path| crate::display_path(&path.with_extension("")));
+            writeln!(
+                writer,
+                "kabi: {}: export-{} {} {}",
+                severity,
+                verdict.kind,
+                verdict.name,
+                module.as_deref().unwrap_or("-")
+            )
+            .map_io_err(err_desc)?;
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes a badness report of the changes to the provided output stream in the format of the
+    /// SUSE kABI result aggregation scripts. The badness of each change is computed by
+    /// [`ScoreOptions::score_changes()`] using `options`.
+    ///
+    /// Each change is written on a line `symbol <name> <module> <kind> <severity> <badness>`,
+    /// followed by a line `module <module> <badness>` for each module and a final line
+    /// `total <badness>`. The module is the path which defines the export with its extension
+    /// removed, or `-` if not known. Changes rated [`Severity::Pass`] are tolerated and not
+    /// included in the module and total badness.
+    pub fn write_badness_report<W: Write>(
+        &self,
+        options: &ScoreOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write severities";
+
+        let mut modules = BTreeMap::new();
+        let mut total: u64 = 0;
+        for verdict in &self.verdicts {
+            let module = verdict.path.as_ref().map_or_else(
+                || "-".to_string(),
+                |path| crate::display_path(&path.with_extension("")),
+            );
+            let badness = options
+                .score_changes([(verdict.name.as_str(), verdict.path(), verdict.kind)])
+                .total();
+            writeln!(
+                writer,
+                "symbol {} {} {} {} {}",
+                verdict.name, module, verdict.kind, verdict.severity, badness
+            )
+            .map_io_err(err_desc)?;
+            if verdict.severity != Severity::Pass {
+                total = total.saturating_add(badness);
+                let module_badness: &mut u64 = modules.entry(module).or_default();
+                *module_badness = module_badness.saturating_add(badness);
+            }
+        }
+        for (module, badness) in modules {
+            writeln!(writer, "module {} {}", module, badness).map_io_err(err_desc)?;
+        }
+        writeln!(writer, "total {}", total).map_io_err(err_desc)?;
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes the severities to the provided output stream as annotations in the specified
+    /// `style`, so that the changes are shown inline on merge requests, referring to the files
+    /// which define the exports.
+    ///
+    /// With [`AnnotationStyle::GitHub`], each change is written as an `::error`, `::warning` or
+    /// `::notice` workflow command for a change rated [`Severity::Fail`], [`Severity::Warn`] or
+    /// [`Severity::Pass`], respectively. With [`AnnotationStyle::GitLab`], the changes are written
+    /// as a JSON array of code quality issues, with the severity `critical`, `minor` or `info`,
+    /// respectively.
+    pub fn write_annotations<W: Write>(
+        &self,
+        style: AnnotationStyle,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write severities";
+
+        match style {
+            AnnotationStyle::GitHub => {
+                for verdict in &self.verdicts {
+                    let level = match verdict.severity {
+                        Severity::Pass => "notice",
+                        Severity::Warn => "warning",
+                        Severity::Fail => "error",
+                    };
+                    let file = verdict.path.as_ref().map_or_else(String::new, |path| {
+                        let path = crate::display_path(path);
+                        format!("file={},", escape_github_property(&path))
+                    });
+                    writeln!(
+                        writer,
+                        "::{} {}title=kABI {}::{}",
+                        level,
+                        file,
+                        verdict.severity,
+                        escape_github_data(&verdict.describe())
+                    )
+                    .map_io_err(err_desc)?;
+                }
+            }
+            AnnotationStyle::GitLab => {
+                write!(writer, "[").map_io_err(err_desc)?;
+                for (i, verdict) in self.verdicts.iter().enumerate() {
+                    let severity = match verdict.severity {
+                        Severity::Pass => "info",
+                        Severity::Warn => "minor",
+                        Severity::Fail => "critical",
+                    };
+                    let path = verdict
+                        .path
+                        .as_ref()
+                        .map(|path| crate::display_path(path))
+                        .unwrap_or_default();
+                    // Identify the issue by the export and the kind of its change, so that GitLab
+                    // can track it between pipelines.
+                    let mut hasher = FxHasher::default();
+                    hasher.write(verdict.name.as_bytes());
+                    hasher.write(verdict.kind.to_string().as_bytes());
+                    write!(
+                        writer,
+                        concat!(
+                            "{}{{\"description\":{},\"check_name\":\"kabi-{}\",",
+                            "\"fingerprint\":\"{:016x}\",\"severity\":\"{}\",",
+                            "\"location\":{{\"path\":{},\"lines\":{{\"begin\":1}}}}}}"
+                        ),
+                        if i > 0 { "," } else { "" },
+                        json_string(&verdict.describe()),
+                        verdict.kind,
+                        hasher.finish(),
+                        severity,
+                        json_string(&path)
+                    )
+                    .map_io_err(err_desc)?;
+                }
+                writeln!(writer, "]").map_io_err(err_desc)?;
+            }
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+}
+
+/// Escapes the data of a GitHub Actions workflow command.
+fn escape_github_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property value of a GitHub Actions workflow command.
+fn escape_github_property(value: &str) -> String {
+    escape_github_data(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
@@ -0,0 +1,244 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use super::*;
+use crate::assert_ok;
+
+#[test]
+fn read_rules() {
+    // Check that symbol and module rules are read, skipping comments and empty lines.
+    let mut rules = Rules::new();
+    let result = rules.load_buffer(
+        "severities",
+        concat!(
+            "# kABI severities\n",
+            "\n",
+            "drivers/scsi/*\t\tPASS\n",
+            "vmlinux  FAIL # core kernel\n",
+            "__foo_*  WARN\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert_eq!(
+        rules.symbol_rules,
+        [Rule {
+            pattern: "__foo_*".to_string(),
+            severity: Severity::Warn,
+        }]
+    );
+    assert_eq!(
+        rules.module_rules,
+        [
+            Rule {
+                pattern: "drivers/scsi/*".to_string(),
+                severity: Severity::Pass,
+            },
+            Rule {
+                pattern: "vmlinux".to_string(),
+                severity: Severity::Fail,
+            },
+        ]
+    );
+}
+
+#[test]
+fn read_malformed() {
+    // Check that lines without a severity or with an unknown severity are rejected.
+    let mut rules = Rules::new();
+    let result = rules.load_buffer("severities", "drivers/scsi/*\n".as_bytes());
+    match result {
+        Err(crate::Error::Parse(err)) => assert_eq!(
+            err.to_string(),
+            concat!(
+                "severities:1:1: Expected a pattern and a severity, found 1 fields\n",
+                "  drivers/scsi/*\n",
+                "  ^~~~~~~~~~~~~~", //
+            )
+        ),
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    let result = rules.load_buffer("severities", "drivers/scsi/* IGNORE\n".as_bytes());
+    match result {
+        Err(crate::Error::Parse(err)) => assert_eq!(
+            err.to_string(),
+            concat!(
+                "severities:1:16: Unrecognized severity 'IGNORE', expected 'PASS', 'WARN' or 'FAIL'\n",
+                "  drivers/scsi/* IGNORE\n",
+                "                 ^~~~~~", //
+            )
+        ),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert!(rules.is_empty());
+}
+
+#[test]
+fn match_rules() {
+    // Check that symbol rules take precedence over module rules, that the first matching rule of
+    // each kind wins and that unmatched changes fail.
+    let mut rules = Rules::new();
+    let result = rules.load_buffer(
+        "severities",
+        concat!(
+            "drivers/scsi/sd_mod  FAIL\n",
+            "drivers/scsi/*       PASS\n",
+            "vmlinux              WARN\n",
+            "scsi_*               WARN\n",
+            "scsi_host_*          PASS\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let sd_mod = Some(Path::new("drivers/scsi/sd_mod"));
+    assert_eq!(rules.severity("sd_open", sd_mod), Severity::Fail);
+    assert_eq!(
+        rules.severity("sd_open", Some(Path::new("drivers/scsi/sd_mod.symtypes"))),
+        Severity::Fail
+    );
+    assert_eq!(
+        rules.severity("sr_open", Some(Path::new("drivers/scsi/sr_mod"))),
+        Severity::Pass
+    );
+    assert_eq!(rules.severity("scsi_host_alloc", sd_mod), Severity::Warn);
+    assert_eq!(
+        rules.severity("printk", Some(Path::new("vmlinux"))),
+        Severity::Warn
+    );
+    assert_eq!(
+        rules.severity("ext4_foo", Some(Path::new("fs/ext4/ext4"))),
+        Severity::Fail
+    );
+    assert_eq!(rules.severity("ext4_foo", None), Severity::Fail);
+}
+
+#[test]
+fn check_changes() {
+    // Check that changes are assigned severities, sorted by the export name, that added exports
+    // are skipped, and that the severities are written as text and OBS findings.
+    let mut rules = Rules::new();
+    let result = rules.load_buffer(
+        "severities",
+        concat!(
+            "drivers/*  PASS\n",
+            "foo        WARN\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let verdicts = rules.check_changes([
+        ("foo", Some(Path::new("vmlinux")), ChangeKind::Changed),
+        ("bar", Some(Path::new("drivers/bar")), ChangeKind::Removed),
+        ("baz", None, ChangeKind::Changed),
+        ("qux", None, ChangeKind::Added),
+    ]);
+    assert_eq!(verdicts.worst(), Some(Severity::Fail));
+
+    let mut out = Vec::new();
+    assert_ok!(verdicts.write_report(&mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "PASS: removed export 'bar'\n",
+            "FAIL: changed export 'baz'\n",
+            "WARN: changed export 'foo'\n", //
+        )
+    );
+
+    let mut out = Vec::new();
+    assert_ok!(verdicts.write_obs_report(&mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "kabi: I: export-removed bar drivers/bar\n",
+            "kabi: E: export-changed baz -\n",
+            "kabi: W: export-changed foo vmlinux\n", //
+        )
+    );
+
+    let verdicts = rules.check_changes([("qux", None, ChangeKind::Added)]);
+    assert_eq!(verdicts.worst(), None);
+}
+
+#[test]
+fn write_annotations() {
+    // Check that the severities are written as GitHub Actions workflow commands and as a GitLab
+    // code quality report.
+    let verdicts = Rules::new().check_changes([
+        (
+            "foo",
+            Some(Path::new("drivers/foo,bar.symtypes")),
+            ChangeKind::Changed,
+        ),
+        ("bar", None, ChangeKind::Removed),
+    ]);
+
+    let mut out = Vec::new();
+    assert_ok!(verdicts.write_annotations(AnnotationStyle::GitHub, &mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "::error title=kABI FAIL::Export 'bar' has been removed\n",
+            "::error file=drivers/foo%2Cbar.symtypes,title=kABI FAIL::",
+            "Export 'foo' has been changed\n", //
+        )
+    );
+
+    let mut out = Vec::new();
+    assert_ok!(verdicts.write_annotations(AnnotationStyle::GitLab, &mut out));
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with(concat!(
+        "[{\"description\":\"Export 'bar' has been removed\",\"check_name\":\"kabi-removed\",",
+        "\"fingerprint\":\"",
+    )));
+    assert!(out.ends_with(concat!(
+        "\"severity\":\"critical\",",
+        "\"location\":{\"path\":\"drivers/foo,bar.symtypes\",\"lines\":{\"begin\":1}}}]\n",
+    )));
+}
+
+#[test]
+fn write_badness_report() {
+    // Check that the badness is reported for each change, and by module and in total without the
+    // tolerated changes.
+    let mut rules = Rules::new();
+    assert_ok!(rules.load_buffer("severities", "drivers/*  PASS\n".as_bytes()));
+    let verdicts = rules.check_changes([
+        ("foo", Some(Path::new("vmlinux")), ChangeKind::Changed),
+        ("bar", Some(Path::new("drivers/bar")), ChangeKind::Removed),
+        ("baz", Some(Path::new("vmlinux")), ChangeKind::Removed),
+        ("qux", None, ChangeKind::Changed),
+    ]);
+
+    let mut out = Vec::new();
+    assert_ok!(verdicts.write_badness_report(&ScoreOptions::new(), &mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "symbol bar drivers/bar removed PASS 8\n",
+            "symbol baz vmlinux removed FAIL 8\n",
+            "symbol foo vmlinux changed FAIL 4\n",
+            "symbol qux - changed FAIL 4\n",
+            "module - 4\n",
+            "module vmlinux 12\n",
+            "total 16\n", //
+        )
+    );
+}
+
+#[test]
+fn badness() {
+    // Check that the total badness does not include the tolerated changes.
+    let mut rules = Rules::new();
+    assert_ok!(rules.load_buffer("severities", "drivers/*  PASS\n".as_bytes()));
+    let verdicts = rules.check_changes([
+        ("foo", Some(Path::new("vmlinux")), ChangeKind::Changed),
+        ("bar", Some(Path::new("drivers/bar")), ChangeKind::Removed),
+        ("baz", Some(Path::new("vmlinux")), ChangeKind::Added),
+    ]);
+    assert_eq!(verdicts.badness(&ScoreOptions::new()), 4);
+    assert_eq!(Verdicts::default().badness(&ScoreOptions::new()), 0);
+}
@@ -1,15 +1,25 @@
 // Copyright (C) 2024 SUSE LLC <petr.pavlu@suse.com>
 // SPDX-License-Identifier: GPL-2.0-or-later
 
-use crate::{debug, MapIOErr, PathFile};
+use crate::diff::DiffOptions;
+use crate::{
+    debug, json_string, run_workers, Diagnostics, DiagnosticsSink, MapIOErr, ParseError,
+    ParseErrorKind, PathFile, ProgressFn, ProgressPhase, ProgressSink, WarningKind,
+};
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::io::{prelude::*, BufReader, BufWriter};
 use std::iter::zip;
-use std::path::{Path, PathBuf};
+use std::ops::{Deref, Range};
+use std::path::{Component, Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Mutex, RwLock};
-use std::{fs, io, thread};
+#[cfg(feature = "tar")]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{fs, io, mem};
 
 #[cfg(test)]
 mod tests;
@@ -24,52 +34,273 @@ mod tests_format;
 //     the first lookup, or opts to run the search again if the key is missing and needs inserting.
 // [2] HashSet in the stable Rust (1.84) doesn't provide the entry functionality. It is
 //     a nightly-only experimental API and so not used by the module.
+// [3] The internal HashMaps and HashSets use the FxHash algorithm instead of the default SipHash.
+//     Their keys are mostly small integer identifiers and short strings. The input is trusted and
+//     so the resistance of SipHash against HashDoS attacks is not needed.
+
+/// An identifier of a string stored in an [`Interner`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+struct StrId(u32);
+
+impl StrId {
+    /// Returns the identifier as an index into [`Interner::strings`].
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
 
-/// A token used in the description of a type.
-#[derive(Eq, PartialEq, Hash, Ord, PartialOrd)]
-enum Token {
-    TypeRef(String),
-    Atom(String),
+/// A storage of unique strings, such as type names and words of type descriptions.
+///
+/// Each string is stored only once and is referred to by its [`StrId`]. The identifiers are
+/// specific to the interner, strings from different interners need to be compared by their content.
+#[derive(Clone, Default)]
+struct Interner {
+    ids: FxHashMap<Arc<str>, StrId>,
+    strings: Vec<Arc<str>>,
+}
+
+impl Interner {
+    /// Returns the identifier of the specified string, adding the string if it is not yet present.
+    fn intern(&mut self, string: &str) -> StrId {
+        if let Some(&id) = self.ids.get(string) {
+            return id;
+        }
+        // The highest bit of the identifier is reserved for the encoding of tokens.
+        let id = match u32::try_from(self.strings.len()) {
+            Ok(id) if id & Token::TYPEREF_BIT == 0 => StrId(id),
+            _ => panic!("Too many unique strings in the corpus"),
+        };
+        let string: Arc<str> = Arc::from(string);
+        self.strings.push(string.clone());
+        self.ids.insert(string, id);
+        id
+    }
+
+    /// Returns the identifier of the specified string, or [`None`] if the string is not present.
+    fn get(&self, string: &str) -> Option<StrId> {
+        self.ids.get(string).copied()
+    }
+
+    /// Returns the string with the specified identifier.
+    fn resolve(&self, id: StrId) -> &str {
+        &self.strings[id.index()]
+    }
 }
 
+/// A token used in the description of a type.
+///
+/// The token is encoded as a [`StrId`] of its data, with the highest bit indicating whether it is
+/// a type reference or an atom.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+struct Token(u32);
+
 impl Token {
-    /// Creates a new `Token::TypeRef`.
-    fn new_typeref<S: Into<String>>(name: S) -> Self {
-        Token::TypeRef(name.into())
+    /// A bit marking a type reference.
+    const TYPEREF_BIT: u32 = 1 << 31;
+
+    /// Creates a new token referencing the type with the specified name.
+    fn new_typeref(ref_name: StrId) -> Self {
+        Self(ref_name.0 | Self::TYPEREF_BIT)
     }
 
-    /// Creates a new `Token::Atom`.
-    fn new_atom<S: Into<String>>(name: S) -> Self {
-        Token::Atom(name.into())
+    /// Creates a new atom token with the specified word.
+    fn new_atom(word: StrId) -> Self {
+        Self(word.0)
     }
 
-    /// Returns the token data as a string slice.
-    fn as_str(&self) -> &str {
-        match self {
-            Self::TypeRef(ref_name) => ref_name.as_str(),
-            Self::Atom(word) => word.as_str(),
+    /// Returns the identifier of the token data.
+    fn id(self) -> StrId {
+        StrId(self.0 & !Self::TYPEREF_BIT)
+    }
+
+    /// Returns the name of the referenced type if the token is a type reference.
+    fn typeref(self) -> Option<StrId> {
+        if self.0 & Self::TYPEREF_BIT != 0 {
+            Some(self.id())
+        } else {
+            None
         }
     }
+
+    /// Converts the token to one which refers to the same string in another interner, using
+    /// a mapping between their identifiers. Returns [`None`] if the string is not present in the
+    /// other interner.
+    fn translate(self, id_map: &[Option<StrId>]) -> Option<Token> {
+        id_map[self.id().index()].map(|id| Self(id.0 | (self.0 & Self::TYPEREF_BIT)))
+    }
 }
 
 /// A sequence of tokens, describing one type.
-type Tokens = Vec<Token>;
+type Tokens = [Token];
+
+/// An index of a file in `SymFiles`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+struct FileIdx(u32);
+
+impl FileIdx {
+    /// Creates a new index from the specified position in `SymFiles`.
+    fn new(index: usize) -> Self {
+        match u32::try_from(index) {
+            Ok(index) => Self(index),
+            Err(_) => panic!("Too many files in the corpus"),
+        }
+    }
+
+    /// Returns the index as a position in `SymFiles`.
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// An index of a type variant in `TypeVariants`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+struct VariantIdx(u32);
+
+impl VariantIdx {
+    /// Creates a new index from the specified position in `TypeVariants`.
+    fn new(index: usize) -> Self {
+        match u32::try_from(index) {
+            Ok(index) => Self(index),
+            Err(_) => panic!("Too many variants of a type in the corpus"),
+        }
+    }
+
+    /// Returns the index as a position in `TypeVariants`.
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A position of a type variant's tokens in [`Types::tokens`].
+#[derive(Clone, Copy, Debug)]
+struct TokenRange {
+    start: u32,
+    len: u32,
+}
 
 /// A collection of all variants of the same type name in a given corpus.
-type TypeVariants = Vec<Tokens>;
+type TypeVariants = Vec<TokenRange>;
 
 /// A mapping from a type name to all its known variants.
-type Types = HashMap<String, TypeVariants>;
+///
+/// The tokens of all variants are stored one after another in a single [`Vec`], each variant
+/// records only its range in it.
+///
+/// The `index` provides a variant of a type by a hash of its tokens, which allows to quickly find
+/// an existing variant when merging a new definition. The index is rebuilt on demand when
+/// `is_indexed` is not set.
+#[derive(Clone, Default)]
+struct Types {
+    variants: FxHashMap<StrId, TypeVariants>,
+    tokens: Vec<Token>,
+    index: FxHashMap<(StrId, u64), VariantIdx>,
+    is_indexed: bool,
+}
+
+impl Types {
+    /// Returns all variants of the specified type, or [`None`] if the type is not present.
+    fn get(&self, name: StrId) -> Option<&TypeVariants> {
+        self.variants.get(&name)
+    }
+
+    /// Returns the tokens of the specified type variant.
+    fn get_tokens(&self, name: StrId, variant_idx: VariantIdx) -> Option<&Tokens> {
+        let range = self.get(name)?.get(variant_idx.index())?;
+        let start = range.start as usize;
+        Some(&self.tokens[start..start + range.len as usize])
+    }
+
+    /// Returns a hash of the specified tokens.
+    fn hash_tokens(tokens: &Tokens) -> u64 {
+        let mut hasher = FxHasher::default();
+        tokens.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Rebuilds the index of variants by the hash of their tokens.
+    fn rebuild_index(&mut self) {
+        self.index.clear();
+        for (&name, variants) in &self.variants {
+            for (i, range) in variants.iter().enumerate() {
+                let start = range.start as usize;
+                let hash = Self::hash_tokens(&self.tokens[start..start + range.len as usize]);
+                self.index.entry((name, hash)).or_insert(VariantIdx::new(i));
+            }
+        }
+        self.is_indexed = true;
+    }
+
+    /// Marks the index of variants as stale, which is needed after the tokens are modified.
+    fn invalidate_index(&mut self) {
+        self.index.clear();
+        self.is_indexed = false;
+    }
+
+    /// Adds the given type definition if not already present, and returns its variant index.
+    fn merge(&mut self, name: StrId, tokens: &Tokens) -> VariantIdx {
+        if !self.is_indexed {
+            self.rebuild_index();
+        }
+
+        // Look up the variant by the hash first and compare the tokens to rule out a collision. In
+        // the unlikely case of a collision, fall back to comparing all variants.
+        let hash = Self::hash_tokens(tokens);
+        if let Some(&variant_idx) = self.index.get(&(name, hash)) {
+            if self.get_tokens(name, variant_idx) == Some(tokens) {
+                return variant_idx;
+            }
+            for (i, range) in self.variants[&name].iter().enumerate() {
+                let start = range.start as usize;
+                if *tokens == self.tokens[start..start + range.len as usize] {
+                    return VariantIdx::new(i);
+                }
+            }
+        }
+
+        let variants = self.variants.entry(name).or_default();
+
+        let range = match (
+            u32::try_from(self.tokens.len()),
+            u32::try_from(tokens.len()),
+        ) {
+            (Ok(start), Ok(len)) if start.checked_add(len).is_some() => TokenRange { start, len },
+            _ => panic!("Too many tokens in the corpus"),
+        };
+        self.tokens.extend_from_slice(tokens);
+        variants.push(range);
+        let variant_idx = VariantIdx::new(variants.len() - 1);
+        self.index.entry((name, hash)).or_insert(variant_idx);
+        variant_idx
+    }
+}
 
 /// A mapping from a symbol name to an index in `SymFiles`, specifying in which file the symbol is
 /// defined.
-type Exports = HashMap<String, usize>;
+type Exports = FxHashMap<StrId, FileIdx>;
+
+/// A mapping from a symbol name to the name of its namespace.
+type Namespaces = FxHashMap<StrId, StrId>;
+
+/// A mapping from a symbol name to its export type, such as `EXPORT_SYMBOL_GPL`.
+type ExportTypes = FxHashMap<StrId, StrId>;
+
+/// A mapping from a symbol name to the module which exports it, such as `vmlinux`.
+type Modules = FxHashMap<StrId, StrId>;
+
+/// A mapping from a symbol name to its genksyms CRC, as recorded in consolidated data.
+type Crcs = FxHashMap<StrId, u32>;
+
+/// The version of the consolidated format which records the CRCs of exports, declared by the
+/// `V#<version>` header on the first line of a consolidated file. A file without the header has
+/// version 1.
+const CRC_FORMAT_VERSION: u32 = 2;
 
 /// A mapping from a type name to an index in `TypeVariants`, specifying its variant in a given
 /// file.
-type FileRecords = HashMap<String, usize>;
+type FileRecords = FxHashMap<StrId, VariantIdx>;
 
 /// A representation of a single `.symtypes` file.
+#[derive(Clone)]
 struct SymFile {
     path: PathBuf,
     records: FileRecords,
@@ -85,6 +316,18 @@ type SymFiles = Vec<SymFile>;
 ///   to reference its variant in `types`.
 /// * The `exports` collection provides all exports in the corpus. Each export uses an index to
 ///   reference its origin in `files`.
+/// * The `namespaces` collection provides the symbol namespace of exports, as loaded from symvers
+///   data. Exports which are not in any namespace are not present in it.
+/// * The `export_types` collection provides the export type of exports, as loaded from symvers
+///   data.
+/// * The `modules` collection provides the module which exports each symbol, as loaded from
+///   symvers data.
+/// * The `crcs` collection provides the genksyms CRCs of exports, as recorded in consolidated
+///   data. The CRCs of other exports are computed from their definitions when needed.
+/// * The `crc_only` flag indicates that the corpus was created from symvers data alone by
+///   [`SymCorpus::from_symvers()`] and describes only the CRCs of its exports.
+/// * The `strings` interner stores all type names and words of type descriptions. The other
+///   collections refer to these strings by their identifiers.
 ///
 /// For instance, consider the following corpus consisting of two files `test_a.symtypes` and
 /// `test_b.symtypes`:
@@ -106,7 +349,9 @@ type SymFiles = Vec<SymFile>;
 /// The corpus has two exports `bar` and `baz`, with each referencing structure `foo`, but with
 /// different definitions, one is complete and one is incomplete.
 ///
-/// The data would be represented as follows:
+/// The data would be represented as follows, with interned strings shown in place of their
+/// identifiers and tokens of each variant shown in place of their range in the shared token
+/// storage:
 ///
 /// ```text
 /// SymCorpus {
@@ -145,929 +390,5976 @@ type SymFiles = Vec<SymFile>;
 /// }
 /// ```
 ///
-/// Note importantly that if a `Token` in `TypeVariants` is a `TypeRef` then the reference only
+/// Note importantly that if a `Token` in `TypeVariants` is a type reference then the reference only
 /// specifies a name of the target type, e.g. `s#foo` above. The actual type variant must be
 /// determined based on what file is being processed. This allows to trivially merge `Tokens` and
-/// limit memory needed to store the corpus. On the other hand, when comparing two `Tokens` vectors
+/// limit memory needed to store the corpus. On the other hand, when comparing two `Tokens` sequences
 /// for ABI equality, the code needs to consider whether all referenced subtypes are actually equal
 /// as well.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct SymCorpus {
     types: Types,
     exports: Exports,
     files: SymFiles,
+    namespaces: Namespaces,
+    export_types: ExportTypes,
+    modules: Modules,
+    crcs: Crcs,
+    strings: Interner,
+    crc_only: bool,
 }
 
-/// A helper struct to provide synchronized access to `SymCorpus` data during parallel loading.
-struct LoadContext<'a> {
-    types: RwLock<&'a mut Types>,
-    exports: Mutex<&'a mut Exports>,
-    files: Mutex<&'a mut SymFiles>,
+/// A [`SymCorpus`] in its serialized form.
+///
+/// The schema does not depend on the internal representation of the corpus. Type names and words
+/// are stored as strings and files refer to type variants by their position in `types`. The
+/// identifiers of the strings are assigned anew when the corpus is deserialized, and all references
+/// between the collections are validated.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerialCorpus {
+    types: BTreeMap<String, Vec<Vec<String>>>,
+    files: Vec<SerialFile>,
+    exports: BTreeMap<String, usize>,
+    #[serde(default)]
+    namespaces: BTreeMap<String, String>,
+    #[serde(default)]
+    export_types: BTreeMap<String, String>,
+    #[serde(default)]
+    modules: BTreeMap<String, String>,
+    #[serde(default)]
+    crcs: BTreeMap<String, u32>,
+    #[serde(default)]
+    crc_only: bool,
 }
 
-/// Type names to be present in the consolidated output, along with a mapping from their internal
-/// symbol variant indices to the output variant indices.
-type ConsolidateOutputTypes<'a> = HashMap<&'a str, HashMap<usize, usize>>;
-
-/// Type names processed during consolidation for a specific file, providing for each type their
-/// output variant index.
-type ConsolidateFileTypes<'a> = HashMap<&'a str, usize>;
-
-/// Changes between two corpuses, recording a tuple of each modified type's `name`, its old `tokens`
-/// and its new `tokens`, along with a [`Vec`] of exported symbols affected by the change.
-type CompareChangedTypes<'a> = HashMap<(&'a str, &'a Tokens, &'a Tokens), Vec<&'a str>>;
+/// A `.symtypes` file in a [`SerialCorpus`], mapping each type name to its variant index.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerialFile {
+    path: PathBuf,
+    records: BTreeMap<String, usize>,
+}
 
-/// Type names processed during comparison for a specific file.
-type CompareFileTypes<'a> = HashSet<&'a str>;
+#[cfg(feature = "serde")]
+impl From<&SymCorpus> for SerialCorpus {
+    fn from(syms: &SymCorpus) -> Self {
+        let strings = &syms.strings;
+        let resolve_map = |map: &FxHashMap<StrId, StrId>| {
+            map.iter()
+                .map(|(&key, &value)| {
+                    (
+                        strings.resolve(key).to_string(),
+                        strings.resolve(value).to_string(),
+                    )
+                })
+                .collect()
+        };
 
-impl SymCorpus {
-    /// Creates a new empty corpus.
-    pub fn new() -> Self {
         Self {
-            types: Types::new(),
-            exports: Exports::new(),
-            files: SymFiles::new(),
+            types: syms
+                .types
+                .variants
+                .iter()
+                .map(|(&name, variants)| {
+                    let variants = (0..variants.len())
+                        .map(|i| {
+                            syms.types
+                                .get_tokens(name, VariantIdx::new(i))
+                                .unwrap()
+                                .iter()
+                                .map(|token| strings.resolve(token.id()).to_string())
+                                .collect()
+                        })
+                        .collect();
+                    (strings.resolve(name).to_string(), variants)
+                })
+                .collect(),
+            files: syms
+                .files
+                .iter()
+                .map(|file| SerialFile {
+                    path: file.path.clone(),
+                    records: file
+                        .records
+                        .iter()
+                        .map(|(&name, &variant_idx)| {
+                            (strings.resolve(name).to_string(), variant_idx.index())
+                        })
+                        .collect(),
+                })
+                .collect(),
+            exports: syms
+                .exports
+                .iter()
+                .map(|(&name, &file_idx)| (strings.resolve(name).to_string(), file_idx.index()))
+                .collect(),
+            namespaces: resolve_map(&syms.namespaces),
+            export_types: resolve_map(&syms.export_types),
+            modules: resolve_map(&syms.modules),
+            crcs: syms
+                .crcs
+                .iter()
+                .map(|(&name, &crc)| (strings.resolve(name).to_string(), crc))
+                .collect(),
+            crc_only: syms.crc_only,
         }
     }
+}
 
-    /// Loads symtypes data from a given location.
-    ///
-    /// The `path` can point to a single `.symtypes` file or a directory. In the latter case, the
-    /// function recursively collects all `.symtypes` in that directory and loads them.
-    pub fn load<P: AsRef<Path>>(&mut self, path: P, num_workers: i32) -> Result<(), crate::Error> {
-        let path = path.as_ref();
-
-        // Determine if the input is a directory tree or a single symtypes file.
-        let md = fs::metadata(path).map_err(|err| {
-            crate::Error::new_io(&format!("Failed to query path '{}'", path.display()), err)
-        })?;
-
-        if md.is_dir() {
-            // Recursively collect symtypes files within the directory.
-            let mut symfiles = Vec::new();
-            Self::collect_symfiles(path, "", &mut symfiles)?;
-
-            // Load all found files.
-            self.load_symfiles(path, &symfiles, num_workers)
-        } else {
-            // Load the single file.
-            self.load_symfiles("", &[path], num_workers)
+#[cfg(feature = "serde")]
+impl TryFrom<SerialCorpus> for SymCorpus {
+    type Error = String;
+
+    fn try_from(data: SerialCorpus) -> Result<Self, Self::Error> {
+        let mut syms = SymCorpus::new();
+
+        // Rebuild the types. Identical variants of a type are merged, so remember the new index of
+        // each serialized variant.
+        let mut variant_maps: FxHashMap<StrId, Vec<VariantIdx>> = FxHashMap::default();
+        let mut tokens = Vec::new();
+        for (name, variants) in &data.types {
+            let name = syms.strings.intern(name);
+            let variant_map = variant_maps.entry(name).or_default();
+            for words in variants {
+                words_into_tokens(
+                    &mut words.iter().map(String::as_str),
+                    &mut syms.strings,
+                    &mut tokens,
+                );
+                variant_map.push(syms.types.merge(name, &tokens));
+            }
         }
-    }
-
-    /// Collects recursively all `.symtypes` files under the given root path and its subpath.
-    fn collect_symfiles<P: AsRef<Path>, Q: AsRef<Path>>(
-        root: P,
-        sub_path: Q,
-        symfiles: &mut Vec<PathBuf>,
-    ) -> Result<(), crate::Error> {
-        let root = root.as_ref();
-        let sub_path = sub_path.as_ref();
-
-        let path = root.join(sub_path);
-
-        let dir_iter = fs::read_dir(&path).map_err(|err| {
-            crate::Error::new_io(
-                &format!("Failed to read directory '{}'", path.display()),
-                err,
-            )
-        })?;
-
-        for maybe_entry in dir_iter {
-            let entry = maybe_entry.map_err(|err| {
-                crate::Error::new_io(
-                    &format!("Failed to read directory '{}'", path.display()),
-                    err,
-                )
-            })?;
-
-            let entry_path = entry.path();
-
-            let md = fs::symlink_metadata(&entry_path).map_err(|err| {
-                crate::Error::new_io(
-                    &format!("Failed to query path '{}'", entry_path.display()),
-                    err,
-                )
-            })?;
 
-            if md.is_symlink() {
-                continue;
+        for file in data.files {
+            let mut records = FileRecords::default();
+            for (name, variant_idx) in file.records {
+                let variant = syms
+                    .strings
+                    .get(&name)
+                    .and_then(|id| Some((id, *variant_maps.get(&id)?.get(variant_idx)?)));
+                match variant {
+                    Some((id, variant_idx)) => {
+                        records.insert(id, variant_idx);
+                    }
+                    None => {
+                        return Err(format!(
+                            "File '{}' refers to undefined variant {} of type '{}'",
+                            file.path.display(),
+                            variant_idx,
+                            name
+                        ))
+                    }
+                }
             }
+            syms.files.push(SymFile {
+                path: file.path,
+                records,
+            });
+        }
 
-            let entry_sub_path = sub_path.join(entry.file_name());
-
-            if md.is_dir() {
-                Self::collect_symfiles(root, &entry_sub_path, symfiles)?;
-                continue;
+        for (name, file_idx) in data.exports {
+            let id = syms.strings.intern(&name);
+            match syms.files.get(file_idx) {
+                Some(file) if file.records.contains_key(&id) => {
+                    syms.exports.insert(id, FileIdx::new(file_idx));
+                }
+                _ => {
+                    return Err(format!(
+                        "Export '{}' refers to file {} which does not define it",
+                        name, file_idx
+                    ))
+                }
             }
+        }
 
-            let ext = match entry_sub_path.extension() {
-                Some(ext) => ext,
-                None => continue,
-            };
-            if ext == "symtypes" {
-                symfiles.push(entry_sub_path);
+        for (map, data) in [
+            (&mut syms.namespaces, data.namespaces),
+            (&mut syms.export_types, data.export_types),
+            (&mut syms.modules, data.modules),
+        ] {
+            for (key, value) in data {
+                map.insert(syms.strings.intern(&key), syms.strings.intern(&value));
             }
         }
-        Ok(())
+        for (name, crc) in data.crcs {
+            syms.crcs.insert(syms.strings.intern(&name), crc);
+        }
+        syms.crc_only = data.crc_only;
+
+        Ok(syms)
     }
+}
 
-    /// Loads all specified `.symtypes` files.
-    fn load_symfiles<P: AsRef<Path>, Q: AsRef<Path> + Sync>(
-        &mut self,
-        root: P,
-        symfiles: &[Q],
-        num_workers: i32,
-    ) -> Result<(), crate::Error> {
-        let root = root.as_ref();
+#[cfg(feature = "serde")]
+impl serde::Serialize for SymCorpus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerialCorpus::from(self).serialize(serializer)
+    }
+}
 
-        // Load data from the files.
-        let next_work_idx = AtomicUsize::new(0);
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SymCorpus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SymCorpus::try_from(SerialCorpus::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
 
-        let load_context = LoadContext {
-            types: RwLock::new(&mut self.types),
-            exports: Mutex::new(&mut self.exports),
-            files: Mutex::new(&mut self.files),
-        };
+/// An order in which records are written to the consolidated output.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ConsolidateSort {
+    /// Type records are written first, followed by exports, with each group sorted by name.
+    #[default]
+    TypesFirst,
+    /// All records are sorted by name, regardless of their kind.
+    Name,
+}
 
-        thread::scope(|s| {
-            let mut workers = Vec::new();
-            for _ in 0..num_workers {
-                workers.push(s.spawn(|| -> Result<(), crate::Error> {
-                    loop {
-                        let work_idx = next_work_idx.fetch_add(1, Ordering::Relaxed);
-                        if work_idx >= symfiles.len() {
-                            return Ok(());
-                        }
-                        let sub_path = &symfiles[work_idx].as_ref();
+/// Options to control the output of [`SymCorpus::write_consolidated_with()`].
+///
+/// The options are created with their default values by [`ConsolidateOptions::new()`] and can be
+/// adjusted using the builder methods:
+///
+/// ```
+/// use suse_kabi_tools::sym::{ConsolidateOptions, ConsolidateSort};
+///
+/// let options = ConsolidateOptions::new()
+///     .sort(ConsolidateSort::Name)
+///     .elide_implicit(false)
+///     .strip_prefix("build/");
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConsolidateOptions {
+    sort: ConsolidateSort,
+    elide_implicit: bool,
+    strip_prefix: Option<PathBuf>,
+    crcs: bool,
+    compression: crate::Compression,
+    diagnostics: DiagnosticsSink,
+    progress: ProgressSink,
+}
 
-                        let path = root.join(sub_path);
-                        let file = PathFile::open(&path).map_err(|err| {
-                            crate::Error::new_io(
-                                &format!("Failed to open file '{}'", path.display()),
-                                err,
-                            )
-                        })?;
+impl Default for ConsolidateOptions {
+    fn default() -> Self {
+        Self {
+            sort: ConsolidateSort::default(),
+            elide_implicit: true,
+            strip_prefix: None,
+            crcs: false,
+            compression: crate::Compression::default(),
+            diagnostics: DiagnosticsSink::default(),
+            progress: ProgressSink::default(),
+        }
+    }
+}
 
-                        Self::load_inner(sub_path, file, &load_context)?;
-                    }
-                }));
-            }
+impl ConsolidateOptions {
+    /// Creates new options with the default values, matching the output of
+    /// [`SymCorpus::write_consolidated()`].
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-            // Join all worker threads. Return the first error if any is found, others are silently
-            // swallowed which is ok.
-            for worker in workers {
-                worker.join().unwrap()?
-            }
+    /// Sets the order of records in the output.
+    pub fn sort(mut self, sort: ConsolidateSort) -> Self {
+        self.sort = sort;
+        self
+    }
 
-            Ok(())
-        })
+    /// Sets whether types which have only one variant in the output are omitted from `F#` records.
+    pub fn elide_implicit(mut self, elide_implicit: bool) -> Self {
+        self.elide_implicit = elide_implicit;
+        self
     }
 
-    /// Loads symtypes data from a specified reader.
+    /// Sets a prefix which is stripped from file paths written on `F#` records. Paths which don't
+    /// start with the prefix are written unchanged.
+    pub fn strip_prefix<P: Into<PathBuf>>(mut self, prefix: P) -> Self {
+        self.strip_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets whether the genksyms CRC of each export is recorded in the output.
     ///
-    /// The `path` should point to a `.symtypes` file name, indicating the origin of the data.
-    pub fn load_buffer<P: AsRef<Path>, R: Read>(
-        &mut self,
-        path: P,
-        reader: R,
-    ) -> Result<(), crate::Error> {
-        let load_context = LoadContext {
-            types: RwLock::new(&mut self.types),
-            exports: Mutex::new(&mut self.exports),
-            files: Mutex::new(&mut self.files),
-        };
+    /// The CRCs are written on `F#` records after the names of the exports, separated by `=`, for
+    /// instance, `foo=0x12345678`. The output then starts with a `V#2` header which declares the
+    /// version of the format, so that readers which do not understand the CRCs reject the file.
+    /// A corpus loaded from such a file uses the recorded CRCs in place of computing them.
+    pub fn crcs(mut self, crcs: bool) -> Self {
+        self.crcs = crcs;
+        self
+    }
 
-        Self::load_inner(path, reader, &load_context)?;
+    /// Sets the compression format of the output.
+    pub fn compression(mut self, compression: crate::Compression) -> Self {
+        self.compression = compression;
+        self
+    }
 
-        Ok(())
+    /// Sets a receiver of warnings found during the consolidation.
+    pub fn diagnostics(mut self, diagnostics: Arc<dyn Diagnostics>) -> Self {
+        self.diagnostics = DiagnosticsSink(Some(diagnostics));
+        self
     }
 
-    /// Loads symtypes data from a specified reader.
-    fn load_inner<P: AsRef<Path>, R: Read>(
-        path: P,
-        reader: R,
-        load_context: &LoadContext,
-    ) -> Result<(), crate::Error> {
-        let path = path.as_ref();
-        debug!("Loading '{}'", path.display());
+    /// Sets an observer of the consolidation progress, which is reported as
+    /// [`ProgressPhase::Consolidate`].
+    pub fn progress(mut self, progress: Arc<ProgressFn>) -> Self {
+        self.progress = ProgressSink(Some(progress));
+        self
+    }
 
-        let mut records = FileRecords::new();
+    /// Returns the path of a file as it should be written on its `F#` record.
+    fn output_path<'a>(&self, path: &'a Path) -> &'a Path {
+        match &self.strip_prefix {
+            Some(prefix) => path.strip_prefix(prefix).unwrap_or(path),
+            None => path,
+        }
+    }
 
-        // Map each variant name/index that the type has in this specific .symtypes file to one
-        // which it got assigned in the entire loaded corpus.
-        let mut remap: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    /// Returns a sort key of a record with the given name.
+    fn sort_key<'a>(&self, name: &'a str) -> (bool, &'a str) {
+        match self.sort {
+            ConsolidateSort::TypesFirst => (is_export_name(name), name),
+            ConsolidateSort::Name => (false, name),
+        }
+    }
+}
 
-        // Read all content from the file.
-        let lines = match read_lines(reader) {
-            Ok(lines) => lines,
-            Err(err) => return Err(crate::Error::new_io("Failed to read symtypes data", err)),
-        };
+/// Types whose definitions depend on the pointer size of the architecture, such as
+/// `t#size_t`, which is `unsigned int` on 32-bit and `unsigned long` on 64-bit architectures.
+pub const POINTER_SIZE_TYPES: &[&str] = &[
+    "t#__kernel_long_t",
+    "t#__kernel_ptrdiff_t",
+    "t#__kernel_size_t",
+    "t#__kernel_ssize_t",
+    "t#__kernel_ulong_t",
+    "t#atomic_long_t",
+    "t#dma_addr_t",
+    "t#phys_addr_t",
+    "t#ptrdiff_t",
+    "t#size_t",
+    "t#ssize_t",
+    "t#uintptr_t",
+];
+
+/// Options to control comparison of symtypes corpuses by [`SymCorpus::compare_with_options()`].
+///
+/// The options are created with their default values by [`CompareOptions::new()`] and can be
+/// adjusted using the builder methods:
+///
+/// ```
+/// use suse_kabi_tools::sym::CompareOptions;
+///
+/// let options = CompareOptions::new().num_workers(4);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompareOptions {
+    num_workers: i32,
+    tolerated_types: FxHashSet<String>,
+    diagnostics: DiagnosticsSink,
+    progress: ProgressSink,
+}
 
-        // Detect whether the input is a single or consolidated symtypes file.
-        let mut is_consolidated = false;
-        for line in &lines {
-            if line.starts_with("F#") {
-                is_consolidated = true;
-                break;
-            }
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            num_workers: 1,
+            tolerated_types: FxHashSet::default(),
+            diagnostics: DiagnosticsSink::default(),
+            progress: ProgressSink::default(),
         }
+    }
+}
 
-        let file_idx = if !is_consolidated {
-            // Record the file early to determine its file_idx.
-            let symfile = SymFile {
-                path: path.to_path_buf(),
-                records: FileRecords::new(),
-            };
-
-            let mut files = load_context.files.lock().unwrap();
-            files.push(symfile);
-            files.len() - 1
-        } else {
-            usize::MAX
-        };
+impl CompareOptions {
+    /// Creates new options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        // Track names of all entries to detect duplicates.
-        let mut all_names = HashSet::new();
+    /// Sets the number of workers to compare exports simultaneously.
+    pub fn num_workers(mut self, num_workers: i32) -> Self {
+        self.num_workers = num_workers;
+        self
+    }
 
-        // Parse all declarations.
-        let mut file_indices = Vec::new();
-        for (line_idx, line) in lines.iter().enumerate() {
-            // Obtain a name of the record.
-            let mut words = line.split_ascii_whitespace();
-            let name = words.next().ok_or_else(|| {
-                crate::Error::new_parse(&format!(
-                    "{}:{}: Expected a record name",
-                    path.display(),
-                    line_idx + 1
-                ))
-            })?;
+    /// Adds types whose changes are tolerated, for instance, types specific to an architecture
+    /// when comparing corpuses of different architectures. Changes of such types are not
+    /// reported, and exports affected only by them are not considered changed.
+    pub fn tolerate_types<I: IntoIterator<Item = S>, S: Into<String>>(mut self, names: I) -> Self {
+        self.tolerated_types
+            .extend(names.into_iter().map(Into::into));
+        self
+    }
 
-            // Check if the record is a duplicate of another one.
-            match all_names.get(name) {
-                Some(_) => {
-                    return Err(crate::Error::new_parse(&format!(
-                        "{}:{}: Duplicate record '{}'",
-                        path.display(),
-                        line_idx + 1,
-                        name,
-                    )))
-                }
-                None => all_names.insert(name.to_string()),
-            };
+    /// Sets a receiver of warnings found during the comparison.
+    pub fn diagnostics(mut self, diagnostics: Arc<dyn Diagnostics>) -> Self {
+        self.diagnostics = DiagnosticsSink(Some(diagnostics));
+        self
+    }
 
-            // Check for a file declaration and remember its index. File declarations are processed
-            // later after remapping of all symbol variants is known.
-            if name.starts_with("F#") {
-                file_indices.push(line_idx);
-                continue;
-            }
+    /// Sets an observer of the comparison progress, which is reported as
+    /// [`ProgressPhase::Compare`].
+    pub fn progress(mut self, progress: Arc<ProgressFn>) -> Self {
+        self.progress = ProgressSink(Some(progress));
+        self
+    }
+}
 
-            // Handle a type/export record.
+/// A kind of change of an export, as considered by [`CompareResult::score()`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum ChangeKind {
+    /// The export is present only in the first corpus.
+    Removed,
+    /// The export is present only in the second corpus.
+    Added,
+    /// The export is present in both corpuses but some of its types differ.
+    Changed,
+}
 
-            // Turn the remaining words into tokens.
-            let tokens = words_into_tokens(&mut words);
+impl ChangeKind {
+    /// Returns the kind with the specified name, `removed`, `added` or `changed`, or [`None`] if the
+    /// name is not recognized.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "removed" => Some(Self::Removed),
+            "added" => Some(Self::Added),
+            "changed" => Some(Self::Changed),
+            _ => None,
+        }
+    }
+}
 
-            // Parse the base name and any variant name/index, which is appended as a suffix after
-            // the `@` character.
-            let (base_name, orig_variant_name) = if is_consolidated {
-                split_type_name(name)
-            } else {
-                (name, &name[name.len()..])
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Removed => write!(f, "removed"),
+            Self::Added => write!(f, "added"),
+            Self::Changed => write!(f, "changed"),
+        }
+    }
+}
+
+/// A function which determines the severity of a change of an export. It receives the name of the
+/// export, the path of the `.symtypes` file which defines it, or the module which provides it when
+/// scoring symvers data, if known, and the kind of the change.
+/// The returned value multiplies the weight of the change kind, with 0 meaning that the change is
+/// ignored.
+pub type SeverityFn = dyn Fn(&str, Option<&Path>, ChangeKind) -> u64 + Send + Sync;
+
+/// Options to control scoring of a comparison result by [`CompareResult::score()`].
+///
+/// The badness of each removed, added or changed export is the weight of its change kind multiplied
+/// by its severity. By default, a removed export weighs 8, a changed export 4 and an added export 0,
+/// and all changes have the severity 1. The options are created with their default values by
+/// [`ScoreOptions::new()`] and can be adjusted using the builder methods:
+///
+/// ```
+/// use suse_kabi_tools::sym::{ChangeKind, ScoreOptions};
+///
+/// let options = ScoreOptions::new()
+///     .weight(ChangeKind::Added, 1)
+///     .whitelist(["foo", "bar"]);
+/// ```
+#[derive(Clone)]
+pub struct ScoreOptions {
+    removed_weight: u64,
+    added_weight: u64,
+    changed_weight: u64,
+    whitelist: Option<FxHashSet<String>>,
+    severity: Option<Arc<SeverityFn>>,
+}
+
+impl Default for ScoreOptions {
+    fn default() -> Self {
+        Self {
+            removed_weight: 8,
+            added_weight: 0,
+            changed_weight: 4,
+            whitelist: None,
+            severity: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for ScoreOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ScoreOptions")
+            .field("removed_weight", &self.removed_weight)
+            .field("added_weight", &self.added_weight)
+            .field("changed_weight", &self.changed_weight)
+            .field("whitelist", &self.whitelist)
+            .field("severity", &self.severity.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl ScoreOptions {
+    /// Creates new options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the weight of the change kind `kind`.
+    pub fn weight(mut self, kind: ChangeKind, weight: u64) -> Self {
+        match kind {
+            ChangeKind::Removed => self.removed_weight = weight,
+            ChangeKind::Added => self.added_weight = weight,
+            ChangeKind::Changed => self.changed_weight = weight,
+        }
+        self
+    }
+
+    /// Restricts scoring to the specified exports. Changes of other exports are ignored.
+    pub fn whitelist<I: IntoIterator<Item = S>, S: Into<String>>(mut self, names: I) -> Self {
+        self.whitelist = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets a function which determines the severity of each change.
+    pub fn severity(mut self, severity: Arc<SeverityFn>) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    /// Calculates the badness of the specified changes. Each change is described by the name of
+    /// the export, the path which defines it, if known, and the kind of the change.
+    pub fn score_changes<'a, I: IntoIterator<Item = (&'a str, Option<&'a Path>, ChangeKind)>>(
+        &self,
+        changes: I,
+    ) -> Score {
+        let mut score = Score::default();
+        for (name, file, kind) in changes {
+            let badness = self.badness(name, file, kind);
+            if badness == 0 {
+                continue;
+            }
+            score.total = score.total.saturating_add(badness);
+            let export_score = score.exports.entry(name.to_string()).or_default();
+            *export_score = export_score.saturating_add(badness);
+            if let Some(file) = file {
+                let module_score = score.modules.entry(file.to_path_buf()).or_default();
+                *module_score = module_score.saturating_add(badness);
+            }
+        }
+        score
+    }
+
+    /// Returns the badness of the change `kind` of the export `name`, defined in `file`.
+    fn badness(&self, name: &str, file: Option<&Path>, kind: ChangeKind) -> u64 {
+        if self
+            .whitelist
+            .as_ref()
+            .is_some_and(|whitelist| !whitelist.contains(name))
+        {
+            return 0;
+        }
+        let weight = match kind {
+            ChangeKind::Removed => self.removed_weight,
+            ChangeKind::Added => self.added_weight,
+            ChangeKind::Changed => self.changed_weight,
+        };
+        let severity = match &self.severity {
+            Some(severity) => severity(name, file, kind),
+            None => 1,
+        };
+        weight.saturating_mul(severity)
+    }
+}
+
+/// An indentation style used by [`pretty_format_type_with()`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum IndentStyle {
+    /// Each indentation level is a single tab.
+    #[default]
+    Tabs,
+    /// Each indentation level is the given number of spaces.
+    Spaces(usize),
+}
+
+/// Options to control the output of [`pretty_format_type_with()`].
+///
+/// The options are created with their default values by [`FormatOptions::new()`] and can be
+/// adjusted using the builder methods:
+///
+/// ```
+/// use suse_kabi_tools::sym::{FormatOptions, IndentStyle};
+///
+/// let options = FormatOptions::new()
+///     .indent(IndentStyle::Spaces(4))
+///     .line_width(80);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FormatOptions {
+    indent: IndentStyle,
+    line_width: usize,
+}
+
+impl FormatOptions {
+    /// Creates new options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the indentation style.
+    pub fn indent(mut self, indent: IndentStyle) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Sets the maximum width of an output line, with a tab counted as 8 columns. A longer line is
+    /// wrapped between words and its continuation is indented by one more level. A single word
+    /// longer than the limit is never split. The value 0, which is the default, disables the
+    /// wrapping.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Appends the indentation for the given level to `line`.
+    fn push_indent(&self, line: &mut String, level: usize) {
+        for _ in 0..level {
+            match self.indent {
+                IndentStyle::Tabs => line.push('\t'),
+                IndentStyle::Spaces(num) => line.extend(std::iter::repeat_n(' ', num)),
+            }
+        }
+    }
+}
+
+/// A policy for handling an export which is defined in multiple files.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DuplicateExports {
+    /// A duplicate export is reported as an error.
+    #[default]
+    Error,
+    /// The first loaded definition of the export is kept and any other is ignored. Note that the
+    /// order in which files are loaded is not defined when using multiple workers.
+    KeepFirst,
+}
+
+/// A dialect of symtypes data, which depends on the version of genksyms that produced it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum InputDialect {
+    /// The dialect is detected separately for each input. An input is read in the legacy dialect
+    /// once a record with its quirks is found.
+    #[default]
+    Auto,
+    /// The output of current genksyms versions, read without any compatibility quirks.
+    Current,
+    /// The output of older genksyms versions, such as those used by SLE12 kernels. A type prefix
+    /// separated from the name by whitespace, as in `s# foo`, is joined with the name.
+    Legacy,
+}
+
+impl InputDialect {
+    /// Returns the dialect with the specified name, `auto`, `current` or `legacy`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "current" => Some(Self::Current),
+            "legacy" => Some(Self::Legacy),
+            _ => None,
+        }
+    }
+}
+
+/// Durations of the phases of loading symtypes data, collected when set by
+/// [`LoadOptions::timings()`].
+///
+/// The loading consists of scanning the input directories for files, parsing the files by the
+/// workers, and merging the data parsed by the workers into the corpus. The workers merge their data
+/// in chunks while other workers continue parsing, the time of the merges is not included in the
+/// parsing time. The durations are summed when the collector is used by multiple loading
+/// operations.
+#[derive(Debug, Default)]
+pub struct LoadTimings {
+    data: Mutex<LoadTimingsData>,
+}
+
+/// Durations stored by [`LoadTimings`].
+#[derive(Debug, Default)]
+struct LoadTimingsData {
+    scan: Duration,
+    parse: Duration,
+    merge: Duration,
+    workers: Vec<WorkerTiming>,
+    files: Vec<(PathBuf, Duration)>,
+}
+
+impl LoadTimings {
+    /// Creates a new empty collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the time spent scanning the input directories for files.
+    pub fn scan(&self) -> Duration {
+        self.data.lock().unwrap().scan
+    }
+
+    /// Returns the wall-clock time spent parsing the files by the workers.
+    pub fn parse(&self) -> Duration {
+        self.data.lock().unwrap().parse
+    }
+
+    /// Returns the time spent merging the data parsed by the workers into the corpus.
+    pub fn merge(&self) -> Duration {
+        self.data.lock().unwrap().merge
+    }
+
+    /// Returns the timings of all workers which parsed the files.
+    pub fn workers(&self) -> Vec<WorkerTiming> {
+        self.data.lock().unwrap().workers.clone()
+    }
+
+    /// Returns up to `num` files which took the longest time to parse, starting with the slowest.
+    pub fn slowest_files(&self, num: usize) -> Vec<(PathBuf, Duration)> {
+        let mut files = self.data.lock().unwrap().files.clone();
+        files.sort_by(|(path, time), (path2, time2)| time2.cmp(time).then_with(|| path.cmp(path2)));
+        files.truncate(num);
+        files
+    }
+
+    /// Adds the time of a loading phase, as selected by `phase_fn`.
+    fn add_phase(&self, phase_fn: fn(&mut LoadTimingsData) -> &mut Duration, time: Duration) {
+        *phase_fn(&mut self.data.lock().unwrap()) += time;
+    }
+}
+
+/// The time spent by a worker parsing symtypes files, see [`LoadTimings::workers()`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct WorkerTiming {
+    num_files: usize,
+    busy: Duration,
+}
+
+impl WorkerTiming {
+    /// Returns the number of files parsed by the worker.
+    pub fn num_files(&self) -> usize {
+        self.num_files
+    }
+
+    /// Returns the time the worker spent parsing the files.
+    pub fn busy(&self) -> Duration {
+        self.busy
+    }
+}
+
+/// Options to control loading of symtypes data by [`SymCorpus::load_with()`] and related functions.
+///
+/// The options are created with their default values by [`LoadOptions::new()`] and can be adjusted
+/// using the builder methods:
+///
+/// ```
+/// use suse_kabi_tools::sym::{DuplicateExports, LoadOptions};
+///
+/// let options = LoadOptions::new()
+///     .num_workers(4)
+///     .duplicate_exports(DuplicateExports::KeepFirst)
+///     .validate_refs(false);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    num_workers: i32,
+    follow_symlinks: bool,
+    extensions: Vec<String>,
+    duplicate_exports: DuplicateExports,
+    validate_refs: bool,
+    lenient: bool,
+    max_errors: usize,
+    diagnostics: DiagnosticsSink,
+    progress: ProgressSink,
+    normalize_paths: bool,
+    strip_prefix: Option<PathBuf>,
+    lowercase_paths: bool,
+    only_exports: Option<FxHashSet<String>>,
+    only_files: Option<FxHashSet<PathBuf>>,
+    timings: Option<Arc<LoadTimings>>,
+    input_dialect: InputDialect,
+    #[cfg(feature = "mmap")]
+    mmap_reads: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            num_workers: 1,
+            follow_symlinks: false,
+            extensions: vec!["symtypes".to_string()],
+            duplicate_exports: DuplicateExports::default(),
+            validate_refs: true,
+            lenient: false,
+            max_errors: 1,
+            diagnostics: DiagnosticsSink::default(),
+            progress: ProgressSink::default(),
+            normalize_paths: false,
+            strip_prefix: None,
+            lowercase_paths: false,
+            only_exports: None,
+            only_files: None,
+            timings: None,
+            input_dialect: InputDialect::default(),
+            #[cfg(feature = "mmap")]
+            mmap_reads: false,
+        }
+    }
+}
+
+impl LoadOptions {
+    /// Creates new options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of workers to load files simultaneously.
+    pub fn num_workers(mut self, num_workers: i32) -> Self {
+        self.num_workers = num_workers;
+        self
+    }
+
+    /// Sets whether symbolic links are followed when searching directories. Otherwise, they are
+    /// skipped.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets extensions of files which are collected when searching directories, for instance,
+    /// `symtypes`. An extension can be composed of several parts, such as `symtypes.gz`. Files with
+    /// a matching extension followed by an extension of a supported compression format are
+    /// collected as well.
+    pub fn extensions<I: IntoIterator<Item = S>, S: Into<String>>(mut self, extensions: I) -> Self {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets how an export defined in multiple files is handled.
+    pub fn duplicate_exports(mut self, duplicate_exports: DuplicateExports) -> Self {
+        self.duplicate_exports = duplicate_exports;
+        self
+    }
+
+    /// Sets whether all type references are checked to be resolvable after a file is loaded. The
+    /// validation is enabled by default.
+    ///
+    /// References in a single symtypes file must resolve to types defined in the same file.
+    /// References in a consolidated file must resolve to types listed in the respective `F#` record
+    /// or to types which can be implicitly added to it. When the validation is disabled, unresolved
+    /// references are skipped by the consolidation and are treated as types with an empty
+    /// definition by the comparison.
+    pub fn validate_refs(mut self, validate_refs: bool) -> Self {
+        self.validate_refs = validate_refs;
+        self
+    }
+
+    /// Sets whether malformed records are skipped instead of being reported as an error. This
+    /// applies to empty records, duplicate records and unknown types on `F#` records.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Sets the maximum number of parse errors which are collected before loading is stopped. All
+    /// collected errors are then reported together as [`crate::Error::Multiple`]. A value of 0
+    /// means no limit. With the default value of 1, loading stops at the first error.
+    ///
+    /// A record causing an error is skipped and loading continues with the next one. Note that
+    /// when using multiple workers, each of them stops on its own when it reaches the limit.
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Sets a receiver of warnings found during loading.
+    pub fn diagnostics(mut self, diagnostics: Arc<dyn Diagnostics>) -> Self {
+        self.diagnostics = DiagnosticsSink(Some(diagnostics));
+        self
+    }
+
+    /// Sets an observer of the loading progress, which is reported as [`ProgressPhase::Load`].
+    /// The total number of files is known in advance only when loading files from the
+    /// filesystem or from an iterator with an exact size.
+    pub fn progress(mut self, progress: Arc<ProgressFn>) -> Self {
+        self.progress = ProgressSink(Some(progress));
+        self
+    }
+
+    /// Sets whether the paths of loaded files are normalized lexically, by removing `.`
+    /// components and resolving `..` components against the preceding ones. The filesystem is not
+    /// accessed, so symbolic links are not resolved.
+    pub fn normalize_paths(mut self, normalize_paths: bool) -> Self {
+        self.normalize_paths = normalize_paths;
+        self
+    }
+
+    /// Sets a prefix to strip from the paths of loaded files, making them relative to it. A path
+    /// which does not start with the prefix is kept as is.
+    pub fn strip_prefix<P: Into<PathBuf>>(mut self, prefix: P) -> Self {
+        self.strip_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets whether the paths of loaded files are converted to lowercase, for corpora produced on
+    /// case-insensitive filesystems.
+    pub fn lowercase_paths(mut self, lowercase_paths: bool) -> Self {
+        self.lowercase_paths = lowercase_paths;
+        self
+    }
+
+    /// Restricts loading to the specified exports. Only their records and the types reachable
+    /// from them are loaded, other records are skipped without being parsed into tokens. A file
+    /// which defines none of the exports is skipped entirely.
+    ///
+    /// Each input is first indexed as by [`LazySymCorpus`] and line numbers in reported errors
+    /// refer to the selected records, rather than to the original input.
+    pub fn only_exports<I: IntoIterator<Item = S>, S: Into<String>>(mut self, names: I) -> Self {
+        self.only_exports = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Restricts loading to the specified symtypes files, for instance, those affected by a change
+    /// of the kernel sources. A file is identified by its path relative to the searched directory,
+    /// by its path recorded in a consolidated file, or by its path as given if the file is
+    /// specified directly. Other files found in directories are not read at all and other files of
+    /// a consolidated file are skipped as with [`LoadOptions::only_exports()`].
+    pub fn only_files<I: IntoIterator<Item = P>, P: Into<PathBuf>>(mut self, paths: I) -> Self {
+        self.only_files = Some(paths.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets a collector of durations of the loading phases, the workers and the individual files.
+    /// The time is measured only when the collector is set.
+    pub fn timings(mut self, timings: Arc<LoadTimings>) -> Self {
+        self.timings = Some(timings);
+        self
+    }
+
+    /// Sets the dialect of the symtypes data, which allows reading corpora produced by older
+    /// genksyms versions without reporting their differences in formatting as type changes.
+    pub fn input_dialect(mut self, input_dialect: InputDialect) -> Self {
+        self.input_dialect = input_dialect;
+        self
+    }
+
+    /// Sets whether uncompressed files found on the filesystem are read through a read-only memory
+    /// mapping, instead of through a buffer.
+    ///
+    /// The lines are parsed directly in the mapping, which saves copying them into the buffer. The
+    /// words are still copied into the corpus, so the memory used by the loaded data is the same in
+    /// both modes.
+    #[cfg(feature = "mmap")]
+    pub fn mmap_reads(mut self, mmap_reads: bool) -> Self {
+        self.mmap_reads = mmap_reads;
+        self
+    }
+
+    /// Returns the path of a loaded file as it should be recorded in the corpus. The path is
+    /// normalized first, then the prefix is stripped and finally the case is converted.
+    fn stored_path(&self, path: &Path) -> PathBuf {
+        let mut path = if self.normalize_paths {
+            normalize_path(path)
+        } else {
+            path.to_path_buf()
+        };
+        if let Some(prefix) = &self.strip_prefix {
+            let prefix = if self.normalize_paths {
+                normalize_path(prefix)
+            } else {
+                prefix.clone()
+            };
+            if let Ok(stripped) = path.strip_prefix(&prefix) {
+                path = stripped.to_path_buf();
+            }
+        }
+        if self.lowercase_paths {
+            path = PathBuf::from(path.to_string_lossy().to_lowercase());
+        }
+        path
+    }
+
+    /// Returns whether only some records of the inputs are selected for loading.
+    fn is_selective(&self) -> bool {
+        self.only_exports.is_some() || self.only_files.is_some()
+    }
+
+    /// Returns whether the export `name` defined by the file `path` is selected for loading.
+    fn is_selected(&self, name: &str, path: &Path) -> bool {
+        self.only_exports
+            .as_ref()
+            .is_none_or(|only_exports| only_exports.contains(name))
+            && self
+                .only_files
+                .as_ref()
+                .is_none_or(|only_files| only_files.contains(path))
+    }
+
+    /// Returns whether the file name of the specified path ends with one of the extensions. An
+    /// extension of a supported compression format is ignored.
+    fn matches_extension(&self, path: &Path) -> bool {
+        let path = crate::Compression::from_path(path).strip_extension(path);
+        let file_name = match path.file_name().and_then(|file_name| file_name.to_str()) {
+            Some(file_name) => file_name,
+            None => return false,
+        };
+        self.extensions.iter().any(|ext| {
+            file_name
+                .strip_suffix(ext.as_str())
+                .and_then(|stem| stem.strip_suffix('.'))
+                .is_some_and(|stem| !stem.is_empty())
+        })
+    }
+}
+
+/// The number of tokens after which the data loaded by a worker is merged into the corpus.
+///
+/// Merging the data in chunks limits the memory held by the workers. Each merge interns the strings
+/// of the chunk anew, so a too small threshold makes the loading slower.
+const MERGE_THRESHOLD: usize = 1 << 14;
+
+/// A helper struct to record symtypes data loaded by one worker during parallel loading.
+///
+/// Each worker loads its inputs into a separate context, without any synchronization with other
+/// workers. A context is merged into the `SymCorpus` when it reaches [`MERGE_THRESHOLD`] tokens
+/// and after all inputs are processed.
+struct LoadContext<'a> {
+    types: Types,
+    exports: Exports,
+    files: SymFiles,
+    strings: Interner,
+    export_lines: FxHashMap<StrId, usize>,
+    crcs: Crcs,
+    errors: LoadErrors,
+    options: &'a LoadOptions,
+}
+
+impl<'a> LoadContext<'a> {
+    /// Creates a new empty context.
+    fn new(options: &'a LoadOptions) -> Self {
+        Self {
+            types: Types::default(),
+            exports: Exports::default(),
+            files: SymFiles::new(),
+            strings: Interner::default(),
+            export_lines: FxHashMap::default(),
+            crcs: Crcs::default(),
+            errors: LoadErrors::new(options.max_errors),
+            options,
+        }
+    }
+
+    /// Formats the path of the input being loaded for a message.
+    fn display_input_path(&self, path: &Path) -> String {
+        match &self.errors.root {
+            Some(root) => crate::display_stored_path(root, path),
+            None => path.display().to_string(),
+        }
+    }
+}
+
+/// A helper struct to collect parse errors found during loading, up to the limit set by
+/// [`LoadOptions::max_errors()`].
+struct LoadErrors {
+    errors: Vec<crate::Error>,
+    max_errors: usize,
+    /// The directory to which the path of the input being loaded is relative, if known.
+    root: Option<PathBuf>,
+}
+
+impl LoadErrors {
+    /// Creates a new empty collection with the given limit.
+    fn new(max_errors: usize) -> Self {
+        Self {
+            errors: Vec::new(),
+            max_errors,
+            root: None,
+        }
+    }
+
+    /// Records a parse error found in the input being loaded. Returns [`Err`] with all collected
+    /// errors if their limit is reached, otherwise the caller should skip the offending record and
+    /// continue.
+    fn report(&mut self, err: crate::Error) -> Result<(), crate::Error> {
+        let err = match &self.root {
+            Some(root) => err.with_root(root),
+            None => err,
+        };
+        self.report_stored(err)
+    }
+
+    /// Records a parse error which refers to a path as stored in the corpus, in the same way as
+    /// [`LoadErrors::report()`].
+    fn report_stored(&mut self, err: crate::Error) -> Result<(), crate::Error> {
+        self.errors.push(err);
+        if self.max_errors != 0 && self.errors.len() >= self.max_errors {
+            return Err(crate::Error::from_multiple(mem::take(&mut self.errors)));
+        }
+        Ok(())
+    }
+
+    /// Records all errors collected in another instance.
+    fn extend(&mut self, other: LoadErrors) -> Result<(), crate::Error> {
+        for err in other.errors {
+            self.report_stored(err)?;
+        }
+        Ok(())
+    }
+
+    /// Returns [`Err`] with all collected errors, or [`Ok`] if there are none.
+    fn into_result(self) -> Result<(), crate::Error> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::Error::from_multiple(self.errors))
+        }
+    }
+}
+
+/// An opened symtypes file, to be loaded by a worker.
+enum SymfileInput {
+    /// A stream of the file data.
+    Reader(Box<dyn Read + Send>),
+    /// A read-only memory mapping of the file.
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+/// A helper struct to record the state of parsing one symtypes input.
+#[derive(Default)]
+struct LoadState {
+    /// Names of all entries, to detect duplicates.
+    all_names: FxHashSet<String>,
+    /// The base name, variant name/index, assigned variant index in the context and line index of
+    /// each type/export record.
+    type_records: Vec<(StrId, String, VariantIdx, usize)>,
+    /// All `F#` records with their line indices, these are processed after all type records are
+    /// known.
+    file_lines: Vec<(usize, String)>,
+    /// Whether the input was detected to be in the legacy dialect.
+    legacy_dialect: bool,
+    /// The format version declared by a `V#` header, or 0 if the input has no header.
+    version: u32,
+    /// A buffer for the tokens of the record being parsed, reused to avoid an allocation for each
+    /// record.
+    tokens: Vec<Token>,
+}
+
+/// Type names to be present in the consolidated output, along with a mapping from their internal
+/// symbol variant indices to the output variant indices.
+type ConsolidateOutputTypes = FxHashMap<StrId, FxHashMap<VariantIdx, usize>>;
+
+/// Type names processed during consolidation for a specific file, providing for each type their
+/// output variant index.
+type ConsolidateFileTypes = FxHashMap<StrId, usize>;
+
+/// Changes between two corpuses, recording a tuple of each modified type's `name`, its old `tokens`
+/// and its new `tokens`, along with a [`Vec`] of exported symbols affected by the change. The names
+/// are identifiers in the first corpus.
+type CompareChangedTypes<'a> = FxHashMap<(StrId, &'a Tokens, &'a Tokens), Vec<StrId>>;
+
+/// Type names processed during comparison for a specific file.
+type CompareFileTypes = FxHashSet<StrId>;
+
+impl SymCorpus {
+    /// Creates a new empty corpus.
+    pub fn new() -> Self {
+        Self {
+            types: Types::default(),
+            exports: Exports::default(),
+            files: SymFiles::new(),
+            namespaces: Namespaces::default(),
+            export_types: ExportTypes::default(),
+            modules: Modules::default(),
+            crcs: Crcs::default(),
+            strings: Interner::default(),
+            crc_only: false,
+        }
+    }
+
+    /// Creates a corpus which describes only the CRCs of the exports in `symvers`, for a kernel
+    /// whose symtypes are not available.
+    ///
+    /// Each export is recorded with an opaque definition `crc <crc>` in the file named after its
+    /// module with the extension `.symtypes`, for instance, `vmlinux.symtypes`. The namespaces,
+    /// export types and modules are taken from `symvers` as by [`SymCorpus::load_symvers()`]. Comparing such
+    /// a corpus finds removed, added and changed exports only by their CRCs, see
+    /// [`SymCorpus::compare_with_options()`].
+    pub fn from_symvers(symvers: &crate::symvers::SymversCorpus) -> Self {
+        let mut files: BTreeMap<PathBuf, String> = BTreeMap::new();
+        for export in symvers.exports() {
+            let path = Path::new(export.module()).with_extension("symtypes");
+            let data = files.entry(path).or_default();
+            data.push_str(&format!("{} crc {}\n", export.name(), export.crc()));
+        }
+
+        let mut syms = Self::new();
+        let options = LoadOptions::new().duplicate_exports(DuplicateExports::KeepFirst);
+        let result = syms.load_from_iter(
+            files
+                .iter()
+                .map(|(path, data)| (path.clone(), data.as_bytes())),
+            &options,
+        );
+        // The records consist of plain words without any type references, which always load.
+        assert!(result.is_ok());
+        syms.add_symvers(symvers);
+        syms.crc_only = true;
+        syms
+    }
+
+    /// Returns whether the corpus describes only the CRCs of its exports, as created by
+    /// [`SymCorpus::from_symvers()`].
+    pub fn is_crc_only(&self) -> bool {
+        self.crc_only
+    }
+
+    /// Turns the corpus into a read-only snapshot which can be cheaply shared between threads.
+    pub fn freeze(self) -> FrozenSymCorpus {
+        FrozenSymCorpus {
+            corpus: Arc::new(self),
+        }
+    }
+
+    /// Loads symtypes data from a given location.
+    ///
+    /// The `path` can point to a single `.symtypes` file or a directory. In the latter case, the
+    /// function recursively collects all `.symtypes` in that directory and loads them.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P, num_workers: i32) -> Result<(), crate::Error> {
+        self.load_with(path, &LoadOptions::new().num_workers(num_workers))
+    }
+
+    /// Loads symtypes data from a given location, using the specified options.
+    ///
+    /// The `path` can point to a single `.symtypes` file or a directory. In the latter case, the
+    /// function recursively collects all files matching the extensions from `options` in that
+    /// directory and loads them. A directory which directly contains kabi-dw dumps of functions or
+    /// variables is instead converted as described by [`crate::kabi_dw`]. The path `-` denotes the
+    /// standard input, which is read as a single file.
+    pub fn load_with<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error> {
+        self.load_multiple_with(&[path], options)
+    }
+
+    /// Loads symtypes data from multiple given locations.
+    ///
+    /// Each path is handled in the same way as by [`SymCorpus::load()`].
+    pub fn load_multiple<P: AsRef<Path>>(
+        &mut self,
+        paths: &[P],
+        num_workers: i32,
+    ) -> Result<(), crate::Error> {
+        self.load_multiple_with(paths, &LoadOptions::new().num_workers(num_workers))
+    }
+
+    /// Loads symtypes data from multiple given locations, using the specified options.
+    ///
+    /// Each path is handled in the same way as by [`SymCorpus::load_with()`].
+    pub fn load_multiple_with<P: AsRef<Path>>(
+        &mut self,
+        paths: &[P],
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error> {
+        let mut symfiles = Vec::new();
+        let mut dump_dirs = Vec::new();
+        #[cfg(feature = "tar")]
+        let mut archives = Vec::new();
+        #[cfg(feature = "rpm")]
+        let mut packages = Vec::new();
+        let mut read_stdin = false;
+
+        let scan_start = options.timings.as_ref().map(|_| Instant::now());
+        for path in paths {
+            let path = path.as_ref();
+
+            // Remember to read the standard input, which can be read only once.
+            if path == Path::new("-") {
+                read_stdin = true;
+                continue;
+            }
+
+            // Determine if the input is a directory tree or a single symtypes file.
+            let md = fs::metadata(path).map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to query path '{}'", crate::display_path(path)),
+                    err,
+                )
+            })?;
+
+            if md.is_dir() {
+                // Remember any directory with kabi-dw dumps, these are loaded after the standard
+                // input.
+                if crate::kabi_dw::is_dump_dir(path) {
+                    dump_dirs.push(path);
+                    continue;
+                }
+
+                // Recursively collect symtypes files within the directory.
+                let mut visited = HashSet::new();
+                Self::collect_symfiles(path, "", options, &mut visited, &mut symfiles)?;
+                continue;
+            }
+
+            // Remember any tar archive, these are loaded separately after all other files.
+            #[cfg(feature = "tar")]
+            if is_tar_archive(path) {
+                archives.push(path);
+                continue;
+            }
+
+            // Remember any RPM package, these are loaded after the tar archives.
+            #[cfg(feature = "rpm")]
+            if crate::rpm::is_package(path) {
+                packages.push(path);
+                continue;
+            }
+
+            // Add the single file.
+            symfiles.push((PathBuf::new(), path.to_path_buf()));
+        }
+        if let (Some(timings), Some(start)) = (&options.timings, scan_start) {
+            timings.add_phase(|data| &mut data.scan, start.elapsed());
+        }
+
+        // Load all found files.
+        self.load_symfiles(&symfiles, options)?;
+
+        // Load the standard input.
+        if read_stdin {
+            self.load_buffer_with("-", io::stdin().lock(), options)?;
+        }
+
+        // Load all directories with kabi-dw dumps.
+        for path in dump_dirs {
+            self.load_kabi_dw(path, options)?;
+        }
+
+        // Load all tar archives.
+        #[cfg(feature = "tar")]
+        for path in archives {
+            let file = PathFile::open(path).map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to open file '{}'", crate::display_path(path)),
+                    err,
+                )
+            })?;
+            self.load_tar(path, file, options)?;
+        }
+
+        // Load all RPM packages.
+        #[cfg(feature = "rpm")]
+        for path in packages {
+            let file = PathFile::open(path).map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to open file '{}'", crate::display_path(path)),
+                    err,
+                )
+            })?;
+            self.load_rpm(path, file, options)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads symtypes data converted from the kabi-dw dumps in the directory `path`, as described
+    /// by [`crate::kabi_dw`]. The converted files are loaded with paths relative to the directory.
+    fn load_kabi_dw(&mut self, path: &Path, options: &LoadOptions) -> Result<(), crate::Error> {
+        let files = crate::kabi_dw::read_dir(path)?;
+        let total = files.len();
+        let files = Mutex::new(
+            files
+                .into_iter()
+                .map(|(file_path, data)| (file_path, io::Cursor::new(data.into_bytes()))),
+        );
+        self.load_parallel(
+            || files.lock().unwrap().next().map(Ok),
+            Self::load_inner,
+            Some(total),
+            options,
+        )
+    }
+
+    /// Loads symtypes data from an RPM package, which is read from the specified reader.
+    ///
+    /// The `path` indicates the origin of the package. All regular files in the payload of the
+    /// package which match the extensions from `options` are loaded, as if they were found in
+    /// a directory. The files are read into memory before they are parsed.
+    #[cfg(feature = "rpm")]
+    fn load_rpm<R: Read + Send>(
+        &mut self,
+        path: &Path,
+        reader: R,
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error> {
+        let err_desc = || format!("Failed to read RPM package '{}'", crate::display_path(path));
+
+        let mut members = Vec::new();
+        crate::rpm::read_files(
+            reader,
+            |member_path| options.matches_extension(member_path),
+            |member_path, data| {
+                let compression = crate::Compression::from_path(&member_path);
+                let member = compression
+                    .reader(io::Cursor::new(data))
+                    .map(|reader| (compression.strip_extension(&member_path), reader))
+                    .map_err(|err| crate::Error::new_io(&err_desc(), err));
+                let is_ok = member.is_ok();
+                members.push(member);
+                is_ok
+            },
+        )
+        .map_err(|err| crate::Error::new_io(&err_desc(), err))?;
+
+        let total = members.len();
+        let members = Mutex::new(members.into_iter());
+        self.load_parallel(
+            || members.lock().unwrap().next(),
+            Self::load_inner,
+            Some(total),
+            options,
+        )
+    }
+
+    /// Loads symtypes data from a tar archive, which is read from the specified reader.
+    ///
+    /// The `path` indicates the origin of the archive and its extension determines the compression
+    /// format of the data. All regular members of the archive which match the extensions from
+    /// `options` are loaded, as if they were found in a directory.
+    #[cfg(feature = "tar")]
+    fn load_tar<R: Read + Send>(
+        &mut self,
+        path: &Path,
+        reader: R,
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error> {
+        let err_desc = || format!("Failed to read tar archive '{}'", crate::display_path(path));
+
+        let reader = crate::Compression::from_path(path)
+            .reader(reader)
+            .map_err(|err| crate::Error::new_io(&err_desc(), err))?;
+
+        if !crate::HAS_THREADS {
+            // Read all members of the archive upfront and load them in the current thread.
+            let mut members = Vec::new();
+            Self::read_tar_members(reader, options, &err_desc, |member| {
+                let is_ok = member.is_ok();
+                members.push(member);
+                is_ok
+            });
+            let total = members.len();
+            let members = Mutex::new(members.into_iter());
+            return self.load_parallel(
+                || members.lock().unwrap().next(),
+                Self::load_inner,
+                Some(total),
+                options,
+            );
+        }
+
+        std::thread::scope(|s| {
+            // Read the archive sequentially in a separate thread and pass the content of its
+            // members to the workers.
+            let (sender, receiver) = mpsc::sync_channel(options.num_workers.max(1) as usize);
+            s.spawn(move || {
+                Self::read_tar_members(reader, options, &err_desc, |member| {
+                    sender.send(member).is_ok()
+                })
+            });
+
+            let receiver = Mutex::new(receiver);
+            self.load_parallel(
+                || receiver.lock().unwrap().recv().ok(),
+                Self::load_inner,
+                None,
+                options,
+            )
+        })
+    }
+
+    /// Reads regular members of a tar archive which match the extensions from `options`.
+    ///
+    /// The path and a decompressing reader of each member are passed to `deliver`, followed by an
+    /// error if reading the archive fails. Reading stops when `deliver` returns `false`.
+    #[cfg(feature = "tar")]
+    fn read_tar_members<R, E, D>(reader: R, options: &LoadOptions, err_desc: &E, mut deliver: D)
+    where
+        R: Read,
+        E: Fn() -> String,
+        D: FnMut(Result<(PathBuf, Box<dyn Read + Send>), crate::Error>) -> bool,
+    {
+        let mut archive = tar::Archive::new(reader);
+        let entries = match archive.entries() {
+            Ok(entries) => entries,
+            Err(err) => {
+                deliver(Err(crate::Error::new_io(&err_desc(), err)));
+                return;
+            }
+        };
+
+        for maybe_entry in entries {
+            let member = maybe_entry.and_then(|mut entry| {
+                if !entry.header().entry_type().is_file() {
+                    return Ok(None);
+                }
+                let member_path = entry.path()?.into_owned();
+                if !options.matches_extension(&member_path) {
+                    return Ok(None);
+                }
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                let compression = crate::Compression::from_path(&member_path);
+                let reader = compression.reader(io::Cursor::new(data))?;
+                Ok(Some((compression.strip_extension(&member_path), reader)))
+            });
+
+            // Stop reading if the data can no longer be delivered, which happens when the workers
+            // end early because of an error.
+            let delivered = match member {
+                Ok(Some(member)) => deliver(Ok(member)),
+                Ok(None) => true,
+                Err(err) => {
+                    deliver(Err(crate::Error::new_io(&err_desc(), err)));
+                    false
+                }
+            };
+            if !delivered {
+                return;
+            }
+        }
+    }
+
+    /// Collects recursively all symtypes files under the given root path and its subpath.
+    ///
+    /// Each found file is recorded in `symfiles` as a tuple of the root path and its subpath.
+    fn collect_symfiles<P: AsRef<Path>, Q: AsRef<Path>>(
+        root: P,
+        sub_path: Q,
+        options: &LoadOptions,
+        visited: &mut HashSet<PathBuf>,
+        symfiles: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<(), crate::Error> {
+        let root = root.as_ref();
+        let sub_path = sub_path.as_ref();
+
+        let path = root.join(sub_path);
+
+        // When following symlinks, visit each directory only once to avoid loops.
+        if options.follow_symlinks {
+            let canonical_path = fs::canonicalize(&path).map_err(|err| {
+                crate::Error::new_io(
+                    &format!(
+                        "Failed to canonicalize path '{}'",
+                        crate::display_path(&path)
+                    ),
+                    err,
+                )
+            })?;
+            if !visited.insert(canonical_path) {
+                return Ok(());
+            }
+        }
+
+        let dir_iter = fs::read_dir(&path).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to read directory '{}'", crate::display_path(&path)),
+                err,
+            )
+        })?;
+
+        for maybe_entry in dir_iter {
+            let entry = maybe_entry.map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to read directory '{}'", crate::display_path(&path)),
+                    err,
+                )
+            })?;
+
+            let entry_path = entry.path();
+
+            let md = if options.follow_symlinks {
+                fs::metadata(&entry_path)
+            } else {
+                fs::symlink_metadata(&entry_path)
+            }
+            .map_err(|err| {
+                crate::Error::new_io(
+                    &format!(
+                        "Failed to query path '{}'",
+                        crate::display_path(&entry_path)
+                    ),
+                    err,
+                )
+            })?;
+
+            if md.is_symlink() {
+                continue;
+            }
+
+            let entry_sub_path = sub_path.join(entry.file_name());
+
+            if md.is_dir() {
+                Self::collect_symfiles(root, &entry_sub_path, options, visited, symfiles)?;
+                continue;
+            }
+
+            if options.matches_extension(&entry_sub_path)
+                && options.only_files.as_ref().is_none_or(|only_files| {
+                    only_files.contains(
+                        &crate::Compression::from_path(&entry_sub_path)
+                            .strip_extension(&entry_sub_path),
+                    )
+                })
+            {
+                symfiles.push((root.to_path_buf(), entry_sub_path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads all specified symtypes files, each given as a tuple of its root path and subpath.
+    fn load_symfiles(
+        &mut self,
+        symfiles: &[(PathBuf, PathBuf)],
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error> {
+        let next_work_idx = AtomicUsize::new(0);
+
+        self.load_parallel(
+            || {
+                let work_idx = next_work_idx.fetch_add(1, Ordering::Relaxed);
+                if work_idx >= symfiles.len() {
+                    return None;
+                }
+                let (root, sub_path) = &symfiles[work_idx];
+
+                // Open the file and transparently decompress its data if the file name indicates
+                // a compression format. Uncompressed files are memory-mapped if requested.
+                let path = root.join(sub_path);
+                let compression = crate::Compression::from_path(sub_path);
+                Some(
+                    Self::open_symfile(&path, compression, options)
+                        .map(|input| (compression.strip_extension(sub_path), (root, input)))
+                        .map_err(|err| {
+                            crate::Error::new_io(
+                                &format!("Failed to open file '{}'", crate::display_path(&path)),
+                                err,
+                            )
+                        }),
+                )
+            },
+            |path, (root, input), load_context| {
+                load_context.errors.root = Some(root.clone());
+                match input {
+                    SymfileInput::Reader(reader) => Self::load_inner(path, reader, load_context),
+                    #[cfg(feature = "mmap")]
+                    SymfileInput::Mapped(map) => Self::load_mapped(&path, map, load_context),
+                }
+            },
+            Some(symfiles.len()),
+            options,
+        )
+    }
+
+    /// Opens a symtypes file for loading.
+    #[cfg_attr(not(feature = "mmap"), allow(unused_variables))]
+    fn open_symfile(
+        path: &Path,
+        compression: crate::Compression,
+        options: &LoadOptions,
+    ) -> io::Result<SymfileInput> {
+        #[cfg(feature = "mmap")]
+        if options.mmap_reads && compression == crate::Compression::None {
+            let file = fs::File::open(path)?;
+            // SAFETY: The mapping is only read while the file is being loaded. The files are
+            // expected to not be modified during that time, as with any other way of reading them.
+            let map = unsafe { memmap2::Mmap::map(&file)? };
+            return Ok(SymfileInput::Mapped(map));
+        }
+
+        let reader = compression.reader(PathFile::open(path)?)?;
+        Ok(SymfileInput::Reader(reader))
+    }
+
+    /// Loads symtypes data from a collection of in-memory files, using the specified options.
+    ///
+    /// Each item of `files` provides a path to a `.symtypes` file name, indicating the origin of the
+    /// data, and a reader with the file content. The files are loaded in parallel by
+    /// `options.num_workers` workers, the options related to collecting files are ignored.
+    pub fn load_from_iter<I, R>(
+        &mut self,
+        files: I,
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error>
+    where
+        I: IntoIterator<Item = (PathBuf, R)>,
+        I::IntoIter: Send,
+        R: Read + Send,
+    {
+        let files = files.into_iter();
+        let total = match files.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(lower),
+            _ => None,
+        };
+        let files = Mutex::new(files);
+
+        self.load_parallel(
+            || files.lock().unwrap().next().map(Ok),
+            Self::load_inner,
+            total,
+            options,
+        )
+    }
+
+    /// Loads symtypes data from inputs provided by `next_input`, using `options.num_workers`
+    /// workers.
+    ///
+    /// The `next_input` function is called by the workers to obtain a path and a source of the next
+    /// input, or [`None`] if all inputs have been processed. The `load_input` function then parses
+    /// the data of the input into a worker-local context. The `total` number of inputs is reported
+    /// with the progress, if it is known.
+    fn load_parallel<F, L, R>(
+        &mut self,
+        next_input: F,
+        load_input: L,
+        total: Option<usize>,
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error>
+    where
+        F: Fn() -> Option<Result<(PathBuf, R), crate::Error>> + Sync,
+        L: Fn(PathBuf, R, &mut LoadContext) -> Result<(), crate::Error> + Sync,
+    {
+        let num_loaded = AtomicUsize::new(0);
+        let timings = options.timings.as_deref();
+        let merge_time = Mutex::new(Duration::ZERO);
+
+        // Merge the data of a worker into the corpus, serialized with the other workers.
+        let shared = Mutex::new((self, LoadErrors::new(options.max_errors)));
+        let merge = |load_context: LoadContext| {
+            let mut shared = shared.lock().unwrap();
+            let (syms, errors) = &mut *shared;
+            let start = timings.map(|_| Instant::now());
+            let result = syms.merge_loaded(load_context, errors);
+            if let Some(start) = start {
+                *merge_time.lock().unwrap() += start.elapsed();
+            }
+            result
+        };
+
+        // Return the first error if any is found, others are silently swallowed which is ok.
+        let parse_start = timings.map(|_| Instant::now());
+        run_workers(options.num_workers, || {
+            let mut load_context = LoadContext::new(options);
+            let mut worker_timing = WorkerTiming::default();
+            while let Some(input) = next_input() {
+                let (path, source) = input?;
+                match timings {
+                    Some(timings) => {
+                        let start = Instant::now();
+                        load_input(path.clone(), source, &mut load_context)?;
+                        let time = start.elapsed();
+                        worker_timing.num_files += 1;
+                        worker_timing.busy += time;
+                        timings.data.lock().unwrap().files.push((path, time));
+                    }
+                    None => load_input(path, source, &mut load_context)?,
+                }
+                let current = num_loaded.fetch_add(1, Ordering::Relaxed) + 1;
+                options.progress.report(ProgressPhase::Load, current, total);
+
+                // Hand over the loaded data once it grows large, so the workers do not hold
+                // a second copy of the whole corpus until all inputs are processed.
+                if load_context.types.tokens.len() >= MERGE_THRESHOLD {
+                    merge(mem::replace(&mut load_context, LoadContext::new(options)))?;
+                }
+            }
+            if let Some(timings) = timings {
+                timings.data.lock().unwrap().workers.push(worker_timing);
+            }
+            merge(load_context)
+        })
+        .into_iter()
+        .collect::<Result<Vec<_>, crate::Error>>()?;
+        if let (Some(timings), Some(start)) = (timings, parse_start) {
+            let merge_time = merge_time.into_inner().unwrap();
+            timings.add_phase(
+                |data| &mut data.parse,
+                start.elapsed().saturating_sub(merge_time),
+            );
+            timings.add_phase(|data| &mut data.merge, merge_time);
+        }
+
+        let (_, errors) = shared.into_inner().unwrap();
+        errors.into_result()
+    }
+
+    /// Merges symtypes data loaded by a worker into the corpus.
+    ///
+    /// All strings, types and files of the worker get new identifiers and indices in the corpus.
+    /// Exports are checked for duplicates in the same way as when they are loaded. Any errors
+    /// collected by the worker are added to `errors`.
+    fn merge_loaded(
+        &mut self,
+        load_context: LoadContext,
+        errors: &mut LoadErrors,
+    ) -> Result<(), crate::Error> {
+        errors.extend(load_context.errors)?;
+
+        // Intern all strings in the corpus and map their identifiers.
+        let id_map: Vec<_> = load_context
+            .strings
+            .strings
+            .iter()
+            .map(|string| Some(self.strings.intern(string)))
+            .collect();
+
+        // Merge all type variants and map their indices.
+        let mut variant_map = FxHashMap::default();
+        let mut tokens = Vec::new();
+        for (&name, variants) in &load_context.types.variants {
+            let new_name = id_map[name.index()].unwrap();
+            let mut new_variants = Vec::with_capacity(variants.len());
+            for i in 0..variants.len() {
+                let variant_tokens = load_context
+                    .types
+                    .get_tokens(name, VariantIdx::new(i))
+                    .unwrap();
+                tokens.clear();
+                tokens.extend(
+                    variant_tokens
+                        .iter()
+                        .map(|token| token.translate(&id_map).unwrap()),
+                );
+                new_variants.push(self.types.merge(new_name, &tokens));
+            }
+            variant_map.insert(name, new_variants);
+        }
+
+        // Add all files, with their records using the new identifiers and indices. Warn about any
+        // file which is already present in the corpus.
+        let file_offset = self.files.len();
+        let mut paths: FxHashSet<PathBuf> = self
+            .files
+            .iter()
+            .map(|symfile| symfile.path.clone())
+            .collect();
+        for symfile in load_context.files {
+            if !paths.insert(symfile.path.clone()) {
+                load_context.options.diagnostics.warning(
+                    WarningKind::DuplicateFile,
+                    format!("File '{}' is loaded multiple times", symfile.path.display()),
+                );
+            }
+            let records = symfile
+                .records
+                .iter()
+                .map(|(name, variant_idx)| {
+                    (
+                        id_map[name.index()].unwrap(),
+                        variant_map[name][variant_idx.index()],
+                    )
+                })
+                .collect();
+            self.files.push(SymFile {
+                path: symfile.path,
+                records,
+            });
+        }
+
+        // Add all exports. Process them in the order of their files and lines so any duplicate is
+        // reported deterministically.
+        let mut exports = load_context.exports.into_iter().collect::<Vec<_>>();
+        exports.sort_by_key(|(name, file_idx)| (file_idx.index(), load_context.export_lines[name]));
+        for (name, file_idx) in exports {
+            let new_name = id_map[name.index()].unwrap();
+            let new_file_idx = FileIdx::new(file_offset + file_idx.index());
+            match self.exports.entry(new_name) {
+                Occupied(export_entry) => {
+                    if let Err(err) = Self::handle_duplicate_export(
+                        (
+                            &self.files[new_file_idx.index()].path,
+                            load_context.export_lines[&name],
+                        ),
+                        self.strings.resolve(new_name),
+                        &self.files[export_entry.get().index()].path,
+                        load_context.options,
+                    ) {
+                        errors.report_stored(err)?;
+                    }
+                }
+                Vacant(export_entry) => {
+                    export_entry.insert(new_file_idx);
+                    if let Some(&crc) = load_context.crcs.get(&name) {
+                        self.crcs.insert(new_name, crc);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads symtypes data from a specified reader.
+    ///
+    /// The `path` should point to a `.symtypes` file name, indicating the origin of the data.
+    pub fn load_buffer<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        reader: R,
+    ) -> Result<(), crate::Error> {
+        self.load_buffer_with(path, reader, &LoadOptions::new())
+    }
+
+    /// Loads symtypes data from a specified reader, using the specified options.
+    ///
+    /// The `path` should point to a `.symtypes` file name, indicating the origin of the data. The
+    /// number of workers and the options related to collecting files are ignored.
+    pub fn load_buffer_with<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        reader: R,
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error> {
+        let mut load_context = LoadContext::new(options);
+        Self::load_inner(path, reader, &mut load_context)?;
+        options.progress.report(ProgressPhase::Load, 1, Some(1));
+        let mut errors = LoadErrors::new(options.max_errors);
+        self.merge_loaded(load_context, &mut errors)?;
+        errors.into_result()
+    }
+
+    /// Loads symtypes data from an in-memory file.
+    ///
+    /// The `path` should point to a `.symtypes` file name, indicating the origin of the data. Its
+    /// extension determines the compression format of `data`, which is decompressed transparently.
+    /// The function does not access the filesystem and is suitable for targets without it, such as
+    /// `wasm32-unknown-unknown`.
+    pub fn load_bytes<P: AsRef<Path>>(&mut self, path: P, data: &[u8]) -> Result<(), crate::Error> {
+        self.load_bytes_with(path, data, &LoadOptions::new())
+    }
+
+    /// Loads symtypes data from an in-memory file, using the specified options.
+    ///
+    /// See [`SymCorpus::load_bytes()`] for details. The number of workers and the options related
+    /// to collecting files are ignored.
+    pub fn load_bytes_with<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        data: &[u8],
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+        let compression = crate::Compression::from_path(path);
+        let reader = compression.reader(data).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to read file '{}'", crate::display_path(path)),
+                err,
+            )
+        })?;
+        self.load_buffer_with(compression.strip_extension(path), reader, options)
+    }
+
+    /// Loads symbol namespaces, export types and modules from a `Module.symvers` file.
+    ///
+    /// Each line of the file has the form `<crc>\t<symbol>\t<module>\t<export-type>` and is
+    /// optionally followed by `\t<namespace>`. The namespace, export type and module of each
+    /// symbol which is exported by the corpus are recorded and can be queried by
+    /// [`ExportView::namespace()`], [`ExportView::export_type()`] and [`ExportView::module()`].
+    /// Symbols which are not exported by the corpus are
+    /// ignored. The data is not preserved in the consolidated output.
+    pub fn load_symvers<P: AsRef<Path>>(&mut self, path: P) -> Result<(), crate::Error> {
+        let mut symvers = crate::symvers::SymversCorpus::new();
+        symvers.load(path)?;
+        self.add_symvers(&symvers);
+        Ok(())
+    }
+
+    /// Loads symbol namespaces, export types and modules from a specified reader with `Module.symvers`
+    /// data.
+    ///
+    /// The `path` identifies the origin of the data and is used in error messages. See
+    /// [`SymCorpus::load_symvers()`] for the details of the format.
+    pub fn load_symvers_buffer<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        reader: R,
+    ) -> Result<(), crate::Error> {
+        let mut symvers = crate::symvers::SymversCorpus::new();
+        symvers.load_buffer(path, reader)?;
+        self.add_symvers(&symvers);
+        Ok(())
+    }
+
+    /// Records the namespaces, export types and modules of the exports of the corpus from
+    /// already loaded `symvers` data. See [`SymCorpus::load_symvers()`] for details.
+    pub fn add_symvers(&mut self, symvers: &crate::symvers::SymversCorpus) {
+        for export in symvers.exports() {
+            let Some(name) = self.strings.get(export.name()) else {
+                continue;
+            };
+            if !self.exports.contains_key(&name) {
+                continue;
+            }
+            let export_type = self.strings.intern(export.export_type());
+            self.export_types.insert(name, export_type);
+            let module = self.strings.intern(export.module());
+            self.modules.insert(name, module);
+            if let Some(namespace) = export.namespace() {
+                let namespace = self.strings.intern(namespace);
+                self.namespaces.insert(name, namespace);
+            }
+        }
+    }
+
+    /// Loads symtypes data from a specified reader.
+    ///
+    /// The data is parsed in a streaming fashion, one line at a time. Type and export records are
+    /// merged into the context as soon as they are read, and only their names and assigned variant
+    /// indices are kept until the end of the input. This is needed because whether the input is
+    /// a single or consolidated symtypes file can be determined only after reading all its `F#`
+    /// records, which appear at the end of a consolidated file.
+    fn load_inner<P: AsRef<Path>, R: Read>(
+        path: P,
+        reader: R,
+        load_context: &mut LoadContext,
+    ) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+        debug!("Loading '{}'", path.display());
+
+        if load_context.options.is_selective() {
+            let lazy = LazySymCorpus::from_reader(path, reader)?;
+            let options = load_context.options;
+            let data = lazy.select_data(|name, file| options.is_selected(name, file));
+            return Self::load_str(path, &data, load_context);
+        }
+
+        let mut load_state = LoadState::default();
+
+        // Parse all declarations.
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        for line_idx in 0.. {
+            // Read the next line.
+            line.clear();
+            let read = reader
+                .read_line(&mut line)
+                .map_err(|err| crate::Error::new_io("Failed to read symtypes data", err))?;
+            if read == 0 {
+                break;
+            }
+            Self::load_line(path, line_idx, &line, &mut load_state, load_context)?;
+        }
+
+        Self::add_loaded_records(path, load_state, load_context)
+    }
+
+    /// Loads symtypes data from a memory-mapped file.
+    ///
+    /// The data is parsed in the same way as by [`SymCorpus::load_inner()`], but the lines are
+    /// processed directly in the mapping, without copying them. A selective load indexes the
+    /// mapping in place as well.
+    #[cfg(feature = "mmap")]
+    fn load_mapped(
+        path: &Path,
+        map: memmap2::Mmap,
+        load_context: &mut LoadContext,
+    ) -> Result<(), crate::Error> {
+        debug!("Loading '{}'", path.display());
+
+        let data = LazyData::mapped(map)?;
+
+        if load_context.options.is_selective() {
+            let lazy = LazySymCorpus::from_data(path, data);
+            let options = load_context.options;
+            let data = lazy.select_data(|name, file| options.is_selected(name, file));
+            return Self::load_str(path, &data, load_context);
+        }
+
+        Self::load_str(path, &data, load_context)
+    }
+
+    /// Loads symtypes data from a string.
+    ///
+    /// The data is parsed in the same way as by [`SymCorpus::load_inner()`], but the lines are
+    /// processed directly in the string, without copying them.
+    fn load_str(
+        path: &Path,
+        data: &str,
+        load_context: &mut LoadContext,
+    ) -> Result<(), crate::Error> {
+        // Skip an input which has no selected records.
+        if data.is_empty() && load_context.options.is_selective() {
+            return Ok(());
+        }
+
+        let mut load_state = LoadState::default();
+        for (line_idx, line) in data.split_inclusive('\n').enumerate() {
+            Self::load_line(path, line_idx, line, &mut load_state, load_context)?;
+        }
+
+        Self::add_loaded_records(path, load_state, load_context)
+    }
+
+    /// Parses a single line of symtypes data, including its line terminator if present.
+    fn load_line(
+        path: &Path,
+        line_idx: usize,
+        line: &str,
+        load_state: &mut LoadState,
+        load_context: &mut LoadContext,
+    ) -> Result<(), crate::Error> {
+        let options = load_context.options;
+
+        let line = match line.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None => line,
+        };
+
+        // Join any type prefixes separated from their names in the legacy dialect.
+        let joined_line;
+        let line = if options.input_dialect != InputDialect::Current && has_split_prefix(line) {
+            if options.input_dialect == InputDialect::Auto && !load_state.legacy_dialect {
+                debug!(
+                    "{}:{}: Reading the legacy symtypes dialect",
+                    load_context.display_input_path(path),
+                    line_idx + 1
+                );
+                load_state.legacy_dialect = true;
+            }
+            joined_line = join_split_prefixes(line);
+            joined_line.as_str()
+        } else {
+            line
+        };
+
+        // Obtain a name of the record.
+        let mut words = Words::new(line);
+        let name = match words.next() {
+            Some(name) => name,
+            None => {
+                if options.lenient {
+                    options.diagnostics.warning(
+                        WarningKind::SkippedRecord,
+                        format!(
+                            "{}:{}: Skipping an empty record",
+                            load_context.display_input_path(path),
+                            line_idx + 1
+                        ),
+                    );
+                    return Ok(());
+                }
+                return load_context.errors.report(
+                    ParseError::new(
+                        ParseErrorKind::MissingName,
+                        path,
+                        line_idx,
+                        "Expected a record name".to_string(),
+                    )
+                    .into(),
+                );
+            }
+        };
+
+        // Check if the record is a duplicate of another one.
+        match load_state.all_names.get(name) {
+            Some(_) => {
+                if options.lenient {
+                    options.diagnostics.warning(
+                        WarningKind::SkippedRecord,
+                        format!(
+                            "{}:{}: Skipping a duplicate record '{}'",
+                            load_context.display_input_path(path),
+                            line_idx + 1,
+                            name
+                        ),
+                    );
+                    return Ok(());
+                }
+                return load_context.errors.report(
+                    ParseError::new(
+                        ParseErrorKind::DuplicateRecord,
+                        path,
+                        line_idx,
+                        format!("Duplicate record '{}'", name),
+                    )
+                    .with_source(line, word_offset(line, name))
+                    .into(),
+                );
+            }
+            None => load_state.all_names.insert(name.to_string()), // [1]
+        };
+
+        // Check for a format version header, which can appear only on the first line.
+        if line_idx == 0 {
+            if let Some(version) = name.strip_prefix("V#") {
+                match version.parse() {
+                    Ok(version @ 1..=CRC_FORMAT_VERSION) => load_state.version = version,
+                    _ => {
+                        return load_context.errors.report(
+                            ParseError::new(
+                                ParseErrorKind::UnsupportedVersion,
+                                path,
+                                line_idx,
+                                format!("Unsupported format version '{}'", version),
+                            )
+                            .with_source(line, 2)
+                            .into(),
+                        );
+                    }
+                }
+                return Ok(());
+            }
+        }
+
+        // Check for a file declaration and remember it. File declarations are processed later after
+        // remapping of all symbol variants is known.
+        if name.starts_with("F#") {
+            load_state.file_lines.push((line_idx, line.to_string()));
+            return Ok(());
+        }
+
+        // Handle a type/export record.
+
+        // Parse the base name and any variant name/index, which is appended as a suffix after the
+        // `@` character.
+        let (base_name, orig_variant_name) = split_type_name(name);
+
+        // Intern the base name and turn the remaining words into tokens.
+        let name_id = load_context.strings.intern(base_name);
+        let tokens = &mut load_state.tokens;
+        words_into_tokens(&mut words, &mut load_context.strings, tokens);
+
+        // Insert the type into the corpus.
+        let variant_idx = load_context.types.merge(name_id, tokens);
+        load_state.type_records.push((
+            name_id,
+            orig_variant_name.to_string(),
+            variant_idx,
+            line_idx,
+        ));
+        Ok(())
+    }
+
+    /// Adds all records parsed from one input to the context, once the whole input is read.
+    fn add_loaded_records(
+        path: &Path,
+        load_state: LoadState,
+        load_context: &mut LoadContext,
+    ) -> Result<(), crate::Error> {
+        if load_state.file_lines.is_empty() {
+            Self::add_single_file(path, load_state.type_records, load_context)
+        } else {
+            Self::add_consolidated_files(
+                path,
+                load_state.version,
+                load_state.type_records,
+                load_state.file_lines,
+                load_context,
+            )
+        }
+    }
+
+    /// Adds records read from a single symtypes file to the corpus.
+    fn add_single_file(
+        path: &Path,
+        type_records: Vec<(StrId, String, VariantIdx, usize)>,
+        load_context: &mut LoadContext,
+    ) -> Result<(), crate::Error> {
+        // Check that no record specifies a variant, which is allowed only in a consolidated file.
+        for (base_name, orig_variant_name, variant_idx, line_idx) in &type_records {
+            if !orig_variant_name.is_empty() {
+                let name = format!(
+                    "{}@{}",
+                    load_context.strings.resolve(*base_name),
+                    orig_variant_name
+                );
+                let (record_line, _) = format_record_line(
+                    &name,
+                    load_context
+                        .types
+                        .get_tokens(*base_name, *variant_idx)
+                        .unwrap(),
+                    &load_context.strings,
+                );
+                load_context.errors.report(
+                    ParseError::new(
+                        ParseErrorKind::UnexpectedVariant,
+                        path,
+                        *line_idx,
+                        format!(
+                            "Type '{}' specifies a variant but the file is not consolidated",
+                            name
+                        ),
+                    )
+                    .with_source(&record_line, 0)
+                    .into(),
+                )?;
+            }
+        }
+
+        // Record the file to determine its file_idx.
+        let file_idx = {
+            let symfile = SymFile {
+                path: load_context.options.stored_path(path),
+                records: FileRecords::default(),
+            };
+
+            load_context.files.push(symfile);
+            FileIdx::new(load_context.files.len() - 1)
+        };
+
+        // Insert the records.
+        let mut records = FileRecords::default();
+        for (base_name, _, variant_idx, line_idx) in &type_records {
+            records.insert(*base_name, *variant_idx);
+            Self::try_insert_export(*base_name, file_idx, *line_idx, load_context)?;
+        }
+
+        // Validate that all references can be resolved in the file.
+        if load_context.options.validate_refs {
+            for (base_name, _, variant_idx, line_idx) in &type_records {
+                Self::validate_record_refs(
+                    path,
+                    *line_idx,
+                    load_context.strings.resolve(*base_name),
+                    load_context
+                        .types
+                        .get_tokens(*base_name, *variant_idx)
+                        .unwrap(),
+                    &records,
+                    &load_context.strings,
+                    &mut load_context.errors,
+                )?;
+            }
+        }
+
+        // Update the file records.
+        load_context.files[file_idx.index()].records = records;
+        Ok(())
+    }
+
+    /// Adds records read from a consolidated symtypes file to the corpus, creating a file for each
+    /// of its `F#` records.
+    ///
+    /// Starting with format `version` 2, exports in `F#` records can be followed by their CRCs.
+    fn add_consolidated_files(
+        path: &Path,
+        version: u32,
+        type_records: Vec<(StrId, String, VariantIdx, usize)>,
+        file_lines: Vec<(usize, String)>,
+        load_context: &mut LoadContext,
+    ) -> Result<(), crate::Error> {
+        let options = load_context.options;
+
+        // Map each variant name/index that the type has in this specific .symtypes file to one
+        // which it got assigned in the entire loaded corpus.
+        let mut remap: FxHashMap<StrId, FxHashMap<String, VariantIdx>> = FxHashMap::default();
+        for (base_name, orig_variant_name, variant_idx, _) in type_records {
+            remap
+                .entry(base_name)
+                .or_default()
+                .insert(orig_variant_name, variant_idx);
+        }
+
+        // Handle file declarations.
+        for (line_idx, line) in file_lines {
+            let mut words = Words::new(&line);
+
+            let record_name = words.next().unwrap();
+            assert!(record_name.starts_with("F#"));
+            let file_name = &record_name[2..];
+
+            let file_idx = {
+                let symfile = SymFile {
+                    path: load_context.options.stored_path(Path::new(file_name)),
+                    records: FileRecords::default(),
+                };
+                load_context.files.push(symfile);
+                FileIdx::new(load_context.files.len() - 1)
+            };
+
+            let mut records = FileRecords::default();
+            for type_name in words {
+                // Split off any CRC of an export.
+                let (type_name, crc) = match type_name.split_once('=') {
+                    Some((type_name, crc)) if version >= CRC_FORMAT_VERSION => {
+                        let crc = match crate::parse_crc(crc).filter(|_| is_export_name(type_name))
+                        {
+                            Some(crc) => crc,
+                            None => {
+                                load_context.errors.report(
+                                    ParseError::new(
+                                        ParseErrorKind::InvalidCrc,
+                                        path,
+                                        line_idx,
+                                        format!("Invalid CRC of '{}'", type_name),
+                                    )
+                                    .with_source(&line, word_offset(&line, type_name))
+                                    .into(),
+                                )?;
+                                continue;
+                            }
+                        };
+                        (type_name, Some(crc))
+                    }
+                    _ => (type_name, None),
+                };
+
+                // Parse the base name and variant name/index.
+                let (base_name, orig_variant_name) = split_type_name(type_name);
+
+                // Look up how the variant got remapped.
+                let base_name = load_context.strings.get(base_name);
+                let variant_idx = match base_name
+                    .and_then(|base_name| remap.get(&base_name))
+                    .and_then(|hash| hash.get(orig_variant_name))
+                {
+                    Some(&variant_idx) => variant_idx,
+                    None => {
+                        if options.lenient {
+                            options.diagnostics.warning(
+                                WarningKind::SkippedRecord,
+                                format!(
+                                    "{}:{}: Skipping an unknown type '{}'",
+                                    load_context.display_input_path(path),
+                                    line_idx + 1,
+                                    type_name
+                                ),
+                            );
+                            continue;
+                        }
+                        load_context.errors.report(
+                            ParseError::new(
+                                ParseErrorKind::UndefinedType,
+                                path,
+                                line_idx,
+                                format!("Type '{}' is not known", type_name),
+                            )
+                            .with_source(&line, word_offset(&line, type_name))
+                            .into(),
+                        )?;
+                        continue;
+                    }
+                };
+
+                // Insert the record.
+                let base_name = base_name.unwrap();
+                records.insert(base_name, variant_idx);
+                Self::try_insert_export(base_name, file_idx, line_idx, load_context)?;
+
+                // Remember the CRC if the export got provided by this file.
+                if let Some(crc) = crc {
+                    if load_context.exports.get(&base_name) == Some(&file_idx) {
+                        load_context.crcs.insert(base_name, crc);
+                    }
+                }
+            }
+
+            // Add implicit references, ones that were omitted by the F# declaration because only
+            // one variant exists in the entire consolidated file.
+            let walk_records: Vec<_> = records.iter().map(|(&k, &v)| (k, v)).collect();
+            for (name, variant_idx) in walk_records {
+                if let Err(err) = Self::extrapolate_file_record(
+                    (path, line_idx, file_name),
+                    name,
+                    variant_idx,
+                    true,
+                    &remap,
+                    (&load_context.types, &load_context.strings, options),
+                    &mut records,
+                ) {
+                    load_context.errors.report(err)?;
+                }
+            }
+
+            load_context.files[file_idx.index()].records = records;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that all types referenced by the given `tokens` are present in `records`, reporting
+    /// each missing type in `errors`.
+    fn validate_record_refs(
+        path: &Path,
+        line_idx: usize,
+        name: &str,
+        tokens: &Tokens,
+        records: &FileRecords,
+        strings: &Interner,
+        errors: &mut LoadErrors,
+    ) -> Result<(), crate::Error> {
+        for (i, token) in tokens.iter().enumerate() {
+            if let Some(ref_name) = token.typeref() {
+                if !records.contains_key(&ref_name) {
+                    let (record_line, offsets) = format_record_line(name, tokens, strings);
+                    errors.report(
+                        ParseError::new(
+                            ParseErrorKind::UndefinedType,
+                            path,
+                            line_idx,
+                            format!("Type '{}' is not defined", strings.resolve(ref_name)),
+                        )
+                        .with_source(&record_line, offsets[i])
+                        .into(),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if a specified `type_name` is an export and, if so, registers it with its `file_idx`
+    /// in the `load_context.exports`.
+    fn try_insert_export(
+        type_name: StrId,
+        file_idx: FileIdx,
+        line_idx: usize,
+        load_context: &mut LoadContext,
+    ) -> Result<(), crate::Error> {
+        if !is_export_name(load_context.strings.resolve(type_name)) {
+            return Ok(());
+        }
+
+        // Try to add the export, handle it according to the policy if it is a duplicate.
+        match load_context.exports.entry(type_name) {
+            Occupied(export_entry) => {
+                if let Err(err) = Self::handle_duplicate_export(
+                    (&load_context.files[file_idx.index()].path, line_idx),
+                    load_context.strings.resolve(type_name),
+                    &load_context.files[export_entry.get().index()].path,
+                    load_context.options,
+                ) {
+                    load_context.errors.report_stored(err)?;
+                }
+                Ok(())
+            }
+            Vacant(export_entry) => {
+                export_entry.insert(file_idx);
+                load_context.export_lines.insert(type_name, line_idx);
+                Ok(())
+            }
+        }
+    }
+
+    /// Handles a duplicate export `type_name` found at (`path`, `line_idx`), with its previous
+    /// occurrence in `other_path`, according to the policy in `options`.
+    fn handle_duplicate_export(
+        (path, line_idx): (&Path, usize),
+        type_name: &str,
+        other_path: &Path,
+        options: &LoadOptions,
+    ) -> Result<(), crate::Error> {
+        match options.duplicate_exports {
+            DuplicateExports::Error => Err(ParseError::new(
+                ParseErrorKind::DuplicateExport,
+                path,
+                line_idx,
+                format!(
+                    "Export '{}' is duplicate. Previous occurrence found in '{}'.",
+                    type_name,
+                    other_path.display()
+                ),
+            )
+            .into()),
+            DuplicateExports::KeepFirst => {
+                options.diagnostics.warning(
+                    WarningKind::DuplicateExport,
+                    format!(
+                        "{}:{}: Ignoring duplicate export '{}', previous occurrence found in '{}'",
+                        path.display(),
+                        line_idx + 1,
+                        type_name,
+                        other_path.display()
+                    ),
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Processes a single symbol in some file originated from an `F#` record and enhances the
+    /// specified file records with the needed implicit types.
+    ///
+    /// This function is used when reading a consolidated input file and processing its `F#`
+    /// records. Each `F#` record is in form `F#<filename> <type@variant>... <export>...`. It lists
+    /// all types and exports in a given file but is allowed to omit any referenced types which have
+    /// only one variant in the whole consolidated file. The purpose of this function is to find all
+    /// such implicit references and add them to `records`. The `remap` parameter provides the
+    /// variants known in the consolidated file and their indices in the corpus.
+    ///
+    /// A caller of this function should pre-fill `records` with all explicit references given on
+    /// the processed `F#` record and then call this function on each of the references. These root
+    /// calls should be invoked with `is_explicit` set to `true`. The function then recursively adds
+    /// all needed implicit types which are referenced from these roots.
+    fn extrapolate_file_record(
+        (corpus_path, line_idx, file_name): (&Path, usize, &str),
+        name: StrId,
+        variant_idx: VariantIdx,
+        is_explicit: bool,
+        remap: &FxHashMap<StrId, FxHashMap<String, VariantIdx>>,
+        (types, strings, options): (&Types, &Interner, &LoadOptions),
+        records: &mut FileRecords,
+    ) -> Result<(), crate::Error> {
+        let variant_idx = if is_explicit {
+            // All explicit symbols need to be added by the caller.
+            assert!(records.get(&name).is_some());
+            variant_idx
+        } else {
+            // See if the symbol was already processed.
+            if records.get(&name).is_some() {
+                return Ok(());
+            }
+
+            // A symbol can be implicit only if it has one variant in the consolidated file.
+            let variant_idx = match remap.get(&name) {
+                Some(variants) => {
+                    if variants.len() > 1 {
+                        return Err(ParseError::new(
+                            ParseErrorKind::AmbiguousType,
+                            corpus_path,
+                            line_idx,
+                            format!(
+                                "Type '{}' is implicitly referenced by file '{}' but has multiple variants in the corpus",
+                                strings.resolve(name),
+                                file_name,
+                            ),
+                        )
+                        .into());
+                    }
+                    *variants.values().next().unwrap()
+                }
+                None => {
+                    if !options.validate_refs {
+                        options.diagnostics.warning(
+                            WarningKind::UndefinedType,
+                            format!(
+                                "{}:{}: Skipping an undefined type '{}' referenced by file '{}'",
+                                corpus_path.display(),
+                                line_idx + 1,
+                                strings.resolve(name),
+                                file_name,
+                            ),
+                        );
+                        return Ok(());
+                    }
+                    return Err(ParseError::new(
+                        ParseErrorKind::UndefinedType,
+                        corpus_path,
+                        line_idx,
+                        format!(
+                            "Type '{}' is implicitly referenced by file '{}' but is not defined",
+                            strings.resolve(name),
+                            file_name,
+                        ),
+                    )
+                    .into());
+                }
+            };
+            records.insert(name, variant_idx);
+            variant_idx
+        };
+
+        // Obtain tokens for the selected variant.
+        let tokens = types.get_tokens(name, variant_idx).unwrap();
+
+        // Process recursively all types referenced by this symbol.
+        for token in tokens {
+            if let Some(ref_name) = token.typeref() {
+                // Process the type. Note that passing variant_idx=0 is ok here:
+                // * If the type is explicitly specified in the parent F# record then it must be
+                //   already added in the records and the called function immediately returns.
+                // * If the type is implicit then the invoked function looks up its only variant
+                //   and checks that no more than one variant is actually present.
+                Self::extrapolate_file_record(
+                    (corpus_path, line_idx, file_name),
+                    ref_name,
+                    VariantIdx(0),
+                    false,
+                    remap,
+                    (types, strings, options),
+                    records,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Processes a single symbol specified in a given file and adds it to the consolidated output.
+    ///
+    /// The specified symbol is added to `output_types` and `processed_types`, if not already
+    /// present, and all its type references get recursively processed in the same way. Any skipped
+    /// unknown type is reported to `diagnostics`.
+    fn consolidate_type(
+        &self,
+        symfile: &SymFile,
+        name: StrId,
+        output_types: &mut ConsolidateOutputTypes,
+        processed_types: &mut ConsolidateFileTypes,
+        diagnostics: &DiagnosticsSink,
+    ) {
+        // See if the symbol was already processed.
+        let processed_entry = match processed_types.entry(name) {
+            Occupied(_) => return,
+            Vacant(processed_entry) => processed_entry,
+        };
+
+        // Look up the internal variant index. A type can be unknown only if the references were
+        // not validated when loading the corpus, skip it in such a case.
+        let variant_idx = match symfile.records.get(&name) {
+            Some(&variant_idx) => variant_idx,
+            None => {
+                diagnostics.warning(
+                    WarningKind::UndefinedType,
+                    format!(
+                        "Skipping an unknown type '{}' in file '{}'",
+                        self.strings.resolve(name),
+                        symfile.path.display()
+                    ),
+                );
+                return;
+            }
+        };
+
+        // Determine the output variant index for the symbol.
+        let remap_idx;
+        match output_types.entry(name) {
+            Occupied(mut active_entry) => {
+                let remap = active_entry.get_mut();
+                let remap_len = remap.len();
+                match remap.entry(variant_idx) {
+                    Occupied(remap_entry) => {
+                        remap_idx = *remap_entry.get();
+                    }
+                    Vacant(remap_entry) => {
+                        remap_idx = remap_len;
+                        remap_entry.insert(remap_idx);
+                    }
+                }
+            }
+            Vacant(active_entry) => {
+                remap_idx = 0;
+                active_entry.insert(FxHashMap::from_iter([(variant_idx, remap_idx)]));
+            }
+        };
+        processed_entry.insert(remap_idx);
+
+        // Process recursively all types that the symbol references.
+        let tokens = match self.types.get_tokens(name, variant_idx) {
+            Some(tokens) => tokens,
+            None => panic!(
+                "Type '{}' has a missing declaration",
+                self.strings.resolve(name)
+            ),
+        };
+
+        for token in tokens {
+            if let Some(ref_name) = token.typeref() {
+                self.consolidate_type(
+                    symfile,
+                    ref_name,
+                    output_types,
+                    processed_types,
+                    diagnostics,
+                )
+            }
+        }
+    }
+
+    /// Writes the corpus in the consolidated form into a specified file.
+    pub fn write_consolidated<P: AsRef<Path>>(&self, path: P) -> Result<(), crate::Error> {
+        self.write_consolidated_file_with(path, &ConsolidateOptions::new())
+    }
+
+    /// Writes the corpus in the consolidated form into a specified file, using the specified
+    /// options. The path `-` denotes the standard output.
+    pub fn write_consolidated_file_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &ConsolidateOptions,
+    ) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+
+        // Open the output file.
+        let writer: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stdout())
+        } else {
+            match PathFile::create(path) {
+                Ok(file) => Box::new(file),
+                Err(err) => {
+                    return Err(crate::Error::new_io(
+                        &format!("Failed to create file '{}'", crate::display_path(path)),
+                        err,
+                    ))
+                }
+            }
+        };
+
+        self.write_consolidated_with(options, writer)
+    }
+
+    /// Writes the corpus in the consolidated form to the provided output stream.
+    pub fn write_consolidated_buffer<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        self.write_consolidated_with(&ConsolidateOptions::new(), writer)
+    }
+
+    /// Writes the corpus in the consolidated form to the provided output stream, using the
+    /// specified options.
+    pub fn write_consolidated_with<W: Write>(
+        &self,
+        options: &ConsolidateOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        options.compression.write_with(writer, |writer| {
+            self.write_consolidated_inner(options, writer)
+        })
+    }
+
+    /// Writes the corpus in the consolidated form to the provided output stream, without applying
+    /// any compression.
+    fn write_consolidated_inner<W: Write>(
+        &self,
+        options: &ConsolidateOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+
+        // Initialize output data. Variable output_types records all output symbols, file_types
+        // provides per-file information.
+        let mut output_types = ConsolidateOutputTypes::default();
+        let mut file_types = vec![ConsolidateFileTypes::default(); self.files.len()];
+
+        // Sort all files in the corpus by their output path.
+        let mut file_indices = (0..self.files.len()).collect::<Vec<_>>();
+        file_indices.sort_by_key(|&i| options.output_path(&self.files[i].path));
+
+        // Process the sorted files and add their needed types to the output.
+        for (num_processed, &i) in file_indices.iter().enumerate() {
+            let symfile = &self.files[i];
+
+            // Collect sorted exports in the file which are the roots for consolidation.
+            let mut exports = Vec::new();
+            for &name in symfile.records.keys() {
+                if is_export_name(self.strings.resolve(name)) {
+                    exports.push(name);
+                }
+            }
+            exports.sort_by_key(|&name| self.strings.resolve(name));
+
+            // Add the exported types and their needed types to the output.
+            let mut processed_types = ConsolidateFileTypes::default();
+            for &name in &exports {
+                self.consolidate_type(
+                    symfile,
+                    name,
+                    &mut output_types,
+                    &mut processed_types,
+                    &options.diagnostics,
+                );
+            }
+            file_types[i] = processed_types;
+            options.progress.report(
+                ProgressPhase::Consolidate,
+                num_processed + 1,
+                Some(file_indices.len()),
+            );
+        }
+
+        // Go through all files and their output types. Check if a given type has only one variant
+        // in the output and mark it as such.
+        for file_types_item in &mut file_types {
+            for (name, remap_idx) in file_types_item {
+                let remap = output_types.get(name).unwrap();
+                if remap.len() == 1 {
+                    *remap_idx = usize::MAX;
+                }
+            }
+        }
+
+        // Sort all output types and write them to the specified file.
+        let mut sorted_records = output_types.into_iter().collect::<Vec<_>>();
+        sorted_records.sort_by_key(|(name, _remap)| options.sort_key(self.strings.resolve(*name)));
+
+        let err_desc = "Failed to write a consolidated record";
+
+        if options.crcs {
+            writeln!(writer, "V#{}", CRC_FORMAT_VERSION).map_io_err(err_desc)?;
+        }
+
+        for (name, remap) in sorted_records {
+            let name_str = self.strings.resolve(name);
+            let mut sorted_remap = remap
+                .iter()
+                .map(|(&variant_idx, &remap_idx)| (remap_idx, variant_idx))
+                .collect::<Vec<_>>();
+            sorted_remap.sort();
+
+            let needs_suffix = sorted_remap.len() > 1;
+            for (remap_idx, variant_idx) in sorted_remap {
+                let tokens = self.types.get_tokens(name, variant_idx).unwrap();
+
+                if needs_suffix {
+                    write!(writer, "{}@{}", name_str, remap_idx).map_io_err(err_desc)?;
+                } else {
+                    write!(writer, "{}", name_str).map_io_err(err_desc)?;
+                }
+                for token in tokens {
+                    write!(writer, " {}", self.strings.resolve(token.id())).map_io_err(err_desc)?;
+                }
+                writeln!(writer).map_io_err(err_desc)?;
+            }
+        }
+
+        // Write file records.
+        for &i in &file_indices {
+            let symfile = &self.files[i];
+
+            let mut sorted_types = file_types[i]
+                .iter()
+                .map(|(&name, &remap_idx)| {
+                    let name = self.strings.resolve(name);
+                    (options.sort_key(name), name, remap_idx)
+                })
+                .collect::<Vec<_>>();
+            sorted_types.sort();
+
+            // Output the F# record in form `F#<filename> <type@variant>... <export>...`. Types with
+            // only one variant in the entire consolidated file can be skipped because they can be
+            // implicitly determined by a reader. Exports are optionally followed by their CRCs.
+            write!(writer, "F#{}", options.output_path(&symfile.path).display())
+                .map_io_err(err_desc)?;
+            for &(_, name, remap_idx) in &sorted_types {
+                if remap_idx != usize::MAX {
+                    write!(writer, " {}@{}", name, remap_idx).map_io_err(err_desc)?;
+                } else if is_export_name(name) || !options.elide_implicit {
+                    write!(writer, " {}", name).map_io_err(err_desc)?;
+                } else {
+                    continue;
+                }
+                if options.crcs && is_export_name(name) {
+                    write!(writer, "={:#010x}", self.file_export_crc(symfile, name))
+                        .map_io_err(err_desc)?;
+                }
+            }
+            writeln!(writer).map_io_err(err_desc)?;
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes a genksyms reference file for each file of the corpus into the directory `dir`.
+    ///
+    /// The reference file of `<path>.symtypes` is written to `<dir>/<path>.symref` and lists all
+    /// types and exports of the file, types first, in the symtypes format. The records of the
+    /// symbols listed in `overrides` are prefixed by the `override` keyword, which instructs
+    /// genksyms to use the reference definition in place of the one found in the sources. Missing
+    /// directories are created.
+    pub fn write_symref_files<P: AsRef<Path>, I: IntoIterator<Item = S>, S: AsRef<str>>(
+        &self,
+        dir: P,
+        overrides: I,
+    ) -> Result<(), crate::Error> {
+        let dir = dir.as_ref();
+        let overrides: FxHashSet<String> = overrides
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .collect();
+        let err_desc = "Failed to write a reference record";
+
+        for symfile in &self.files {
+            let path = dir.join(symfile.path.with_extension("symref"));
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|err| {
+                    crate::Error::new_io(
+                        &format!(
+                            "Failed to create directory '{}'",
+                            crate::display_path(parent)
+                        ),
+                        err,
+                    )
+                })?;
+            }
+            let mut writer = PathFile::create(&path).map(BufWriter::new).map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to create file '{}'", crate::display_path(&path)),
+                    err,
+                )
+            })?;
+
+            let mut names = symfile
+                .records
+                .keys()
+                .map(|&name| (self.strings.resolve(name), name))
+                .collect::<Vec<_>>();
+            names.sort_by_key(|&(name_str, _)| (is_export_name(name_str), name_str));
+
+            for (name_str, name) in names {
+                if overrides.contains(name_str) {
+                    write!(writer, "override ").map_io_err(err_desc)?;
+                }
+                write!(writer, "{}", name_str).map_io_err(err_desc)?;
+                for token in Self::get_type_tokens(self, symfile, name) {
+                    write!(writer, " {}", self.strings.resolve(token.id())).map_io_err(err_desc)?;
+                }
+                writeln!(writer).map_io_err(err_desc)?;
+            }
+            writer.flush().map_io_err(err_desc)?;
+        }
+        Ok(())
+    }
+
+    /// Obtains tokens which describe a specified type name, in a given corpus and file.
+    ///
+    /// An unknown type, which can be present only if the references were not validated when
+    /// loading the corpus, is described by no tokens.
+    fn get_type_tokens<'a>(symtypes: &'a SymCorpus, file: &SymFile, name: StrId) -> &'a Tokens {
+        match file.records.get(&name) {
+            Some(&variant_idx) => match symtypes.types.get_tokens(name, variant_idx) {
+                Some(tokens) => tokens,
+                None => {
+                    panic!(
+                        "Type '{}' has a missing declaration",
+                        symtypes.strings.resolve(name)
+                    );
+                }
+            },
+            None => &[],
+        }
+    }
+
+    /// Compares the definition of the symbol `name` in (`corpus`, `file`) with its definition in
+    /// (`other_corpus`, `other_file`).
+    ///
+    /// If the immediate definition of the symbol differs between the two corpuses then it gets
+    /// added in `changes`. The `export` parameter identifies the top-level exported symbol affected
+    /// by the change. All names are identifiers in `corpus`, the `id_map` parameter provides their
+    /// counterparts in `other_corpus`.
+    ///
+    /// The specified symbol is added to `processed_types`, if not already present, and all its type
+    /// references get recursively processed in the same way.
+    fn compare_types<'a>(
+        (corpus, file): (&'a SymCorpus, &'a SymFile),
+        (other_corpus, other_file): (&'a SymCorpus, &'a SymFile),
+        id_map: &[Option<StrId>],
+        name: StrId,
+        export: StrId,
+        changes: &Mutex<CompareChangedTypes<'a>>,
+        processed: &mut CompareFileTypes,
+    ) {
+        // See if the symbol was already processed.
+        if processed.get(&name).is_some() {
+            return;
+        }
+        processed.insert(name); // [2]
+
+        // Look up how the symbol is defined in each corpus.
+        let other_name = match id_map[name.index()] {
+            Some(other_name) => other_name,
+            None => panic!(
+                "Type '{}' is not known in file '{}'",
+                corpus.strings.resolve(name),
+                other_file.path.display()
+            ),
+        };
+        let tokens = Self::get_type_tokens(corpus, file, name);
+        let other_tokens = Self::get_type_tokens(other_corpus, other_file, other_name);
+
+        // Compare the immediate tokens.
+        let is_equal = tokens.len() == other_tokens.len()
+            && zip(tokens.iter(), other_tokens.iter())
+                .all(|(token, other_token)| token.translate(id_map) == Some(*other_token));
+        if !is_equal {
+            let mut changes = changes.lock().unwrap();
+            changes
+                .entry((name, tokens, other_tokens))
+                .or_default()
+                .push(export);
+        }
+
+        // Compare recursively same referenced types. This can be done trivially if the tokens are
+        // equal. If they are not, try hard (and slowly) to find any matching types.
+        if is_equal {
+            for token in tokens {
+                if let Some(ref_name) = token.typeref() {
+                    Self::compare_types(
+                        (corpus, file),
+                        (other_corpus, other_file),
+                        id_map,
+                        ref_name,
+                        export,
+                        changes,
+                        processed,
+                    );
+                }
+            }
+        } else {
+            for token in tokens {
+                if let Some(ref_name) = token.typeref() {
+                    for other_token in other_tokens {
+                        if let Some(other_ref_name) = other_token.typeref() {
+                            if id_map[ref_name.index()] == Some(other_ref_name) {
+                                Self::compare_types(
+                                    (corpus, file),
+                                    (other_corpus, other_file),
+                                    id_map,
+                                    ref_name,
+                                    export,
+                                    changes,
+                                    processed,
+                                );
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Compares symbols in the `self` and `other_corpus` and returns the found changes.
+    pub fn compare(&self, other_corpus: &SymCorpus, num_workers: i32) -> CompareResult {
+        self.compare_with_options(
+            other_corpus,
+            &CompareOptions::new().num_workers(num_workers),
+        )
+    }
+
+    /// Compares symbols in the `self` and `other_corpus`, using the specified options, and returns
+    /// the found changes.
+    ///
+    /// If one of the corpuses describes only CRCs, as created by [`SymCorpus::from_symvers()`],
+    /// the CRCs of the exports of the other corpus are computed and only the CRCs are compared.
+    /// Each changed export is then reported as a change of its own record, from `crc <old>` to
+    /// `crc <new>`, and [`CompareResult::is_crc_only()`] returns `true`.
+    pub fn compare_with_options(
+        &self,
+        other_corpus: &SymCorpus,
+        options: &CompareOptions,
+    ) -> CompareResult {
+        // Reduce a full corpus to the CRCs of its exports if the other one describes only CRCs.
+        if !self.crc_only && other_corpus.crc_only {
+            return self
+                .to_crc_only()
+                .compare_with_options(other_corpus, options);
+        }
+        if self.crc_only && !other_corpus.crc_only {
+            return self.compare_with_options(&other_corpus.to_crc_only(), options);
+        }
+
+        // Check for symbols in self but not in other_corpus, and vice versa.
+        let mut removed_exports = Vec::new();
+        let mut added_exports = Vec::new();
+        for (corpus_a, corpus_b, changed_exports) in [
+            (self, other_corpus, &mut removed_exports),
+            (other_corpus, self, &mut added_exports),
+        ] {
+            for &name in corpus_a.exports.keys() {
+                let name = corpus_a.strings.resolve(name);
+                if !corpus_b
+                    .strings
+                    .get(name)
+                    .is_some_and(|other_name| corpus_b.exports.contains_key(&other_name))
+                {
+                    changed_exports.push(name.to_string());
+                }
+            }
+            changed_exports.sort();
+        }
+
+        // Map the string identifiers in self to the ones in other_corpus. This allows to compare
+        // tokens of the two corpuses by their identifiers.
+        let id_map = self.map_string_ids(other_corpus);
+
+        // Compare symbols that are in both corpuses.
+        let works: Vec<_> = self.exports.iter().collect();
+        let next_work_idx = AtomicUsize::new(0);
+        let num_compared = AtomicUsize::new(0);
+
+        let changes = Mutex::new(CompareChangedTypes::default());
+
+        run_workers(options.num_workers, || loop {
+            let work_idx = next_work_idx.fetch_add(1, Ordering::Relaxed);
+            if work_idx >= works.len() {
+                break;
+            }
+            let (&name, &file_idx) = works[work_idx];
+
+            let file = &self.files[file_idx.index()];
+            if let Some(other_file_idx) = id_map[name.index()]
+                .filter(|&other_name| !self.recorded_crcs_equal(other_corpus, name, other_name))
+                .and_then(|other_name| other_corpus.exports.get(&other_name))
+            {
+                let other_file = &other_corpus.files[other_file_idx.index()];
+                let mut processed = CompareFileTypes::default();
+                Self::compare_types(
+                    (self, file),
+                    (other_corpus, other_file),
+                    &id_map,
+                    name,
+                    name,
+                    &changes,
+                    &mut processed,
+                );
+            }
+
+            let current = num_compared.fetch_add(1, Ordering::Relaxed) + 1;
+            options
+                .progress
+                .report(ProgressPhase::Compare, current, Some(works.len()));
+        });
+
+        // Turn the collected changes into the result and sort them.
+        let changes = changes.into_inner().unwrap(); // Get the inner HashMap.
+        let mut changed_types = changes
+            .into_iter()
+            .map(|((name, tokens, other_tokens), exports)| {
+                let mut exports = exports
+                    .into_iter()
+                    .map(|export| self.strings.resolve(export).to_string())
+                    .collect::<Vec<_>>();
+                exports.sort();
+                TypeChange {
+                    name: self.strings.resolve(name).to_string(),
+                    old_tokens: self.token_words(tokens),
+                    new_tokens: other_corpus.token_words(other_tokens),
+                    exports,
+                }
+            })
+            .filter(|type_change| !options.tolerated_types.contains(&type_change.name))
+            .collect::<Vec<_>>();
+        changed_types.sort();
+
+        // Warn about changes where only one definition is incomplete. Such a change is likely
+        // caused by a declaration which was not visible when the data was produced, rather than by
+        // an actual modification of the type.
+        let is_incomplete = |words: &[String]| words.iter().any(|word| word == "UNKNOWN");
+        for type_change in &changed_types {
+            if is_incomplete(&type_change.old_tokens) != is_incomplete(&type_change.new_tokens) {
+                options.diagnostics.warning(
+                    WarningKind::IncompleteType,
+                    format!(
+                        "Type '{}' has an incomplete definition in one of the corpuses",
+                        type_change.name
+                    ),
+                );
+            }
+        }
+
+        // Record the kinds, files, export types and modules of all reported exports. Removed and
+        // changed exports are described by their definition in self, added exports by the one in
+        // other_corpus.
+        let mut export_kinds = BTreeMap::new();
+        let mut export_files = BTreeMap::new();
+        let mut export_types = BTreeMap::new();
+        let mut export_modules = BTreeMap::new();
+        let changed_exports = changed_types
+            .iter()
+            .flat_map(|type_change| type_change.exports.iter());
+        let reported_exports = removed_exports
+            .iter()
+            .chain(changed_exports)
+            .map(|name| (self, name))
+            .chain(added_exports.iter().map(|name| (other_corpus, name)));
+        for (corpus, name) in reported_exports {
+            if let Some(export) = corpus.get_export(name) {
+                // The kind of an export cannot be determined from its CRC.
+                if !self.crc_only {
+                    export_kinds.insert(name.clone(), export.symbol_kind());
+                }
+                export_files.insert(name.clone(), export.file().to_path_buf());
+                if let Some(export_type) = export.export_type() {
+                    export_types.insert(name.clone(), export_type.to_string());
+                }
+                if let Some(module) = export.module() {
+                    export_modules.insert(name.clone(), module.to_string());
+                }
+            }
+        }
+
+        // Changed exports can be annotated also by symvers data known only for other_corpus, such
+        // as when it was created by SymCorpus::from_symvers().
+        let changed_exports = changed_types
+            .iter()
+            .flat_map(|type_change| type_change.exports.iter());
+        for name in changed_exports {
+            if let Some(export) = other_corpus.get_export(name) {
+                if let Some(export_type) = export.export_type() {
+                    export_types
+                        .entry(name.clone())
+                        .or_insert_with(|| export_type.to_string());
+                }
+                if let Some(module) = export.module() {
+                    export_modules
+                        .entry(name.clone())
+                        .or_insert_with(|| module.to_string());
+                }
+            }
+        }
+
+        CompareResult {
+            removed_exports,
+            added_exports,
+            changed_types,
+            export_kinds,
+            export_files,
+            export_types,
+            export_modules,
+            crc_only: self.crc_only,
+        }
+    }
+
+    /// Creates a corpus which describes only the CRCs of the exports of `self`, for comparison
+    /// with a corpus created by [`SymCorpus::from_symvers()`].
+    fn to_crc_only(&self) -> Self {
+        let mut syms = Self::from_symvers(&crate::symvers::SymversCorpus::from_symtypes(self));
+
+        // Keep only the export types and modules known for `self`, instead of the defaults filled
+        // in by SymversCorpus::from_symtypes().
+        syms.export_types.clear();
+        syms.modules.clear();
+        for export in self.export_views() {
+            let Some(name) = syms.strings.get(export.name()) else {
+                continue;
+            };
+            if let Some(export_type) = export.export_type() {
+                let export_type = syms.strings.intern(export_type);
+                syms.export_types.insert(name, export_type);
+            }
+            if let Some(module) = export.module() {
+                let module = syms.strings.intern(module);
+                syms.modules.insert(name, module);
+            }
+        }
+        syms
+    }
+
+    /// Returns whether both `self` and `other_corpus` have a CRC recorded for the export `name`,
+    /// respectively `other_name`, and the CRCs are equal. The comparison of the types of such an
+    /// export can be skipped.
+    fn recorded_crcs_equal(
+        &self,
+        other_corpus: &SymCorpus,
+        name: StrId,
+        other_name: StrId,
+    ) -> bool {
+        match (self.crcs.get(&name), other_corpus.crcs.get(&other_name)) {
+            (Some(crc), Some(other_crc)) => crc == other_crc,
+            _ => false,
+        }
+    }
+
+    /// Returns a mapping from the string identifiers in `self` to the ones in `other_corpus`.
+    fn map_string_ids(&self, other_corpus: &SymCorpus) -> Vec<Option<StrId>> {
+        self.strings
+            .strings
+            .iter()
+            .map(|string| other_corpus.strings.get(string))
+            .collect()
+    }
+
+    /// Returns whether the exported ABI of `self` and `other_corpus` is identical, that is, both
+    /// corpuses have the same exports and all their types are equal.
+    ///
+    /// This is equivalent to checking that [`SymCorpus::compare()`] finds no differences, but the
+    /// check stops at the first found difference and no report is produced.
+    pub fn abi_equal(&self, other_corpus: &SymCorpus) -> bool {
+        if self.exports.len() != other_corpus.exports.len() {
+            return false;
+        }
+        let id_map = self.map_string_ids(other_corpus);
+        let mut processed = FxHashSet::default();
+        self.exports
+            .keys()
+            .all(|&name| self.export_abi_equal(other_corpus, &id_map, name, &mut processed))
+    }
+
+    /// Returns whether the ABI of the specified `symbols` is identical in `self` and
+    /// `other_corpus`. A symbol which is exported by neither corpus is considered equal.
+    pub fn abi_equal_symbols<S: AsRef<str>>(
+        &self,
+        other_corpus: &SymCorpus,
+        symbols: &[S],
+    ) -> bool {
+        let id_map = self.map_string_ids(other_corpus);
+        let mut processed = FxHashSet::default();
+        symbols.iter().all(|symbol| {
+            let symbol = symbol.as_ref();
+            match self.strings.get(symbol) {
+                Some(name) if self.exports.contains_key(&name) => {
+                    self.export_abi_equal(other_corpus, &id_map, name, &mut processed)
+                }
+                _ => !other_corpus
+                    .strings
+                    .get(symbol)
+                    .is_some_and(|other_name| other_corpus.exports.contains_key(&other_name)),
+            }
+        })
+    }
+
+    /// Returns whether the export `name` of `self` is also present in `other_corpus` and has the
+    /// same definition, including all referenced types.
+    ///
+    /// Types which were already found equal for the same pair of files are recorded in `processed`
+    /// and are not compared again.
+    fn export_abi_equal(
+        &self,
+        other_corpus: &SymCorpus,
+        id_map: &[Option<StrId>],
+        name: StrId,
+        processed: &mut FxHashSet<(FileIdx, FileIdx, StrId)>,
+    ) -> bool {
+        let Some((other_name, &other_file_idx)) = id_map[name.index()]
+            .and_then(|other_name| Some((other_name, other_corpus.exports.get(&other_name)?)))
+        else {
+            return false;
+        };
+        if self.recorded_crcs_equal(other_corpus, name, other_name) {
+            return true;
+        }
+        let file_idx = self.exports[&name];
+        Self::types_equal(
+            (self, file_idx),
+            (other_corpus, other_file_idx),
+            id_map,
+            name,
+            processed,
+        )
+    }
+
+    /// Returns whether the definition of the symbol `name` in (`corpus`, `file_idx`) is equal to
+    /// its definition in (`other_corpus`, `other_file_idx`), including all referenced types.
+    fn types_equal(
+        (corpus, file_idx): (&SymCorpus, FileIdx),
+        (other_corpus, other_file_idx): (&SymCorpus, FileIdx),
+        id_map: &[Option<StrId>],
+        name: StrId,
+        processed: &mut FxHashSet<(FileIdx, FileIdx, StrId)>,
+    ) -> bool {
+        // A type which is already processed is either equal or its comparison is in progress higher
+        // up in the recursion, which then determines the result.
+        if !processed.insert((file_idx, other_file_idx, name)) {
+            return true;
+        }
+
+        let Some(other_name) = id_map[name.index()] else {
+            return false;
+        };
+        let file = &corpus.files[file_idx.index()];
+        let other_file = &other_corpus.files[other_file_idx.index()];
+        let tokens = Self::get_type_tokens(corpus, file, name);
+        let other_tokens = Self::get_type_tokens(other_corpus, other_file, other_name);
+
+        let is_equal = tokens.len() == other_tokens.len()
+            && zip(tokens.iter(), other_tokens.iter())
+                .all(|(token, other_token)| token.translate(id_map) == Some(*other_token));
+        if !is_equal {
+            return false;
+        }
+
+        tokens.iter().all(|token| match token.typeref() {
+            Some(ref_name) => Self::types_equal(
+                (corpus, file_idx),
+                (other_corpus, other_file_idx),
+                id_map,
+                ref_name,
+                processed,
+            ),
+            None => true,
+        })
+    }
+
+    /// Returns the words of the specified tokens.
+    fn token_words(&self, tokens: &Tokens) -> Vec<String> {
+        tokens
+            .iter()
+            .map(|token| self.strings.resolve(token.id()).to_string())
+            .collect()
+    }
+
+    /// Compares symbols in the `self` and `other_corpus`.
+    ///
+    /// A human-readable report about all found changes is written to the provided output stream.
+    pub fn compare_with<W: Write>(
+        &self,
+        other_corpus: &SymCorpus,
+        writer: W,
+        num_workers: i32,
+    ) -> Result<(), crate::Error> {
+        self.compare(other_corpus, num_workers).write_report(writer)
+    }
+
+    /// Returns the pretty-formatted definition of the type or export `name`, as seen in the
+    /// `.symtypes` file `file`, or [`None`] if the file does not contain such a record.
+    pub fn pretty_type<P: AsRef<Path>>(&self, file: P, name: &str) -> Option<Vec<String>> {
+        self.pretty_type_with(file, name, &FormatOptions::default())
+    }
+
+    /// Returns the pretty-formatted definition of the type or export `name`, as seen in the
+    /// `.symtypes` file `file`, using the specified options, or [`None`] if the file does not
+    /// contain such a record.
+    pub fn pretty_type_with<P: AsRef<Path>>(
+        &self,
+        file: P,
+        name: &str,
+        options: &FormatOptions,
+    ) -> Option<Vec<String>> {
+        self.get_type(file, name)
+            .map(|type_view| type_view.pretty_with(options))
+    }
+
+    /// Returns a view of the type or export `name`, as seen in the `.symtypes` file `file`, or
+    /// [`None`] if the file does not contain such a record.
+    pub fn get_type<P: AsRef<Path>>(&self, file: P, name: &str) -> Option<TypeView<'_>> {
+        let file = self.files.iter().find(|f| f.path == file.as_ref())?;
+        let name = self.strings.get(name)?;
+        TypeView::new(self, file, name)
+    }
+
+    /// Returns the sorted paths of all `.symtypes` files in the corpus, as recorded when loading
+    /// them according to the path options in [`LoadOptions`].
+    pub fn file_paths(&self) -> Vec<&Path> {
+        let mut paths: Vec<_> = self.files.iter().map(|file| file.path.as_path()).collect();
+        paths.sort();
+        paths
+    }
+
+    /// Returns a view of the export `name`, or [`None`] if the corpus has no such export.
+    pub fn get_export(&self, name: &str) -> Option<ExportView<'_>> {
+        let name = self.strings.get(name)?;
+        let &file_idx = self.exports.get(&name)?;
+        let type_view = TypeView::new(self, &self.files[file_idx.index()], name)?;
+        Some(ExportView { type_view })
+    }
+
+    /// Returns the number of exports in the corpus.
+    pub fn num_exports(&self) -> usize {
+        self.exports.len()
+    }
+
+    /// Returns an iterator over views of all exports in the corpus, in an unspecified order.
+    pub fn export_views(&self) -> impl Iterator<Item = ExportView<'_>> {
+        self.exports.iter().filter_map(|(&name, &file_idx)| {
+            let type_view = TypeView::new(self, &self.files[file_idx.index()], name)?;
+            Some(ExportView { type_view })
+        })
+    }
+
+    /// Returns an iterator over views of all exports in the namespace `namespace`, in an
+    /// unspecified order.
+    pub fn namespace_exports<'a>(
+        &'a self,
+        namespace: &str,
+    ) -> impl Iterator<Item = ExportView<'a>> + 'a {
+        let namespace = self.strings.get(namespace);
+        self.export_views().filter(move |export| {
+            namespace.is_some() && self.namespaces.get(&export.type_view.name) == namespace.as_ref()
+        })
+    }
+
+    /// Returns the sorted names of all namespaces used by exports in the corpus.
+    pub fn namespaces(&self) -> Vec<&str> {
+        let mut namespaces: Vec<_> = self
+            .namespaces
+            .values()
+            .map(|&namespace| self.strings.resolve(namespace))
+            .collect();
+        namespaces.sort();
+        namespaces.dedup();
+        namespaces
+    }
+
+    /// Returns the words of the definition of the type `name` as seen by `file`, or [`None`] if
+    /// the file does not contain such a record.
+    fn file_type_words<'a>(&'a self, file: &SymFile, name: &str) -> Option<Vec<&'a str>> {
+        let name = self.strings.get(name)?;
+        let &variant_idx = file.records.get(&name)?;
+        let tokens = self.types.get_tokens(name, variant_idx)?;
+        Some(
+            tokens
+                .iter()
+                .map(|token| self.strings.resolve(token.id()))
+                .collect(),
+        )
+    }
+
+    /// Computes the CRC of the export `name` in the same way as genksyms, or returns [`None`] if
+    /// no such export exists.
+    ///
+    /// The CRC is calculated over the definition of the export in which every referenced type is
+    /// expanded to its definition on its first occurrence.
+    ///
+    /// A CRC recorded in consolidated data is returned without computing it again.
+    pub fn export_crc(&self, name: &str) -> Option<u32> {
+        let name_id = self.strings.get(name)?;
+        let file = &self.files[self.exports.get(&name_id)?.index()];
+        if self.crc_only {
+            // The CRC is recorded as the definition of the export.
+            let words = self.file_type_words(file, name)?;
+            let crc = words.last()?;
+            return u32::from_str_radix(crc.strip_prefix("0x").unwrap_or(crc), 16).ok();
+        }
+        Some(self.file_export_crc(file, name))
+    }
+
+    /// Returns the CRC of the export `name` as defined by `file`, either as recorded in
+    /// consolidated data, or computed in the same way as by genksyms.
+    fn file_export_crc(&self, file: &SymFile, name: &str) -> u32 {
+        let recorded = self.strings.get(name).and_then(|name_id| {
+            let owned = self
+                .exports
+                .get(&name_id)
+                .is_some_and(|&file_idx| std::ptr::eq(&self.files[file_idx.index()], file));
+            self.crcs.get(&name_id).filter(|_| owned)
+        });
+        match recorded {
+            Some(&crc) => crc,
+            None => genksyms_crc(name, &|type_name| self.file_type_words(file, type_name)),
+        }
+    }
+
+    /// Predicts which exports would get a different genksyms CRC if the type `type_name` was
+    /// changed from `old_words` to `new_words`, without rebuilding the kernel.
+    ///
+    /// The change applies to every file in which the type is defined by `old_words`. Exports
+    /// defined in such files are checked if their CRC is affected. The result is sorted by the
+    /// export names.
+    pub fn predict_crc_changes<S: AsRef<str>, T: AsRef<str>>(
+        &self,
+        type_name: &str,
+        old_words: &[S],
+        new_words: &[T],
+    ) -> Vec<CrcChange> {
+        let old_words: Vec<_> = old_words.iter().map(AsRef::as_ref).collect();
+        let new_words: Vec<_> = new_words.iter().map(AsRef::as_ref).collect();
+
+        let mut crc_changes = Vec::new();
+        for (&name_id, &file_idx) in &self.exports {
+            let file = &self.files[file_idx.index()];
+            if self.file_type_words(file, type_name).as_ref() != Some(&old_words) {
+                continue;
+            }
+
+            let name = self.strings.resolve(name_id);
+            let old_crc = genksyms_crc(name, &|ref_name| self.file_type_words(file, ref_name));
+            let new_crc = genksyms_crc(name, &|ref_name| {
+                if ref_name == type_name {
+                    Some(new_words.clone())
+                } else {
+                    self.file_type_words(file, ref_name)
+                }
+            });
+            if old_crc != new_crc {
+                crc_changes.push(CrcChange {
+                    export: name.to_string(),
+                    old_crc,
+                    new_crc,
+                });
+            }
+        }
+        crc_changes.sort();
+        crc_changes
+    }
+
+    /// Renames the type `old_name` to `new_name` in the whole corpus, for instance, `s#foo` to
+    /// `s#foo_v2`.
+    ///
+    /// All variants of the type are renamed, and every type reference to it is updated. Occurrences
+    /// of the base name of the type, such as `foo` in `struct foo`, are replaced in the definitions
+    /// of the type itself. This allows to normalize a deliberately renamed type before comparing
+    /// two corpuses.
+    ///
+    /// Returns `false` and leaves the corpus unchanged if either name is not a type name, the type
+    /// `old_name` is not present or the type `new_name` already exists.
+    pub fn rename_type(&mut self, old_name: &str, new_name: &str) -> bool {
+        if is_export_name(old_name) || is_export_name(new_name) {
+            return false;
+        }
+        let Some(old_id) = self.strings.get(old_name) else {
+            return false;
+        };
+        if !self.types.variants.contains_key(&old_id)
+            || self
+                .strings
+                .get(new_name)
+                .is_some_and(|new_id| self.types.variants.contains_key(&new_id))
+        {
+            return false;
+        }
+
+        // Strip the `<X>#` prefix to get the base names.
+        let base_name = |name: &str| name.split_at(name.find('#').unwrap() + 1).1.to_string();
+        let new_id = self.strings.intern(new_name);
+        let old_base = self.strings.get(&base_name(old_name));
+        let new_base = self.strings.intern(&base_name(new_name));
+
+        // Replace the base name in the definitions of the type itself.
+        let variants = self.types.variants.remove(&old_id).unwrap();
+        if let Some(old_base) = old_base {
+            for range in &variants {
+                let start = range.start as usize;
+                for token in &mut self.types.tokens[start..start + range.len as usize] {
+                    if *token == Token::new_atom(old_base) {
+                        *token = Token::new_atom(new_base);
+                    }
+                }
+            }
+        }
+        self.types.variants.insert(new_id, variants);
+
+        // Update all references to the type.
+        for token in &mut self.types.tokens {
+            if token.typeref() == Some(old_id) {
+                *token = Token::new_typeref(new_id);
+            }
+        }
+
+        self.types.invalidate_index();
+
+        // Update the records of all files.
+        for file in &mut self.files {
+            if let Some(variant_idx) = file.records.remove(&old_id) {
+                file.records.insert(new_id, variant_idx);
+            }
+        }
+
+        true
+    }
+}
+
+/// A read-only snapshot of a [`SymCorpus`], created by [`SymCorpus::freeze()`].
+///
+/// The snapshot shares the data of the corpus. Cloning it is cheap and the clones can be handed
+/// out to multiple threads, which can query the corpus simultaneously without any locking. All
+/// read-only methods of [`SymCorpus`] are available through [`Deref`].
+#[derive(Clone)]
+pub struct FrozenSymCorpus {
+    corpus: Arc<SymCorpus>,
+}
+
+impl FrozenSymCorpus {
+    /// Turns the snapshot back into a modifiable corpus. The data is copied only if it is still
+    /// shared by other snapshots.
+    pub fn thaw(self) -> SymCorpus {
+        Arc::try_unwrap(self.corpus).unwrap_or_else(|corpus| (*corpus).clone())
+    }
+}
+
+impl Deref for FrozenSymCorpus {
+    type Target = SymCorpus;
+
+    fn deref(&self) -> &SymCorpus {
+        &self.corpus
+    }
+}
+
+/// A symtypes file which is indexed by record names and parsed only on demand.
+///
+/// Opening the file reads its data and records the location of each record, without turning the
+/// records into tokens. Queries then parse only the records they need. This is useful for looking
+/// up a few types or exports in a large consolidated file. The data is validated only when
+/// records are materialized into a [`SymCorpus`] by [`LazySymCorpus::materialize()`].
+///
+/// When the crate is built with the `mmap` feature, an uncompressed file can be opened by
+/// [`LazySymCorpus::open_mapped()`], which indexes the records directly in a memory mapping of the
+/// file, instead of reading all its data first.
+#[derive(Debug)]
+pub struct LazySymCorpus {
+    path: PathBuf,
+    data: LazyData,
+    records: FxHashMap<String, Range<usize>>,
+    files: Vec<LazyFile>,
+    exports: FxHashMap<String, usize>,
+}
+
+/// The data of a [`LazySymCorpus`], which is known to be valid UTF-8.
+#[derive(Debug)]
+enum LazyData {
+    /// Data read into memory.
+    Owned(String),
+    /// A read-only memory mapping of a file.
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl LazyData {
+    /// Creates data from a memory mapping of a file, checking that it is valid UTF-8.
+    #[cfg(feature = "mmap")]
+    fn mapped(map: memmap2::Mmap) -> Result<Self, crate::Error> {
+        if let Err(err) = std::str::from_utf8(&map) {
+            return Err(crate::Error::new_io(
+                "Failed to read symtypes data",
+                io::Error::new(io::ErrorKind::InvalidData, err),
+            ));
+        }
+        Ok(Self::Mapped(map))
+    }
+}
+
+impl Deref for LazyData {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Self::Owned(data) => data,
+            // SAFETY: The mapping was checked to be valid UTF-8 when the data was created.
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map) => unsafe { std::str::from_utf8_unchecked(map) },
+        }
+    }
+}
+
+/// A file described by a [`LazySymCorpus`].
+#[derive(Debug)]
+struct LazyFile {
+    path: PathBuf,
+    /// The location of the `F#` record of the file, or [`None`] if the data is a single file.
+    line: Option<Range<usize>>,
+}
+
+impl LazySymCorpus {
+    /// Opens a single or consolidated symtypes file and indexes its records.
+    ///
+    /// The extension of `path` determines the compression format of the file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, crate::Error> {
+        let path = path.as_ref();
+        let err_desc = || format!("Failed to read file '{}'", crate::display_path(path));
+        let compression = crate::Compression::from_path(path);
+        let file = PathFile::open(path).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to open file '{}'", crate::display_path(path)),
+                err,
+            )
+        })?;
+        let reader = compression
+            .reader(file)
+            .map_err(|err| crate::Error::new_io(&err_desc(), err))?;
+        Self::from_reader(compression.strip_extension(path), reader)
+    }
+
+    /// Opens an uncompressed single or consolidated symtypes file by memory-mapping it and indexes
+    /// its records directly in the mapping.
+    ///
+    /// The file must not be modified while the returned corpus exists.
+    #[cfg(feature = "mmap")]
+    pub fn open_mapped<P: AsRef<Path>>(path: P) -> Result<Self, crate::Error> {
+        let path = path.as_ref();
+        let file = fs::File::open(path).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to open file '{}'", crate::display_path(path)),
+                err,
+            )
+        })?;
+        // SAFETY: The mapping is only read and the file is expected to not be modified while the
+        // corpus exists, as documented above.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to read file '{}'", crate::display_path(path)),
+                err,
+            )
+        })?;
+        Ok(Self::from_data(path, LazyData::mapped(map)?))
+    }
+
+    /// Reads symtypes data from the specified reader and indexes its records.
+    ///
+    /// The `path` indicates the origin of the data.
+    pub fn from_reader<P: AsRef<Path>, R: Read>(
+        path: P,
+        mut reader: R,
+    ) -> Result<Self, crate::Error> {
+        let mut data = String::new();
+        reader
+            .read_to_string(&mut data)
+            .map_err(|err| crate::Error::new_io("Failed to read symtypes data", err))?;
+        Ok(Self::from_data(path.as_ref(), LazyData::Owned(data)))
+    }
+
+    /// Indexes the records of the symtypes data `data`, which originates from `path`.
+    fn from_data(path: &Path, data: LazyData) -> Self {
+        // Record the location of each record. Empty lines are skipped and only the first record of
+        // a given name is considered.
+        let mut records = FxHashMap::default();
+        let mut files = Vec::new();
+        let mut offset = 0;
+        for line in data.split_inclusive('\n') {
+            let start = offset;
+            offset += line.len();
+            let Some(name) = Words::new(line).next() else {
+                continue;
+            };
+            let range = start..start + line.trim_end().len();
+            if name.starts_with("V#") && start == 0 {
+                // Skip the format version header.
+                continue;
+            } else if let Some(file_name) = name.strip_prefix("F#") {
+                files.push(LazyFile {
+                    path: PathBuf::from(file_name),
+                    line: Some(range),
+                });
+            } else if !records.contains_key(name) {
+                records.insert(name.to_string(), range); // [1]
+            }
+        }
+
+        // Index the exports by their files. A single file owns all records.
+        let mut exports = FxHashMap::default();
+        if files.is_empty() {
+            files.push(LazyFile {
+                path: path.to_path_buf(),
+                line: None,
+            });
+            for name in records.keys() {
+                if is_export_name(name) {
+                    exports.insert(name.clone(), 0);
+                }
+            }
+        } else {
+            for (file_idx, file) in files.iter().enumerate() {
+                let line = &data[file.line.clone().unwrap()];
+                for name in Words::new(line).skip(1) {
+                    let (base_name, _) = split_type_name(strip_entry_crc(name));
+                    if is_export_name(base_name) && !exports.contains_key(base_name) {
+                        exports.insert(base_name.to_string(), file_idx); // [1]
+                    }
+                }
+            }
+        }
+
+        Self {
+            path: path.to_path_buf(),
+            data,
+            records,
+            files,
+            exports,
+        }
+    }
+
+    /// Returns the sorted names of all exports.
+    pub fn export_names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.exports.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// Returns the path of the file which defines the export `name`, or [`None`] if no such export
+    /// exists.
+    pub fn export_file(&self, name: &str) -> Option<&Path> {
+        let &file_idx = self.exports.get(name)?;
+        Some(&self.files[file_idx].path)
+    }
+
+    /// Returns the paths of all files.
+    pub fn file_paths(&self) -> Vec<&Path> {
+        self.files.iter().map(|file| file.path.as_path()).collect()
+    }
+
+    /// Returns the words of the definition of the type or export `name`, as seen by the file
+    /// `file`, or [`None`] if the file does not contain such a record.
+    ///
+    /// Only the records reachable from the file are parsed.
+    pub fn type_words(&self, file: &Path, name: &str) -> Option<Vec<&str>> {
+        let file_idx = self.files.iter().position(|lazy| lazy.path == file)?;
+        let full_name = if self.files[file_idx].line.is_none() {
+            self.resolve_name(file_idx, name)?
+        } else {
+            // Check that the type is reachable from the records listed by the file.
+            self.file_closure(file_idx, self.file_roots(file_idx))
+                .into_iter()
+                .find(|full_name| split_type_name(full_name).0 == name)?
+        };
+        Some(self.record_words(full_name)?.collect())
+    }
+
+    /// Materializes the specified exports and all types reachable from them into a new corpus,
+    /// using the specified options. Names which are not exports are ignored.
+    ///
+    /// The number of workers and the options related to collecting files are ignored.
+    pub fn materialize<S: AsRef<str>>(
+        &self,
+        names: &[S],
+        options: &LoadOptions,
+    ) -> Result<SymCorpus, crate::Error> {
+        let names: FxHashSet<_> = names.iter().map(AsRef::as_ref).collect();
+        let data = self.select_data(|name, _| names.contains(name));
+        let mut syms = SymCorpus::new();
+        if !data.is_empty() {
+            syms.load_buffer_with(&self.path, data.as_bytes(), options)?;
+        }
+        Ok(syms)
+    }
+
+    /// Returns the records of the exports accepted by `is_selected`, which is given the name of an
+    /// export and the path of its file, and of all types reachable from them, written in the
+    /// consolidated format, followed by an `F#` record for each file with a selected export.
+    fn select_data<F: Fn(&str, &Path) -> bool>(&self, is_selected: F) -> String {
+        // Collect the selected exports of each file.
+        let mut file_roots: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+        for name in self.export_names() {
+            if is_selected(name, &self.files[self.exports[name]].path) {
+                file_roots.entry(self.exports[name]).or_default().push(name);
+            }
+        }
+
+        let mut data = String::new();
+        let mut written = FxHashSet::default();
+        let mut file_lines = String::new();
+        for (file_idx, roots) in file_roots {
+            let roots = roots
+                .into_iter()
+                .filter_map(|root| self.resolve_name(file_idx, root));
+            let full_names = self.file_closure(file_idx, roots);
+            file_lines.push_str("F#");
+            file_lines.push_str(&self.files[file_idx].path.to_string_lossy());
+            for full_name in full_names {
+                file_lines.push(' ');
+                file_lines.push_str(full_name);
+                if written.insert(full_name) {
+                    data.push_str(&self.data[self.records[full_name].clone()]);
+                    data.push('\n');
+                }
+            }
+            file_lines.push('\n');
+        }
+        data.push_str(&file_lines);
+        data
+    }
+
+    /// Returns the full names of all records explicitly listed by the `F#` record of the file
+    /// `file_idx`.
+    fn file_roots(&self, file_idx: usize) -> Vec<&str> {
+        let line = self.files[file_idx].line.clone().unwrap_or_default();
+        Words::new(&self.data[line])
+            .skip(1)
+            .map(strip_entry_crc)
+            .filter(|name| self.records.contains_key(*name))
+            .collect()
+    }
+
+    /// Returns the full name of the record which provides the type `base_name` in the file
+    /// `file_idx`. The type is either listed explicitly by the `F#` record of the file, or it has
+    /// only one variant which is then stored without the variant suffix.
+    fn resolve_name(&self, file_idx: usize, base_name: &str) -> Option<&str> {
+        if let Some(line) = &self.files[file_idx].line {
+            let explicit = Words::new(&self.data[line.clone()])
+                .skip(1)
+                .map(strip_entry_crc)
+                .find(|name| split_type_name(name).0 == base_name);
+            if let Some(name) = explicit {
+                return self.records.contains_key(name).then_some(name);
+            }
+        }
+        self.records
+            .get_key_value(base_name)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the full names of the records `roots` and all records reachable from them in the
+    /// file `file_idx`, in the order in which they were found.
+    fn file_closure<'a, I: IntoIterator<Item = &'a str>>(
+        &'a self,
+        file_idx: usize,
+        roots: I,
+    ) -> Vec<&'a str> {
+        let mut full_names = Vec::new();
+        let mut visited = FxHashSet::default();
+        let mut stack: Vec<_> = roots.into_iter().collect();
+        stack.reverse();
+        while let Some(full_name) = stack.pop() {
+            if !visited.insert(split_type_name(full_name).0) {
+                continue;
+            }
+            full_names.push(full_name);
+            let Some(words) = self.record_words(full_name) else {
+                continue;
             };
+            let refs: Vec<_> = words
+                .filter(|word| !is_export_name(word))
+                .filter_map(|word| self.resolve_name(file_idx, word))
+                .collect();
+            stack.extend(refs.into_iter().rev());
+        }
+        full_names
+    }
 
-            // Insert the type into the corpus.
-            let variant_idx = Self::merge_type(base_name, tokens, load_context);
+    /// Returns an iterator over the words of the definition of the record `full_name`.
+    fn record_words(&self, full_name: &str) -> Option<impl Iterator<Item = &str>> {
+        let range = self.records.get(full_name)?;
+        Some(Words::new(&self.data[range.clone()]).skip(1))
+    }
+}
 
-            if is_consolidated {
-                // Record a mapping from the original variant name/index to the new one.
-                remap
-                    .entry(base_name.to_string()) // [1]
-                    .or_default()
-                    .insert(orig_variant_name.to_string(), variant_idx);
-            } else {
-                // Insert the record.
-                records.insert(base_name.to_string(), variant_idx);
-                Self::try_insert_export(base_name, file_idx, line_idx, load_context)?;
-            }
+/// A kind of a record in symtypes data, determined by the prefix of its name.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum RecordKind {
+    /// An exported function or variable, with no prefix.
+    Export,
+    /// A typedef, with the `t#` prefix.
+    Typedef,
+    /// An enumeration, with the `e#` prefix.
+    Enum,
+    /// A structure, with the `s#` prefix.
+    Struct,
+    /// A union, with the `u#` prefix.
+    Union,
+    /// An enumerator constant, with the `E#` prefix.
+    EnumConstant,
+    /// A type with an unrecognized prefix.
+    Other,
+}
+
+impl RecordKind {
+    /// Determines the kind of a record from its name.
+    pub fn from_name(name: &str) -> Self {
+        if is_export_name(name) {
+            return Self::Export;
         }
+        match name.as_bytes()[0] {
+            b't' => Self::Typedef,
+            b'e' => Self::Enum,
+            b's' => Self::Struct,
+            b'u' => Self::Union,
+            b'E' => Self::EnumConstant,
+            _ => Self::Other,
+        }
+    }
+}
 
-        // TODO Validate all references?
+/// A kind of an exported symbol, inferred from the structure of its definition.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SymbolKind {
+    /// A function.
+    Function,
+    /// A data object, such as a variable or a function pointer.
+    Data,
+}
 
-        if !is_consolidated {
-            // Update the file records.
-            let mut files = load_context.files.lock().unwrap();
-            files[file_idx].records = records;
-            return Ok(());
+impl SymbolKind {
+    /// Returns the kind with the specified name, `function` or `data`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "function" => Some(Self::Function),
+            "data" => Some(Self::Data),
+            _ => None,
         }
+    }
 
-        // Consolidated file needs more work.
+    /// Infers the kind of the export `name` from the words of its definition. The export is
+    /// a function if its definition is parsed as such by [`crate::ast::Decl::parse()`].
+    fn classify<'a, I: IntoIterator<Item = &'a str>>(name: &str, words: I) -> Self {
+        let words: Vec<_> = words.into_iter().collect();
+        match crate::ast::Decl::parse(name, &words) {
+            crate::ast::Decl::Function(_) => Self::Function,
+            _ => Self::Data,
+        }
+    }
+}
 
-        // Handle file declarations.
-        for line_idx in file_indices {
-            let mut words = lines[line_idx].split_ascii_whitespace();
+impl std::fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Function => write!(f, "function"),
+            Self::Data => write!(f, "data"),
+        }
+    }
+}
 
-            let record_name = words.next().unwrap();
-            assert!(record_name.starts_with("F#"));
-            let file_name = &record_name[2..];
+/// A raw token of a type definition, as provided by [`TypeView::tokens()`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RawToken<'a> {
+    /// A reference to another type, such as `s#foo`.
+    TypeRef(&'a str),
+    /// Any other word of the definition.
+    Atom(&'a str),
+}
 
-            let file_idx = {
-                let symfile = SymFile {
-                    path: Path::new(file_name).to_path_buf(),
-                    records: FileRecords::new(),
-                };
-                let mut files = load_context.files.lock().unwrap();
-                files.push(symfile);
-                files.len() - 1
+impl<'a> RawToken<'a> {
+    /// Returns the word of the token.
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            Self::TypeRef(word) | Self::Atom(word) => word,
+        }
+    }
+}
+
+/// A view of a type definition in a [`SymCorpus`], as seen by one of its `.symtypes` files.
+///
+/// The [`Display`](std::fmt::Display) implementation writes the pretty-formatted definition.
+#[derive(Clone, Copy)]
+pub struct TypeView<'a> {
+    corpus: &'a SymCorpus,
+    file: &'a SymFile,
+    name: StrId,
+    variant_idx: VariantIdx,
+    tokens: &'a Tokens,
+}
+
+impl<'a> TypeView<'a> {
+    /// Creates a view of the type `name` in `file`, or returns [`None`] if the file does not
+    /// contain such a record.
+    fn new(corpus: &'a SymCorpus, file: &'a SymFile, name: StrId) -> Option<Self> {
+        let &variant_idx = file.records.get(&name)?;
+        let tokens = corpus.types.get_tokens(name, variant_idx)?;
+        Some(Self {
+            corpus,
+            file,
+            name,
+            variant_idx,
+            tokens,
+        })
+    }
+
+    /// Returns the name of the type.
+    pub fn name(&self) -> &'a str {
+        self.corpus.strings.resolve(self.name)
+    }
+
+    /// Returns the path of the `.symtypes` file which owns this definition.
+    pub fn file(&self) -> &'a Path {
+        &self.file.path
+    }
+
+    /// Returns the index of the definition among all variants of the type in the corpus.
+    pub fn variant_index(&self) -> usize {
+        self.variant_idx.index()
+    }
+
+    /// Returns the kind of the record.
+    pub fn kind(&self) -> RecordKind {
+        RecordKind::from_name(self.name())
+    }
+
+    /// Returns the numeric value of an enumerator constant, or [`None`] if the record is not an
+    /// `E#` record or its value is not a plain integer.
+    pub fn enum_value(&self) -> Option<i64> {
+        if self.kind() != RecordKind::EnumConstant {
+            return None;
+        }
+        let words: Vec<_> = self.tokens().map(|token| token.as_str()).collect();
+        parse_enum_value(&words)
+    }
+
+    /// Returns an iterator over the raw tokens of the definition.
+    pub fn tokens(&self) -> impl Iterator<Item = RawToken<'a>> + 'a {
+        let strings = &self.corpus.strings;
+        self.tokens.iter().map(move |token| {
+            let word = strings.resolve(token.id());
+            match token.typeref() {
+                Some(_) => RawToken::TypeRef(word),
+                None => RawToken::Atom(word),
+            }
+        })
+    }
+
+    /// Returns an iterator over views of the types directly referenced by the definition, as seen
+    /// by the same file. A type which is referenced multiple times is returned each time.
+    pub fn references(&self) -> impl Iterator<Item = TypeView<'a>> + 'a {
+        let (corpus, file) = (self.corpus, self.file);
+        self.tokens
+            .iter()
+            .filter_map(move |token| TypeView::new(corpus, file, token.typeref()?))
+    }
+
+    /// Parses the definition into a structured model.
+    pub fn parse(&self) -> crate::ast::Decl {
+        let words: Vec<_> = self.tokens().map(|token| token.as_str()).collect();
+        crate::ast::Decl::parse(self.name(), &words)
+    }
+
+    /// Returns the pretty-formatted definition, using the specified options.
+    pub fn pretty_with(&self, options: &FormatOptions) -> Vec<String> {
+        let words: Vec<_> = self.tokens().map(|token| token.as_str()).collect();
+        pretty_format_type_with(&words, options)
+    }
+
+    /// Walks the graph of types reachable from this type, as seen by the same file, and invokes
+    /// the callbacks of `visitor`.
+    ///
+    /// The walk is depth-first. Each token of a type is passed to [`TypeVisitor::visit_token()`]
+    /// and a referenced type is walked right after its token. Every type is walked at most once,
+    /// which protects against cycles and avoids repeating types referenced multiple times.
+    /// A reference to a type which has no record in the file is passed as a token but not walked.
+    pub fn visit<V: TypeVisitor<'a>>(&self, visitor: &mut V) {
+        let mut visited = FxHashSet::default();
+        visited.insert(self.name);
+        self.visit_inner(visitor, 0, &mut visited);
+    }
+
+    /// Walks the graph of types reachable from this type, which is at the given `depth`. Types
+    /// which were already walked are recorded in `visited`.
+    fn visit_inner<V: TypeVisitor<'a>>(
+        &self,
+        visitor: &mut V,
+        depth: usize,
+        visited: &mut FxHashSet<StrId>,
+    ) {
+        if !visitor.enter_type(self, depth) {
+            return;
+        }
+
+        let strings = &self.corpus.strings;
+        for token in self.tokens.iter() {
+            let word = strings.resolve(token.id());
+            let Some(ref_name) = token.typeref() else {
+                visitor.visit_token(self, RawToken::Atom(word));
+                continue;
             };
+            visitor.visit_token(self, RawToken::TypeRef(word));
+            if visited.insert(ref_name) {
+                if let Some(ref_view) = TypeView::new(self.corpus, self.file, ref_name) {
+                    ref_view.visit_inner(visitor, depth + 1, visited);
+                }
+            }
+        }
 
-            let mut records = FileRecords::new();
-            for type_name in words {
-                // Parse the base name and variant name/index.
-                let (base_name, orig_variant_name) = split_type_name(type_name);
+        visitor.leave_type(self, depth);
+    }
+}
 
-                // Look up how the variant got remapped.
-                let variant_idx = *remap
-                    .get(base_name)
-                    .and_then(|hash| hash.get(orig_variant_name))
-                    .ok_or_else(|| {
-                        crate::Error::new_parse(&format!(
-                            "{}:{}: Type '{}' is not known",
-                            path.display(),
-                            line_idx + 1,
-                            type_name
-                        ))
-                    })?;
+/// Callbacks invoked when walking a type graph by [`TypeView::visit()`] or
+/// [`ExportView::visit()`].
+///
+/// All methods have a default implementation which does nothing, so a visitor needs to implement
+/// only the callbacks it is interested in.
+pub trait TypeVisitor<'a> {
+    /// Called when the walk enters the type `view`, which is at the given `depth` from the
+    /// starting type. Returning `false` skips the tokens of the type and the types referenced by
+    /// it.
+    ///
+    /// [`TypeVisitor::leave_type()`] is called for the type only if this method returns `true`. A
+    /// visitor which keeps a stack of the entered types should therefore push a type only when it
+    /// returns `true`.
+    fn enter_type(&mut self, view: &TypeView<'a>, depth: usize) -> bool {
+        let _ = (view, depth);
+        true
+    }
+
+    /// Called for each token of the type `view`.
+    fn visit_token(&mut self, view: &TypeView<'a>, token: RawToken<'a>) {
+        let _ = (view, token);
+    }
+
+    /// Called when the walk leaves the type `view`, after all its tokens and referenced types were
+    /// walked. The call is made only for a type for which [`TypeVisitor::enter_type()`] returned
+    /// `true`.
+    fn leave_type(&mut self, view: &TypeView<'a>, depth: usize) {
+        let _ = (view, depth);
+    }
+}
+
+impl std::fmt::Debug for TypeView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TypeView")
+            .field("name", &self.name())
+            .field("file", &self.file())
+            .field("variant_index", &self.variant_index())
+            .finish()
+    }
+}
+
+impl std::fmt::Display for TypeView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, line) in self
+            .pretty_with(&FormatOptions::default())
+            .iter()
+            .enumerate()
+        {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// A view of an export in a [`SymCorpus`].
+///
+/// The [`Display`](std::fmt::Display) implementation writes the pretty-formatted definition of the
+/// export.
+#[derive(Clone, Copy, Debug)]
+pub struct ExportView<'a> {
+    type_view: TypeView<'a>,
+}
+
+impl<'a> ExportView<'a> {
+    /// Returns the name of the export.
+    pub fn name(&self) -> &'a str {
+        self.type_view.name()
+    }
+
+    /// Returns the path of the `.symtypes` file which defines the export.
+    pub fn file(&self) -> &'a Path {
+        self.type_view.file()
+    }
+
+    /// Returns whether the export is a function or a data object.
+    pub fn symbol_kind(&self) -> SymbolKind {
+        SymbolKind::classify(
+            self.name(),
+            self.type_view.tokens().map(|token| token.as_str()),
+        )
+    }
+
+    /// Returns the namespace of the export, or [`None`] if the export is not in any namespace or
+    /// no symvers data was loaded.
+    pub fn namespace(&self) -> Option<&'a str> {
+        let corpus = self.type_view.corpus;
+        corpus
+            .namespaces
+            .get(&self.type_view.name)
+            .map(|&namespace| corpus.strings.resolve(namespace))
+    }
+
+    /// Returns the export type, for instance, `EXPORT_SYMBOL_GPL`, or [`None`] if no symvers data
+    /// with the export was loaded.
+    pub fn export_type(&self) -> Option<&'a str> {
+        let corpus = self.type_view.corpus;
+        corpus
+            .export_types
+            .get(&self.type_view.name)
+            .map(|&export_type| corpus.strings.resolve(export_type))
+    }
+
+    /// Returns the module which exports the symbol, for instance, `vmlinux`, or [`None`] if no
+    /// symvers data with the export was loaded.
+    pub fn module(&self) -> Option<&'a str> {
+        let corpus = self.type_view.corpus;
+        corpus
+            .modules
+            .get(&self.type_view.name)
+            .map(|&module| corpus.strings.resolve(module))
+    }
+
+    /// Returns a view of the definition of the export.
+    pub fn definition(&self) -> TypeView<'a> {
+        self.type_view
+    }
+
+    /// Walks the type graph of the export and invokes the callbacks of `visitor`. See
+    /// [`TypeView::visit()`] for details.
+    pub fn visit<V: TypeVisitor<'a>>(&self, visitor: &mut V) {
+        self.type_view.visit(visitor)
+    }
+}
+
+impl std::fmt::Display for ExportView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.type_view.fmt(f)
+    }
+}
+
+/// A predicted change of the genksyms CRC of an export, as provided by
+/// [`SymCorpus::predict_crc_changes()`].
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct CrcChange {
+    export: String,
+    old_crc: u32,
+    new_crc: u32,
+}
+
+impl CrcChange {
+    /// Returns the name of the affected export.
+    pub fn export(&self) -> &str {
+        &self.export
+    }
+
+    /// Returns the current CRC of the export.
+    pub fn old_crc(&self) -> u32 {
+        self.old_crc
+    }
+
+    /// Returns the CRC of the export after the change.
+    pub fn new_crc(&self) -> u32 {
+        self.new_crc
+    }
+}
+
+/// A type which differs between two compared corpuses, along with exports affected by the change.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeChange {
+    name: String,
+    old_tokens: Vec<String>,
+    new_tokens: Vec<String>,
+    exports: Vec<String>,
+}
+
+impl TypeChange {
+    /// Returns the name of the changed type.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the sorted names of exports affected by the change.
+    pub fn exports(&self) -> &[String] {
+        &self.exports
+    }
+
+    /// Returns the old and new numeric value if the change is of an enumerator constant with
+    /// plain integer values, or [`None`] otherwise.
+    pub fn enum_value_change(&self) -> Option<(i64, i64)> {
+        if RecordKind::from_name(&self.name) != RecordKind::EnumConstant {
+            return None;
+        }
+        Some((
+            parse_enum_value(&self.old_tokens)?,
+            parse_enum_value(&self.new_tokens)?,
+        ))
+    }
+
+    /// Returns human-readable descriptions of changes of individual members, if the type is
+    /// a structure or a union, for instance, `struct foo: member 'a' changed type from int to
+    /// long`.
+    pub fn describe_member_changes(&self) -> Vec<String> {
+        let old_decl = crate::ast::Decl::parse(&self.name, &self.old_tokens);
+        let new_decl = crate::ast::Decl::parse(&self.name, &self.new_tokens);
+        old_decl.describe_member_changes(&new_decl)
+    }
+
+    /// Returns human-readable descriptions of the change, either of a changed value of an
+    /// enumerator, or of changes of individual members, as reported by
+    /// [`CompareResult::write_report()`].
+    pub fn describe_changes(&self) -> Vec<String> {
+        match self.enum_value_change() {
+            Some((old_value, new_value)) => vec![format!(
+                "{} changed from {} to {}",
+                self.name, old_value, new_value
+            )],
+            None => self.describe_member_changes(),
+        }
+    }
+
+    /// Writes a unified diff between the old and new definition of the type to the provided
+    /// output stream.
+    pub fn write_diff<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        self.write_diff_with(&DiffOptions::new(), writer)
+    }
+
+    /// Writes a unified diff between the old and new definition of the type, computed using the
+    /// specified options, to the provided output stream.
+    pub fn write_diff_with<W: Write>(
+        &self,
+        options: &DiffOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        write_type_diff(&self.old_tokens, &self.new_tokens, options, writer)
+    }
+}
+
+/// A comparison result as stored in a JSON report written by
+/// [`CompareResult::write_json_report_with()`]. Members which are computed when the report is
+/// written are not read.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct JsonReport {
+    removed_exports: Vec<String>,
+    added_exports: Vec<String>,
+    changed_types: Vec<JsonTypeChange>,
+    // Reports written by older versions do not describe the exports.
+    #[serde(default)]
+    exports: BTreeMap<String, JsonExport>,
+    #[serde(default)]
+    crc_only: bool,
+}
+
+/// A changed type in a JSON report.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct JsonTypeChange {
+    name: String,
+    old_tokens: Vec<String>,
+    new_tokens: Vec<String>,
+    exports: Vec<String>,
+}
+
+/// A description of an export in a JSON report, with `null` for an unknown property.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct JsonExport {
+    kind: Option<String>,
+    file: Option<String>,
+    export_type: Option<String>,
+    module: Option<String>,
+}
+
+/// A result of comparing two corpuses by [`SymCorpus::compare()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompareResult {
+    removed_exports: Vec<String>,
+    added_exports: Vec<String>,
+    changed_types: Vec<TypeChange>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    export_kinds: BTreeMap<String, SymbolKind>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    export_files: BTreeMap<String, PathBuf>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    export_types: BTreeMap<String, String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    export_modules: BTreeMap<String, String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    crc_only: bool,
+}
+
+impl CompareResult {
+    /// Returns the sorted names of exports present only in the first corpus.
+    pub fn removed_exports(&self) -> &[String] {
+        &self.removed_exports
+    }
+
+    /// Returns the sorted names of exports present only in the second corpus.
+    pub fn added_exports(&self) -> &[String] {
+        &self.added_exports
+    }
+
+    /// Returns all changed types, sorted by their name.
+    pub fn changed_types(&self) -> &[TypeChange] {
+        &self.changed_types
+    }
+
+    /// Returns the kind of the export `name`, which must be one of the removed, added or changed
+    /// exports, or [`None`] if it is not reported by the result.
+    pub fn export_kind(&self, name: &str) -> Option<SymbolKind> {
+        self.export_kinds.get(name).copied()
+    }
+
+    /// Returns the path of the `.symtypes` file which defines the export `name`, which must be one
+    /// of the removed, added or changed exports, or [`None`] if it is not reported by the result.
+    pub fn export_file(&self, name: &str) -> Option<&Path> {
+        self.export_files.get(name).map(PathBuf::as_path)
+    }
+
+    /// Returns the export type of the export `name`, for instance, `EXPORT_SYMBOL_GPL`, or
+    /// [`None`] if it is not reported by the result or no symvers data with the export was loaded.
+    pub fn export_type(&self, name: &str) -> Option<&str> {
+        self.export_types.get(name).map(String::as_str)
+    }
+
+    /// Returns the module which exports the symbol `name`, for instance, `vmlinux`, or [`None`]
+    /// if it is not reported by the result or no symvers data with the export was loaded.
+    pub fn export_module(&self, name: &str) -> Option<&str> {
+        self.export_modules.get(name).map(String::as_str)
+    }
+
+    /// Aggregates the changes into a badness score, using the specified options.
+    ///
+    /// Each removed, added and changed export contributes its badness to the total score and to
+    /// the score of the `.symtypes` file which defines it, which corresponds to a module or an
+    /// object of the kernel build. An export affected by multiple changed types is counted once.
+    pub fn score(&self, options: &ScoreOptions) -> Score {
+        options.score_changes(self.export_changes())
+    }
+
+    /// Returns the removed, added and changed exports, each with the path of the `.symtypes` file
+    /// which defines it and the kind of the change. An export affected by multiple changed types is
+    /// listed once.
+    pub fn export_changes(&self) -> Vec<(&str, Option<&Path>, ChangeKind)> {
+        let mut changed_exports: Vec<_> = self
+            .changed_types
+            .iter()
+            .flat_map(|type_change| type_change.exports.iter())
+            .collect();
+        changed_exports.sort();
+        changed_exports.dedup();
+
+        self.removed_exports
+            .iter()
+            .map(|name| (name, ChangeKind::Removed))
+            .chain(
+                self.added_exports
+                    .iter()
+                    .map(|name| (name, ChangeKind::Added)),
+            )
+            .chain(
+                changed_exports
+                    .into_iter()
+                    .map(|name| (name, ChangeKind::Changed)),
+            )
+            .map(|(name, kind)| (name.as_str(), self.export_file(name), kind))
+            .collect()
+    }
+
+    /// Removes the changes for which `is_suppressed` returns `true`.
+    ///
+    /// The function receives the name of a removed or added export, of a changed type, or of an
+    /// export affected by a changed type, together with the kind of the change, which is
+    /// [`ChangeKind::Changed`] for the latter two. A changed type which no longer affects any export
+    /// is removed as well.
+    pub fn suppress<F: Fn(&str, ChangeKind) -> bool>(&mut self, is_suppressed: F) {
+        self.removed_exports
+            .retain(|name| !is_suppressed(name, ChangeKind::Removed));
+        self.added_exports
+            .retain(|name| !is_suppressed(name, ChangeKind::Added));
+        self.changed_types.retain_mut(|type_change| {
+            if is_suppressed(&type_change.name, ChangeKind::Changed) {
+                return false;
+            }
+            type_change
+                .exports
+                .retain(|name| !is_suppressed(name, ChangeKind::Changed));
+            !type_change.exports.is_empty()
+        });
+    }
+
+    /// Returns a suffix describing the kind, export type and module of the export `name` in the
+    /// report, for instance, ` (function, EXPORT_SYMBOL_GPL in vmlinux)`.
+    fn kind_suffix(&self, name: &str) -> String {
+        let mut parts = Vec::new();
+        if let Some(kind) = self.export_kind(name) {
+            parts.push(kind.to_string());
+        }
+        match (self.export_type(name), self.export_module(name)) {
+            (Some(export_type), Some(module)) => {
+                parts.push(format!("{} in {}", export_type, module))
+            }
+            (Some(export_type), None) => parts.push(export_type.to_string()),
+            (None, Some(module)) => parts.push(format!("in {}", module)),
+            (None, None) => {}
+        }
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", parts.join(", "))
+        }
+    }
+
+    /// Returns whether only the CRCs of the exports were compared, because one of the corpuses
+    /// was created from symvers data alone. The changed types are then the changed exports and
+    /// their structural differences are not known.
+    pub fn is_crc_only(&self) -> bool {
+        self.crc_only
+    }
+
+    /// Returns whether no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self.removed_exports.is_empty()
+            && self.added_exports.is_empty()
+            && self.changed_types.is_empty()
+    }
+
+    /// Writes a human-readable report about all changes to the provided output stream.
+    pub fn write_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        self.write_report_with(&DiffOptions::new(), writer)
+    }
+
+    /// Writes a human-readable report about all changes to the provided output stream, computing
+    /// diffs of the changed types using the specified options.
+    ///
+    /// If labels are set in the options, each diff is preceded by header lines with the labels
+    /// followed by a colon and the name of the changed type, for instance, `--- a.symtypes:s#foo`.
+    pub fn write_report_with<W: Write>(
+        &self,
+        diff_options: &DiffOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+
+        for (names, change) in [
+            (&self.removed_exports, "removed"),
+            (&self.added_exports, "added"),
+        ] {
+            for name in names {
+                writeln!(
+                    writer,
+                    "Export '{}'{} has been {}",
+                    name,
+                    self.kind_suffix(name),
+                    change
+                )
+                .map_io_err(err_desc)?;
+            }
+        }
+
+        let mut add_separator = false;
+        for type_change in &self.changed_types {
+            // Add an empty line to separate individual changes.
+            if add_separator {
+                writeln!(writer).map_io_err(err_desc)?;
+            } else {
+                add_separator = true;
+            }
+
+            writeln!(
+                writer,
+                "The following '{}' exports are different:",
+                type_change.exports.len()
+            )
+            .map_io_err(err_desc)?;
+            for export in &type_change.exports {
+                writeln!(writer, " {}{}", export, self.kind_suffix(export)).map_io_err(err_desc)?;
+            }
+            writeln!(writer).map_io_err(err_desc)?;
+
+            if self.crc_only {
+                let crc = |words: &[String]| words.last().cloned().unwrap_or_default();
+                writeln!(
+                    writer,
+                    "because of a changed CRC from {} to {}, structural differences are not available",
+                    crc(&type_change.old_tokens),
+                    crc(&type_change.new_tokens)
+                )
+                .map_io_err(err_desc)?;
+                continue;
+            }
+
+            writeln!(writer, "because of a changed '{}':", type_change.name)
+                .map_io_err(err_desc)?;
+            for description in type_change.describe_changes() {
+                writeln!(writer, "{}", description).map_io_err(err_desc)?;
+            }
+            if type_change.enum_value_change().is_none() {
+                let type_options = Self::type_diff_options(diff_options, type_change);
+                type_change.write_diff_with(&type_options, writer.by_ref())?;
+            }
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Returns the options for the diff of `type_change`. If labels are set in `diff_options`, each
+    /// is followed by a colon and the name of the changed type.
+    fn type_diff_options(diff_options: &DiffOptions, type_change: &TypeChange) -> DiffOptions {
+        match (diff_options.old_label(), diff_options.new_label()) {
+            (Some(old_label), Some(new_label)) => diff_options.clone().labels(
+                format!("{}:{}", old_label, type_change.name),
+                format!("{}:{}", new_label, type_change.name),
+            ),
+            _ => diff_options.clone(),
+        }
+    }
+
+    /// Returns the titles and numbers of the removed, added and changed exports and changed types,
+    /// as summarized at the start of the mail, Markdown and HTML reports. An export affected by
+    /// multiple changed types is counted once.
+    fn summary(&self) -> [(&'static str, usize); 4] {
+        let num_changed = self
+            .export_changes()
+            .iter()
+            .filter(|(_, _, kind)| *kind == ChangeKind::Changed)
+            .count();
+        [
+            ("Removed exports", self.removed_exports.len()),
+            ("Added exports", self.added_exports.len()),
+            ("Changed exports", num_changed),
+            ("Changed types", self.changed_types.len()),
+        ]
+    }
+
+    /// Writes a report about all changes as a JSON object to the provided output stream, computing
+    /// diffs of the changed types using the specified options.
+    ///
+    /// The object contains arrays `removed_exports` and `added_exports` with names of the exports,
+    /// and an array `changed_types`. Each changed type is described by its `name`, names of the
+    /// affected `exports`, human-readable `descriptions` of the changes, the words of its old and
+    /// new definition in `old_tokens` and `new_tokens`, and `hunks` of the diff in the form
+    /// described by [`crate::diff::Format::Json`]. The object `exports` maps the names of the
+    /// reported exports to their `kind`, `file`, `export_type` and `module`, each `null` if not
+    /// known, and `crc_only` tells whether only the CRCs of the exports were compared.
+    ///
+    /// When the crate is built with the `serde` feature, the report can be read back by
+    /// `CompareResult::read_json_report()`.
+    pub fn write_json_report_with<W: Write>(
+        &self,
+        diff_options: &DiffOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+        let json_options = diff_options.clone().format(crate::diff::Format::Json);
 
-                // Insert the record.
-                records.insert(base_name.to_string(), variant_idx);
-                Self::try_insert_export(base_name, file_idx, line_idx, load_context)?;
+        let json_array = |strings: &[String]| {
+            let strings: Vec<_> = strings.iter().map(|string| json_string(string)).collect();
+            format!("[{}]", strings.join(","))
+        };
+
+        write!(
+            writer,
+            "{{\"removed_exports\":{},\"added_exports\":{},\"changed_types\":[",
+            json_array(&self.removed_exports),
+            json_array(&self.added_exports)
+        )
+        .map_io_err(err_desc)?;
+        for (i, type_change) in self.changed_types.iter().enumerate() {
+            if i > 0 {
+                write!(writer, ",").map_io_err(err_desc)?;
             }
+            write!(writer, "{{").map_io_err(err_desc)?;
+            Self::write_json_type_change(type_change, &json_options, writer.by_ref())?;
+            write!(writer, "}}").map_io_err(err_desc)?;
+        }
 
-            // Add implicit references, ones that were omitted by the F# declaration because only
-            // one variant exists in the entire consolidated file.
-            let walk_records: Vec<_> = records.iter().map(|(k, v)| (k.clone(), *v)).collect();
-            for (name, variant_idx) in walk_records {
-                let types = load_context.types.read().unwrap();
-                Self::extrapolate_file_record(
-                    path,
-                    file_name,
-                    &name,
-                    variant_idx,
-                    true,
-                    *types,
-                    &mut records,
-                )?;
+        let json_optional = |value: Option<String>| match value {
+            Some(value) => json_string(&value),
+            None => "null".to_string(),
+        };
+        let mut names: Vec<_> = self
+            .export_kinds
+            .keys()
+            .chain(self.export_files.keys())
+            .chain(self.export_types.keys())
+            .chain(self.export_modules.keys())
+            .collect();
+        names.sort();
+        names.dedup();
+        write!(writer, "],\"exports\":{{").map_io_err(err_desc)?;
+        for (i, name) in names.into_iter().enumerate() {
+            if i > 0 {
+                write!(writer, ",").map_io_err(err_desc)?;
             }
+            write!(
+                writer,
+                "{}:{{\"kind\":{},\"file\":{},\"export_type\":{},\"module\":{}}}",
+                json_string(name),
+                json_optional(self.export_kind(name).map(|kind| kind.to_string())),
+                json_optional(
+                    self.export_file(name)
+                        .map(|file| file.display().to_string())
+                ),
+                json_optional(self.export_type(name).map(str::to_string)),
+                json_optional(self.export_module(name).map(str::to_string))
+            )
+            .map_io_err(err_desc)?;
+        }
+        writeln!(writer, "}},\"crc_only\":{}}}", self.crc_only).map_io_err(err_desc)?;
+
+        writer.flush().map_io_err(err_desc)
+    }
 
-            let mut files = load_context.files.lock().unwrap();
-            files[file_idx].records = records;
+    /// Loads a comparison result from a JSON report written by [`Self::write_json_report_with()`]
+    /// to a file, which can be compressed in one of the supported formats. The path `-` denotes
+    /// the standard input.
+    #[cfg(feature = "serde")]
+    pub fn load_json_report<P: AsRef<Path>>(path: P) -> Result<Self, crate::Error> {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            return Self::read_json_report(path, io::stdin().lock());
         }
 
-        Ok(())
+        let file = PathFile::open(path).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to open file '{}'", crate::display_path(path)),
+                err,
+            )
+        })?;
+        let reader = crate::Compression::from_path(path)
+            .reader(file)
+            .map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to read file '{}'", crate::display_path(path)),
+                    err,
+                )
+            })?;
+        Self::read_json_report(path, reader)
     }
 
-    /// Adds the given type definition to the corpus if not already present, and returns its variant
-    /// index.
-    fn merge_type(type_name: &str, tokens: Tokens, load_context: &LoadContext) -> usize {
-        let mut types = load_context.types.write().unwrap();
-        match types.get_mut(type_name) {
-            Some(variants) => {
-                for (i, variant) in variants.iter().enumerate() {
-                    if tokens == *variant {
-                        return i;
-                    }
-                }
-                variants.push(tokens);
-                variants.len() - 1
+    /// Reads a comparison result from a JSON report written by [`Self::write_json_report_with()`],
+    /// provided by the specified reader. The `path` identifies the origin of the data in error
+    /// messages.
+    ///
+    /// The descriptions and hunks in the report are not read, they are computed again from the
+    /// old and new tokens of the changed types when the result is written.
+    #[cfg(feature = "serde")]
+    pub fn read_json_report<P: AsRef<Path>, R: Read>(
+        path: P,
+        mut reader: R,
+    ) -> Result<Self, crate::Error> {
+        let path = path.as_ref();
+        let mut data = String::new();
+        reader.read_to_string(&mut data).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to read file '{}'", crate::display_path(path)),
+                err,
+            )
+        })?;
+        let report: JsonReport = serde_json::from_str(&data).map_err(|err| {
+            // The location is reported separately, remove it from the message.
+            let message = err.to_string();
+            let message = match message.rfind(" at line ") {
+                Some(end) => message[..end].to_string(),
+                None => message,
+            };
+            let line_idx = err.line().saturating_sub(1);
+            let mut parse_error =
+                ParseError::new(ParseErrorKind::MalformedJson, path, line_idx, message);
+            if let (Some(source_line), Some(column_idx)) =
+                (data.lines().nth(line_idx), err.column().checked_sub(1))
+            {
+                parse_error = parse_error.with_source(source_line, column_idx);
             }
-            None => {
-                types.insert(type_name.to_string(), vec![tokens]); // [1]
-                0
+            crate::Error::from(parse_error)
+        })?;
+
+        let mut result = Self {
+            removed_exports: report.removed_exports,
+            added_exports: report.added_exports,
+            changed_types: report
+                .changed_types
+                .into_iter()
+                .map(|type_change| TypeChange {
+                    name: type_change.name,
+                    old_tokens: type_change.old_tokens,
+                    new_tokens: type_change.new_tokens,
+                    exports: type_change.exports,
+                })
+                .collect(),
+            crc_only: report.crc_only,
+            ..Default::default()
+        };
+        for (name, export) in report.exports {
+            if let Some(kind) = export.kind {
+                let kind = SymbolKind::from_name(&kind).ok_or_else(|| {
+                    ParseError::new(
+                        ParseErrorKind::MalformedJson,
+                        path,
+                        0,
+                        format!("Unrecognized kind '{}' of export '{}'", kind, name),
+                    )
+                })?;
+                result.export_kinds.insert(name.clone(), kind);
+            }
+            if let Some(file) = export.file {
+                result
+                    .export_files
+                    .insert(name.clone(), PathBuf::from(file));
+            }
+            if let Some(export_type) = export.export_type {
+                result.export_types.insert(name.clone(), export_type);
+            }
+            if let Some(module) = export.module {
+                result.export_modules.insert(name, module);
             }
         }
+
+        result.removed_exports.sort();
+        result.added_exports.sort();
+        result.changed_types.sort();
+        Ok(result)
     }
 
-    /// Checks if a specified `type_name` is an export and, if so, registers it with its `file_idx`
-    /// in the `load_context.exports`.
-    fn try_insert_export(
-        type_name: &str,
-        file_idx: usize,
-        line_idx: usize,
-        load_context: &LoadContext,
+    /// Writes a report about all changes in the JSON Lines format to the provided output stream,
+    /// computing diffs of the changed types using the specified options.
+    ///
+    /// Each change is written as a separate JSON object on its own line, and the output is flushed
+    /// after each line, so that a consumer can process the report in a streaming fashion. The
+    /// member `change` of each object is `removed_export` or `added_export` for an export present
+    /// only in one corpus, which is identified by its `name`, or `changed_type` for a changed type,
+    /// which is described by the same members as in [`Self::write_json_report_with()`].
+    pub fn write_jsonl_report_with<W: Write>(
+        &self,
+        diff_options: &DiffOptions,
+        writer: W,
     ) -> Result<(), crate::Error> {
-        if !is_export_name(type_name) {
-            return Ok(());
-        }
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+        let json_options = diff_options.clone().format(crate::diff::Format::Json);
 
-        // Try to add the export, return an error if it is a duplicate.
-        let other_file_idx = {
-            let mut exports = load_context.exports.lock().unwrap();
-            match exports.entry(type_name.to_string()) // [1]
-            {
-                Occupied(export_entry) => *export_entry.get(),
-                Vacant(export_entry) => {
-                    export_entry.insert(file_idx);
-                    return Ok(());
-                }
+        for (names, change) in [
+            (&self.removed_exports, "removed_export"),
+            (&self.added_exports, "added_export"),
+        ] {
+            for name in names {
+                writeln!(
+                    writer,
+                    "{{\"change\":\"{}\",\"name\":{}}}",
+                    change,
+                    json_string(name)
+                )
+                .map_io_err(err_desc)?;
+                writer.flush().map_io_err(err_desc)?;
             }
+        }
+        for type_change in &self.changed_types {
+            write!(writer, "{{\"change\":\"changed_type\",").map_io_err(err_desc)?;
+            Self::write_json_type_change(type_change, &json_options, writer.by_ref())?;
+            writeln!(writer, "}}").map_io_err(err_desc)?;
+            writer.flush().map_io_err(err_desc)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the members `name`, `exports`, `descriptions`, `old_tokens`, `new_tokens` and `hunks`
+    /// which describe `type_change` in a JSON object to the provided output stream.
+    fn write_json_type_change<W: Write>(
+        type_change: &TypeChange,
+        json_options: &DiffOptions,
+        mut writer: W,
+    ) -> Result<(), crate::Error> {
+        let err_desc = "Failed to write a comparison result";
+        let json_array = |strings: &[String]| {
+            let strings: Vec<_> = strings.iter().map(|string| json_string(string)).collect();
+            format!("[{}]", strings.join(","))
         };
 
-        let files = load_context.files.lock().unwrap();
-        let path = &files[file_idx].path;
-        let other_path = &files[other_file_idx].path;
-        Err(crate::Error::new_parse(&format!(
-            "{}:{}: Export '{}' is duplicate. Previous occurrence found in '{}'.",
-            path.display(),
-            line_idx + 1,
-            type_name,
-            other_path.display()
-        )))
+        write!(
+            writer,
+            "\"name\":{},\"exports\":{},\"descriptions\":{},\"old_tokens\":{},\"new_tokens\":{},\
+             \"hunks\":",
+            json_string(&type_change.name),
+            json_array(&type_change.exports),
+            json_array(&type_change.describe_changes()),
+            json_array(&type_change.old_tokens),
+            json_array(&type_change.new_tokens)
+        )
+        .map_io_err(err_desc)?;
+        if type_change.enum_value_change().is_none() {
+            type_change.write_diff_with(json_options, writer)
+        } else {
+            write!(writer, "[]").map_io_err(err_desc)
+        }
     }
 
-    /// Processes a single symbol in some file originated from an `F#` record and enhances the
-    /// specified file records with the needed implicit types.
-    ///
-    /// This function is used when reading a consolidated input file and processing its `F#`
-    /// records. Each `F#` record is in form `F#<filename> <type@variant>... <export>...`. It lists
-    /// all types and exports in a given file but is allowed to omit any referenced types which have
-    /// only one variant in the whole consolidated file. The purpose of this function is to find all
-    /// such implicit references and add them to `records`.
+    /// Writes a plain-ASCII report about all changes to the provided output stream, suitable for
+    /// sending by mail, computing diffs of the changed types using the specified options.
     ///
-    /// A caller of this function should pre-fill `records` with all explicit references given on
-    /// the processed `F#` record and then call this function on each of the references. These root
-    /// calls should be invoked with `is_explicit` set to `true`. The function then recursively adds
-    /// all needed implicit types which are referenced from these roots.
-    fn extrapolate_file_record(
-        corpus_path: &Path,
-        file_name: &str,
-        name: &str,
-        variant_idx: usize,
-        is_explicit: bool,
-        types: &Types,
-        records: &mut FileRecords,
+    /// The report starts with a summary of the numbers of removed, added and changed exports and
+    /// changed types, followed by a section for each non-empty group with its number in the
+    /// heading. Prose is wrapped to 72 columns. Each changed type is described by a paragraph
+    /// listing the affected exports and the descriptions of the change, followed by its diff
+    /// preceded by header lines as in a patch, with the labels from the options, or `a` and `b`
+    /// if not set, followed by a colon and the name of the changed type.
+    pub fn write_mail_report_with<W: Write>(
+        &self,
+        diff_options: &DiffOptions,
+        writer: W,
     ) -> Result<(), crate::Error> {
-        if is_explicit {
-            // All explicit symbols need to be added by the caller.
-            assert!(records.get(name).is_some());
-        } else {
-            // A symbol can be implicit only if it has one variant.
-            assert!(variant_idx == 0);
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+        let old_label = diff_options.old_label().unwrap_or("a");
+        let new_label = diff_options.new_label().unwrap_or("b");
+
+        let mut lines = Vec::new();
+        lines.extend(crate::mail_heading("Summary", '='));
+        lines.push(String::new());
+        for (title, count) in self.summary() {
+            lines.push(format!("{}: {}", title, count));
+        }
 
-            // See if the symbol was already processed.
-            if records.get(name).is_some() {
-                return Ok(());
+        for (names, title) in [
+            (&self.removed_exports, "Removed exports"),
+            (&self.added_exports, "Added exports"),
+        ] {
+            if names.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.extend(crate::mail_heading(
+                &format!("{} ({})", title, names.len()),
+                '-',
+            ));
+            lines.push(String::new());
+            for name in names {
+                lines.extend(crate::mail_wrap(
+                    &format!("{}{}", name, self.kind_suffix(name)),
+                    "  ",
+                ));
             }
-            records.insert(name.to_string(), variant_idx); // [1]
         }
 
-        // Obtain tokens for the selected variant and check it is correctly specified.
-        let variants = types.get(name).unwrap();
-        assert!(!variants.is_empty());
-        if !is_explicit && variants.len() > 1 {
-            return Err(crate::Error::new_parse(&format!(
-                "{}: Type '{}' is implicitly referenced by file '{}' but has multiple variants in the corpus",
-                corpus_path.display(),
-                name,
-                file_name,
-            )));
+        if !self.changed_types.is_empty() {
+            lines.push(String::new());
+            lines.extend(crate::mail_heading(
+                &format!("Changed types ({})", self.changed_types.len()),
+                '-',
+            ));
         }
-        let tokens = &variants[variant_idx];
+        for type_change in &self.changed_types {
+            let exports: Vec<_> = type_change
+                .exports
+                .iter()
+                .map(|export| format!("{}{}", export, self.kind_suffix(export)))
+                .collect();
+            lines.push(String::new());
+
+            if self.crc_only {
+                let crc = |words: &[String]| words.last().cloned().unwrap_or_default();
+                lines.extend(crate::mail_wrap(
+                    &format!(
+                        "Export {} has a changed CRC from {} to {}, structural differences are \
+                         not available.",
+                        exports.join(", "),
+                        crc(&type_change.old_tokens),
+                        crc(&type_change.new_tokens)
+                    ),
+                    "",
+                ));
+                continue;
+            }
 
-        // Process recursively all types referenced by this symbol.
-        for token in tokens {
-            match token {
-                Token::TypeRef(ref_name) => {
-                    // Process the type. Note that passing variant_idx=0 is ok here:
-                    // * If the type is explicitly specified in the parent F# record then it must be
-                    //   already added in the records and the called function immediately returns.
-                    // * If the type is implicit then it can have only one variant and so only
-                    //   variant_idx=0 can be correct. The invoked function will check that no more
-                    //   than one variant is actually present.
-                    Self::extrapolate_file_record(
-                        corpus_path,
-                        file_name,
-                        ref_name,
-                        0,
-                        false,
-                        types,
-                        records,
-                    )?;
-                }
-                Token::Atom(_word) => {}
+            lines.extend(crate::mail_wrap(
+                &format!(
+                    "Type '{}' has changed, affecting {} {}: {}.",
+                    type_change.name,
+                    exports.len(),
+                    if exports.len() == 1 {
+                        "export"
+                    } else {
+                        "exports"
+                    },
+                    exports.join(", ")
+                ),
+                "",
+            ));
+            for description in type_change.describe_changes() {
+                lines.extend(crate::mail_wrap(&description, ""));
+            }
+            if type_change.enum_value_change().is_none() {
+                let type_options = diff_options.clone().labels(
+                    format!("{}:{}", old_label, type_change.name),
+                    format!("{}:{}", new_label, type_change.name),
+                );
+                let mut diff = Vec::new();
+                type_change.write_diff_with(&type_options, &mut diff)?;
+                lines.push(String::new());
+                lines.extend(
+                    String::from_utf8_lossy(&diff)
+                        .lines()
+                        .map(crate::mail_ascii),
+                );
             }
         }
 
-        Ok(())
+        for line in lines {
+            writeln!(writer, "{}", line).map_io_err(err_desc)?;
+        }
+        writer.flush().map_io_err(err_desc)
     }
 
-    /// Processes a single symbol specified in a given file and adds it to the consolidated output.
-    ///
-    /// The specified symbol is added to `output_types` and `processed_types`, if not already
-    /// present, and all its type references get recursively processed in the same way.
-    fn consolidate_type<'a>(
-        &'a self,
-        symfile: &SymFile,
-        name: &'a str,
-        output_types: &mut ConsolidateOutputTypes<'a>,
-        processed_types: &mut ConsolidateFileTypes<'a>,
-    ) {
-        // See if the symbol was already processed.
-        let processed_entry = match processed_types.entry(name) {
-            Occupied(_) => return,
-            Vacant(processed_entry) => processed_entry,
-        };
+    /// Returns a sentence listing the exports affected by `type_change`, each formatted by
+    /// `format_export`, for the Markdown and HTML reports.
+    fn affected_exports<F: Fn(&str) -> String>(
+        type_change: &TypeChange,
+        format_export: F,
+    ) -> String {
+        let exports: Vec<_> = type_change
+            .exports
+            .iter()
+            .map(|export| format_export(export))
+            .collect();
+        format!(
+            "Affects {} {}: {}.",
+            exports.len(),
+            if exports.len() == 1 {
+                "export"
+            } else {
+                "exports"
+            },
+            exports.join(", ")
+        )
+    }
 
-        // Look up the internal variant index.
-        let variant_idx = match symfile.records.get(name) {
-            Some(&variant_idx) => variant_idx,
-            None => panic!(
-                "Type '{}' is not known in file '{}'",
+    /// Writes a report about all changes in the Markdown format to the provided output stream,
+    /// computing diffs of the changed types using the specified options.
+    ///
+    /// The report starts with a table of the numbers of removed, added and changed exports and
+    /// changed types, followed by a section for each non-empty group. Each changed type has its own
+    /// subsection listing the affected exports and the descriptions of the change, followed by its
+    /// diff in a fenced code block. If labels are set in the options, the diff is preceded by
+    /// header lines as in [`Self::write_report_with()`].
+    pub fn write_markdown_report_with<W: Write>(
+        &self,
+        diff_options: &DiffOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+        let format_export = |name: &str| {
+            format!(
+                "`{}`{}",
                 name,
-                symfile.path.display()
-            ),
-        };
-
-        // Determine the output variant index for the symbol.
-        let remap_idx;
-        match output_types.entry(name) {
-            Occupied(mut active_entry) => {
-                let remap = active_entry.get_mut();
-                let remap_len = remap.len();
-                match remap.entry(variant_idx) {
-                    Occupied(remap_entry) => {
-                        remap_idx = *remap_entry.get();
-                    }
-                    Vacant(remap_entry) => {
-                        remap_idx = remap_len;
-                        remap_entry.insert(remap_idx);
-                    }
-                }
-            }
-            Vacant(active_entry) => {
-                remap_idx = 0;
-                active_entry.insert(HashMap::from([(variant_idx, remap_idx)]));
-            }
+                crate::markdown_escape(&self.kind_suffix(name))
+            )
         };
-        processed_entry.insert(remap_idx);
 
-        // Process recursively all types that the symbol references.
-        let variants = match self.types.get(name) {
-            Some(variants) => variants,
-            None => panic!("Type '{}' has a missing declaration", name),
-        };
+        let mut lines = vec![
+            "# kABI changes".to_string(),
+            String::new(),
+            "| Change | Count |".to_string(),
+            "| --- | ---: |".to_string(),
+        ];
+        for (title, count) in self.summary() {
+            lines.push(format!("| {} | {} |", title, count));
+        }
 
-        for token in &variants[variant_idx] {
-            match token {
-                Token::TypeRef(ref_name) => {
-                    self.consolidate_type(symfile, ref_name, output_types, processed_types)
-                }
-                Token::Atom(_word) => {}
+        for (names, title) in [
+            (&self.removed_exports, "Removed exports"),
+            (&self.added_exports, "Added exports"),
+        ] {
+            if names.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.push(format!("## {} ({})", title, names.len()));
+            lines.push(String::new());
+            for name in names {
+                lines.push(format!("- {}", format_export(name)));
             }
         }
-    }
 
-    /// Writes the corpus in the consolidated form into a specified file.
-    pub fn write_consolidated<P: AsRef<Path>>(&self, path: P) -> Result<(), crate::Error> {
-        let path = path.as_ref();
+        if !self.changed_types.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("## Changed types ({})", self.changed_types.len()));
+        }
+        for type_change in &self.changed_types {
+            lines.push(String::new());
+            lines.push(format!("### `{}`", type_change.name));
+            lines.push(String::new());
+            lines.push(Self::affected_exports(type_change, format_export));
+
+            if self.crc_only {
+                let crc = |words: &[String]| words.last().cloned().unwrap_or_default();
+                lines.push(String::new());
+                lines.push(format!(
+                    "The CRC has changed from `{}` to `{}`, structural differences are not \
+                     available.",
+                    crc(&type_change.old_tokens),
+                    crc(&type_change.new_tokens)
+                ));
+                continue;
+            }
 
-        // Open the output file.
-        let writer: Box<dyn Write> = if path == Path::new("-") {
-            Box::new(io::stdout())
-        } else {
-            match PathFile::create(path) {
-                Ok(file) => Box::new(file),
-                Err(err) => {
-                    return Err(crate::Error::new_io(
-                        &format!("Failed to create file '{}'", path.display()),
-                        err,
-                    ))
+            let descriptions = type_change.describe_changes();
+            if !descriptions.is_empty() {
+                lines.push(String::new());
+                for description in descriptions {
+                    lines.push(format!("- {}", crate::markdown_escape(&description)));
                 }
             }
-        };
+            if type_change.enum_value_change().is_none() {
+                let type_options = Self::type_diff_options(diff_options, type_change);
+                let mut diff = Vec::new();
+                type_change.write_diff_with(&type_options, &mut diff)?;
+                lines.push(String::new());
+                lines.push("```diff".to_string());
+                lines.extend(String::from_utf8_lossy(&diff).lines().map(str::to_string));
+                lines.push("```".to_string());
+            }
+        }
 
-        self.write_consolidated_buffer(writer)
+        for line in lines {
+            writeln!(writer, "{}", line).map_io_err(err_desc)?;
+        }
+        writer.flush().map_io_err(err_desc)
     }
 
-    /// Writes the corpus in the consolidated form to the provided output stream.
-    pub fn write_consolidated_buffer<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+    /// Writes a report about all changes as a standalone HTML document to the provided output
+    /// stream, computing diffs of the changed types using the specified options.
+    ///
+    /// The document has the same structure as the report written by
+    /// [`Self::write_markdown_report_with()`]. Removed and added lines of the diffs are
+    /// highlighted.
+    pub fn write_html_report_with<W: Write>(
+        &self,
+        diff_options: &DiffOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
         let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+        let format_export = |name: &str| {
+            format!(
+                "<code>{}</code>{}",
+                crate::html_escape(name),
+                crate::html_escape(&self.kind_suffix(name))
+            )
+        };
 
-        // Initialize output data. Variable output_types records all output symbols, file_types
-        // provides per-file information.
-        let mut output_types = ConsolidateOutputTypes::new();
-        let mut file_types = vec![ConsolidateFileTypes::new(); self.files.len()];
+        let mut lines: Vec<String> = [
+            "<!DOCTYPE html>",
+            "<html lang=\"en\">",
+            "<head>",
+            "<meta charset=\"utf-8\">",
+            "<title>kABI changes</title>",
+            "<style>",
+            ".del { color: #b31d28; }",
+            ".add { color: #22863a; }",
+            ".hunk { color: #6f42c1; }",
+            "</style>",
+            "</head>",
+            "<body>",
+            "<h1>kABI changes</h1>",
+            "<table>",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        for (title, count) in self.summary() {
+            lines.push(format!("<tr><th>{}</th><td>{}</td></tr>", title, count));
+        }
+        lines.push("</table>".to_string());
 
-        // Sort all files in the corpus by their path.
-        let mut file_indices = (0..self.files.len()).collect::<Vec<_>>();
-        file_indices.sort_by_key(|&i| &self.files[i].path);
+        for (names, title) in [
+            (&self.removed_exports, "Removed exports"),
+            (&self.added_exports, "Added exports"),
+        ] {
+            if names.is_empty() {
+                continue;
+            }
+            lines.push(format!("<h2>{} ({})</h2>", title, names.len()));
+            lines.push("<ul>".to_string());
+            for name in names {
+                lines.push(format!("<li>{}</li>", format_export(name)));
+            }
+            lines.push("</ul>".to_string());
+        }
 
-        // Process the sorted files and add their needed types to the output.
-        for &i in &file_indices {
-            let symfile = &self.files[i];
+        if !self.changed_types.is_empty() {
+            lines.push(format!(
+                "<h2>Changed types ({})</h2>",
+                self.changed_types.len()
+            ));
+        }
+        for type_change in &self.changed_types {
+            lines.push(format!(
+                "<h3><code>{}</code></h3>",
+                crate::html_escape(&type_change.name)
+            ));
+            lines.push(format!(
+                "<p>{}</p>",
+                Self::affected_exports(type_change, format_export)
+            ));
+
+            if self.crc_only {
+                let crc = |words: &[String]| words.last().cloned().unwrap_or_default();
+                lines.push(format!(
+                    "<p>The CRC has changed from <code>{}</code> to <code>{}</code>, structural \
+                     differences are not available.</p>",
+                    crate::html_escape(&crc(&type_change.old_tokens)),
+                    crate::html_escape(&crc(&type_change.new_tokens))
+                ));
+                continue;
+            }
 
-            // Collect sorted exports in the file which are the roots for consolidation.
-            let mut exports = Vec::new();
-            for name in symfile.records.keys() {
-                if is_export_name(name) {
-                    exports.push(name.as_str());
+            let descriptions = type_change.describe_changes();
+            if !descriptions.is_empty() {
+                lines.push("<ul>".to_string());
+                for description in descriptions {
+                    lines.push(format!("<li>{}</li>", crate::html_escape(&description)));
+                }
+                lines.push("</ul>".to_string());
+            }
+            if type_change.enum_value_change().is_none() {
+                let type_options = Self::type_diff_options(diff_options, type_change);
+                let mut diff = Vec::new();
+                type_change.write_diff_with(&type_options, &mut diff)?;
+                lines.push("<pre>".to_string());
+                for line in String::from_utf8_lossy(&diff).lines() {
+                    let class = match line.chars().next() {
+                        Some('-') => "del",
+                        Some('+') => "add",
+                        Some('@') => "hunk",
+                        _ => {
+                            lines.push(crate::html_escape(line));
+                            continue;
+                        }
+                    };
+                    lines.push(format!(
+                        "<span class=\"{}\">{}</span>",
+                        class,
+                        crate::html_escape(line)
+                    ));
                 }
+                lines.push("</pre>".to_string());
             }
-            exports.sort();
+        }
+        lines.push("</body>".to_string());
+        lines.push("</html>".to_string());
 
-            // Add the exported types and their needed types to the output.
-            let mut processed_types = ConsolidateFileTypes::new();
-            for name in &exports {
-                self.consolidate_type(symfile, name, &mut output_types, &mut processed_types);
+        for line in lines {
+            writeln!(writer, "{}", line).map_io_err(err_desc)?;
+        }
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes a report about all changes to the directory `dir` as separate files, computing diffs
+    /// of the changed types using the specified options.
+    ///
+    /// Each changed export is written to a file `<export>.diff` with the descriptions and diffs of
+    /// all changed types which affect it. The diffs are preceded by header lines with the labels
+    /// from the options, or `a` and `b` if not set, followed by a colon and the name of the changed
+    /// type. The file `index.txt` lists the removed and added exports and each changed export with
+    /// the name of its file and the changed types. The directory is created if it does not exist.
+    pub fn write_split_report_with<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        diff_options: &DiffOptions,
+    ) -> Result<(), crate::Error> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to create directory '{}'", crate::display_path(dir)),
+                err,
+            )
+        })?;
+        let create_file = |name: &str| {
+            let path = dir.join(name);
+            PathFile::create(&path).map(BufWriter::new).map_err(|err| {
+                crate::Error::new_io(
+                    &format!("Failed to create file '{}'", crate::display_path(&path)),
+                    err,
+                )
+            })
+        };
+        let err_desc = "Failed to write a comparison result";
+        let old_label = diff_options.old_label().unwrap_or("a");
+        let new_label = diff_options.new_label().unwrap_or("b");
+
+        // Group the changed types by the affected exports.
+        let mut exports: BTreeMap<&str, Vec<&TypeChange>> = BTreeMap::new();
+        for type_change in &self.changed_types {
+            for export in &type_change.exports {
+                exports.entry(export).or_default().push(type_change);
             }
-            file_types[i] = processed_types;
         }
 
-        // Go through all files and their output types. Check if a given type has only one variant
-        // in the output and mark it as such.
-        for file_types_item in &mut file_types {
-            for (name, remap_idx) in file_types_item {
-                let remap = output_types.get(name).unwrap();
-                if remap.len() == 1 {
-                    *remap_idx = usize::MAX;
-                }
+        let mut index = create_file("index.txt")?;
+        for (names, change) in [
+            (&self.removed_exports, "removed"),
+            (&self.added_exports, "added"),
+        ] {
+            for name in names {
+                writeln!(
+                    index,
+                    "Export '{}'{} has been {}",
+                    name,
+                    self.kind_suffix(name),
+                    change
+                )
+                .map_io_err(err_desc)?;
             }
         }
 
-        // Sort all output types and write them to the specified file.
-        let mut sorted_records = output_types.into_iter().collect::<Vec<_>>();
-        sorted_records.sort_by_key(|(name, _remap)| (is_export_name(name), *name));
-
-        let err_desc = "Failed to write a consolidated record";
-
-        for (name, remap) in sorted_records {
-            let variants = self.types.get(name).unwrap();
-            let mut sorted_remap = remap
+        for (export, type_changes) in exports {
+            let file_name = format!("{}.diff", export);
+            let type_names: Vec<_> = type_changes
                 .iter()
-                .map(|(&variant_idx, &remap_idx)| (remap_idx, variant_idx))
-                .collect::<Vec<_>>();
-            sorted_remap.sort();
-
-            let needs_suffix = sorted_remap.len() > 1;
-            for (remap_idx, variant_idx) in sorted_remap {
-                let tokens = &variants[variant_idx];
+                .map(|type_change| format!("'{}'", type_change.name))
+                .collect();
+            writeln!(
+                index,
+                "{}: export '{}'{} changed because of {}",
+                file_name,
+                export,
+                self.kind_suffix(export),
+                type_names.join(", ")
+            )
+            .map_io_err(err_desc)?;
 
-                if needs_suffix {
-                    write!(writer, "{}@{}", name, remap_idx).map_io_err(err_desc)?;
-                } else {
-                    write!(writer, "{}", name).map_io_err(err_desc)?;
+            let mut writer = create_file(&file_name)?;
+            for type_change in type_changes {
+                writeln!(writer, "Changed '{}':", type_change.name).map_io_err(err_desc)?;
+                for description in type_change.describe_changes() {
+                    writeln!(writer, "{}", description).map_io_err(err_desc)?;
                 }
-                for token in tokens {
-                    write!(writer, " {}", token.as_str()).map_io_err(err_desc)?;
+                if type_change.enum_value_change().is_none() {
+                    let type_options = diff_options.clone().labels(
+                        format!("{}:{}", old_label, type_change.name),
+                        format!("{}:{}", new_label, type_change.name),
+                    );
+                    type_change.write_diff_with(&type_options, writer.by_ref())?;
                 }
-                writeln!(writer).map_io_err(err_desc)?;
             }
+            writer.flush().map_io_err(err_desc)?;
         }
 
-        // Write file records.
-        for &i in &file_indices {
-            let symfile = &self.files[i];
+        index.flush().map_io_err(err_desc)
+    }
+}
 
-            // TODO Sorting, make same as above.
-            let mut sorted_types = file_types[i]
-                .iter()
-                .map(|(&name, &remap_idx)| (is_export_name(name), name, remap_idx))
-                .collect::<Vec<_>>();
-            sorted_types.sort();
+/// A badness score of a comparison result, computed by [`CompareResult::score()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Score {
+    total: u64,
+    modules: BTreeMap<PathBuf, u64>,
+    exports: BTreeMap<String, u64>,
+}
 
-            // Output the F# record in form `F#<filename> <type@variant>... <export>...`. Types with
-            // only one variant in the entire consolidated file can be skipped because they can be
-            // implicitly determined by a reader.
-            write!(writer, "F#{}", symfile.path.display()).map_io_err(err_desc)?;
-            for &(_, name, remap_idx) in &sorted_types {
-                if remap_idx != usize::MAX {
-                    write!(writer, " {}@{}", name, remap_idx).map_io_err(err_desc)?;
-                } else if is_export_name(name) {
-                    write!(writer, " {}", name).map_io_err(err_desc)?;
-                }
+impl Score {
+    /// Returns the total badness.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns an iterator over the paths of `.symtypes` files with a non-zero badness and their
+    /// scores, sorted by the path.
+    pub fn module_scores(&self) -> impl Iterator<Item = (&Path, u64)> {
+        self.modules
+            .iter()
+            .map(|(path, &badness)| (path.as_path(), badness))
+    }
+
+    /// Returns an iterator over the names of exports with a non-zero badness and their scores,
+    /// sorted by the name.
+    pub fn export_scores(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.exports
+            .iter()
+            .map(|(name, &badness)| (name.as_str(), badness))
+    }
+
+    /// Writes a human-readable report of the score to the provided output stream.
+    pub fn write_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a score report";
+
+        writeln!(writer, "Total badness: {}", self.total).map_io_err(err_desc)?;
+        if !self.modules.is_empty() {
+            writeln!(writer, "Badness by module:").map_io_err(err_desc)?;
+            for (path, badness) in self.module_scores() {
+                writeln!(writer, " {}: {}", path.display(), badness).map_io_err(err_desc)?;
             }
-            writeln!(writer).map_io_err(err_desc)?;
         }
-        Ok(())
+
+        writer.flush().map_io_err(err_desc)
     }
+}
 
-    /// Obtains tokens which describe a specified type name, in a given corpus and file.
-    fn get_type_tokens<'a>(symtypes: &'a SymCorpus, file: &SymFile, name: &str) -> &'a Tokens {
-        match file.records.get(name) {
-            Some(&variant_idx) => match symtypes.types.get(name) {
-                Some(variants) => &variants[variant_idx],
-                None => {
-                    panic!("Type '{}' has a missing declaration", name);
-                }
-            },
-            None => {
-                panic!(
-                    "Type '{}' is not known in file '{}'",
-                    name,
-                    file.path.display()
-                )
-            }
+/// Counts of changed exports and their badness, in total or for a single module.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ChangeCounts {
+    removed: usize,
+    added: usize,
+    changed: usize,
+    badness: u64,
+}
+
+impl ChangeCounts {
+    /// Records a change of the kind `kind` with the specified badness.
+    fn add(&mut self, kind: ChangeKind, badness: u64) {
+        match kind {
+            ChangeKind::Removed => self.removed += 1,
+            ChangeKind::Added => self.added += 1,
+            ChangeKind::Changed => self.changed += 1,
         }
+        self.badness = self.badness.saturating_add(badness);
     }
 
-    /// Compares the definition of the symbol `name` in (`corpus`, `file`) with its definition in
-    /// (`other_corpus`, `other_file`).
-    ///
-    /// If the immediate definition of the symbol differs between the two corpuses then it gets
-    /// added in `changes`. The `export` parameter identifies the top-level exported symbol affected
-    /// by the change.
+    /// Returns the numbers of removed, added and changed exports, and the badness.
+    fn values(&self) -> [u64; 4] {
+        [
+            self.removed as u64,
+            self.added as u64,
+            self.changed as u64,
+            self.badness,
+        ]
+    }
+}
+
+/// Metrics of a comparison result for tracking the kABI drift over time, computed by
+/// [`Metrics::new()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metrics {
+    num_exports: usize,
+    total: ChangeCounts,
+    modules: BTreeMap<PathBuf, ChangeCounts>,
+}
+
+impl Metrics {
+    /// Computes the metrics of the specified changes, as provided by
+    /// [`CompareResult::export_changes()`] or
+    /// [`crate::symvers::SymversCompareResult::export_changes()`], in a corpus with `num_exports`
+    /// exports. The badness of the changes is calculated according to `options`.
     ///
-    /// The specified symbol is added to `processed_types`, if not already present, and all its type
-    /// references get recursively processed in the same way.
-    fn compare_types<'a>(
-        (corpus, file): (&'a SymCorpus, &'a SymFile),
-        (other_corpus, other_file): (&'a SymCorpus, &'a SymFile),
-        name: &'a str,
-        export: &'a str,
-        changes: &Mutex<CompareChangedTypes<'a>>,
-        processed: &mut CompareFileTypes<'a>,
-    ) {
-        // See if the symbol was already processed.
-        if processed.get(name).is_some() {
-            return;
+    /// The changes are counted in total and for each module, which is the path defining the export
+    /// with its extension removed.
+    pub fn new<'a, I: IntoIterator<Item = (&'a str, Option<&'a Path>, ChangeKind)>>(
+        num_exports: usize,
+        changes: I,
+        options: &ScoreOptions,
+    ) -> Self {
+        let mut metrics = Self {
+            num_exports,
+            ..Self::default()
+        };
+        for (name, file, kind) in changes {
+            let badness = options.badness(name, file, kind);
+            metrics.total.add(kind, badness);
+            if let Some(file) = file {
+                metrics
+                    .modules
+                    .entry(file.with_extension(""))
+                    .or_default()
+                    .add(kind, badness);
+            }
         }
-        processed.insert(name); // [2]
+        metrics
+    }
 
-        // Look up how the symbol is defined in each corpus.
-        let tokens = Self::get_type_tokens(corpus, file, name);
-        let other_tokens = Self::get_type_tokens(other_corpus, other_file, name);
+    /// Returns the number of exports in the compared corpus.
+    pub fn num_exports(&self) -> usize {
+        self.num_exports
+    }
 
-        // Compare the immediate tokens.
-        let is_equal = tokens.len() == other_tokens.len()
-            && zip(tokens.iter(), other_tokens.iter())
-                .all(|(token, other_token)| token == other_token);
-        if !is_equal {
-            let mut changes = changes.lock().unwrap();
-            changes
-                .entry((name, tokens, other_tokens))
-                .or_default()
-                .push(export);
+    /// Returns the number of exports with the change `kind`.
+    pub fn num_changes(&self, kind: ChangeKind) -> usize {
+        match kind {
+            ChangeKind::Removed => self.total.removed,
+            ChangeKind::Added => self.total.added,
+            ChangeKind::Changed => self.total.changed,
         }
+    }
 
-        // Compare recursively same referenced types. This can be done trivially if the tokens are
-        // equal. If they are not, try hard (and slowly) to find any matching types.
-        if is_equal {
-            for token in tokens {
-                if let Token::TypeRef(ref_name) = token {
-                    Self::compare_types(
-                        (corpus, file),
-                        (other_corpus, other_file),
-                        ref_name.as_str(),
-                        export,
-                        changes,
-                        processed,
-                    );
-                }
-            }
-        } else {
-            for token in tokens {
-                if let Token::TypeRef(ref_name) = token {
-                    for other_token in other_tokens {
-                        if let Token::TypeRef(other_ref_name) = other_token {
-                            if ref_name == other_ref_name {
-                                Self::compare_types(
-                                    (corpus, file),
-                                    (other_corpus, other_file),
-                                    ref_name.as_str(),
-                                    export,
-                                    changes,
-                                    processed,
-                                );
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    /// Returns the total badness.
+    pub fn badness(&self) -> u64 {
+        self.total.badness
     }
 
-    /// Compares symbols in the `self` and `other_corpus`.
+    /// Writes the metrics to the provided output stream in the Prometheus text exposition format.
     ///
-    /// A human-readable report about all found changes is written to the provided output stream.
-    pub fn compare_with<W: Write>(
-        &self,
-        other_corpus: &SymCorpus,
-        writer: W,
-        num_workers: i32,
-    ) -> Result<(), crate::Error> {
+    /// The totals are written as the gauges `kabi_exports_total`, `kabi_exports_removed`,
+    /// `kabi_exports_added`, `kabi_exports_changed` and `kabi_badness_score`. The counts of each
+    /// module are written as the gauges `kabi_module_exports_removed`,
+    /// `kabi_module_exports_added`, `kabi_module_exports_changed` and `kabi_module_badness_score`
+    /// with the label `module`.
+    pub fn write_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
         let mut writer = BufWriter::new(writer);
-        let err_desc = "Failed to write a comparison result";
-
-        // Check for symbols in self but not in other_corpus, and vice versa.
-        for (exports_a, exports_b, change) in [
-            (&self.exports, &other_corpus.exports, "removed"),
-            (&other_corpus.exports, &self.exports, "added"),
-        ] {
-            for name in exports_a.keys() {
-                if !exports_b.contains_key(name) {
-                    writeln!(writer, "Export '{}' has been {}", name, change)
-                        .map_io_err(err_desc)?;
-                }
+        let err_desc = "Failed to write metrics";
+
+        let gauges = [
+            ("exports_removed", "Number of removed exports"),
+            ("exports_added", "Number of added exports"),
+            ("exports_changed", "Number of changed exports"),
+            ("badness_score", "Badness of the changes"),
+        ];
+
+        writeln!(writer, "# HELP kabi_exports_total Number of exports.").map_io_err(err_desc)?;
+        writeln!(writer, "# TYPE kabi_exports_total gauge").map_io_err(err_desc)?;
+        writeln!(writer, "kabi_exports_total {}", self.num_exports).map_io_err(err_desc)?;
+        for (i, (name, help)) in gauges.iter().enumerate() {
+            writeln!(writer, "# HELP kabi_{} {}.", name, help).map_io_err(err_desc)?;
+            writeln!(writer, "# TYPE kabi_{} gauge", name).map_io_err(err_desc)?;
+            writeln!(writer, "kabi_{} {}", name, self.total.values()[i]).map_io_err(err_desc)?;
+        }
+        for (i, (name, help)) in gauges.iter().enumerate() {
+            writeln!(writer, "# HELP kabi_module_{} {} by module.", name, help)
+                .map_io_err(err_desc)?;
+            writeln!(writer, "# TYPE kabi_module_{} gauge", name).map_io_err(err_desc)?;
+            for (path, counts) in &self.modules {
+                writeln!(
+                    writer,
+                    "kabi_module_{}{{module=\"{}\"}} {}",
+                    name,
+                    escape_label_value(&crate::display_path(path)),
+                    counts.values()[i]
+                )
+                .map_io_err(err_desc)?;
             }
         }
 
-        // Compare symbols that are in both corpuses.
-        let works: Vec<_> = self.exports.iter().collect();
-        let next_work_idx = AtomicUsize::new(0);
+        writer.flush().map_io_err(err_desc)
+    }
+}
 
-        let changes = Mutex::new(CompareChangedTypes::new());
+/// Escapes a label value in the Prometheus text exposition format.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
 
-        thread::scope(|s| {
-            for _ in 0..num_workers {
-                s.spawn(|| loop {
-                    let work_idx = next_work_idx.fetch_add(1, Ordering::Relaxed);
-                    if work_idx >= works.len() {
-                        break;
-                    }
-                    let (name, file_idx) = works[work_idx];
-
-                    let file = &self.files[*file_idx];
-                    if let Some(other_file_idx) = other_corpus.exports.get(name) {
-                        let other_file = &other_corpus.files[*other_file_idx];
-                        let mut processed = CompareFileTypes::new();
-                        Self::compare_types(
-                            (self, file),
-                            (other_corpus, other_file),
-                            name,
-                            name,
-                            &changes,
-                            &mut processed,
-                        );
-                    }
-                });
-            }
+/// An iterator over the words of a line of symtypes data, which are separated by ASCII whitespace.
+///
+/// The line is scanned as raw bytes. All separators are ASCII characters, so each word is sliced
+/// directly from the line and needs no further validation or allocation.
+struct Words<'a> {
+    line: &'a str,
+    pos: usize,
+}
+
+impl<'a> Words<'a> {
+    /// Creates an iterator over the words of `line`.
+    fn new(line: &'a str) -> Self {
+        Self { line, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let bytes = self.line.as_bytes();
+        let Some(len) = bytes[self.pos..]
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+        else {
+            self.pos = bytes.len();
+            return None;
+        };
+        let start = self.pos + len;
+        let end = bytes[start..]
+            .iter()
+            .position(u8::is_ascii_whitespace)
+            .map_or(bytes.len(), |len| start + len);
+        self.pos = end;
+        Some(&self.line[start..end])
+    }
+}
+
+/// Reads words from a given iterator and converts them to tokens in `tokens`, interning their
+/// strings in `strings`. Any previous content of `tokens` is discarded, which allows to reuse its
+/// allocation for each record.
+fn words_into_tokens<'a, I: Iterator<Item = &'a str>>(
+    words: &mut I,
+    strings: &mut Interner,
+    tokens: &mut Vec<Token>,
+) {
+    tokens.clear();
+    for word in words {
+        let id = strings.intern(word);
+        tokens.push(if is_export_name(word) {
+            Token::new_atom(id)
+        } else {
+            Token::new_typeref(id)
         });
+    }
+}
 
-        // Format and output collected changes.
-        let changes = changes.into_inner().unwrap(); // Get the inner HashMap.
-        let mut changes = changes.into_iter().collect::<Vec<_>>();
-        changes.iter_mut().for_each(|(_, exports)| exports.sort());
-        changes.sort();
+/// Computes the genksyms CRC of the export `name`. The `resolve` function provides the words of
+/// the definition of a type or export, as seen by the file of the export.
+///
+/// The calculation follows `expand_and_crc_sym()` in genksyms. Each word of the definition is
+/// followed by a space. A referenced type is expanded to its definition on its first occurrence,
+/// further occurrences are written as the name of the type, prefixed by `struct`, `union` or
+/// `enum` for these kinds. An unknown structure, union or enumeration is expanded as an incomplete
+/// definition.
+fn genksyms_crc<'a, F: Fn(&str) -> Option<Vec<&'a str>>>(name: &str, resolve: &F) -> u32 {
+    fn expand<'a, F: Fn(&str) -> Option<Vec<&'a str>>>(
+        words: &[&str],
+        resolve: &F,
+        expanded: &mut FxHashSet<String>,
+        data: &mut Vec<u8>,
+    ) {
+        for &word in words {
+            let prefix = match RecordKind::from_name(word) {
+                RecordKind::Typedef | RecordKind::EnumConstant => None,
+                RecordKind::Struct => Some("struct"),
+                RecordKind::Union => Some("union"),
+                RecordKind::Enum => Some("enum"),
+                RecordKind::Export | RecordKind::Other => {
+                    data.extend_from_slice(word.as_bytes());
+                    data.push(b' ');
+                    continue;
+                }
+            };
+            let base_name = &word[2..];
 
-        let mut add_separator = false;
-        for ((name, tokens, other_tokens), exports) in changes {
-            // Add an empty line to separate individual changes.
-            if add_separator {
-                writeln!(writer).map_io_err(err_desc)?;
-            } else {
-                add_separator = true;
+            if !expanded.insert(word.to_string()) {
+                if let Some(prefix) = prefix {
+                    data.extend_from_slice(prefix.as_bytes());
+                    data.push(b' ');
+                }
+                data.extend_from_slice(base_name.as_bytes());
+                data.push(b' ');
+                continue;
             }
 
-            writeln!(
-                writer,
-                "The following '{}' exports are different:",
-                exports.len()
-            )
-            .map_io_err(err_desc)?;
-            for export in exports {
-                writeln!(writer, " {}", export).map_io_err(err_desc)?;
+            match (resolve(word), prefix) {
+                (Some(ref_words), _) => expand(&ref_words, resolve, expanded, data),
+                (None, Some(prefix)) => expand(
+                    &[prefix, base_name, "{", "UNKNOWN", "}"],
+                    resolve,
+                    expanded,
+                    data,
+                ),
+                (None, None) => {
+                    data.extend_from_slice(base_name.as_bytes());
+                    data.push(b' ');
+                }
             }
-            writeln!(writer).map_io_err(err_desc)?;
-
-            writeln!(writer, "because of a changed '{}':", name).map_io_err(err_desc)?;
-            write_type_diff(tokens, other_tokens, writer.by_ref())?;
         }
+    }
 
-        Ok(())
+    let mut data = Vec::new();
+    if let Some(words) = resolve(name) {
+        expand(&words, resolve, &mut FxHashSet::default(), &mut data);
     }
+    crc32(&data)
 }
 
-/// Reads data from a specified reader and returns its content as a [`Vec`] of [`String`] lines.
-fn read_lines<R: Read>(reader: R) -> io::Result<Vec<String>> {
-    let reader = BufReader::new(reader);
-    let mut lines = Vec::new();
-    for maybe_line in reader.lines() {
-        match maybe_line {
-            Ok(line) => lines.push(line),
-            Err(err) => return Err(err),
-        };
+/// Computes the CRC-32 checksum of `data`, using the reflected polynomial `0xedb88320` as zlib
+/// and genksyms.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffff_u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
     }
-    Ok(lines)
+    crc ^ 0xffffffff
 }
 
-/// Reads words from a given iterator and converts them to `Tokens`.
-fn words_into_tokens<'a, I: Iterator<Item = &'a str>>(words: &mut I) -> Tokens {
-    let mut tokens = Tokens::new();
-    for word in words {
-        let mut is_typeref = false;
-        if let Some(ch) = word.chars().nth(1) {
-            if ch == '#' {
-                is_typeref = true;
-            }
+/// Returns whether the specified path names a tar archive, optionally compressed in one of the
+/// supported formats.
+#[cfg(feature = "tar")]
+fn is_tar_archive(path: &Path) -> bool {
+    let path = if crate::Compression::from_path(path) != crate::Compression::None {
+        Path::new(path.file_stem().unwrap_or_default())
+    } else {
+        path
+    };
+    path.extension().is_some_and(|ext| ext == "tar")
+}
+
+/// Parses the value of an enumerator constant from the words of its `E#` record. Returns [`None`] if
+/// the value is not a plain decimal, hexadecimal or octal integer, optionally negated.
+fn parse_enum_value<S: AsRef<str>>(words: &[S]) -> Option<i64> {
+    let (negative, word) = match words {
+        [word] => (false, word.as_ref()),
+        [minus, word] if minus.as_ref() == "-" => (true, word.as_ref()),
+        _ => return None,
+    };
+    let (negative, word) = match word.strip_prefix('-') {
+        Some(word) => (!negative, word),
+        None => (negative, word),
+    };
+    // Drop any C integer suffix, such as in 1UL.
+    let word = word.trim_end_matches(['u', 'U', 'l', 'L']);
+    let value = if let Some(hex) = word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if word.len() > 1 && word.starts_with('0') {
+        i64::from_str_radix(&word[1..], 8).ok()?
+    } else {
+        word.parse::<i64>().ok()?
+    };
+    Some(if negative {
+        value.checked_neg()?
+    } else {
+        value
+    })
+}
+
+/// Normalizes the specified path lexically by removing `.` components and resolving `..`
+/// components against the preceding normal ones.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut res = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match res.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    res.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => res.push(".."),
+            },
+            _ => res.push(component),
         }
-        tokens.push(if is_typeref {
-            Token::new_typeref(word)
-        } else {
-            Token::new_atom(word)
-        });
     }
-    tokens
+    res
 }
 
 /// Returns whether the specified type name is an export definition, as opposed to a `<X>#<foo>`
@@ -1079,6 +6371,83 @@ fn is_export_name(type_name: &str) -> bool {
     }
 }
 
+/// Returns the byte offset of `word` on `line`. The `word` must be a slice of `line`.
+fn word_offset(line: &str, word: &str) -> usize {
+    let offset = word.as_ptr() as usize - line.as_ptr() as usize;
+    assert!(offset + word.len() <= line.len());
+    offset
+}
+
+/// Reconstructs the line of a type record from its name and tokens, for use in error messages.
+/// Returns the line and the byte offset of each token on it.
+fn format_record_line(name: &str, tokens: &Tokens, strings: &Interner) -> (String, Vec<usize>) {
+    let mut line = name.to_string();
+    let mut offsets = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        line.push(' ');
+        offsets.push(line.len());
+        line.push_str(strings.resolve(token.id()));
+    }
+    (line, offsets)
+}
+
+/// Returns whether the specified word is a type prefix without a name, such as `s#`.
+fn is_bare_prefix(word: &str) -> bool {
+    is_bare_prefix_bytes(word.as_bytes())
+}
+
+/// Returns whether the specified bytes are a type prefix without a name, such as `s#`.
+fn is_bare_prefix_bytes(word: &[u8]) -> bool {
+    matches!(word, [b't' | b'e' | b's' | b'u' | b'E', b'#'])
+}
+
+/// Returns whether the specified line of symtypes data contains a type prefix separated from its
+/// name, which is a quirk of the legacy dialect.
+///
+/// The check is done for every line when the dialect is detected automatically. Instead of
+/// splitting the line into words, only the `#` characters are looked up and the bytes around each
+/// are examined.
+fn has_split_prefix(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    let mut pos = 0;
+    while let Some(len) = bytes[pos..].iter().position(|&byte| byte == b'#') {
+        let end = pos + len + 1;
+        pos = end;
+        if end < 2 || !is_bare_prefix_bytes(&bytes[end - 2..end]) {
+            continue;
+        }
+        let at_word_start = end == 2 || bytes[end - 3].is_ascii_whitespace();
+        let at_word_end = bytes.get(end).is_some_and(u8::is_ascii_whitespace);
+        if at_word_start
+            && at_word_end
+            && bytes[end..].iter().any(|byte| !byte.is_ascii_whitespace())
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Joins each type prefix separated from its name in the specified line of symtypes data with the
+/// name, for instance, `s# foo` becomes `s#foo`. The words of the result are separated by single
+/// spaces.
+fn join_split_prefixes(line: &str) -> String {
+    let mut res = String::with_capacity(line.len());
+    let mut words = Words::new(line);
+    while let Some(word) = words.next() {
+        if !res.is_empty() {
+            res.push(' ');
+        }
+        res.push_str(word);
+        if is_bare_prefix(word) {
+            if let Some(name) = words.next() {
+                res.push_str(name);
+            }
+        }
+    }
+    res
+}
+
 /// Splits the specified type name into a tuple of two string slices, with the first one being the
 /// base name and the second one containing the variant name/index (or an empty string if no variant
 /// was present).
@@ -1089,18 +6458,46 @@ fn split_type_name(type_name: &str) -> (&str, &str) {
     }
 }
 
-/// Processes tokens describing a type and produces its pretty-formatted version as a [`Vec`] of
+/// Returns the name of an entry of an `F#` record, without the CRC which can follow an export.
+fn strip_entry_crc(entry: &str) -> &str {
+    entry.split_once('=').map_or(entry, |(name, _)| name)
+}
+
+/// Processes words describing a type and produces its pretty-formatted version as a [`Vec`] of
 /// [`String`] lines.
-fn pretty_format_type(tokens: &Tokens) -> Vec<String> {
+///
+/// This is the format used by reports of [`SymCorpus::compare_with()`]. Nested blocks are indented
+/// by tabs and no line wrapping is performed.
+///
+/// ```
+/// use suse_kabi_tools::sym::pretty_format_type;
+///
+/// let pretty = pretty_format_type(&["struct", "foo", "{", "int", "a", ";", "}"]);
+/// assert_eq!(pretty, ["struct foo {", "\tint a;", "}"]);
+/// ```
+pub fn pretty_format_type<S: AsRef<str>>(words: &[S]) -> Vec<String> {
+    pretty_format_type_with(words, &FormatOptions::default())
+}
+
+/// Returns the display width of `line`, with a tab counted as 8 columns.
+fn line_width(line: &str) -> usize {
+    line.chars().map(|ch| if ch == '\t' { 8 } else { 1 }).sum()
+}
+
+/// Processes words describing a type and produces its pretty-formatted version as a [`Vec`] of
+/// [`String`] lines, using the specified options.
+pub fn pretty_format_type_with<S: AsRef<str>>(words: &[S], options: &FormatOptions) -> Vec<String> {
     // Iterate over all tokens and produce the formatted output.
     let mut res = Vec::new();
     let mut indent: usize = 0;
 
     let mut line = String::new();
-    for token in tokens {
+    for word in words {
+        let word = word.as_ref();
+
         // Handle the closing bracket and parenthesis early, they end any prior line and reduce
         // indentation.
-        if token.as_str() == "}" || token.as_str() == ")" {
+        if word == "}" || word == ")" {
             if !line.is_empty() {
                 res.push(line);
             }
@@ -1109,27 +6506,38 @@ fn pretty_format_type(tokens: &Tokens) -> Vec<String> {
         }
 
         // Insert any newline indentation.
-        let is_first = line.is_empty();
+        let mut is_first = line.is_empty();
         if is_first {
-            for _ in 0..indent {
-                line.push('\t');
-            }
+            options.push_indent(&mut line, indent);
+        }
+
+        // Wrap the line if appending the word would make it too long.
+        if options.line_width > 0
+            && !is_first
+            && word != ";"
+            && word != ","
+            && line_width(&line) + 1 + word.len() > options.line_width
+        {
+            res.push(line);
+            line = String::new();
+            options.push_indent(&mut line, indent + 1);
+            is_first = true;
         }
 
         // Check if the token is special and append it appropriately to the output.
-        match token.as_str() {
+        match word {
             "{" | "(" => {
                 if !is_first {
                     line.push(' ');
                 }
-                line.push_str(token.as_str());
+                line.push_str(word);
                 res.push(line);
                 indent = indent.saturating_add(1);
 
                 line = String::new();
             }
             "}" | ")" => {
-                line.push_str(token.as_str());
+                line.push_str(word);
             }
             ";" => {
                 line.push(';');
@@ -1147,7 +6555,7 @@ fn pretty_format_type(tokens: &Tokens) -> Vec<String> {
                 if !is_first {
                     line.push(' ');
                 }
-                line.push_str(token.as_str());
+                line.push_str(word);
             }
         };
     }
@@ -1160,13 +6568,46 @@ fn pretty_format_type(tokens: &Tokens) -> Vec<String> {
 }
 
 /// Formats a unified diff between two supposedly different types and writes it to the provided
-/// output stream.
-fn write_type_diff<W: Write>(
-    tokens: &Tokens,
-    other_tokens: &Tokens,
+/// output stream. Lines declaring the same member are aligned with each other.
+fn write_type_diff<S: AsRef<str>, W: Write>(
+    words: &[S],
+    other_words: &[S],
+    options: &DiffOptions,
     writer: W,
 ) -> Result<(), crate::Error> {
-    let pretty = pretty_format_type(tokens);
-    let other_pretty = pretty_format_type(other_tokens);
-    crate::diff::unified(&pretty, &other_pretty, writer)
+    let pretty = pretty_format_type(words);
+    let other_pretty = pretty_format_type(other_words);
+    crate::diff::write_anchored_with(
+        &pretty,
+        &other_pretty,
+        |line: &String| member_line_key(line),
+        options,
+        writer,
+    )
+}
+
+/// Returns the indentation and the member name of a pretty-formatted line declaring a structure
+/// member, for instance, `("\t", "a")` for `\tint a [ 4 ];`, or [`None`] if the line is not
+/// a member declaration.
+fn member_line_key(line: &str) -> Option<(&str, &str)> {
+    let decl = line.strip_suffix(';')?;
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let mut words: Vec<_> = decl.split_whitespace().collect();
+
+    // Drop any bit-field width and array dimensions.
+    if let Some(colon) = words.iter().position(|&word| word == ":") {
+        words.truncate(colon);
+    }
+    while words.last() == Some(&"]") {
+        let open = words.iter().rposition(|&word| word == "[")?;
+        words.truncate(open);
+    }
+
+    // Check that the declaration has a type and ends with an identifier.
+    let name = *words.last()?;
+    let is_ident = name.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_')
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+    (words.len() > 1 && is_ident).then_some((indent, name))
 }
@@ -2,12 +2,13 @@
 // SPDX-License-Identifier: GPL-2.0-or-later
 
 use super::*;
-use crate::assert_ok;
+use crate::{assert_ok, ProgressPhase, Warning};
+use std::thread;
 
 macro_rules! assert_parse_err {
     ($result:expr, $exp_desc:expr) => {
         match $result {
-            Err(crate::Error::Parse(actual_desc)) => assert_eq!(actual_desc, $exp_desc),
+            Err(crate::Error::Parse(err)) => assert_eq!(err.to_string(), $exp_desc),
             result => panic!(
                 "assertion failed: {:?} is not of type Err(crate::Error::Parse())",
                 result
@@ -16,6 +17,68 @@ macro_rules! assert_parse_err {
     };
 }
 
+/// A diagnostics receiver which records all warnings.
+#[derive(Default)]
+struct RecordingDiagnostics {
+    warnings: Mutex<Vec<Warning>>,
+}
+
+impl Diagnostics for RecordingDiagnostics {
+    fn warning(&self, warning: Warning) {
+        self.warnings.lock().unwrap().push(warning);
+    }
+}
+
+impl RecordingDiagnostics {
+    /// Returns the kinds and messages of all recorded warnings.
+    fn take(&self) -> Vec<(WarningKind, String)> {
+        self.warnings
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|warning| (warning.kind(), warning.message().to_string()))
+            .collect()
+    }
+}
+
+/// Returns an anonymous read-only memory mapping with the specified data, as if a file with the
+/// data was mapped.
+#[cfg(feature = "mmap")]
+fn map_data(data: &[u8]) -> memmap2::Mmap {
+    let mut map = memmap2::MmapMut::map_anon(data.len()).unwrap();
+    map.copy_from_slice(data);
+    map.make_read_only().unwrap()
+}
+
+#[test]
+fn types_merge_variants() {
+    // Check that merging type definitions finds existing variants, including after the index of
+    // variants is invalidated.
+    let mut strings = Interner::default();
+    let name = strings.intern("s#foo");
+    let variant = |word: &str, strings: &mut Interner| {
+        vec![
+            Token::new_atom(strings.intern("struct")),
+            Token::new_atom(strings.intern(word)),
+        ]
+    };
+    let mut types = Types::default();
+    let tokens: Vec<_> = (0..32)
+        .map(|i| variant(&format!("foo{}", i), &mut strings))
+        .collect();
+    for (i, tokens) in tokens.iter().enumerate() {
+        assert_eq!(types.merge(name, tokens), VariantIdx::new(i));
+    }
+    assert_eq!(types.merge(name, &tokens[7]), VariantIdx::new(7));
+    types.invalidate_index();
+    assert_eq!(types.merge(name, &tokens[31]), VariantIdx::new(31));
+    assert_eq!(
+        types.merge(name, &variant("bar", &mut strings)),
+        VariantIdx::new(32)
+    );
+    assert_eq!(types.get(name).unwrap().len(), 33);
+}
+
 #[test]
 fn read_empty_record() {
     // Check that empty records are rejected when reading a file.
@@ -44,7 +107,14 @@ fn read_duplicate_type_record() {
         )
         .as_bytes(),
     );
-    assert_parse_err!(result, "test.symtypes:2: Duplicate record 's#test'");
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.symtypes:2:1: Duplicate record 's#test'\n",
+            "  s#test struct test { int b ; }\n",
+            "  ^~~~~~", //
+        )
+    );
 }
 
 #[test]
@@ -63,7 +133,11 @@ fn read_duplicate_file_record() {
     );
     assert_parse_err!(
         result,
-        "test.symtypes:4: Duplicate record 'F#test.symtypes'"
+        concat!(
+            "test.symtypes:4:1: Duplicate record 'F#test.symtypes'\n",
+            "  F#test.symtypes baz\n",
+            "  ^~~~~~~~~~~~~~~", //
+        )
     );
 }
 
@@ -79,7 +153,14 @@ fn read_invalid_file_record_ref() {
         )
         .as_bytes(),
     );
-    assert_parse_err!(result, "test.symtypes:1: Type 'bar' is not known");
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.symtypes:1:17: Type 'bar' is not known\n",
+            "  F#test.symtypes bar\n",
+            "                  ^~~", //
+        )
+    );
 }
 
 #[test]
@@ -94,7 +175,14 @@ fn read_invalid_file_record_ref2() {
         )
         .as_bytes(),
     );
-    assert_parse_err!(result, "test.symtypes:1: Type 'bar@0' is not known");
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.symtypes:1:17: Type 'bar@0' is not known\n",
+            "  F#test.symtypes bar@0\n",
+            "                  ^~~~~", //
+        )
+    );
 }
 
 #[test]
@@ -111,40 +199,48 @@ fn read_invalid_file_record_ref3() {
         )
         .as_bytes(),
     );
-    assert_parse_err!(result, "test.symtypes:3: Type 'bar@1' is not known");
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.symtypes:3:18: Type 'bar@1' is not known\n",
+            "  F#test2.symtypes bar@1\n",
+            "                   ^~~~~", //
+        )
+    );
 }
 
 #[test]
-fn read_duplicate_type_export() {
-    // Check that two exports with the same name in different files get rejected.
+fn read_variant_in_single_file() {
+    // Check that a type record with a variant suffix is rejected when reading a file which is not
+    // consolidated.
     let mut syms = SymCorpus::new();
     let result = syms.load_buffer(
         "test.symtypes",
         concat!(
-            "foo int foo ( )\n", //
+            "s#foo@0 struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
         )
         .as_bytes(),
     );
-    assert_ok!(result);
-    let result = syms.load_buffer(
-        "test2.symtypes",
+    assert_parse_err!(
+        result,
         concat!(
-            "foo int foo ( )", //
+            "test.symtypes:1:1: Type 's#foo@0' specifies a variant but the file is not consolidated\n",
+            "  s#foo@0 struct foo { int a ; }\n",
+            "  ^~~~~~~", //
         )
-        .as_bytes(),
     );
-    assert_parse_err!(result, "test2.symtypes:1: Export 'foo' is duplicate. Previous occurrence found in 'test.symtypes'.");
 }
 
 #[test]
-fn read_write_basic() {
-    // Check reading of a single file and writing the consolidated output.
+fn read_crlf_lines() {
+    // Check that lines terminated by CRLF are correctly read.
     let mut syms = SymCorpus::new();
     let result = syms.load_buffer(
         "test.symtypes",
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "bar int bar ( s#foo )\n", //
+            "s#foo struct foo { int a ; }\r\n",
+            "bar int bar ( s#foo )\r\n", //
         )
         .as_bytes(),
     );
@@ -163,15 +259,13 @@ fn read_write_basic() {
 }
 
 #[test]
-fn read_write_shared_struct() {
-    // Check that a structure declaration shared by two files appears only once in the consolidated
-    // output.
+fn read_duplicate_type_export() {
+    // Check that two exports with the same name in different files get rejected.
     let mut syms = SymCorpus::new();
     let result = syms.load_buffer(
         "test.symtypes",
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "bar int bar ( s#foo )\n", //
+            "foo int foo ( )\n", //
         )
         .as_bytes(),
     );
@@ -179,258 +273,3150 @@ fn read_write_shared_struct() {
     let result = syms.load_buffer(
         "test2.symtypes",
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "baz int baz ( s#foo )\n", //
+            "foo int foo ( )", //
         )
         .as_bytes(),
     );
-    assert_ok!(result);
-    let mut out = Vec::new();
-    let result = syms.write_consolidated_buffer(&mut out);
-    assert_ok!(result);
-    assert_eq!(
-        String::from_utf8(out).unwrap(),
+    assert_parse_err!(result, "test2.symtypes:1: Export 'foo' is duplicate. Previous occurrence found in 'test.symtypes'.");
+}
+
+#[test]
+fn read_lenient_empty_record() {
+    // Check that empty records are skipped when reading a file in the lenient mode.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "bar int bar ( s#foo )\n",
-            "baz int baz ( s#foo )\n",
-            "F#test.symtypes bar\n",
-            "F#test2.symtypes baz\n", //
+            "s#test struct test { }\n",
+            "\n",
+            "s#test2 struct test2 { }\n", //
         )
+        .as_bytes(),
+        &LoadOptions::new().lenient(true),
     );
+    assert_ok!(result);
+    assert_eq!(syms.files[0].records.len(), 2);
 }
 
 #[test]
-fn read_write_differing_struct() {
-    // Check that a structure declaration different in two files appears in all variants in the
-    // consolidated output and they are correctly referenced by the F# entries.
+fn read_lenient_duplicate_record() {
+    // Check that duplicate records are skipped when reading a file in the lenient mode, keeping the
+    // first definition.
     let mut syms = SymCorpus::new();
-    let result = syms.load_buffer(
+    let result = syms.load_buffer_with(
         "test.symtypes",
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "bar int bar ( s#foo )\n", //
+            "s#test struct test { int a ; }\n",
+            "s#test struct test { int b ; }\n", //
         )
         .as_bytes(),
+        &LoadOptions::new().lenient(true),
     );
     assert_ok!(result);
-    let result = syms.load_buffer(
-        "test2.symtypes",
+    assert_eq!(
+        syms.types
+            .get(syms.strings.get("s#test").unwrap())
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn read_lenient_warnings() {
+    // Check that records skipped in the lenient mode are reported as warnings.
+    let diagnostics = Arc::new(RecordingDiagnostics::default());
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
         concat!(
-            "s#foo struct foo { UNKNOWN }\n",
-            "baz int baz ( s#foo )\n", //
+            "s#test struct test { int a ; }\n",
+            "\n",
+            "s#test struct test { int b ; }\n", //
         )
         .as_bytes(),
+        &LoadOptions::new()
+            .lenient(true)
+            .diagnostics(diagnostics.clone()),
     );
     assert_ok!(result);
-    let mut out = Vec::new();
-    let result = syms.write_consolidated_buffer(&mut out);
-    assert_ok!(result);
     assert_eq!(
-        String::from_utf8(out).unwrap(),
-        concat!(
-            "s#foo@0 struct foo { int a ; }\n",
-            "s#foo@1 struct foo { UNKNOWN }\n",
-            "bar int bar ( s#foo )\n",
-            "baz int baz ( s#foo )\n",
-            "F#test.symtypes s#foo@0 bar\n",
-            "F#test2.symtypes s#foo@1 baz\n", //
-        )
+        diagnostics.take(),
+        [
+            (
+                WarningKind::SkippedRecord,
+                "test.symtypes:2: Skipping an empty record".to_string()
+            ),
+            (
+                WarningKind::SkippedRecord,
+                "test.symtypes:3: Skipping a duplicate record 's#test'".to_string()
+            ),
+        ]
     );
 }
 
 #[test]
-fn compare_identical() {
-    // Check that the comparison of two identical corpuses shows no differences.
+fn read_normalized_paths() {
+    // Check that the paths of loaded files are normalized according to the options.
+    let options = LoadOptions::new()
+        .normalize_paths(true)
+        .strip_prefix("./build")
+        .lowercase_paths(true);
     let mut syms = SymCorpus::new();
-    let result = syms.load_buffer(
-        "a/test.symtypes",
+    let result = syms.load_buffer_with(
+        "build/./Foo/../Bar/test.symtypes",
         concat!(
             "bar int bar ( )\n", //
         )
         .as_bytes(),
+        &options,
     );
     assert_ok!(result);
-    let mut syms2 = SymCorpus::new();
-    let result = syms2.load_buffer(
-        "b/test.symtypes",
+    let result = syms.load_buffer_with(
+        "consolidated.symtypes",
         concat!(
-            "bar int bar ( )\n", //
+            "baz int baz ( )\n",
+            "F#build/Baz/./test.symtypes baz\n", //
         )
         .as_bytes(),
+        &options,
     );
     assert_ok!(result);
-    let mut out = Vec::new();
-    let result = syms.compare_with(&syms2, &mut out, 1);
-    assert_ok!(result);
-    assert_eq!(
-        String::from_utf8(out).unwrap(),
+    let result = syms.load_buffer_with(
+        "../other/test.symtypes",
         concat!(
-            "", //
+            "qux int qux ( )\n", //
         )
+        .as_bytes(),
+        &options,
+    );
+    assert_ok!(result);
+    assert_eq!(
+        syms.file_paths(),
+        [
+            Path::new("../other/test.symtypes"),
+            Path::new("bar/test.symtypes"),
+            Path::new("baz/test.symtypes")
+        ]
+    );
+    assert_eq!(
+        syms.get_export("bar").unwrap().file(),
+        Path::new("bar/test.symtypes")
     );
 }
 
 #[test]
-fn compare_added_export() {
-    // Check that the comparison of two corpuses reports any newly added export.
+fn read_legacy_dialect() {
+    // Check that type prefixes separated from their names by older genksyms versions are joined
+    // with the names, unless the current dialect is requested.
+    let legacy_data = concat!(
+        "s# foo struct foo { int a ; }\n",
+        "bar int bar ( s# foo * )\n", //
+    );
     let mut syms = SymCorpus::new();
     let result = syms.load_buffer(
-        "a/test.symtypes",
+        "b/test.symtypes",
         concat!(
-            "bar int bar ( )\n", //
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo * )\n", //
         )
         .as_bytes(),
     );
     assert_ok!(result);
+
+    for input_dialect in [InputDialect::Auto, InputDialect::Legacy] {
+        let mut syms2 = SymCorpus::new();
+        let result = syms2.load_buffer_with(
+            "a/test.symtypes",
+            legacy_data.as_bytes(),
+            &LoadOptions::new().input_dialect(input_dialect),
+        );
+        assert_ok!(result);
+        assert!(syms2.abi_equal(&syms));
+    }
+
     let mut syms2 = SymCorpus::new();
-    let result = syms2.load_buffer(
-        "b/test.symtypes",
-        concat!(
-            "bar int bar ( )\n",
-            "baz int baz ( )\n", //
-        )
-        .as_bytes(),
+    let result = syms2.load_buffer_with(
+        "a/test.symtypes",
+        legacy_data.as_bytes(),
+        &LoadOptions::new().input_dialect(InputDialect::Current),
     );
     assert_ok!(result);
-    let mut out = Vec::new();
-    let result = syms.compare_with(&syms2, &mut out, 1);
-    assert_ok!(result);
+    assert!(!syms2.abi_equal(&syms));
+}
+
+#[test]
+fn split_words() {
+    // Check that lines are split into words at any ASCII whitespace.
+    let words: Vec<_> = Words::new("  s#foo\tstruct  foo {\r\n").collect();
+    assert_eq!(words, ["s#foo", "struct", "foo", "{"]);
+    assert_eq!(Words::new("").next(), None);
+    assert_eq!(Words::new(" \t ").next(), None);
+    let words: Vec<_> = Words::new("bar int bar ( const char * \"é\" )").collect();
     assert_eq!(
-        String::from_utf8(out).unwrap(),
-        concat!(
-            "Export 'baz' has been added\n", //
-        )
+        words,
+        ["bar", "int", "bar", "(", "const", "char", "*", "\"é\"", ")"]
     );
 }
 
 #[test]
-fn compare_removed_export() {
-    // Check that the comparison of two corpuses reports any removed export.
+fn detect_split_prefix() {
+    // Check that only a type prefix separated from a following name is detected as the legacy
+    // dialect.
+    assert!(has_split_prefix("s# foo struct foo { int a ; }"));
+    assert!(has_split_prefix("bar int bar ( E# FOO )"));
+    assert!(has_split_prefix("bar int bar\tt#\tu32"));
+    assert!(!has_split_prefix("s#foo struct foo { int a ; }"));
+    assert!(!has_split_prefix("bar int bar ( xs# foo )"));
+    assert!(!has_split_prefix("bar int bar ( x# foo )"));
+    assert!(!has_split_prefix("bar int bar ( s#"));
+    assert!(!has_split_prefix("bar int bar ( s# "));
+    assert!(!has_split_prefix("é# foo #"));
+}
+
+#[test]
+fn read_duplicate_file_warning() {
+    // Check that loading a file with the same path multiple times is reported as a warning.
+    let diagnostics = Arc::new(RecordingDiagnostics::default());
+    let options = LoadOptions::new().diagnostics(diagnostics.clone());
     let mut syms = SymCorpus::new();
-    let result = syms.load_buffer(
-        "a/test.symtypes",
+    let result = syms.load_buffer_with(
+        "test.symtypes",
         concat!(
-            "bar int bar ( )\n",
-            "baz int baz ( )\n", //
+            "s#foo struct foo { int a ; }\n", //
         )
         .as_bytes(),
+        &options,
     );
     assert_ok!(result);
-    let mut syms2 = SymCorpus::new();
-    let result = syms2.load_buffer(
-        "b/test.symtypes",
+    assert_eq!(diagnostics.take(), []);
+    let result = syms.load_buffer_with(
+        "test.symtypes",
         concat!(
-            "baz int baz ( )\n", //
+            "s#foo struct foo { int a ; }\n", //
         )
         .as_bytes(),
+        &options,
     );
     assert_ok!(result);
-    let mut out = Vec::new();
-    let result = syms.compare_with(&syms2, &mut out, 1);
-    assert_ok!(result);
     assert_eq!(
-        String::from_utf8(out).unwrap(),
-        concat!(
-            "Export 'bar' has been removed\n", //
-        )
+        diagnostics.take(),
+        [(
+            WarningKind::DuplicateFile,
+            "File 'test.symtypes' is loaded multiple times".to_string()
+        )]
     );
 }
 
 #[test]
-fn compare_changed_type() {
-    // Check that the comparison of two corpuses reports changed types and affected exports.
+fn read_duplicate_type_export_keep_first() {
+    // Check that a duplicate export is ignored when the policy is set to keep the first one.
+    let options = LoadOptions::new().duplicate_exports(DuplicateExports::KeepFirst);
     let mut syms = SymCorpus::new();
-    let result = syms.load_buffer(
-        "a/test.symtypes",
+    let result = syms.load_buffer_with(
+        "test.symtypes",
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "bar int bar ( s#foo )\n", //
+            "foo int foo ( )\n", //
         )
         .as_bytes(),
+        &options,
     );
     assert_ok!(result);
-    let mut syms2 = SymCorpus::new();
-    let result = syms2.load_buffer(
-        "b/test.symtypes",
+    let result = syms.load_buffer_with(
+        "test2.symtypes",
         concat!(
-            "s#foo struct foo { int a ; int b ; }\n",
-            "bar int bar ( s#foo )\n", //
+            "foo int foo ( )", //
         )
         .as_bytes(),
+        &options,
     );
     assert_ok!(result);
-    let mut out = Vec::new();
-    let result = syms.compare_with(&syms2, &mut out, 1);
-    assert_ok!(result);
-    assert_eq!(
-        String::from_utf8(out).unwrap(),
+    assert_eq!(syms.exports[&syms.strings.get("foo").unwrap()], FileIdx(0));
+}
+
+#[test]
+fn read_validate_refs() {
+    // Check that a reference to an undefined type is rejected by default.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
         concat!(
-            "The following '1' exports are different:\n",
-            " bar\n",
-            "\n",
-            "because of a changed 's#foo':\n",
-            "@@ -1,3 +1,4 @@\n",
-            " struct foo {\n",
-            " \tint a;\n",
-            "+\tint b;\n",
-            " }\n", //
+            "s#foo struct foo { t#bar a ; }\n",
+            "baz int baz ( s#foo )\n", //
+        )
+        .as_bytes(),
+        &LoadOptions::new(),
+    );
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.symtypes:1:20: Type 't#bar' is not defined\n",
+            "  s#foo struct foo { t#bar a ; }\n",
+            "                     ^~~~~", //
         )
     );
 }
 
 #[test]
-fn compare_changed_nested_type() {
-    // Check that the comparison of two corpuses reports also changes in subtypes even if the parent
-    // type itself is modified, as long as each subtype is referenced by the parent type in both
-    // inputs.
+fn read_parse_error_fields() {
+    // Check that a parse error provides the location and kind of the problem.
     let mut syms = SymCorpus::new();
     let result = syms.load_buffer(
-        "a/test.symtypes",
+        "test.symtypes",
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "bar int bar ( int a , s#foo )\n", //
+            "s#foo struct foo { t#bar a ; }\n", //
         )
         .as_bytes(),
     );
-    assert_ok!(result);
-    let mut syms2 = SymCorpus::new();
-    let result = syms2.load_buffer(
-        "b/test.symtypes",
+    match result {
+        Err(crate::Error::Parse(err)) => {
+            assert_eq!(err.kind(), crate::ParseErrorKind::UndefinedType);
+            assert_eq!(err.path(), Path::new("test.symtypes"));
+            assert_eq!(err.line(), 1);
+            assert_eq!(err.column(), Some(20));
+            assert_eq!(err.message(), "Type 't#bar' is not defined");
+            assert_eq!(err.source_line(), Some("s#foo struct foo { t#bar a ; }"));
+        }
+        result => panic!(
+            "assertion failed: {:?} is not of type Err(crate::Error::Parse())",
+            result
+        ),
+    }
+}
+
+#[test]
+fn read_parse_error_long_line() {
+    // Check that a long line is shortened around the column in the source excerpt of a parse error.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
         concat!(
-            "s#foo struct foo { int a ; int b ; }\n",
-            "bar int bar ( s#foo , int a )\n", //
+            "s#foo struct foo { int a ; int b ; int c ; int d ; int e ; int f ; t#bar g ; int h ; int i ; int j ; int k ; }\n", //
         )
         .as_bytes(),
     );
-    assert_ok!(result);
-    let mut out = Vec::new();
-    let result = syms.compare_with(&syms2, &mut out, 1);
-    assert_ok!(result);
-    assert_eq!(
-        String::from_utf8(out).unwrap(),
+    assert_parse_err!(
+        result,
         concat!(
-            "The following '1' exports are different:\n",
-            " bar\n",
+            "test.symtypes:1:68: Type 't#bar' is not defined\n",
+            "  ...int b ; int c ; int d ; int e ; int f ; t#bar g ; int h ; int i ; int j ; int k ...\n",
+            "                                             ^~~~~", //
+        )
+    );
+}
+
+#[test]
+fn read_max_errors() {
+    // Check that multiple errors are collected and reported together when the limit allows it.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
+        concat!(
+            "bar int bar ( s#foo )\n",
             "\n",
-            "because of a changed 'bar':\n",
-            "@@ -1,4 +1,4 @@\n",
-            " int bar (\n",
-            "-\tint a,\n",
-            "-\ts#foo\n",
-            "+\ts#foo,\n",
-            "+\tint a\n",
-            " )\n",
+            "bar int bar ( )\n",
+            "baz int baz ( t#qux )\n", //
+        )
+        .as_bytes(),
+        &LoadOptions::new().max_errors(0),
+    );
+    match result {
+        Err(crate::Error::Multiple(errors)) => assert_eq!(
+            errors.iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+            [
+                "test.symtypes:2: Expected a record name",
+                concat!(
+                    "test.symtypes:3:1: Duplicate record 'bar'\n",
+                    "  bar int bar ( )\n",
+                    "  ^~~", //
+                ),
+                concat!(
+                    "test.symtypes:1:15: Type 's#foo' is not defined\n",
+                    "  bar int bar ( s#foo )\n",
+                    "                ^~~~~", //
+                ),
+                concat!(
+                    "test.symtypes:4:15: Type 't#qux' is not defined\n",
+                    "  baz int baz ( t#qux )\n",
+                    "                ^~~~~", //
+                ),
+            ]
+        ),
+        result => panic!(
+            "assertion failed: {:?} is not of type Err(crate::Error::Multiple())",
+            result
+        ),
+    }
+}
+
+#[test]
+fn read_max_errors_limit() {
+    // Check that loading stops once the limit of collected errors is reached.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
+        concat!(
             "\n",
-            "The following '1' exports are different:\n",
-            " bar\n",
             "\n",
-            "because of a changed 's#foo':\n",
-            "@@ -1,3 +1,4 @@\n",
-            " struct foo {\n",
-            " \tint a;\n",
-            "+\tint b;\n",
-            " }\n", //
+            "\n", //
+        )
+        .as_bytes(),
+        &LoadOptions::new().max_errors(2),
+    );
+    match result {
+        Err(crate::Error::Multiple(errors)) => assert_eq!(
+            errors.iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+            [
+                "test.symtypes:1: Expected a record name",
+                "test.symtypes:2: Expected a record name",
+            ]
+        ),
+        result => panic!(
+            "assertion failed: {:?} is not of type Err(crate::Error::Multiple())",
+            result
+        ),
+    }
+}
+
+#[test]
+fn read_max_errors_single() {
+    // Check that a single collected error is reported as is.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
+        concat!(
+            "bar int bar ( )\n",
+            "\n", //
+        )
+        .as_bytes(),
+        &LoadOptions::new().max_errors(0),
+    );
+    assert_parse_err!(result, "test.symtypes:2: Expected a record name");
+}
+
+#[test]
+fn read_no_validate_refs() {
+    // Check that a reference to an undefined type is accepted when the validation is disabled.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { t#bar a ; }\n",
+            "baz int baz ( s#foo )\n", //
+        )
+        .as_bytes(),
+        &LoadOptions::new().validate_refs(false),
+    );
+    assert_ok!(result);
+}
+
+#[test]
+fn read_consolidated_no_validate_refs() {
+    // Check that an implicit reference to an undefined type in a consolidated file is skipped when
+    // the validation is disabled.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
+        concat!(
+            "bar int bar ( s#foo )\n",
+            "F#test.symtypes bar\n", //
+        )
+        .as_bytes(),
+        &LoadOptions::new().validate_refs(false),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "bar int bar ( s#foo )\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[test]
+fn read_invalid_implicit_ref() {
+    // Check that an implicit reference to an undefined type is rejected when reading a consolidated
+    // file.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "bar int bar ( s#foo )\n",
+            "F#test.symtypes bar\n", //
+        )
+        .as_bytes(),
+    );
+    assert_parse_err!(
+        result,
+        "test.symtypes:2: Type 's#foo' is implicitly referenced by file 'test.symtypes' but is not defined"
+    );
+}
+
+#[test]
+fn read_consolidated_implicit_ref_variant() {
+    // Check that an implicit reference in a consolidated file resolves to the variant defined in
+    // that file, even if the corpus already contains other variants of the type.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_buffer(
+        "consolidated.symtypes",
+        concat!(
+            "s#foo struct foo { long a ; }\n",
+            "baz int baz ( s#foo )\n",
+            "F#test2.symtypes baz\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert_eq!(
+        syms.files[1].records[&syms.strings.get("s#foo").unwrap()],
+        VariantIdx(1)
+    );
+}
+
+#[test]
+fn load_options_extensions() {
+    // Check that file names are matched against the configured extensions.
+    let options = LoadOptions::new().extensions(["symtypes", "symtypes.gz"]);
+    assert!(options.matches_extension(Path::new("a/test.symtypes")));
+    assert!(options.matches_extension(Path::new("a/test.symtypes.gz")));
+    assert!(!options.matches_extension(Path::new("a/test.symtypes.bak")));
+    assert!(!options.matches_extension(Path::new("a/testsymtypes")));
+    assert!(!options.matches_extension(Path::new("a/.symtypes")));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn load_options_compressed_extensions() {
+    // Check that file names with an additional extension of a compression format are matched.
+    let options = LoadOptions::new();
+    assert!(options.matches_extension(Path::new("a/test.symtypes.zst")));
+    assert!(!options.matches_extension(Path::new("a/test.zst")));
+}
+
+#[test]
+fn load_multiple_paths() {
+    // Check that symtypes data can be loaded from multiple locations at once.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_multiple(
+        &[
+            "tests/consolidate_cmd/a.symtypes",
+            "tests/consolidate_cmd/b.symtypes",
+        ],
+        2,
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#tests/consolidate_cmd/a.symtypes bar\n",
+            "F#tests/consolidate_cmd/b.symtypes baz\n", //
+        )
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn load_mmap() {
+    // Check that symtypes files can be loaded by reading them through a memory mapping.
+    let mut syms = SymCorpus::new();
+    let options = LoadOptions::new().num_workers(2).mmap_reads(true);
+    let result = syms.load_with("tests/consolidate_cmd", &options);
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#a.symtypes bar\n",
+            "F#b.symtypes baz\n", //
+        )
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn load_mapped_line_endings() {
+    // Check that mapped data with CRLF line endings and without a final newline is parsed in the
+    // same way as when it is read through a buffer.
+    let options = LoadOptions::new();
+    let mut load_context = LoadContext::new(&options);
+    let result = SymCorpus::load_mapped(
+        Path::new("test.symtypes"),
+        map_data(
+            concat!(
+                "s#foo struct foo { int a ; }\r\n",
+                "bar int bar ( s#foo )", //
+            )
+            .as_bytes(),
+        ),
+        &mut load_context,
+    );
+    assert_ok!(result);
+    let mut syms = SymCorpus::new();
+    let result = syms.merge_loaded(load_context, &mut LoadErrors::new(1));
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn load_mapped_invalid_utf8() {
+    // Check that mapped data which is not valid UTF-8 is rejected.
+    let options = LoadOptions::new();
+    let mut load_context = LoadContext::new(&options);
+    let result = SymCorpus::load_mapped(
+        Path::new("test.symtypes"),
+        map_data(b"bar int bar ( \xff )\n"),
+        &mut load_context,
+    );
+    assert!(matches!(result, Err(crate::Error::IO { .. })));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn load_mapped_selective() {
+    // Check that only the selected exports are loaded from mapped data.
+    let options = LoadOptions::new().only_exports(["baz"]);
+    let mut load_context = LoadContext::new(&options);
+    let result = SymCorpus::load_mapped(
+        Path::new("test.symtypes"),
+        map_data(
+            concat!(
+                "s#foo struct foo { int a ; }\n",
+                "bar int bar ( s#foo )\n",
+                "baz int baz ( )\n", //
+            )
+            .as_bytes(),
+        ),
+        &mut load_context,
+    );
+    assert_ok!(result);
+    let mut syms = SymCorpus::new();
+    let result = syms.merge_loaded(load_context, &mut LoadErrors::new(1));
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "baz int baz ( )\n",
+            "F#test.symtypes baz\n", //
+        )
+    );
+}
+
+#[test]
+fn load_merge_workers() {
+    // Check that data loaded separately by multiple workers is correctly merged into the corpus.
+    let options = LoadOptions::new();
+    let mut load_context = LoadContext::new(&options);
+    let result = SymCorpus::load_inner(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+        &mut load_context,
+    );
+    assert_ok!(result);
+    let mut load_context2 = LoadContext::new(&options);
+    let result = SymCorpus::load_inner(
+        "test2.symtypes",
+        concat!(
+            "s#foo struct foo { long b ; }\n",
+            "baz int baz ( s#foo )\n", //
+        )
+        .as_bytes(),
+        &mut load_context2,
+    );
+    assert_ok!(result);
+    let mut load_context3 = LoadContext::new(&options);
+    let result = SymCorpus::load_inner(
+        "test3.symtypes",
+        concat!(
+            "bar int bar ( )\n", //
+        )
+        .as_bytes(),
+        &mut load_context3,
+    );
+    assert_ok!(result);
+    let mut syms = SymCorpus::new();
+    assert_ok!(syms.merge_loaded(load_context, &mut LoadErrors::new(1)));
+    assert_ok!(syms.merge_loaded(load_context2, &mut LoadErrors::new(1)));
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo@0 struct foo { int a ; }\n",
+            "s#foo@1 struct foo { long b ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#test.symtypes s#foo@0 bar\n",
+            "F#test2.symtypes s#foo@1 baz\n", //
         )
     );
+    assert_parse_err!(
+        syms.merge_loaded(load_context3, &mut LoadErrors::new(1)),
+        "test3.symtypes:1: Export 'bar' is duplicate. Previous occurrence found in 'test.symtypes'."
+    );
+}
+
+#[test]
+fn load_from_iter() {
+    // Check that symtypes data can be loaded from a collection of in-memory files.
+    let files = [
+        (
+            PathBuf::from("test.symtypes"),
+            concat!(
+                "s#foo struct foo { int a ; }\n",
+                "bar int bar ( s#foo )\n", //
+            )
+            .as_bytes(),
+        ),
+        (
+            PathBuf::from("test2.symtypes"),
+            concat!(
+                "s#foo struct foo { int a ; }\n",
+                "baz int baz ( s#foo )\n", //
+            )
+            .as_bytes(),
+        ),
+    ];
+    let mut syms = SymCorpus::new();
+    let result = syms.load_from_iter(files, &LoadOptions::new().num_workers(2));
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#test.symtypes bar\n",
+            "F#test2.symtypes baz\n", //
+        )
+    );
+}
+
+#[test]
+fn load_timings() {
+    // Check that the timings of all loaded files and workers are collected when requested.
+    let files = [
+        (
+            PathBuf::from("test.symtypes"),
+            "bar int bar ( )\n".as_bytes(),
+        ),
+        (
+            PathBuf::from("test2.symtypes"),
+            "baz int baz ( )\n".as_bytes(),
+        ),
+        (
+            PathBuf::from("test3.symtypes"),
+            "qux int qux ( )\n".as_bytes(),
+        ),
+    ];
+    let timings = Arc::new(LoadTimings::new());
+    let mut syms = SymCorpus::new();
+    let result = syms.load_from_iter(
+        files,
+        &LoadOptions::new().num_workers(2).timings(timings.clone()),
+    );
+    assert_ok!(result);
+    let workers = timings.workers();
+    assert_eq!(workers.len(), 2);
+    assert_eq!(
+        workers.iter().map(WorkerTiming::num_files).sum::<usize>(),
+        3
+    );
+    let files = timings.slowest_files(2);
+    assert_eq!(files.len(), 2);
+    assert!(files[0].1 >= files[1].1);
+    assert_eq!(timings.slowest_files(10).len(), 3);
+}
+
+#[test]
+fn load_only_exports() {
+    // Check that loading can be restricted to selected exports and the types reachable from them,
+    // skipping files without any selected export.
+    let files = [
+        (
+            PathBuf::from("test.symtypes"),
+            concat!(
+                "s#foo struct foo { int a ; }\n",
+                "s#unused struct unused { int a ; }\n",
+                "bar int bar ( s#foo )\n",
+                "baz int baz ( s#unused )\n", //
+            )
+            .as_bytes(),
+        ),
+        (
+            PathBuf::from("test2.symtypes"),
+            concat!(
+                "qux int qux ( )\n", //
+            )
+            .as_bytes(),
+        ),
+    ];
+    let mut syms = SymCorpus::new();
+    let result = syms.load_from_iter(files, &LoadOptions::new().only_exports(["bar"]));
+    assert_ok!(result);
+    assert_eq!(syms.file_paths(), [Path::new("test.symtypes")]);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[test]
+fn load_only_files() {
+    // Check that loading of a consolidated file can be restricted to selected files.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.kabi",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "qux int qux ( )\n",
+            "F#test.symtypes bar\n",
+            "F#test2.symtypes qux\n", //
+        )
+        .as_bytes(),
+        &LoadOptions::new().only_files(["test.symtypes"]),
+    );
+    assert_ok!(result);
+    assert_eq!(syms.file_paths(), [Path::new("test.symtypes")]);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn load_bytes_gzip() {
+    // Check that an in-memory file is decompressed according to its extension.
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(
+            concat!(
+                "bar int bar ( )\n", //
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    let data = encoder.finish().unwrap();
+    let mut syms = SymCorpus::new();
+    let result = syms.load_bytes("test.symtypes.gz", &data);
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "bar int bar ( )\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[cfg(feature = "tar")]
+#[test]
+fn load_tar() {
+    // Check that symtypes data can be loaded from members of a tar archive.
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in [
+        (
+            "a/test.symtypes",
+            concat!(
+                "s#foo struct foo { int a ; }\n",
+                "bar int bar ( s#foo )\n", //
+            ),
+        ),
+        ("a/README", "Not a symtypes file\n"),
+        (
+            "b/test2.symtypes",
+            concat!(
+                "s#foo struct foo { int a ; }\n",
+                "baz int baz ( s#foo )\n", //
+            ),
+        ),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, data.as_bytes())
+            .unwrap();
+    }
+    let archive = builder.into_inner().unwrap();
+
+    let mut syms = SymCorpus::new();
+    let result = syms.load_tar(
+        Path::new("symtypes.tar"),
+        archive.as_slice(),
+        &LoadOptions::new().num_workers(2),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#a/test.symtypes bar\n",
+            "F#b/test2.symtypes baz\n", //
+        )
+    );
+}
+
+#[cfg(feature = "tar")]
+#[test]
+fn tar_archive_names() {
+    // Check that tar archives are recognized by their file name.
+    assert!(is_tar_archive(Path::new("symtypes.tar")));
+    assert!(!is_tar_archive(Path::new("test.symtypes")));
+    assert!(!is_tar_archive(Path::new("tar")));
+    #[cfg(feature = "xz")]
+    assert!(is_tar_archive(Path::new("symtypes.tar.xz")));
+}
+
+#[cfg(all(feature = "rpm", feature = "gzip"))]
+#[test]
+fn load_rpm() {
+    // Check that symtypes data can be loaded from files in an RPM package.
+    let package = crate::rpm::tests::make_package(
+        "gzip",
+        &[
+            (
+                "./usr/src/linux-obj/a/test.symtypes",
+                0o100644,
+                concat!(
+                    "s#foo struct foo { int a ; }\n",
+                    "bar int bar ( s#foo )\n", //
+                )
+                .as_bytes(),
+            ),
+            (
+                "./usr/src/linux-obj/Module.symvers",
+                0o100644,
+                b"0x12345678\tbar\tvmlinux\tEXPORT_SYMBOL\n",
+            ),
+        ],
+    );
+
+    let mut syms = SymCorpus::new();
+    let result = syms.load_rpm(
+        Path::new("kernel-default-devel.rpm"),
+        package.as_slice(),
+        &LoadOptions::new().num_workers(2),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "F#usr/src/linux-obj/a/test.symtypes bar\n", //
+        )
+    );
+}
+
+#[test]
+fn frozen_corpus() {
+    // Check that a frozen corpus can be queried by multiple threads and turned back into
+    // a modifiable corpus.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "bar int bar ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let frozen = syms.freeze();
+
+    thread::scope(|s| {
+        for _ in 0..2 {
+            let snapshot = frozen.clone();
+            s.spawn(move || {
+                assert!(snapshot.get_export("bar").is_some());
+                assert!(snapshot.compare(&snapshot, 1).is_empty());
+            });
+        }
+    });
+
+    let snapshot = frozen.clone();
+    let mut syms = frozen.thaw();
+    let result = syms.load_buffer(
+        "test2.symtypes",
+        concat!(
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert!(syms.get_export("baz").is_some());
+    assert!(snapshot.get_export("baz").is_none());
+}
+
+#[test]
+fn lazy_consolidated() {
+    // Check that a lazily indexed consolidated file provides its exports and types, and that
+    // selected exports can be materialized into a corpus.
+    let syms = LazySymCorpus::from_reader(
+        "consolidated.symtypes",
+        concat!(
+            "s#foo@0 struct foo { int a ; }\n",
+            "s#foo@1 struct foo { long a ; }\n",
+            "t#u32 typedef unsigned int u32\n",
+            "s#unused struct unused { }\n",
+            "bar int bar ( s#foo * , t#u32 )\n",
+            "baz int baz ( s#foo * )\n",
+            "qux int qux ( )\n",
+            "F#test.symtypes s#foo@0 bar\n",
+            "F#test2.symtypes s#foo@1 baz qux\n", //
+        )
+        .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(syms.export_names(), ["bar", "baz", "qux"]);
+    assert_eq!(syms.export_file("baz"), Some(Path::new("test2.symtypes")));
+    assert_eq!(syms.export_file("s#foo"), None);
+    assert_eq!(
+        syms.file_paths(),
+        [Path::new("test.symtypes"), Path::new("test2.symtypes")]
+    );
+    assert_eq!(
+        syms.type_words(Path::new("test2.symtypes"), "s#foo"),
+        Some(vec!["struct", "foo", "{", "long", "a", ";", "}"])
+    );
+    assert_eq!(
+        syms.type_words(Path::new("test.symtypes"), "t#u32"),
+        Some(vec!["typedef", "unsigned", "int", "u32"])
+    );
+    assert_eq!(syms.type_words(Path::new("test2.symtypes"), "t#u32"), None);
+    assert_eq!(
+        syms.type_words(Path::new("test.symtypes"), "s#unused"),
+        None
+    );
+
+    let syms = syms
+        .materialize(&["bar", "baz", "missing"], &LoadOptions::new())
+        .unwrap();
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo@0 struct foo { int a ; }\n",
+            "s#foo@1 struct foo { long a ; }\n",
+            "t#u32 typedef unsigned int u32\n",
+            "bar int bar ( s#foo * , t#u32 )\n",
+            "baz int baz ( s#foo * )\n",
+            "F#test.symtypes s#foo@0 bar\n",
+            "F#test2.symtypes s#foo@1 baz\n", //
+        )
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn lazy_open_mapped() {
+    // Check that a memory-mapped file is indexed in the same way as data read into memory.
+    let syms = LazySymCorpus::open_mapped("tests/consolidate_cmd/a.symtypes").unwrap();
+    assert_eq!(syms.export_names(), ["bar"]);
+    assert_eq!(
+        syms.type_words(Path::new("tests/consolidate_cmd/a.symtypes"), "s#foo"),
+        Some(vec!["struct", "foo", "{", "int", "a", ";", "}"])
+    );
+
+    let result = LazySymCorpus::open_mapped("tests/missing_file");
+    assert!(matches!(result, Err(crate::Error::IO { .. })));
+}
+
+#[test]
+fn read_write_basic() {
+    // Check reading of a single file and writing the consolidated output.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[test]
+fn read_write_shared_struct() {
+    // Check that a structure declaration shared by two files appears only once in the consolidated
+    // output.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_buffer(
+        "test2.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "baz int baz ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#test.symtypes bar\n",
+            "F#test2.symtypes baz\n", //
+        )
+    );
+}
+
+#[test]
+fn read_write_differing_struct() {
+    // Check that a structure declaration different in two files appears in all variants in the
+    // consolidated output and they are correctly referenced by the F# entries.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_buffer(
+        "test2.symtypes",
+        concat!(
+            "s#foo struct foo { UNKNOWN }\n",
+            "baz int baz ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo@0 struct foo { int a ; }\n",
+            "s#foo@1 struct foo { UNKNOWN }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#test.symtypes s#foo@0 bar\n",
+            "F#test2.symtypes s#foo@1 baz\n", //
+        )
+    );
+}
+
+#[test]
+fn write_consolidated_sort_name() {
+    // Check that the consolidated output can be sorted by name, regardless of the record kind.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let options = ConsolidateOptions::new().sort(ConsolidateSort::Name);
+    let result = syms.write_consolidated_with(&options, &mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "bar int bar ( s#foo )\n",
+            "s#foo struct foo { int a ; }\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[test]
+fn write_consolidated_no_elide() {
+    // Check that types with only one variant are explicitly listed on F# records when the elision
+    // of implicit references is disabled, and that such output can be read back.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_buffer(
+        "test2.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "t#baz typedef int baz\n",
+            "qux int qux ( s#foo , t#baz )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let options = ConsolidateOptions::new().elide_implicit(false);
+    let result = syms.write_consolidated_with(&options, &mut out);
+    assert_ok!(result);
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(
+        out,
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "t#baz typedef int baz\n",
+            "bar int bar ( s#foo )\n",
+            "qux int qux ( s#foo , t#baz )\n",
+            "F#test.symtypes s#foo bar\n",
+            "F#test2.symtypes s#foo t#baz qux\n", //
+        )
+    );
+
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer("consolidated.symtypes", out.as_bytes());
+    assert_ok!(result);
+    let mut out2 = Vec::new();
+    let result = syms2.write_consolidated_buffer(&mut out2);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out2).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "t#baz typedef int baz\n",
+            "bar int bar ( s#foo )\n",
+            "qux int qux ( s#foo , t#baz )\n",
+            "F#test.symtypes bar\n",
+            "F#test2.symtypes qux\n", //
+        )
+    );
+}
+
+#[test]
+fn write_consolidated_strip_prefix() {
+    // Check that a specified prefix is stripped from file paths on F# records.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "build/test.symtypes",
+        concat!(
+            "bar int bar ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_buffer(
+        "other/test2.symtypes",
+        concat!(
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let options = ConsolidateOptions::new().strip_prefix("build");
+    let result = syms.write_consolidated_with(&options, &mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "bar int bar ( )\n",
+            "baz int baz ( )\n",
+            "F#other/test2.symtypes baz\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[test]
+fn write_consolidated_crcs() {
+    // Check that CRCs of exports are recorded on F# records when requested, and that they are read
+    // back from the consolidated file.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; s#foo * next ; }\n",
+            "t#u32 typedef unsigned int u32\n",
+            "bar int bar ( s#foo * , t#u32 )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let options = ConsolidateOptions::new().crcs(true);
+    let result = syms.write_consolidated_with(&options, &mut out);
+    assert_ok!(result);
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(
+        out,
+        concat!(
+            "V#2\n",
+            "s#foo struct foo { int a ; s#foo * next ; }\n",
+            "t#u32 typedef unsigned int u32\n",
+            "bar int bar ( s#foo * , t#u32 )\n",
+            "F#test.symtypes bar=0x35d049d8\n", //
+        )
+    );
+
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer("consolidated.symtypes", out.as_bytes());
+    assert_ok!(result);
+    assert_eq!(syms2.export_crc("bar"), Some(0x35d049d8));
+
+    // Check that the lazy reader understands the recorded CRCs as well.
+    let lazy = LazySymCorpus::from_reader("consolidated.symtypes", out.as_bytes()).unwrap();
+    assert_eq!(lazy.export_names(), ["bar"]);
+    assert_eq!(
+        lazy.type_words(Path::new("test.symtypes"), "t#u32"),
+        Some(vec!["typedef", "unsigned", "int", "u32"])
+    );
+}
+
+#[test]
+fn read_consolidated_recorded_crcs() {
+    // Check that a CRC recorded in a consolidated file is used instead of computing it, and that
+    // the types of an export with equal recorded CRCs are not compared.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "consolidated.symtypes",
+        concat!(
+            "V#2\n",
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo * )\n",
+            "F#test.symtypes bar=0x12345678\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert_eq!(syms.export_crc("bar"), Some(0x12345678));
+
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "consolidated2.symtypes",
+        concat!(
+            "V#2\n",
+            "s#foo struct foo { long a ; }\n",
+            "bar int bar ( s#foo * )\n",
+            "F#test.symtypes bar=0x12345678\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert!(syms.abi_equal(&syms2));
+    let result = syms.compare(&syms2, 1);
+    assert!(result.is_empty());
+}
+
+#[test]
+fn read_consolidated_unsupported_version() {
+    // Check that a consolidated file with an unknown format version is rejected.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "V#3\n",
+            "bar int bar ( )\n",
+            "F#test.symtypes bar=0x12345678\n", //
+        )
+        .as_bytes(),
+    );
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.symtypes:1:3: Unsupported format version '3'\n",
+            "  V#3\n",
+            "    ^", //
+        )
+    );
+}
+
+#[test]
+fn read_consolidated_invalid_crc() {
+    // Check that an invalid CRC of an export on an F# record is rejected.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "V#2\n",
+            "bar int bar ( )\n",
+            "F#test.symtypes bar=12345678\n", //
+        )
+        .as_bytes(),
+    );
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.symtypes:3:17: Invalid CRC of 'bar'\n",
+            "  F#test.symtypes bar=12345678\n",
+            "                  ^~~~~~~~~~~~", //
+        )
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn write_consolidated_gzip() {
+    // Check that the consolidated output can be compressed with gzip.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "bar int bar ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let options = ConsolidateOptions::new().compression(crate::Compression::Gzip);
+    let result = syms.write_consolidated_with(&options, &mut out);
+    assert_ok!(result);
+    let mut decoded = String::new();
+    flate2::read::GzDecoder::new(out.as_slice())
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(
+        decoded,
+        concat!(
+            "bar int bar ( )\n",
+            "F#test.symtypes bar\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_incomplete_type_warning() {
+    // Check that a changed type which has an incomplete definition in only one of the corpuses is
+    // reported as a warning.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { UNKNOWN }\n",
+            "bar int bar ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let diagnostics = Arc::new(RecordingDiagnostics::default());
+    let result = syms.compare_with_options(
+        &syms2,
+        &CompareOptions::new().diagnostics(diagnostics.clone()),
+    );
+    assert_eq!(result.changed_types().len(), 1);
+    assert_eq!(
+        diagnostics.take(),
+        [(
+            WarningKind::IncompleteType,
+            "Type 's#foo' has an incomplete definition in one of the corpuses".to_string()
+        )]
+    );
+}
+
+#[test]
+fn progress_reporting() {
+    // Check that the progress of loading, consolidation and comparison is reported.
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let progress: Arc<ProgressFn> = {
+        let calls = calls.clone();
+        Arc::new(move |phase, current, total| calls.lock().unwrap().push((phase, current, total)))
+    };
+    let files = [
+        (PathBuf::from("a.symtypes"), "foo int foo ( )\n".as_bytes()),
+        (PathBuf::from("b.symtypes"), "bar int bar ( )\n".as_bytes()),
+    ];
+    let mut syms = SymCorpus::new();
+    let result = syms.load_from_iter(files, &LoadOptions::new().progress(progress.clone()));
+    assert_ok!(result);
+    assert_eq!(
+        mem::take(&mut *calls.lock().unwrap()),
+        [
+            (ProgressPhase::Load, 1, Some(2)),
+            (ProgressPhase::Load, 2, Some(2))
+        ]
+    );
+
+    let mut out = Vec::new();
+    let result = syms.write_consolidated_with(
+        &ConsolidateOptions::new().progress(progress.clone()),
+        &mut out,
+    );
+    assert_ok!(result);
+    assert_eq!(
+        mem::take(&mut *calls.lock().unwrap()),
+        [
+            (ProgressPhase::Consolidate, 1, Some(2)),
+            (ProgressPhase::Consolidate, 2, Some(2))
+        ]
+    );
+
+    syms.compare_with_options(&syms, &CompareOptions::new().progress(progress));
+    assert_eq!(
+        mem::take(&mut *calls.lock().unwrap()),
+        [
+            (ProgressPhase::Compare, 1, Some(2)),
+            (ProgressPhase::Compare, 2, Some(2))
+        ]
+    );
+}
+
+#[test]
+fn type_views() {
+    // Check that views of exports and types provide their names, files, variants and tokens.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let export = syms.get_export("bar").unwrap();
+    assert_eq!(export.name(), "bar");
+    assert_eq!(export.file(), Path::new("test.symtypes"));
+    assert_eq!(export.to_string(), "int bar (\n\ts#foo *\n)");
+    assert!(syms.get_export("s#foo").is_none());
+    assert!(syms.get_export("baz").is_none());
+    let names: Vec<_> = syms.export_views().map(|export| export.name()).collect();
+    assert_eq!(names, ["bar"]);
+
+    let definition = export.definition();
+    assert_eq!(definition.variant_index(), 0);
+    assert_eq!(
+        definition.tokens().collect::<Vec<_>>(),
+        [
+            RawToken::Atom("int"),
+            RawToken::Atom("bar"),
+            RawToken::Atom("("),
+            RawToken::TypeRef("s#foo"),
+            RawToken::Atom("*"),
+            RawToken::Atom(")"),
+        ]
+    );
+
+    assert!(matches!(definition.parse(), crate::ast::Decl::Function(_)));
+
+    let references: Vec<_> = definition.references().collect();
+    assert_eq!(references.len(), 1);
+    assert_eq!(references[0].name(), "s#foo");
+    assert_eq!(references[0].file(), Path::new("test.symtypes"));
+    assert_eq!(references[0].to_string(), "struct foo {\n\tint a;\n}");
+
+    let type_view = syms.get_type("test.symtypes", "s#foo").unwrap();
+    assert_eq!(type_view.name(), "s#foo");
+    assert!(syms.get_type("other.symtypes", "s#foo").is_none());
+}
+
+#[test]
+fn visit_type_graph() {
+    // Check that a type graph is walked depth-first, with each type visited once even if it is
+    // referenced multiple times or recursively.
+    struct Recorder(Vec<String>);
+
+    impl<'a> TypeVisitor<'a> for Recorder {
+        fn enter_type(&mut self, view: &TypeView<'a>, depth: usize) -> bool {
+            self.0
+                .push(format!("{}enter {}", " ".repeat(depth), view.name()));
+            view.name() != "s#skipped"
+        }
+
+        fn visit_token(&mut self, _view: &TypeView<'a>, token: RawToken<'a>) {
+            if let RawToken::TypeRef(word) = token {
+                self.0.push(format!("ref {}", word));
+            }
+        }
+
+        fn leave_type(&mut self, view: &TypeView<'a>, depth: usize) {
+            self.0
+                .push(format!("{}leave {}", " ".repeat(depth), view.name()));
+        }
+    }
+
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer_with(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { s#foo * next ; t#u32 a ; t#u32 b ; s#skipped * c ; s#missing * d ; }\n",
+            "t#u32 typedef unsigned int u32\n",
+            "s#skipped struct skipped { t#u32 a ; }\n",
+            "bar int bar ( s#foo * )\n", //
+        )
+        .as_bytes(),
+        &LoadOptions::new().validate_refs(false),
+    );
+    assert_ok!(result);
+
+    let mut recorder = Recorder(Vec::new());
+    syms.get_export("bar").unwrap().visit(&mut recorder);
+    assert_eq!(
+        recorder.0,
+        [
+            "enter bar",
+            "ref s#foo",
+            " enter s#foo",
+            "ref s#foo",
+            "ref t#u32",
+            "  enter t#u32",
+            "  leave t#u32",
+            "ref t#u32",
+            "ref s#skipped",
+            "  enter s#skipped",
+            "ref s#missing",
+            " leave s#foo",
+            "leave bar",
+        ]
+    );
+}
+
+#[test]
+fn visit_type_graph_skipped_leave() {
+    // Check that leave_type() is not called for a type whose enter_type() returned false, so that
+    // a visitor which pushes only the entered types on a stack stays balanced.
+    struct StackChecker<'a> {
+        stack: Vec<&'a str>,
+        left: Vec<&'a str>,
+    }
+
+    impl<'a> TypeVisitor<'a> for StackChecker<'a> {
+        fn enter_type(&mut self, view: &TypeView<'a>, depth: usize) -> bool {
+            if view.name() == "s#skipped" {
+                return false;
+            }
+            assert_eq!(self.stack.len(), depth);
+            self.stack.push(view.name());
+            true
+        }
+
+        fn leave_type(&mut self, view: &TypeView<'a>, depth: usize) {
+            assert_eq!(self.stack.pop(), Some(view.name()));
+            assert_eq!(self.stack.len(), depth);
+            self.left.push(view.name());
+        }
+    }
+
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { s#skipped * a ; t#u32 b ; }\n",
+            "s#skipped struct skipped { t#u32 a ; }\n",
+            "t#u32 typedef unsigned int u32\n",
+            "bar int bar ( s#foo * , s#skipped * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let mut checker = StackChecker {
+        stack: Vec::new(),
+        left: Vec::new(),
+    };
+    syms.get_export("bar").unwrap().visit(&mut checker);
+    assert!(checker.stack.is_empty());
+    assert_eq!(checker.left, ["t#u32", "s#foo", "bar"]);
+}
+
+#[test]
+fn export_symbol_kind() {
+    // Check that exports are classified as functions or data objects.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "foo_init int foo_init ( s#foo * )\n",
+            "foo_ops_ptr s#foo * ( * foo_ops_ptr ) ( void )\n",
+            "foo_get s#foo * foo_get ( int ( * ) ( void ) )\n",
+            "jiffies volatile unsigned long jiffies\n",
+            "foo_table s#foo foo_table [ 16 ]\n",
+            "foo_handler void ( * foo_handler ( int ) ) ( long )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let kind = |name| syms.get_export(name).unwrap().symbol_kind();
+    assert_eq!(kind("foo_init"), SymbolKind::Function);
+    assert_eq!(kind("foo_ops_ptr"), SymbolKind::Data);
+    assert_eq!(kind("foo_get"), SymbolKind::Function);
+    assert_eq!(kind("jiffies"), SymbolKind::Data);
+    assert_eq!(kind("foo_table"), SymbolKind::Data);
+    assert_eq!(kind("foo_handler"), SymbolKind::Function);
+}
+
+#[test]
+fn read_symvers_namespaces() {
+    // Check that namespaces and export types of exports are loaded from symvers data.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "foo int foo ( )\n",
+            "bar int bar ( )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_symvers_buffer(
+        "Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL_GPL\tFOO_NS\n",
+            "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL\t\n",
+            "0x3456789a\tbaz\tdrivers/baz\tEXPORT_SYMBOL_GPL\tFOO_NS\n",
+            "0x456789ab\tqux\tvmlinux\tEXPORT_SYMBOL\tQUX_NS\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert_eq!(syms.get_export("foo").unwrap().namespace(), Some("FOO_NS"));
+    assert_eq!(syms.get_export("bar").unwrap().namespace(), None);
+    assert_eq!(
+        syms.get_export("foo").unwrap().export_type(),
+        Some("EXPORT_SYMBOL_GPL")
+    );
+    assert_eq!(
+        syms.get_export("bar").unwrap().export_type(),
+        Some("EXPORT_SYMBOL")
+    );
+    assert_eq!(syms.namespaces(), ["FOO_NS"]);
+    let mut names: Vec<_> = syms
+        .namespace_exports("FOO_NS")
+        .map(|export| export.name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["baz", "foo"]);
+    assert_eq!(syms.namespace_exports("QUX_NS").count(), 0);
+}
+
+#[test]
+fn read_symvers_malformed() {
+    // Check that a symvers line with a wrong number of fields is rejected.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_symvers_buffer(
+        "Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\n", //
+        )
+        .as_bytes(),
+    );
+    assert_parse_err!(
+        result,
+        concat!(
+            "Module.symvers:1:1: Expected 4 or 5 tab-separated fields, found 3\n",
+            "  0x12345678\tfoo\tvmlinux\n",
+            "  ^~~~~~~~~~", //
+        )
+    );
+}
+
+#[test]
+fn compare_identical() {
+    // Check that the comparison of two identical corpuses shows no differences.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "bar int bar ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "bar int bar ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.compare_with(&syms2, &mut out, 1);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "", //
+        )
+    );
+}
+
+#[test]
+fn compare_identical_string_order() {
+    // Check that the comparison of two identical corpuses shows no differences when their strings
+    // were interned in a different order.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "bar int bar ( s#foo )\n",
+            "s#foo struct foo { int a ; }\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert_ne!(syms.strings.get("int"), syms2.strings.get("int"));
+    let mut out = Vec::new();
+    let result = syms.compare_with(&syms2, &mut out, 1);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "", //
+        )
+    );
+}
+
+#[test]
+fn compare_added_export() {
+    // Check that the comparison of two corpuses reports any newly added export.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "bar int bar ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "bar int bar ( )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.compare_with(&syms2, &mut out, 1);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Export 'baz' (function) has been added\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_removed_export() {
+    // Check that the comparison of two corpuses reports any removed export.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "bar int bar ( )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.compare_with(&syms2, &mut out, 1);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Export 'bar' (function) has been removed\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_crc_only() {
+    // Check that a corpus created from symvers data is compared with a full corpus by the CRCs of
+    // the exports.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "bar int bar ( )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let bar_crc = syms.export_crc("bar").unwrap();
+    let baz_crc = syms.export_crc("baz").unwrap();
+    let mut symvers = crate::symvers::SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "Module.symvers",
+        format!(
+            concat!(
+                "0x{:08x}\tbar\tvmlinux\tEXPORT_SYMBOL\n",
+                "0x12345678\tbaz\tvmlinux\tEXPORT_SYMBOL\n",
+                "0x00000001\tqux\tvmlinux\tEXPORT_SYMBOL_GPL\n", //
+            ),
+            bar_crc
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let syms2 = SymCorpus::from_symvers(&symvers);
+    assert!(syms2.is_crc_only());
+    assert_eq!(syms2.export_crc("baz"), Some(0x12345678));
+
+    let result = syms.compare(&syms2, 1);
+    assert!(result.is_crc_only());
+    assert_eq!(
+        result.export_file("qux"),
+        Some(Path::new("vmlinux.symtypes"))
+    );
+    let mut out = Vec::new();
+    assert_ok!(result.write_report(&mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        format!(
+            concat!(
+                "Export 'qux' (EXPORT_SYMBOL_GPL in vmlinux) has been added\n",
+                "The following '1' exports are different:\n",
+                " baz (EXPORT_SYMBOL in vmlinux)\n",
+                "\n",
+                "because of a changed CRC from 0x{:08x} to 0x12345678, structural differences are ",
+                "not available\n", //
+            ),
+            baz_crc
+        )
+    );
+}
+
+#[test]
+fn compare_symvers_annotations() {
+    // Check that the reported exports are annotated with their export types and modules from
+    // symvers data.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "bar int bar ( )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "bar long bar ( )\n",
+            "qux int qux\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers = crate::symvers::SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "Module.symvers",
+        concat!(
+            "0x00000000\tbar\tvmlinux\tEXPORT_SYMBOL_GPL\n",
+            "0x00000000\tqux\tfs/qux.ko\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    syms.add_symvers(&symvers);
+    syms2.add_symvers(&symvers);
+    assert_eq!(syms.get_export("bar").unwrap().module(), Some("vmlinux"));
+    assert_eq!(syms.get_export("baz").unwrap().module(), None);
+
+    let result = syms.compare(&syms2, 1);
+    assert_eq!(result.export_type("bar"), Some("EXPORT_SYMBOL_GPL"));
+    assert_eq!(result.export_module("qux"), Some("fs/qux.ko"));
+    assert_eq!(result.export_module("baz"), None);
+    let mut out = Vec::new();
+    assert_ok!(result.write_report(&mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Export 'baz' (function) has been removed\n",
+            "Export 'qux' (data, EXPORT_SYMBOL in fs/qux.ko) has been added\n",
+            "The following '1' exports are different:\n",
+            " bar (function, EXPORT_SYMBOL_GPL in vmlinux)\n",
+            "\n",
+            "because of a changed 'bar':\n",
+            "@@ -1,2 +1,2 @@\n",
+            "-int bar (\n",
+            "+long bar (\n",
+            " )\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_tolerated_types() {
+    // Check that changes of tolerated types are not reported and that exports affected only by
+    // them are not considered changed.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "t#size_t typedef unsigned long size_t\n",
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo * , t#size_t )\n",
+            "baz int baz ( t#size_t )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "t#size_t typedef unsigned int size_t\n",
+            "s#foo struct foo { long a ; }\n",
+            "bar int bar ( s#foo * , t#size_t )\n",
+            "baz int baz ( t#size_t )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let result = syms.compare(&syms2, 1);
+    assert_eq!(result.changed_types().len(), 2);
+
+    let options = CompareOptions::new().tolerate_types(POINTER_SIZE_TYPES.iter().copied());
+    let result = syms.compare_with_options(&syms2, &options);
+    let changed_types: Vec<_> = result
+        .changed_types()
+        .iter()
+        .map(|type_change| (type_change.name(), type_change.exports()))
+        .collect();
+    assert_eq!(changed_types, [("s#foo", &["bar".to_string()][..])]);
+}
+
+#[test]
+fn compare_changed_type() {
+    // Check that the comparison of two corpuses reports changed types and affected exports.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; int b ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.compare_with(&syms2, &mut out, 1);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'b' added at position 2 with type int\n",
+            "@@ -1,3 +1,4 @@\n",
+            " struct foo {\n",
+            " \tint a;\n",
+            "+\tint b;\n",
+            " }\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_changed_nested_type() {
+    // Check that the comparison of two corpuses reports also changes in subtypes even if the parent
+    // type itself is modified, as long as each subtype is referenced by the parent type in both
+    // inputs.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( int a , s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; int b ; }\n",
+            "bar int bar ( s#foo , int a )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut out = Vec::new();
+    let result = syms.compare_with(&syms2, &mut out, 1);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 'bar':\n",
+            "@@ -1,4 +1,4 @@\n",
+            " int bar (\n",
+            "-\tint a,\n",
+            "-\ts#foo\n",
+            "+\ts#foo,\n",
+            "+\tint a\n",
+            " )\n",
+            "\n",
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'b' added at position 2 with type int\n",
+            "@@ -1,3 +1,4 @@\n",
+            " struct foo {\n",
+            " \tint a;\n",
+            "+\tint b;\n",
+            " }\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_enum_constant() {
+    // Check that a changed value of an enumerator constant is reported explicitly.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "E#FOO_MAX 7\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "bar int bar ( e#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "E#FOO_MAX 0x8\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "bar int bar ( e#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let type_view = syms.get_type("a/test.symtypes", "E#FOO_MAX").unwrap();
+    assert_eq!(type_view.kind(), RecordKind::EnumConstant);
+    assert_eq!(type_view.enum_value(), Some(7));
+    let mut out = Vec::new();
+    let result = syms.compare_with(&syms2, &mut out, 1);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 'E#FOO_MAX':\n",
+            "E#FOO_MAX changed from 7 to 8\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_json_report() {
+    // Check that a comparison result can be written as JSON.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "E#FOO_MAX 7\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { int a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "old void old ( void )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "E#FOO_MAX 8\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { long a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let compare_result = syms.compare(&syms2, 1);
+    let mut out = Vec::new();
+    let result = compare_result.write_json_report_with(&DiffOptions::new(), &mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "{\"removed_exports\":[\"old\"],\"added_exports\":[],\"changed_types\":[",
+            "{\"name\":\"E#FOO_MAX\",\"exports\":[\"baz\"],",
+            "\"descriptions\":[\"E#FOO_MAX changed from 7 to 8\"],",
+            "\"old_tokens\":[\"7\"],\"new_tokens\":[\"8\"],\"hunks\":[]},",
+            "{\"name\":\"s#bar\",\"exports\":[\"baz\"],",
+            "\"descriptions\":[\"struct bar: member 'a' changed type from int to long\"],",
+            "\"old_tokens\":[\"struct\",\"bar\",\"{\",\"int\",\"a\",\";\",\"}\"],",
+            "\"new_tokens\":[\"struct\",\"bar\",\"{\",\"long\",\"a\",\";\",\"}\"],",
+            "\"hunks\":[{\"pos_a\":1,\"len_a\":3,\"pos_b\":1,\"len_b\":3,\"lines\":[",
+            "{\"type\":\"context\",\"text\":\"struct bar {\"},",
+            "{\"type\":\"removed\",\"text\":\"\\tint a;\"},",
+            "{\"type\":\"added\",\"text\":\"\\tlong a;\"},",
+            "{\"type\":\"context\",\"text\":\"}\"}]}]}],",
+            "\"exports\":{",
+            "\"baz\":{\"kind\":\"function\",\"file\":\"a/test.symtypes\",",
+            "\"export_type\":null,\"module\":null},",
+            "\"old\":{\"kind\":\"function\",\"file\":\"a/test.symtypes\",",
+            "\"export_type\":null,\"module\":null}},",
+            "\"crc_only\":false}\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_jsonl_report() {
+    // Check that a comparison result can be written in the JSON Lines format.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "E#FOO_MAX 7\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { int a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "old void old ( void )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "E#FOO_MAX 8\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { long a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "new int new\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let compare_result = syms.compare(&syms2, 1);
+    let mut out = Vec::new();
+    let result = compare_result.write_jsonl_report_with(&DiffOptions::new(), &mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "{\"change\":\"removed_export\",\"name\":\"old\"}\n",
+            "{\"change\":\"added_export\",\"name\":\"new\"}\n",
+            "{\"change\":\"changed_type\",\"name\":\"E#FOO_MAX\",\"exports\":[\"baz\"],",
+            "\"descriptions\":[\"E#FOO_MAX changed from 7 to 8\"],",
+            "\"old_tokens\":[\"7\"],\"new_tokens\":[\"8\"],\"hunks\":[]}\n",
+            "{\"change\":\"changed_type\",\"name\":\"s#bar\",\"exports\":[\"baz\"],",
+            "\"descriptions\":[\"struct bar: member 'a' changed type from int to long\"],",
+            "\"old_tokens\":[\"struct\",\"bar\",\"{\",\"int\",\"a\",\";\",\"}\"],",
+            "\"new_tokens\":[\"struct\",\"bar\",\"{\",\"long\",\"a\",\";\",\"}\"],",
+            "\"hunks\":[{\"pos_a\":1,\"len_a\":3,\"pos_b\":1,\"len_b\":3,\"lines\":[",
+            "{\"type\":\"context\",\"text\":\"struct bar {\"},",
+            "{\"type\":\"removed\",\"text\":\"\\tint a;\"},",
+            "{\"type\":\"added\",\"text\":\"\\tlong a;\"},",
+            "{\"type\":\"context\",\"text\":\"}\"}]}]}\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_mail_report() {
+    // Check that a comparison result can be written as a wrapped plain-ASCII report for mail.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "E#FOO_MAX 7\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { int a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "bar_first_user_with_a_long_name int bar_first_user_with_a_long_name ( s#bar * )\n",
+            "bar_second_user_with_a_long_name int bar_second_user_with_a_long_name ( s#bar * )\n",
+            "old void old ( void )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "E#FOO_MAX 8\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { long a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "bar_first_user_with_a_long_name int bar_first_user_with_a_long_name ( s#bar * )\n",
+            "bar_second_user_with_a_long_name int bar_second_user_with_a_long_name ( s#bar * )\n",
+            "new int new\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let compare_result = syms.compare(&syms2, 1);
+    let mut out = Vec::new();
+    let result = compare_result.write_mail_report_with(&DiffOptions::new(), &mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Summary\n",
+            "=======\n",
+            "\n",
+            "Removed exports: 1\n",
+            "Added exports: 1\n",
+            "Changed exports: 3\n",
+            "Changed types: 2\n",
+            "\n",
+            "Removed exports (1)\n",
+            "-------------------\n",
+            "\n",
+            "  old (function)\n",
+            "\n",
+            "Added exports (1)\n",
+            "-----------------\n",
+            "\n",
+            "  new (data)\n",
+            "\n",
+            "Changed types (2)\n",
+            "-----------------\n",
+            "\n",
+            "Type 'E#FOO_MAX' has changed, affecting 1 export: baz (function).\n",
+            "E#FOO_MAX changed from 7 to 8\n",
+            "\n",
+            "Type 's#bar' has changed, affecting 3 exports:\n",
+            "bar_first_user_with_a_long_name (function),\n",
+            "bar_second_user_with_a_long_name (function), baz (function).\n",
+            "struct bar: member 'a' changed type from int to long\n",
+            "\n",
+            "--- a:s#bar\n",
+            "+++ b:s#bar\n",
+            "@@ -1,3 +1,3 @@\n",
+            " struct bar {\n",
+            "-\tint a;\n",
+            "+\tlong a;\n",
+            " }\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_markdown_report() {
+    // Check that a comparison result can be written in the Markdown format.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "E#FOO_MAX 7\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { int a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "old void old ( void )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "E#FOO_MAX 8\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { int * a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "new int new\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let compare_result = syms.compare(&syms2, 1);
+    let mut out = Vec::new();
+    let result = compare_result.write_markdown_report_with(&DiffOptions::new(), &mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "# kABI changes\n",
+            "\n",
+            "| Change | Count |\n",
+            "| --- | ---: |\n",
+            "| Removed exports | 1 |\n",
+            "| Added exports | 1 |\n",
+            "| Changed exports | 1 |\n",
+            "| Changed types | 2 |\n",
+            "\n",
+            "## Removed exports (1)\n",
+            "\n",
+            "- `old` (function)\n",
+            "\n",
+            "## Added exports (1)\n",
+            "\n",
+            "- `new` (data)\n",
+            "\n",
+            "## Changed types (2)\n",
+            "\n",
+            "### `E#FOO_MAX`\n",
+            "\n",
+            "Affects 1 export: `baz` (function).\n",
+            "\n",
+            "- E\\#FOO\\_MAX changed from 7 to 8\n",
+            "\n",
+            "### `s#bar`\n",
+            "\n",
+            "Affects 1 export: `baz` (function).\n",
+            "\n",
+            "- struct bar: member 'a' changed type from int to int \\*\n",
+            "\n",
+            "```diff\n",
+            "@@ -1,3 +1,3 @@\n",
+            " struct bar {\n",
+            "-\tint a;\n",
+            "+\tint * a;\n",
+            " }\n",
+            "```\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_html_report() {
+    // Check that a comparison result can be written as an HTML document.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#bar struct bar { int a ; }\n",
+            "baz int baz ( s#bar * )\n",
+            "old void old ( void )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#bar struct bar { int * a ; }\n",
+            "baz int baz ( s#bar * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let compare_result = syms.compare(&syms2, 1);
+    let mut out = Vec::new();
+    let result = compare_result.write_html_report_with(&DiffOptions::new(), &mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html lang=\"en\">\n",
+            "<head>\n",
+            "<meta charset=\"utf-8\">\n",
+            "<title>kABI changes</title>\n",
+            "<style>\n",
+            ".del { color: #b31d28; }\n",
+            ".add { color: #22863a; }\n",
+            ".hunk { color: #6f42c1; }\n",
+            "</style>\n",
+            "</head>\n",
+            "<body>\n",
+            "<h1>kABI changes</h1>\n",
+            "<table>\n",
+            "<tr><th>Removed exports</th><td>1</td></tr>\n",
+            "<tr><th>Added exports</th><td>0</td></tr>\n",
+            "<tr><th>Changed exports</th><td>1</td></tr>\n",
+            "<tr><th>Changed types</th><td>1</td></tr>\n",
+            "</table>\n",
+            "<h2>Removed exports (1)</h2>\n",
+            "<ul>\n",
+            "<li><code>old</code> (function)</li>\n",
+            "</ul>\n",
+            "<h2>Changed types (1)</h2>\n",
+            "<h3><code>s#bar</code></h3>\n",
+            "<p>Affects 1 export: <code>baz</code> (function).</p>\n",
+            "<ul>\n",
+            "<li>struct bar: member 'a' changed type from int to int *</li>\n",
+            "</ul>\n",
+            "<pre>\n",
+            "<span class=\"hunk\">@@ -1,3 +1,3 @@</span>\n",
+            " struct bar {\n",
+            "<span class=\"del\">-\tint a;</span>\n",
+            "<span class=\"add\">+\tint * a;</span>\n",
+            " }\n",
+            "</pre>\n",
+            "</body>\n",
+            "</html>\n", //
+        )
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn compare_read_json_report() {
+    // Check that a comparison result written as JSON can be read back.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "E#FOO_MAX 7\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { int a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "old void old ( void )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "E#FOO_MAX 8\n",
+            "e#foo enum foo { FOO_A , FOO_MAX = E#FOO_MAX }\n",
+            "s#bar struct bar { long a ; }\n",
+            "baz int baz ( e#foo , s#bar * )\n",
+            "new int new\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let compare_result = syms.compare(&syms2, 1);
+    let mut out = Vec::new();
+    let result = compare_result.write_json_report_with(&DiffOptions::new(), &mut out);
+    assert_ok!(result);
+    let read_result = CompareResult::read_json_report("test.json", out.as_slice()).unwrap();
+    assert_eq!(read_result, compare_result);
+
+    // Check that a report with a missing member is rejected.
+    let result = CompareResult::read_json_report(
+        "test.json",
+        "{\"removed_exports\":[],\"added_exports\":[]}".as_bytes(),
+    );
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.json:1:41: missing field `changed_types`\n",
+            "  {\"removed_exports\":[],\"added_exports\":[]}\n",
+            "                                          ^", //
+        )
+    );
+
+    // Check that a member of a wrong type is reported at its position.
+    let result = CompareResult::read_json_report(
+        "test.json",
+        "{\"removed_exports\":[],\n\"added_exports\":{}}".as_bytes(),
+    );
+    assert_parse_err!(
+        result,
+        concat!(
+            "test.json:2:16: invalid type: map, expected a sequence\n",
+            "  \"added_exports\":{}}\n",
+            "                 ^~~~", //
+        )
+    );
+}
+
+#[test]
+fn compare_abi_equal() {
+    // Check that the ABI equality of two corpuses considers exports and all referenced types.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; s#foo * next ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#foo struct foo { long a ; s#foo * next ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms3 = SymCorpus::new();
+    let result = syms3.load_buffer(
+        "c/test.symtypes",
+        concat!(
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( )\n",
+            "s#foo struct foo { int a ; s#foo * next ; }\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert!(syms.abi_equal(&syms));
+    assert!(syms.abi_equal(&syms3));
+    assert!(!syms.abi_equal(&syms2));
+    assert!(syms.abi_equal_symbols(&syms2, &["baz", "qux"]));
+    assert!(!syms.abi_equal_symbols(&syms2, &["bar"]));
+    assert!(!syms.abi_equal(&SymCorpus::new()));
+    assert!(!syms.abi_equal_symbols(&SymCorpus::new(), &["baz"]));
+}
+
+#[test]
+fn export_crc_prediction() {
+    // Check that genksyms CRCs of exports are computed and that a type change predicts new CRCs
+    // only for exports which see the changed variant.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; s#foo * next ; }\n",
+            "t#u32 typedef unsigned int u32\n",
+            "bar int bar ( s#foo * , t#u32 )\n",
+            "qux int qux ( t#u32 )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_buffer(
+        "test2.symtypes",
+        concat!(
+            "s#foo struct foo { UNKNOWN }\n",
+            "baz int baz ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    assert_eq!(syms.export_crc("bar"), Some(0x35d049d8));
+    assert_eq!(syms.export_crc("baz"), Some(0xc3d50522));
+    assert_eq!(syms.export_crc("s#foo"), None);
+
+    let crc_changes = syms.predict_crc_changes(
+        "s#foo",
+        &[
+            "struct", "foo", "{", "int", "a", ";", "s#foo", "*", "next", ";", "}",
+        ],
+        &[
+            "struct", "foo", "{", "long", "a", ";", "s#foo", "*", "next", ";", "}",
+        ],
+    );
+    let crc_changes: Vec<_> = crc_changes
+        .iter()
+        .map(|change| (change.export(), change.old_crc(), change.new_crc()))
+        .collect();
+    assert_eq!(crc_changes, [("bar", 0x35d049d8, 0x8e944d06)]);
+}
+
+#[test]
+fn compare_renamed_type() {
+    // Check that a type renamed in the corpus compares equal to its new definition.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; s#foo * next ; }\n",
+            "bar int bar ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#foo_v2 struct foo_v2 { int a ; s#foo_v2 * next ; }\n",
+            "bar int bar ( s#foo_v2 * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert!(!syms.abi_equal(&syms2));
+    assert!(!syms.rename_type("s#baz", "s#baz_v2"));
+    assert!(!syms.rename_type("bar", "bar_v2"));
+    assert!(syms.rename_type("s#foo", "s#foo_v2"));
+    assert!(!syms.rename_type("s#foo_v2", "s#foo_v2"));
+    assert!(syms.get_type("a/test.symtypes", "s#foo").is_none());
+    assert_eq!(
+        syms.pretty_type("a/test.symtypes", "s#foo_v2"),
+        Some(crate::string_vec!(
+            "struct foo_v2 {",
+            "\tint a;",
+            "\ts#foo_v2 * next;",
+            "}", //
+        ))
+    );
+    assert!(syms.abi_equal(&syms2));
+}
+
+#[test]
+fn compare_result() {
+    // Check that the structured comparison result provides all found changes.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; int b ; }\n",
+            "bar int bar ( s#foo )\n",
+            "qux int qux ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.compare(&syms2, 1);
+    assert!(!result.is_empty());
+    assert_eq!(result.removed_exports(), ["baz"]);
+    assert_eq!(result.added_exports(), ["qux"]);
+    assert_eq!(result.changed_types().len(), 1);
+    assert_eq!(result.changed_types()[0].name(), "s#foo");
+    assert_eq!(result.changed_types()[0].exports(), ["bar"]);
+    assert_eq!(result.export_kind("baz"), Some(SymbolKind::Function));
+    assert_eq!(result.export_kind("qux"), Some(SymbolKind::Function));
+    assert_eq!(result.export_kind("bar"), Some(SymbolKind::Function));
+}
+
+#[test]
+fn compare_suppress() {
+    // Check that suppressed changes are removed from a comparison result, including changed types
+    // which no longer affect any export.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "s#baz struct baz { int a ; }\n",
+            "bar int bar ( s#foo , s#baz )\n",
+            "qux int qux ( s#foo )\n",
+            "quux int quux ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "a.symtypes",
+        concat!(
+            "s#foo struct foo { long a ; }\n",
+            "s#baz struct baz { long a ; }\n",
+            "bar int bar ( s#foo , s#baz )\n",
+            "qux int qux ( s#foo )\n",
+            "corge int corge ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut result = syms.compare(&syms2, 1);
+    assert_eq!(
+        result.export_changes(),
+        [
+            ("quux", Some(Path::new("a.symtypes")), ChangeKind::Removed),
+            ("corge", Some(Path::new("a.symtypes")), ChangeKind::Added),
+            ("bar", Some(Path::new("a.symtypes")), ChangeKind::Changed),
+            ("qux", Some(Path::new("a.symtypes")), ChangeKind::Changed),
+        ]
+    );
+
+    result.suppress(|name, kind| match kind {
+        ChangeKind::Removed => name == "quux",
+        ChangeKind::Added => false,
+        ChangeKind::Changed => name == "s#baz" || name == "qux",
+    });
+    assert_eq!(
+        result.export_changes(),
+        [
+            ("corge", Some(Path::new("a.symtypes")), ChangeKind::Added),
+            ("bar", Some(Path::new("a.symtypes")), ChangeKind::Changed),
+        ]
+    );
+    assert_eq!(result.changed_types().len(), 1);
+    assert_eq!(result.changed_types()[0].name(), "s#foo");
+    assert_eq!(result.changed_types()[0].exports(), ["bar"]);
+
+    result.suppress(|name, _| name == "s#foo" || name == "corge");
+    assert!(result.is_empty());
+}
+
+#[test]
+fn compare_score() {
+    // Check that a comparison result is scored by the change kinds, severities and whitelist.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "qux int qux ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "a.symtypes",
+        concat!(
+            "s#foo struct foo { long a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms2.load_buffer(
+        "b.symtypes",
+        concat!(
+            "quux int quux ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.compare(&syms2, 1);
+    assert_eq!(result.export_file("quux"), Some(Path::new("b.symtypes")));
+
+    let score = result.score(&ScoreOptions::new());
+    assert_eq!(score.total(), 16);
+    assert_eq!(
+        score.module_scores().collect::<Vec<_>>(),
+        [(Path::new("a.symtypes"), 16)]
+    );
+    assert_eq!(
+        score.export_scores().collect::<Vec<_>>(),
+        [("bar", 4), ("baz", 4), ("qux", 8)]
+    );
+
+    let severity = |name: &str, _: Option<&Path>, _| if name == "bar" { 3 } else { 1 };
+    let options = ScoreOptions::new()
+        .weight(ChangeKind::Added, 1)
+        .whitelist(["bar", "quux"])
+        .severity(Arc::new(severity));
+    let score = result.score(&options);
+    assert_eq!(score.total(), 13);
+    let mut out = Vec::new();
+    let result = score.write_report(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Total badness: 13\n",
+            "Badness by module:\n",
+            " a.symtypes: 12\n",
+            " b.symtypes: 1\n", //
+        )
+    );
+}
+
+#[test]
+fn score_saturation() {
+    // Check that the total, module and export scores saturate instead of overflowing when large
+    // weights and severities are used.
+    let severity = |_: &str, _: Option<&Path>, _| u64::MAX / 2;
+    let options = ScoreOptions::new()
+        .weight(ChangeKind::Changed, 2)
+        .severity(Arc::new(severity));
+    let score = options.score_changes([
+        ("bar", Some(Path::new("a.symtypes")), ChangeKind::Changed),
+        ("bar", Some(Path::new("a.symtypes")), ChangeKind::Changed),
+        ("baz", Some(Path::new("a.symtypes")), ChangeKind::Changed),
+    ]);
+    assert_eq!(score.total(), u64::MAX);
+    assert_eq!(
+        score.module_scores().collect::<Vec<_>>(),
+        [(Path::new("a.symtypes"), u64::MAX)]
+    );
+    assert_eq!(
+        score.export_scores().collect::<Vec<_>>(),
+        [("bar", u64::MAX), ("baz", u64::MAX - 1)]
+    );
+}
+
+#[test]
+fn metrics() {
+    // Check that the metrics of a comparison result count the changes in total and by module.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a.symtypes",
+        concat!(
+            "bar int bar ( long )\n",
+            "baz int baz ( )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "a.symtypes",
+        concat!(
+            "s#foo struct foo { long a ; }\n",
+            "bar int bar ( long )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms2.load_buffer(
+        "b\"c.symtypes",
+        concat!(
+            "qux int qux ( s#foo )\n",
+            "s#foo struct foo { long a ; }\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.compare(&syms2, 1);
+
+    let metrics = Metrics::new(
+        syms2.num_exports(),
+        result.export_changes(),
+        &ScoreOptions::new(),
+    );
+    assert_eq!(metrics.num_exports(), 2);
+    assert_eq!(metrics.num_changes(ChangeKind::Removed), 1);
+    assert_eq!(metrics.num_changes(ChangeKind::Added), 1);
+    assert_eq!(metrics.num_changes(ChangeKind::Changed), 0);
+    assert_eq!(metrics.badness(), 8);
+
+    let mut out = Vec::new();
+    assert_ok!(metrics.write_report(&mut out));
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with(concat!(
+        "# HELP kabi_exports_total Number of exports.\n",
+        "# TYPE kabi_exports_total gauge\n",
+        "kabi_exports_total 2\n",
+        "# HELP kabi_exports_removed Number of removed exports.\n",
+        "# TYPE kabi_exports_removed gauge\n",
+        "kabi_exports_removed 1\n", //
+    )));
+    assert!(out.ends_with(concat!(
+        "# HELP kabi_module_badness_score Badness of the changes by module.\n",
+        "# TYPE kabi_module_badness_score gauge\n",
+        "kabi_module_badness_score{module=\"a\"} 8\n",
+        "kabi_module_badness_score{module=\"b\\\"c\"} 0\n", //
+    )));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_roundtrip() {
+    // Check that a corpus and a comparison result survive serialization and deserialization.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#foo struct foo { long a ; }\n",
+            "bar int bar ( s#foo )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let json = serde_json::to_string(&syms).unwrap();
+    let syms_copy: SymCorpus = serde_json::from_str(&json).unwrap();
+    let mut out = Vec::new();
+    let result = syms_copy.write_consolidated_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "F#a/test.symtypes bar\n", //
+        )
+    );
+
+    let compare_result = syms.compare(&syms2, 1);
+    let json = serde_json::to_string(&compare_result).unwrap();
+    let compare_result_copy: CompareResult = serde_json::from_str(&json).unwrap();
+    assert_eq!(compare_result, compare_result_copy);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_malformed() {
+    // Check that references between the collections of a serialized corpus are validated.
+    let result = serde_json::from_str::<SymCorpus>(concat!(
+        "{\"types\": {\"bar\": [[\"int\", \"bar\"]]},",
+        " \"files\": [{\"path\": \"test.symtypes\", \"records\": {\"bar\": 1}}],",
+        " \"exports\": {\"bar\": 0}}", //
+    ));
+    assert_eq!(
+        result.err().map(|err| err.to_string()),
+        Some("File 'test.symtypes' refers to undefined variant 1 of type 'bar'".to_string())
+    );
+
+    let result = serde_json::from_str::<SymCorpus>(concat!(
+        "{\"types\": {\"bar\": [[\"int\", \"bar\"]]},",
+        " \"files\": [{\"path\": \"test.symtypes\", \"records\": {\"bar\": 0}}],",
+        " \"exports\": {\"bar\": 1}}", //
+    ));
+    assert_eq!(
+        result.err().map(|err| err.to_string()),
+        Some("Export 'bar' refers to file 1 which does not define it".to_string())
+    );
 }
@@ -7,13 +7,7 @@ use crate::assert_ok;
 #[test]
 fn format_typedef() {
     // Check the pretty format of a typedef declaration.
-    let pretty = pretty_format_type(&vec![
-        Token::new_atom("typedef"),
-        Token::new_atom("unsigned"),
-        Token::new_atom("long"),
-        Token::new_atom("long"),
-        Token::new_atom("u64"),
-    ]);
+    let pretty = pretty_format_type(&["typedef", "unsigned", "long", "long", "u64"]);
     assert_eq!(
         pretty,
         crate::string_vec!(
@@ -25,16 +19,8 @@ fn format_typedef() {
 #[test]
 fn format_enum() {
     // Check the pretty format of an enum declaration.
-    let pretty = pretty_format_type(&vec![
-        Token::new_atom("enum"),
-        Token::new_atom("test"),
-        Token::new_atom("{"),
-        Token::new_atom("VALUE1"),
-        Token::new_atom(","),
-        Token::new_atom("VALUE2"),
-        Token::new_atom(","),
-        Token::new_atom("VALUE3"),
-        Token::new_atom("}"),
+    let pretty = pretty_format_type(&[
+        "enum", "test", "{", "VALUE1", ",", "VALUE2", ",", "VALUE3", "}",
     ]);
     assert_eq!(
         pretty,
@@ -51,17 +37,8 @@ fn format_enum() {
 #[test]
 fn format_struct() {
     // Check the pretty format of a struct declaration.
-    let pretty = pretty_format_type(&vec![
-        Token::new_atom("struct"),
-        Token::new_atom("test"),
-        Token::new_atom("{"),
-        Token::new_atom("int"),
-        Token::new_atom("ivalue"),
-        Token::new_atom(";"),
-        Token::new_atom("long"),
-        Token::new_atom("lvalue"),
-        Token::new_atom(";"),
-        Token::new_atom("}"),
+    let pretty = pretty_format_type(&[
+        "struct", "test", "{", "int", "ivalue", ";", "long", "lvalue", ";", "}",
     ]);
     assert_eq!(
         pretty,
@@ -77,17 +54,8 @@ fn format_struct() {
 #[test]
 fn format_union() {
     // Check the pretty format of a union declaration.
-    let pretty = pretty_format_type(&vec![
-        Token::new_atom("union"),
-        Token::new_atom("test"),
-        Token::new_atom("{"),
-        Token::new_atom("int"),
-        Token::new_atom("ivalue"),
-        Token::new_atom(";"),
-        Token::new_atom("long"),
-        Token::new_atom("lvalue"),
-        Token::new_atom(";"),
-        Token::new_atom("}"),
+    let pretty = pretty_format_type(&[
+        "union", "test", "{", "int", "ivalue", ";", "long", "lvalue", ";", "}",
     ]);
     assert_eq!(
         pretty,
@@ -103,16 +71,8 @@ fn format_union() {
 #[test]
 fn format_function() {
     // Check the pretty format of a function declaration.
-    let pretty = pretty_format_type(&vec![
-        Token::new_atom("void"),
-        Token::new_atom("test"),
-        Token::new_atom("("),
-        Token::new_atom("int"),
-        Token::new_atom("ivalue"),
-        Token::new_atom(","),
-        Token::new_atom("long"),
-        Token::new_atom("lvalue"),
-        Token::new_atom(")"),
+    let pretty = pretty_format_type(&[
+        "void", "test", "(", "int", "ivalue", ",", "long", "lvalue", ")",
     ]);
     assert_eq!(
         pretty,
@@ -128,7 +88,7 @@ fn format_function() {
 #[test]
 fn format_enum_constant() {
     // Check the pretty format of an enum constant declaration.
-    let pretty = pretty_format_type(&vec![Token::new_atom("7")]);
+    let pretty = pretty_format_type(&["7"]);
     assert_eq!(
         pretty,
         crate::string_vec!(
@@ -140,24 +100,9 @@ fn format_enum_constant() {
 #[test]
 fn format_nested() {
     // Check the pretty format of a nested declaration.
-    let pretty = pretty_format_type(&vec![
-        Token::new_atom("union"),
-        Token::new_atom("nested"),
-        Token::new_atom("{"),
-        Token::new_atom("struct"),
-        Token::new_atom("{"),
-        Token::new_atom("int"),
-        Token::new_atom("ivalue1"),
-        Token::new_atom(";"),
-        Token::new_atom("int"),
-        Token::new_atom("ivalue2"),
-        Token::new_atom(";"),
-        Token::new_atom("}"),
-        Token::new_atom(";"),
-        Token::new_atom("long"),
-        Token::new_atom("lvalue"),
-        Token::new_atom(";"),
-        Token::new_atom("}"),
+    let pretty = pretty_format_type(&[
+        "union", "nested", "{", "struct", "{", "int", "ivalue1", ";", "int", "ivalue2", ";", "}",
+        ";", "long", "lvalue", ";", "}",
     ]);
     assert_eq!(
         pretty,
@@ -176,17 +121,17 @@ fn format_nested() {
 #[test]
 fn format_imbalanced() {
     // Check the pretty format of a declaration with wrongly balanced brackets.
-    let pretty = pretty_format_type(&vec![
-        Token::new_atom("struct"),
-        Token::new_atom("imbalanced"),
-        Token::new_atom("{"),
-        Token::new_atom("{"),
-        Token::new_atom("}"),
-        Token::new_atom("}"),
-        Token::new_atom("}"),
-        Token::new_atom(";"),
-        Token::new_atom("{"),
-        Token::new_atom("{"),
+    let pretty = pretty_format_type(&[
+        "struct",
+        "imbalanced",
+        "{",
+        "{",
+        "}",
+        "}",
+        "}",
+        ";",
+        "{",
+        "{",
     ]);
     assert_eq!(
         pretty,
@@ -202,18 +147,90 @@ fn format_imbalanced() {
     );
 }
 
+#[test]
+fn format_spaces() {
+    // Check the pretty format of a nested declaration indented by spaces.
+    let pretty = pretty_format_type_with(
+        &[
+            "struct", "spaces", "{", "struct", "{", "int", "a", ";", "}", ";", "}",
+        ],
+        &FormatOptions::new().indent(IndentStyle::Spaces(2)),
+    );
+    assert_eq!(
+        pretty,
+        crate::string_vec!(
+            "struct spaces {",
+            "  struct {",
+            "    int a;",
+            "  };",
+            "}", //
+        )
+    );
+}
+
+#[test]
+fn format_line_width() {
+    // Check that a long line is wrapped according to the line width and its continuation is
+    // indented by one more level.
+    let pretty = pretty_format_type_with(
+        &[
+            "struct", "wrapped", "{", "const", "volatile", "unsigned", "long", "long", "value",
+            ";", "}",
+        ],
+        &FormatOptions::new().line_width(32),
+    );
+    assert_eq!(
+        pretty,
+        crate::string_vec!(
+            "struct wrapped {",
+            "\tconst volatile unsigned",
+            "\t\tlong long value;",
+            "}", //
+        )
+    );
+}
+
+#[test]
+fn format_corpus_type() {
+    // Check the pretty format of a type obtained from a loaded corpus.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert_eq!(
+        syms.pretty_type("test.symtypes", "s#foo"),
+        Some(crate::string_vec!(
+            "struct foo {",
+            "\tint a;",
+            "}", //
+        ))
+    );
+    assert_eq!(
+        syms.pretty_type_with(
+            "test.symtypes",
+            "bar",
+            &FormatOptions::new().indent(IndentStyle::Spaces(4))
+        ),
+        Some(crate::string_vec!(
+            "int bar (",
+            "    s#foo *",
+            ")", //
+        ))
+    );
+    assert_eq!(syms.pretty_type("test.symtypes", "s#baz"), None);
+    assert_eq!(syms.pretty_type("other.symtypes", "s#foo"), None);
+}
+
 #[test]
 fn format_typeref() {
     // Check the pretty format of a declaration with a reference to another type.
-    let pretty = pretty_format_type(&vec![
-        Token::new_atom("struct"),
-        Token::new_atom("typeref"),
-        Token::new_atom("{"),
-        Token::new_typeref("s#other"),
-        Token::new_atom("other"),
-        Token::new_atom(";"),
-        Token::new_atom("}"),
-    ]);
+    let pretty = pretty_format_type(&["struct", "typeref", "{", "s#other", "other", ";", "}"]);
     assert_eq!(
         pretty,
         crate::string_vec!(
@@ -229,27 +246,11 @@ fn format_removal() {
     // Check the diff format when a struct member is removed.
     let mut out = Vec::new();
     let result = write_type_diff(
-        &vec![
-            Token::new_atom("struct"),
-            Token::new_atom("test"),
-            Token::new_atom("{"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("}"),
-        ],
-        &vec![
-            Token::new_atom("struct"),
-            Token::new_atom("test"),
-            Token::new_atom("{"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("}"),
+        &[
+            "struct", "test", "{", "int", "ivalue1", ";", "int", "ivalue2", ";", "}",
         ],
+        &["struct", "test", "{", "int", "ivalue1", ";", "}"],
+        &DiffOptions::new(),
         &mut out,
     );
     assert_ok!(result);
@@ -270,37 +271,15 @@ fn format_removal_top() {
     // Check the diff format when data is removed at the top.
     let mut out = Vec::new();
     let result = write_type_diff(
-        &vec![
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue3"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue4"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue5"),
-            Token::new_atom(";"),
+        &[
+            "int", "ivalue1", ";", "int", "ivalue2", ";", "int", "ivalue3", ";", "int", "ivalue4",
+            ";", "int", "ivalue5", ";",
         ],
-        &vec![
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue3"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue4"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue5"),
-            Token::new_atom(";"),
+        &[
+            "int", "ivalue2", ";", "int", "ivalue3", ";", "int", "ivalue4", ";", "int", "ivalue5",
+            ";",
         ],
+        &DiffOptions::new(),
         &mut out,
     );
     assert_ok!(result);
@@ -321,37 +300,15 @@ fn format_removal_end() {
     // Check the diff format when data is removed at the end.
     let mut out = Vec::new();
     let result = write_type_diff(
-        &vec![
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue3"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue4"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue5"),
-            Token::new_atom(";"),
+        &[
+            "int", "ivalue1", ";", "int", "ivalue2", ";", "int", "ivalue3", ";", "int", "ivalue4",
+            ";", "int", "ivalue5", ";",
         ],
-        &vec![
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue3"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue4"),
-            Token::new_atom(";"),
+        &[
+            "int", "ivalue1", ";", "int", "ivalue2", ";", "int", "ivalue3", ";", "int", "ivalue4",
+            ";",
         ],
+        &DiffOptions::new(),
         &mut out,
     );
     assert_ok!(result);
@@ -372,52 +329,16 @@ fn format_max_context() {
     // Check the diff format shows changes separated by up to 6 lines of context as one hunk.
     let mut out = Vec::new();
     let result = write_type_diff(
-        &vec![
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue3"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue4"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue5"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue6"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue7"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue8"),
-            Token::new_atom(";"),
+        &[
+            "int", "ivalue1", ";", "int", "ivalue2", ";", "int", "ivalue3", ";", "int", "ivalue4",
+            ";", "int", "ivalue5", ";", "int", "ivalue6", ";", "int", "ivalue7", ";", "int",
+            "ivalue8", ";",
         ],
-        &vec![
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue3"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue4"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue5"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue6"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue7"),
-            Token::new_atom(";"),
+        &[
+            "int", "ivalue2", ";", "int", "ivalue3", ";", "int", "ivalue4", ";", "int", "ivalue5",
+            ";", "int", "ivalue6", ";", "int", "ivalue7", ";",
         ],
+        &DiffOptions::new(),
         &mut out,
     );
     assert_ok!(result);
@@ -442,58 +363,16 @@ fn format_max_context2() {
     // Check the diff format shows changes separated by more than 6 lines of context as two hunks.
     let mut out = Vec::new();
     let result = write_type_diff(
-        &vec![
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue3"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue4"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue5"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue6"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue7"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue8"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue9"),
-            Token::new_atom(";"),
+        &[
+            "int", "ivalue1", ";", "int", "ivalue2", ";", "int", "ivalue3", ";", "int", "ivalue4",
+            ";", "int", "ivalue5", ";", "int", "ivalue6", ";", "int", "ivalue7", ";", "int",
+            "ivalue8", ";", "int", "ivalue9", ";",
         ],
-        &vec![
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue3"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue4"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue5"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue6"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue7"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue8"),
-            Token::new_atom(";"),
+        &[
+            "int", "ivalue2", ";", "int", "ivalue3", ";", "int", "ivalue4", ";", "int", "ivalue5",
+            ";", "int", "ivalue6", ";", "int", "ivalue7", ";", "int", "ivalue8", ";",
         ],
+        &DiffOptions::new(),
         &mut out,
     );
     assert_ok!(result);
@@ -519,27 +398,11 @@ fn format_addition() {
     // Check the diff format when a struct member is added.
     let mut out = Vec::new();
     let result = write_type_diff(
-        &vec![
-            Token::new_atom("struct"),
-            Token::new_atom("test"),
-            Token::new_atom("{"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("}"),
-        ],
-        &vec![
-            Token::new_atom("struct"),
-            Token::new_atom("test"),
-            Token::new_atom("{"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("}"),
+        &["struct", "test", "{", "int", "ivalue1", ";", "}"],
+        &[
+            "struct", "test", "{", "int", "ivalue1", ";", "int", "ivalue2", ";", "}",
         ],
+        &DiffOptions::new(),
         &mut out,
     );
     assert_ok!(result);
@@ -560,24 +423,9 @@ fn format_modification() {
     // Check the diff format when a struct member is modified.
     let mut out = Vec::new();
     let result = write_type_diff(
-        &vec![
-            Token::new_atom("struct"),
-            Token::new_atom("test"),
-            Token::new_atom("{"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue1"),
-            Token::new_atom(";"),
-            Token::new_atom("}"),
-        ],
-        &vec![
-            Token::new_atom("struct"),
-            Token::new_atom("test"),
-            Token::new_atom("{"),
-            Token::new_atom("int"),
-            Token::new_atom("ivalue2"),
-            Token::new_atom(";"),
-            Token::new_atom("}"),
-        ],
+        &["struct", "test", "{", "int", "ivalue1", ";", "}"],
+        &["struct", "test", "{", "int", "ivalue2", ";", "}"],
+        &DiffOptions::new(),
         &mut out,
     );
     assert_ok!(result);
@@ -592,3 +440,37 @@ fn format_modification() {
         )
     );
 }
+
+#[test]
+fn format_retyped_members() {
+    // Check that changed declarations of struct members are aligned with each other by the member
+    // names.
+    let mut out = Vec::new();
+    let result = write_type_diff(
+        &[
+            "struct", "test", "{", "int", "a", ";", "int", "b", "[", "4", "]", ";", "int", "c",
+            ":", "3", ";", "}",
+        ],
+        &[
+            "struct", "test", "{", "long", "a", ";", "long", "b", "[", "4", "]", ";", "long", "c",
+            ":", "3", ";", "}",
+        ],
+        &DiffOptions::new(),
+        &mut out,
+    );
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "@@ -1,5 +1,5 @@\n",
+            " struct test {\n",
+            "-\tint a;\n",
+            "+\tlong a;\n",
+            "-\tint b [ 4 ];\n",
+            "+\tlong b [ 4 ];\n",
+            "-\tint c : 3;\n",
+            "+\tlong c : 3;\n",
+            " }\n", //
+        )
+    );
+}
@@ -0,0 +1,1098 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+//! Processing of `Module.symvers` data.
+//!
+//! A `Module.symvers` file is produced by a kernel build and lists all exports of the kernel and
+//! its modules. Each line has the form `<crc>\t<symbol>\t<module>\t<export-type>` and is optionally
+//! followed by `\t<namespace>`. The data can be validated, filtered, and two versions of it can be
+//! compared and scored using the same rules as symtypes comparisons. Changed CRCs can be explained
+//! by the type changes found by comparing the corresponding symtypes corpora.
+
+use crate::diff::DiffOptions;
+use crate::rules::{Rules, Severity};
+use crate::sym::{ChangeKind, CompareResult, Score, ScoreOptions, SymCorpus, TypeChange};
+use crate::{
+    debug, json_string, MapIOErr, ParseError, ParseErrorKind, PathFile, Warning, WarningKind,
+};
+use rustc_hash::FxHashSet;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{prelude::*, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// Export types recognized by [`SymversCorpus::validate()`].
+const EXPORT_TYPES: [&str; 5] = [
+    "EXPORT_SYMBOL",
+    "EXPORT_SYMBOL_GPL",
+    "EXPORT_SYMBOL_GPL_FUTURE",
+    "EXPORT_UNUSED_SYMBOL",
+    "EXPORT_UNUSED_SYMBOL_GPL",
+];
+
+/// A single export listed in `Module.symvers` data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SymversExport {
+    name: String,
+    crc: String,
+    module: String,
+    export_type: String,
+    namespace: Option<String>,
+    /// The index of the file and line from which the export was loaded, or [`None`] if the export
+    /// was generated.
+    location: Option<(usize, usize)>,
+}
+
+impl SymversExport {
+    /// Returns the name of the exported symbol.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the CRC of the export, as written in the data, for instance, `0x12345678`.
+    pub fn crc(&self) -> &str {
+        &self.crc
+    }
+
+    /// Returns the module which provides the export, `vmlinux` for the kernel itself.
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    /// Returns the export type, for instance, `EXPORT_SYMBOL_GPL`.
+    pub fn export_type(&self) -> &str {
+        &self.export_type
+    }
+
+    /// Returns the namespace of the export, or [`None`] if the export is not in any namespace.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Returns whether the export is available only to GPL-compatible modules.
+    pub fn is_gpl_only(&self) -> bool {
+        self.export_type.contains("_GPL")
+    }
+
+    /// Formats the export as a JSON object with its `name`, `crc`, `module`, `export_type` and
+    /// `namespace`, which is `null` if the export is not in any namespace.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"name\":{},\"crc\":{},\"module\":{},\"export_type\":{},\"namespace\":{}}}",
+            json_string(&self.name),
+            json_string(&self.crc),
+            json_string(&self.module),
+            json_string(&self.export_type),
+            self.namespace
+                .as_deref()
+                .map_or("null".to_string(), json_string)
+        )
+    }
+}
+
+/// A collection of exports loaded from `Module.symvers` data.
+///
+/// The exports are kept in the order in which they were loaded. If the same symbol is listed
+/// multiple times, lookups return its first occurrence and [`SymversCorpus::validate()`] reports
+/// the duplicates.
+#[derive(Debug, Default)]
+pub struct SymversCorpus {
+    files: Vec<PathBuf>,
+    exports: Vec<SymversExport>,
+}
+
+impl SymversCorpus {
+    /// Creates a new empty corpus.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads exports from a `Module.symvers` file, which can be compressed in one of the supported
+    /// formats. The path `-` denotes the standard input.
+    ///
+    /// When the crate is built with the `rpm` feature, the path can also name an RPM package which
+    /// contains exactly one symvers file, either `Module.symvers` or `symvers-<version>`, possibly
+    /// compressed.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            return self.load_buffer(path, io::stdin().lock());
+        }
+
+        let file = PathFile::open(path).map_err(|err| {
+            crate::Error::new_io(
+                &format!("Failed to open file '{}'", crate::display_path(path)),
+                err,
+            )
+        })?;
+        #[cfg(feature = "rpm")]
+        if crate::rpm::is_package(path) {
+            return self.load_rpm(path, file);
+        }
+        let reader = crate::Compression::from_path(path)
+            .reader(file)
+            .map_err(|err| {
+                crate::Error::new_io(
+                    &format!(
+                        "Failed to read symvers file '{}'",
+                        crate::display_path(path)
+                    ),
+                    err,
+                )
+            })?;
+        self.load_buffer(path, reader)
+            .map_err(|err| err.with_root(Path::new("")))
+    }
+
+    /// Loads exports from the symvers file in an RPM package, which is read from the specified
+    /// reader. The `path` indicates the origin of the package.
+    #[cfg(feature = "rpm")]
+    fn load_rpm<R: Read + Send>(&mut self, path: &Path, reader: R) -> Result<(), crate::Error> {
+        let err_desc = format!("Failed to read RPM package '{}'", crate::display_path(path));
+
+        let mut members = Vec::new();
+        crate::rpm::read_files(reader, is_symvers_file, |member_path, data| {
+            members.push((member_path, data));
+            true
+        })
+        .map_err(|err| crate::Error::new_io(&err_desc, err))?;
+
+        let (member_path, data) = match members.len() {
+            1 => members.pop().unwrap(),
+            0 => {
+                return Err(crate::Error::new_io(
+                    &err_desc,
+                    io::Error::new(io::ErrorKind::NotFound, "No symvers file found"),
+                ))
+            }
+            _ => {
+                let paths: Vec<_> = members
+                    .iter()
+                    .map(|(member_path, _)| format!("'{}'", member_path.display()))
+                    .collect();
+                return Err(crate::Error::new_io(
+                    &err_desc,
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Multiple symvers files found: {}", paths.join(", ")),
+                    ),
+                ));
+            }
+        };
+
+        let compression = crate::Compression::from_path(&member_path);
+        let reader = compression
+            .reader(data.as_slice())
+            .map_err(|err| crate::Error::new_io(&err_desc, err))?;
+        self.load_buffer(compression.strip_extension(&member_path), reader)
+    }
+
+    /// Loads exports from a specified reader with `Module.symvers` data.
+    ///
+    /// The `path` identifies the origin of the data and is used in error messages. A line which
+    /// doesn't have the expected number of fields is rejected with
+    /// [`ParseErrorKind::MalformedSymvers`]. Other problems in the data are not checked when
+    /// loading it, see [`SymversCorpus::validate()`].
+    pub fn load_buffer<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        reader: R,
+    ) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+        debug!("Loading symvers '{}'", path.display());
+
+        let file_idx = self.files.len();
+        let mut exports = Vec::new();
+        let reader = BufReader::new(reader);
+        for (line_idx, line) in reader.lines().enumerate() {
+            let line =
+                line.map_err(|err| crate::Error::new_io("Failed to read symvers data", err))?;
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<_> = line.split('\t').collect();
+            if fields.len() < 4 || fields.len() > 5 {
+                return Err(ParseError::new(
+                    ParseErrorKind::MalformedSymvers,
+                    path,
+                    line_idx,
+                    format!(
+                        "Expected 4 or 5 tab-separated fields, found {}",
+                        fields.len()
+                    ),
+                )
+                .with_source(line, 0)
+                .into());
+            }
+
+            let namespace = fields.get(4).copied().unwrap_or_default();
+            exports.push(SymversExport {
+                name: fields[1].to_string(),
+                crc: fields[0].to_string(),
+                module: fields[2].to_string(),
+                export_type: fields[3].to_string(),
+                namespace: (!namespace.is_empty()).then(|| namespace.to_string()),
+                location: Some((file_idx, line_idx)),
+            });
+        }
+
+        self.files.push(path.to_path_buf());
+        self.exports.append(&mut exports);
+        Ok(())
+    }
+
+    /// Creates `Module.symvers` data for the exports of a symtypes `corpus`, with their CRCs
+    /// computed in the same way as by genksyms.
+    ///
+    /// The module of each export is the path of the object file built along with the `.symtypes`
+    /// file which defines the export, for instance, `fs/ext4/inode.o`. The export type and
+    /// namespace are taken from symvers data loaded into the corpus by
+    /// [`SymCorpus::load_symvers()`]. Exports missing in such data are written as `EXPORT_SYMBOL`
+    /// without a namespace. The exports are sorted by their modules and names.
+    pub fn from_symtypes(corpus: &SymCorpus) -> Self {
+        let mut exports: Vec<_> = corpus
+            .export_views()
+            .filter_map(|export| {
+                let crc = corpus.export_crc(export.name())?;
+                Some(SymversExport {
+                    name: export.name().to_string(),
+                    crc: format!("0x{:08x}", crc),
+                    module: export.file().with_extension("o").display().to_string(),
+                    export_type: export.export_type().unwrap_or(EXPORT_TYPES[0]).to_string(),
+                    namespace: export.namespace().map(str::to_string),
+                    location: None,
+                })
+            })
+            .collect();
+        exports.sort_by(|a, b| (&a.module, &a.name).cmp(&(&b.module, &b.name)));
+        Self {
+            files: Vec::new(),
+            exports,
+        }
+    }
+
+    /// Returns all exports, in the order in which they were loaded.
+    pub fn exports(&self) -> &[SymversExport] {
+        &self.exports
+    }
+
+    /// Returns the export `name`, or [`None`] if the symbol is not exported.
+    pub fn get_export(&self, name: &str) -> Option<&SymversExport> {
+        self.exports.iter().find(|export| export.name == name)
+    }
+
+    /// Returns the sorted names of all namespaces used by the exports.
+    pub fn namespaces(&self) -> Vec<&str> {
+        let mut namespaces: Vec<_> = self
+            .exports
+            .iter()
+            .filter_map(|export| export.namespace())
+            .collect();
+        namespaces.sort();
+        namespaces.dedup();
+        namespaces
+    }
+
+    /// Returns an iterator over all exports in the namespace `namespace`, in the order in which
+    /// they were loaded.
+    pub fn namespace_exports<'a>(
+        &'a self,
+        namespace: &'a str,
+    ) -> impl Iterator<Item = &'a SymversExport> + 'a {
+        self.exports
+            .iter()
+            .filter(move |export| export.namespace() == Some(namespace))
+    }
+
+    /// Keeps only the exports for which `predicate` returns `true`.
+    pub fn retain<F: FnMut(&SymversExport) -> bool>(&mut self, predicate: F) {
+        self.exports.retain(predicate);
+    }
+
+    /// Keeps only the exports whose names are listed in `names`.
+    pub fn retain_whitelist<I: IntoIterator<Item = S>, S: AsRef<str>>(&mut self, names: I) {
+        let whitelist: FxHashSet<_> = names
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .collect();
+        self.retain(|export| whitelist.contains(&export.name));
+    }
+
+    /// Checks the exports for problems and returns a warning for each found one.
+    ///
+    /// The check reports symbols which are listed multiple times, CRCs which are malformed or zero,
+    /// as produced by a build without `CONFIG_MODVERSIONS` or with a failed genksyms run, and
+    /// unknown export types.
+    pub fn validate(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        let mut seen = FxHashSet::default();
+        for export in &self.exports {
+            let location = match export.location {
+                Some((file_idx, line_idx)) => format!(
+                    "{}:{}: ",
+                    crate::display_path(&self.files[file_idx]),
+                    line_idx + 1
+                ),
+                None => String::new(),
+            };
+            let mut warn = |kind, message: String| {
+                warnings.push(Warning {
+                    kind,
+                    message: format!("{}{}", location, message),
+                })
+            };
+
+            if !seen.insert(export.name.as_str()) {
+                warn(
+                    WarningKind::DuplicateExport,
+                    format!("Export '{}' is listed multiple times", export.name),
+                );
+            }
+            match crate::parse_crc(&export.crc) {
+                Some(0) => warn(
+                    WarningKind::MissingCrc,
+                    format!("Export '{}' has a zero CRC", export.name),
+                ),
+                Some(_) => {}
+                None => warn(
+                    WarningKind::MalformedCrc,
+                    format!(
+                        "Export '{}' has a malformed CRC '{}'",
+                        export.name, export.crc
+                    ),
+                ),
+            }
+            if !EXPORT_TYPES.contains(&export.export_type.as_str()) {
+                warn(
+                    WarningKind::UnknownExportType,
+                    format!(
+                        "Export '{}' has an unknown export type '{}'",
+                        export.name, export.export_type
+                    ),
+                );
+            }
+        }
+        warnings
+    }
+
+    /// Writes the exports in the `Module.symvers` format to the specified file. The path `-`
+    /// denotes the standard output.
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+
+        // Open the output file.
+        let writer: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stdout())
+        } else {
+            match PathFile::create(path) {
+                Ok(file) => Box::new(file),
+                Err(err) => {
+                    return Err(crate::Error::new_io(
+                        &format!("Failed to create file '{}'", crate::display_path(path)),
+                        err,
+                    ))
+                }
+            }
+        };
+
+        self.write_buffer(writer)
+    }
+
+    /// Writes the exports in the `Module.symvers` format to the provided output stream.
+    pub fn write_buffer<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write symvers data";
+
+        for export in &self.exports {
+            write!(
+                writer,
+                "{}\t{}\t{}\t{}",
+                export.crc, export.name, export.module, export.export_type
+            )
+            .map_io_err(err_desc)?;
+            if let Some(namespace) = &export.namespace {
+                write!(writer, "\t{}", namespace).map_io_err(err_desc)?;
+            }
+            writeln!(writer).map_io_err(err_desc)?;
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Compares the exports with `other_corpus` and returns the found differences.
+    pub fn compare(&self, other_corpus: &SymversCorpus) -> SymversCompareResult {
+        let exports = self.export_map();
+        let other_exports = other_corpus.export_map();
+
+        let mut result = SymversCompareResult::default();
+        for (name, export) in &exports {
+            match other_exports.get(name) {
+                Some(other_export) => {
+                    let change = SymversChange {
+                        old: (*export).clone(),
+                        new: (*other_export).clone(),
+                    };
+                    if change.is_abi_change() || change.module_changed() {
+                        result.changed_exports.push(change);
+                    }
+                }
+                None => result.removed_exports.push((*export).clone()),
+            }
+        }
+        for (name, other_export) in &other_exports {
+            if !exports.contains_key(name) {
+                result.added_exports.push((*other_export).clone());
+            }
+        }
+        result
+    }
+
+    /// Returns a map of the first occurrence of each export, sorted by the name.
+    fn export_map(&self) -> BTreeMap<&str, &SymversExport> {
+        let mut map = BTreeMap::new();
+        for export in &self.exports {
+            map.entry(export.name.as_str()).or_insert(export);
+        }
+        map
+    }
+}
+
+/// A change of an export present in both compared corpuses.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SymversChange {
+    old: SymversExport,
+    new: SymversExport,
+}
+
+impl SymversChange {
+    /// Returns the name of the export.
+    pub fn name(&self) -> &str {
+        &self.old.name
+    }
+
+    /// Returns the export in the first corpus.
+    pub fn old_export(&self) -> &SymversExport {
+        &self.old
+    }
+
+    /// Returns the export in the second corpus.
+    pub fn new_export(&self) -> &SymversExport {
+        &self.new
+    }
+
+    /// Returns whether the CRC of the export differs.
+    pub fn crc_changed(&self) -> bool {
+        self.old.crc != self.new.crc
+    }
+
+    /// Returns whether the export type differs.
+    pub fn export_type_changed(&self) -> bool {
+        self.old.export_type != self.new.export_type
+    }
+
+    /// Returns whether the namespace of the export differs.
+    pub fn namespace_changed(&self) -> bool {
+        self.old.namespace != self.new.namespace
+    }
+
+    /// Returns whether the export is provided by a different module. A move between modules is
+    /// reported but not considered a change of the ABI.
+    pub fn module_changed(&self) -> bool {
+        self.old.module != self.new.module
+    }
+
+    /// Returns whether the change can break existing users of the export.
+    pub fn is_abi_change(&self) -> bool {
+        self.crc_changed() || self.export_type_changed() || self.namespace_changed()
+    }
+
+    /// Returns human-readable descriptions of the individual differences.
+    pub fn describe_changes(&self) -> Vec<String> {
+        let mut descriptions = Vec::new();
+        if self.crc_changed() {
+            descriptions.push(format!(
+                "Export '{}' has changed its CRC from {} to {}",
+                self.name(),
+                self.old.crc,
+                self.new.crc
+            ));
+        }
+        if self.export_type_changed() {
+            descriptions.push(format!(
+                "Export '{}' has changed its export type from {} to {}",
+                self.name(),
+                self.old.export_type,
+                self.new.export_type
+            ));
+        }
+        if self.namespace_changed() {
+            let description = match (&self.old.namespace, &self.new.namespace) {
+                (Some(old), Some(new)) => format!("from namespace '{}' to '{}'", old, new),
+                (None, Some(new)) => format!("to namespace '{}'", new),
+                (Some(old), None) => format!("out of namespace '{}'", old),
+                (None, None) => unreachable!(),
+            };
+            descriptions.push(format!(
+                "Export '{}' has been moved {}",
+                self.name(),
+                description
+            ));
+        }
+        if self.module_changed() {
+            descriptions.push(format!(
+                "Export '{}' has been moved from module '{}' to '{}'",
+                self.name(),
+                self.old.module,
+                self.new.module
+            ));
+        }
+        descriptions
+    }
+}
+
+/// A result of comparing two [`SymversCorpus`] instances.
+#[derive(Debug, Default)]
+pub struct SymversCompareResult {
+    removed_exports: Vec<SymversExport>,
+    added_exports: Vec<SymversExport>,
+    changed_exports: Vec<SymversChange>,
+}
+
+impl SymversCompareResult {
+    /// Returns the exports present only in the first corpus, sorted by the name.
+    pub fn removed_exports(&self) -> &[SymversExport] {
+        &self.removed_exports
+    }
+
+    /// Returns the exports present only in the second corpus, sorted by the name.
+    pub fn added_exports(&self) -> &[SymversExport] {
+        &self.added_exports
+    }
+
+    /// Returns the changes of exports present in both corpuses, sorted by the name.
+    pub fn changed_exports(&self) -> &[SymversChange] {
+        &self.changed_exports
+    }
+
+    /// Returns whether no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self.removed_exports.is_empty()
+            && self.added_exports.is_empty()
+            && self.changed_exports.is_empty()
+    }
+
+    /// Calculates the badness of the changes according to the specified options.
+    ///
+    /// The module of each export is passed as its path to the severity function and is used to
+    /// group the badness by module. Moves of exports between modules are not scored.
+    pub fn score(&self, options: &ScoreOptions) -> Score {
+        options.score_changes(self.export_changes())
+    }
+
+    /// Returns the removed, added and changed exports, each with the module which provides it as
+    /// a path and the kind of the change. Moves of exports between modules are not included.
+    pub fn export_changes(&self) -> Vec<(&str, Option<&Path>, ChangeKind)> {
+        let removed = self
+            .removed_exports
+            .iter()
+            .map(|export| (export, ChangeKind::Removed));
+        let added = self
+            .added_exports
+            .iter()
+            .map(|export| (export, ChangeKind::Added));
+        let changed = self
+            .changed_exports
+            .iter()
+            .filter(|change| change.is_abi_change())
+            .map(|change| (&change.new, ChangeKind::Changed));
+        removed
+            .chain(added)
+            .chain(changed)
+            .map(|(export, kind)| (export.name.as_str(), Some(Path::new(&export.module)), kind))
+            .collect()
+    }
+
+    /// Writes a human-readable report about all changes to the provided output stream.
+    pub fn write_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+
+        for (exports, change) in [
+            (&self.removed_exports, "removed"),
+            (&self.added_exports, "added"),
+        ] {
+            for export in exports {
+                writeln!(writer, "Export '{}' has been {}", export.name, change)
+                    .map_io_err(err_desc)?;
+            }
+        }
+        for change in &self.changed_exports {
+            for description in change.describe_changes() {
+                writeln!(writer, "{}", description).map_io_err(err_desc)?;
+            }
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes a plain-ASCII report about all changes to the provided output stream, suitable for
+    /// sending by mail.
+    ///
+    /// The report starts with a summary of the numbers of removed, added and changed exports,
+    /// followed by a section for each non-empty group with its number in the heading. Removed and
+    /// added exports are listed with their modules, changed exports by the descriptions of their
+    /// changes. Prose is wrapped to 72 columns.
+    pub fn write_mail_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+
+        let mut lines = Vec::new();
+        lines.extend(crate::mail_heading("Summary", '='));
+        lines.push(String::new());
+        lines.push(format!("Removed exports: {}", self.removed_exports.len()));
+        lines.push(format!("Added exports: {}", self.added_exports.len()));
+        lines.push(format!("Changed exports: {}", self.changed_exports.len()));
+
+        for (exports, title) in [
+            (&self.removed_exports, "Removed exports"),
+            (&self.added_exports, "Added exports"),
+        ] {
+            if exports.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.extend(crate::mail_heading(
+                &format!("{} ({})", title, exports.len()),
+                '-',
+            ));
+            lines.push(String::new());
+            for export in exports {
+                lines.extend(crate::mail_wrap(
+                    &format!("{} ({})", export.name, export.module),
+                    "  ",
+                ));
+            }
+        }
+
+        if !self.changed_exports.is_empty() {
+            lines.push(String::new());
+            lines.extend(crate::mail_heading(
+                &format!("Changed exports ({})", self.changed_exports.len()),
+                '-',
+            ));
+            lines.push(String::new());
+        }
+        for change in &self.changed_exports {
+            for description in change.describe_changes() {
+                lines.extend(crate::mail_wrap(&description, ""));
+            }
+        }
+
+        for line in lines {
+            writeln!(writer, "{}", line).map_io_err(err_desc)?;
+        }
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes a report about all changes as a JSON object to the provided output stream.
+    ///
+    /// The object contains arrays `removed_exports` and `added_exports` with the exports in the
+    /// form described by [`SymversExport::to_json()`], and an array `changed_exports`. Each changed
+    /// export is described by its `name`, human-readable `descriptions` of the changes, and its
+    /// `old` and `new` form.
+    pub fn write_json_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+
+        let json_array = |items: Vec<String>| format!("[{}]", items.join(","));
+
+        write!(
+            writer,
+            "{{\"removed_exports\":{},\"added_exports\":{},\"changed_exports\":[",
+            json_array(
+                self.removed_exports
+                    .iter()
+                    .map(SymversExport::to_json)
+                    .collect()
+            ),
+            json_array(
+                self.added_exports
+                    .iter()
+                    .map(SymversExport::to_json)
+                    .collect()
+            )
+        )
+        .map_io_err(err_desc)?;
+        for (i, change) in self.changed_exports.iter().enumerate() {
+            let descriptions = change
+                .describe_changes()
+                .iter()
+                .map(|description| json_string(description))
+                .collect();
+            write!(
+                writer,
+                "{}{{\"name\":{},\"descriptions\":{},\"old\":{},\"new\":{}}}",
+                if i > 0 { "," } else { "" },
+                json_string(change.name()),
+                json_array(descriptions),
+                change.old.to_json(),
+                change.new.to_json()
+            )
+            .map_io_err(err_desc)?;
+        }
+        writeln!(writer, "]}}").map_io_err(err_desc)?;
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes a report about all changes in the JSON Lines format to the provided output stream.
+    ///
+    /// Each change is written as a separate JSON object on its own line, and the output is flushed
+    /// after each line, so that a consumer can process the report in a streaming fashion. The
+    /// member `change` of each object is `removed_export` or `added_export` for an export present
+    /// only in one corpus, which is described in the `export` member, or `changed_export` for
+    /// a changed export, which is described by the same members as in
+    /// [`Self::write_json_report()`].
+    pub fn write_jsonl_report<W: Write>(&self, writer: W) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+
+        for (exports, change) in [
+            (&self.removed_exports, "removed_export"),
+            (&self.added_exports, "added_export"),
+        ] {
+            for export in exports {
+                writeln!(
+                    writer,
+                    "{{\"change\":\"{}\",\"export\":{}}}",
+                    change,
+                    export.to_json()
+                )
+                .map_io_err(err_desc)?;
+                writer.flush().map_io_err(err_desc)?;
+            }
+        }
+        for change in &self.changed_exports {
+            let descriptions: Vec<_> = change
+                .describe_changes()
+                .iter()
+                .map(|description| json_string(description))
+                .collect();
+            writeln!(
+                writer,
+                "{{\"change\":\"changed_export\",\"name\":{},\"descriptions\":[{}],\"old\":{},\"new\":{}}}",
+                json_string(change.name()),
+                descriptions.join(","),
+                change.old.to_json(),
+                change.new.to_json()
+            )
+            .map_io_err(err_desc)?;
+            writer.flush().map_io_err(err_desc)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a report about the changes to the provided output stream in the format of the kABI
+    /// check script of the kernel-source repository, so that the output can be processed by
+    /// existing log parsers.
+    ///
+    /// Each removed export and each difference of the CRC, export type or namespace of a changed
+    /// export is described on a line in the form `KABI: symbol <name>(<module>) <change> (badness
+    /// <badness>)`, sorted by the export name. The badness is computed by [`Self::score()`] with
+    /// the default options. If `rules` are specified, changes rated [`Severity::Pass`] are left
+    /// out. The report ends with the line `KABI: aborting due to kabi changes.` if any reported
+    /// change is rated [`Severity::Fail`], or if any change is reported and no rules are
+    /// specified. Added exports and moves of exports between modules are not reported.
+    pub fn write_legacy_report<W: Write>(
+        &self,
+        rules: Option<&Rules>,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a comparison result";
+
+        let mut lines = Vec::new();
+        for export in &self.removed_exports {
+            lines.push((export, "lost".to_string()));
+        }
+        for change in self
+            .changed_exports
+            .iter()
+            .filter(|change| change.is_abi_change())
+        {
+            let (old, new) = (&change.old, &change.new);
+            if change.crc_changed() {
+                let message = format!("changed crc from {} to {}", old.crc, new.crc);
+                lines.push((old, message));
+            }
+            if change.export_type_changed() {
+                let message = format!(
+                    "changed type from {} to {}",
+                    old.export_type, new.export_type
+                );
+                lines.push((old, message));
+            }
+            if change.namespace_changed() {
+                let message = format!(
+                    "changed namespace from {} to {}",
+                    old.namespace.as_deref().unwrap_or("(none)"),
+                    new.namespace.as_deref().unwrap_or("(none)")
+                );
+                lines.push((old, message));
+            }
+        }
+        lines.sort_by(|(export, _), (other, _)| export.name.cmp(&other.name));
+
+        let score = self.score(&ScoreOptions::new());
+        let badness: BTreeMap<_, _> = score.export_scores().collect();
+        let mut abort = false;
+        for (export, message) in lines {
+            let severity =
+                rules.map(|rules| rules.severity(&export.name, Some(Path::new(&export.module))));
+            if severity == Some(Severity::Pass) {
+                continue;
+            }
+            abort |= severity.is_none_or(|severity| severity == Severity::Fail);
+            writeln!(
+                writer,
+                "KABI: symbol {}({}) {} (badness {})",
+                export.name,
+                export.module,
+                message,
+                badness.get(export.name.as_str()).copied().unwrap_or(0)
+            )
+            .map_io_err(err_desc)?;
+        }
+        if abort {
+            writeln!(writer, "KABI: aborting due to kabi changes.").map_io_err(err_desc)?;
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Links the exports whose CRC changed to the type changes responsible for them, provided as
+    /// `types`, the result of comparing the symtypes corpora from which the two symvers corpora
+    /// were built.
+    ///
+    /// An export whose CRC changed but which is not affected by any of the type changes is
+    /// included with an empty list of the type changes.
+    pub fn explain_crc_changes(&self, types: &CompareResult) -> CrcExplanations {
+        let explanations = self
+            .changed_exports
+            .iter()
+            .filter(|change| change.crc_changed())
+            .map(|change| CrcExplanation {
+                name: change.name().to_string(),
+                old_crc: change.old.crc.clone(),
+                new_crc: change.new.crc.clone(),
+                type_changes: types
+                    .changed_types()
+                    .iter()
+                    .filter(|type_change| {
+                        type_change
+                            .exports()
+                            .binary_search_by(|export| export.as_str().cmp(change.name()))
+                            .is_ok()
+                    })
+                    .cloned()
+                    .collect(),
+            })
+            .collect();
+        CrcExplanations { explanations }
+    }
+}
+
+/// A changed CRC of an export together with the type changes which caused it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CrcExplanation {
+    name: String,
+    old_crc: String,
+    new_crc: String,
+    type_changes: Vec<TypeChange>,
+}
+
+impl CrcExplanation {
+    /// Returns the name of the export.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the CRC of the export in the first corpus.
+    pub fn old_crc(&self) -> &str {
+        &self.old_crc
+    }
+
+    /// Returns the CRC of the export in the second corpus.
+    pub fn new_crc(&self) -> &str {
+        &self.new_crc
+    }
+
+    /// Returns the type changes which affect the export, sorted by the type name. The list is
+    /// empty if no type change explains the changed CRC.
+    pub fn type_changes(&self) -> &[TypeChange] {
+        &self.type_changes
+    }
+}
+
+/// Explanations of changed CRCs, computed by [`SymversCompareResult::explain_crc_changes()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CrcExplanations {
+    explanations: Vec<CrcExplanation>,
+}
+
+impl CrcExplanations {
+    /// Returns the explanations, sorted by the export name.
+    pub fn explanations(&self) -> &[CrcExplanation] {
+        &self.explanations
+    }
+
+    /// Returns whether no CRC has changed.
+    pub fn is_empty(&self) -> bool {
+        self.explanations.is_empty()
+    }
+
+    /// Writes a human-readable report of the changed CRCs and the diffs of the responsible types
+    /// to the provided output stream, computing the diffs using the specified options.
+    ///
+    /// If labels are set in the options, each diff is preceded by header lines with the labels
+    /// followed by a colon and the name of the changed type, as by
+    /// [`CompareResult::write_report_with()`].
+    pub fn write_report_with<W: Write>(
+        &self,
+        diff_options: &DiffOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a CRC explanation";
+
+        for (i, explanation) in self.explanations.iter().enumerate() {
+            // Add an empty line to separate individual exports.
+            if i > 0 {
+                writeln!(writer).map_io_err(err_desc)?;
+            }
+
+            writeln!(
+                writer,
+                "Export '{}' has changed its CRC from {} to {}",
+                explanation.name, explanation.old_crc, explanation.new_crc
+            )
+            .map_io_err(err_desc)?;
+            if explanation.type_changes.is_empty() {
+                writeln!(writer, "but no changed type in the symtypes explains it")
+                    .map_io_err(err_desc)?;
+                continue;
+            }
+
+            for type_change in &explanation.type_changes {
+                writeln!(writer, "because of a changed '{}':", type_change.name())
+                    .map_io_err(err_desc)?;
+                for description in type_change.describe_changes() {
+                    writeln!(writer, "{}", description).map_io_err(err_desc)?;
+                }
+                if type_change.enum_value_change().is_some() {
+                    continue;
+                }
+                match (diff_options.old_label(), diff_options.new_label()) {
+                    (Some(old_label), Some(new_label)) => {
+                        let type_options = diff_options.clone().labels(
+                            format!("{}:{}", old_label, type_change.name()),
+                            format!("{}:{}", new_label, type_change.name()),
+                        );
+                        type_change.write_diff_with(&type_options, writer.by_ref())?
+                    }
+                    _ => type_change.write_diff_with(diff_options, writer.by_ref())?,
+                }
+            }
+        }
+
+        writer.flush().map_io_err(err_desc)
+    }
+
+    /// Writes the changed CRCs and the diffs of the responsible types as a JSON object to the
+    /// provided output stream, computing the diffs using the specified options.
+    ///
+    /// The object contains an array `crc_changes`. Each changed CRC is described by the `name` of
+    /// the export, its `old_crc` and `new_crc`, and an array `changed_types`. Each changed type is
+    /// described by its `name`, human-readable `descriptions` of the changes and `hunks` of the
+    /// diff in the form described by [`crate::diff::Format::Json`].
+    pub fn write_json_report_with<W: Write>(
+        &self,
+        diff_options: &DiffOptions,
+        writer: W,
+    ) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(writer);
+        let err_desc = "Failed to write a CRC explanation";
+        let json_options = diff_options.clone().format(crate::diff::Format::Json);
+
+        write!(writer, "{{\"crc_changes\":[").map_io_err(err_desc)?;
+        for (i, explanation) in self.explanations.iter().enumerate() {
+            write!(
+                writer,
+                "{}{{\"name\":{},\"old_crc\":{},\"new_crc\":{},\"changed_types\":[",
+                if i > 0 { "," } else { "" },
+                json_string(&explanation.name),
+                json_string(&explanation.old_crc),
+                json_string(&explanation.new_crc)
+            )
+            .map_io_err(err_desc)?;
+            for (j, type_change) in explanation.type_changes.iter().enumerate() {
+                let descriptions: Vec<_> = type_change
+                    .describe_changes()
+                    .iter()
+                    .map(|description| json_string(description))
+                    .collect();
+                write!(
+                    writer,
+                    "{}{{\"name\":{},\"descriptions\":[{}],\"hunks\":",
+                    if j > 0 { "," } else { "" },
+                    json_string(type_change.name()),
+                    descriptions.join(",")
+                )
+                .map_io_err(err_desc)?;
+                if type_change.enum_value_change().is_none() {
+                    type_change.write_diff_with(&json_options, writer.by_ref())?;
+                } else {
+                    write!(writer, "[]").map_io_err(err_desc)?;
+                }
+                write!(writer, "}}").map_io_err(err_desc)?;
+            }
+            write!(writer, "]}}").map_io_err(err_desc)?;
+        }
+        writeln!(writer, "]}}").map_io_err(err_desc)?;
+
+        writer.flush().map_io_err(err_desc)
+    }
+}
+
+/// Returns whether the `path` names a symvers file, `Module.symvers` or `symvers-<version>` as
+/// installed in `/boot`, possibly compressed.
+#[cfg(feature = "rpm")]
+fn is_symvers_file(path: &Path) -> bool {
+    let path = crate::Compression::from_path(path).strip_extension(path);
+    path.file_name()
+        .and_then(|file_name| file_name.to_str())
+        .is_some_and(|file_name| file_name == "Module.symvers" || file_name.starts_with("symvers-"))
+}
+
+/// Reads a list of symbol names, such as a kABI whitelist, from the provided input stream.
+///
+/// Each non-empty line names one symbol. Leading and trailing whitespace is ignored, as are lines
+/// starting with `#` and section headers in the form `[name]`.
+pub fn read_symbol_list<R: Read>(reader: R) -> Result<Vec<String>, crate::Error> {
+    let mut names = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line.map_err(|err| crate::Error::new_io("Failed to read symbol list", err))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+            continue;
+        }
+        names.push(line.to_string());
+    }
+    Ok(names)
+}
@@ -0,0 +1,637 @@
+// Copyright (C) 2025 SUSE LLC <petr.pavlu@suse.com>
+// SPDX-License-Identifier: GPL-2.0-or-later
+
+use super::*;
+use crate::assert_ok;
+
+#[test]
+fn read_exports() {
+    // Check that exports are loaded with all their fields.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL_GPL\tFOO_NS\n",
+            "\n",
+            "0x23456789\tbar\tdrivers/bar\tEXPORT_SYMBOL\t\n",
+            "0x3456789a\tbaz\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert_eq!(symvers.exports().len(), 3);
+    let foo = symvers.get_export("foo").unwrap();
+    assert_eq!(foo.crc(), "0x12345678");
+    assert_eq!(foo.module(), "vmlinux");
+    assert_eq!(foo.export_type(), "EXPORT_SYMBOL_GPL");
+    assert_eq!(foo.namespace(), Some("FOO_NS"));
+    assert!(foo.is_gpl_only());
+    let bar = symvers.get_export("bar").unwrap();
+    assert_eq!(bar.module(), "drivers/bar");
+    assert_eq!(bar.namespace(), None);
+    assert!(!bar.is_gpl_only());
+    assert_eq!(symvers.get_export("baz").unwrap().namespace(), None);
+    assert!(symvers.get_export("qux").is_none());
+}
+
+#[test]
+fn read_malformed() {
+    // Check that a line with a wrong number of fields is rejected.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbar\n", //
+        )
+        .as_bytes(),
+    );
+    match result {
+        Err(crate::Error::Parse(err)) => assert_eq!(
+            err.to_string(),
+            concat!(
+                "Module.symvers:2:1: Expected 4 or 5 tab-separated fields, found 2\n",
+                "  0x23456789\tbar\n",
+                "  ^~~~~~~~~~", //
+            )
+        ),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert!(symvers.exports().is_empty());
+}
+
+#[test]
+fn namespaces() {
+    // Check that exports are grouped by their namespaces.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL_GPL\tFOO_NS\n",
+            "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL\tBAR_NS\n",
+            "0x3456789a\tbaz\tdrivers/baz\tEXPORT_SYMBOL_GPL\tFOO_NS\n",
+            "0x456789ab\tqux\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    assert_eq!(symvers.namespaces(), ["BAR_NS", "FOO_NS"]);
+    let names: Vec<_> = symvers
+        .namespace_exports("FOO_NS")
+        .map(|export| export.name())
+        .collect();
+    assert_eq!(names, ["foo", "baz"]);
+    assert_eq!(symvers.namespace_exports("QUX_NS").count(), 0);
+}
+
+#[test]
+fn validate() {
+    // Check that duplicate exports, bad CRCs and unknown export types are reported.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x00000000\tbar\tvmlinux\tEXPORT_SYMBOL_GPL\n",
+            "0xfoo\tbaz\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x3456789a\tqux\tvmlinux\tEXPORT_SYMBOL_FOO\n",
+            "0x456789ab\tfoo\tdrivers/foo\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let warnings = symvers.validate();
+    let warnings: Vec<_> = warnings
+        .iter()
+        .map(|warning| (warning.kind(), warning.message()))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            (
+                WarningKind::MissingCrc,
+                "Module.symvers:2: Export 'bar' has a zero CRC"
+            ),
+            (
+                WarningKind::MalformedCrc,
+                "Module.symvers:3: Export 'baz' has a malformed CRC '0xfoo'"
+            ),
+            (
+                WarningKind::UnknownExportType,
+                "Module.symvers:4: Export 'qux' has an unknown export type 'EXPORT_SYMBOL_FOO'"
+            ),
+            (
+                WarningKind::DuplicateExport,
+                "Module.symvers:5: Export 'foo' is listed multiple times"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn retain_whitelist() {
+    // Check that filtering by a whitelist keeps only the listed exports, in their original order.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL\tBAR_NS\n",
+            "0x3456789a\tbaz\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    symvers.retain_whitelist(["baz", "bar", "qux"]);
+    let mut out = Vec::new();
+    let result = symvers.write_buffer(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL\tBAR_NS\n",
+            "0x3456789a\tbaz\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+    );
+}
+
+#[test]
+fn from_symtypes() {
+    // Check that symvers data is generated from a symtypes corpus with computed CRCs, taking export
+    // types and namespaces from symvers data loaded into the corpus.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "lib/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; s#foo * next ; }\n",
+            "t#u32 typedef unsigned int u32\n",
+            "bar int bar ( s#foo * , t#u32 )\n",
+            "qux int qux ( t#u32 )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_buffer(
+        "fs/test2.symtypes",
+        concat!(
+            "s#foo struct foo { UNKNOWN }\n",
+            "baz int baz ( s#foo * )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let result = syms.load_symvers_buffer(
+        "Module.symvers",
+        "0x00000000\tbar\tvmlinux\tEXPORT_SYMBOL_GPL\tBAR_NS\n".as_bytes(),
+    );
+    assert_ok!(result);
+
+    let symvers = SymversCorpus::from_symtypes(&syms);
+    let mut out = Vec::new();
+    assert_ok!(symvers.write_buffer(&mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "0xc3d50522\tbaz\tfs/test2.o\tEXPORT_SYMBOL\n",
+            "0x35d049d8\tbar\tlib/test.o\tEXPORT_SYMBOL_GPL\tBAR_NS\n",
+            "0x14c5a578\tqux\tlib/test.o\tEXPORT_SYMBOL\n", //
+        )
+    );
+    assert!(symvers.validate().is_empty());
+}
+
+#[test]
+fn compare() {
+    // Check that removed, added and changed exports are reported.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "a/Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x3456789a\tbaz\tvmlinux\tEXPORT_SYMBOL\tBAZ_NS\n",
+            "0x456789ab\tqux\tdrivers/qux\tEXPORT_SYMBOL\n",
+            "0x56789abc\tquux\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers2 = SymversCorpus::new();
+    let result = symvers2.load_buffer(
+        "b/Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x2345678a\tbar\tvmlinux\tEXPORT_SYMBOL_GPL\n",
+            "0x3456789a\tbaz\tvmlinux\tEXPORT_SYMBOL\tBAZ2_NS\n",
+            "0x456789ab\tqux\tdrivers/qux2\tEXPORT_SYMBOL\n",
+            "0x6789abcd\tcorge\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let result = symvers.compare(&symvers2);
+    assert!(!result.is_empty());
+    let mut out = Vec::new();
+    let write_result = result.write_report(&mut out);
+    assert_ok!(write_result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Export 'quux' has been removed\n",
+            "Export 'corge' has been added\n",
+            "Export 'bar' has changed its CRC from 0x23456789 to 0x2345678a\n",
+            "Export 'bar' has changed its export type from EXPORT_SYMBOL to EXPORT_SYMBOL_GPL\n",
+            "Export 'baz' has been moved from namespace 'BAZ_NS' to 'BAZ2_NS'\n",
+            "Export 'qux' has been moved from module 'drivers/qux' to 'drivers/qux2'\n", //
+        )
+    );
+
+    // Check that the comparison with itself finds no differences.
+    assert!(symvers.compare(&symvers).is_empty());
+}
+
+#[test]
+fn compare_json_report() {
+    // Check that a symvers comparison result can be written as JSON.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "a/Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL\tBAR_NS\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers2 = SymversCorpus::new();
+    let result = symvers2.load_buffer(
+        "b/Module.symvers",
+        concat!(
+            "0x1234567a\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x3456789a\tbaz\tdrivers/baz\tEXPORT_SYMBOL_GPL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let compare_result = symvers.compare(&symvers2);
+    let mut out = Vec::new();
+    let result = compare_result.write_json_report(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "{\"removed_exports\":[{\"name\":\"bar\",\"crc\":\"0x23456789\",\"module\":\"vmlinux\",",
+            "\"export_type\":\"EXPORT_SYMBOL\",\"namespace\":\"BAR_NS\"}],",
+            "\"added_exports\":[{\"name\":\"baz\",\"crc\":\"0x3456789a\",\"module\":\"drivers/baz\",",
+            "\"export_type\":\"EXPORT_SYMBOL_GPL\",\"namespace\":null}],",
+            "\"changed_exports\":[{\"name\":\"foo\",",
+            "\"descriptions\":[\"Export 'foo' has changed its CRC from 0x12345678 to 0x1234567a\"],",
+            "\"old\":{\"name\":\"foo\",\"crc\":\"0x12345678\",\"module\":\"vmlinux\",",
+            "\"export_type\":\"EXPORT_SYMBOL\",\"namespace\":null},",
+            "\"new\":{\"name\":\"foo\",\"crc\":\"0x1234567a\",\"module\":\"vmlinux\",",
+            "\"export_type\":\"EXPORT_SYMBOL\",\"namespace\":null}}]}\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_jsonl_report() {
+    // Check that a symvers comparison result can be written in the JSON Lines format.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "a/Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL\tBAR_NS\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers2 = SymversCorpus::new();
+    let result = symvers2.load_buffer(
+        "b/Module.symvers",
+        concat!(
+            "0x1234567a\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x3456789a\tbaz\tdrivers/baz\tEXPORT_SYMBOL_GPL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let compare_result = symvers.compare(&symvers2);
+    let mut out = Vec::new();
+    let result = compare_result.write_jsonl_report(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "{\"change\":\"removed_export\",\"export\":{\"name\":\"bar\",\"crc\":\"0x23456789\",",
+            "\"module\":\"vmlinux\",\"export_type\":\"EXPORT_SYMBOL\",\"namespace\":\"BAR_NS\"}}\n",
+            "{\"change\":\"added_export\",\"export\":{\"name\":\"baz\",\"crc\":\"0x3456789a\",",
+            "\"module\":\"drivers/baz\",\"export_type\":\"EXPORT_SYMBOL_GPL\",\"namespace\":null}}\n",
+            "{\"change\":\"changed_export\",\"name\":\"foo\",",
+            "\"descriptions\":[\"Export 'foo' has changed its CRC from 0x12345678 to 0x1234567a\"],",
+            "\"old\":{\"name\":\"foo\",\"crc\":\"0x12345678\",\"module\":\"vmlinux\",",
+            "\"export_type\":\"EXPORT_SYMBOL\",\"namespace\":null},",
+            "\"new\":{\"name\":\"foo\",\"crc\":\"0x1234567a\",\"module\":\"vmlinux\",",
+            "\"export_type\":\"EXPORT_SYMBOL\",\"namespace\":null}}\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_mail_report() {
+    // Check that a symvers comparison result can be written as a plain-ASCII report for mail.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "a/Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL\tBAR_NS\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers2 = SymversCorpus::new();
+    let result = symvers2.load_buffer(
+        "b/Module.symvers",
+        concat!(
+            "0x1234567a\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x3456789a\tbaz\tdrivers/baz\tEXPORT_SYMBOL_GPL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let compare_result = symvers.compare(&symvers2);
+    let mut out = Vec::new();
+    let result = compare_result.write_mail_report(&mut out);
+    assert_ok!(result);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Summary\n",
+            "=======\n",
+            "\n",
+            "Removed exports: 1\n",
+            "Added exports: 1\n",
+            "Changed exports: 1\n",
+            "\n",
+            "Removed exports (1)\n",
+            "-------------------\n",
+            "\n",
+            "  bar (vmlinux)\n",
+            "\n",
+            "Added exports (1)\n",
+            "-----------------\n",
+            "\n",
+            "  baz (drivers/baz)\n",
+            "\n",
+            "Changed exports (1)\n",
+            "-------------------\n",
+            "\n",
+            "Export 'foo' has changed its CRC from 0x12345678 to 0x1234567a\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_score() {
+    // Check that a symvers comparison is scored by the modules of the exports, ignoring module
+    // moves.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "a/Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbar\tdrivers/bar\tEXPORT_SYMBOL\n",
+            "0x3456789a\tbaz\tdrivers/baz\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers2 = SymversCorpus::new();
+    let result = symvers2.load_buffer(
+        "b/Module.symvers",
+        concat!(
+            "0x1234567a\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x3456789a\tbaz\tdrivers/baz2\tEXPORT_SYMBOL\n",
+            "0x456789ab\tqux\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let result = symvers.compare(&symvers2);
+    let score = result.score(&ScoreOptions::new());
+    assert_eq!(score.total(), 12);
+    assert_eq!(
+        score.module_scores().collect::<Vec<_>>(),
+        [(Path::new("drivers/bar"), 8), (Path::new("vmlinux"), 4)]
+    );
+    assert_eq!(
+        score.export_scores().collect::<Vec<_>>(),
+        [("bar", 8), ("foo", 4)]
+    );
+
+    let score = result.score(&ScoreOptions::new().whitelist(["foo"]));
+    assert_eq!(score.total(), 4);
+}
+
+#[test]
+fn legacy_report() {
+    // Check that the legacy report lists lost and changed exports with their badness, and that
+    // changes rated PASS by rules are omitted and do not abort the check.
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "a/Module.symvers",
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbar\tdrivers/bar\tEXPORT_SYMBOL\n",
+            "0x3456789a\tbaz\tdrivers/baz\tEXPORT_SYMBOL\tBAZ_NS\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers2 = SymversCorpus::new();
+    let result = symvers2.load_buffer(
+        "b/Module.symvers",
+        concat!(
+            "0x1234567a\tfoo\tvmlinux\tEXPORT_SYMBOL_GPL\n",
+            "0x3456789a\tbaz\tdrivers/baz\tEXPORT_SYMBOL\n",
+            "0x456789ab\tqux\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let result = symvers.compare(&symvers2);
+    let mut out = Vec::new();
+    assert_ok!(result.write_legacy_report(None, &mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "KABI: symbol bar(drivers/bar) lost (badness 8)\n",
+            "KABI: symbol baz(drivers/baz) changed namespace from BAZ_NS to (none) (badness 4)\n",
+            "KABI: symbol foo(vmlinux) changed crc from 0x12345678 to 0x1234567a (badness 4)\n",
+            "KABI: symbol foo(vmlinux) changed type from EXPORT_SYMBOL to EXPORT_SYMBOL_GPL (badness 4)\n",
+            "KABI: aborting due to kabi changes.\n", //
+        )
+    );
+
+    let mut rules = Rules::new();
+    assert_ok!(rules.load_buffer("severities", "drivers/*  PASS\nfoo  WARN\n".as_bytes()));
+    let mut out = Vec::new();
+    assert_ok!(result.write_legacy_report(Some(&rules), &mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "KABI: symbol foo(vmlinux) changed crc from 0x12345678 to 0x1234567a (badness 4)\n",
+            "KABI: symbol foo(vmlinux) changed type from EXPORT_SYMBOL to EXPORT_SYMBOL_GPL (badness 4)\n", //
+        )
+    );
+}
+
+#[test]
+fn explain_crc_changes() {
+    // Check that changed CRCs are linked to the type changes which affect the exports, and that
+    // a changed CRC without a responsible type change is reported too.
+    let mut syms = SymCorpus::new();
+    let result = syms.load_buffer(
+        "a/test.symtypes",
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo * )\n",
+            "baz int baz ( int )\n",
+            "qux int qux ( int )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut syms2 = SymCorpus::new();
+    let result = syms2.load_buffer(
+        "b/test.symtypes",
+        concat!(
+            "s#foo struct foo { long a ; }\n",
+            "bar int bar ( s#foo * )\n",
+            "baz int baz ( int )\n",
+            "qux int qux ( int )\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_buffer(
+        "a/Module.symvers",
+        concat!(
+            "0x12345678\tbar\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbaz\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x3456789a\tqux\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+    let mut symvers2 = SymversCorpus::new();
+    let result = symvers2.load_buffer(
+        "b/Module.symvers",
+        concat!(
+            "0x1234567a\tbar\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x23456789\tbaz\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x3456789b\tqux\tvmlinux\tEXPORT_SYMBOL\n", //
+        )
+        .as_bytes(),
+    );
+    assert_ok!(result);
+
+    let types = syms.compare(&syms2, 1);
+    let explanations = symvers.compare(&symvers2).explain_crc_changes(&types);
+    let summary: Vec<_> = explanations
+        .explanations()
+        .iter()
+        .map(|explanation| {
+            (
+                explanation.name(),
+                explanation
+                    .type_changes()
+                    .iter()
+                    .map(TypeChange::name)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    assert_eq!(summary, [("bar", vec!["s#foo"]), ("qux", vec![])]);
+
+    let mut out = Vec::new();
+    assert_ok!(explanations.write_report_with(&DiffOptions::new(), &mut out));
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Export 'bar' has changed its CRC from 0x12345678 to 0x1234567a\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'a' changed type from int to long\n",
+            "@@ -1,3 +1,3 @@\n",
+            " struct foo {\n",
+            "-\tint a;\n",
+            "+\tlong a;\n",
+            " }\n",
+            "\n",
+            "Export 'qux' has changed its CRC from 0x3456789a to 0x3456789b\n",
+            "but no changed type in the symtypes explains it\n", //
+        )
+    );
+}
+
+#[cfg(all(feature = "rpm", feature = "gzip"))]
+#[test]
+fn load_rpm() {
+    // Check that the symvers file is found in an RPM package and that a package with multiple
+    // symvers files is rejected.
+    let package = crate::rpm::tests::make_package(
+        "gzip",
+        &[
+            ("./boot/vmlinuz-6.4.0-default", 0o100644, b"kernel"),
+            (
+                "./boot/symvers-6.4.0-default",
+                0o100644,
+                b"0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            ),
+        ],
+    );
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_rpm(Path::new("kernel-default.rpm"), package.as_slice());
+    assert_ok!(result);
+    let names: Vec<_> = symvers.exports().iter().map(SymversExport::name).collect();
+    assert_eq!(names, ["foo"]);
+
+    let package = crate::rpm::tests::make_package(
+        "gzip",
+        &[
+            ("./a/Module.symvers", 0o100644, b""),
+            ("./b/Module.symvers", 0o100644, b""),
+        ],
+    );
+    let mut symvers = SymversCorpus::new();
+    let result = symvers.load_rpm(Path::new("kernel-syms.rpm"), package.as_slice());
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Failed to read RPM package 'kernel-syms.rpm': Multiple symvers files found: 'a/Module.symvers', 'b/Module.symvers'"
+    );
+}
+
+#[test]
+fn read_whitelist() {
+    // Check that a symbol list skips empty lines, comments and section headers.
+    let result = read_symbol_list(
+        concat!(
+            "[kabi_whitelist]\n",
+            "# Networking\n",
+            "  foo\n",
+            "\n",
+            "bar\n", //
+        )
+        .as_bytes(),
+    );
+    assert_eq!(result.unwrap(), ["foo", "bar"]);
+}
@@ -3,8 +3,9 @@
 
 use std::ffi::OsStr;
 use std::fs;
+use std::io::Write;
 use std::path::Path;
-use std::process::{Command, ExitStatus};
+use std::process::{Command, ExitStatus, Stdio};
 
 struct RunResult {
     status: ExitStatus,
@@ -13,8 +14,23 @@ struct RunResult {
 }
 
 fn ksymtypes_run<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(args: I) -> RunResult {
-    let output = Command::new(env!("CARGO_BIN_EXE_ksymtypes"))
-        .args(args)
+    ksymtypes_run_in(env!("CARGO_MANIFEST_DIR"), args)
+}
+
+fn ksymtypes_run_in<P: AsRef<Path>, I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
+    dir: P,
+    args: I,
+) -> RunResult {
+    ksymtypes_run_with_env(dir, [], args)
+}
+
+fn ksymtypes_run_with_env<P: AsRef<Path>, I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
+    dir: P,
+    vars: impl IntoIterator<Item = (&'static str, &'static str)>,
+    args: I,
+) -> RunResult {
+    let output = ksymtypes_command(dir, args)
+        .envs(vars)
         .output()
         .expect("failed to execute ksymtypes");
     RunResult {
@@ -24,6 +40,68 @@ fn ksymtypes_run<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(args: I) -> RunResu
     }
 }
 
+fn ksymtypes_run_with_input<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
+    args: I,
+    input: &str,
+) -> RunResult {
+    let mut child = ksymtypes_command(env!("CARGO_MANIFEST_DIR"), args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to execute ksymtypes");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    RunResult {
+        status: output.status,
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+fn ksymtypes_command<P: AsRef<Path>, I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
+    dir: P,
+    args: I,
+) -> Command {
+    // Point the user configuration to a non-existent directory and clear the settings environment
+    // variables, so that the tests are not affected by the configuration of the user running them.
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ksymtypes"));
+    command
+        .args(args)
+        .current_dir(dir)
+        .env(
+            "XDG_CONFIG_HOME",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-config-home"),
+        )
+        .env_remove("KSYMTYPES_JOBS")
+        .env_remove("KSYMTYPES_FORMAT")
+        .env_remove("KSYMTYPES_COLOR")
+        .env_remove("KSYMTYPES_RULES")
+        .env_remove("KSYMTYPES_WHITELIST")
+        .env_remove("KSYMTYPES_MAX_BADNESS")
+        .env_remove("GITLAB_CI")
+        .env_remove("KBUILD_OUTPUT");
+    command
+}
+
+fn ksymvers_run<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(args: I) -> RunResult {
+    let output = Command::new(env!("CARGO_BIN_EXE_ksymvers"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("failed to execute ksymvers");
+    RunResult {
+        status: output.status,
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
 #[test]
 fn compare_cmd() {
     // Check that the compare command trivially works.
@@ -32,12 +110,12 @@ fn compare_cmd() {
         "tests/compare_cmd/a.symtypes",
         "tests/compare_cmd/b.symtypes",
     ]);
-    assert!(result.status.success());
+    assert_eq!(result.status.code(), Some(1));
     assert_eq!(
         result.stdout,
         concat!(
             "The following '1' exports are different:\n",
-            " foo\n",
+            " foo (function)\n",
             "\n",
             "because of a changed 'foo':\n",
             "@@ -1,3 +1,3 @@\n",
@@ -51,22 +129,60 @@ fn compare_cmd() {
 }
 
 #[test]
-fn compare_cmd_dash_dash() {
-    // Check that operands of the compare command can be specified after '--'.
+fn compare_cmd_jsonl() {
+    // Check that the compare command can write a report in the JSON Lines format.
     let result = ksymtypes_run([
         "compare",
-        "--",
+        "--format=jsonl",
         "tests/compare_cmd/a.symtypes",
         "tests/compare_cmd/b.symtypes",
     ]);
-    assert!(result.status.success());
+    assert_eq!(result.status.code(), Some(1));
     assert_eq!(
         result.stdout,
         concat!(
-            "The following '1' exports are different:\n",
-            " foo\n",
+            "{\"change\":\"changed_type\",\"name\":\"foo\",\"exports\":[\"foo\"],",
+            "\"descriptions\":[],",
+            "\"old_tokens\":[\"void\",\"foo\",\"(\",\"int\",\"a\",\")\"],",
+            "\"new_tokens\":[\"void\",\"foo\",\"(\",\"long\",\"a\",\")\"],",
+            "\"hunks\":[{\"pos_a\":1,\"len_a\":3,\"pos_b\":1,\"len_b\":3,",
+            "\"lines\":[{\"type\":\"context\",\"text\":\"void foo (\"},",
+            "{\"type\":\"removed\",\"text\":\"\\tint a\"},",
+            "{\"type\":\"added\",\"text\":\"\\tlong a\"},",
+            "{\"type\":\"context\",\"text\":\")\"}]}]}\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_mail() {
+    // Check that the compare command can write a report for mail.
+    let result = ksymtypes_run([
+        "compare",
+        "--format=mail",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "Summary\n",
+            "=======\n",
             "\n",
-            "because of a changed 'foo':\n",
+            "Removed exports: 0\n",
+            "Added exports: 0\n",
+            "Changed exports: 1\n",
+            "Changed types: 1\n",
+            "\n",
+            "Changed types (1)\n",
+            "-----------------\n",
+            "\n",
+            "Type 'foo' has changed, affecting 1 export: foo (function).\n",
+            "\n",
+            "--- a:foo\n",
+            "+++ b:foo\n",
             "@@ -1,3 +1,3 @@\n",
             " void foo (\n",
             "-\tint a\n",
@@ -75,50 +191,2759 @@ fn compare_cmd_dash_dash() {
         )
     );
     assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--format=mail",
+        "--score",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--score' option cannot be used with '--format=mail'\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+/// Runs the compare command on the test corpuses and returns the comparison result saved as JSON.
+fn saved_report() -> String {
+    let result = ksymtypes_run([
+        "compare",
+        "--format=json",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    result.stdout
 }
 
+#[cfg(feature = "serde")]
 #[test]
-fn consolidate_cmd() {
-    // Check that the consolidate command trivially works.
-    let result = ksymtypes_run(["consolidate", "tests/consolidate_cmd"]);
-    assert!(result.status.success());
+fn report_cmd_markdown() {
+    // Check that the report command renders a comparison result saved as JSON.
+    let result = ksymtypes_run_with_input(["report", "--format=markdown", "-"], &saved_report());
+    assert_eq!(result.status.code(), Some(1));
     assert_eq!(
         result.stdout,
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "bar int bar ( s#foo )\n",
-            "baz int baz ( s#foo )\n",
-            "F#a.symtypes bar\n",
-            "F#b.symtypes baz\n", //
+            "# kABI changes\n",
+            "\n",
+            "| Change | Count |\n",
+            "| --- | ---: |\n",
+            "| Removed exports | 0 |\n",
+            "| Added exports | 0 |\n",
+            "| Changed exports | 1 |\n",
+            "| Changed types | 1 |\n",
+            "\n",
+            "## Changed types (1)\n",
+            "\n",
+            "### `foo`\n",
+            "\n",
+            "Affects 1 export: `foo` (function).\n",
+            "\n",
+            "```diff\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void foo (\n",
+            "-\tint a\n",
+            "+\tlong a\n",
+            " )\n",
+            "```\n", //
         )
     );
     assert_eq!(result.stderr, "");
 }
 
+#[cfg(feature = "serde")]
 #[test]
-fn consolidate_cmd_output() {
-    // Check that the consolidate command writes its result to the file specified by --output.
-    let output_path =
-        Path::new(env!("CARGO_TARGET_TMPDIR")).join("consolidate_cmd_output.symtypes");
-    fs::remove_file(&output_path).ok();
+fn report_cmd_json_roundtrip() {
+    // Check that a comparison result can be saved again unchanged.
+    let saved = saved_report();
+    let result = ksymtypes_run_with_input(["report", "--format=json", "-"], &saved);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(result.stdout, saved);
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn report_cmd_rejected_format() {
+    // Check that the metrics format, which needs the number of all exports, is rejected.
+    let result = ksymtypes_run_with_input(["report", "--format=metrics", "-"], &saved_report());
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The 'metrics' format cannot be used with the report command\n",
+            "Try 'ksymtypes report --help' for more information.\n", //
+        )
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn report_cmd_malformed() {
+    // Check that a malformed comparison result is reported with its location.
+    let result = ksymtypes_run_with_input(["report", "-"], "{\"removed_exports\":[]}");
+    assert_eq!(result.status.code(), Some(3));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Failed to read the comparison result from '-': ",
+            "-:1:22: missing field `added_exports`\n",
+            "  {\"removed_exports\":[]}\n",
+            "                       ^\n", //
+        )
+    );
+}
+
+#[cfg(not(feature = "serde"))]
+#[test]
+fn report_cmd_no_serde() {
+    // Check that reading a comparison result is refused without the serde feature.
+    let result = ksymtypes_run_with_input(["report", "-"], &saved_report());
+    assert_eq!(result.status.code(), Some(3));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Failed to read the comparison result from '-': ",
+            "reading JSON requires the 'serde' feature\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_exit_status() {
+    // Check that the compare command distinguishes no differences, found differences, usage errors
+    // and operational errors by its exit status.
     let result = ksymtypes_run([
-        AsRef::<OsStr>::as_ref("consolidate"),
-        "--output".as_ref(),
-        &output_path.as_ref(),
-        "tests/consolidate_cmd".as_ref(),
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/a.symtypes",
     ]);
-    assert!(result.status.success());
+    assert_eq!(result.status.code(), Some(0));
     assert_eq!(result.stdout, "");
     assert_eq!(result.stderr, "");
-    let output_data = fs::read_to_string(output_path).expect("Unable to read the output file");
+
+    let result = ksymtypes_run([
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+
+    let result = ksymtypes_run(["compare", "--unknown", "tests/compare_cmd/a.symtypes"]);
+    assert_eq!(result.status.code(), Some(2));
+
+    let result = ksymtypes_run(["--unknown", "compare"]);
+    assert_eq!(result.status.code(), Some(2));
+
+    let result = ksymtypes_run([
+        "compare",
+        "tests/compare_cmd/missing.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(3));
+    assert_eq!(result.stdout, "");
+
+    let result = ksymtypes_run(["consolidate", "tests/consolidate_cmd_errors"]);
+    assert_eq!(result.status.code(), Some(3));
+}
+
+#[test]
+fn command_abbreviations() {
+    // Check that commands can be specified by an alias or an unambiguous prefix, and that a typo
+    // in a command name is reported with a suggestion.
+    let result = ksymtypes_run([
+        "cmp",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/a.symtypes",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run(["conso", "--help"]);
+    assert!(result.status.success());
+    assert!(result.stdout.starts_with("Usage: ksymtypes consolidate "));
+
+    let result = ksymtypes_run(["co"]);
+    assert_eq!(result.status.code(), Some(2));
     assert_eq!(
-        output_data,
+        result.stderr,
         concat!(
-            "s#foo struct foo { int a ; }\n",
-            "bar int bar ( s#foo )\n",
-            "baz int baz ( s#foo )\n",
-            "F#a.symtypes bar\n",
-            "F#b.symtypes baz\n", //
+            "Ambiguous command 'co', possible commands: 'consolidate', 'compare', 'completions'\n",
+            "Try 'ksymtypes --help' for more information.\n", //
+        )
+    );
+
+    let result = ksymtypes_run(["comapre"]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Unrecognized command 'comapre', did you mean 'compare'?\n",
+            "Try 'ksymtypes --help' for more information.\n", //
+        )
+    );
+
+    let result = ksymtypes_run(["frobnicate"]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Unrecognized command 'frobnicate'\n",
+            "Try 'ksymtypes --help' for more information.\n", //
+        )
+    );
+
+    let result = ksymvers_run([
+        "cmp",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/a.symvers",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stderr, "");
+
+    let result = ksymvers_run(["filtr", "tests/ksymvers_cmd/a.symvers"]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Unrecognized command 'filtr', did you mean 'filter'?\n",
+            "Try 'ksymvers --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_stdin() {
+    // Check that the compare command reads one of its inputs from the standard input when it is
+    // specified as '-'.
+    let input = fs::read_to_string("tests/compare_cmd/a.symtypes").unwrap();
+    let result = ksymtypes_run_with_input(["compare", "-", "tests/compare_cmd/b.symtypes"], &input);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that the standard input cannot be used for both inputs.
+    let result = ksymtypes_run_with_input(["compare", "-", "-"], &input);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        "The standard input can be used only for one compare source\n"
+    );
+}
+
+#[test]
+fn compare_cmd_invalid_args() {
+    // Check that all problems in the arguments of the compare command are reported at once,
+    // followed by a usage hint.
+    let result = ksymtypes_run([
+        "compare",
+        "--jobs=0",
+        "--bogus",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+        "tests/compare_cmd/c.symtypes",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid value for '--jobs=0': must be positive\n",
+            "Unrecognized compare option '--bogus'\n",
+            "Excess compare argument 'tests/compare_cmd/c.symtypes' specified\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+
+    // Check that all missing operands are reported.
+    let result = ksymtypes_run(["compare"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The first compare source is missing\n",
+            "The second compare source is missing\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_dash_dash() {
+    // Check that operands of the compare command can be specified after '--'.
+    let result = ksymtypes_run([
+        "compare",
+        "--",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function)\n",
+            "\n",
+            "because of a changed 'foo':\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void foo (\n",
+            "-\tint a\n",
+            "+\tlong a\n",
+            " )\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_intermixed_options() {
+    // Check that options of the compare command can be specified after operands.
+    let result = ksymtypes_run([
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "--format",
+        "json",
+        "tests/compare_cmd/b.symtypes",
+        "--no-validate",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result.stdout.starts_with("{\"removed_exports\":[]"));
+    assert_eq!(result.stderr, "");
+
+    // Check that arguments after '--' are treated as operands, even if they look like options.
+    let result = ksymtypes_run([
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "--",
+        "--no-validate",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert!(result
+        .stderr
+        .starts_with("Failed to read symtypes from '--no-validate': "));
+
+    // Check that a lone '-' is treated as an operand, denoting the standard input.
+    let input = fs::read_to_string("tests/compare_cmd/b.symtypes").unwrap();
+    let result = ksymtypes_run_with_input(["compare", "tests/compare_cmd/a.symtypes", "-"], &input);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_bundled_options() {
+    // Check that bundled short options are split into separate options.
+    let result = ksymtypes_run([
+        "-dd",
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/a.symtypes",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stdout, "");
+    assert!(result.stderr.starts_with(
+        "debug: Compare 'tests/compare_cmd/a.symtypes' and 'tests/compare_cmd/a.symtypes'\n"
+    ));
+
+    // Check that a short option which takes a value ends the bundle.
+    let result = ksymtypes_run(["consolidate", "-hj8"]);
+    assert!(result.status.success());
+    assert!(result.stdout.starts_with("Usage: ksymtypes consolidate "));
+    assert_eq!(result.stderr, "");
+
+    // Check that a bundle with an unknown option is reported as a whole.
+    let result = ksymtypes_run(["-dx", "compare"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Unrecognized global option '-dx'\n",
+            "Try 'ksymtypes --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_log_level() {
+    // Check that debug messages are logged when --log-level=debug is specified.
+    let result = ksymtypes_run([
+        "--log-level=debug",
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/a.symtypes",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stdout, "");
+    assert!(result.stderr.starts_with(
+        "debug: Compare 'tests/compare_cmd/a.symtypes' and 'tests/compare_cmd/a.symtypes'\n"
+    ));
+
+    // Check that --log-level takes precedence over --debug.
+    let result = ksymtypes_run([
+        "-d",
+        "--log-level=warn",
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/a.symtypes",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+
+    // Check that log messages are prefixed with timestamps when --log-timestamps is specified.
+    let result = ksymtypes_run([
+        "-d",
+        "--log-timestamps",
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/a.symtypes",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stdout, "");
+    let first_line = result.stderr.lines().next().unwrap();
+    assert!(first_line.starts_with('['));
+    assert!(first_line.ends_with(
+        "] debug: Compare 'tests/compare_cmd/a.symtypes' and 'tests/compare_cmd/a.symtypes'"
+    ));
+
+    // Check that an invalid level is reported.
+    let result = ksymtypes_run(["--log-level=verbose", "compare"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid value for '--log-level=verbose': unknown level 'verbose'\n",
+            "Try 'ksymtypes --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_timing_json() {
+    // Check that the durations of operations are output as a JSON object when --timing=json is
+    // specified.
+    let result = ksymtypes_run([
+        "--timing=json",
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert!(result.stderr.starts_with(concat!(
+        "{\"phases\":[{\"phase\":\"load\",",
+        "\"description\":\"Reading symtypes from 'tests/compare_cmd/a.symtypes'\",", //
+    )));
+    assert!(result
+        .stderr
+        .contains("\"loads\":[{\"path\":\"tests/compare_cmd/a.symtypes\","));
+    assert!(result.stderr.ends_with("}]}]}\n"));
+    assert_eq!(result.stderr.lines().count(), 1);
+
+    // Check that an invalid format is reported.
+    let result = ksymtypes_run(["--timing=yaml", "compare"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid value for '--timing=yaml': unknown format 'yaml'\n",
+            "Try 'ksymtypes --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn completions_cmd() {
+    // Check that the completions command prints scripts which cover the options of all commands.
+    let result = ksymtypes_run(["completions", "bash"]);
+    assert!(result.status.success());
+    assert!(result
+        .stdout
+        .starts_with("# bash completion for ksymtypes\n"));
+    assert!(result.stdout.contains(concat!(
+        "        consolidate)\n",
+        "            opts='-h --help -j --jobs= --crcs --input-dialect= --max-errors= --no-validate ",
+        "-o --output= ",
+        "--warnings-as-errors'\n",
+        "            words=''\n", //
+    )));
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run(["completions", "fish"]);
+    assert!(result.status.success());
+    assert!(result.stdout.contains(concat!(
+        "complete -c ksymtypes -n '__fish_seen_subcommand_from compare' -l diff-moves ",
+        "-d 'annotate blocks of lines moved in type diffs'\n", //
+    )));
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run(["completions", "zsh"]);
+    assert!(result.status.success());
+    assert!(result.stdout.starts_with("#compdef ksymtypes\n"));
+    assert!(result
+        .stdout
+        .contains("'--no-config:do not read configuration files'"));
+    assert_eq!(result.stderr, "");
+
+    // Check that an unknown shell is reported.
+    let result = ksymtypes_run(["completions", "tcsh"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "Unrecognized completions shell 'tcsh'\n");
+}
+
+#[test]
+fn version_option() {
+    // Check that --version outputs only the version by default.
+    let result = ksymtypes_run(["--version"]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        format!("ksymtypes {}\n", env!("CARGO_PKG_VERSION"))
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that --version --verbose outputs also the build details.
+    let result = ksymtypes_run(["--version", "--verbose"]);
+    assert!(result.status.success());
+    let lines = result.stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], format!("ksymtypes {}", env!("CARGO_PKG_VERSION")));
+    assert!(lines[1].starts_with("commit: "));
+    assert!(lines[2].starts_with("build date: "));
+    assert!(lines[3].starts_with("features: "));
+    assert_eq!(lines[3].contains("gzip"), cfg!(feature = "gzip"));
+    assert!(lines[4].starts_with("input formats: symtypes, "));
+    assert_eq!(lines[4].contains("tar"), cfg!(feature = "tar"));
+    assert_eq!(lines[4].contains("rpm"), cfg!(feature = "rpm"));
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_score() {
+    // Check that the compare command appends the badness score when --score is specified.
+    let result = ksymtypes_run([
+        "compare",
+        "--score",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function)\n",
+            "\n",
+            "because of a changed 'foo':\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void foo (\n",
+            "-\tint a\n",
+            "+\tlong a\n",
+            " )\n",
+            "\n",
+            "Total badness: 4\n",
+            "Badness by module:\n",
+            " tests/compare_cmd/a.symtypes: 4\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn compare_cmd_gzip() {
+    // Check that the compare command transparently decompresses gzip input files.
+    let input_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("compare_cmd_gzip.symtypes.gz");
+    let input_data = fs::read("tests/compare_cmd/a.symtypes").expect("Unable to read the input");
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&input_data).unwrap();
+    fs::write(&input_path, encoder.finish().unwrap()).expect("Unable to write the input file");
+    let result = ksymtypes_run([
+        AsRef::<OsStr>::as_ref("compare"),
+        input_path.as_ref(),
+        "tests/compare_cmd/b.symtypes".as_ref(),
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function)\n",
+            "\n",
+            "because of a changed 'foo':\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void foo (\n",
+            "-\tint a\n",
+            "+\tlong a\n",
+            " )\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_incomplete_type() {
+    // Check that the compare command warns about a changed type which has an incomplete definition
+    // in one of the corpuses.
+    let result = ksymtypes_run([
+        "compare",
+        "tests/compare_cmd_incomplete/a.symtypes",
+        "tests/compare_cmd_incomplete/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "@@ -1,3 +1,3 @@\n",
+            " struct foo {\n",
+            "-\tUNKNOWN\n",
+            "+\tint a;\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(
+        result.stderr,
+        "Warning: Type 's#foo' has an incomplete definition in one of the corpuses\n"
+    );
+}
+
+#[test]
+fn compare_cmd_quiet() {
+    // Check that warnings and timings are not output when --quiet is specified.
+    let result = ksymtypes_run([
+        "--quiet",
+        "--timing",
+        "compare",
+        "tests/compare_cmd_incomplete/a.symtypes",
+        "tests/compare_cmd_incomplete/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that warnings treated as errors are still reported.
+    let result = ksymtypes_run([
+        "-q",
+        "compare",
+        "--warnings-as-errors",
+        "tests/compare_cmd_incomplete/a.symtypes",
+        "tests/compare_cmd_incomplete/b.symtypes",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "Found 1 warning(s) treated as errors\n");
+}
+
+#[test]
+fn compare_cmd_diff_algorithm() {
+    // Check that the compare command uses the algorithm specified by --diff-algorithm to compute
+    // diffs of changed types.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-algorithm=patience",
+        "tests/compare_cmd_patience/a.symtypes",
+        "tests/compare_cmd_patience/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'b' added at position 1 with type long\n",
+            "struct foo: member 'a' moved from position 1 to 3\n",
+            "@@ -1,5 +1,5 @@\n",
+            " struct foo {\n",
+            "+\tlong b;\n",
+            "+\tint x;\n",
+            " \tlong a;\n",
+            "-\tint x;\n",
+            "-\tint x;\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-algorithm=histogram",
+        "tests/compare_cmd_patience/a.symtypes",
+        "tests/compare_cmd_patience/b.symtypes",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid value for '--diff-algorithm=histogram': unknown algorithm 'histogram'\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_diff_headers() {
+    // Check that the compare command precedes type diffs with header lines when --diff-headers is
+    // specified.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-headers",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function)\n",
+            "\n",
+            "because of a changed 'foo':\n",
+            "--- tests/compare_cmd/a.symtypes:foo\n",
+            "+++ tests/compare_cmd/b.symtypes:foo\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void foo (\n",
+            "-\tint a\n",
+            "+\tlong a\n",
+            " )\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_diff_max_cost() {
+    // Check that the compare command shows a type diff exceeding the cost specified by
+    // --diff-max-cost as a whole removal and addition.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-max-cost=1",
+        "tests/compare_cmd_max_cost/a.symtypes",
+        "tests/compare_cmd_max_cost/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 'e#foo':\n",
+            "@@ -1,5 +1,5 @@\n",
+            " enum foo {\n",
+            "-\tA,\n",
+            "-\tB,\n",
+            "-\tC\n",
+            "+\tB,\n",
+            "+\tC,\n",
+            "+\tD\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_diff_ignore() {
+    // Check that the compare command leaves out lines matching the patterns specified by
+    // --diff-ignore from type diffs.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-ignore=__reserved",
+        "tests/compare_cmd_ignore/a.symtypes",
+        "tests/compare_cmd_ignore/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'a' changed type from int to long\n",
+            "struct foo: member '__reserved1' added at position 3 with type int\n",
+            "@@ -1,4 +1,4 @@\n",
+            " struct foo {\n",
+            "-\tint a;\n",
+            "+\tlong a;\n",
+            " \tint b;\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that an invalid pattern is rejected.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-ignore=(",
+        "tests/compare_cmd_ignore/a.symtypes",
+        "tests/compare_cmd_ignore/b.symtypes",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert!(result
+        .stderr
+        .starts_with("Invalid value for '--diff-ignore=(': "));
+}
+
+#[test]
+fn compare_cmd_diff_max_lines() {
+    // Check that the compare command truncates type diffs to the number of lines specified by
+    // --diff-max-lines.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-max-lines=2",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function)\n",
+            "\n",
+            "because of a changed 'foo':\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void foo (\n",
+            "... diff truncated (3 more lines)\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_diff_merge_gap() {
+    // Check that the compare command merges changes separated by up to the number of unchanged
+    // lines specified by --diff-merge-gap into one hunk.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-merge-gap=8",
+        "tests/compare_cmd_merge_gap/a.symtypes",
+        "tests/compare_cmd_merge_gap/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'a' changed type from int to long\n",
+            "struct foo: member 'j' changed type from int to long\n",
+            "@@ -1,12 +1,12 @@\n",
+            " struct foo {\n",
+            "-\tint a;\n",
+            "+\tlong a;\n",
+            " \tint b;\n",
+            " \tint c;\n",
+            " \tint d;\n",
+            " \tint e;\n",
+            " \tint f;\n",
+            " \tint g;\n",
+            " \tint h;\n",
+            " \tint i;\n",
+            "-\tint j;\n",
+            "+\tlong j;\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_diff_moves() {
+    // Check that the compare command annotates moved blocks of lines in type diffs when
+    // --diff-moves is specified.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-moves",
+        "tests/compare_cmd_moves/a.symtypes",
+        "tests/compare_cmd_moves/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'a' moved from position 1 to 3\n",
+            "struct foo: member 'b' moved from position 2 to 1\n",
+            "struct foo: member 'c' moved from position 3 to 2\n",
+            "@@ -1,5 +1,5 @@ moved -2,1 +4,1\n",
+            " struct foo {\n",
+            "-\tint a;\n",
+            " \tint b;\n",
+            " \tint c;\n",
+            "+\tint a;\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_diff_view() {
+    // Check that the compare command shows type diffs in the view specified by --diff-view.
+    let result = ksymtypes_run([
+        "compare",
+        "--diff-view=side-by-side",
+        "--diff-width=41",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function)\n",
+            "\n",
+            "because of a changed 'foo':\n",
+            "void foo (            void foo (\n",
+            "        int a       |         long a\n",
+            ")                     )\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_json() {
+    // Check that the compare command writes the report as JSON when --format=json is specified.
+    let result = ksymtypes_run([
+        "compare",
+        "--format=json",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "{\"removed_exports\":[],\"added_exports\":[],\"changed_types\":[",
+            "{\"name\":\"foo\",\"exports\":[\"foo\"],\"descriptions\":[],",
+            "\"old_tokens\":[\"void\",\"foo\",\"(\",\"int\",\"a\",\")\"],",
+            "\"new_tokens\":[\"void\",\"foo\",\"(\",\"long\",\"a\",\")\"],",
+            "\"hunks\":[{\"pos_a\":1,\"len_a\":3,\"pos_b\":1,\"len_b\":3,\"lines\":[",
+            "{\"type\":\"context\",\"text\":\"void foo (\"},",
+            "{\"type\":\"removed\",\"text\":\"\\tint a\"},",
+            "{\"type\":\"added\",\"text\":\"\\tlong a\"},",
+            "{\"type\":\"context\",\"text\":\")\"}]}]}],",
+            "\"exports\":{\"foo\":{\"kind\":\"function\",",
+            "\"file\":\"tests/compare_cmd/a.symtypes\",\"export_type\":null,\"module\":null}},",
+            "\"crc_only\":false}\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--format=json",
+        "--score",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--score' option cannot be used with '--format=json'\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_config() {
+    // Check that the compare command uses the default format specified in a local configuration
+    // file.
+    let result = ksymtypes_run_in(
+        "tests/config_cmd",
+        [
+            "compare",
+            "../compare_cmd/a.symtypes",
+            "../compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result.stdout.starts_with("{\"removed_exports\":[]"));
+    assert_eq!(result.stderr, "");
+
+    // Check that command-line options override the configuration.
+    let expected = concat!(
+        "The following '1' exports are different:\n",
+        " foo (function)\n",
+        "\n",
+        "because of a changed 'foo':\n",
+        "@@ -1,3 +1,3 @@\n",
+        " void foo (\n",
+        "-\tint a\n",
+        "+\tlong a\n",
+        " )\n", //
+    );
+    let result = ksymtypes_run_in(
+        "tests/config_cmd",
+        [
+            "compare",
+            "--format=text",
+            "../compare_cmd/a.symtypes",
+            "../compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(result.stdout, expected);
+    assert_eq!(result.stderr, "");
+
+    // Check that the configuration is not read when --no-config is specified.
+    let result = ksymtypes_run_in(
+        "tests/config_cmd",
+        [
+            "--no-config",
+            "compare",
+            "../compare_cmd/a.symtypes",
+            "../compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(result.stdout, expected);
+    assert_eq!(result.stderr, "");
+
+    // Check that an invalid configuration is reported.
+    let result = ksymtypes_run_in(
+        "tests/config_cmd_invalid",
+        [
+            "compare",
+            "../compare_cmd/a.symtypes",
+            "../compare_cmd/b.symtypes",
+        ],
+    );
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        format!(
+            "Invalid configuration in '{}': line 2: invalid value for 'format'\n",
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/config_cmd_invalid/.ksymtypes.toml")
+                .display()
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_env() {
+    // Check that the compare command uses the default format specified by an environment variable.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_FORMAT", "json")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result.stdout.starts_with("{\"removed_exports\":[]"));
+    assert_eq!(result.stderr, "");
+
+    // Check that environment variables override the configuration files.
+    let result = ksymtypes_run_with_env(
+        "tests/config_cmd",
+        [("KSYMTYPES_FORMAT", "text")],
+        [
+            "compare",
+            "../compare_cmd/a.symtypes",
+            "../compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that command-line options override environment variables.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_FORMAT", "json"), ("KSYMTYPES_JOBS", "auto")],
+        [
+            "compare",
+            "--format=text",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that an invalid value is reported.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_JOBS", "0")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "Invalid value for 'KSYMTYPES_JOBS'\n");
+}
+
+#[test]
+fn compare_cmd_color() {
+    // Check that the compare command colors the type diffs when requested by an environment
+    // variable.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_COLOR", "always")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function)\n",
+            "\n",
+            "because of a changed 'foo':\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void foo (\n",
+            "\x1b[31m-\tint a\x1b[0m\n",
+            "\x1b[32m+\tlong a\x1b[0m\n",
+            " )\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that the --color option overrides the environment variable.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_COLOR", "always")],
+        [
+            "--color=never",
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result.stdout.contains("\n-\tint a\n+\tlong a\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that the diffs are not colored in the automatic mode when the output is not
+    // a terminal.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_COLOR", "auto")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result.stdout.contains("\n-\tint a\n+\tlong a\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that invalid values are reported.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_COLOR", "yes")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "Invalid value for 'KSYMTYPES_COLOR'\n");
+
+    let result = ksymtypes_run([
+        "--color=yes",
+        "compare",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid value for '--color=yes': unknown mode 'yes'\n",
+            "Try 'ksymtypes --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_config_rules() {
+    // Check that the compare command uses the default rules specified in a local configuration
+    // file, relative to the directory of the file.
+    let result = ksymtypes_run_in(
+        "tests/config_cmd_rules",
+        [
+            "compare",
+            "../compare_cmd/a.symtypes",
+            "../compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(0));
+    assert!(result.stdout.ends_with("\nPASS: changed export 'foo'\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that the compare command uses the default rules specified by an environment variable.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_RULES", "tests/compare_cmd_rules/severities")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(0));
+    assert!(result.stdout.ends_with("\nPASS: changed export 'foo'\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that the default rules are not used with a format that cannot show the severities.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_RULES", "tests/compare_cmd_rules/severities")],
+        [
+            "compare",
+            "--format=json",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result.stdout.starts_with("{\"removed_exports\":[]"));
+    assert_eq!(result.stderr, "");
+
+    // Check that a missing rules file is reported.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_RULES", "tests/missing_file")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        "File 'tests/missing_file' for 'KSYMTYPES_RULES' does not exist\n"
+    );
+}
+
+#[test]
+fn compare_cmd_config_option() {
+    // Check that the compare command accepts the changes when their total badness does not exceed
+    // the threshold given in the configuration file specified by --config.
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/compare_cmd_config/threshold.toml",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(0));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_MAX_BADNESS", "3")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that the settings of the file take precedence over the environment variables.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_MAX_BADNESS", "3")],
+        [
+            "compare",
+            "--config=tests/compare_cmd_config/threshold.toml",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(0));
+
+    // Check that only the exports listed in the whitelist are compared, with the path of the
+    // whitelist relative to the configuration file.
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/compare_cmd_config/whitelist.toml",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(0));
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+
+    // Check that an invalid configuration is reported with the number of the offending line.
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/compare_cmd_config/invalid.toml",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid configuration in 'tests/compare_cmd_config/invalid.toml': line 3: file ",
+            "'tests/compare_cmd_config/missing_file' for 'whitelist' does not exist\n",
+        )
+    );
+
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KSYMTYPES_MAX_BADNESS", "-1")],
+        [
+            "compare",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "Invalid value for 'KSYMTYPES_MAX_BADNESS'\n");
+
+    // Check that a missing configuration file is reported.
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/missing_file",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert!(result
+        .stderr
+        .starts_with("Failed to read configuration from 'tests/missing_file': "));
+}
+
+#[test]
+fn compare_cmd_config_suppress() {
+    // Check that a change acknowledged by a suppression in the configuration file is not reported
+    // and does not fail the comparison.
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/compare_cmd_config/suppress.toml",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(0));
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+
+    // Check that a suppression of another kind of change does not apply.
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/compare_cmd_config/suppress_removed.toml",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that suppressions are validated.
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/compare_cmd_config/suppress_duplicate.toml",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid configuration in 'tests/compare_cmd_config/suppress_duplicate.toml': ",
+            "line 5: duplicate suppression of 'foo'\n",
+        )
+    );
+
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/compare_cmd_config/suppress_unknown.toml",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid configuration in 'tests/compare_cmd_config/suppress_unknown.toml': ",
+            "line 3: unknown key 'reason'\n",
+        )
+    );
+
+    let result = ksymtypes_run([
+        "compare",
+        "--config=tests/compare_cmd_config/suppress_unjustified.toml",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid configuration in 'tests/compare_cmd_config/suppress_unjustified.toml': ",
+            "line 1: suppression without 'justification'\n",
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_warnings_as_errors() {
+    // Check that the compare command fails without writing the report if a warning is found and
+    // --warnings-as-errors is specified.
+    let result = ksymtypes_run([
+        "compare",
+        "--warnings-as-errors",
+        "tests/compare_cmd_incomplete/a.symtypes",
+        "tests/compare_cmd_incomplete/b.symtypes",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Warning: Type 's#foo' has an incomplete definition in one of the corpuses\n",
+            "Found 1 warning(s) treated as errors\n", //
+        )
+    );
+}
+
+#[test]
+fn consolidate_cmd() {
+    // Check that the consolidate command trivially works.
+    let result = ksymtypes_run(["consolidate", "tests/consolidate_cmd"]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#a.symtypes bar\n",
+            "F#b.symtypes baz\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn consolidate_cmd_glob() {
+    // Check that the consolidate command expands a glob pattern specified as its input.
+    let result = ksymtypes_run(["consolidate", "tests/consolidate_cmd/*.symtypes"]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#tests/consolidate_cmd/a.symtypes bar\n",
+            "F#tests/consolidate_cmd/b.symtypes baz\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that a pattern which doesn't match any file is reported.
+    let result = ksymtypes_run(["consolidate", "tests/consolidate_cmd/*.symvers"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        "No files match the pattern 'tests/consolidate_cmd/*.symvers'\n"
+    );
+}
+
+#[test]
+fn consolidate_cmd_jobs() {
+    // Check that the consolidate command accepts 'auto' and a positive number as the number of
+    // workers specified by --jobs.
+    for jobs in ["--jobs=auto", "--jobs=2"] {
+        let result = ksymtypes_run(["consolidate", jobs, "tests/consolidate_cmd"]);
+        assert!(result.status.success());
+        assert_eq!(
+            result.stdout,
+            concat!(
+                "s#foo struct foo { int a ; }\n",
+                "bar int bar ( s#foo )\n",
+                "baz int baz ( s#foo )\n",
+                "F#a.symtypes bar\n",
+                "F#b.symtypes baz\n", //
+            )
+        );
+        assert_eq!(result.stderr, "");
+    }
+
+    // Check that an invalid number of workers is rejected.
+    let result = ksymtypes_run(["consolidate", "--jobs=0", "tests/consolidate_cmd"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid value for '--jobs=0': must be positive\n",
+            "Try 'ksymtypes consolidate --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn consolidate_cmd_output() {
+    // Check that the consolidate command writes its result to the file specified by --output.
+    let output_path =
+        Path::new(env!("CARGO_TARGET_TMPDIR")).join("consolidate_cmd_output.symtypes");
+    fs::remove_file(&output_path).ok();
+    let result = ksymtypes_run([
+        AsRef::<OsStr>::as_ref("consolidate"),
+        "--output".as_ref(),
+        output_path.as_ref(),
+        "tests/consolidate_cmd".as_ref(),
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+    let output_data = fs::read_to_string(output_path).expect("Unable to read the output file");
+    assert_eq!(
+        output_data,
+        concat!(
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#a.symtypes bar\n",
+            "F#b.symtypes baz\n", //
+        )
+    );
+}
+
+#[test]
+fn consolidate_cmd_crcs() {
+    // Check that the consolidate command records CRCs of exports when --crcs is specified.
+    let result = ksymtypes_run(["consolidate", "--crcs", "tests/consolidate_cmd"]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "V#2\n",
+            "s#foo struct foo { int a ; }\n",
+            "bar int bar ( s#foo )\n",
+            "baz int baz ( s#foo )\n",
+            "F#a.symtypes bar=0xb9f180ac\n",
+            "F#b.symtypes baz=0x172d450e\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn consolidate_cmd_invalid_ref() {
+    // Check that the consolidate command rejects a reference to an undefined type by default.
+    let result = ksymtypes_run(["consolidate", "tests/consolidate_cmd_no_validate"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Failed to read symtypes from 'tests/consolidate_cmd_no_validate': a.symtypes:1:15: Type 's#foo' is not defined\n",
+            "  bar int bar ( s#foo )\n",
+            "                ^~~~~\n", //
+        )
+    );
+}
+
+#[test]
+fn consolidate_cmd_no_validate() {
+    // Check that the consolidate command accepts a reference to an undefined type when the
+    // validation is disabled by --no-validate, only reporting a warning about it.
+    let result = ksymtypes_run([
+        "consolidate",
+        "--no-validate",
+        "tests/consolidate_cmd_no_validate",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "bar int bar ( s#foo )\n",
+            "F#a.symtypes bar\n", //
+        )
+    );
+    assert_eq!(
+        result.stderr,
+        "Warning: Skipping an unknown type 's#foo' in file 'a.symtypes'\n"
+    );
+}
+
+#[test]
+fn consolidate_cmd_max_errors() {
+    // Check that the consolidate command reports all errors found in the input when --max-errors=0
+    // is specified.
+    let result = ksymtypes_run([
+        "consolidate",
+        "--max-errors=0",
+        "tests/consolidate_cmd_errors",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Failed to read symtypes from 'tests/consolidate_cmd_errors': a.symtypes:2: Expected a record name\n",
+            "a.symtypes:1:15: Type 's#foo' is not defined\n",
+            "  bar int bar ( s#foo )\n",
+            "                ^~~~~\n",
+            "a.symtypes:3:15: Type 't#x' is not defined\n",
+            "  baz int baz ( t#x )\n",
+            "                ^~~\n", //
+        )
+    );
+}
+
+#[test]
+fn symvers_cmd() {
+    // Check that the symvers command generates Module.symvers data with computed CRCs and takes
+    // export types and namespaces from the reference symvers file.
+    let result = ksymtypes_run([
+        "symvers",
+        "--symvers=tests/symvers_cmd/reference.symvers",
+        "tests/symvers_cmd",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "0xc3d50522\tbaz\tfs/test2.o\tEXPORT_SYMBOL\n",
+            "0x35d049d8\tbar\tlib/test.o\tEXPORT_SYMBOL_GPL\tBAR_NS\n",
+            "0x14c5a578\tqux\tlib/test.o\tEXPORT_SYMBOL\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn symref_cmd() {
+    // Check that the symref command writes a reference file for each symtypes file and marks the
+    // symbols listed in --overrides.
+    let output_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("symref_cmd");
+    fs::remove_dir_all(&output_dir).ok();
+    let result = ksymtypes_run([
+        AsRef::<OsStr>::as_ref("symref"),
+        "--output".as_ref(),
+        output_dir.as_ref(),
+        "--overrides=tests/symref_cmd/overrides".as_ref(),
+        "tests/symref_cmd/test.kabi".as_ref(),
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+    let output_data = fs::read_to_string(output_dir.join("drivers/baz.symref"))
+        .expect("Unable to read the output file");
+    assert_eq!(
+        output_data,
+        concat!(
+            "override s#foo struct foo { int a ; }\n",
+            "baz void baz ( s#foo * )\n", //
+        )
+    );
+    let output_data = fs::read_to_string(output_dir.join("lib/qux.symref"))
+        .expect("Unable to read the output file");
+    assert_eq!(
+        output_data,
+        concat!(
+            "s#bar struct bar { int b ; }\n",
+            "qux void qux ( s#bar * )\n", //
+        )
+    );
+}
+
+#[test]
+fn explain_cmd() {
+    // Check that the explain command links a changed CRC to the responsible type change.
+    let result = ksymtypes_run([
+        "explain",
+        "tests/explain_cmd/a.symvers",
+        "tests/explain_cmd/b.symvers",
+        "tests/explain_cmd/a",
+        "tests/explain_cmd/b",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "Export 'bar' has changed its CRC from 0xc34ecb06 to 0x94584e94\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'b' added at position 2 with type int\n",
+            "@@ -1,3 +1,4 @@\n",
+            " struct foo {\n",
+            " \tint a;\n",
+            "+\tint b;\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that no output is produced when no CRC changed.
+    let result = ksymtypes_run([
+        "explain",
+        "tests/explain_cmd/a.symvers",
+        "tests/explain_cmd/a.symvers",
+        "tests/explain_cmd/a",
+        "tests/explain_cmd/b",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn verify_btf_cmd() {
+    // Check that the verify-btf command reports types inconsistent with BTF, considering only
+    // exports described by the BTF.
+    let result = ksymtypes_run([
+        "verify-btf",
+        "tests/verify_btf_cmd/test.symtypes",
+        "tests/verify_btf_cmd/vmlinux.btf",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "Type 's#foo' in 'tests/verify_btf_cmd/test.symtypes' is inconsistent with BTF, used by 'bar'\n",
+            "  symtypes members: b, a\n",
+            "  BTF members: a, b\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "verify-btf",
+        "--format=json",
+        "tests/verify_btf_cmd/test.symtypes",
+        "tests/verify_btf_cmd/vmlinux.btf",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "{\"checked_exports\":1,\"divergences\":[{\"type\":\"s#foo\",",
+            "\"file\":\"tests/verify_btf_cmd/test.symtypes\",\"exports\":[\"bar\"],",
+            "\"symtypes_members\":[\"b\",\"a\"],\"btf_members\":[\"a\",\"b\"]}]}\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that an input which is not BTF is reported.
+    let result = ksymtypes_run([
+        "verify-btf",
+        "tests/verify_btf_cmd/test.symtypes",
+        "tests/verify_btf_cmd/test.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(3));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Failed to read BTF from 'tests/verify_btf_cmd/test.symtypes': ",
+            "Failed to read BTF data: Not a BTF or ELF file\n", //
+        )
+    );
+}
+
+#[test]
+fn scan_build_cmd() {
+    // Check that the scan-build command compares the symtypes of a build with a reference corpus,
+    // without following the link to the source tree and keeping the first of duplicate exports.
+    let result = ksymtypes_run([
+        "scan-build",
+        "--obj=tests/scan_build_cmd/build",
+        "--reference=tests/scan_build_cmd/reference.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'b' added at position 2 with type int\n",
+            "@@ -1,3 +1,4 @@\n",
+            " struct foo {\n",
+            " \tint a;\n",
+            "+\tint b;\n",
+            " }\n", //
+        )
+    );
+    assert!(result
+        .stderr
+        .contains("Ignoring duplicate export 'baz', previous occurrence found in"));
+
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("KBUILD_OUTPUT", "tests/scan_build_cmd/build")],
+        [
+            "-q",
+            "scan-build",
+            "--rules=tests/scan_build_cmd/severities",
+            "--reference=tests/scan_build_cmd/reference.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(0));
+    assert!(result.stdout.ends_with("\n\nPASS: changed export 'bar'\n"));
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run(["scan-build", "tests/scan_build_cmd/build"]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--reference' option is missing\n",
+            "Excess scan-build argument 'tests/scan_build_cmd/build' specified\n",
+            "Try 'ksymtypes scan-build --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn scan_build_cmd_config() {
+    // Check that the scan-build command takes the threshold from the configuration file specified
+    // by --config.
+    let result = ksymtypes_run([
+        "scan-build",
+        "--config=tests/compare_cmd_config/threshold.toml",
+        "--obj=tests/scan_build_cmd/build",
+        "--reference=tests/scan_build_cmd/reference.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(0));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n bar (function)\n"));
+
+    // Check that only the exports listed in the whitelist are checked.
+    let result = ksymtypes_run([
+        "scan-build",
+        "--config=tests/compare_cmd_config/whitelist.toml",
+        "--obj=tests/scan_build_cmd/build",
+        "--reference=tests/scan_build_cmd/reference.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result
+        .stdout
+        .starts_with("The following '1' exports are different:\n bar (function)\n"));
+}
+
+#[test]
+fn consolidate_cmd_path_style() {
+    // Check that the --relative-to and --absolute-paths options change how paths of files are shown
+    // in error messages.
+    let result = ksymtypes_run([
+        "--relative-to=tests",
+        "consolidate",
+        "tests/consolidate_cmd_errors",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        "Failed to read symtypes from 'consolidate_cmd_errors': consolidate_cmd_errors/a.symtypes:2: Expected a record name\n"
+    );
+
+    let result = ksymtypes_run([
+        "--absolute-paths",
+        "consolidate",
+        "tests/consolidate_cmd_errors",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    let dir = std::env::current_dir()
+        .unwrap()
+        .join("tests/consolidate_cmd_errors");
+    assert_eq!(
+        result.stderr,
+        format!(
+            "Failed to read symtypes from '{}': {}/a.symtypes:2: Expected a record name\n",
+            dir.display(),
+            dir.display()
+        )
+    );
+
+    let result = ksymvers_run([
+        "--relative-to=tests/ksymvers_cmd",
+        "filter",
+        "tests/ksymvers_cmd/missing.symvers",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert!(result.stderr.starts_with(
+        "Failed to read symvers from 'missing.symvers': Failed to open file 'missing.symvers': "
+    ));
+}
+
+#[test]
+fn symvers_compare_cmd() {
+    // Check that the ksymvers compare command reports changes of exports and scores them.
+    let result = ksymvers_run([
+        "compare",
+        "--score",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "Export 'qux' has been removed\n",
+            "Export 'quux' has been added\n",
+            "Export 'baz' has been moved out of namespace 'BAR_NS'\n",
+            "Export 'foo' has changed its CRC from 0x12345678 to 0x1234567a\n",
+            "\n",
+            "Total badness: 16\n",
+            "Badness by module:\n",
+            " drivers/baz: 4\n",
+            " drivers/qux: 8\n",
+            " vmlinux: 4\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that --whitelist restricts the comparison to the listed exports.
+    let result = ksymvers_run([
+        "compare",
+        "--whitelist=tests/ksymvers_cmd/whitelist",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "Export 'qux' has been removed\n",
+            "Export 'foo' has changed its CRC from 0x12345678 to 0x1234567a\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn symvers_compare_cmd_json() {
+    // Check that the ksymvers compare command writes a JSON report when --format=json is
+    // specified.
+    let result = ksymvers_run([
+        "compare",
+        "--format=json",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_str(&result.stdout).unwrap();
+    assert_eq!(report["removed_exports"][0]["name"], "qux");
+    assert_eq!(report["added_exports"][0]["name"], "quux");
+    assert_eq!(report["changed_exports"][0]["name"], "baz");
+    assert_eq!(report["changed_exports"][0]["old"]["namespace"], "BAR_NS");
+    assert_eq!(
+        report["changed_exports"][0]["new"]["namespace"],
+        serde_json::Value::Null
+    );
+    assert_eq!(report["changed_exports"][1]["name"], "foo");
+    assert_eq!(result.stderr, "");
+
+    // Check that the validate and namespaces commands support the same formats.
+    let result = ksymvers_run(["validate", "--format=json", "tests/ksymvers_cmd/b.symvers"]);
+    assert!(!result.status.success());
+    let report: serde_json::Value = serde_json::from_str(&result.stdout).unwrap();
+    assert_eq!(
+        report["problems"][0],
+        "tests/ksymvers_cmd/b.symvers:4: Export 'quux' has a zero CRC"
+    );
+    let result = ksymvers_run([
+        "namespaces",
+        "--format=json",
+        "tests/ksymvers_cmd/a.symvers",
+    ]);
+    assert!(result.status.success());
+    let report: serde_json::Value = serde_json::from_str(&result.stdout).unwrap();
+    assert_eq!(report["namespaces"][0]["name"], "BAR_NS");
+    assert_eq!(
+        report["namespaces"][0]["exports"][1]["module"],
+        "drivers/baz"
+    );
+
+    // Check that an unknown format is rejected.
+    let result = ksymvers_run([
+        "namespaces",
+        "--format=yaml",
+        "tests/ksymvers_cmd/a.symvers",
+    ]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid value for '--format=yaml': unknown format 'yaml'\n",
+            "Try 'ksymvers namespaces --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn symvers_exit_status() {
+    // Check that the ksymvers commands use the same exit statuses as ksymtypes.
+    let result = ksymvers_run([
+        "compare",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/a.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(0));
+    assert_eq!(result.stdout, "");
+
+    let result = ksymvers_run([
+        "compare",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+
+    let result = ksymvers_run(["validate", "tests/ksymvers_cmd/b.symvers"]);
+    assert_eq!(result.status.code(), Some(1));
+
+    let result = ksymvers_run(["validate"]);
+    assert_eq!(result.status.code(), Some(2));
+
+    let result = ksymvers_run(["validate", "tests/ksymvers_cmd/missing.symvers"]);
+    assert_eq!(result.status.code(), Some(3));
+}
+
+#[test]
+fn compare_cmd_input_dialect() {
+    // Check that a corpus produced by an older genksyms version is detected and compared without
+    // spurious differences.
+    let result = ksymtypes_run([
+        "compare",
+        "tests/compare_cmd_dialect/a.symtypes",
+        "tests/compare_cmd_dialect/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(0));
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+
+    // Check that the compatibility quirks are not applied for the current dialect.
+    let result = ksymtypes_run([
+        "compare",
+        "--input-dialect=current",
+        "tests/compare_cmd_dialect/a.symtypes",
+        "tests/compare_cmd_dialect/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--input-dialect=sle11",
+        "tests/compare_cmd_dialect/a.symtypes",
+        "tests/compare_cmd_dialect/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Invalid value for '--input-dialect=sle11': unknown dialect 'sle11'\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_kabi_dw() {
+    // Check that directories with kabi-dw dumps are converted and compared as symtypes corpuses.
+    let result = ksymtypes_run(["compare", "tests/kabi_dw/a", "tests/kabi_dw/b"]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function)\n",
+            "\n",
+            "because of a changed 's#bar':\n",
+            "@@ -1,4 +1,5 @@\n",
+            " struct bar {\n",
+            " \t0x0 a int;\n",
+            " \t0x4 b t#u32;\n",
+            "+\t0x8 c int;\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_rules() {
+    // Check that the --rules option rates the changes by severities and that only changes rated
+    // FAIL are considered differences.
+    let result = ksymtypes_run([
+        "compare",
+        "--rules=tests/compare_cmd_rules/severities",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(0));
+    assert!(result.stdout.ends_with("\nPASS: changed export 'foo'\n"));
+    assert_eq!(result.stderr, "");
+
+    let result = ksymvers_run([
+        "compare",
+        "--rules=tests/ksymvers_cmd/severities",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "Export 'qux' has been removed\n",
+            "Export 'quux' has been added\n",
+            "Export 'baz' has been moved out of namespace 'BAR_NS'\n",
+            "Export 'foo' has changed its CRC from 0x12345678 to 0x1234567a\n",
+            "\n",
+            "WARN: changed export 'baz'\n",
+            "FAIL: changed export 'foo'\n",
+            "PASS: removed export 'qux'\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymvers_run([
+        "compare",
+        "--rules=tests/ksymvers_cmd/severities",
+        "--format=json",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--rules' option cannot be used with '--format=json'\n",
+            "Try 'ksymvers compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_symvers() {
+    // Check that a Module.symvers file is compared with a symtypes corpus only by the CRCs of the
+    // exports.
+    let result = ksymtypes_run([
+        "compare",
+        "tests/symvers_cmd",
+        "tests/compare_cmd_symvers/Module.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "Export 'qux' has been removed\n",
+            "Export 'quux' (EXPORT_SYMBOL in lib/test.o) has been added\n",
+            "The following '1' exports are different:\n",
+            " baz (EXPORT_SYMBOL in fs/test2.o)\n",
+            "\n",
+            "because of a changed CRC from 0xc3d50522 to 0x12345678, structural differences are ",
+            "not available\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_symvers_option() {
+    // Check that the --symvers option annotates the reported exports with their modules and export
+    // types.
+    let result = ksymtypes_run([
+        "compare",
+        "--symvers=tests/compare_cmd_module/Module.symvers",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " foo (function, EXPORT_SYMBOL_GPL in vmlinux)\n",
+            "\n",
+            "because of a changed 'foo':\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void foo (\n",
+            "-\tint a\n",
+            "+\tlong a\n",
+            " )\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--symvers=tests/compare_cmd_module/missing.symvers",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(3));
+    assert_eq!(result.stdout, "");
+    assert!(result
+        .stderr
+        .starts_with("Failed to read symvers from 'tests/compare_cmd_module/missing.symvers': "));
+}
+
+#[test]
+fn compare_cmd_cross_arch() {
+    // Check that the --cross-arch option tolerates changes of pointer-size-dependent types and of
+    // the types listed by the --arch-types option.
+    let result = ksymtypes_run([
+        "compare",
+        "--cross-arch",
+        "--arch-types=tests/compare_cmd_cross_arch/arch-types",
+        "tests/compare_cmd_cross_arch/x86_64.symtypes",
+        "tests/compare_cmd_cross_arch/i386.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 's#foo':\n",
+            "struct foo: member 'a' changed type from int to long\n",
+            "@@ -1,3 +1,3 @@\n",
+            " struct foo {\n",
+            "-\tint a;\n",
+            "+\tlong a;\n",
+            " }\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--arch-types=tests/compare_cmd_cross_arch/arch-types",
+        "tests/compare_cmd_cross_arch/x86_64.symtypes",
+        "tests/compare_cmd_cross_arch/i386.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--arch-types' option can be used only with '--cross-arch'\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_changed_sources() {
+    // Check that the --changed-sources option restricts the comparison to the symtypes files of
+    // the changed sources and that the --source-map option maps the sources to objects.
+    let result = ksymtypes_run([
+        "compare",
+        "--changed-sources=tests/compare_cmd_changed/changed",
+        "tests/compare_cmd_changed/a",
+        "tests/compare_cmd_changed/b",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "The following '1' exports are different:\n",
+            " bar (function)\n",
+            "\n",
+            "because of a changed 'bar':\n",
+            "@@ -1,3 +1,3 @@\n",
+            " void bar (\n",
+            "-\tint\n",
+            "+\tlong\n",
+            " )\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--changed-sources=tests/compare_cmd_changed/changed",
+        "--source-map=tests/compare_cmd_changed/deps",
+        "tests/compare_cmd_changed/a",
+        "tests/compare_cmd_changed/b",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result.stdout.contains(" bar (function)\n"));
+    assert!(result.stdout.contains(" foo (function)\n"));
+    assert!(!result.stdout.contains(" baz (function)\n"));
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--source-map=tests/compare_cmd_changed/deps",
+        "tests/compare_cmd_changed/a",
+        "tests/compare_cmd_changed/b",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--source-map' option can be used only with '--changed-sources'\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_output_dir() {
+    // Check that the --output-dir option writes a diff file for each changed export and an index.
+    let output_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("compare_cmd_output_dir");
+    fs::remove_dir_all(&output_dir).ok();
+    let result = ksymtypes_run([
+        AsRef::<OsStr>::as_ref("compare"),
+        "--output-dir".as_ref(),
+        output_dir.as_ref(),
+        "tests/compare_cmd_output_dir/a.symtypes".as_ref(),
+        "tests/compare_cmd_output_dir/b.symtypes".as_ref(),
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+    let read_output =
+        |name| fs::read_to_string(output_dir.join(name)).expect("Unable to read the output file");
+    assert_eq!(
+        read_output("index.txt"),
+        concat!(
+            "Export 'qux' (function) has been removed\n",
+            "bar.diff: export 'bar' (function) changed because of 's#foo'\n",
+            "baz.diff: export 'baz' (function) changed because of 'baz', 's#foo'\n", //
+        )
+    );
+    assert_eq!(
+        read_output("bar.diff"),
+        concat!(
+            "Changed 's#foo':\n",
+            "struct foo: member 'b' added at position 2 with type long\n",
+            "--- a:s#foo\n",
+            "+++ b:s#foo\n",
+            "@@ -1,3 +1,4 @@\n",
+            " struct foo {\n",
+            " \tint a;\n",
+            "+\tlong b;\n",
+            " }\n", //
+        )
+    );
+    assert!(read_output("baz.diff").starts_with(concat!(
+        "Changed 'baz':\n",
+        "--- a:baz\n",
+        "+++ b:baz\n", //
+    )));
+
+    // Check that the option cannot be combined with a machine-readable format.
+    let result = ksymtypes_run([
+        "compare",
+        "--output-dir=out",
+        "--format=json",
+        "tests/compare_cmd_output_dir/a.symtypes",
+        "tests/compare_cmd_output_dir/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--output-dir' option cannot be used with '--format=json'\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_obs() {
+    // Check that the OBS format writes the changes as rpmlint-style findings, all rated as errors
+    // without rules.
+    let result = ksymtypes_run([
+        "compare",
+        "--format=obs",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        "kabi: E: export-changed foo tests/compare_cmd/a\n"
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymvers_run([
+        "compare",
+        "--format=obs",
+        "--rules=tests/ksymvers_cmd/severities",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "kabi: W: export-changed baz drivers/baz\n",
+            "kabi: E: export-changed foo vmlinux\n",
+            "kabi: I: export-removed qux drivers/qux\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that the format is rejected by commands other than compare.
+    let result = ksymvers_run(["validate", "--format=obs", "tests/ksymvers_cmd/a.symvers"]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The 'obs' format can be used only with the compare command\n",
+            "Try 'ksymvers validate --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn compare_cmd_annotations() {
+    // Check that the annotations format writes GitHub Actions workflow commands by default.
+    let result = ksymtypes_run([
+        "compare",
+        "--format=annotations",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "::error file=tests/compare_cmd/a.symtypes,title=kABI FAIL::",
+            "Export 'foo' has been changed\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that a GitLab code quality report is written when running in GitLab CI.
+    let result = ksymtypes_run_with_env(
+        env!("CARGO_MANIFEST_DIR"),
+        [("GITLAB_CI", "true")],
+        [
+            "compare",
+            "--format=annotations",
+            "--rules=tests/compare_cmd_rules/severities",
+            "tests/compare_cmd/a.symtypes",
+            "tests/compare_cmd/b.symtypes",
+        ],
+    );
+    assert_eq!(result.status.code(), Some(0));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "[{\"description\":\"Export 'foo' has been changed\",\"check_name\":\"kabi-changed\",",
+            "\"fingerprint\":\"0739467052399297\",\"severity\":\"info\",",
+            "\"location\":{\"path\":\"tests/compare_cmd/a.symtypes\",",
+            "\"lines\":{\"begin\":1}}}]\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_badness() {
+    // Check that the badness format reports the badness of the changes by symbol and module.
+    let result = ksymvers_run([
+        "compare",
+        "--format=badness",
+        "--rules=tests/ksymvers_cmd/severities",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "symbol baz drivers/baz changed WARN 4\n",
+            "symbol foo vmlinux changed FAIL 4\n",
+            "symbol qux drivers/qux removed PASS 8\n",
+            "module drivers/baz 4\n",
+            "module vmlinux 4\n",
+            "total 8\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymtypes_run([
+        "compare",
+        "--format=badness",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "symbol foo tests/compare_cmd/a changed FAIL 4\n",
+            "module tests/compare_cmd/a 4\n",
+            "total 4\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn compare_cmd_metrics() {
+    // Check that the metrics format writes Prometheus gauges of the changes.
+    let result = ksymvers_run([
+        "compare",
+        "--format=metrics",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(result.stdout.contains("\nkabi_exports_total 5\n"));
+    assert!(result.stdout.contains("\nkabi_badness_score 16\n"));
+    assert!(result
+        .stdout
+        .contains("\nkabi_module_exports_removed{module=\"drivers/qux\"} 1\n"));
+    assert_eq!(result.stderr, "");
+
+    // Check that the format cannot be combined with rules.
+    let result = ksymtypes_run([
+        "compare",
+        "--format=metrics",
+        "--rules=tests/compare_cmd_rules/severities",
+        "tests/compare_cmd/a.symtypes",
+        "tests/compare_cmd/b.symtypes",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--rules' option cannot be used with '--format=metrics'\n",
+            "Try 'ksymtypes compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn symvers_compare_cmd_legacy() {
+    // Check that the --legacy-output option writes the report in the format of the kernel-source
+    // kABI check script.
+    let result = ksymvers_run([
+        "compare",
+        "--legacy-output",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "KABI: symbol baz(drivers/baz) changed namespace from BAR_NS to (none) (badness 4)\n",
+            "KABI: symbol foo(vmlinux) changed crc from 0x12345678 to 0x1234567a (badness 4)\n",
+            "KABI: symbol qux(drivers/qux) lost (badness 8)\n",
+            "KABI: aborting due to kabi changes.\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that changes rated PASS by rules are omitted from the report.
+    let result = ksymvers_run([
+        "compare",
+        "--legacy-output",
+        "--rules=tests/ksymvers_cmd/severities",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "KABI: symbol baz(drivers/baz) changed namespace from BAR_NS to (none) (badness 4)\n",
+            "KABI: symbol foo(vmlinux) changed crc from 0x12345678 to 0x1234567a (badness 4)\n",
+            "KABI: aborting due to kabi changes.\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    let result = ksymvers_run([
+        "compare",
+        "--legacy-output",
+        "--score",
+        "tests/ksymvers_cmd/a.symvers",
+        "tests/ksymvers_cmd/b.symvers",
+    ]);
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "The '--legacy-output' option cannot be used with '--score'\n",
+            "Try 'ksymvers compare --help' for more information.\n", //
+        )
+    );
+}
+
+#[test]
+fn symvers_validate_cmd() {
+    // Check that the ksymvers validate command succeeds for valid data.
+    let result = ksymvers_run(["validate", "tests/ksymvers_cmd/a.symvers"]);
+    assert!(result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(result.stderr, "");
+
+    // Check that problems in the data are reported.
+    let result = ksymvers_run(["validate", "tests/ksymvers_cmd/b.symvers"]);
+    assert!(!result.status.success());
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "tests/ksymvers_cmd/b.symvers:4: Export 'quux' has a zero CRC\n",
+            "tests/ksymvers_cmd/b.symvers:5: Export 'bar' is listed multiple times\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn symvers_filter_cmd() {
+    // Check that the ksymvers filter command selects exports by their module and namespace.
+    let result = ksymvers_run([
+        "filter",
+        "--module=vmlinux",
+        "--module=drivers/qux",
+        "--namespace=BAR_NS",
+        "tests/ksymvers_cmd/a.symvers",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        "0x23456789\tbar\tvmlinux\tEXPORT_SYMBOL_GPL\tBAR_NS\n"
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that exports can be selected by a whitelist.
+    let result = ksymvers_run([
+        "filter",
+        "--whitelist=tests/ksymvers_cmd/whitelist",
+        "tests/ksymvers_cmd/a.symvers",
+    ]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "0x12345678\tfoo\tvmlinux\tEXPORT_SYMBOL\n",
+            "0x456789ab\tqux\tdrivers/qux\tEXPORT_SYMBOL\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+}
+
+#[test]
+fn symvers_namespaces_cmd() {
+    // Check that the ksymvers namespaces command lists exports by their namespaces.
+    let result = ksymvers_run(["namespaces", "tests/ksymvers_cmd/a.symvers"]);
+    assert!(result.status.success());
+    assert_eq!(
+        result.stdout,
+        concat!(
+            "Namespace 'BAR_NS' (2 exports):\n",
+            " bar (vmlinux)\n",
+            " baz (drivers/baz)\n", //
+        )
+    );
+    assert_eq!(result.stderr, "");
+
+    // Check that invalid arguments are reported with a usage hint for ksymvers.
+    let result = ksymvers_run(["namespaces", "--foo"]);
+    assert!(!result.status.success());
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.stderr,
+        concat!(
+            "Unrecognized namespaces option '--foo'\n",
+            "The namespaces source is missing\n",
+            "Try 'ksymvers namespaces --help' for more information.\n", //
         )
     );
 }